@@ -0,0 +1,46 @@
+//! Minimal consumer of the `[stream]` event feed
+//!
+//! Connects to a running xkippo-tui with `stream.bind` set and prints one
+//! line per event: time, source IP, event, session risk and threat intel
+//! labels. Usage:
+//!
+//! ```text
+//! cargo run --example stream_consumer -- 127.0.0.1:9000
+//! ```
+
+use std::io::{BufRead, BufReader};
+use std::net::TcpStream;
+
+use serde_json::Value;
+
+fn main() -> std::io::Result<()> {
+    let address = std::env::args().nth(1).unwrap_or_else(|| "127.0.0.1:9000".to_string());
+    let stream = TcpStream::connect(&address)?;
+    eprintln!("Connected to {}", address);
+
+    for line in BufReader::new(stream).lines() {
+        let event: Value = match serde_json::from_str(&line?) {
+            Ok(event) => event,
+            Err(e) => {
+                eprintln!("Skipping a line that isn't JSON: {}", e);
+                continue;
+            }
+        };
+
+        let entry = &event["entry"];
+        let labels: Vec<&str> = event["intel"]["labels"].as_array().into_iter().flatten()
+            .filter_map(Value::as_str)
+            .collect();
+        println!(
+            "{} {:<15} {:<20} risk={:<3} {}",
+            entry["timestamp"].as_str().unwrap_or("-"),
+            entry["src_ip"].as_str().unwrap_or("-"),
+            entry["eventid"].as_str().unwrap_or("-"),
+            event["session"]["risk"].as_u64().map_or("-".to_string(), |risk| risk.to_string()),
+            labels.join(","),
+        );
+    }
+
+    eprintln!("Stream closed");
+    Ok(())
+}
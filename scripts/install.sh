@@ -189,7 +189,7 @@ esac
 
 echo -e "\n${BLUE}${BOLD}Installation Complete!${NC}"
 echo -e "You can now run xKippo-tui to monitor your Cowrie honeypot logs."
-echo -e "Run the setup script first to configure the tool:"
-echo -e "  ./scripts/setup.sh"
+echo -e "Run the setup wizard first to configure the tool:"
+echo -e "  xkippo-tui --setup"
 
 exit 0
\ No newline at end of file
@@ -1,13 +1,135 @@
+impl AppFilters {
+    /// Rolling ranges `P` cycles through, in hours; "all time" follows the last
+    pub const TIME_RANGES: [u64; 3] = [1, 24, 24 * 7];
+
+    /// Switch to the next rolling range, or back to all time after the last
+    pub fn cycle_time_range(&mut self, now: DateTime<Utc>) {
+        self.last_hours = match self.last_hours {
+            None => Some(Self::TIME_RANGES[0]),
+            Some(hours) => Self::TIME_RANGES.iter().copied().find(|range| *range > hours),
+        };
+        if self.last_hours.is_none() {
+            self.from_time = None;
+            self.to_time = None;
+        }
+        self.roll_time_range(now);
+    }
+
+    /// Move the time filter along with its rolling range, if one is picked
+    pub fn roll_time_range(&mut self, now: DateTime<Utc>) {
+        if let Some(hours) = self.last_hours {
+            self.from_time = Some(now - chrono::Duration::hours(hours as i64));
+            self.to_time = None;
+        }
+    }
+
+    /// Apply `query` as the search, or clear it if empty
+    ///
+    /// An invalid regex leaves the current search in place and returns the
+    /// compile error.
+    pub fn set_search(&mut self, query: String, case_sensitive: bool) -> Result<(), String> {
+        if query.is_empty() {
+            self.clear_search();
+            return Ok(());
+        }
+
+        self.search_regex = Some(compile_search(&query, case_sensitive)?);
+        self.search = Some(query);
+        self.search_case_sensitive = case_sensitive;
+        Ok(())
+    }
+
+    /// Remove the search
+    pub fn clear_search(&mut self) {
+        self.search = None;
+        self.search_regex = None;
+    }
+
+    /// Whether any of `fields` matches the search (always true without a search)
+    pub fn matches_search<'a>(&self, mut fields: impl Iterator<Item = &'a str>) -> bool {
+        match &self.search_regex {
+            Some(regex) => fields.any(|field| regex.is_match(field)),
+            None => true,
+        }
+    }
+
+    /// Byte ranges of `text` matched by the search, for highlighting
+    pub fn search_matches(&self, text: &str) -> Vec<Range<usize>> {
+        match &self.search_regex {
+            Some(regex) => regex.find_iter(text)
+                .map(|found| found.range())
+                .filter(|range| !range.is_empty())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Search queries starting with this are regular expressions
+pub const REGEX_SEARCH_PREFIX: &str = "re:";
+
+/// Compiled size limit for search regexes
+///
+/// The regex crate matches in linear time, so the compiled size is what
+/// bounds how expensive a pattern can be; larger ones fail to compile.
+const SEARCH_SIZE_LIMIT: usize = 1 << 20;
+
+/// Nesting limit for search regexes
+const SEARCH_NEST_LIMIT: u32 = 32;
+
+/// Compile a search query: a substring, or a regex after [`REGEX_SEARCH_PREFIX`]
+///
+/// Errors are a single line, ready to show under the search input.
+pub fn compile_search(query: &str, case_sensitive: bool) -> Result<Regex, String> {
+    let pattern = match query.strip_prefix(REGEX_SEARCH_PREFIX) {
+        Some(pattern) => pattern.to_string(),
+        None => regex::escape(query),
+    };
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!case_sensitive)
+        .size_limit(SEARCH_SIZE_LIMIT)
+        .dfa_size_limit(SEARCH_SIZE_LIMIT)
+        .nest_limit(SEARCH_NEST_LIMIT)
+        .build()
+        .map_err(|e| {
+            // Syntax errors span several lines with a caret; the last one says what's wrong
+            let message = e.to_string();
+            message.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or_default().trim().to_string()
+        })
+}
+
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use log::{debug, error, info, warn};
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use std::ops::Range;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::{broadcast, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::{broadcast, Mutex, Semaphore};
 
+use crate::config::detect::detect_log_files;
 use crate::config::Config;
-use crate::core::{self, SessionManager};
-use crate::data::{LogEntry, Session, Store};
+use crate::core::event_source::{self, EventSource};
+use crate::core::{self, AlertEngine, AlertHistory, Canaries, SessionManager, StreamStats, TaskHealth};
+use crate::core::Severity;
+use crate::core::export::ExportScope;
+use crate::core::rdap::RdapInfo;
+use crate::core::threat_feeds;
+use crate::data::{backend, ClientFingerprint, LogEntry, Session, Store, StoreHandle, StoreSnapshot, WhitelistFilter};
+use crate::ui::alerts::AlertsView;
+use crate::ui::columns::ColumnChooser;
+use crate::ui::rule_tester::RuleTester;
+use crate::ui::keys::KeySequence;
+use crate::ui::log_viewer::LogViewer;
+use crate::ui::panels::{PanelCaches, PanelFocus};
+use crate::ui::watchlist::CommandWatchlist;
+use crate::ui::theme::Theme;
+use crate::ui::wizard::SetupWizard;
+use crate::ui::{filter_sessions, malware_files, visible_logs, ClientSort, GaugeScale, GeoRanking, LiveFeed, LogViewState};
+use crate::utils::time::Clock;
 
 /// Current application state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -17,8 +139,10 @@ pub enum AppState {
     /// Normal operation mode
     Running,
     /// Configuration mode
+    #[allow(dead_code)]
     ConfigMode,
     /// Viewing session details
+    #[allow(dead_code)]
     SessionView,
     /// Shutting down
     ShuttingDown,
@@ -33,8 +157,12 @@ pub enum ConnectionStatus {
     Connecting,
     /// Connected and monitoring
     Connected,
+    /// Connection lost, attempting to reconnect
+    Reconnecting(u8), // Attempt number
     /// Connection failed
     Failed(u8), // Retry count
+    /// Connected, but no line has arrived since this time for longer than `honeypot.stale_after_secs`
+    Stale(DateTime<Utc>),
 }
 
 /// Main application struct
@@ -45,8 +173,10 @@ pub struct App {
     pub config: Config,
     /// Status of connection to honeypot
     pub connection_status: ConnectionStatus,
+    /// When the status last became Connected, so a quiet start can also go stale
+    pub connected_at: Option<DateTime<Utc>>,
     /// Data store
-    pub store: Arc<RwLock<Store>>,
+    pub store: StoreHandle,
     /// Session manager
     pub session_manager: Arc<SessionManager>,
     /// Event sender
@@ -61,6 +191,92 @@ pub struct App {
     pub stats: Arc<Mutex<AppStats>>,
     /// Path to honeypot logs
     pub log_paths: Vec<PathBuf>,
+    /// Turns at reading log files, shared by every file watcher (`honeypot.max_concurrent_watchers`)
+    watcher_permits: Arc<Semaphore>,
+    /// Mask sensitive values when rendering
+    pub anonymize: bool,
+    /// Show passwords as "••••" when rendering
+    pub mask_secrets: bool,
+    /// Redraw only on keypresses and refreshes (`ui.manual_refresh`)
+    pub manual_refresh: bool,
+    /// When the view on screen was last drawn
+    pub drawn_at: DateTime<Utc>,
+    /// Show the parse failure diagnostics on the logs tab
+    pub show_parse_errors: bool,
+    /// Show the threat intel column on the logs tab (when intel is loaded)
+    pub show_intel_column: bool,
+    /// Selection and tail-follow state of the logs tab
+    pub log_view: LogViewState,
+    /// Log entry shown in the details pane
+    pub selected_log_entry_id: Option<String>,
+    /// How the geography view ranks countries and ASNs
+    pub geo_ranking: GeoRanking,
+    /// What the dashboard's activity gauges are filled relative to
+    pub gauge_scale: GaugeScale,
+    /// Row selected in the security view's malware panel
+    pub selected_malware: usize,
+    /// Bar selected in the security view's listeners panel
+    pub selected_listener: usize,
+    /// Row selected in the security view's clients panel
+    pub selected_client: usize,
+    /// How the clients panel is sorted
+    pub client_sort: ClientSort,
+    /// Least severe alert the alerts panel and view show
+    pub alert_min_severity: Severity,
+    /// Alerts kept across restarts, written by the alert engine
+    pub alert_history: Arc<parking_lot::Mutex<AlertHistory>>,
+    /// Full-screen alerts view, while open
+    pub alerts_view: Option<AlertsView>,
+    /// Row selected in the sessions list (newest first)
+    pub session_cursor: usize,
+    /// Row selected among the open session's similar sessions, while they have the keys
+    pub similar_cursor: Option<usize>,
+    /// Show the collapsed scan summaries under the sessions list
+    pub show_scan_summaries: bool,
+    /// Partially typed multi-key sequence
+    pub keys: KeySequence,
+    /// Search being typed after `/`, before it is applied
+    pub search_input: Option<String>,
+    /// Why `search_input` doesn't compile, if it doesn't
+    pub search_error: Option<String>,
+    /// Show the help overlay
+    pub show_help: bool,
+    /// Column chooser popup, while open
+    pub column_chooser: Option<ColumnChooser>,
+    /// Filtered-or-all choice after `E`, while open
+    pub export_prompt: Option<ExportPrompt>,
+    /// Rule pattern tester on the settings tab, while open
+    pub rule_tester: Option<RuleTester>,
+    /// Application log viewer, while open
+    pub log_viewer: Option<LogViewer>,
+    /// Health of the background tasks
+    pub health: TaskHealth,
+    /// Show the task health overlay
+    pub show_health: bool,
+    /// Threat intel refresh, updated by its background task
+    pub intel_refresh: Arc<parking_lot::Mutex<IntelRefresh>>,
+    /// Registration lookups by IP, filled in by their background tasks
+    pub rdap_lookups: Arc<parking_lot::Mutex<HashMap<String, RdapLookup>>>,
+    /// IP whose registration popup is open
+    pub rdap_popup: Option<String>,
+    /// Clients and drops of the event stream, while it runs
+    pub stream_stats: Option<Arc<StreamStats>>,
+    /// Focused panel on the dashboard, security and geography tabs
+    pub panel_focus: PanelFocus,
+    /// Aggregations behind the heavier panels
+    pub panel_caches: PanelCaches,
+    /// Ticker of notable events
+    pub live_feed: LiveFeed,
+    /// Commands highlighted wherever they are shown
+    pub watchlist: CommandWatchlist,
+    /// Honeytokens looked for in commands and their output (`[canaries]`)
+    pub canaries: Canaries,
+    /// Formats timestamps in the configured zone
+    pub clock: Clock,
+    /// Setup wizard, while open
+    pub wizard: Option<SetupWizard>,
+    /// Styles for risk levels, event types and outcomes
+    pub theme: Theme,
 }
 
 /// Application event types
@@ -69,6 +285,7 @@ pub enum AppEvent {
     /// New log entry detected
     NewLogEntry(LogEntry),
     /// New session detected
+    #[allow(dead_code)]
     NewSession(Session),
     /// Session updated
     SessionUpdate(Session),
@@ -89,10 +306,58 @@ pub struct AppFilters {
     pub from_time: Option<DateTime<Utc>>,
     /// Filter by timestamp (to)
     pub to_time: Option<DateTime<Utc>>,
+    /// Rolling time filter picked with `P`: the last this many hours, moved
+    /// along on every tick
+    pub last_hours: Option<u64>,
     /// Filter by event type
     pub event_type: Option<String>,
-    /// Search string
+    /// Search string, as typed
     pub search: Option<String>,
+    /// `search` compiled by [`compile_search`]
+    pub search_regex: Option<Regex>,
+    /// Whether `search` was compiled case-sensitively
+    pub search_case_sensitive: bool,
+    /// Filter sessions by tag
+    pub tag: Option<String>,
+    /// Filter sessions by destination port (honeypot listener)
+    pub dst_port: Option<u16>,
+    /// Filter sessions by client fingerprint (HASSH and banner)
+    pub client: Option<ClientFingerprint>,
+}
+
+/// Entry counts offered by the export prompt
+#[derive(Debug, Clone)]
+pub struct ExportPrompt {
+    /// Filters behind the view, as shown in the prompt
+    pub filters: String,
+    /// Entries in the filtered view
+    pub filtered: usize,
+    /// Entries in the store
+    pub total: usize,
+}
+
+/// Progress of a threat intel refresh started with `i` on the security tab
+#[derive(Debug, Clone)]
+pub enum IntelRefresh {
+    /// No refresh started yet
+    Idle,
+    /// Feeds are being downloaded and read
+    Running,
+    /// The last refresh loaded this many entries
+    Done { entries: usize, at: DateTime<Utc> },
+    /// The last refresh failed
+    Failed { error: String, at: DateTime<Utc> },
+}
+
+/// State of an RDAP lookup started with `w`
+#[derive(Debug, Clone)]
+pub enum RdapLookup {
+    /// Waiting for the server
+    Running,
+    /// Registration found; kept for the rest of the run
+    Done(RdapInfo),
+    /// The lookup failed; the next `w` tries again
+    Failed(String),
 }
 
 /// Application statistics
@@ -123,11 +388,23 @@ impl App {
     pub async fn new(config: Config) -> Result<Self> {
         info!("Initializing application");
 
+        // Catch unknown panel names before the dashboard is drawn
+        crate::ui::check_dashboard_panels(&config.dashboard)?;
+
         // Set up event channel
         let (event_tx, _) = broadcast::channel(100);
 
-        // Create data store
-        let store = Arc::new(RwLock::new(Store::new(&config)?));
+        // Every background task reports here
+        let health = TaskHealth::new();
+
+        // Create data store; snapshots are published once per UI refresh
+        let (store, writer) = StoreHandle::spawn(
+            Store::new(&config)?,
+            WhitelistFilter::from_config(&config),
+            backend::open(&config)?,
+            Duration::from_millis(config.ui.refresh_interval_ms.max(1)),
+        );
+        health.track_draining(health.register("store writer", false), writer);
 
         // Create session manager
         let session_manager = Arc::new(SessionManager::new(
@@ -141,7 +418,7 @@ impl App {
             .context("Failed to locate honeypot log paths")?;
 
         if log_paths.is_empty() {
-            warn!("No log paths found. Use setup script or configure manually.");
+            warn!("No log paths found. Run the setup wizard (--setup) or configure manually.");
         } else {
             info!("Found {} log paths", log_paths.len());
             for path in &log_paths {
@@ -149,10 +426,107 @@ impl App {
             }
         }
 
+        let anonymize = config.ui.anonymize;
+        let mask_secrets = config.ui.mask_secrets;
+        let manual_refresh = config.ui.manual_refresh;
+        let live_feed = LiveFeed::new(config.dashboard.feed_max_lines);
+        let clock = Clock::from_config(&config.ui);
+        let theme = Theme::from_config(&config.ui);
+        let mut log_view = LogViewState::new(config.ui.follow_logs);
+        log_view.collapse_duplicates = config.ui.collapse_duplicates;
+        let watchlist = CommandWatchlist::from_config(&config)?;
+        let canaries = Canaries::from_config(&config)?;
+        let panel_caches = PanelCaches::new(Duration::from_secs(config.dashboard.refresh_interval as u64));
+        let watcher_permits = Arc::new(Semaphore::new(config.honeypot.max_concurrent_watchers.max(1)));
+
+        // Alerts from earlier runs are loaded as they were; only new events notify
+        let alert_history = AlertHistory::load(&config).unwrap_or_else(|e| {
+            error!("{:#}; alerts are kept in memory only", e);
+            AlertHistory::default()
+        });
+        let alert_history = Arc::new(parking_lot::Mutex::new(alert_history));
+        let mut alert_engine = AlertEngine::new(config.alert.clone(), event_tx.clone())
+            .with_canaries(canaries.clone())
+            .with_history(alert_history.clone());
+        if config.anomaly.enabled {
+            alert_engine = alert_engine.with_anomalies(store.clone());
+        }
+        let reporter = health.register("alert engine", false);
+        let engine_reporter = reporter.clone();
+        let engine = tokio::spawn(async move {
+            if let Err(e) = alert_engine.start().await {
+                error!("Alert engine stopped: {:#}", e);
+                engine_reporter.report_error(format!("{:#}", e));
+            }
+        });
+        health.track(reporter, engine);
+
+        let stream_stats = if config.stream.enabled {
+            let stats = Arc::new(StreamStats::default());
+            let reporter = health.register("event stream", false);
+            let stream = core::start_event_stream(
+                &config,
+                event_tx.clone(),
+                store.clone(),
+                session_manager.open_sessions(),
+                session_manager.shared_analyzer(),
+                stats.clone(),
+                reporter.clone(),
+            )
+            .await;
+            match stream {
+                Ok(stream) => {
+                    health.track(reporter, stream);
+                    Some(stats)
+                }
+                Err(e) => {
+                    error!("Event stream not started: {:#}", e);
+                    reporter.report_error(format!("{:#}", e));
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        if config.siem_integration.enabled {
+            #[cfg(feature = "siem")]
+            {
+                let reporter = health.register("SIEM sender", false);
+                match core::start_siem_sender(&config, event_tx.clone(), store.clone(), reporter.clone()) {
+                    Ok(sender) => health.track(reporter, sender),
+                    Err(e) => {
+                        error!("SIEM forwarding not started: {:#}", e);
+                        reporter.report_error(format!("{:#}", e));
+                    }
+                }
+            }
+            #[cfg(not(feature = "siem"))]
+            warn!("SIEM integration is enabled but xkippo-tui was built without the siem feature");
+        }
+
+        if config.threat_intel.enabled && config.threat_intel.update_frequency > 0 && !config.threat_intel.feeds.is_empty() {
+            let reporter = health.register("threat intel updater", false);
+            let updater = threat_feeds::start_feed_updater(
+                &config.threat_intel,
+                session_manager.shared_analyzer(),
+                reporter.clone(),
+            );
+            health.track(reporter, updater);
+        }
+
+        if config.web.enabled {
+            match core::start_web_server(&config, store.clone(), alert_history.clone(), stream_stats.clone()).await {
+                Ok(server) => health.track(health.register("web server", false), server),
+                Err(e) => error!("Web snapshot not started: {:#}", e),
+            }
+        }
+
         let app = Self {
             state: AppState::Starting,
             config,
             connection_status: ConnectionStatus::Disconnected,
+            connected_at: None,
             store,
             session_manager,
             event_tx,
@@ -160,61 +534,465 @@ impl App {
             selected_session_id: None,
             filters: AppFilters::default(),
             stats: Arc::new(Mutex::new(AppStats::default())),
+            watcher_permits,
             log_paths,
+            anonymize,
+            mask_secrets,
+            manual_refresh,
+            drawn_at: Utc::now(),
+            show_parse_errors: false,
+            show_intel_column: true,
+            log_view,
+            selected_log_entry_id: None,
+            geo_ranking: GeoRanking::default(),
+            gauge_scale: GaugeScale::default(),
+            selected_malware: 0,
+            selected_listener: 0,
+            selected_client: 0,
+            client_sort: ClientSort::default(),
+            alert_min_severity: Severity::default(),
+            alert_history,
+            alerts_view: None,
+            session_cursor: 0,
+            show_scan_summaries: false,
+            similar_cursor: None,
+            keys: KeySequence::default(),
+            search_input: None,
+            search_error: None,
+            show_help: false,
+            column_chooser: None,
+            export_prompt: None,
+            rule_tester: None,
+            log_viewer: None,
+            health,
+            show_health: false,
+            intel_refresh: Arc::new(parking_lot::Mutex::new(IntelRefresh::Idle)),
+            rdap_lookups: Arc::new(parking_lot::Mutex::new(HashMap::new())),
+            rdap_popup: None,
+            stream_stats,
+            panel_focus: PanelFocus::default(),
+            panel_caches,
+            live_feed,
+            watchlist,
+            canaries,
+            clock,
+            wizard: None,
+            theme,
         };
 
         Ok(app)
     }
 
+    /// Open the setup wizard, to write its result to `path`
+    pub fn open_wizard(&mut self, path: PathBuf) {
+        self.wizard = Some(SetupWizard::new(&self.config, path));
+    }
+
+    /// Switch to a configuration the setup wizard saved
+    ///
+    /// Newly chosen log files are watched straight away; source, GeoIP and
+    /// alert changes take effect on the next start.
+    pub async fn apply_setup(&mut self, config: Config) -> Result<()> {
+        self.watchlist = CommandWatchlist::from_config(&config)?;
+        self.canaries = Canaries::from_config(&config)?;
+        self.config = config;
+
+        if self.config.honeypot.source != "file" {
+            return Ok(());
+        }
+
+        for path in find_log_paths(&self.config)? {
+            if self.log_paths.contains(&path) {
+                continue;
+            }
+            match self.watch_log_file(path.clone()).await {
+                Ok(()) => info!("Started watching log file: {}", path.display()),
+                Err(e) => error!("Failed to watch log file {}: {}", path.display(), e),
+            }
+            self.log_paths.push(path);
+        }
+
+        Ok(())
+    }
+
     /// Connect to the honeypot logs
     pub async fn connect(&mut self) -> Result<()> {
         info!("Connecting to honeypot logs");
         self.connection_status = ConnectionStatus::Connecting;
 
-        // Initialize file watchers for log paths
-        for path in &self.log_paths {
-            match core::start_log_watcher(
-                path.clone(),
-                self.store.clone(),
-                self.event_tx.clone(),
-                &self.config,
-            )
-            .await
-            {
-                Ok(_) => {
-                    info!("Started watching log file: {}", path.display());
-                }
-                Err(e) => {
-                    error!("Failed to watch log file {}: {}", path.display(), e);
-                    self.connection_status = ConnectionStatus::Failed(1);
-                    return Err(e);
-                }
+        // Files, the journal or MySQL per honeypot.source, and the remote tail on top
+        let log_paths = if self.config.honeypot.source == "file" {
+            self.log_paths.as_slice()
+        } else {
+            &[]
+        };
+
+        let max_readers = self.config.honeypot.max_concurrent_watchers.max(1);
+        if log_paths.len() > max_readers {
+            info!("Watching {} log files, reading at most {} at a time", log_paths.len(), max_readers);
+        }
+
+        let sources = match event_source::sources(&self.config, log_paths, &self.watcher_permits) {
+            Ok(sources) => sources,
+            Err(e) => {
+                self.connection_status = ConnectionStatus::Failed(1);
+                return Err(e);
+            }
+        };
+
+        let mut status_from_source = false;
+        for source in sources {
+            let name = source.name();
+            if let Err(e) = self.start_source(source.clone()).await {
+                error!("Failed to start {}: {:#}", name, e);
+                self.connection_status = ConnectionStatus::Failed(1);
+                return Err(e);
             }
+            info!("Started {}", name);
+            status_from_source |= source.reports_status();
         }
 
         // Start session manager
-        self.session_manager.start().await?;
+        self.session_manager.start(&self.health).await?;
 
-        self.connection_status = ConnectionStatus::Connected;
+        // The remote transport and the journal report their own status through ConnectionStatusChange events
+        self.set_connection_status(if status_from_source {
+            ConnectionStatus::Connecting
+        } else {
+            ConnectionStatus::Connected
+        });
         self.state = AppState::Running;
 
         info!("Connected successfully");
         Ok(())
     }
 
+    /// Watch the log file at `path`, restarting the watcher whenever it stops
+    async fn watch_log_file(&self, path: PathBuf) -> Result<()> {
+        self.start_source(event_source::file_source(&self.config, path, &self.watcher_permits)).await
+    }
+
+    /// Start reading `source`, restarting it whenever it stops
+    async fn start_source(&self, source: Arc<dyn EventSource>) -> Result<()> {
+        let (store, event_tx) = (self.store.clone(), self.event_tx.clone());
+        self.health.supervise(source.name(), move |reporter| {
+            source.start(store.clone(), event_tx.clone(), reporter)
+        })
+        .await
+    }
+
     /// Update application state
     pub fn update(&mut self) -> Result<()> {
         // Process any pending events
         // Update statistics
-        // Check connection status
+        self.check_staleness();
+        self.filters.roll_time_range(Utc::now());
+        Ok(())
+    }
+
+    /// Change the connection status, noting when it becomes Connected
+    pub fn set_connection_status(&mut self, status: ConnectionStatus) {
+        if status == ConnectionStatus::Connected && self.connection_status != ConnectionStatus::Connected {
+            self.connected_at = Some(Utc::now());
+        }
+        self.connection_status = status;
+    }
+
+    /// Flag the connection as Stale when lines stop arriving, and recover when they resume
+    ///
+    /// The watchers keep running either way; this only reflects what the status bar shows.
+    fn check_staleness(&mut self) {
+        let window = self.config.honeypot.stale_after_secs;
+        if window == 0 {
+            return;
+        }
+
+        // Quiet since connecting counts too, but lines from before the connection don't
+        let last_line = self.store.snapshot().get_last_line_time();
+        let last_heard = match (last_line, self.connected_at) {
+            (Some(line), Some(connected)) => line.max(connected),
+            (line, connected) => match line.or(connected) {
+                Some(time) => time,
+                None => return,
+            },
+        };
+        let stale = Utc::now() - last_heard > chrono::Duration::seconds(window as i64);
+
+        let status = match self.connection_status {
+            ConnectionStatus::Connected if stale => {
+                warn!("No log lines for over {}s; is the honeypot still running?", window);
+                ConnectionStatus::Stale(last_heard)
+            }
+            ConnectionStatus::Stale(_) if !stale => {
+                info!("Log lines resumed");
+                ConnectionStatus::Connected
+            }
+            _ => return,
+        };
+
+        self.connection_status = status;
+        let _ = self.event_tx.send(AppEvent::ConnectionStatusChange(status));
+    }
+
+    /// Switch the security dashboard to its next layout, saving it if configured
+    pub fn cycle_dashboard_layout(&mut self) -> Result<()> {
+        let layout = self.config.dashboard.cycle_layout().to_string();
+        info!("Switched dashboard layout to {}", layout);
+
+        if self.config.dashboard.persist_layout {
+            if let Some(path) = &self.config.source_path {
+                self.config
+                    .save(path)
+                    .context("Failed to save dashboard layout")?;
+            }
+        }
+
         Ok(())
     }
 
+    /// Set the columns of `table` (`None` restores its default), saving them to the config file
+    pub fn set_columns(&mut self, table: &str, columns: Option<Vec<String>>) -> Result<()> {
+        match columns {
+            Some(columns) => self.config.ui.columns.insert(table.to_string(), columns),
+            None => self.config.ui.columns.remove(table),
+        };
+        info!("Updated the {} columns", table);
+
+        if let Some(path) = &self.config.source_path {
+            self.config
+                .save(path)
+                .context("Failed to save table columns")?;
+        }
+
+        Ok(())
+    }
+
+    /// Export the store, or the current tab's filtered view, in the configured formats
+    pub fn export(&self, scope: ExportScope) -> Result<Vec<PathBuf>> {
+        if !self.config.export.enabled {
+            warn!("Export is disabled; set export.enabled = true to enable it");
+            return Ok(Vec::new());
+        }
+
+        let store = self.store.snapshot();
+        let entries = match scope {
+            ExportScope::All => store.get_log_entries(),
+            ExportScope::Filtered => self.filtered_log_entries(&store),
+        };
+        core::export::export_snapshot(&self.config, &store, &entries, scope)
+            .context("Failed to export data")
+    }
+
+    /// Filters narrowing the current tab, for the export prompt; `None` when nothing is hidden
+    ///
+    /// The sessions tab also filters by tag, port and client; every other tab exports what the
+    /// logs tab would show for the same search.
+    pub fn view_filters(&self) -> Option<String> {
+        let mut filters = Vec::new();
+        if let Some(search) = &self.filters.search {
+            filters.push(format!("search \"{}\"", search));
+        }
+        if let (3, Some(tag)) = (self.selected_tab, &self.filters.tag) {
+            filters.push(format!("tag {}", tag));
+        }
+        if let (3, Some(port)) = (self.selected_tab, self.filters.dst_port) {
+            filters.push(format!("port {}", port));
+        }
+        if let (3, Some(client)) = (self.selected_tab, &self.filters.client) {
+            filters.push(format!("client {}", client));
+        }
+        if filters.is_empty() { None } else { Some(filters.join(", ")) }
+    }
+
+    /// Log entries behind the current tab's filtered view, oldest first
+    ///
+    /// Uses the same predicates as the views themselves: on the sessions tab
+    /// every entry of a listed session, elsewhere the logs tab's list.
+    pub fn filtered_log_entries<'a>(&self, store: &'a StoreSnapshot) -> Vec<&'a LogEntry> {
+        if self.selected_tab != 3 {
+            return visible_logs(self, store);
+        }
+
+        let listed = filter_sessions(&self.filters, store);
+        let sessions: std::collections::HashSet<&str> = listed.iter().map(String::as_str).collect();
+        store.get_log_entries()
+            .into_iter()
+            .filter(|entry| store.get_session_for_entry(entry).map_or(false, |session| sessions.contains(session.id.as_str())))
+            .collect()
+    }
+
+    /// Export directly when nothing is filtered, otherwise ask which to export
+    pub fn start_export(&mut self) {
+        let filters = match self.view_filters() {
+            Some(filters) => filters,
+            None => {
+                if let Err(e) = self.export(ExportScope::All) {
+                    error!("{:#}", e);
+                }
+                return;
+            }
+        };
+
+        let store = self.store.snapshot();
+        self.export_prompt = Some(ExportPrompt {
+            filters,
+            filtered: self.filtered_log_entries(&store).len(),
+            total: store.get_log_entry_count(),
+        });
+    }
+
+    /// Export the logs tab's group-by table as CSV
+    pub fn export_group_by(&self) -> Result<Option<PathBuf>> {
+        let group_by = match &self.log_view.group_by {
+            Some(group_by) => group_by,
+            None => return Ok(None),
+        };
+        if !self.config.export.enabled {
+            warn!("Export is disabled; set export.enabled = true to enable it");
+            return Ok(None);
+        }
+
+        let store = self.store.snapshot();
+        let (rows, total) = group_by.visible_rows(self, &store);
+        core::export::export_counts(&self.config, group_by.field.name(), &rows, total)
+            .map(Some)
+            .context("Failed to export group-by table")
+    }
+
+    /// Export the terminal recording of the session open in the sessions tab as an asciinema cast
+    pub fn export_selected_cast(&self) -> Result<PathBuf> {
+        if !self.config.export.enabled {
+            anyhow::bail!("Export is disabled; set export.enabled = true to enable it");
+        }
+
+        let store = self.store.snapshot();
+        let session = self.selected_session_id.as_ref()
+            .and_then(|id| store.get_session(id))
+            .context("No session open")?;
+        core::cast::export_cast(&self.config, session)
+            .context("Failed to export terminal recording")
+    }
+
+    /// Download the threat intel feeds and reload them in the background, unless a refresh is already running
+    ///
+    /// Every feed is downloaded, however recent its copy; the status bar
+    /// shows progress and the outcome through `intel_refresh`. A feed that
+    /// fails keeps its last copy, and the failure is what the status bar shows.
+    pub fn refresh_threat_intel(&self) {
+        {
+            let mut refresh = self.intel_refresh.lock();
+            if let IntelRefresh::Running = *refresh {
+                return;
+            }
+            if !self.config.threat_intel.enabled {
+                *refresh = IntelRefresh::Failed {
+                    error: "threat intel is disabled (threat_intel.enabled)".to_string(),
+                    at: Utc::now(),
+                };
+                return;
+            }
+            *refresh = IntelRefresh::Running;
+        }
+
+        let analyzer = self.session_manager.shared_analyzer();
+        let refresh = self.intel_refresh.clone();
+        let config = self.config.threat_intel.clone();
+        tokio::spawn(async move {
+            let downloaded = threat_feeds::download_feeds(&config, true).await;
+            let loaded = tokio::task::spawn_blocking(move || analyzer.load_threat_intel()).await;
+
+            let failed = |error: String| IntelRefresh::Failed { error, at: Utc::now() };
+            let outcome = match (downloaded, loaded) {
+                (Err(e), _) | (_, Ok(Err(e))) => failed(format!("{:#}", e)),
+                (_, Err(e)) => failed(e.to_string()),
+                (Ok(downloads), Ok(Ok(_))) if !downloads.errors.is_empty() => failed(format!(
+                    "{} of {} feeds failed: {}",
+                    downloads.errors.len(),
+                    config.feeds.len(),
+                    downloads.errors.join("; ")
+                )),
+                (Ok(_), Ok(Ok(entries))) => {
+                    info!("Refreshed threat intel: {} entries", entries);
+                    IntelRefresh::Done { entries, at: Utc::now() }
+                }
+            };
+            if let IntelRefresh::Failed { error, .. } = &outcome {
+                warn!("Failed to refresh threat intel: {}", error);
+            }
+            *refresh.lock() = outcome;
+        });
+    }
+
+    /// Open the registration popup for `ip`, looking it up unless it is known
+    ///
+    /// Lookups run in the background and the popup shows their progress;
+    /// `rdap.timeout_secs` bounds how long one can take.
+    pub fn lookup_rdap(&mut self, ip: &str) {
+        self.rdap_popup = Some(ip.to_string());
+
+        {
+            let mut lookups = self.rdap_lookups.lock();
+            if let Some(RdapLookup::Running) | Some(RdapLookup::Done(_)) = lookups.get(ip) {
+                return;
+            }
+            if !self.config.rdap.enabled {
+                lookups.insert(ip.to_string(), RdapLookup::Failed("RDAP lookups are disabled (rdap.enabled)".to_string()));
+                return;
+            }
+            lookups.insert(ip.to_string(), RdapLookup::Running);
+        }
+
+        let config = self.config.rdap.clone();
+        let lookups = self.rdap_lookups.clone();
+        let ip = ip.to_string();
+        tokio::spawn(async move {
+            let outcome = match core::rdap::lookup(&config, &ip).await {
+                Ok(info) => RdapLookup::Done(info),
+                Err(e) => {
+                    warn!("RDAP lookup for {} failed: {:#}", ip, e);
+                    RdapLookup::Failed(format!("{:#}", e))
+                }
+            };
+            lookups.lock().insert(ip, outcome);
+        });
+    }
+
+    /// Export the original log lines of the session open in the sessions tab
+    pub fn export_selected_raw(&self) -> Result<PathBuf> {
+        if !self.config.export.enabled {
+            anyhow::bail!("Export is disabled; set export.enabled = true to enable it");
+        }
+
+        let store = self.store.snapshot();
+        let session = self.selected_session_id.as_ref()
+            .and_then(|id| store.get_session(id))
+            .context("No session open")?;
+        core::export::export_raw_session(&self.config, &self.store, session)
+            .map(|(path, _)| path)
+            .context("Failed to export original log lines")
+    }
+
+    /// Copy the file selected in the malware panel out for analysis
+    pub fn extract_selected_sample(&self) -> Result<PathBuf> {
+        let store = self.store.snapshot();
+        let files = malware_files(self, &store);
+        let (session, file) = files.get(self.selected_malware)
+            .context("No captured file selected")?;
+
+        let family = self.session_manager.analyzer().detect_malware_family(session);
+        core::samples::extract_sample(&self.config, session, file, family.as_deref())
+            .context("Failed to extract sample")
+    }
+
     /// Handle quit request
     pub async fn quit(&mut self) -> Result<()> {
         info!("Shutting down");
         self.state = AppState::ShuttingDown;
         
+        // Tasks ending from here on are expected; this aborts the watchers,
+        // the alert engine and everything else still running
+        self.health.shut_down();
+        
         // Graceful shutdown of components
         self.session_manager.stop().await?;
         
@@ -238,18 +1016,10 @@ fn find_log_paths(config: &Config) -> Result<Vec<PathBuf>> {
 
     // If no configured paths or auto-detect is enabled, try common locations
     if paths.is_empty() || config.honeypot.auto_detect {
-        // Common Cowrie log locations
-        let common_paths = [
-            "/var/log/cowrie/cowrie.json",
-            "/opt/cowrie/var/log/cowrie/cowrie.json",
-            "/home/cowrie/cowrie/var/log/cowrie/cowrie.json",
-            "/usr/local/cowrie/var/log/cowrie/cowrie.json",
-        ];
-
-        for path in &common_paths {
-            let path = PathBuf::from(path);
-            if path.exists() {
-                paths.push(path);
+        for log in detect_log_files() {
+            if !paths.contains(&log.path) {
+                info!("Detected log {} ({})", log.path.display(), log.source);
+                paths.push(log.path);
             }
         }
     }
@@ -0,0 +1,288 @@
+//! Finding Cowrie's JSON log on this machine
+//!
+//! Several probes run at once, each bounded by [`PROBE_TIMEOUT`] so a slow
+//! `docker` or `systemctl` can never hold up startup. Every file found says
+//! which probe found it, for the startup log and the setup wizard.
+
+use log::debug;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Longest any probe, and detection as a whole, may take
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Environment variable holding a glob of log files, e.g. `/srv/*/cowrie.json`
+pub const LOG_GLOB_VAR: &str = "COWRIE_LOG";
+
+/// Where Cowrie installs usually write their JSON log
+pub const COMMON_LOG_PATHS: &[&str] = &[
+    "/var/log/cowrie/cowrie.json",
+    "/opt/cowrie/var/log/cowrie/cowrie.json",
+    "/home/cowrie/cowrie/var/log/cowrie/cowrie.json",
+    "/usr/local/cowrie/var/log/cowrie/cowrie.json",
+];
+
+/// The log below a Cowrie install directory
+const INSTALL_LOG: &str = "var/log/cowrie/cowrie.json";
+
+/// Install directories relative to a home directory
+const HOME_LAYOUTS: &[&str] = &["cowrie", "cowrie-git", ""];
+
+/// Unit files that may name Cowrie's working directory
+const UNIT_FILES: &[&str] = &[
+    "/etc/systemd/system/cowrie.service",
+    "/lib/systemd/system/cowrie.service",
+    "/usr/lib/systemd/system/cowrie.service",
+];
+
+/// How a log file was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DetectionSource {
+    /// Matched the `COWRIE_LOG` glob
+    EnvGlob,
+    /// Below the `WorkingDirectory` of a systemd unit
+    Systemd(String),
+    /// Below a volume mounted into a running container
+    Docker(String),
+    /// Below a user's home directory
+    Home(String),
+    /// One of [`COMMON_LOG_PATHS`]
+    CommonPath,
+}
+
+impl fmt::Display for DetectionSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DetectionSource::EnvGlob => write!(f, "${}", LOG_GLOB_VAR),
+            DetectionSource::Systemd(unit) => write!(f, "systemd unit {}", unit),
+            DetectionSource::Docker(container) => write!(f, "docker container {}", container),
+            DetectionSource::Home(user) => write!(f, "home of {}", user),
+            DetectionSource::CommonPath => write!(f, "common location"),
+        }
+    }
+}
+
+/// A log file that exists, and how it was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedLog {
+    /// The file
+    pub path: PathBuf,
+    /// The probe that found it
+    pub source: DetectionSource,
+}
+
+/// Run every probe concurrently, returning what they found within [`PROBE_TIMEOUT`]
+///
+/// Results are ordered by probe (environment, systemd, docker, home, common
+/// locations) and each file is listed once, under the first probe that found it.
+pub fn detect_log_files() -> Vec<DetectedLog> {
+    let probes: [fn() -> Vec<DetectedLog>; 5] = [probe_env, probe_systemd, probe_docker, probe_homes, probe_common];
+
+    let (tx, rx) = mpsc::channel();
+    for (order, probe) in probes.iter().copied().enumerate() {
+        let tx = tx.clone();
+        // A probe that overruns is left behind; its result is simply never read
+        thread::spawn(move || {
+            let _ = tx.send((order, probe()));
+        });
+    }
+    drop(tx);
+
+    let deadline = Instant::now() + PROBE_TIMEOUT;
+    let mut results = Vec::new();
+    while results.len() < probes.len() {
+        match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(result) => results.push(result),
+            Err(_) => {
+                debug!("Log detection gave up on {} slow probe(s)", probes.len() - results.len());
+                break;
+            }
+        }
+    }
+    results.sort_by_key(|(order, _)| *order);
+
+    let mut found: Vec<DetectedLog> = Vec::new();
+    for log in results.into_iter().flat_map(|(_, logs)| logs) {
+        if !found.iter().any(|known| known.path == log.path) {
+            found.push(log);
+        }
+    }
+    found
+}
+
+/// Files matching the `COWRIE_LOG` glob
+fn probe_env() -> Vec<DetectedLog> {
+    let pattern = match std::env::var(LOG_GLOB_VAR) {
+        Ok(pattern) if !pattern.trim().is_empty() => pattern,
+        _ => return Vec::new(),
+    };
+
+    match glob::glob(pattern.trim()) {
+        Ok(paths) => paths
+            .filter_map(|path| path.ok())
+            .filter(|path| path.is_file())
+            .map(|path| DetectedLog { path, source: DetectionSource::EnvGlob })
+            .collect(),
+        Err(e) => {
+            debug!("Ignoring {}: {}", LOG_GLOB_VAR, e);
+            Vec::new()
+        }
+    }
+}
+
+/// Logs below the working directory of the cowrie unit, from systemd or its unit files
+fn probe_systemd() -> Vec<DetectedLog> {
+    let mut dirs: Vec<(String, PathBuf)> = Vec::new();
+
+    if let Some(output) = run_command("systemctl", &["show", "cowrie", "--property=WorkingDirectory", "--value"]) {
+        let dir = output.trim();
+        if !dir.is_empty() {
+            dirs.push(("cowrie".to_string(), PathBuf::from(dir)));
+        }
+    }
+
+    for unit in UNIT_FILES {
+        let content = match fs::read_to_string(unit) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        let name = Path::new(unit).file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        dirs.extend(
+            content.lines()
+                .filter_map(|line| line.trim().strip_prefix("WorkingDirectory="))
+                // A leading '-' only tells systemd to ignore a missing directory
+                .map(|dir| PathBuf::from(dir.trim().trim_start_matches('-')))
+                .map(|dir| (name.clone(), dir)),
+        );
+    }
+
+    dirs.into_iter()
+        .flat_map(|(unit, dir)| {
+            vec![dir.join(INSTALL_LOG), dir.join("cowrie.json")]
+                .into_iter()
+                .filter(|path| path.is_file())
+                .map(move |path| DetectedLog { path, source: DetectionSource::Systemd(unit.clone()) })
+        })
+        .collect()
+}
+
+/// Logs in the volumes of running containers whose image name contains "cowrie"
+fn probe_docker() -> Vec<DetectedLog> {
+    let containers = match run_command("docker", &["ps", "--format", "{{.ID}} {{.Image}} {{.Names}}"]) {
+        Some(output) => output,
+        None => return Vec::new(),
+    };
+
+    let mut found = Vec::new();
+    for line in containers.lines() {
+        let mut fields = line.split_whitespace();
+        let (id, image, name) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(id), Some(image), Some(name)) => (id, image, name),
+            _ => continue,
+        };
+        if !image.to_lowercase().contains("cowrie") {
+            continue;
+        }
+
+        let mounts = match run_command("docker", &["inspect", "--format", "{{range .Mounts}}{{.Source}}\n{{end}}", id]) {
+            Some(mounts) => mounts,
+            None => continue,
+        };
+        for source in mounts.lines().map(str::trim).filter(|source| !source.is_empty()) {
+            let source = Path::new(source);
+            // The volume may be the log directory itself, the var directory or the whole install
+            found.extend(
+                vec![source.join("cowrie.json"), source.join("log/cowrie/cowrie.json"), source.join(INSTALL_LOG)]
+                    .into_iter()
+                    .filter(|path| path.is_file())
+                    .map(|path| DetectedLog { path, source: DetectionSource::Docker(name.to_string()) }),
+            );
+        }
+    }
+    found
+}
+
+/// Logs in the usual install layouts below the current user's and the cowrie user's homes
+fn probe_homes() -> Vec<DetectedLog> {
+    let mut homes: Vec<(String, PathBuf)> = Vec::new();
+    if let Some(home) = dirs::home_dir() {
+        let user = std::env::var("USER").unwrap_or_else(|_| "current user".to_string());
+        homes.push((user, home));
+    }
+    if let Some(home) = user_home("cowrie") {
+        if !homes.iter().any(|(_, known)| *known == home) {
+            homes.push(("cowrie".to_string(), home));
+        }
+    }
+
+    homes.into_iter()
+        .flat_map(|(user, home)| {
+            HOME_LAYOUTS.iter()
+                .map(move |layout| home.join(layout).join(INSTALL_LOG))
+                .filter(|path| path.is_file())
+                .map(move |path| DetectedLog { path, source: DetectionSource::Home(user.clone()) })
+        })
+        .collect()
+}
+
+/// Common locations that exist
+fn probe_common() -> Vec<DetectedLog> {
+    COMMON_LOG_PATHS.iter()
+        .map(PathBuf::from)
+        .filter(|path| path.is_file())
+        .map(|path| DetectedLog { path, source: DetectionSource::CommonPath })
+        .collect()
+}
+
+/// Home directory of `user` from /etc/passwd
+fn user_home(user: &str) -> Option<PathBuf> {
+    fs::read_to_string("/etc/passwd").ok()?
+        .lines()
+        .map(|line| line.split(':').collect::<Vec<_>>())
+        .find(|fields| fields.len() > 5 && fields[0] == user)
+        .map(|fields| PathBuf::from(fields[5]))
+}
+
+/// Stdout of a command that succeeded within [`PROBE_TIMEOUT`]; it is killed if it takes longer
+fn run_command(program: &str, args: &[&str]) -> Option<String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    // Read while waiting, so a full pipe can't stall the command
+    let mut stdout = child.stdout.take()?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut output = String::new();
+        let _ = tx.send(stdout.read_to_string(&mut output).map(|_| output));
+    });
+
+    let deadline = Instant::now() + PROBE_TIMEOUT;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(20)),
+            _ => {
+                debug!("{} {} timed out", program, args.join(" "));
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+    if !status.success() {
+        return None;
+    }
+
+    rx.recv_timeout(deadline.saturating_duration_since(Instant::now())).ok()?.ok()
+}
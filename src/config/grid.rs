@@ -0,0 +1,137 @@
+//! Grid specifications for the security dashboard (`dashboard.layout`)
+//!
+//! A grid is a list of rows separated by `;`. A row starts with an optional
+//! relative height and `:`, followed by cells separated by `,`. A cell names
+//! one or more panels, stacked top to bottom with `/`, and ends with an
+//! optional relative width. Heights and widths default to 1.
+//!
+//! ```text
+//! 60: threat_overview 60, attack_map 40; 40: high_risk_sessions, alerts
+//! ```
+
+use anyhow::{anyhow, bail, Result};
+use std::str::FromStr;
+
+/// Built-in layouts as grid specifications, in the order they are cycled
+pub const PRESETS: &[(&str, &str)] = &[
+    (
+        "standard",
+        "60: threat_overview 60, attack_map 40; \
+         40: high_risk_sessions 50, alerts 50",
+    ),
+    (
+        "security",
+        "30: attack_map 60, threat_scores 40; \
+         40: high_risk_sessions 60, clients 40; \
+         30: alerts 40, malware 30, forward_destinations/attack_techniques 30",
+    ),
+    (
+        "analytics",
+        "30: threat_summary 40, time_series 60; \
+         40: command_distribution 30, recon_patterns 45, geographic_distribution 25; \
+         30: attacker_correlation 45, session_durations 30, listeners 25",
+    ),
+    (
+        "feed",
+        "live_feed 65, threat_overview/high_risk_sessions 35",
+    ),
+];
+
+/// Panels arranged in rows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid {
+    /// Rows, top to bottom
+    pub rows: Vec<GridRow>,
+}
+
+/// One row of a [`Grid`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridRow {
+    /// Height relative to the other rows
+    pub height: u32,
+    /// Cells, left to right
+    pub cells: Vec<GridCell>,
+}
+
+/// One cell of a [`GridRow`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridCell {
+    /// Width relative to the other cells in the row
+    pub width: u32,
+    /// Panel names, stacked top to bottom in equal parts
+    pub panels: Vec<String>,
+}
+
+impl Grid {
+    /// Every panel name in the grid, in reading order
+    pub fn panels(&self) -> impl Iterator<Item = &str> {
+        self.rows.iter()
+            .flat_map(|row| row.cells.iter())
+            .flat_map(|cell| cell.panels.iter().map(String::as_str))
+    }
+}
+
+impl FromStr for Grid {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let rows = spec.split(';')
+            .map(str::trim)
+            .filter(|row| !row.is_empty())
+            .map(parse_row)
+            .collect::<Result<Vec<GridRow>>>()?;
+
+        if rows.is_empty() {
+            bail!("grid '{}' has no rows", spec);
+        }
+
+        Ok(Self { rows })
+    }
+}
+
+/// Parse `[height:] cell, cell, ...`
+fn parse_row(row: &str) -> Result<GridRow> {
+    let (height, cells) = match row.split_once(':') {
+        Some((height, cells)) => (parse_weight(height.trim(), row)?, cells),
+        None => (1, row),
+    };
+
+    let cells = cells.split(',')
+        .map(|cell| parse_cell(cell.trim(), row))
+        .collect::<Result<Vec<GridCell>>>()?;
+
+    Ok(GridRow { height, cells })
+}
+
+/// Parse `panel[/panel...] [width]`
+fn parse_cell(cell: &str, row: &str) -> Result<GridCell> {
+    let (panels, width) = match cell.rsplit_once(char::is_whitespace) {
+        Some((panels, width)) if width.chars().all(|c| c.is_ascii_digit()) => {
+            (panels, parse_weight(width, row)?)
+        }
+        _ => (cell, 1),
+    };
+
+    let panels = panels.split('/')
+        .map(str::trim)
+        .map(|panel| {
+            let valid = !panel.is_empty()
+                && panel.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if valid {
+                Ok(panel.to_string())
+            } else {
+                Err(anyhow!("'{}' in row '{}' is not a panel name", panel, row))
+            }
+        })
+        .collect::<Result<Vec<String>>>()?;
+
+    Ok(GridCell { width, panels })
+}
+
+/// Parse a relative height or width, which must be at least 1
+fn parse_weight(weight: &str, row: &str) -> Result<u32> {
+    match weight.parse::<u32>() {
+        Ok(weight) if weight > 0 => Ok(weight),
+        _ => Err(anyhow!("'{}' in row '{}' is not a positive size", weight, row)),
+    }
+}
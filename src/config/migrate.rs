@@ -0,0 +1,246 @@
+//! Upgrades configuration files written for older versions
+//!
+//! A file records the format it was written in as `config_version`; files
+//! from before versioning count as version 0. On load, the parsed TOML goes
+//! through each step from its version up to [`CONFIG_VERSION`] before it is
+//! deserialized, so old files keep working unchanged on disk. The upgraded
+//! file is only written back on request (`--migrate-config`), next to a
+//! `.bak` copy of the original.
+
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::value::Table;
+use toml::Value;
+
+/// Format written by this build
+pub const CONFIG_VERSION: u32 = 2;
+
+/// Upgrades a file by one version, noting anything the user should know
+type Step = fn(&mut Table, &mut Vec<String>);
+
+/// `STEPS[n]` upgrades version `n` to `n + 1`
+const STEPS: &[Step] = &[v0_drop_dashboard_toggles, v1_command_alert_tables];
+
+/// What upgrading a file did
+#[derive(Debug, Clone)]
+pub struct Migration {
+    /// Version the file was written in
+    pub from: u32,
+    /// Changes the user should know about
+    pub notes: Vec<String>,
+}
+
+/// Version `table` was written in
+///
+/// A version this build doesn't know is an error rather than a guess.
+pub fn version_of(table: &Table) -> Result<u32> {
+    match table.get("config_version") {
+        None => Ok(0),
+        Some(Value::Integer(version)) if *version >= 0 && *version <= CONFIG_VERSION as i64 => Ok(*version as u32),
+        Some(Value::Integer(version)) => anyhow::bail!(
+            "config_version {} is newer than this build understands ({}); upgrade xkippo-tui",
+            version,
+            CONFIG_VERSION
+        ),
+        Some(other) => anyhow::bail!("config_version must be a number, not {}", other),
+    }
+}
+
+/// Upgrade `table` to [`CONFIG_VERSION`] in place; `None` if it already was
+pub fn migrate(table: &mut Table) -> Result<Option<Migration>> {
+    let from = version_of(table)?;
+    if from == CONFIG_VERSION {
+        return Ok(None);
+    }
+
+    let mut notes = Vec::new();
+    for step in &STEPS[from as usize..] {
+        step(table, &mut notes);
+    }
+    table.insert("config_version".to_string(), Value::Integer(CONFIG_VERSION as i64));
+
+    Ok(Some(Migration { from, notes }))
+}
+
+/// Rewrite the file at `path` in the current format, keeping the original as `<path>.bak`
+///
+/// Returns the version the file was upgraded from, or `None` if it was
+/// current and left alone. Comments don't survive the rewrite.
+pub fn upgrade_file(path: &Path) -> Result<Option<u32>> {
+    let content = fs::read_to_string(path)
+        .context(format!("Failed to read config file: {}", path.display()))?;
+    let mut table: Table = toml::from_str(&content).context("Failed to parse config file")?;
+
+    let migration = match migrate(&mut table)? {
+        Some(migration) => migration,
+        None => return Ok(None),
+    };
+
+    let backup = backup_path(path);
+    fs::copy(path, &backup)
+        .context(format!("Failed to back up config file to {}", backup.display()))?;
+
+    let upgraded = toml::to_string_pretty(&table).context("Failed to serialize config")?;
+    fs::write(path, upgraded)
+        .context(format!("Failed to write config file: {}", path.display()))?;
+
+    Ok(Some(migration.from))
+}
+
+/// Where [`upgrade_file`] keeps the original
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = OsString::from(path.as_os_str());
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+/// Version 0 to 1: drop the `dashboard.show_*` toggles
+///
+/// The panel grid (`dashboard.layout`) replaced them, and nothing read them
+/// since; a panel that was switched off has to be left out of the grid instead.
+fn v0_drop_dashboard_toggles(table: &mut Table, notes: &mut Vec<String>) {
+    const TOGGLES: &[&str] = &["show_map", "show_stats", "show_alerts", "show_top_attackers", "show_command_cloud"];
+
+    let dashboard = match table.get_mut("dashboard") {
+        Some(Value::Table(dashboard)) => dashboard,
+        _ => return,
+    };
+
+    for toggle in TOGGLES {
+        if let Some(Value::Boolean(false)) = dashboard.remove(*toggle) {
+            notes.push(format!(
+                "dashboard.{} = false was removed; leave the panel out of dashboard.layout instead",
+                toggle
+            ));
+        }
+    }
+}
+
+/// Version 1 to 2: `alert.on_commands` entries become tables
+///
+/// Each pattern can now carry its own severity and score; a plain string
+/// becomes `{ pattern = "..." }` and keeps alerting as before.
+#[allow(clippy::ptr_arg)] // every step shares the signature in STEPS
+fn v1_command_alert_tables(table: &mut Table, _notes: &mut Vec<String>) {
+    let commands = match table.get_mut("alert").and_then(|alert| alert.get_mut("on_commands")) {
+        Some(Value::Array(commands)) => commands,
+        _ => return,
+    };
+
+    for command in commands.iter_mut() {
+        if let Value::String(pattern) = command {
+            let mut entry = Table::new();
+            entry.insert("pattern".to_string(), Value::String(pattern.clone()));
+            *command = Value::Table(entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn table(content: &str) -> Table {
+        toml::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn files_without_a_version_are_version_0() {
+        assert_eq!(version_of(&table("[ui]\ntheme = \"dark\"")).unwrap(), 0);
+        assert_eq!(version_of(&table("config_version = 1")).unwrap(), 1);
+    }
+
+    #[test]
+    fn newer_or_malformed_versions_are_refused() {
+        let newer = format!("config_version = {}", CONFIG_VERSION + 1);
+        assert!(version_of(&table(&newer)).unwrap_err().to_string().contains("newer than this build"));
+        assert!(version_of(&table("config_version = \"2\"")).is_err());
+        assert!(version_of(&table("config_version = -1")).is_err());
+    }
+
+    #[test]
+    fn v0_drops_the_dashboard_toggles_and_notes_the_ones_switched_off() {
+        let mut file = table("[dashboard]\nshow_map = false\nshow_stats = true\nshow_alerts = false\nlayout = \"compact\"");
+        let mut notes = Vec::new();
+        v0_drop_dashboard_toggles(&mut file, &mut notes);
+
+        assert_eq!(file, table("[dashboard]\nlayout = \"compact\""));
+        assert_eq!(notes, [
+            "dashboard.show_map = false was removed; leave the panel out of dashboard.layout instead",
+            "dashboard.show_alerts = false was removed; leave the panel out of dashboard.layout instead",
+        ]);
+    }
+
+    #[test]
+    fn v0_leaves_files_without_a_dashboard_alone() {
+        let mut file = table("[ui]\ntheme = \"dark\"");
+        let mut notes = Vec::new();
+        v0_drop_dashboard_toggles(&mut file, &mut notes);
+
+        assert_eq!(file, table("[ui]\ntheme = \"dark\""));
+        assert!(notes.is_empty());
+    }
+
+    #[test]
+    fn v1_turns_command_patterns_into_tables() {
+        let mut file = table("[alert]\non_commands = [\"wget\", { pattern = \"re:curl.*\\\\|sh\", severity = \"high\" }]");
+        v1_command_alert_tables(&mut file, &mut Vec::new());
+
+        assert_eq!(file, table(
+            "[alert]\non_commands = [{ pattern = \"wget\" }, { pattern = \"re:curl.*\\\\|sh\", severity = \"high\" }]"
+        ));
+    }
+
+    #[test]
+    fn every_step_runs_from_the_file_version() {
+        let mut file = table("[dashboard]\nshow_map = false\n[alert]\non_commands = [\"wget\"]");
+        let migration = migrate(&mut file).unwrap().unwrap();
+
+        assert_eq!((migration.from, migration.notes.len()), (0, 1));
+        assert_eq!(file, table(&format!(
+            "config_version = {}\n[dashboard]\n[alert]\non_commands = [{{ pattern = \"wget\" }}]",
+            CONFIG_VERSION
+        )));
+    }
+
+    #[test]
+    fn steps_before_the_file_version_are_skipped() {
+        // A version 1 file keeps a key step 0 would have dropped
+        let mut file = table("config_version = 1\n[dashboard]\nshow_map = false\n[alert]\non_commands = [\"wget\"]");
+        let migration = migrate(&mut file).unwrap().unwrap();
+
+        assert_eq!(migration.from, 1);
+        assert!(migration.notes.is_empty());
+        assert_eq!(file["dashboard"]["show_map"], Value::Boolean(false));
+        assert_eq!(file["alert"]["on_commands"][0]["pattern"], Value::String("wget".to_string()));
+    }
+
+    #[test]
+    fn current_files_are_not_migrated() {
+        let current = format!("config_version = {}\n[alert]\non_commands = [\"wget\"]", CONFIG_VERSION);
+        let mut file = table(&current);
+        assert!(migrate(&mut file).unwrap().is_none());
+        assert_eq!(file, table(&current));
+    }
+
+    #[test]
+    fn upgrading_a_file_keeps_the_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let original = "# mine\n[alert]\non_commands = [\"wget\"]\n";
+        fs::write(&path, original).unwrap();
+
+        assert_eq!(upgrade_file(&path).unwrap(), Some(0));
+        assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), original);
+        let upgraded = table(&fs::read_to_string(&path).unwrap());
+        assert_eq!(version_of(&upgraded).unwrap(), CONFIG_VERSION);
+
+        // Already current: neither rewritten nor backed up again
+        fs::remove_file(backup_path(&path)).unwrap();
+        assert_eq!(upgrade_file(&path).unwrap(), None);
+        assert!(!backup_path(&path).exists());
+    }
+}
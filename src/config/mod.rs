@@ -1,9 +1,23 @@
+pub mod detect;
+pub mod grid;
+pub mod migrate;
 pub mod settings;
+pub mod wizard;
 
 pub use settings::Config;
 pub use settings::HoneypotConfig;
+#[cfg(feature = "remote-ssh")]
+pub use settings::RemoteConfig;
 pub use settings::UIConfig;
 pub use settings::FilterConfig;
 pub use settings::LoggingConfig;
 pub use settings::AlertConfig;
-pub use settings::GeoIPConfig;
\ No newline at end of file
+pub use settings::CommandAlert;
+pub use settings::MalwareAnalysisConfig;
+pub use settings::AttackerWeights;
+pub use settings::IngestConfig;
+pub use settings::AnomalyConfig;
+pub use settings::RdapConfig;
+#[cfg(feature = "siem")]
+pub use settings::SIEMIntegrationConfig;
+pub use settings::ThreatIntelConfig;
\ No newline at end of file
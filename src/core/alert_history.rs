@@ -0,0 +1,328 @@
+//! Alerts kept across restarts, for the alerts view and panel
+//!
+//! Unlike the alert log, which is an append-only trail, the history is the
+//! current list: acknowledging or clearing alerts rewrites it. With the
+//! SQLite backend it is the database's `alerts` table; otherwise a JSON Lines
+//! file under the data directory (or `alert.history_path`). Either is trimmed
+//! to `alert.history_max_count` alerts and `alert.history_max_age_days` days.
+
+use anyhow::{Context, Result};
+use chrono::{Duration, Utc};
+use log::{error, warn};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+
+use crate::config::{AlertConfig, Config};
+use crate::core::alert_engine::{alert_key, Alert, AlertType};
+#[cfg(feature = "sqlite-db")]
+use crate::data::sqlite::SqliteAlerts;
+
+/// File name of the history in the data directory
+const HISTORY_FILE: &str = "alerts.jsonl";
+
+/// Where the history is kept
+#[derive(Debug, Default)]
+enum Storage {
+    /// Nowhere: memory only
+    #[default]
+    Memory,
+    /// A JSON Lines file
+    File(PathBuf),
+    /// The SQLite backend's `alerts` table
+    #[cfg(feature = "sqlite-db")]
+    Sqlite(SqliteAlerts),
+}
+
+/// Persisted alerts, oldest first
+#[derive(Debug, Default)]
+pub struct AlertHistory {
+    /// Where the history is kept
+    storage: Storage,
+    /// Alerts, oldest first
+    alerts: Vec<Alert>,
+    /// Most alerts kept (0 = no limit)
+    max_count: usize,
+    /// Oldest alert kept (`None` = no limit)
+    max_age: Option<Duration>,
+    /// Alerts loaded at startup, so events read again don't raise them twice
+    loaded: HashSet<String>,
+    /// Stored alerts that retention has since dropped
+    stale: usize,
+    /// Repeats counted since the history was last rewritten
+    unsaved: bool,
+}
+
+impl AlertHistory {
+    /// Load the history for `config`, dropping alerts past retention
+    ///
+    /// A missing file is an empty history; unreadable lines are skipped.
+    pub fn load(config: &Config) -> Result<Self> {
+        #[cfg(feature = "sqlite-db")]
+        if config.storage.backend == "sqlite" {
+            let alerts = SqliteAlerts::open(&crate::data::backend::database_path(config)?)?;
+            return Self::open(Storage::Sqlite(alerts), &config.alert);
+        }
+
+        let storage = match &config.alert.history_path {
+            Some(path) => Storage::File(PathBuf::from(path)),
+            None => dirs::data_dir()
+                .map(|dir| Storage::File(dir.join("xkippo").join(HISTORY_FILE)))
+                .unwrap_or_default(),
+        };
+        Self::open(storage, &config.alert)
+    }
+
+    /// Read the history from `storage`, dropping alerts past retention
+    fn open(storage: Storage, config: &AlertConfig) -> Result<Self> {
+        let mut history = Self {
+            storage,
+            alerts: Vec::new(),
+            max_count: config.history_max_count,
+            max_age: match config.history_max_age_days {
+                0 => None,
+                days => Some(Duration::days(days as i64)),
+            },
+            loaded: HashSet::new(),
+            stale: 0,
+            unsaved: false,
+        };
+
+        history.alerts = history.read()?;
+        history.alerts.sort_by_key(|alert| alert.timestamp);
+        history.loaded = history.alerts.iter().map(|alert| alert_key(&alert.alert_type)).collect();
+
+        // Write back what retention left, so the history doesn't grow between runs
+        if history.retain() > 0 {
+            history.save()?;
+        }
+
+        Ok(history)
+    }
+
+    /// Alerts, oldest first
+    pub fn alerts(&self) -> &[Alert] {
+        &self.alerts
+    }
+
+    /// Whether an alert like this one was loaded at startup
+    ///
+    /// Events from before the restart can be read again (history_hours, a
+    /// rotated log); their alerts are already here and shouldn't notify twice.
+    pub fn was_loaded(&self, alert_type: &AlertType) -> bool {
+        self.loaded.contains(&alert_key(alert_type))
+    }
+
+    /// Record a new alert
+    pub fn push(&mut self, alert: Alert) {
+        if let Err(e) = self.append(&alert) {
+            error!("{:#}", e);
+        }
+        self.alerts.push(alert);
+
+        // Dropped alerts stay in the file until it is twice the kept size
+        self.stale += self.retain();
+        if self.stale > self.alerts.len() {
+            self.save_or_log();
+        }
+    }
+
+    /// Bring the stored copy of `alert` up to date after it repeated
+    ///
+    /// The file is not rewritten for every repeat; counts are saved with the
+    /// next rewrite, or when the history is dropped at exit.
+    pub fn count_repeat(&mut self, alert: &Alert) {
+        let stored = self.alerts.iter_mut()
+            .rev()
+            .find(|stored| stored.timestamp == alert.timestamp && stored.alert_type == alert.alert_type);
+        if let Some(stored) = stored {
+            stored.count = alert.count;
+            stored.last_seen = alert.last_seen;
+            self.unsaved = true;
+        }
+    }
+
+    /// Mark the alert at `index` (oldest first) as acknowledged
+    pub fn acknowledge(&mut self, index: usize) {
+        if let Some(alert) = self.alerts.get_mut(index) {
+            if !alert.acknowledged {
+                alert.acknowledged = true;
+                self.save_or_log();
+            }
+        }
+    }
+
+    /// Drop every acknowledged alert
+    pub fn clear_acknowledged(&mut self) {
+        let before = self.alerts.len();
+        self.alerts.retain(|alert| !alert.acknowledged);
+        if self.alerts.len() != before {
+            self.save_or_log();
+        }
+    }
+
+    /// Drop every alert
+    pub fn clear_all(&mut self) {
+        if !self.alerts.is_empty() {
+            self.alerts.clear();
+            self.save_or_log();
+        }
+    }
+
+    /// Apply retention; how many alerts were dropped
+    fn retain(&mut self) -> usize {
+        let before = self.alerts.len();
+        if let Some(max_age) = self.max_age {
+            let cutoff = Utc::now() - max_age;
+            self.alerts.retain(|alert| alert.timestamp >= cutoff);
+        }
+        if self.max_count > 0 && self.alerts.len() > self.max_count {
+            let excess = self.alerts.len() - self.max_count;
+            self.alerts.drain(..excess);
+        }
+        before - self.alerts.len()
+    }
+
+    /// Every stored alert
+    fn read(&self) -> Result<Vec<Alert>> {
+        let path = match &self.storage {
+            Storage::Memory => return Ok(Vec::new()),
+            Storage::File(path) if !path.exists() => return Ok(Vec::new()),
+            Storage::File(path) => path,
+            #[cfg(feature = "sqlite-db")]
+            Storage::Sqlite(table) => return table.load(),
+        };
+
+        let file = File::open(path)
+            .context(format!("Failed to open alert history: {}", path.display()))?;
+        let mut alerts = Vec::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.context(format!("Failed to read alert history: {}", path.display()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<Alert>(&line) {
+                Ok(alert) => alerts.push(alert),
+                Err(e) => warn!("Skipping line {} of {}: {}", number + 1, path.display(), e),
+            }
+        }
+        Ok(alerts)
+    }
+
+    /// Store one more alert
+    fn append(&self, alert: &Alert) -> Result<()> {
+        let path = match &self.storage {
+            Storage::Memory => return Ok(()),
+            Storage::File(path) => path,
+            #[cfg(feature = "sqlite-db")]
+            Storage::Sqlite(table) => return table.append(alert).context("Failed to write alert history"),
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .context(format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        let mut line = serde_json::to_vec(alert)?;
+        line.push(b'\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(&line))
+            .context(format!("Failed to write alert history: {}", path.display()))
+    }
+
+    /// Replace the stored alerts with the current ones
+    fn save(&mut self) -> Result<()> {
+        let path = match &mut self.storage {
+            Storage::Memory => return Ok(()),
+            Storage::File(path) => path,
+            #[cfg(feature = "sqlite-db")]
+            Storage::Sqlite(table) => return table.replace(&self.alerts).context("Failed to write alert history"),
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .context(format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        // Write aside and rename, so a crash mid-write keeps the old history
+        let mut temporary = path.clone().into_os_string();
+        temporary.push(".tmp");
+        let mut writer = BufWriter::new(
+            File::create(&temporary)
+                .context(format!("Failed to write alert history: {}", path.display()))?,
+        );
+        for alert in &self.alerts {
+            serde_json::to_writer(&mut writer, alert)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        drop(writer);
+
+        fs::rename(&temporary, &path)
+            .context(format!("Failed to write alert history: {}", path.display()))
+    }
+
+    /// [`save`](Self::save), logging instead of failing
+    fn save_or_log(&mut self) {
+        match self.save() {
+            Ok(()) => {
+                self.stale = 0;
+                self.unsaved = false;
+            }
+            Err(e) => error!("{:#}", e),
+        }
+    }
+}
+
+impl Drop for AlertHistory {
+    fn drop(&mut self) {
+        if self.unsaved {
+            self.save_or_log();
+        }
+    }
+}
+
+#[cfg(all(test, feature = "sqlite-db"))]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn alert(ip: &str) -> Alert {
+        Alert {
+            alert_type: AlertType::BlacklistedIp { ip: ip.to_string() },
+            timestamp: Utc::now(),
+            acknowledged: false,
+            message: format!("Connection from blacklisted IP: {}", ip),
+            severity: crate::core::alert_engine::Severity::High,
+            techniques: Vec::new(),
+            count: 1,
+            last_seen: None,
+        }
+    }
+
+    #[test]
+    fn the_sqlite_history_keeps_alerts_and_their_changes_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.backend = "sqlite".to_string();
+        config.storage.path = Some(dir.path().join("xkippo.db").display().to_string());
+        config.alert.history_max_count = 2;
+
+        let mut history = AlertHistory::load(&config).unwrap();
+        for ip in ["203.0.113.1", "203.0.113.2", "203.0.113.3"] {
+            history.push(alert(ip));
+        }
+        history.acknowledge(0);
+        drop(history);
+
+        let history = AlertHistory::load(&config).unwrap();
+        let ips: Vec<(String, bool)> = history.alerts().iter()
+            .map(|alert| (alert.message.rsplit(' ').next().unwrap().to_string(), alert.acknowledged))
+            .collect();
+        assert_eq!(ips, [("203.0.113.2".to_string(), true), ("203.0.113.3".to_string(), false)]);
+        assert!(history.was_loaded(&AlertType::BlacklistedIp { ip: "203.0.113.3".to_string() }));
+        assert!(!dir.path().join(HISTORY_FILE).exists());
+    }
+}
@@ -0,0 +1,90 @@
+//! Append-only record of every alert, for review after the fact
+//!
+//! Each alert is one JSON line with its structured fields and timestamp.
+//! The file is written independently of the in-memory alert list, so
+//! clearing the alerts panel or restarting doesn't lose the trail.
+
+use anyhow::{Context, Result};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::config::AlertConfig;
+use crate::core::alert_engine::Alert;
+
+/// JSON Lines file that alerts are appended to
+#[derive(Debug)]
+pub struct AlertLog {
+    /// Where alerts are written
+    path: PathBuf,
+    /// Size at which the file is rotated to `<path>.1` (0 = never)
+    max_bytes: u64,
+    /// Open handle, appending
+    file: File,
+    /// Bytes in the current file
+    size: u64,
+}
+
+impl AlertLog {
+    /// Open `alert.log_path` for appending, or `None` if it is unset
+    pub fn open(config: &AlertConfig) -> Result<Option<Self>> {
+        let path = match &config.log_path {
+            Some(path) => PathBuf::from(path),
+            None => return Ok(None),
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .context(format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        let file = open_append(&path)?;
+        let size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+
+        Ok(Some(Self {
+            path,
+            max_bytes: config.log_max_size_mb * 1024 * 1024,
+            file,
+            size,
+        }))
+    }
+
+    /// Append `alert` as one line, rotating first if the file is full
+    pub fn append(&mut self, alert: &Alert) -> Result<()> {
+        let mut line = serde_json::to_vec(alert)?;
+        line.push(b'\n');
+
+        if self.max_bytes > 0 && self.size > 0 && self.size + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        self.file.write_all(&line)
+            .and_then(|_| self.file.flush())
+            .context(format!("Failed to write alert log: {}", self.path.display()))?;
+        self.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    /// Move the current file to `<path>.1`, replacing any older one, and start afresh
+    fn rotate(&mut self) -> Result<()> {
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+
+        fs::rename(&self.path, &rotated)
+            .context(format!("Failed to rotate alert log: {}", self.path.display()))?;
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+/// Open `path` for appending, creating it if needed
+fn open_append(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .context(format!("Failed to open alert log: {}", path.display()))
+}
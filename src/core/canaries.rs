@@ -0,0 +1,57 @@
+//! Honeytoken detection
+//!
+//! Canaries are fake credentials, hostnames and the like planted in the
+//! honeypot's filesystem. An attacker who reads or uses one has gone looking
+//! for secrets, so every hit is reported, whether the token shows up in the
+//! command typed or only in what it printed.
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+
+use crate::app::compile_search;
+use crate::config::Config;
+use crate::data::Command;
+
+/// Compiled `[[canaries.tokens]]`
+#[derive(Debug, Clone, Default)]
+pub struct Canaries {
+    /// Token name and what it matches
+    tokens: Vec<(String, Regex)>,
+}
+
+impl Canaries {
+    /// Compile the configured tokens, failing on the first that doesn't compile
+    ///
+    /// Tokens are case-sensitive substrings, or regular expressions after
+    /// `re:` as in the search prompt.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let tokens = config.canaries.tokens.iter()
+            .filter(|token| !token.token.is_empty())
+            .map(|token| {
+                compile_search(&token.token, true)
+                    .map(|pattern| (token.name.clone(), pattern))
+                    .map_err(|e| anyhow!("Invalid canary '{}': {}", token.name, e))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { tokens })
+    }
+
+    /// Whether any tokens are configured
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Name of the first token found in `text`
+    pub fn find(&self, text: &str) -> Option<&str> {
+        self.tokens.iter()
+            .find(|(_, pattern)| pattern.is_match(text))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Name of the first token in a command or its output
+    pub fn hit(&self, command: &Command) -> Option<&str> {
+        self.find(&command.command)
+            .or_else(|| command.output.as_deref().and_then(|output| self.find(output)))
+    }
+}
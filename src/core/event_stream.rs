@@ -0,0 +1,389 @@
+//! Event stream for downstream pipelines (`[stream]`)
+//!
+//! Every ingested entry goes out as one line of JSON, along with what xKippo
+//! has worked out about it by then: its session's risk and tags, the
+//! session's location, and any threat intel on the source IP. The session is
+//! taken from the session tracker's open sessions, so it includes the entries
+//! before this one even when no snapshot has them yet. With `stream.bind`, any
+//! number of clients connect and read. With `stream.connect`, xKippo dials out
+//! to a collector and keeps reconnecting while it is away.
+//!
+//! With `stream.format = "grpc"`, clients of `stream.bind` call
+//! `xkippo.EventStream/Subscribe` instead (see `proto/event_stream.proto`) and
+//! get each line as the `json` field of an `Event` message.
+//!
+//! Each client reads from its own place in a buffer of `stream.buffer` lines.
+//! A client that falls further behind than that loses the oldest lines
+//! rather than holding up ingestion; [`StreamStats`] counts them.
+
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+use crate::app::AppEvent;
+use crate::config::Config;
+use crate::core::{EnhancedLogAnalyzer, OpenSessions, TaskReporter};
+use crate::data::{LogEntry, Session, StoreHandle, StoreSnapshot};
+
+/// First wait before connecting to `stream.connect` again
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+
+/// Longest wait between connection attempts
+const RECONNECT_MAX: Duration = Duration::from_secs(60);
+
+/// How long a connection must last for the next attempt to start from [`RECONNECT_MIN`] again
+///
+/// A collector that accepts and then hangs up at once is as unavailable as
+/// one that refuses.
+const RECONNECT_STABLE: Duration = Duration::from_secs(10);
+
+/// Counters for the status bar and the web snapshot
+#[derive(Debug, Default)]
+pub struct StreamStats {
+    /// Clients connected now
+    pub(crate) clients: AtomicUsize,
+    /// Lines written to clients, counted once per client
+    pub(crate) sent: AtomicU64,
+    /// Lines lost to slow clients, or while the stream fell behind ingestion
+    pub(crate) dropped: AtomicU64,
+}
+
+impl StreamStats {
+    /// Clients connected now
+    pub fn clients(&self) -> usize {
+        self.clients.load(Ordering::Relaxed)
+    }
+
+    /// Lines written to clients so far
+    pub fn sent(&self) -> u64 {
+        self.sent.load(Ordering::Relaxed)
+    }
+
+    /// Lines dropped so far
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Start streaming ingested entries as `[stream]` describes
+///
+/// With `stream.bind`, the address is bound before this returns, so one in
+/// use is reported at startup. Abort the returned task to stop streaming.
+pub async fn start_event_stream(
+    config: &Config,
+    event_tx: broadcast::Sender<AppEvent>,
+    store: StoreHandle,
+    open_sessions: OpenSessions,
+    analyzer: Arc<EnhancedLogAnalyzer>,
+    stats: Arc<StreamStats>,
+    reporter: TaskReporter,
+) -> Result<JoinHandle<()>> {
+    let (lines, _) = broadcast::channel::<Arc<str>>(config.stream.buffer.max(1));
+
+    let listener = match &config.stream.bind {
+        Some(bind) => {
+            let bind: SocketAddr = bind.parse()
+                .context(format!("stream.bind '{}' is not an address and port", bind))?;
+            let listener = TcpListener::bind(bind).await
+                .context(format!("Failed to listen on {}", bind))?;
+            info!("Streaming events to clients of {}", bind);
+            Some(listener)
+        }
+        None => None,
+    };
+    let connect = config.stream.connect.clone();
+    let grpc = config.stream.format == "grpc";
+
+    let mut events = event_tx.subscribe();
+    let enrich_lines = lines.clone();
+    let enrich_stats = stats.clone();
+    let enricher = async move {
+        loop {
+            match events.recv().await {
+                Ok(AppEvent::NewLogEntry(entry)) => {
+                    let line = {
+                        let (open_sessions, snapshot) = (open_sessions.lock().await, store.snapshot());
+                        enrich(&entry, entry_session(&entry, &open_sessions, &snapshot), &analyzer)
+                    };
+                    // Sending only fails with no client connected, which is fine
+                    let _ = enrich_lines.send(line);
+                }
+                Ok(AppEvent::Quit) | Err(RecvError::Closed) => break,
+                Ok(_) => {}
+                Err(RecvError::Lagged(missed)) => {
+                    warn!("Event stream fell behind ingestion; {} entries not streamed", missed);
+                    enrich_stats.dropped.fetch_add(missed, Ordering::Relaxed);
+                }
+            }
+        }
+    };
+
+    Ok(tokio::spawn(async move {
+        let clients = async move {
+            match (listener, connect) {
+                (Some(listener), _) => accept_clients(listener, lines, stats, grpc).await,
+                (None, Some(target)) => connect_to(&target, lines, stats, reporter).await,
+                (None, None) => std::future::pending().await,
+            }
+        };
+        // Whichever ends first (only the enricher can) stops the other
+        tokio::select! {
+            _ = enricher => {}
+            _ = clients => {}
+        }
+    }))
+}
+
+/// Serve each client of `listener` from its own place in `lines`, as gRPC calls if `grpc`
+async fn accept_clients(listener: TcpListener, lines: broadcast::Sender<Arc<str>>, stats: Arc<StreamStats>, grpc: bool) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                info!("Event stream client {} connected", peer);
+                let (lines, stats) = (lines.clone(), stats.clone());
+                tokio::spawn(async move {
+                    if grpc {
+                        #[cfg(feature = "grpc")]
+                        if let Err(e) = crate::core::grpc_stream::serve(stream, lines, stats).await {
+                            debug!("Event stream client {} failed: {}", peer, e);
+                        }
+                    } else {
+                        serve(stream, lines.subscribe(), &stats).await;
+                    }
+                    info!("Event stream client {} disconnected", peer);
+                });
+            }
+            Err(e) => warn!("Event stream failed to accept a client: {}", e),
+        }
+    }
+}
+
+/// Keep a connection open to `target`, reconnecting with backoff
+async fn connect_to(
+    target: &str,
+    lines: broadcast::Sender<Arc<str>>,
+    stats: Arc<StreamStats>,
+    reporter: TaskReporter,
+) {
+    let mut backoff = RECONNECT_MIN;
+    loop {
+        match TcpStream::connect(target).await {
+            Ok(stream) => {
+                info!("Streaming events to {}", target);
+                let connected = tokio::time::Instant::now();
+                serve(stream, lines.subscribe(), &stats).await;
+                if connected.elapsed() >= RECONNECT_STABLE {
+                    backoff = RECONNECT_MIN;
+                }
+                warn!("Event stream collector {} went away; reconnecting in {}s", target, backoff.as_secs());
+                reporter.report_error(format!("{} closed the connection", target));
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(RECONNECT_MAX);
+            }
+            Err(e) => {
+                debug!("Failed to connect to event stream collector {}: {}", target, e);
+                reporter.report_error(format!("Failed to connect to {}: {}", target, e));
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(RECONNECT_MAX);
+            }
+        }
+    }
+}
+
+/// Write lines to `stream` until it closes or fails
+///
+/// Whatever the client sends is read and thrown away; reading is only how a
+/// client hanging up is noticed while no lines are due.
+async fn serve(stream: TcpStream, mut lines: broadcast::Receiver<Arc<str>>, stats: &StreamStats) {
+    stats.clients.fetch_add(1, Ordering::Relaxed);
+    let (mut reader, mut writer) = stream.into_split();
+    let mut discard = [0u8; 512];
+
+    loop {
+        tokio::select! {
+            line = lines.recv() => match line {
+                Ok(line) => {
+                    let written = match writer.write_all(line.as_bytes()).await {
+                        Ok(()) => writer.write_all(b"\n").await,
+                        Err(e) => Err(e),
+                    };
+                    if written.is_err() {
+                        break;
+                    }
+                    stats.sent.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    stats.dropped.fetch_add(missed, Ordering::Relaxed);
+                }
+                Err(RecvError::Closed) => break,
+            },
+            read = reader.read(&mut discard) => {
+                if !matches!(read, Ok(read) if read > 0) {
+                    break;
+                }
+            }
+        }
+    }
+
+    stats.clients.fetch_sub(1, Ordering::Relaxed);
+}
+
+/// Session `entry` belongs to: the open one the tracker is building, else the
+/// one the snapshot has, for entries after the session closed
+fn entry_session<'a>(entry: &LogEntry, open_sessions: &'a HashMap<String, Session>, snapshot: &'a StoreSnapshot) -> Option<&'a Session> {
+    entry.session.as_deref()
+        .and_then(|id| open_sessions.get(id))
+        .filter(|session| session.start_time <= entry.timestamp)
+        .or_else(|| snapshot.get_session_for_entry(entry))
+}
+
+/// `entry` as a stream line, with its session, location and threat intel
+fn enrich(entry: &LogEntry, session: Option<&Session>, analyzer: &EnhancedLogAnalyzer) -> Arc<str> {
+    let intel = entry.src_ip.as_deref().and_then(|ip| analyzer.get_threat_intel(ip));
+
+    let line = json!({
+        "entry": entry,
+        "session": session.map(|session| json!({
+            "id": session.id,
+            "risk": session.malicious_score,
+            "malicious": session.is_malicious,
+            "tags": session.tags,
+            "techniques": session.techniques,
+            "commands": session.commands.len(),
+            "login_success": session.user.as_ref().map_or(false, |user| user.login_success),
+        })),
+        "geo": session.and_then(|session| session.geo_location.as_ref()),
+        "intel": intel.map(|intel| json!({
+            "score": intel.score,
+            "labels": intel.labels,
+            "source": intel.source,
+        })),
+    });
+    Arc::from(line.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::TaskHealth;
+    use crate::data::testing::{at, command, eventually, session};
+    use crate::data::Store;
+    use pretty_assertions::assert_eq;
+    use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+
+    type Client = Lines<BufReader<TcpStream>>;
+
+    /// Stream clients of a free port, fed from a buffer of `buffer` lines
+    async fn listen(buffer: usize) -> (SocketAddr, broadcast::Sender<Arc<str>>, Arc<StreamStats>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (lines, _) = broadcast::channel(buffer);
+        let stats = Arc::new(StreamStats::default());
+        tokio::spawn(accept_clients(listener, lines.clone(), stats.clone(), false));
+        (address, lines, stats)
+    }
+
+    async fn connect(address: SocketAddr) -> Client {
+        BufReader::new(TcpStream::connect(address).await.unwrap()).lines()
+    }
+
+    /// Number a line starts with
+    fn number(line: &str) -> usize {
+        line.split(' ').next().unwrap().parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn every_client_gets_every_line() {
+        let (address, lines, stats) = listen(16).await;
+        let mut clients = vec![connect(address).await, connect(address).await, connect(address).await];
+        eventually(|| stats.clients() == 3).await;
+
+        for i in 0..5 {
+            lines.send(Arc::from(format!("{} {{}}", i))).unwrap();
+        }
+        for client in &mut clients {
+            for i in 0..5 {
+                assert_eq!(number(&client.next_line().await.unwrap().unwrap()), i);
+            }
+        }
+        assert_eq!(stats.sent(), 15);
+        assert_eq!(stats.dropped(), 0);
+
+        drop(clients);
+        eventually(|| stats.clients() == 0).await;
+    }
+
+    #[tokio::test]
+    async fn a_slow_client_loses_the_oldest_lines_alone() {
+        let (address, lines, stats) = listen(8).await;
+        let slow = connect(address).await;
+        let mut fast = connect(address).await;
+        eventually(|| stats.clients() == 2).await;
+
+        // Far more than the socket buffers hold, so the slow client's writes
+        // stall and it falls out of the line buffer
+        let padding = "x".repeat(4096);
+        let count = 8000;
+        for i in 0..count {
+            lines.send(Arc::from(format!("{} {}", i, padding))).unwrap();
+            assert_eq!(number(&fast.next_line().await.unwrap().unwrap()), i);
+        }
+
+        let mut slow = slow;
+        let mut received = Vec::new();
+        while received.last() != Some(&(count - 1)) {
+            received.push(number(&slow.next_line().await.unwrap().unwrap()));
+        }
+        assert!(received.len() < count);
+        assert!(received.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(stats.dropped() as usize, count - received.len());
+    }
+
+    #[tokio::test]
+    async fn a_collector_that_hangs_up_at_once_is_backed_off_from() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = listener.local_addr().unwrap().to_string();
+        let (lines, _) = broadcast::channel(4);
+        let reporter = TaskHealth::new().register("event stream", false);
+        let client = tokio::spawn(async move {
+            connect_to(&target, lines, Arc::new(StreamStats::default()), reporter).await
+        });
+
+        let deadline = tokio::time::Instant::now() + Duration::from_millis(2500);
+        let mut accepted = 0;
+        while let Ok(Ok((stream, _))) = tokio::time::timeout_at(deadline, listener.accept()).await {
+            drop(stream);
+            accepted += 1;
+        }
+        client.abort();
+
+        // At 0s and 1s; the next is due at 3s
+        assert_eq!(accepted, 2);
+    }
+
+    #[test]
+    fn entries_take_the_session_the_tracker_is_building() {
+        let entry = command("c0ffee", "203.0.113.7", "uname -a", at(5));
+        let mut open = session("c0ffee", "203.0.113.7", at(0));
+        let mut snapshot = Store::new(&Config::default()).unwrap();
+        snapshot.add_session(open.clone()).unwrap();
+        open.malicious_score = 70;
+        let open_sessions = HashMap::from([("c0ffee".to_string(), open)]);
+
+        let risk = |open_sessions: &HashMap<String, Session>| {
+            entry_session(&entry, open_sessions, &snapshot).map(|session| session.malicious_score)
+        };
+        assert_eq!(risk(&open_sessions), Some(70));
+        // Once closed, it's only in the snapshot
+        assert_eq!(risk(&HashMap::new()), Some(0));
+    }
+}
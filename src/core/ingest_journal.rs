@@ -0,0 +1,257 @@
+use anyhow::{Context, Result};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Persisted read position for a single watched log file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Path of the watched file
+    pub path: String,
+    /// Inode of the file when the position was recorded
+    pub inode: u64,
+    /// Byte offset just past the last complete line ingested
+    pub offset: u64,
+    /// Byte offset where the last ingested line starts
+    pub last_line_offset: u64,
+    /// Hash of the last ingested line
+    pub last_entry_hash: Option<String>,
+}
+
+/// Where a watcher should start reading a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResumeDecision {
+    /// Continue from the journaled byte offset
+    Resume(u64),
+    /// The file was replaced or truncated since the journal was written
+    Rotated,
+    /// No journal exists for this file
+    Fresh,
+}
+
+/// Write-ahead journal of ingestion progress for one log file
+///
+/// Positions are [`recorded`](Self::record) as lines are read, but only
+/// [`flushed`](Self::flush) once the store writer has applied the lines
+/// before them, so a restart neither skips nor re-ingests a batch.
+pub struct IngestJournal {
+    /// Location of the journal file
+    journal_path: PathBuf,
+    /// Current position
+    entry: JournalEntry,
+    /// Whether a journal was found on disk
+    loaded: bool,
+}
+
+impl IngestJournal {
+    /// Open (or start) the journal for `source` under `state_dir`
+    pub fn open(state_dir: &Path, source: &Path) -> Self {
+        let source_name = source.display().to_string();
+        let journal_path = state_dir
+            .join("journal")
+            .join(format!("{:016x}.json", fnv1a(source_name.as_bytes())));
+
+        let stored = fs::read_to_string(&journal_path).ok().and_then(|content| {
+            match serde_json::from_str::<JournalEntry>(&content) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    warn!("Ignoring corrupt journal {}: {}", journal_path.display(), e);
+                    None
+                }
+            }
+        });
+
+        let loaded = stored.is_some();
+        let entry = stored.unwrap_or_else(|| JournalEntry {
+            path: source_name,
+            ..JournalEntry::default()
+        });
+
+        Self {
+            journal_path,
+            entry,
+            loaded,
+        }
+    }
+
+    /// Decide whether the journaled position is still valid for `source`
+    pub fn decide(&self, source: &Path) -> Result<ResumeDecision> {
+        if !self.loaded {
+            return Ok(ResumeDecision::Fresh);
+        }
+
+        let metadata = fs::metadata(source)?;
+
+        // Replaced or truncated since the last run
+        if file_inode(&metadata) != self.entry.inode || metadata.len() < self.entry.offset {
+            return Ok(ResumeDecision::Rotated);
+        }
+
+        // Make sure the bytes before the offset are still the line we last ingested
+        if let Some(expected) = &self.entry.last_entry_hash {
+            // A line ending before it starts can only come from a damaged journal
+            let len = match self.entry.offset.checked_sub(self.entry.last_line_offset) {
+                Some(len) => len as usize,
+                None => return Ok(ResumeDecision::Rotated),
+            };
+            let mut line = vec![0u8; len];
+            let mut file = File::open(source)?;
+            file.seek(SeekFrom::Start(self.entry.last_line_offset))?;
+
+            if file.read_exact(&mut line).is_err() || &hash_line(&line) != expected {
+                return Ok(ResumeDecision::Rotated);
+            }
+        }
+
+        Ok(ResumeDecision::Resume(self.entry.offset))
+    }
+
+    /// Start over at the beginning of a (new) file
+    pub fn reset(&mut self, inode: u64) {
+        self.entry.inode = inode;
+        self.entry.offset = 0;
+        self.entry.last_line_offset = 0;
+        self.entry.last_entry_hash = None;
+    }
+
+    /// Record that the line spanning `start..end` has been read, without writing it out
+    pub fn record(&mut self, start: u64, end: u64, line: &[u8]) {
+        self.entry.offset = end;
+        self.entry.last_line_offset = start;
+        self.entry.last_entry_hash = Some(hash_line(line));
+    }
+
+    /// Atomically write the current position to disk
+    pub fn flush(&mut self) -> Result<()> {
+        if let Some(parent) = self.journal_path.parent() {
+            fs::create_dir_all(parent)
+                .context(format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        let content = serde_json::to_string(&self.entry)?;
+        let tmp_path = self.journal_path.with_extension("json.tmp");
+
+        fs::write(&tmp_path, content)
+            .context(format!("Failed to write journal: {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &self.journal_path)
+            .context(format!("Failed to replace journal: {}", self.journal_path.display()))?;
+
+        Ok(())
+    }
+}
+
+/// Inode of a file (0 on platforms without inodes)
+pub fn file_inode(metadata: &fs::Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.ino()
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        0
+    }
+}
+
+/// Stable hash of a log line, as hex
+fn hash_line(line: &[u8]) -> String {
+    format!("{:016x}", fnv1a(line))
+}
+
+/// 64-bit FNV-1a, stable across builds unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::io::Write;
+
+    /// Byte range and content of a line
+    type Line = (u64, u64, &'static [u8]);
+
+    /// A log with two lines
+    fn log(dir: &Path) -> (PathBuf, [Line; 2]) {
+        let path = dir.join("cowrie.json");
+        fs::write(&path, "first line\nsecond line\n").unwrap();
+        (path, [(0, 11, b"first line\n"), (11, 23, b"second line\n")])
+    }
+
+    fn journal(dir: &Path, log: &Path) -> IngestJournal {
+        let mut journal = IngestJournal::open(dir, log);
+        journal.reset(file_inode(&fs::metadata(log).unwrap()));
+        journal
+    }
+
+    #[test]
+    fn a_missing_journal_reads_from_the_start() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, _) = log(dir.path());
+        assert_eq!(IngestJournal::open(dir.path(), &path).decide(&path).unwrap(), ResumeDecision::Fresh);
+    }
+
+    #[test]
+    fn resumes_after_the_last_flushed_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, lines) = log(dir.path());
+        let mut journal = journal(dir.path(), &path);
+        let (start, end, line) = lines[0];
+        journal.record(start, end, line);
+        journal.flush().unwrap();
+
+        // Read, but not yet applied by the writer when we stopped
+        let (start, end, line) = lines[1];
+        journal.record(start, end, line);
+
+        assert_eq!(IngestJournal::open(dir.path(), &path).decide(&path).unwrap(), ResumeDecision::Resume(11));
+    }
+
+    #[test]
+    fn a_truncated_file_is_read_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, lines) = log(dir.path());
+        let mut journal = journal(dir.path(), &path);
+        let (start, end, line) = lines[1];
+        journal.record(start, end, line);
+        journal.flush().unwrap();
+
+        fs::OpenOptions::new().write(true).open(&path).unwrap().set_len(5).unwrap();
+
+        assert_eq!(IngestJournal::open(dir.path(), &path).decide(&path).unwrap(), ResumeDecision::Rotated);
+    }
+
+    #[test]
+    fn a_rewritten_last_line_is_read_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, lines) = log(dir.path());
+        let mut journal = journal(dir.path(), &path);
+        let (start, end, line) = lines[1];
+        journal.record(start, end, line);
+        journal.flush().unwrap();
+
+        let mut file = fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.seek(SeekFrom::Start(11)).unwrap();
+        file.write_all(b"other").unwrap();
+
+        assert_eq!(IngestJournal::open(dir.path(), &path).decide(&path).unwrap(), ResumeDecision::Rotated);
+    }
+
+    #[test]
+    fn a_damaged_journal_is_treated_as_a_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, _) = log(dir.path());
+        let mut journal = journal(dir.path(), &path);
+        journal.record(20, 11, b"second line\n");
+        journal.flush().unwrap();
+
+        assert_eq!(IngestJournal::open(dir.path(), &path).decide(&path).unwrap(), ResumeDecision::Rotated);
+    }
+}
@@ -0,0 +1,209 @@
+//! Ingestion from the systemd journal
+//!
+//! For Cowrie running under systemd with its JSON log written to stdout, each
+//! journal entry's `MESSAGE` is one line of `cowrie.json`. The unit's entries
+//! are followed with `journalctl -o json`, and their messages handed to the
+//! regular [`LogAnalyzer`]. Messages that aren't JSON, such as Twisted's own
+//! text log, are skipped.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::{debug, error, info, warn};
+use serde_json::Value;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+use crate::app::{AppEvent, ConnectionStatus};
+use crate::config::Config as AppConfig;
+use crate::core::log_analyzer::LogAnalyzer;
+use crate::core::log_watcher::determine_start_time;
+use crate::core::task_health::TaskReporter;
+use crate::data::StoreHandle;
+
+/// Longest wait before an exited `journalctl` is started again
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(30);
+
+/// Where journal messages go once read
+struct JournalSink {
+    /// Source name used for parse statistics
+    source: String,
+    store: StoreHandle,
+    event_tx: broadcast::Sender<AppEvent>,
+    log_analyzer: LogAnalyzer,
+    /// Heartbeat for every message, and where failures are reported
+    health: TaskReporter,
+}
+
+/// Start following the journal of `honeypot.journald_unit`
+///
+/// Only Linux has a systemd journal; elsewhere this fails straight away.
+pub async fn start_journald_watcher(
+    store: StoreHandle,
+    event_tx: broadcast::Sender<AppEvent>,
+    config: &AppConfig,
+    health: TaskReporter,
+) -> Result<JoinHandle<()>> {
+    if !cfg!(target_os = "linux") {
+        return Err(anyhow::anyhow!(
+            "honeypot.source = \"journald\" is only supported on Linux; use source = \"file\" instead"
+        ));
+    }
+
+    let unit = config.honeypot.journald_unit.clone();
+    info!("Starting journald watcher for unit {}", unit);
+
+    // Catch a missing journalctl here, where the error can still be shown
+    Command::new("journalctl")
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .context("journalctl was not found; honeypot.source = \"journald\" needs systemd")?;
+
+    let start_time = determine_start_time(config)?;
+    let sink = JournalSink {
+        source: format!("journald://{}", unit),
+        store,
+        event_tx,
+        log_analyzer: LogAnalyzer::with_config(config),
+        health,
+    };
+
+    let task = tokio::spawn(async move {
+        // Last entry read, so a restart resumes instead of replaying
+        let mut cursor: Option<String> = None;
+        let mut attempt: u8 = 0;
+
+        loop {
+            let status = if attempt == 0 {
+                ConnectionStatus::Connecting
+            } else {
+                ConnectionStatus::Reconnecting(attempt)
+            };
+            let _ = sink.event_tx.send(AppEvent::ConnectionStatusChange(status));
+
+            match follow(&unit, start_time, &mut cursor, &sink).await {
+                Ok(true) => {
+                    warn!("journalctl for {} exited", unit);
+                    attempt = 0;
+                }
+                Ok(false) => warn!("journalctl for {} exited without reading anything", unit),
+                Err(e) => {
+                    error!("Following the journal of {} failed: {:#}", unit, e);
+                    sink.health.report_error(format!("Following the journal failed: {:#}", e));
+                }
+            }
+
+            attempt = attempt.saturating_add(1);
+            let _ = sink.event_tx.send(AppEvent::ConnectionStatusChange(ConnectionStatus::Failed(attempt)));
+
+            let delay = restart_delay(attempt);
+            debug!("Restarting journalctl for {} in {}s", unit, delay.as_secs());
+            tokio::time::sleep(delay).await;
+        }
+    });
+
+    Ok(task)
+}
+
+/// Run `journalctl --follow` until it exits, returning whether it produced any entries
+///
+/// Starts after `cursor` when set, otherwise at `start_time`, and keeps
+/// `cursor` at the last entry read.
+async fn follow(unit: &str, start_time: DateTime<Utc>, cursor: &mut Option<String>, sink: &JournalSink) -> Result<bool> {
+    let mut command = Command::new("journalctl");
+    command.args(["--unit", unit, "--output", "json", "--follow", "--no-pager"]);
+    match cursor.as_deref() {
+        Some(after) => command.arg(format!("--after-cursor={}", after)),
+        None => command.arg(format!("--since=@{}", start_time.timestamp().max(0))),
+    };
+
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to run journalctl")?;
+    let stdout = child.stdout.take().context("journalctl has no stdout")?;
+
+    info!("Following the journal of {}", unit);
+    let _ = sink.event_tx.send(AppEvent::ConnectionStatusChange(ConnectionStatus::Connected));
+
+    let mut lines = BufReader::new(stdout).lines();
+    let mut read_any = false;
+    while let Some(line) = lines.next_line().await.context("Failed to read from journalctl")? {
+        let entry: Value = match serde_json::from_str(&line) {
+            Ok(entry) => entry,
+            Err(e) => {
+                debug!("Skipping unreadable journal entry: {}", e);
+                continue;
+            }
+        };
+        read_any = true;
+
+        if let Some(position) = entry.get("__CURSOR").and_then(Value::as_str) {
+            *cursor = Some(position.to_string());
+        }
+        if let Some(message) = journal_message(&entry) {
+            sink.ingest(&message).await;
+        }
+    }
+
+    let status = child.wait().await.context("Failed to wait for journalctl")?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("journalctl exited with {}", status));
+    }
+
+    Ok(read_any)
+}
+
+impl JournalSink {
+    /// Parse one Cowrie JSON line and feed it into the store
+    async fn ingest(&self, message: &str) {
+        self.health.heartbeat();
+        let message = message.trim();
+        if !message.starts_with('{') {
+            return;
+        }
+
+        match self.log_analyzer.parse_log_entry(message) {
+            Ok(entry) => {
+                self.store.record_parsed(&self.source).await;
+                self.store.add_log_entry(entry.clone()).await;
+                let _ = self.event_tx.send(AppEvent::NewLogEntry(entry));
+            }
+            Err(e) => {
+                debug!("Error parsing journal entry: {}", e);
+                self.store.record_parse_failure(&self.source, message, &format!("{:#}", e)).await;
+            }
+        }
+    }
+}
+
+/// The `MESSAGE` of a journal entry
+///
+/// journalctl writes messages that aren't valid UTF-8 as arrays of bytes.
+fn journal_message(entry: &Value) -> Option<String> {
+    match entry.get("MESSAGE")? {
+        Value::String(message) => Some(message.clone()),
+        Value::Array(bytes) => {
+            let bytes = bytes.iter()
+                .map(|byte| byte.as_u64().map(|byte| byte as u8))
+                .collect::<Option<Vec<u8>>>()?;
+            Some(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        _ => None,
+    }
+}
+
+/// Exponential backoff capped at [`MAX_RESTART_DELAY`]
+fn restart_delay(attempt: u8) -> Duration {
+    Duration::from_secs(1u64 << attempt.min(5)).min(MAX_RESTART_DELAY)
+}
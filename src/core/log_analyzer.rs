@@ -1,16 +1,114 @@
 use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
-use log::{debug, error, trace};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use log::{debug, trace, warn};
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use uuid::Uuid;
 
-use crate::data::{EventType, FileTransfer, FileTransferDirection, LogEntry};
+use crate::config::{Config, HoneypotConfig};
+use crate::data::{EventType, FileTransfer, FileTransferDirection, IStr, LogEntry};
+
+/// Field names that moved between Cowrie versions, tried in order
+const FIELD_ALIASES: &[(&str, &[&str])] = &[
+    ("timestamp", &["timestamp", "time"]),
+    ("src_ip", &["src_ip", "srcip"]),
+    ("src_port", &["src_port", "srcport"]),
+    ("dst_ip", &["dst_ip", "dest_ip", "dstip"]),
+    ("dst_port", &["dst_port", "dest_port", "dstport"]),
+    ("outfile", &["outfile", "destfile"]),
+    ("shasum", &["shasum", "sha256"]),
+];
+
+/// Fields that are numeric but some versions deliver as strings
+const NUMERIC_FIELDS: &[&str] = &["duration", "size", "ttysize"];
+
+/// Cowrie fields parsed into `LogEntry` rather than kept among the additional fields
+const EXTRACTED_FIELDS: &[&str] = &[
+    "eventid", "timestamp", "session", "src_ip", "src_port", "dst_ip", "dst_port", "username", "password", "input",
+];
+
+/// Renames from Cowrie's field names for each of `HoneypotConfig::SCHEMAS`
+const SCHEMA_RENAMES: &[(&str, &[(&str, &str)])] = &[
+    ("cowrie", &[]),
+    ("generic", &[
+        ("eventid", "event"),
+        ("session", "session_id"),
+        ("src_ip", "source_ip"),
+        ("src_port", "source_port"),
+        ("dst_ip", "destination_ip"),
+        ("dst_port", "destination_port"),
+        ("username", "user"),
+        ("input", "command"),
+        ("outfile", "file_path"),
+        ("shasum", "sha256"),
+    ]),
+];
+
+/// Where each Cowrie field is found in the configured log schema
+///
+/// Unmapped fields, and mapped ones missing from an event, fall back to the
+/// Cowrie name and its aliases, so Cowrie-shaped events (e.g. from the MySQL
+/// source) still parse under any schema.
+#[derive(Debug, Clone, Default)]
+pub struct FieldMap {
+    /// Field name in the logs by Cowrie field name; dots reach into nested objects
+    names: HashMap<String, String>,
+}
+
+impl FieldMap {
+    /// The `honeypot.schema` preset with `honeypot.field_mapping` merged over it
+    pub fn from_config(config: &HoneypotConfig) -> Self {
+        let renames = SCHEMA_RENAMES
+            .iter()
+            .find(|(schema, _)| *schema == config.schema)
+            .map_or(&[][..], |(_, renames)| *renames);
+        
+        let mut names: HashMap<String, String> = renames
+            .iter()
+            .map(|(field, name)| (field.to_string(), name.to_string()))
+            .collect();
+        names.extend(config.field_mapping.iter().map(|(field, name)| (field.clone(), name.clone())));
+        
+        Self { names }
+    }
+    
+    /// Look up a field by its Cowrie name: mapped name first, then known aliases in order
+    pub fn lookup<'a>(&self, value: &'a Value, field_name: &str) -> Option<&'a Value> {
+        let mapped = self.names
+            .get(field_name)
+            .and_then(|name| name.split('.').try_fold(value, |value, key| value.get(key)))
+            .filter(|field| !field.is_null());
+        if mapped.is_some() {
+            return mapped;
+        }
+        
+        let aliases = FIELD_ALIASES
+            .iter()
+            .find(|(canonical, _)| *canonical == field_name)
+            .map_or(&[][..], |(_, aliases)| *aliases);
+        
+        aliases
+            .iter()
+            .chain(std::iter::once(&field_name))
+            .map(|name| &value[*name])
+            .find(|field| !field.is_null())
+    }
+    
+    /// Top-level keys the mapping reads the fields parsed into `LogEntry` from
+    pub fn extracted_keys(&self) -> impl Iterator<Item = &str> + '_ {
+        self.names
+            .iter()
+            .filter(|(field, name)| EXTRACTED_FIELDS.contains(&field.as_str()) && !name.contains('.'))
+            .map(|(_, name)| name.as_str())
+    }
+}
 
 /// Analyzes and parses log entries from Cowrie honeypot
 pub struct LogAnalyzer {
     /// Mapping of Cowrie event types to our EventType enum
     event_type_mapping: HashMap<String, EventType>,
+    /// Where each field is found in the logs
+    fields: FieldMap,
 }
 
 impl LogAnalyzer {
@@ -35,9 +133,18 @@ impl LogAnalyzer {
         
         Self {
             event_type_mapping,
+            fields: FieldMap::default(),
         }
     }
     
+    /// Create a log analyzer for the configured schema, with the user's eventid mappings merged over the built-in map
+    pub fn with_config(config: &Config) -> Self {
+        let mut analyzer = Self::new();
+        merge_event_mapping(&mut analyzer.event_type_mapping, &config.honeypot.event_mapping);
+        analyzer.fields = FieldMap::from_config(&config.honeypot);
+        analyzer
+    }
+    
     /// Parse a JSON log entry into our LogEntry struct
     pub fn parse_log_entry(&self, line: &str) -> Result<LogEntry> {
         trace!("Parsing log entry: {}", line);
@@ -46,15 +153,21 @@ impl LogAnalyzer {
         let value: Value = serde_json::from_str(line)
             .context("Failed to parse log entry as JSON")?;
         
+        self.parse_value(value)
+    }
+    
+    /// Convert an already-decoded Cowrie JSON event into our LogEntry struct
+    pub fn parse_value(&self, value: Value) -> Result<LogEntry> {
         // Extract required fields
+        let eventid = self.extract_string_field(&value, "eventid").unwrap_or_default();
         let event_type = self.extract_event_type(&value)?;
         let timestamp = self.extract_timestamp(&value)?;
         let session = self.extract_string_field(&value, "session");
-        let src_ip = self.extract_string_field(&value, "src_ip");
+        let src_ip = self.extract_string_field(&value, "src_ip").map(IStr::from);
         let src_port = self.extract_number_field(&value, "src_port").map(|n| n as u16);
-        let dst_ip = self.extract_string_field(&value, "dst_ip");
+        let dst_ip = self.extract_string_field(&value, "dst_ip").map(IStr::from);
         let dst_port = self.extract_number_field(&value, "dst_port").map(|n| n as u16);
-        let username = self.extract_string_field(&value, "username");
+        let username = self.extract_string_field(&value, "username").map(IStr::from);
         let password = self.extract_string_field(&value, "password");
         let command = self.extract_string_field(&value, "input");
         
@@ -71,6 +184,7 @@ impl LogAnalyzer {
             id,
             timestamp,
             event_type,
+            eventid,
             session,
             src_ip,
             src_port,
@@ -81,7 +195,7 @@ impl LogAnalyzer {
             command,
             file,
             fields,
-            raw: value.clone(),
+            raw: value,
         };
         
         Ok(entry)
@@ -89,8 +203,9 @@ impl LogAnalyzer {
     
     /// Extract the event type from a log entry
     fn extract_event_type(&self, value: &Value) -> Result<EventType> {
-        let event_name = value["eventid"]
-            .as_str()
+        let event_name = self
+            .lookup_field(value, "eventid")
+            .and_then(Value::as_str)
             .context("Missing or invalid eventid field")?;
         
         let event_type = self.event_type_mapping
@@ -103,44 +218,76 @@ impl LogAnalyzer {
     
     /// Extract the timestamp from a log entry
     fn extract_timestamp(&self, value: &Value) -> Result<DateTime<Utc>> {
-        let timestamp_str = value["timestamp"]
-            .as_str()
-            .context("Missing or invalid timestamp field")?;
+        let timestamp = self
+            .lookup_field(value, "timestamp")
+            .context("Missing timestamp field")?;
         
-        let timestamp = DateTime::parse_from_rfc3339(timestamp_str)
-            .context("Failed to parse timestamp")?
-            .with_timezone(&Utc);
+        // Some exporters write epoch seconds instead of a string
+        if let Some(epoch) = number_value(timestamp) {
+            let secs = epoch.trunc() as i64;
+            let nanos = (epoch.fract() * 1e9) as u32;
+            return Utc
+                .timestamp_opt(secs, nanos)
+                .single()
+                .context("Timestamp out of range");
+        }
         
-        Ok(timestamp)
+        let timestamp_str = timestamp
+            .as_str()
+            .context("Invalid timestamp field")?;
+        
+        parse_timestamp(timestamp_str)
+            .context(format!("Failed to parse timestamp '{}'", timestamp_str))
+    }
+    
+    /// Look up a field by its canonical name, in the configured schema
+    fn lookup_field<'a>(&self, value: &'a Value, field_name: &str) -> Option<&'a Value> {
+        self.fields.lookup(value, field_name)
     }
     
     /// Extract a string field from a log entry
     fn extract_string_field(&self, value: &Value, field_name: &str) -> Option<String> {
-        value[field_name].as_str().map(String::from)
+        self.lookup_field(value, field_name)?.as_str().map(String::from)
     }
     
     /// Extract a number field from a log entry
     fn extract_number_field(&self, value: &Value, field_name: &str) -> Option<u64> {
-        if let Some(num) = value[field_name].as_u64() {
-            Some(num)
-        } else if let Some(num_str) = value[field_name].as_str() {
-            num_str.parse::<u64>().ok()
-        } else {
-            None
-        }
+        number_value(self.lookup_field(value, field_name)?)
+            .filter(|num| *num >= 0.0)
+            .map(|num| num as u64)
     }
     
     /// Extract additional fields from a log entry
     fn extract_additional_fields(&self, value: &Value) -> HashMap<String, Value> {
         let mut fields = HashMap::new();
         
+        // Fields (and their aliases and mapped names) already extracted into LogEntry
+        let extracted = ["id", "eventid", "session", "username", "password", "input"]
+            .iter()
+            .copied()
+            .chain(
+                FIELD_ALIASES
+                    .iter()
+                    .filter(|(canonical, _)| !matches!(*canonical, "outfile" | "shasum"))
+                    .flat_map(|(_, aliases)| aliases.iter().copied()),
+            )
+            .chain(self.fields.extracted_keys())
+            .collect::<Vec<_>>();
+        
         if let Some(obj) = value.as_object() {
             for (key, val) in obj {
-                // Skip fields we've already extracted
-                if !["id", "timestamp", "eventid", "session", "src_ip", "src_port",
-                     "dst_ip", "dst_port", "username", "password", "input"].contains(&key.as_str()) {
-                    fields.insert(key.clone(), val.clone());
+                if extracted.contains(&key.as_str()) {
+                    continue;
                 }
+                
+                // Store numeric fields as numbers whatever the version sent
+                let val = if NUMERIC_FIELDS.contains(&key.as_str()) {
+                    number_value(val).map_or_else(|| val.clone(), |num| json!(num))
+                } else {
+                    val.clone()
+                };
+                
+                fields.insert(key.clone(), val);
             }
         }
         
@@ -151,9 +298,12 @@ impl LogAnalyzer {
     fn extract_file_info(&self, value: &Value, event_type: &EventType) -> Option<FileTransfer> {
         match event_type {
             EventType::FileUpload | EventType::FileDownload => {
-                let filename = self.extract_string_field(value, "filename")?;
+                // Downloads are logged with the URL they came from, not a file name
+                let filename = self.extract_string_field(value, "filename")
+                    .or_else(|| self.extract_string_field(value, "url"))?;
                 let outfile = self.extract_string_field(value, "outfile");
                 let shasum = self.extract_string_field(value, "shasum");
+                let size = self.extract_number_field(value, "size");
                 
                 let direction = match event_type {
                     EventType::FileUpload => FileTransferDirection::Upload,
@@ -166,13 +316,14 @@ impl LogAnalyzer {
                 Some(FileTransfer {
                     filename,
                     local_path: outfile,
-                    size: None, // Size is often not included in log entries
+                    size, // Often not included in log entries
                     shasum,
                     timestamp,
                     direction,
                     mime_type: None,
                     is_executable: false,
                     is_malware: false,
+                    hash_list: None,
                 })
             }
             _ => None,
@@ -180,6 +331,7 @@ impl LogAnalyzer {
     }
     
     /// Analyze a session for potential malicious activity
+    #[allow(dead_code)]
     pub fn analyze_session_risk(&self, session: &crate::data::Session) -> u8 {
         let mut score = 0;
         
@@ -229,4 +381,262 @@ impl LogAnalyzer {
         // Cap score at 100
         score.min(100)
     }
-}
\ No newline at end of file
+}
+
+/// Read a number that may have been delivered as a string
+fn number_value(value: &Value) -> Option<f64> {
+    value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|s| s.trim().parse::<f64>().ok()))
+}
+
+/// Parse Cowrie timestamps, with or without fractional seconds and timezone
+fn parse_timestamp(timestamp: &str) -> Result<DateTime<Utc>> {
+    let timestamp = timestamp.trim();
+    
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(timestamp) {
+        return Ok(parsed.with_timezone(&Utc));
+    }
+    
+    // Offsets without a colon, e.g. "+0000"
+    for format in ["%Y-%m-%dT%H:%M:%S%.f%z", "%Y-%m-%d %H:%M:%S%.f%z"] {
+        if let Ok(parsed) = DateTime::parse_from_str(timestamp, format) {
+            return Ok(parsed.with_timezone(&Utc));
+        }
+    }
+    
+    // No offset (or a lowercase/duplicated "Z"): Cowrie always logs UTC
+    let naive = timestamp.trim_end_matches(|c| c == 'Z' || c == 'z');
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"] {
+        if let Ok(parsed) = NaiveDateTime::parse_from_str(naive, format) {
+            return Ok(Utc.from_utc_datetime(&parsed));
+        }
+    }
+    
+    Err(anyhow::anyhow!("Unrecognized timestamp format"))
+}
+
+/// Merge user-supplied `eventid = "EventType"` mappings over a built-in map
+pub fn merge_event_mapping(mapping: &mut HashMap<String, EventType>, overrides: &HashMap<String, String>) {
+    for (eventid, event_type) in overrides {
+        match event_type.parse::<EventType>() {
+            Ok(event_type) => {
+                debug!("Mapping {} to {}", eventid, event_type);
+                mapping.insert(eventid.clone(), event_type);
+            }
+            Err(e) => warn!("Ignoring event mapping for {}: {}", eventid, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// Sessions as logged by Cowrie 1.x, 2.x and current git, one event per line
+    const FIXTURES: &[(&str, &str)] = &[
+        ("cowrie-1.x", include_str!("fixtures/cowrie-1.x.json")),
+        ("cowrie-2.x", include_str!("fixtures/cowrie-2.x.json")),
+        ("cowrie-git", include_str!("fixtures/cowrie-git.json")),
+    ];
+
+    /// Events a session can't be reconstructed without
+    const CRITICAL: &[&str] = &[
+        "cowrie.session.connect",
+        "cowrie.session.closed",
+        "cowrie.login.success",
+        "cowrie.login.failed",
+        "cowrie.command.input",
+        "cowrie.command.success",
+        "cowrie.session.file_download",
+        "cowrie.session.file_upload",
+    ];
+
+    /// Every entry of fixture `version`
+    fn fixture(version: &str) -> Vec<LogEntry> {
+        let (_, lines) = FIXTURES.iter().find(|(name, _)| *name == version).unwrap();
+        let analyzer = LogAnalyzer::new();
+        lines.lines()
+            .enumerate()
+            .map(|(number, line)| {
+                analyzer.parse_log_entry(line)
+                    .unwrap_or_else(|e| panic!("{} line {}: {:#}", version, number + 1, e))
+            })
+            .collect()
+    }
+
+    /// The first entry of `fixture` logged as `eventid`
+    fn event<'a>(fixture: &'a [LogEntry], eventid: &str) -> &'a LogEntry {
+        fixture.iter().find(|entry| entry.eventid == eventid).unwrap()
+    }
+
+    #[test]
+    fn every_fixture_parses_with_no_unknown_critical_events() {
+        for (version, _) in FIXTURES {
+            for entry in fixture(version) {
+                assert!(entry.session.is_some(), "{}: {} has no session", version, entry.eventid);
+                if CRITICAL.contains(&entry.eventid.as_str()) {
+                    assert_ne!(entry.event_type, EventType::Unknown, "{}: {}", version, entry.eventid);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn renamed_fields_are_found_under_either_name() {
+        for (version, ports) in [("cowrie-1.x", (51422, 2222)), ("cowrie-2.x", (40112, 22)), ("cowrie-git", (55910, 22))] {
+            let entries = fixture(version);
+            let connect = event(&entries, "cowrie.session.connect");
+            assert_eq!((connect.src_port, connect.dst_port), (Some(ports.0), Some(ports.1)), "{}", version);
+            assert_eq!(connect.dst_ip.as_deref(), Some("10.0.0.2"), "{}", version);
+        }
+
+        let entries = fixture("cowrie-1.x");
+        let file = event(&entries, "cowrie.session.file_download").file.as_ref().unwrap();
+        assert_eq!(file.local_path.as_deref(), Some("dl/6b86b273ff34fce19d6b804eff5a3f5747ada4eaa22f1d49c01e52ddb7875b4b"));
+        assert_eq!(file.size, Some(4096));
+
+        // Current Cowrie logs both, and outfile is the stored copy; the URL names the file
+        let entries = fixture("cowrie-git");
+        let file = event(&entries, "cowrie.session.file_download").file.as_ref().unwrap();
+        assert_eq!(file.filename, "http://198.51.100.200/x86");
+        assert_eq!(file.local_path.as_deref(), Some("var/lib/cowrie/downloads/4e07408562bedb8b60ce05c1decfe3ad16b72230967de01f640b7e4729b49fce"));
+    }
+
+    #[test]
+    fn durations_are_numbers_whichever_way_they_were_logged() {
+        for (version, duration) in [("cowrie-1.x", 12.4), ("cowrie-2.x", 7.1), ("cowrie-git", 5.2)] {
+            let entries = fixture(version);
+            assert_eq!(event(&entries, "cowrie.session.closed").fields["duration"], json!(duration), "{}", version);
+        }
+    }
+
+    #[test]
+    fn timestamps_are_read_in_every_variant() {
+        let expected = Utc.with_ymd_and_hms(2020, 9, 12, 14, 3, 11).unwrap();
+        for timestamp in [
+            "2020-09-12T14:03:11Z",
+            "2020-09-12T14:03:11.000000Z",
+            "2020-09-12T14:03:11+00:00",
+            "2020-09-12T16:03:11.000+02:00",
+            "2020-09-12T14:03:11+0000",
+            "2020-09-12T14:03:11",
+            "2020-09-12 14:03:11",
+            "2020-09-12T14:03:11zZ",
+        ] {
+            assert_eq!(parse_timestamp(timestamp).unwrap(), expected, "{}", timestamp);
+        }
+        assert!(parse_timestamp("12/09/2020 14:03").is_err());
+
+        let epoch = LogAnalyzer::new()
+            .parse_log_entry(r#"{"eventid": "cowrie.session.connect", "time": 1599919391.5, "session": "s"}"#)
+            .unwrap();
+        assert_eq!(epoch.timestamp, expected + chrono::Duration::milliseconds(500));
+    }
+
+    #[test]
+    fn fixture_timestamps_keep_their_fractions() {
+        let entries = fixture("cowrie-git");
+        assert_eq!(entries[0].timestamp, Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap() + chrono::Duration::microseconds(123_456));
+    }
+
+    #[test]
+    fn a_configured_mapping_reclassifies_a_fixture_line() {
+        let line = FIXTURES[1].1.lines().find(|line| line.contains("\"cowrie.log.closed\"")).unwrap();
+        assert_eq!(LogAnalyzer::new().parse_log_entry(line).unwrap().event_type, EventType::Unknown);
+
+        let mut config = Config::default();
+        config.honeypot.event_mapping = [
+            ("cowrie.log.closed", "Disconnect"),
+            ("cowrie.client.kex", "Key Auth"),
+            ("cowrie.session.params", "NoSuchType"),
+        ].iter().map(|(eventid, event_type)| (eventid.to_string(), event_type.to_string())).collect();
+        let analyzer = LogAnalyzer::with_config(&config);
+
+        let entry = analyzer.parse_log_entry(line).unwrap();
+        assert_eq!(entry.event_type, EventType::Disconnect);
+        assert_eq!(entry.eventid, "cowrie.log.closed");
+        // Mappings override the built-in ones; bad ones are ignored
+        let kex = FIXTURES[1].1.lines().find(|line| line.contains("\"cowrie.client.kex\"")).unwrap();
+        assert_eq!(analyzer.parse_log_entry(kex).unwrap().event_type, EventType::KeyAuth);
+        let params = FIXTURES[1].1.lines().find(|line| line.contains("\"cowrie.session.params\"")).unwrap();
+        assert_eq!(analyzer.parse_log_entry(params).unwrap().event_type, EventType::Unknown);
+    }
+
+    /// Config reading logs of `schema` with `field_mapping` over it
+    fn schema(schema: &str, field_mapping: &[(&str, &str)]) -> Config {
+        let mut config = Config::default();
+        config.honeypot.schema = schema.to_string();
+        config.honeypot.field_mapping = field_mapping.iter()
+            .map(|(field, name)| (field.to_string(), name.to_string()))
+            .collect();
+        config
+    }
+
+    #[test]
+    fn the_generic_schema_reads_its_own_field_names() {
+        let analyzer = LogAnalyzer::with_config(&schema("generic", &[]));
+        let entry = analyzer.parse_log_entry(r#"{
+            "event": "cowrie.command.input", "timestamp": "2024-05-01T12:00:00Z", "session_id": "s1",
+            "source_ip": "203.0.113.7", "source_port": 40112, "destination_ip": "10.0.0.2", "destination_port": 22,
+            "user": "root", "command": "uname -a", "sensor": "hp1"
+        }"#).unwrap();
+
+        assert_eq!(entry.event_type, EventType::Command);
+        assert_eq!(entry.session.as_deref(), Some("s1"));
+        assert_eq!((entry.src_ip.as_deref(), entry.src_port), (Some("203.0.113.7"), Some(40112)));
+        assert_eq!((entry.dst_ip.as_deref(), entry.dst_port), (Some("10.0.0.2"), Some(22)));
+        assert_eq!((entry.username.as_deref(), entry.command.as_deref()), (Some("root"), Some("uname -a")));
+        // Mapped keys aren't repeated among the additional fields
+        assert_eq!(entry.fields, HashMap::from([("sensor".to_string(), json!("hp1"))]));
+    }
+
+    #[test]
+    fn a_mapping_reaches_into_nested_objects() {
+        let config = schema("generic", &[("src_ip", "source.ip"), ("eventid", "kind")]);
+        let entry = LogAnalyzer::with_config(&config).parse_log_entry(r#"{
+            "kind": "cowrie.login.failed", "timestamp": "2024-05-01T12:00:00Z", "session_id": "s1",
+            "source": {"ip": "198.51.100.23"}, "user": "admin"
+        }"#).unwrap();
+
+        assert_eq!(entry.event_type, EventType::LoginFailed);
+        assert_eq!(entry.src_ip.as_deref(), Some("198.51.100.23"));
+        assert_eq!(entry.username.as_deref(), Some("admin"));
+    }
+
+    #[test]
+    fn cowrie_events_still_parse_under_another_schema() {
+        let config = schema("generic", &[("src_ip", "source.ip")]);
+        let remapped = LogAnalyzer::with_config(&config);
+        for (version, lines) in FIXTURES {
+            for (line, expected) in lines.lines().zip(fixture(version)) {
+                let entry = remapped.parse_log_entry(line).unwrap();
+                assert_eq!(entry.event_type, expected.event_type, "{}", line);
+                assert_eq!((&entry.session, &entry.src_ip), (&expected.session, &expected.src_ip), "{}", line);
+            }
+        }
+    }
+
+    #[test]
+    fn the_enhanced_analyzer_uses_the_same_mapping() {
+        let config = schema("generic", &[("dst_port", "honeypot.port")]);
+        let entry = crate::core::EnhancedLogAnalyzer::new(&config).parse_log_entry(r#"{
+            "event": "cowrie.session.connect", "timestamp": "2024-05-01T12:00:00Z", "session_id": "s1",
+            "source_ip": "203.0.113.7", "honeypot": {"port": 2222}
+        }"#).unwrap();
+
+        assert_eq!(entry.event_type, EventType::Connect);
+        assert_eq!((entry.src_ip.as_deref(), entry.dst_port), (Some("203.0.113.7"), Some(2222)));
+    }
+
+    #[test]
+    fn unknown_schemas_and_fields_are_rejected() {
+        assert!(schema("generic", &[("src_ip", "source.ip")]).validate().is_ok());
+
+        let error = schema("suricata", &[]).validate().unwrap_err().to_string();
+        assert!(error.contains("honeypot.schema must be one of cowrie, generic"), "{}", error);
+        let error = schema("cowrie", &[("source_address", "src")]).validate().unwrap_err().to_string();
+        assert!(error.contains("honeypot.field_mapping has unknown fields source_address"), "{}", error);
+    }
+}
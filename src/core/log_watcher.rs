@@ -1,26 +1,35 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use chrono::{DateTime, Duration, Utc};
 use log::{debug, error, info, warn};
 use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::io::SeekFrom;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::SystemTime;
 use tokio::fs::File;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::io::{AsyncBufReadExt, AsyncSeekExt, BufReader};
+use tokio::sync::{broadcast, mpsc, Semaphore};
 use tokio::task::JoinHandle;
 
 use crate::app::AppEvent;
 use crate::config::Config as AppConfig;
+use crate::core::ingest_journal::{file_inode, IngestJournal, ResumeDecision};
 use crate::core::log_analyzer::LogAnalyzer;
-use crate::data::{LogEntry, Store};
+use crate::core::task_health::TaskReporter;
+use crate::data::StoreHandle;
 
 /// Start watching a log file for changes
+///
+/// Each read of new lines holds one of `permits`, so watchers sharing them
+/// read at most `honeypot.max_concurrent_watchers` files at a time.
+/// The task heartbeats on `health` for every change it handles.
 pub async fn start_log_watcher(
     path: PathBuf,
-    store: Arc<RwLock<Store>>,
+    store: StoreHandle,
     event_tx: broadcast::Sender<AppEvent>,
     config: &AppConfig,
+    permits: Arc<Semaphore>,
+    health: TaskReporter,
 ) -> Result<JoinHandle<()>> {
     info!("Starting log watcher for {}", path.display());
     
@@ -30,7 +39,7 @@ pub async fn start_log_watcher(
     }
     
     // Create log analyzer
-    let log_analyzer = LogAnalyzer::new();
+    let log_analyzer = LogAnalyzer::with_config(config);
     
     // Set up channel for file events
     let (file_event_tx, file_event_rx) = mpsc::channel(100);
@@ -55,167 +64,265 @@ pub async fn start_log_watcher(
     
     // Determine starting point for log processing
     let start_time = determine_start_time(config)?;
+    let mut journal = journal_dir(config).map(|dir| IngestJournal::open(&dir, &path));
     let file_path = path.clone();
     
     // Start processing task
     let task = tokio::spawn(async move {
+        // Keep the watcher alive for as long as we are processing its events
+        let _watcher = watcher;
+        
+        let inode = std::fs::metadata(&file_path).map(|m| file_inode(&m)).unwrap_or(0);
+        let (offset, cutoff) = resume_point(&file_path, journal.as_mut(), inode, start_time);
+        
         // Process existing log entries first
-        if let Err(e) = process_existing_logs(
+        let file_position = match read_new_lines(
             &file_path,
-            store.clone(),
-            event_tx.clone(),
+            offset,
+            cutoff,
+            &store,
+            &event_tx,
             &log_analyzer,
-            start_time,
+            &permits,
+            journal.as_mut(),
         ).await {
-            error!("Error processing existing logs: {}", e);
-        }
+            Ok(position) => position,
+            Err(e) => {
+                error!("Error processing existing logs: {}", e);
+                health.report_error(format!("Error processing existing logs: {}", e));
+                offset
+            }
+        };
+        health.heartbeat();
         
         // Process file change events
         process_file_events(
             file_event_rx,
             file_path,
+            file_position,
+            inode,
             store,
             event_tx,
             log_analyzer,
+            permits,
+            journal,
+            health,
         ).await;
     });
     
     Ok(task)
 }
 
-/// Process existing log entries in the file
-async fn process_existing_logs(
+/// Decide where to start reading, returning the byte offset and an optional timestamp cutoff
+fn resume_point(
     path: &Path,
-    store: Arc<RwLock<Store>>,
-    event_tx: broadcast::Sender<AppEvent>,
-    log_analyzer: &LogAnalyzer,
+    journal: Option<&mut IngestJournal>,
+    inode: u64,
     start_time: DateTime<Utc>,
-) -> Result<()> {
-    info!("Processing existing logs from {}", path.display());
+) -> (u64, Option<DateTime<Utc>>) {
+    let journal = match journal {
+        Some(journal) => journal,
+        None => {
+            info!("Reading {} from the start (journal disabled)", path.display());
+            return (0, Some(start_time));
+        }
+    };
+    
+    match journal.decide(path) {
+        Ok(ResumeDecision::Resume(offset)) => {
+            info!("Resuming {} from journaled offset {}", path.display(), offset);
+            return (offset, None);
+        }
+        Ok(ResumeDecision::Rotated) => {
+            info!(
+                "{} was rotated or replaced since the last run, re-reading from the start",
+                path.display()
+            );
+        }
+        Ok(ResumeDecision::Fresh) => {
+            info!("No ingestion journal for {}, reading from the start", path.display());
+        }
+        Err(e) => {
+            warn!("Unable to check ingestion journal for {}: {}", path.display(), e);
+        }
+    }
+    
+    journal.reset(inode);
+    (0, Some(start_time))
+}
+
+/// Ingest complete lines starting at `offset`, returning the offset after the last one
+#[allow(clippy::too_many_arguments)]
+async fn read_new_lines(
+    path: &Path,
+    offset: u64,
+    cutoff: Option<DateTime<Utc>>,
+    store: &StoreHandle,
+    event_tx: &broadcast::Sender<AppEvent>,
+    log_analyzer: &LogAnalyzer,
+    permits: &Semaphore,
+    mut journal: Option<&mut IngestJournal>,
+) -> Result<u64> {
+    // Wait for a turn; the semaphore is never closed
+    let _permit = permits.acquire().await?;
+    debug!("Reading {} from offset {}", path.display(), offset);
     
-    // Open the file
-    let file = File::open(path).await?;
-    let reader = BufReader::new(file);
-    let mut lines = reader.lines();
+    // Open the file at the last position
+    let mut file = File::open(path).await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+    let mut reader = BufReader::new(file);
     
+    let source = path.display().to_string();
+    let mut batch = store.batch(&source);
+    let mut position = offset;
+    let mut buf = Vec::new();
     let mut count = 0;
     
-    // Read lines and process
-    while let Some(line) = lines.next_line().await? {
-        // Parse and process the log entry
-        match log_analyzer.parse_log_entry(&line) {
-            Ok(entry) => {
-                // Skip entries before start time
-                if entry.timestamp < start_time {
-                    continue;
-                }
-                
-                // Add entry to store
-                {
-                    let mut store = store.write().await;
-                    if let Err(e) = store.add_log_entry(entry.clone()) {
-                        error!("Error adding log entry to store: {}", e);
+    loop {
+        let mut queued = false;
+
+        buf.clear();
+        let bytes = reader.read_until(b'\n', &mut buf).await?;
+        
+        // Stop at end of file, or at a line that is still being written
+        if bytes == 0 || buf.last() != Some(&b'\n') {
+            break;
+        }
+        
+        let line_start = position;
+        position += bytes as u64;
+        
+        // Invalid UTF-8 shouldn't stop ingestion of the rest of the file
+        let line = String::from_utf8_lossy(&buf);
+        
+        if !line.trim().is_empty() {
+            // Parse and process the log entry
+            match log_analyzer.parse_log_entry(line.trim()) {
+                Ok(entry) => {
+                    // Skip entries before start time
+                    if cutoff.map_or(true, |cutoff| entry.timestamp >= cutoff) {
+                        // Add entry to store
+                        queued = batch.push(entry.clone()).await;
+                        
+                        // Send event
+                        let _ = event_tx.send(AppEvent::NewLogEntry(entry));
+                        
+                        count += 1;
                     }
                 }
-                
-                // Send event
-                let _ = event_tx.send(AppEvent::NewLogEntry(entry));
-                
-                count += 1;
+                Err(e) => {
+                    debug!("Error parsing log entry: {}", e);
+                    store.record_parse_failure(&source, line.trim(), &format!("{:#}", e)).await;
+                }
             }
-            Err(e) => {
-                debug!("Error parsing log entry: {}", e);
+        }
+        
+        if let Some(journal) = journal.as_mut() {
+            journal.record(line_start, position, &buf);
+            if queued {
+                checkpoint(store, journal).await;
             }
         }
     }
     
-    info!("Processed {} existing log entries", count);
-    Ok(())
+    batch.flush().await;
+    
+    if let Some(journal) = journal {
+        checkpoint(store, journal).await;
+    }
+    
+    debug!("Processed {} log entries from {}", count, path.display());
+    Ok(position)
+}
+
+/// Write the journal once the writer has applied every line recorded in it
+async fn checkpoint(store: &StoreHandle, journal: &mut IngestJournal) {
+    store.applied().await;
+    if let Err(e) = journal.flush() {
+        warn!("Failed to write ingestion journal: {}", e);
+    }
 }
 
 /// Process file change events
+#[allow(clippy::too_many_arguments)]
 async fn process_file_events(
     mut file_event_rx: mpsc::Receiver<Event>,
     path: PathBuf,
-    store: Arc<RwLock<Store>>,
+    mut file_position: u64,
+    mut inode: u64,
+    store: StoreHandle,
     event_tx: broadcast::Sender<AppEvent>,
     log_analyzer: LogAnalyzer,
+    permits: Arc<Semaphore>,
+    mut journal: Option<IngestJournal>,
+    health: TaskReporter,
 ) {
-    let mut file_position = get_file_size(&path).unwrap_or(0);
-    
     while let Some(event) = file_event_rx.recv().await {
         // Check if the event is relevant
         if !is_relevant_event(&event, &path) {
             continue;
         }
         
-        // Handle file modification
-        if matches!(event.kind, EventKind::Modify(_)) {
+        // Handle file modification (and re-creation after rotation)
+        if matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_)) {
             if let Err(e) = process_file_changes(
                 &path,
                 &mut file_position,
-                store.clone(),
-                event_tx.clone(),
+                &mut inode,
+                &store,
+                &event_tx,
                 &log_analyzer,
+                &permits,
+                journal.as_mut(),
             ).await {
                 error!("Error processing file changes: {}", e);
+                health.report_error(format!("Error processing file changes: {}", e));
             }
+            health.heartbeat();
         }
     }
 }
 
 /// Process changes to the log file
+#[allow(clippy::too_many_arguments)]
 async fn process_file_changes(
     path: &Path,
     file_position: &mut u64,
-    store: Arc<RwLock<Store>>,
-    event_tx: broadcast::Sender<AppEvent>,
+    inode: &mut u64,
+    store: &StoreHandle,
+    event_tx: &broadcast::Sender<AppEvent>,
     log_analyzer: &LogAnalyzer,
+    permits: &Semaphore,
+    mut journal: Option<&mut IngestJournal>,
 ) -> Result<()> {
-    // Open the file
-    let file = File::open(path).await?;
-    
-    // Get current file size
-    let metadata = file.metadata().await?;
+    // Get current file size and identity
+    let metadata = tokio::fs::metadata(path).await?;
     let new_size = metadata.len();
+    let new_inode = file_inode(&metadata);
     
-    // Check if file was truncated
-    if new_size < *file_position {
-        debug!("File was truncated, resetting position");
+    // Check if file was rotated or truncated
+    if new_inode != *inode || new_size < *file_position {
+        debug!("File was rotated or truncated, resetting position");
         *file_position = 0;
+        *inode = new_inode;
+        
+        if let Some(journal) = journal.as_mut() {
+            journal.reset(new_inode);
+        }
     }
     
     // Check if file has grown
     if new_size > *file_position {
-        // Seek to last position
-        let take_bytes = new_size - *file_position;
-        let file = tokio::fs::File::open(path).await?;
-        let reader = BufReader::new(file.take(take_bytes));
-        let mut lines = reader.lines();
-        
-        // Read and process new lines
-        while let Some(line) = lines.next_line().await? {
-            match log_analyzer.parse_log_entry(&line) {
-                Ok(entry) => {
-                    // Add entry to store
-                    {
-                        let mut store = store.write().await;
-                        if let Err(e) = store.add_log_entry(entry.clone()) {
-                            error!("Error adding log entry to store: {}", e);
-                        }
-                    }
-                    
-                    // Send event
-                    let _ = event_tx.send(AppEvent::NewLogEntry(entry));
-                }
-                Err(e) => {
-                    debug!("Error parsing log entry: {}", e);
-                }
-            }
-        }
-        
-        // Update position
-        *file_position = new_size;
+        *file_position = read_new_lines(
+            path,
+            *file_position,
+            None,
+            store,
+            event_tx,
+            log_analyzer,
+            permits,
+            journal,
+        ).await?;
     }
     
     Ok(())
@@ -232,14 +339,20 @@ fn is_relevant_event(event: &Event, path: &Path) -> bool {
     false
 }
 
-/// Get the current size of a file
-fn get_file_size(path: &Path) -> Result<u64> {
-    let metadata = std::fs::metadata(path)?;
-    Ok(metadata.len())
+/// Directory holding the ingestion journal, if resuming is enabled
+fn journal_dir(config: &AppConfig) -> Option<PathBuf> {
+    if !config.honeypot.resume_from_journal {
+        return None;
+    }
+    
+    match &config.honeypot.state_dir {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => dirs::data_dir().map(|dir| dir.join("xkippo")),
+    }
 }
 
 /// Determine the start time for log processing based on configuration
-fn determine_start_time(config: &AppConfig) -> Result<DateTime<Utc>> {
+pub(crate) fn determine_start_time(config: &AppConfig) -> Result<DateTime<Utc>> {
     let now = Utc::now();
     
     // Use history_hours from config
@@ -252,4 +365,94 @@ fn determine_start_time(config: &AppConfig) -> Result<DateTime<Utc>> {
         // Process logs from N hours ago
         Ok(now - Duration::hours(hours as i64))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::store::Store;
+    use crate::data::testing::eventually;
+    use crate::data::store_writer::WhitelistFilter;
+    use pretty_assertions::assert_eq;
+    use std::io::Write;
+    use std::time::Duration as StdDuration;
+
+    #[tokio::test]
+    async fn a_line_written_in_two_chunks_is_read_once_whole() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cowrie.json");
+        let line = "{\"eventid\":\"cowrie.session.connect\",\"session\":\"s1\",\"src_ip\":\"198.51.100.4\",\"timestamp\":\"2023-11-14T22:13:20Z\"}\n";
+        let (first, rest) = line.split_at(40);
+        std::fs::write(&path, first).unwrap();
+
+        let config = AppConfig::default();
+        let (store, _writer) = StoreHandle::spawn(
+            Store::new(&config).unwrap(),
+            WhitelistFilter::from_config(&config),
+            None,
+            StdDuration::from_millis(10),
+        );
+        let (event_tx, mut events) = broadcast::channel(16);
+        let analyzer = LogAnalyzer::with_config(&config);
+        let permits = Semaphore::new(1);
+        let mut inode = file_inode(&std::fs::metadata(&path).unwrap());
+        let mut position = 0;
+
+        process_file_changes(&path, &mut position, &mut inode, &store, &event_tx, &analyzer, &permits, None).await.unwrap();
+        assert_eq!(position, 0);
+
+        std::fs::OpenOptions::new().append(true).open(&path).unwrap()
+            .write_all(rest.as_bytes()).unwrap();
+        process_file_changes(&path, &mut position, &mut inode, &store, &event_tx, &analyzer, &permits, None).await.unwrap();
+        assert_eq!(position, line.len() as u64);
+
+        match events.try_recv() {
+            Ok(AppEvent::NewLogEntry(entry)) => assert_eq!(entry.session.as_deref(), Some("s1")),
+            other => panic!("expected one log entry, got {:?}", other.is_ok()),
+        }
+        assert!(events.try_recv().is_err());
+        eventually(|| store.snapshot().get_total_parse_stats().parsed == 1).await;
+        assert_eq!(store.snapshot().get_total_parse_stats().failed, 0);
+    }
+
+    #[tokio::test]
+    async fn the_journal_covers_every_line_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cowrie.json");
+        let lines = (0..600).fold(String::new(), |mut lines, i| {
+            lines.push_str(&format!(
+                "{{\"eventid\":\"cowrie.session.connect\",\"session\":\"s{}\",\"src_ip\":\"198.51.100.4\",\"timestamp\":\"2023-11-14T22:13:20Z\"}}\n",
+                i
+            ));
+            lines
+        });
+        std::fs::write(&path, &lines).unwrap();
+
+        let config = AppConfig::default();
+        let (store, _writer) = StoreHandle::spawn(
+            Store::new(&config).unwrap(),
+            WhitelistFilter::from_config(&config),
+            None,
+            StdDuration::from_secs(60),
+        );
+        let (event_tx, _) = broadcast::channel(1000);
+        let mut journal = IngestJournal::open(dir.path(), &path);
+        journal.reset(file_inode(&std::fs::metadata(&path).unwrap()));
+
+        let position = read_new_lines(
+            &path,
+            0,
+            None,
+            &store,
+            &event_tx,
+            &LogAnalyzer::with_config(&config),
+            &Semaphore::new(1),
+            Some(&mut journal),
+        ).await.unwrap();
+
+        assert_eq!(position, lines.len() as u64);
+        assert_eq!(
+            IngestJournal::open(dir.path(), &path).decide(&path).unwrap(),
+            ResumeDecision::Resume(position)
+        );
+    }
+}
@@ -1,11 +1,43 @@
 mod alert_engine;
+mod alert_history;
+mod alert_log;
+mod canaries;
+pub mod event_source;
+mod event_stream;
+#[cfg(feature = "grpc")]
+mod grpc_stream;
+mod ingest_governor;
+mod ingest_journal;
+mod journald_source;
 mod log_analyzer;
 mod log_watcher;
+#[cfg(feature = "mysql-db")]
+mod mysql_source;
+#[cfg(feature = "remote-ssh")]
+mod remote_watcher;
 mod session_manager;
+#[cfg(feature = "siem")]
+mod siem;
+mod task_health;
+pub mod threat_feeds;
+mod web_server;
 mod enhanced_log_analyzer;
+mod hash_lists;
+pub mod cast;
+pub mod credential_leaks;
+pub mod export;
+pub mod persistence;
+pub mod rdap;
+pub mod samples;
 
-pub use alert_engine::AlertEngine;
+pub use alert_engine::{Alert, AlertEngine, AlertType, Severity};
+pub use alert_history::AlertHistory;
+pub use canaries::Canaries;
+pub use event_stream::{start_event_stream, StreamStats};
 pub use log_analyzer::LogAnalyzer;
-pub use log_watcher::start_log_watcher;
-pub use session_manager::SessionManager;
-pub use enhanced_log_analyzer::EnhancedLogAnalyzer;
\ No newline at end of file
+pub use session_manager::{OpenSessions, SessionManager};
+#[cfg(feature = "siem")]
+pub use siem::start_siem_sender;
+pub use task_health::{TaskHealth, TaskReporter, TaskState};
+pub use web_server::start_web_server;
+pub use enhanced_log_analyzer::EnhancedLogAnalyzer;
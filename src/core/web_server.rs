@@ -0,0 +1,550 @@
+//! Read-only web snapshot (`[web]`)
+//!
+//! A small HTTP/1.1 server on the application's runtime, for a glance at the
+//! honeypot from a phone. It answers `GET` only: a static page at `/`, and
+//! JSON for `/summary`, `/sessions`, `/alerts` and `/top/attackers` read from
+//! the latest store snapshot. Every JSON endpoint needs
+//! `Authorization: Bearer <web.auth_token>`; nothing can be changed through
+//! it. Each connection carries one request and is then closed.
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use log::{debug, info, warn};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::config::{AttackerWeights, Config};
+use crate::core::{AlertHistory, StreamStats};
+use crate::data::attackers::{attacker_profiles, rank_attackers};
+use crate::data::{Session, StoreHandle};
+
+/// The page served at `/`; it fetches the JSON endpoints itself
+const INDEX_HTML: &str = include_str!("web_index.html");
+
+/// Largest request head read; anything longer is refused
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+/// Time a client gets to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Sessions returned when `limit` isn't given, and the most returned
+const DEFAULT_SESSIONS: usize = 50;
+const MAX_SESSIONS: usize = 500;
+
+/// Alerts returned, newest first
+const MAX_ALERTS: usize = 100;
+
+/// Attackers returned when `limit` isn't given, and the most returned
+const DEFAULT_ATTACKERS: usize = 10;
+const MAX_ATTACKERS: usize = 100;
+
+/// What the request handlers read from
+struct WebState {
+    /// Store the snapshots are taken from
+    store: StoreHandle,
+    /// Alerts as the alerts panel shows them
+    alert_history: Arc<parking_lot::Mutex<AlertHistory>>,
+    /// Weights Top Attackers is ranked by
+    weights: AttackerWeights,
+    /// Event stream counters, while it runs
+    stream: Option<Arc<StreamStats>>,
+    /// Token every JSON request must carry
+    auth_token: String,
+}
+
+/// A parsed request line and the headers that matter
+struct Request {
+    /// Method, e.g. "GET"
+    method: String,
+    /// Path without the query
+    path: String,
+    /// Query parameters; the last of repeated ones wins
+    query: HashMap<String, String>,
+    /// Token from `Authorization: Bearer`
+    bearer: Option<String>,
+}
+
+/// Status code, reason and JSON or HTML body of a response
+struct Response {
+    /// Status code
+    status: u16,
+    /// Reason phrase for the status line
+    reason: &'static str,
+    /// Media type of `body`
+    content_type: &'static str,
+    /// Body, sent whole
+    body: String,
+}
+
+impl Response {
+    /// 200 with `value` as JSON
+    fn json(value: Value) -> Self {
+        Self { status: 200, reason: "OK", content_type: "application/json", body: value.to_string() }
+    }
+
+    /// An error status with `{"error": message}`
+    fn error(status: u16, reason: &'static str, message: &str) -> Self {
+        Self {
+            status,
+            reason,
+            content_type: "application/json",
+            body: json!({ "error": message }).to_string(),
+        }
+    }
+}
+
+/// Bind `web.bind` and serve until the returned task is aborted
+///
+/// Binding happens before this returns, so an address in use is reported
+/// at startup rather than from inside the task.
+pub async fn start_web_server(
+    config: &Config,
+    store: StoreHandle,
+    alert_history: Arc<parking_lot::Mutex<AlertHistory>>,
+    stream: Option<Arc<StreamStats>>,
+) -> Result<JoinHandle<()>> {
+    let bind: SocketAddr = config.web.bind.parse()
+        .context(format!("web.bind '{}' is not an address and port", config.web.bind))?;
+    let listener = TcpListener::bind(bind).await
+        .context(format!("Failed to listen on {}", bind))?;
+    info!("Web snapshot listening on http://{}", bind);
+
+    let state = Arc::new(WebState {
+        store,
+        alert_history,
+        weights: config.scoring.attacker.clone(),
+        stream,
+        auth_token: config.web.auth_token.clone().unwrap_or_default(),
+    });
+
+    Ok(tokio::spawn(serve(listener, state)))
+}
+
+/// Answer each connection to `listener` on a task of its own
+async fn serve(listener: TcpListener, state: Arc<WebState>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve_connection(stream, &state).await {
+                        debug!("Web request from {} failed: {:#}", peer, e);
+                    }
+                });
+            }
+            Err(e) => warn!("Web server failed to accept a connection: {}", e),
+        }
+    }
+}
+
+/// Read one request from `stream`, answer it and close the connection
+async fn serve_connection(mut stream: TcpStream, state: &WebState) -> Result<()> {
+    let response = match tokio::time::timeout(REQUEST_TIMEOUT, read_head(&mut stream)).await {
+        Ok(Ok(Some(head))) => match parse_request(&head) {
+            Some(request) => respond(&request, state),
+            None => Response::error(400, "Bad Request", "malformed request"),
+        },
+        Ok(Ok(None)) => Response::error(431, "Request Header Fields Too Large", "request too large"),
+        Ok(Err(e)) => return Err(e),
+        Err(_) => Response::error(408, "Request Timeout", "request timed out"),
+    };
+
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.reason,
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(response.body.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Bytes up to the blank line ending the request head; `None` if it is too long
+async fn read_head(stream: &mut TcpStream) -> Result<Option<String>> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST_BYTES {
+            return Ok(None);
+        }
+        let read = stream.read(&mut buf).await?;
+        if read == 0 {
+            anyhow::bail!("connection closed mid-request");
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+    Ok(Some(String::from_utf8_lossy(&head).into_owned()))
+}
+
+/// Method, path, query and bearer token of a request head
+fn parse_request(head: &str) -> Option<Request> {
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let target = request_line.next()?;
+    request_line.next().filter(|version| version.starts_with("HTTP/"))?;
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query.split('&')
+        .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+        .filter(|(name, _)| !name.is_empty())
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+
+    let bearer = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("authorization"))
+        .and_then(|(_, value)| value.trim().strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string());
+
+    Some(Request { method, path: path.to_string(), query, bearer })
+}
+
+/// Route `request`
+fn respond(request: &Request, state: &WebState) -> Response {
+    if request.method != "GET" {
+        return Response::error(405, "Method Not Allowed", "only GET is supported");
+    }
+    if request.path == "/" {
+        return Response { status: 200, reason: "OK", content_type: "text/html", body: INDEX_HTML.to_string() };
+    }
+
+    let authorized = request.bearer.as_deref()
+        .map_or(false, |token| constant_time_eq(token.as_bytes(), state.auth_token.as_bytes()));
+    if !authorized {
+        return Response::error(401, "Unauthorized", "missing or wrong bearer token");
+    }
+
+    let limit = |default: usize, max: usize| {
+        request.query.get("limit").and_then(|limit| limit.parse::<usize>().ok()).unwrap_or(default).min(max)
+    };
+    match request.path.as_str() {
+        "/summary" => Response::json(summary(state)),
+        "/sessions" => {
+            let min_risk = request.query.get("min_risk").and_then(|risk| risk.parse::<u8>().ok()).unwrap_or(0);
+            Response::json(sessions(state, limit(DEFAULT_SESSIONS, MAX_SESSIONS), min_risk))
+        }
+        "/alerts" => Response::json(alerts(state)),
+        "/top/attackers" => Response::json(top_attackers(state, limit(DEFAULT_ATTACKERS, MAX_ATTACKERS))),
+        _ => Response::error(404, "Not Found", "no such endpoint"),
+    }
+}
+
+/// Headline counts, as in the dashboard summary, and the parse and event stream counters
+fn summary(state: &WebState) -> Value {
+    let store = state.store.snapshot();
+    let now = Utc::now();
+    let anomalies: Vec<Value> = store.get_anomalies(&now).iter()
+        .map(|anomaly| json!({
+            "metric": anomaly.metric.label(),
+            "current": anomaly.current,
+            "ratio": anomaly.ratio(),
+            "severe": anomaly.severe,
+        }))
+        .collect();
+    let total = store.get_total_parse_stats();
+    let sources: serde_json::Map<String, Value> = store.get_parse_stats().iter()
+        .map(|(source, stats)| (source.clone(), json!({ "parsed": stats.parsed, "failed": stats.failed })))
+        .collect();
+
+    json!({
+        "generated_at": now,
+        "sessions": store.get_session_count(),
+        "active_sessions": store.get_active_sessions().len(),
+        "log_entries": store.get_log_entry_count(),
+        "unique_ips": store.get_unique_source_ips().len(),
+        "unique_usernames": store.get_unique_usernames().len(),
+        "unique_passwords": store.get_unique_passwords().len(),
+        "anomalies": anomalies,
+        "parse": {
+            "parsed": total.parsed,
+            "failed": total.failed,
+            "failure_rate": total.failure_rate(),
+            "sources": sources,
+        },
+        "stream": state.stream.as_ref().map(|stream| json!({
+            "clients": stream.clients(),
+            "sent": stream.sent(),
+            "dropped": stream.dropped(),
+        })),
+    })
+}
+
+/// Newest sessions scoring at least `min_risk`
+fn sessions(state: &WebState, limit: usize, min_risk: u8) -> Value {
+    let store = state.store.snapshot();
+    let sessions: Vec<Value> = store.get_sessions().into_iter()
+        .rev()
+        .filter(|session| session.malicious_score >= min_risk)
+        .take(limit)
+        .map(session_json)
+        .collect();
+    json!({ "sessions": sessions })
+}
+
+/// What the sessions endpoint shows of a session; passwords are left out
+fn session_json(session: &Session) -> Value {
+    json!({
+        "id": session.display_id(),
+        "src_ip": session.src_ip.as_str(),
+        "start_time": session.start_time,
+        "end_time": session.end_time,
+        "protocol": session.protocol,
+        "username": session.user.as_ref().map(|user| user.username.as_str()),
+        "login_success": session.user.as_ref().map_or(false, |user| user.login_success),
+        "commands": session.commands.len(),
+        "files": session.files.len(),
+        "risk": session.malicious_score,
+        "tags": session.tags,
+        "country": session.geo_location.as_ref().map(|geo| geo.country_code.as_str()),
+    })
+}
+
+/// Alerts in the panel, newest first
+fn alerts(state: &WebState) -> Value {
+    let history = state.alert_history.lock();
+    let alerts: Vec<Value> = history.alerts().iter()
+        .rev()
+        .take(MAX_ALERTS)
+        .map(|alert| json!({
+            "timestamp": alert.timestamp,
+            "severity": alert.severity,
+            "kind": alert.alert_type.kind(),
+            "message": alert.message,
+            "count": alert.count,
+            "last_seen": alert.last_seen,
+            "acknowledged": alert.acknowledged,
+        }))
+        .collect();
+    json!({ "alerts": alerts })
+}
+
+/// Top attackers by composite score
+fn top_attackers(state: &WebState, limit: usize) -> Value {
+    let store = state.store.snapshot();
+    let mut profiles = attacker_profiles(&store);
+    rank_attackers(&mut profiles, &state.weights);
+
+    let attackers: Vec<Value> = profiles.iter()
+        .take(limit)
+        .map(|profile| json!({
+            "ip": profile.ip,
+            "score": profile.score(&state.weights),
+            "sessions": profile.sessions,
+            "successful_logins": profile.successful_logins,
+            "max_risk": profile.max_risk,
+            "files": profile.files,
+            "malware": profile.malware,
+            "credentials": profile.credentials,
+        }))
+        .collect();
+    json!({ "attackers": attackers })
+}
+
+/// Compare tokens without stopping at the first difference, so response
+/// times don't reveal how much of a guess was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::settings::WebConfig;
+    use crate::core::{Alert, AlertType, Severity};
+    use crate::data::testing::{at, command, login, scan, session};
+    use crate::data::{Store, WhitelistFilter};
+    use pretty_assertions::assert_eq;
+
+    const TOKEN: &str = "s3cret";
+
+    /// A server on a free port answering from `store` and `alerts`
+    async fn start(store: Store, alerts: Vec<Alert>) -> SocketAddr {
+        let config = Config::default();
+        let (store, _writer) = StoreHandle::spawn(store, WhitelistFilter::from_config(&config), None, Duration::from_secs(60));
+        let mut history = AlertHistory::default();
+        for alert in alerts {
+            history.push(alert);
+        }
+        let state = Arc::new(WebState {
+            store,
+            alert_history: Arc::new(parking_lot::Mutex::new(history)),
+            weights: config.scoring.attacker.clone(),
+            stream: Some(Arc::new(StreamStats::default())),
+            auth_token: TOKEN.to_string(),
+        });
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, state));
+        address
+    }
+
+    /// Status and body of `GET path`, sending `token` if given
+    async fn get(address: SocketAddr, path: &str, token: Option<&str>) -> (u16, String) {
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        let authorization = token.map_or(String::new(), |token| format!("Authorization: Bearer {}\r\n", token));
+        let request = format!("GET {} HTTP/1.1\r\nHost: xkippo\r\n{}\r\n", path, authorization);
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, body.to_string())
+    }
+
+    /// Body of an authorized `GET path`, which must succeed
+    async fn get_json(address: SocketAddr, path: &str) -> Value {
+        let (status, body) = get(address, path, Some(TOKEN)).await;
+        assert_eq!(status, 200, "{}", body);
+        serde_json::from_str(&body).unwrap()
+    }
+
+    fn alert(message: &str, seconds: i64) -> Alert {
+        Alert {
+            alert_type: AlertType::NewSourceIp { ip: "203.0.113.7".to_string() },
+            timestamp: at(seconds),
+            acknowledged: false,
+            message: message.to_string(),
+            severity: Severity::Low,
+            techniques: Vec::new(),
+            count: 1,
+            last_seen: None,
+        }
+    }
+
+    /// Two sessions from one attacker, one of them logged in and risky, a scan
+    /// from another, and a command from each
+    fn store() -> Store {
+        let mut store = Store::new(&Config::default()).unwrap();
+        let mut risky = session("aaaa", "203.0.113.7", at(0));
+        risky.user = Some(login("root", at(1)));
+        risky.malicious_score = 80;
+        risky.tags = vec!["miner".to_string()];
+        store.add_session(risky).unwrap();
+        store.add_session(scan("bbbb", "203.0.113.7", at(10), 1)).unwrap();
+        store.add_session(scan("cccc", "198.51.100.9", at(20), 1)).unwrap();
+        store.add_log_entry(command("aaaa", "203.0.113.7", "uname -a", at(2))).unwrap();
+        store.add_log_entry(command("cccc", "198.51.100.9", "id", at(20))).unwrap();
+        for _ in 0..3 {
+            store.record_parsed("cowrie.json");
+        }
+        store.record_parse_failure("cowrie.json", "{\"eventid\":", "EOF while parsing an object");
+        store
+    }
+
+    #[tokio::test]
+    async fn json_needs_the_token_and_the_page_does_not() {
+        let address = start(store(), Vec::new()).await;
+
+        for token in [None, Some("wrong"), Some("s3cre")] {
+            let (status, body) = get(address, "/summary", token).await;
+            assert_eq!(status, 401);
+            assert_eq!(serde_json::from_str::<Value>(&body).unwrap(), json!({ "error": "missing or wrong bearer token" }));
+        }
+        assert_eq!(get(address, "/", None).await.0, 200);
+        assert_eq!(get(address, "/nothing", Some(TOKEN)).await.0, 404);
+    }
+
+    #[tokio::test]
+    async fn summary_counts_the_snapshot() {
+        let address = start(store(), Vec::new()).await;
+        let summary = get_json(address, "/summary").await;
+
+        assert_eq!(summary["sessions"], 3);
+        assert_eq!(summary["active_sessions"], 1);
+        assert_eq!(summary["log_entries"], 2);
+        assert_eq!(summary["unique_ips"], 2);
+        assert_eq!(summary["anomalies"], json!([]));
+        assert_eq!(summary["parse"], json!({
+            "parsed": 3,
+            "failed": 1,
+            "failure_rate": 0.25,
+            "sources": { "cowrie.json": { "parsed": 3, "failed": 1 } },
+        }));
+        assert_eq!(summary["stream"], json!({ "clients": 0, "sent": 0, "dropped": 0 }));
+    }
+
+    #[tokio::test]
+    async fn sessions_are_newest_first_and_filtered() {
+        let address = start(store(), Vec::new()).await;
+
+        let ids = |sessions: &Value| -> Vec<String> {
+            sessions["sessions"].as_array().unwrap().iter()
+                .map(|session| session["id"].as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(ids(&get_json(address, "/sessions").await), ["cccc", "bbbb", "aaaa"]);
+        assert_eq!(ids(&get_json(address, "/sessions?limit=1").await), ["cccc"]);
+
+        let risky = get_json(address, "/sessions?min_risk=50").await;
+        assert_eq!(risky["sessions"][0], json!({
+            "id": "aaaa",
+            "src_ip": "203.0.113.7",
+            "start_time": at(0),
+            "end_time": null,
+            "protocol": "ssh",
+            "username": "root",
+            "login_success": true,
+            "commands": 0,
+            "files": 0,
+            "risk": 80,
+            "tags": ["miner"],
+            "country": null,
+        }));
+    }
+
+    #[tokio::test]
+    async fn alerts_are_newest_first() {
+        let address = start(store(), vec![alert("first", 0), alert("second", 60)]).await;
+        let alerts = get_json(address, "/alerts").await;
+
+        assert_eq!(alerts["alerts"][0], json!({
+            "timestamp": at(60),
+            "severity": "low",
+            "kind": "new_source_ip",
+            "message": "second",
+            "count": 1,
+            "last_seen": null,
+            "acknowledged": false,
+        }));
+        assert_eq!(alerts["alerts"][1]["message"], "first");
+    }
+
+    #[tokio::test]
+    async fn attackers_are_ranked() {
+        let address = start(store(), Vec::new()).await;
+        let attackers = get_json(address, "/top/attackers?limit=5").await;
+
+        let attackers = attackers["attackers"].as_array().unwrap();
+        assert_eq!(attackers.len(), 2);
+        assert_eq!(attackers[0]["ip"], "203.0.113.7");
+        assert_eq!(attackers[0]["sessions"], 2);
+        assert_eq!(attackers[0]["successful_logins"], 1);
+        assert_eq!(attackers[1]["ip"], "198.51.100.9");
+    }
+
+    #[test]
+    fn the_token_is_trimmed_once_when_loaded() {
+        let web: WebConfig = toml::from_str("enabled = true\nauth_token = \"  s3cret\\n\"\n").unwrap();
+        assert_eq!(web.auth_token.as_deref(), Some(TOKEN));
+
+        let web: WebConfig = toml::from_str("enabled = true\nauth_token = \"   \"\n").unwrap();
+        let blank = Config { web, ..Config::default() };
+        let problems = blank.validate().unwrap_err().to_string();
+        assert!(problems.contains("web.auth_token must be set"), "{}", problems);
+    }
+}
@@ -0,0 +1,94 @@
+//! MITRE ATT&CK techniques observed in sessions
+//!
+//! Which detections map to which techniques is data, not code: the table is
+//! `attack.toml` next to this file, compiled in. Sessions carry the IDs of
+//! the techniques they were seen using (see [`session_techniques`]), which the
+//! details view, the ATT&CK summary panel, alerts and ECS exports pick up.
+
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+use crate::data::{FileTransferDirection, Session};
+
+/// The embedded mapping table
+const MAPPING: &str = include_str!("attack.toml");
+
+/// One ATT&CK technique and the detections that observe it
+#[derive(Debug, Clone, Deserialize)]
+pub struct Technique {
+    /// Technique ID, e.g. "T1059.004"
+    pub id: String,
+    /// Technique name
+    pub name: String,
+    /// Tactic it is listed under
+    pub tactic: String,
+    /// Session tags and events that mean the technique was used
+    #[serde(default)]
+    pub detections: Vec<String>,
+}
+
+/// Techniques and the order their tactics are listed in
+#[derive(Debug, Clone, Deserialize)]
+pub struct AttackMapping {
+    /// Tactics in kill-chain order
+    #[serde(default)]
+    pub tactics: Vec<String>,
+    /// Every mapped technique, in table order
+    #[serde(default, rename = "technique")]
+    pub techniques: Vec<Technique>,
+}
+
+impl AttackMapping {
+    /// Technique with ID `id`
+    pub fn technique(&self, id: &str) -> Option<&Technique> {
+        self.techniques.iter().find(|technique| technique.id == id)
+    }
+
+    /// IDs of the techniques any of `detections` observe, in table order
+    pub fn ids_for<'a, I>(&self, detections: I) -> Vec<String>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let detections = detections.into_iter().collect::<Vec<_>>();
+        self.techniques.iter()
+            .filter(|technique| technique.detections.iter().any(|d| detections.contains(&d.as_str())))
+            .map(|technique| technique.id.clone())
+            .collect()
+    }
+
+    /// Position of `tactic` in [`tactics`](Self::tactics); unlisted tactics sort last
+    pub fn tactic_rank(&self, tactic: &str) -> usize {
+        self.tactics.iter().position(|t| t == tactic).unwrap_or(self.tactics.len())
+    }
+}
+
+/// The mapping table, parsed on first use
+pub fn attack_mapping() -> &'static AttackMapping {
+    static MAPPING_TABLE: OnceLock<AttackMapping> = OnceLock::new();
+    MAPPING_TABLE.get_or_init(|| toml::from_str(MAPPING).expect("attack.toml is valid"))
+}
+
+/// Tags and events of `session` that techniques can be mapped from
+pub fn session_detections(session: &Session) -> Vec<&str> {
+    let mut detections = session.tags.iter().map(String::as_str).collect::<Vec<_>>();
+    match &session.user {
+        Some(user) if user.login_success => detections.push("login-success"),
+        Some(_) => detections.push("login-failed"),
+        None => {}
+    }
+    for file in &session.files {
+        detections.push(match file.direction {
+            FileTransferDirection::Upload => "file-upload",
+            FileTransferDirection::Download => "file-download",
+        });
+    }
+    if !session.forwards.is_empty() {
+        detections.push("tcp-forward");
+    }
+    detections
+}
+
+/// IDs of the ATT&CK techniques `session` was seen using
+pub fn session_techniques(session: &Session) -> Vec<String> {
+    attack_mapping().ids_for(session_detections(session))
+}
@@ -0,0 +1,281 @@
+//! Per-IP attacker profiles and the composite score Top Attackers is ranked by
+//!
+//! Ranking by session count alone puts noisy scanners first; the score also
+//! weighs successful logins, the riskiest session, captured files and the
+//! variety of credentials tried (see [`AttackerWeights`]).
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use crate::config::AttackerWeights;
+use crate::data::{EventType, StoreSnapshot};
+
+/// What one source IP has done, as ranked in Top Attackers
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AttackerProfile {
+    /// Source IP
+    pub ip: String,
+    /// Sessions from the IP
+    pub sessions: usize,
+    /// Sessions that logged in
+    pub successful_logins: usize,
+    /// Highest session risk score
+    pub max_risk: u8,
+    /// Files uploaded or downloaded into the honeypot
+    pub files: usize,
+    /// Whether any of those files is known or detected malware
+    pub malware: bool,
+    /// Distinct username/password pairs tried
+    pub credentials: usize,
+}
+
+impl AttackerProfile {
+    /// Weighted score; counts are dampened as `ln(1 + n)`
+    pub fn score(&self, weights: &AttackerWeights) -> f64 {
+        let dampened = |count: usize| (count as f64).ln_1p();
+
+        weights.sessions * dampened(self.sessions)
+            + weights.successful_logins * dampened(self.successful_logins)
+            + weights.max_risk * self.max_risk as f64
+            + weights.files * dampened(self.files)
+            + weights.credentials * dampened(self.credentials)
+    }
+
+    /// Short markers for the components that make the IP stand out, e.g. "↑login ⚠malware"
+    pub fn badges(&self, risky_from: u8) -> Vec<&'static str> {
+        let mut badges = Vec::new();
+        if self.successful_logins > 0 {
+            badges.push("↑login");
+        }
+        if self.malware {
+            badges.push("⚠malware");
+        } else if self.files > 0 {
+            badges.push("⇩file");
+        }
+        if self.max_risk >= risky_from {
+            badges.push("!risk");
+        }
+        badges
+    }
+}
+
+/// Normalized username and password of one login attempt
+type Credential<'a> = (Cow<'a, str>, Cow<'a, str>);
+
+/// Profile every source IP in `store`
+pub fn attacker_profiles(store: &StoreSnapshot) -> Vec<AttackerProfile> {
+    let mut profiles: HashMap<&str, AttackerProfile> = HashMap::new();
+    for session in store.get_sessions() {
+        let profile = profiles.entry(&session.src_ip).or_default();
+        profile.sessions += 1;
+        if session.user.as_ref().map_or(false, |user| user.login_success) {
+            profile.successful_logins += 1;
+        }
+        profile.max_risk = profile.max_risk.max(session.malicious_score);
+        profile.files += session.files.len();
+        profile.malware |= session.files.iter().any(|file| file.is_malware || file.hash_list.is_some());
+    }
+    // Scans folded out of the sessions still count (`filter.collapse_scans`)
+    for summary in store.get_scan_summaries() {
+        profiles.entry(&summary.src_ip).or_default().sessions += summary.sessions as usize;
+    }
+
+    // Sessions keep only their last login, so credentials come from the log entries
+    let normalizer = store.credential_normalizer();
+    let mut credentials: HashMap<&str, HashSet<Credential>> = HashMap::new();
+    for entry in store.get_log_entries() {
+        let login = matches!(
+            entry.event_type,
+            EventType::LoginAttempt | EventType::LoginSuccess | EventType::LoginFailed
+        );
+        if !login {
+            continue;
+        }
+        let src_ip = entry.src_ip.as_deref().or_else(|| {
+            store.get_session_for_entry(entry).map(|session| &*session.src_ip)
+        });
+        if let (Some(src_ip), Some(username), Some(password)) = (src_ip, &entry.username, &entry.password) {
+            let pair = (normalizer.username(username), normalizer.password(password));
+            credentials.entry(src_ip).or_default().insert(pair);
+        }
+    }
+
+    profiles.into_iter()
+        .map(|(ip, mut profile)| {
+            profile.ip = ip.to_string();
+            profile.credentials = credentials.get(ip).map_or(0, HashSet::len);
+            profile
+        })
+        .collect()
+}
+
+/// Sort `profiles` by score, highest first; ties go to the busier IP, then by address
+pub fn rank_attackers(profiles: &mut [AttackerProfile], weights: &AttackerWeights) {
+    profiles.sort_by(|a, b| {
+        b.score(weights)
+            .partial_cmp(&a.score(weights))
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| b.sessions.cmp(&a.sessions))
+            .then_with(|| a.ip.cmp(&b.ip))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::data::store_writer::{apply, StoreCommand};
+    use crate::data::testing::{at, connect, login, scan, session};
+    use crate::data::{FileTransfer, FileTransferDirection, LogEntry, Store};
+    use pretty_assertions::assert_eq;
+
+    fn profile(ip: &str, sessions: usize, successful_logins: usize, max_risk: u8, files: usize, credentials: usize) -> AttackerProfile {
+        AttackerProfile {
+            ip: ip.to_string(),
+            sessions,
+            successful_logins,
+            max_risk,
+            files,
+            malware: files > 0,
+            credentials,
+        }
+    }
+
+    /// A noisy scanner, a brute-forcer that got in, one intruder dropping malware and a quiet prober
+    fn attackers() -> Vec<AttackerProfile> {
+        vec![
+            profile("198.51.100.1", 5000, 0, 20, 0, 200),
+            profile("198.51.100.2", 300, 1, 40, 0, 300),
+            profile("198.51.100.3", 1, 1, 95, 2, 1),
+            profile("198.51.100.4", 2, 0, 0, 0, 2),
+        ]
+    }
+
+    fn ranked(mut profiles: Vec<AttackerProfile>, weights: &AttackerWeights) -> Vec<String> {
+        rank_attackers(&mut profiles, weights);
+        profiles.into_iter().map(|profile| profile.ip).collect()
+    }
+
+    fn failed_login(logged_id: &str, src_ip: Option<&str>, username: &str, password: &str, second: i64) -> LogEntry {
+        let mut entry = LogEntry {
+            event_type: EventType::LoginFailed,
+            eventid: "cowrie.login.failed".to_string(),
+            username: Some(username.into()),
+            password: Some(password.to_string()),
+            ..connect(logged_id, src_ip.unwrap_or("0.0.0.0"), at(second))
+        };
+        entry.src_ip = src_ip.map(Into::into);
+        entry
+    }
+
+    #[test]
+    fn the_intruder_outranks_the_noisy_scanner() {
+        assert_eq!(ranked(attackers(), &AttackerWeights::default()), [
+            "198.51.100.3",
+            "198.51.100.2",
+            "198.51.100.1",
+            "198.51.100.4",
+        ]);
+    }
+
+    #[test]
+    fn the_weights_decide_the_order() {
+        let volume = AttackerWeights { sessions: 1.0, successful_logins: 0.0, max_risk: 0.0, files: 0.0, credentials: 0.0 };
+        assert_eq!(ranked(attackers(), &volume), ["198.51.100.1", "198.51.100.2", "198.51.100.4", "198.51.100.3"]);
+
+        let variety = AttackerWeights { sessions: 0.0, credentials: 1.0, ..volume };
+        assert_eq!(ranked(attackers(), &variety), ["198.51.100.2", "198.51.100.1", "198.51.100.4", "198.51.100.3"]);
+    }
+
+    #[test]
+    fn ties_go_to_the_busier_ip_then_the_address() {
+        let nothing = AttackerWeights { sessions: 0.0, successful_logins: 0.0, max_risk: 0.0, files: 0.0, credentials: 0.0 };
+        let profiles = vec![
+            profile("203.0.113.9", 1, 0, 0, 0, 0),
+            profile("203.0.113.10", 3, 0, 0, 0, 0),
+            profile("203.0.113.1", 1, 0, 0, 0, 0),
+        ];
+
+        assert_eq!(ranked(profiles, &nothing), ["203.0.113.10", "203.0.113.1", "203.0.113.9"]);
+    }
+
+    #[test]
+    fn counts_are_dampened() {
+        let weights = AttackerWeights::default();
+        let one = profile("a", 1, 0, 0, 0, 0).score(&weights);
+        let thousand = profile("a", 1000, 0, 0, 0, 0).score(&weights);
+
+        assert!((one - 10.0 * 2f64.ln()).abs() < 1e-9, "{}", one);
+        assert!(thousand < 10.0 * one, "{} against {}", thousand, one);
+        assert_eq!(profile("a", 0, 0, 0, 0, 0).score(&weights), 0.0);
+    }
+
+    #[test]
+    fn badges_name_what_stands_out() {
+        assert_eq!(profile("a", 1, 1, 95, 2, 1).badges(70), ["↑login", "⚠malware", "!risk"]);
+        let unflagged = AttackerProfile { malware: false, ..profile("a", 1, 0, 10, 1, 0) };
+        assert_eq!(unflagged.badges(70), ["⇩file"]);
+        assert!(profile("a", 5000, 0, 20, 0, 200).badges(70).is_empty());
+    }
+
+    #[test]
+    fn profiles_gather_sessions_scans_files_and_credentials() {
+        let mut store = Store::new(&Config::default()).unwrap();
+        let mut intruder = session("a1", "198.51.100.3", at(0));
+        intruder.user = Some(login("root", at(5)));
+        intruder.malicious_score = 95;
+        intruder.files.push(FileTransfer {
+            filename: "x86".to_string(),
+            local_path: None,
+            size: None,
+            shasum: Some("4e07408562bedb8b60ce05c1decfe3ad16b72230967de01f640b7e4729b49fce".to_string()),
+            timestamp: at(10),
+            direction: FileTransferDirection::Download,
+            mime_type: None,
+            is_executable: true,
+            is_malware: false,
+            hash_list: Some("bazaar".to_string()),
+        });
+        let mut probe = session("a2", "198.51.100.3", at(100));
+        probe.malicious_score = 30;
+        for session in [intruder, probe, scan("b1", "198.51.100.1", at(0), 1), scan("b2", "198.51.100.1", at(1), 1)] {
+            apply(&mut store, StoreCommand::UpsertSession(Box::new(session)));
+        }
+        apply(&mut store, StoreCommand::CollapseScans(at(60)));
+        apply(&mut store, StoreCommand::AddLogEntries {
+            source: "cowrie.json".to_string(),
+            entries: vec![
+                failed_login("a1", Some("198.51.100.3"), "root", "admin", 1),
+                failed_login("a1", Some("198.51.100.3"), "root", "admin", 2),
+                // Without an IP of its own, the entry counts for its session's
+                failed_login("a1", None, "root", "toor", 3),
+                failed_login("b1", Some("198.51.100.1"), "admin", "admin", 0),
+            ],
+        });
+
+        let mut profiles = attacker_profiles(&store);
+        profiles.sort_by(|a, b| a.ip.cmp(&b.ip));
+        assert_eq!(profiles, [
+            AttackerProfile { ip: "198.51.100.1".to_string(), sessions: 2, credentials: 1, ..AttackerProfile::default() },
+            AttackerProfile {
+                ip: "198.51.100.3".to_string(),
+                sessions: 2,
+                successful_logins: 1,
+                max_risk: 95,
+                files: 1,
+                malware: true,
+                credentials: 2,
+            },
+        ]);
+    }
+
+    #[test]
+    fn weights_must_not_be_negative() {
+        let mut config = Config::default();
+        config.scoring.attacker.files = -1.0;
+
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("scoring.attacker.files must be zero or more"), "{}", error);
+    }
+}
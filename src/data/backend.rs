@@ -0,0 +1,80 @@
+//! Storage behind the in-memory store
+//!
+//! The [`Store`](crate::data::Store) only ever holds the window the views read
+//! (`filter.max_logs` entries, `filter.max_sessions` sessions). A backend
+//! keeps everything the store writer applies, so captured data outlives that
+//! window and the process; on startup the newest of it refills the store.
+//! Views that look further back than the window read it through [`History`].
+
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::data::models::{LogEntry, Session};
+use crate::data::store_writer::StoreCommand;
+
+/// How much a backend holds in total
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StoredCounts {
+    /// Sessions stored
+    pub sessions: usize,
+    /// Log entries stored
+    pub log_entries: usize,
+}
+
+/// Queries over everything a backend holds, past the in-memory window
+pub trait History: Send + Sync {
+    /// Stored sessions from `src_ip`, oldest first
+    fn sessions_by_source_ip(&self, src_ip: &str) -> Result<Vec<Session>>;
+
+    /// Stored sessions the honeypot logged as `logged_id`, oldest first
+    fn sessions_by_logged_id(&self, logged_id: &str) -> Result<Vec<Session>>;
+
+    /// Stored log entries logged under `logged_id`, in the order they were written
+    fn log_entries_by_logged_id(&self, logged_id: &str) -> Result<Vec<LogEntry>>;
+}
+
+/// Persistent storage the store writer writes through to
+///
+/// Writes happen on a blocking thread of their own, never on the runtime.
+pub trait StorageBackend: Send {
+    /// Name for messages and the settings tab
+    fn name(&self) -> &'static str;
+
+    /// Persist a command the writer applied
+    fn write(&mut self, command: &StoreCommand) -> Result<()>;
+
+    /// Commands that bring back the newest stored data, oldest first
+    fn restore(&mut self) -> Result<Vec<StoreCommand>>;
+
+    /// What is stored in total, including what the store has pruned
+    fn counts(&self) -> StoredCounts;
+
+    /// Queries on the stored data, usable while the writer keeps writing
+    fn history(&self) -> Result<Box<dyn History>>;
+}
+
+/// Open the backend `storage.backend` names; `None` keeps data in memory only
+pub fn open(config: &Config) -> Result<Option<Box<dyn StorageBackend>>> {
+    match config.storage.backend.as_str() {
+        #[cfg(feature = "sqlite-db")]
+        "sqlite" => {
+            let path = database_path(config)?;
+            let backend = crate::data::sqlite::SqliteBackend::open(&path, config.filter.max_logs, config.filter.max_sessions)?;
+            Ok(Some(Box::new(backend)))
+        }
+        #[cfg(not(feature = "sqlite-db"))]
+        "sqlite" => anyhow::bail!("storage.backend = \"sqlite\" needs a build with the sqlite-db feature"),
+        _ => Ok(None),
+    }
+}
+
+/// Where the SQLite database is: `storage.path`, or `xkippo.db` in the data directory
+#[cfg(feature = "sqlite-db")]
+pub fn database_path(config: &Config) -> Result<std::path::PathBuf> {
+    match &config.storage.path {
+        Some(path) => Ok(std::path::PathBuf::from(path)),
+        None => dirs::data_dir()
+            .map(|dir| dir.join("xkippo").join(crate::data::sqlite::DATABASE_FILE))
+            .ok_or_else(|| anyhow::anyhow!("No data directory for the SQLite database; set storage.path")),
+    }
+}
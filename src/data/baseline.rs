@@ -0,0 +1,543 @@
+//! Rolling per-hour baselines for anomaly detection
+//!
+//! Each hour's new sessions, failed logins and distinct source IPs are kept
+//! for `anomaly.baseline_days`. The hour in progress is compared with the
+//! same hour of day on each of those days: once it goes past their mean plus
+//! `anomaly.sigma` standard deviations, it is an [`Anomaly`]. Closed hours are
+//! handed to the store writer, which persists them, so with the SQLite
+//! backend the baselines survive a restart.
+
+use chrono::{DateTime, TimeZone, Utc};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::config::AnomalyConfig;
+use crate::data::IStr;
+
+/// Earlier days with totals for the hour needed before it is judged at all
+const MIN_SAMPLES: usize = 3;
+
+/// Floor on the standard deviation, so a perfectly steady history doesn't
+/// flag the first extra event
+const MIN_STDDEV: f64 = 1.0;
+
+/// Rates an hour is compared with its baseline on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnomalyMetric {
+    /// New sessions
+    Sessions,
+    /// Failed logins
+    LoginFailures,
+    /// Distinct source IPs
+    UniqueIps,
+}
+
+impl AnomalyMetric {
+    /// Every metric, in the order they are checked
+    pub const ALL: [AnomalyMetric; 3] = [AnomalyMetric::Sessions, AnomalyMetric::LoginFailures, AnomalyMetric::UniqueIps];
+
+    /// Name for alerts and the dashboard
+    pub fn label(self) -> &'static str {
+        match self {
+            AnomalyMetric::Sessions => "Session rate",
+            AnomalyMetric::LoginFailures => "Login failure rate",
+            AnomalyMetric::UniqueIps => "Distinct IP rate",
+        }
+    }
+}
+
+/// Counts for one closed hour, as persisted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HourTotals {
+    /// Hours since the epoch
+    pub hour: i64,
+    /// New sessions
+    pub sessions: u64,
+    /// Failed logins
+    pub login_failures: u64,
+    /// Distinct source IPs
+    pub unique_ips: u64,
+}
+
+impl HourTotals {
+    /// Count for `metric`
+    pub fn get(&self, metric: AnomalyMetric) -> u64 {
+        match metric {
+            AnomalyMetric::Sessions => self.sessions,
+            AnomalyMetric::LoginFailures => self.login_failures,
+            AnomalyMetric::UniqueIps => self.unique_ips,
+        }
+    }
+
+    /// Count one more for `metric`
+    fn add(&mut self, metric: AnomalyMetric) {
+        match metric {
+            AnomalyMetric::Sessions => self.sessions += 1,
+            AnomalyMetric::LoginFailures => self.login_failures += 1,
+            AnomalyMetric::UniqueIps => self.unique_ips += 1,
+        }
+    }
+
+    /// Keep the larger of each count
+    ///
+    /// A replay on startup only brings back the newest entries, so its
+    /// counts for an hour never exceed what was persisted for it.
+    fn merge(&mut self, other: &HourTotals) {
+        self.sessions = self.sessions.max(other.sessions);
+        self.login_failures = self.login_failures.max(other.login_failures);
+        self.unique_ips = self.unique_ips.max(other.unique_ips);
+    }
+}
+
+/// Counts for the hour in progress
+#[derive(Debug, Clone)]
+struct CurrentHour {
+    /// Sessions and failed logins so far; IPs are counted from `ips`
+    counts: HourTotals,
+    /// Source IPs seen
+    ips: HashSet<IStr>,
+}
+
+impl CurrentHour {
+    /// Counts so far
+    fn totals(&self) -> HourTotals {
+        HourTotals { unique_ips: self.ips.len() as u64, ..self.counts }
+    }
+}
+
+/// A rate running above its baseline in the hour in progress
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Anomaly {
+    /// Which rate
+    pub metric: AnomalyMetric,
+    /// Start of the hour
+    pub hour: DateTime<Utc>,
+    /// Count so far this hour
+    pub current: u64,
+    /// Mean for this hour of day over the baseline days
+    pub mean: f64,
+    /// Past twice the usual margin: mean plus 2 × `sigma` standard deviations
+    pub severe: bool,
+}
+
+impl Anomaly {
+    /// How many times the usual count this is
+    pub fn ratio(&self) -> f64 {
+        self.current as f64 / self.mean.max(1.0)
+    }
+}
+
+/// Per-hour totals over `anomaly.baseline_days`, and the hour in progress
+#[derive(Debug, Clone)]
+pub struct Baselines {
+    /// Whether anything is counted (`anomaly.enabled`)
+    enabled: bool,
+    /// Closed hours by hour since the epoch
+    closed: BTreeMap<i64, HourTotals>,
+    /// The newest hour seen
+    current: Option<CurrentHour>,
+    /// Hours closed or changed since the store writer last took them
+    unsaved: Vec<HourTotals>,
+    /// Days of history compared with
+    days: i64,
+    /// Standard deviations above the mean that count as anomalous
+    sigma: f64,
+    /// Smallest count flagged, however quiet the baseline
+    min_count: u64,
+}
+
+impl Baselines {
+    /// Empty baselines configured by `[anomaly]`
+    pub fn new(config: &AnomalyConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            closed: BTreeMap::new(),
+            current: None,
+            unsaved: Vec::new(),
+            days: config.baseline_days.max(1) as i64,
+            sigma: config.sigma,
+            min_count: config.min_count,
+        }
+    }
+
+    /// Count a session started at `timestamp`
+    pub fn record_session(&mut self, timestamp: &DateTime<Utc>) {
+        self.count(AnomalyMetric::Sessions, timestamp);
+    }
+
+    /// Count a failed login at `timestamp`
+    pub fn record_login_failure(&mut self, timestamp: &DateTime<Utc>) {
+        self.count(AnomalyMetric::LoginFailures, timestamp);
+    }
+
+    /// Note `ip` as seen at `timestamp`
+    ///
+    /// Only the hour in progress keeps its IPs, so a late sighting for a
+    /// closed hour isn't counted.
+    pub fn record_ip(&mut self, ip: &IStr, timestamp: &DateTime<Utc>) {
+        let hour = match self.advance(timestamp) {
+            Some(hour) => hour,
+            None => return,
+        };
+        if let Some(current) = self.current.as_mut().filter(|current| current.counts.hour == hour) {
+            if !current.ips.contains(ip) {
+                current.ips.insert(ip.clone());
+            }
+        }
+    }
+
+    /// Bring back totals persisted for a closed hour
+    pub fn restore(&mut self, totals: HourTotals) {
+        if !self.enabled {
+            return;
+        }
+        self.closed_hour(totals.hour).merge(&totals);
+        self.prune();
+    }
+
+    /// Hours closed or changed since the last call, to be persisted
+    pub fn take_unsaved(&mut self) -> Vec<HourTotals> {
+        std::mem::take(&mut self.unsaved)
+    }
+
+    /// Rates running above their baseline in the hour `now` falls in
+    ///
+    /// Each is compared with the same hour on every earlier day that has
+    /// totals for it; hours with none (nothing logged, or not running) are
+    /// left out rather than counted as zero, and a rate with fewer than
+    /// [`MIN_SAMPLES`] days behind it isn't judged.
+    pub fn anomalies(&self, now: &DateTime<Utc>) -> Vec<Anomaly> {
+        let current = match &self.current {
+            Some(current) if self.enabled && current.counts.hour == hour_of(now) => current.totals(),
+            _ => return Vec::new(),
+        };
+
+        let mut anomalies = Vec::new();
+        for metric in AnomalyMetric::ALL {
+            let samples: Vec<f64> = (1..=self.days)
+                .filter_map(|day| self.closed.get(&(current.hour - day * 24)))
+                .map(|totals| totals.get(metric) as f64)
+                .collect();
+            if samples.len() < MIN_SAMPLES {
+                continue;
+            }
+
+            let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+            let variance = samples.iter().map(|sample| (sample - mean).powi(2)).sum::<f64>() / samples.len() as f64;
+            let margin = self.sigma * variance.sqrt().max(MIN_STDDEV);
+
+            let count = current.get(metric);
+            if count >= self.min_count && count as f64 > mean + margin {
+                anomalies.push(Anomaly {
+                    metric,
+                    hour: Utc.timestamp_opt(current.hour * 3600, 0).single().unwrap_or(*now),
+                    current: count,
+                    mean,
+                    severe: count as f64 > mean + 2.0 * margin,
+                });
+            }
+        }
+        anomalies
+    }
+
+    /// Remove all counts
+    pub fn clear(&mut self) {
+        self.closed.clear();
+        self.current = None;
+        self.unsaved.clear();
+    }
+
+    /// Count one more for `metric` in the hour `timestamp` falls in
+    fn count(&mut self, metric: AnomalyMetric, timestamp: &DateTime<Utc>) {
+        let hour = match self.advance(timestamp) {
+            Some(hour) => hour,
+            None => return,
+        };
+        match self.current.as_mut().filter(|current| current.counts.hour == hour) {
+            Some(current) => current.counts.add(metric),
+            None => {
+                // A late event for a closed hour; persisted again with it counted
+                let closed = self.closed_hour(hour);
+                closed.add(metric);
+                let closed = *closed;
+                self.unsaved.push(closed);
+            }
+        }
+    }
+
+    /// Hour `timestamp` falls in, closing the hour in progress if it is newer
+    ///
+    /// `None` when disabled or older than the history kept.
+    fn advance(&mut self, timestamp: &DateTime<Utc>) -> Option<i64> {
+        if !self.enabled {
+            return None;
+        }
+
+        let hour = hour_of(timestamp);
+        let newest = self.current.as_ref().map_or(i64::MIN, |current| current.counts.hour);
+        if hour > newest {
+            if let Some(finished) = self.current.take() {
+                let totals = finished.totals();
+                let closed = self.closed_hour(totals.hour);
+                closed.merge(&totals);
+                let closed = *closed;
+                self.unsaved.push(closed);
+            }
+            self.current = Some(CurrentHour {
+                counts: HourTotals { hour, ..Default::default() },
+                ips: HashSet::new(),
+            });
+            self.prune();
+            return Some(hour);
+        }
+
+        (hour >= newest - self.days * 24).then_some(hour)
+    }
+
+    /// Totals kept for the closed `hour`, starting from zero
+    fn closed_hour(&mut self, hour: i64) -> &mut HourTotals {
+        self.closed.entry(hour).or_insert(HourTotals { hour, ..Default::default() })
+    }
+
+    /// Drop closed hours older than the baseline days
+    fn prune(&mut self) {
+        let newest = match (&self.current, self.closed.keys().next_back()) {
+            (Some(current), _) => current.counts.hour,
+            (None, Some(&hour)) => hour,
+            (None, None) => return,
+        };
+        self.closed = self.closed.split_off(&(newest - self.days * 24));
+    }
+}
+
+/// Hours since the epoch
+fn hour_of(timestamp: &DateTime<Utc>) -> i64 {
+    timestamp.timestamp().div_euclid(3600)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::testing::at;
+    use pretty_assertions::assert_eq;
+
+    /// Small seeded generator (xorshift64), so histories are noisy but repeatable
+    struct Noise(u64);
+
+    impl Noise {
+        /// `mean` give or take up to `spread`
+        fn around(&mut self, mean: u64, spread: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            mean - spread + self.0 % (2 * spread + 1)
+        }
+    }
+
+    /// A minute into the hour `hours` after the hour `at(0)` falls in
+    fn hour(hours: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt((hour_of(&at(0)) + hours) * 3600 + 60, 0).unwrap()
+    }
+
+    fn empty() -> Baselines {
+        Baselines::new(&AnomalyConfig::default())
+    }
+
+    /// Count `sessions` sessions, `failures` failed logins and `ips` distinct IPs in the hour of `time`
+    fn record(baselines: &mut Baselines, time: &DateTime<Utc>, sessions: u64, failures: u64, ips: u64) {
+        for _ in 0..sessions {
+            baselines.record_session(time);
+        }
+        for _ in 0..failures {
+            baselines.record_login_failure(time);
+        }
+        for n in 0..ips {
+            baselines.record_ip(&IStr::from(format!("10.0.{}.{}", n / 256, n % 256).as_str()), time);
+        }
+    }
+
+    /// The same hour of day on each of the 14 days before `hour(0)`, around 40 of each
+    fn noisy_history(baselines: &mut Baselines, seed: u64) {
+        let mut noise = Noise(seed);
+        for day in (1..=14).rev() {
+            let (sessions, failures, ips) = (noise.around(40, 5), noise.around(40, 5), noise.around(40, 5));
+            record(baselines, &hour(-day * 24), sessions, failures, ips);
+        }
+    }
+
+    fn metrics(anomalies: &[Anomaly]) -> Vec<AnomalyMetric> {
+        anomalies.iter().map(|anomaly| anomaly.metric).collect()
+    }
+
+    #[test]
+    fn a_usual_hour_is_not_an_anomaly() {
+        for seed in [1, 7, 42, 1234, 99_999] {
+            let mut baselines = empty();
+            noisy_history(&mut baselines, seed);
+            record(&mut baselines, &hour(0), 42, 38, 41);
+            assert!(baselines.anomalies(&hour(0)).is_empty(), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn a_burst_is_a_severe_anomaly() {
+        for seed in [1, 7, 42, 1234, 99_999] {
+            let mut baselines = empty();
+            noisy_history(&mut baselines, seed);
+            record(&mut baselines, &hour(0), 200, 40, 40);
+
+            let anomalies = baselines.anomalies(&hour(0));
+            assert_eq!(metrics(&anomalies), [AnomalyMetric::Sessions], "seed {}", seed);
+            let anomaly = anomalies[0];
+            assert_eq!((anomaly.current, anomaly.severe), (200, true));
+            assert_eq!(anomaly.hour.timestamp(), hour_of(&at(0)) * 3600);
+            assert!((35.0..=45.0).contains(&anomaly.mean), "mean {}", anomaly.mean);
+            assert!((4.4..=5.8).contains(&anomaly.ratio()), "ratio {}", anomaly.ratio());
+        }
+    }
+
+    #[test]
+    fn a_modest_rise_is_not_severe() {
+        let mut baselines = empty();
+        for day in (1..=14).rev() {
+            record(&mut baselines, &hour(-day * 24), 40 + day as u64 % 2 * 2, 0, 0);
+        }
+        // Mean 41, stddev 1, so past 44 is anomalous and past 47 severe
+        record(&mut baselines, &hour(0), 46, 0, 0);
+
+        let anomalies = baselines.anomalies(&hour(0));
+        assert_eq!(metrics(&anomalies), [AnomalyMetric::Sessions]);
+        assert!(!anomalies[0].severe);
+    }
+
+    #[test]
+    fn each_hour_of_day_has_its_own_baseline() {
+        let mut baselines = empty();
+        let mut noise = Noise(5);
+        for day in (1..=14).rev() {
+            record(&mut baselines, &hour(-day * 24 - 12), noise.around(10, 2), 0, 0);
+            record(&mut baselines, &hour(-day * 24), noise.around(100, 5), 0, 0);
+        }
+
+        // Busy for the quiet hour, usual for this one
+        record(&mut baselines, &hour(0), 100, 0, 0);
+        assert!(baselines.anomalies(&hour(0)).is_empty());
+    }
+
+    #[test]
+    fn fewer_than_three_days_are_not_judged() {
+        let mut baselines = empty();
+        record(&mut baselines, &hour(-48), 40, 40, 40);
+        record(&mut baselines, &hour(-24), 40, 40, 40);
+        record(&mut baselines, &hour(0), 500, 500, 500);
+        assert!(baselines.anomalies(&hour(0)).is_empty());
+
+        let mut baselines = empty();
+        record(&mut baselines, &hour(-72), 40, 40, 40);
+        record(&mut baselines, &hour(-48), 40, 40, 40);
+        record(&mut baselines, &hour(-24), 40, 40, 40);
+        record(&mut baselines, &hour(0), 500, 500, 500);
+        assert_eq!(baselines.anomalies(&hour(0)).len(), 3);
+    }
+
+    #[test]
+    fn counts_under_the_minimum_are_not_flagged() {
+        let mut baselines = empty();
+        let mut noise = Noise(11);
+        for day in (1..=14).rev() {
+            record(&mut baselines, &hour(-day * 24), noise.around(2, 1), 0, 0);
+        }
+
+        // Many times a quiet baseline, but under min_count = 20
+        record(&mut baselines, &hour(0), 19, 0, 0);
+        assert!(baselines.anomalies(&hour(0)).is_empty());
+
+        record(&mut baselines, &hour(0), 1, 0, 0);
+        assert_eq!(metrics(&baselines.anomalies(&hour(0))), [AnomalyMetric::Sessions]);
+    }
+
+    #[test]
+    fn days_without_totals_are_left_out_not_counted_as_zero() {
+        let mut baselines = empty();
+        for day in [13, 8, 2] {
+            record(&mut baselines, &hour(-day * 24), 40, 0, 0);
+        }
+
+        // Against 40 on three days, not 120 over fourteen
+        record(&mut baselines, &hour(0), 41, 0, 0);
+        assert!(baselines.anomalies(&hour(0)).is_empty());
+
+        record(&mut baselines, &hour(0), 9, 0, 0);
+        let anomalies = baselines.anomalies(&hour(0));
+        assert_eq!(metrics(&anomalies), [AnomalyMetric::Sessions]);
+        assert_eq!(anomalies[0].mean, 40.0);
+    }
+
+    #[test]
+    fn days_past_the_baseline_are_forgotten() {
+        let mut baselines = empty();
+        for day in (15..=20).rev() {
+            record(&mut baselines, &hour(-day * 24), 40, 0, 0);
+        }
+        record(&mut baselines, &hour(0), 200, 0, 0);
+        assert!(baselines.anomalies(&hour(0)).is_empty());
+    }
+
+    #[test]
+    fn restored_totals_detect_like_live_counts() {
+        let mut live = empty();
+        noisy_history(&mut live, 42);
+        record(&mut live, &hour(0), 200, 41, 40);
+        let saved = live.take_unsaved();
+        assert_eq!(saved.len(), 14);
+
+        let mut restored = empty();
+        for totals in saved {
+            restored.restore(totals);
+        }
+        record(&mut restored, &hour(0), 200, 41, 40);
+
+        let anomalies = restored.anomalies(&hour(0));
+        assert_eq!(anomalies, live.anomalies(&hour(0)));
+        assert_eq!(metrics(&anomalies), [AnomalyMetric::Sessions]);
+    }
+
+    #[test]
+    fn each_rate_is_judged_on_its_own() {
+        let mut baselines = empty();
+        noisy_history(&mut baselines, 7);
+
+        // A spray from one address: many failed logins, few sessions and IPs
+        record(&mut baselines, &hour(0), 3, 300, 0);
+        for _ in 0..300 {
+            baselines.record_ip(&IStr::from("203.0.113.7"), &hour(0));
+        }
+        assert_eq!(metrics(&baselines.anomalies(&hour(0))), [AnomalyMetric::LoginFailures]);
+
+        // Then a botnet: a few hundred distinct addresses
+        record(&mut baselines, &hour(0), 0, 0, 250);
+        assert_eq!(
+            metrics(&baselines.anomalies(&hour(0))),
+            [AnomalyMetric::LoginFailures, AnomalyMetric::UniqueIps]
+        );
+    }
+
+    #[test]
+    fn only_the_hour_in_progress_is_judged() {
+        let mut baselines = empty();
+        noisy_history(&mut baselines, 1);
+        record(&mut baselines, &hour(0), 200, 0, 0);
+
+        assert!(baselines.anomalies(&hour(1)).is_empty());
+    }
+
+    #[test]
+    fn nothing_is_counted_when_disabled() {
+        let config = AnomalyConfig { enabled: false, ..Default::default() };
+        let mut baselines = Baselines::new(&config);
+        noisy_history(&mut baselines, 1);
+        record(&mut baselines, &hour(0), 200, 200, 200);
+
+        assert!(baselines.anomalies(&hour(0)).is_empty());
+        assert!(baselines.take_unsaved().is_empty());
+    }
+}
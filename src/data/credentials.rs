@@ -0,0 +1,155 @@
+use std::borrow::Cow;
+
+use crate::config::FilterConfig;
+
+/// Normalizes usernames and passwords before they are aggregated
+///
+/// `"root"`, `"root "` and `"ROOT"` are often the same attempt typed by a
+/// different bot. With normalization on, the unique-credential sets, Top
+/// Credentials and attacker profiles count them once; log entries always keep
+/// the bytes the attacker sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CredentialNormalizer {
+    /// Whether anything is normalized at all
+    enabled: bool,
+    /// Lowercase usernames
+    fold_usernames: bool,
+    /// Lowercase passwords as well
+    fold_passwords: bool,
+}
+
+impl CredentialNormalizer {
+    /// Normalizer for `filter.normalize_credentials` and `filter.credential_case_fold`
+    pub fn from_config(config: &FilterConfig) -> Self {
+        let fold = config.credential_case_fold.as_str();
+        Self {
+            enabled: config.normalize_credentials,
+            fold_usernames: fold == "usernames" || fold == "all",
+            fold_passwords: fold == "all",
+        }
+    }
+
+    /// Username as aggregated
+    pub fn username<'a>(&self, username: &'a str) -> Cow<'a, str> {
+        self.normalize(username, self.fold_usernames)
+    }
+
+    /// Password as aggregated
+    pub fn password<'a>(&self, password: &'a str) -> Cow<'a, str> {
+        self.normalize(password, self.fold_passwords)
+    }
+
+    /// Drop control characters, trim and optionally lowercase; borrows when nothing changes
+    fn normalize<'a>(&self, value: &'a str, fold: bool) -> Cow<'a, str> {
+        if !self.enabled {
+            return Cow::Borrowed(value);
+        }
+
+        let trimmed = value.trim();
+        let needs_strip = trimmed.chars().any(char::is_control);
+        let needs_fold = fold && trimmed.chars().any(char::is_uppercase);
+        if !needs_strip && !needs_fold {
+            return Cow::Borrowed(trimmed);
+        }
+
+        // Trim again, as whitespace can sit behind the control characters
+        let stripped: String = trimmed.chars().filter(|c| !c.is_control()).collect();
+        let stripped = stripped.trim();
+        if needs_fold {
+            Cow::Owned(stripped.chars().flat_map(char::to_lowercase).collect())
+        } else {
+            Cow::Owned(stripped.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::data::store_writer::{apply, StoreCommand};
+    use crate::data::testing::{at, connect};
+    use crate::data::{EventType, LogEntry, Store};
+    use pretty_assertions::assert_eq;
+    use std::collections::HashSet;
+
+    fn normalizer(enabled: bool, case_fold: &str) -> CredentialNormalizer {
+        CredentialNormalizer::from_config(&FilterConfig {
+            normalize_credentials: enabled,
+            credential_case_fold: case_fold.to_string(),
+            ..FilterConfig::default()
+        })
+    }
+
+    fn attempt(username: &str, password: &str, second: i64) -> LogEntry {
+        LogEntry {
+            event_type: EventType::LoginFailed,
+            eventid: "cowrie.login.failed".to_string(),
+            username: Some(username.into()),
+            password: Some(password.to_string()),
+            ..connect("s1", "203.0.113.7", at(second))
+        }
+    }
+
+    /// Ways bots send the same root/admin attempt
+    const VARIANTS: [(&str, &str); 5] = [
+        ("root", "Admin"),
+        ("root ", "Admin\r\n"),
+        ("\troot", " Admin"),
+        ("ROOT", "Admin"),
+        ("ro\u{0}ot \u{7f}", "Ad\u{1b}min"),
+    ];
+
+    #[test]
+    fn normalized_variants_collapse_into_one_bucket() {
+        let folded = normalizer(true, "usernames");
+        let pairs: HashSet<_> = VARIANTS.iter()
+            .map(|(username, password)| (folded.username(username), folded.password(password)))
+            .collect();
+
+        assert_eq!(pairs, HashSet::from([("root".into(), "Admin".into())]));
+    }
+
+    #[test]
+    fn passwords_keep_their_case_unless_everything_is_folded() {
+        assert_eq!(normalizer(true, "usernames").password("Admin"), "Admin");
+        assert_eq!(normalizer(true, "none").username("ROOT"), "ROOT");
+        assert_eq!(normalizer(true, "all").password("Admin "), "admin");
+    }
+
+    #[test]
+    fn exact_bytes_are_kept_when_normalization_is_off() {
+        let exact = normalizer(false, "all");
+        for (username, password) in VARIANTS {
+            assert_eq!((exact.username(username), exact.password(password)), (username.into(), password.into()));
+        }
+    }
+
+    #[test]
+    fn clean_values_are_borrowed() {
+        let folded = normalizer(true, "all");
+        assert!(matches!(folded.username("root"), Cow::Borrowed("root")));
+        assert!(matches!(folded.username("  root  "), Cow::Borrowed("root")));
+        assert!(matches!(folded.username("Root"), Cow::Owned(_)));
+    }
+
+    #[test]
+    fn the_store_aggregates_normalized_and_logs_raw() {
+        let mut config = Config::default();
+        config.filter.normalize_credentials = true;
+        config.filter.credential_case_fold = "usernames".to_string();
+        let mut store = Store::new(&config).unwrap();
+        let entries = VARIANTS.iter().enumerate()
+            .map(|(i, (username, password))| attempt(username, password, i as i64))
+            .collect();
+        apply(&mut store, StoreCommand::AddLogEntries { source: "cowrie.json".to_string(), entries });
+
+        assert_eq!(store.get_unique_usernames(), &HashSet::from(["root".to_string()]));
+        assert_eq!(store.get_unique_passwords(), &HashSet::from(["Admin".to_string()]));
+        let mut logged: Vec<_> = store.get_log_entries().iter().filter_map(|entry| entry.username.as_deref().map(str::to_string)).collect();
+        logged.sort();
+        let mut sent: Vec<_> = VARIANTS.iter().map(|(username, _)| username.to_string()).collect();
+        sent.sort();
+        assert_eq!(logged, sent);
+    }
+}
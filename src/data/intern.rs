@@ -0,0 +1,184 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// Shared, immutable string for values that repeat across many entries
+///
+/// Behaves like a `&str` for display, comparison and serde, so code reading
+/// the field doesn't need to know it is interned.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct IStr(Arc<str>);
+
+impl IStr {
+    /// View as a string slice
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for IStr {
+    fn default() -> Self {
+        IStr::from("")
+    }
+}
+
+impl Deref for IStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for IStr {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for IStr {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for IStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Debug for IStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl From<&str> for IStr {
+    fn from(value: &str) -> Self {
+        IStr(Arc::from(value))
+    }
+}
+
+impl From<String> for IStr {
+    fn from(value: String) -> Self {
+        IStr(Arc::from(value))
+    }
+}
+
+impl From<IStr> for String {
+    fn from(value: IStr) -> Self {
+        value.0.to_string()
+    }
+}
+
+impl PartialEq<str> for IStr {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl<'a> PartialEq<&'a str> for IStr {
+    fn eq(&self, other: &&'a str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for IStr {
+    fn eq(&self, other: &String) -> bool {
+        &*self.0 == other.as_str()
+    }
+}
+
+impl Serialize for IStr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for IStr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(IStr::from)
+    }
+}
+
+/// Table of interned strings owned by the store
+#[derive(Clone, Default)]
+pub struct Interner {
+    /// Every distinct value handed out
+    strings: HashSet<IStr>,
+}
+
+impl Interner {
+    /// Create an empty table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shared copy of `value`, adding it to the table if it is new
+    pub fn intern(&mut self, value: &str) -> IStr {
+        if let Some(existing) = self.strings.get(value) {
+            return existing.clone();
+        }
+
+        let interned = IStr::from(value);
+        self.strings.insert(interned.clone());
+        interned
+    }
+
+    /// Drop strings that nothing but the table refers to any more
+    ///
+    /// A string still held by a published snapshot survives until a later call.
+    pub fn release_unused(&mut self) {
+        self.strings.retain(|value| Arc::strong_count(&value.0) > 1);
+    }
+
+    /// Number of distinct strings held
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.strings.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn equal_values_share_one_allocation() {
+        let mut interner = Interner::new();
+        let first = interner.intern("198.51.100.4");
+        let second = interner.intern(&String::from("198.51.100.4"));
+        let other = interner.intern("203.0.113.7");
+
+        assert!(std::ptr::eq(first.as_str(), second.as_str()));
+        assert!(!std::ptr::eq(first.as_str(), other.as_str()));
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn interned_strings_read_and_serialize_as_plain_strings() {
+        let ip = Interner::new().intern("198.51.100.4");
+
+        assert_eq!(ip.to_string(), "198.51.100.4");
+        assert_eq!(format!("{:?}", ip), "\"198.51.100.4\"");
+        assert!(ip == "198.51.100.4");
+        assert_eq!(serde_json::to_string(&ip).unwrap(), "\"198.51.100.4\"");
+        assert_eq!(serde_json::from_str::<IStr>("\"198.51.100.4\"").unwrap(), ip);
+    }
+
+    #[test]
+    fn only_strings_nothing_else_holds_are_released() {
+        let mut interner = Interner::new();
+        let kept = interner.intern("198.51.100.4");
+        interner.intern("203.0.113.7");
+        interner.release_unused();
+
+        assert_eq!(interner.len(), 1);
+        assert!(std::ptr::eq(interner.intern("198.51.100.4").as_str(), kept.as_str()));
+    }
+}
@@ -1,12 +1,33 @@
+pub mod attack;
+pub mod attackers;
+pub mod backend;
+pub mod baseline;
+pub mod credentials;
+pub mod intern;
 pub mod models;
+pub mod rate;
+pub mod similarity;
+#[cfg(feature = "sqlite-db")]
+pub mod sqlite;
 pub mod store;
+pub mod store_writer;
+#[cfg(test)]
+pub mod testing;
 
+pub use attackers::AttackerProfile;
+pub use credentials::CredentialNormalizer;
+pub use intern::IStr;
 pub use models::LogEntry;
 pub use models::Session;
 pub use models::User;
 pub use models::Command;
 pub use models::FileTransfer;
+pub use models::TcpForward;
 pub use models::EventType;
 pub use models::GeoLocation;
+pub use models::ClientFingerprint;
+pub use models::ClientStats;
+pub use models::ListenerCount;
 pub use models::FileTransferDirection;
-pub use store::Store;
\ No newline at end of file
+pub use store::Store;
+pub use store_writer::{StoreCommand, StoreHandle, StoreSnapshot, WhitelistFilter};
\ No newline at end of file
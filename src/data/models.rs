@@ -1,6 +1,11 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::data::intern::IStr;
+
+/// Commands that fetch a payload, ending a session's reconnaissance
+const FETCH_COMMANDS: &[&str] = &["wget", "curl", "tftp", "ftpget", "scp"];
 
 /// Log entry from honeypot
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,18 +16,21 @@ pub struct LogEntry {
     pub timestamp: DateTime<Utc>,
     /// Type of event
     pub event_type: EventType,
+    /// Original Cowrie eventid (kept even when it maps to Unknown)
+    #[serde(default)]
+    pub eventid: String,
     /// Session identifier
     pub session: Option<String>,
     /// Source IP address
-    pub src_ip: Option<String>,
+    pub src_ip: Option<IStr>,
     /// Source port
     pub src_port: Option<u16>,
     /// Destination IP address
-    pub dst_ip: Option<String>,
+    pub dst_ip: Option<IStr>,
     /// Destination port
     pub dst_port: Option<u16>,
     /// Username (for login attempts)
-    pub username: Option<String>,
+    pub username: Option<IStr>,
     /// Password (for login attempts)
     pub password: Option<String>,
     /// Command (for command execution)
@@ -80,35 +88,75 @@ impl std::fmt::Display for EventType {
     }
 }
 
+impl std::str::FromStr for EventType {
+    type Err = anyhow::Error;
+
+    /// Parse a variant name ("LoginSuccess") or its display form ("Login Success")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.replace(|c: char| c == ' ' || c == '_' || c == '-', "").to_lowercase();
+
+        match normalized.as_str() {
+            "connect" => Ok(EventType::Connect),
+            "disconnect" => Ok(EventType::Disconnect),
+            "loginattempt" => Ok(EventType::LoginAttempt),
+            "loginsuccess" => Ok(EventType::LoginSuccess),
+            "loginfailed" => Ok(EventType::LoginFailed),
+            "command" => Ok(EventType::Command),
+            "fileupload" => Ok(EventType::FileUpload),
+            "filedownload" => Ok(EventType::FileDownload),
+            "keyauth" => Ok(EventType::KeyAuth),
+            "tcpforward" => Ok(EventType::TCPForward),
+            "unknown" => Ok(EventType::Unknown),
+            _ => Err(anyhow::anyhow!("Unknown event type '{}'", s)),
+        }
+    }
+}
+
 /// Session information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
-    /// Session identifier
+    /// Session identifier: the logged ID and connect time (see [`Session::key`])
     pub id: String,
+    /// Session ID as the honeypot logged it; after a restart, unrelated
+    /// sessions can share one
+    #[serde(default)]
+    pub logged_id: String,
     /// Start time
     pub start_time: DateTime<Utc>,
     /// End time (if session has ended)
     pub end_time: Option<DateTime<Utc>>,
     /// Source IP address
-    pub src_ip: String,
+    pub src_ip: IStr,
     /// Source port
     pub src_port: u16,
     /// Destination IP address
-    pub dst_ip: String,
+    pub dst_ip: IStr,
     /// Destination port
     pub dst_port: u16,
     /// Protocol (SSH, Telnet)
     pub protocol: String,
     /// Client version
     pub client_version: Option<String>,
+    /// HASSH of the client's key exchange (`cowrie.client.kex`)
+    #[serde(default)]
+    pub hassh: Option<String>,
     /// User information
     pub user: Option<User>,
     /// Session duration in seconds
     pub duration: Option<u64>,
+    /// Time of the most recent event other than the close
+    #[serde(default)]
+    pub last_activity: Option<DateTime<Utc>>,
+    /// Seconds from the start to the last activity, set when the session closes
+    #[serde(default)]
+    pub active_duration: Option<u64>,
     /// Commands executed in this session
     pub commands: Vec<Command>,
     /// Files transferred in this session
     pub files: Vec<FileTransfer>,
+    /// Port forwards (`direct-tcpip` requests) the client asked for
+    #[serde(default)]
+    pub forwards: Vec<TcpForward>,
     /// Geographic location information
     pub geo_location: Option<GeoLocation>,
     /// Path to TTY log file
@@ -119,13 +167,95 @@ pub struct Session {
     pub is_malicious: bool,
     /// Malicious score (0-100)
     pub malicious_score: u8,
+    /// Descriptive tags (e.g. "mirai", "scanner", "reverse-shell")
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// IDs of the ATT&CK techniques observed (see [`crate::data::attack`])
+    #[serde(default)]
+    pub techniques: Vec<String>,
+}
+
+impl Session {
+    /// Store key for the session the honeypot logged as `logged_id`, started at `start`
+    pub fn key(logged_id: &str, start: &DateTime<Utc>) -> String {
+        format!("{}@{}", logged_id, start.timestamp())
+    }
+    
+    /// The ID to show: the one the honeypot logged
+    pub fn display_id(&self) -> &str {
+        if self.logged_id.is_empty() {
+            &self.id
+        } else {
+            &self.logged_id
+        }
+    }
+    
+    /// Whether the session closed having only connected: no auth attempt,
+    /// command, file or forward (a version exchange doesn't count)
+    pub fn is_scan(&self) -> bool {
+        self.end_time.is_some()
+            && self.user.is_none()
+            && self.commands.is_empty()
+            && self.files.is_empty()
+            && self.forwards.is_empty()
+            && self.tty_log.is_none()
+    }
+    
+    /// The client's HASSH and version banner, if either is known
+    pub fn client_fingerprint(&self) -> Option<ClientFingerprint> {
+        if self.hassh.is_none() && self.client_version.is_none() {
+            return None;
+        }
+        Some(ClientFingerprint {
+            hassh: self.hassh.clone(),
+            version: self.client_version.clone(),
+        })
+    }
+    
+    /// Seconds since the start: the final duration once closed, the live elapsed time while active
+    ///
+    /// Use this to compare or sort sessions by duration, so open and closed ones line up.
+    pub fn elapsed_secs(&self, now: DateTime<Utc>) -> u64 {
+        self.duration
+            .unwrap_or_else(|| now.signed_duration_since(self.start_time).num_seconds().max(0) as u64)
+    }
+    
+    /// Seconds between the start and the last activity, excluding any idle tail
+    pub fn active_secs(&self) -> u64 {
+        self.active_duration.unwrap_or_else(|| {
+            self.last_activity.map_or(0, |last| {
+                last.signed_duration_since(self.start_time).num_seconds().max(0) as u64
+            })
+        })
+    }
+    
+    /// Commands run before the payload arrived, the attacker's reconnaissance
+    ///
+    /// The payload phase starts at the first file transfer, or at the first
+    /// command that fetches one if that came earlier. Sessions that never
+    /// transferred a file have no payload phase, so this is empty for them.
+    pub fn recon_commands(&self) -> &[Command] {
+        let first_transfer = match self.files.iter().map(|file| file.timestamp).min() {
+            Some(timestamp) => timestamp,
+            None => return &[],
+        };
+        
+        let end = self.commands.iter()
+            .position(|command| {
+                let program = command.command.split_whitespace().next().unwrap_or_default();
+                let program = program.rsplit('/').next().unwrap_or(program);
+                command.timestamp >= first_transfer || FETCH_COMMANDS.contains(&program)
+            })
+            .unwrap_or(self.commands.len());
+        &self.commands[..end]
+    }
 }
 
 /// User information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     /// Username
-    pub username: String,
+    pub username: IStr,
     /// Password
     pub password: Option<String>,
     /// SSH key fingerprint
@@ -170,6 +300,31 @@ pub struct FileTransfer {
     pub is_executable: bool,
     /// Is file detected as malware
     pub is_malware: bool,
+    /// Local hash list (`malware_analysis.hash_lists`) the SHA-256 was found on
+    #[serde(default)]
+    pub hash_list: Option<String>,
+}
+
+/// A TCP forward requested through the honeypot's SSH server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TcpForward {
+    /// Host or address the client wanted to reach
+    pub dst_host: String,
+    /// Port the client wanted to reach
+    pub dst_port: u16,
+    /// Timestamp of the request
+    pub timestamp: DateTime<Utc>,
+}
+
+impl TcpForward {
+    /// The destination as `host:port`, bracketing IPv6 addresses
+    pub fn destination(&self) -> String {
+        if self.dst_host.contains(':') {
+            format!("[{}]:{}", self.dst_host, self.dst_port)
+        } else {
+            format!("{}:{}", self.dst_host, self.dst_port)
+        }
+    }
 }
 
 /// File transfer direction
@@ -198,4 +353,174 @@ pub struct GeoLocation {
     pub asn: Option<String>,
     /// ISP name
     pub isp: Option<String>,
-}
\ No newline at end of file
+}
+
+/// Span of activity after which a source IP counts as a returning visitor
+pub const RETURNING_AFTER_HOURS: i64 = 24;
+
+/// When a source IP was first and last seen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IpActivity {
+    /// Timestamp of the earliest entry from the IP
+    pub first_seen: DateTime<Utc>,
+    /// Timestamp of the latest entry from the IP
+    pub last_seen: DateTime<Utc>,
+    /// Entries seen from the IP, including pruned ones
+    pub entries: u64,
+}
+
+impl IpActivity {
+    /// Activity for an IP first seen at `timestamp`
+    pub fn new(timestamp: DateTime<Utc>) -> Self {
+        Self {
+            first_seen: timestamp,
+            last_seen: timestamp,
+            entries: 1,
+        }
+    }
+
+    /// Count an entry at `timestamp`, which may be older than those already seen
+    pub fn record(&mut self, timestamp: DateTime<Utc>) {
+        self.first_seen = self.first_seen.min(timestamp);
+        self.last_seen = self.last_seen.max(timestamp);
+        self.entries += 1;
+    }
+
+    /// Seconds between the first and last entry
+    pub fn span_secs(&self) -> u64 {
+        (self.last_seen - self.first_seen).num_seconds().max(0) as u64
+    }
+
+    /// Whether the IP has been active for longer than [`RETURNING_AFTER_HOURS`]
+    pub fn is_returning(&self) -> bool {
+        self.last_seen - self.first_seen >= chrono::Duration::hours(RETURNING_AFTER_HOURS)
+    }
+}
+
+/// What identifies an SSH client implementation: its HASSH and version banner
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ClientFingerprint {
+    /// MD5 over the client's key exchange algorithms
+    pub hassh: Option<String>,
+    /// Version banner, e.g. "SSH-2.0-Go"
+    pub version: Option<String>,
+}
+
+impl std::fmt::Display for ClientFingerprint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            self.hassh.as_deref().unwrap_or("-"),
+            self.version.as_deref().unwrap_or("-"),
+        )
+    }
+}
+
+/// Sessions from one client fingerprint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientStats {
+    /// Sessions with the fingerprint, including pruned ones
+    pub sessions: u64,
+    /// Sessions per source IP
+    pub ips: HashMap<IStr, u64>,
+    /// Sum of the sessions' `malicious_score`
+    pub total_risk: u64,
+    /// Start of the earliest session
+    pub first_seen: DateTime<Utc>,
+    /// Latest activity of any session
+    pub last_seen: DateTime<Utc>,
+}
+
+impl ClientStats {
+    /// Stats for a fingerprint first seen at `timestamp`
+    pub fn new(timestamp: DateTime<Utc>) -> Self {
+        Self {
+            sessions: 0,
+            ips: HashMap::new(),
+            total_risk: 0,
+            first_seen: timestamp,
+            last_seen: timestamp,
+        }
+    }
+    
+    /// Mean risk score per session
+    pub fn average_risk(&self) -> f64 {
+        if self.sessions == 0 {
+            0.0
+        } else {
+            self.total_risk as f64 / self.sessions as f64
+        }
+    }
+}
+
+/// Sessions seen on one honeypot listener (destination port)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListenerCount {
+    /// Sessions that connected to the port, including pruned ones
+    pub sessions: u64,
+    /// Protocol of the most recent session on the port
+    pub protocol: String,
+}
+
+/// Scan sessions from one source IP folded out of the sessions table (`filter.collapse_scans`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanSummary {
+    /// Source IP
+    pub src_ip: IStr,
+    /// Sessions folded in and not promoted back out
+    pub sessions: u64,
+    /// Start of the earliest folded session
+    pub first_seen: DateTime<Utc>,
+    /// End of the latest folded session
+    pub last_seen: DateTime<Utc>,
+    /// Folded sessions per destination port
+    pub ports: BTreeMap<u16, u64>,
+}
+
+impl ScanSummary {
+    /// Empty summary for `src_ip`, first seen at `timestamp`
+    pub fn new(src_ip: IStr, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            src_ip,
+            sessions: 0,
+            first_seen: timestamp,
+            last_seen: timestamp,
+            ports: BTreeMap::new(),
+        }
+    }
+}
+
+/// A log line that could not be parsed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParseFailure {
+    /// Source the line was read from
+    pub source: String,
+    /// When the failure was recorded
+    pub timestamp: DateTime<Utc>,
+    /// The raw line
+    pub line: String,
+    /// Parser error message
+    pub error: String,
+}
+
+/// Parse counters for a single source
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ParseStats {
+    /// Lines parsed successfully
+    pub parsed: u64,
+    /// Lines that failed to parse
+    pub failed: u64,
+}
+
+impl ParseStats {
+    /// Fraction of lines that failed to parse (0.0 - 1.0)
+    pub fn failure_rate(&self) -> f64 {
+        let total = self.parsed + self.failed;
+        if total == 0 {
+            0.0
+        } else {
+            self.failed as f64 / total as f64
+        }
+    }
+}
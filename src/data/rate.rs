@@ -0,0 +1,241 @@
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+
+use crate::data::IStr;
+
+/// Minutes of history kept for the dashboard rate sparklines
+pub const RATE_WINDOW_MINUTES: usize = 60;
+
+/// Ring buffer of per-minute event counts for the trailing hour
+///
+/// Counts are keyed by the event's own timestamp, so replayed history
+/// lands in the right minute and anything older than the window is dropped.
+#[derive(Debug, Clone, Default)]
+pub struct MinuteCounts {
+    /// Counts, oldest minute first; the back is `newest`
+    counts: VecDeque<u64>,
+    /// Minute (since the epoch) of the last slot
+    newest: i64,
+}
+
+impl MinuteCounts {
+    /// Create an empty buffer
+    pub fn new() -> Self {
+        Self {
+            counts: VecDeque::with_capacity(RATE_WINDOW_MINUTES),
+            newest: 0,
+        }
+    }
+
+    /// Count one event at `timestamp`
+    pub fn record(&mut self, timestamp: &DateTime<Utc>) {
+        let minute = minute_of(timestamp);
+        self.advance(minute);
+
+        let age = (self.newest - minute) as usize;
+        if age < self.counts.len() {
+            let index = self.counts.len() - 1 - age;
+            self.counts[index] += 1;
+        }
+    }
+
+    /// Per-minute counts for the hour ending at `now`, oldest first
+    ///
+    /// Always [`RATE_WINDOW_MINUTES`] long; minutes before the first event
+    /// and quiet minutes since the last one read as zero.
+    pub fn series(&self, now: &DateTime<Utc>) -> Vec<u64> {
+        let mut counts = self.clone();
+        counts.advance(minute_of(now));
+
+        let mut series = vec![0; RATE_WINDOW_MINUTES - counts.counts.len()];
+        series.extend(counts.counts.iter().copied());
+        series
+    }
+
+    /// Remove all counts
+    pub fn clear(&mut self) {
+        self.counts.clear();
+        self.newest = 0;
+    }
+
+    /// Move the window forward so its last slot is `minute`
+    fn advance(&mut self, minute: i64) {
+        if self.counts.is_empty() {
+            self.counts.push_back(0);
+            self.newest = minute;
+            return;
+        }
+
+        let gap = (minute - self.newest).clamp(0, RATE_WINDOW_MINUTES as i64);
+        for _ in 0..gap {
+            self.counts.push_back(0);
+        }
+        while self.counts.len() > RATE_WINDOW_MINUTES {
+            self.counts.pop_front();
+        }
+        self.newest = self.newest.max(minute);
+    }
+}
+
+/// Headline counts for one hour
+#[derive(Debug, Clone, Default)]
+struct HourBucket {
+    /// Sessions started
+    sessions: u64,
+    /// Source IPs seen
+    ips: HashSet<IStr>,
+    /// Successful logins
+    successful_logins: u64,
+    /// Files flagged as malware when their transfer was logged
+    malware_uploads: u64,
+}
+
+/// Headline counts over a span of hours
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PeriodTotals {
+    /// Sessions started
+    pub sessions: u64,
+    /// Distinct source IPs
+    pub unique_ips: u64,
+    /// Successful logins
+    pub successful_logins: u64,
+    /// Files flagged as malware
+    pub malware_uploads: u64,
+}
+
+/// Headline counts per hour, kept for `dashboard.history_hours`
+///
+/// Unlike the lifetime counters these can be summed over any span, so a
+/// period can be compared with the one before it after the entries behind
+/// both have been pruned. Keyed by the event's own timestamp, like
+/// [`MinuteCounts`].
+#[derive(Debug, Clone, Default)]
+pub struct HourlyCounts {
+    /// Buckets by hour since the epoch
+    hours: BTreeMap<i64, HourBucket>,
+    /// Hours of history kept before the newest
+    horizon: i64,
+    /// Newest hour recorded
+    newest: i64,
+}
+
+impl HourlyCounts {
+    /// Empty counts keeping `horizon_hours` of history
+    pub fn new(horizon_hours: u64) -> Self {
+        Self {
+            hours: BTreeMap::new(),
+            horizon: horizon_hours.max(1) as i64,
+            newest: i64::MIN,
+        }
+    }
+
+    /// Count a session started at `timestamp`
+    pub fn record_session(&mut self, timestamp: &DateTime<Utc>) {
+        if let Some(bucket) = self.bucket(timestamp) {
+            bucket.sessions += 1;
+        }
+    }
+
+    /// Note `ip` as seen at `timestamp`
+    pub fn record_ip(&mut self, ip: &IStr, timestamp: &DateTime<Utc>) {
+        if let Some(bucket) = self.bucket(timestamp) {
+            if !bucket.ips.contains(ip) {
+                bucket.ips.insert(ip.clone());
+            }
+        }
+    }
+
+    /// Count a successful login at `timestamp`
+    pub fn record_login(&mut self, timestamp: &DateTime<Utc>) {
+        if let Some(bucket) = self.bucket(timestamp) {
+            bucket.successful_logins += 1;
+        }
+    }
+
+    /// Count a malware transfer at `timestamp`
+    pub fn record_malware(&mut self, timestamp: &DateTime<Utc>) {
+        if let Some(bucket) = self.bucket(timestamp) {
+            bucket.malware_uploads += 1;
+        }
+    }
+
+    /// Totals from `start` to `end`, and for the equally long period before it
+    ///
+    /// Both are counted in whole hours: the period covers every hour from the
+    /// one `start` falls in to the one `end` falls in. The earlier period is
+    /// `None` when part of it is older than the history kept.
+    pub fn compare(&self, start: &DateTime<Utc>, end: &DateTime<Utc>) -> (PeriodTotals, Option<PeriodTotals>) {
+        let last = hour_of(end);
+        let hours = (last - hour_of(start) + 1).max(1);
+        let current = self.totals(last, hours);
+
+        let previous_last = last - hours;
+        let kept = self.newest == i64::MIN || previous_last - hours + 1 >= self.newest - self.horizon;
+        (current, if kept { Some(self.totals(previous_last, hours)) } else { None })
+    }
+
+    /// Totals for the `hours` hours ending with hour `last`
+    fn totals(&self, last: i64, hours: i64) -> PeriodTotals {
+        let mut totals = PeriodTotals::default();
+        let mut ips: HashSet<&IStr> = HashSet::new();
+        for bucket in self.hours.range(last - hours + 1..=last).map(|(_, bucket)| bucket) {
+            totals.sessions += bucket.sessions;
+            totals.successful_logins += bucket.successful_logins;
+            totals.malware_uploads += bucket.malware_uploads;
+            ips.extend(bucket.ips.iter());
+        }
+        totals.unique_ips = ips.len() as u64;
+        totals
+    }
+
+    /// Remove all counts
+    pub fn clear(&mut self) {
+        self.hours.clear();
+        self.newest = i64::MIN;
+    }
+
+    /// Bucket for `timestamp`, or `None` if it is older than the history kept
+    fn bucket(&mut self, timestamp: &DateTime<Utc>) -> Option<&mut HourBucket> {
+        let hour = hour_of(timestamp);
+        if hour > self.newest {
+            self.newest = hour;
+            let oldest = hour - self.horizon;
+            self.hours = self.hours.split_off(&oldest);
+        }
+        if hour < self.newest - self.horizon {
+            return None;
+        }
+        Some(self.hours.entry(hour).or_default())
+    }
+}
+
+/// Bucket `timestamps` into per-minute counts from `start` to `end`, oldest first
+pub fn per_minute<'a>(
+    timestamps: impl IntoIterator<Item = &'a DateTime<Utc>>,
+    start: &DateTime<Utc>,
+    end: &DateTime<Utc>,
+) -> Vec<u64> {
+    let (first, last) = (minute_of(start), minute_of(end));
+    if last < first {
+        return Vec::new();
+    }
+
+    let mut series = vec![0; (last - first + 1) as usize];
+    for timestamp in timestamps {
+        let minute = minute_of(timestamp);
+        if (first..=last).contains(&minute) {
+            series[(minute - first) as usize] += 1;
+        }
+    }
+    series
+}
+
+/// Minutes since the epoch
+fn minute_of(timestamp: &DateTime<Utc>) -> i64 {
+    timestamp.timestamp().div_euclid(60)
+}
+
+/// Hours since the epoch
+fn hour_of(timestamp: &DateTime<Utc>) -> i64 {
+    timestamp.timestamp().div_euclid(3600)
+}
@@ -0,0 +1,134 @@
+//! Sessions that look like the same actor or tooling as another
+//!
+//! Sessions from other addresses are compared on traits an attacker tends to
+//! carry between them: the credentials they try, the commands they run, the
+//! files they drop, their SSH client (HASSH, as in the attacker correlation
+//! panel) and, as a weak hint only, the network they come from.
+
+use std::collections::HashSet;
+
+use crate::data::{CredentialNormalizer, Session};
+
+/// Weight of a shared username and password
+const CREDENTIALS_WEIGHT: f64 = 3.0;
+/// Weight of an identical command set; partial overlap scales it
+const COMMANDS_WEIGHT: f64 = 4.0;
+/// Least overlap (Jaccard index) of the command sets that counts
+const MIN_COMMAND_OVERLAP: f64 = 0.5;
+/// Weight of a transferred file with the same SHA-256
+const FILE_HASH_WEIGHT: f64 = 5.0;
+/// Weight of the same HASSH
+const HASSH_WEIGHT: f64 = 2.0;
+/// Weight of the same ASN, which alone links nothing
+const ASN_WEIGHT: f64 = 1.0;
+
+/// A session similar to the open one and why
+#[derive(Debug, Clone)]
+pub struct SimilarSession {
+    /// Store key of the similar session
+    pub id: String,
+    /// Sum of the weights of the shared traits
+    pub score: f64,
+    /// The traits the sessions share, strongest first
+    pub shared: Vec<String>,
+}
+
+/// Up to `limit` of `candidates` most similar to `session`, most similar first
+///
+/// Sessions from the same source IP are left out (the risk trend already
+/// steps through those), as are sessions sharing nothing but the ASN.
+pub fn similar_sessions<'a>(
+    session: &Session,
+    candidates: impl IntoIterator<Item = &'a Session>,
+    normalizer: &CredentialNormalizer,
+    limit: usize,
+) -> Vec<SimilarSession> {
+    let traits = Traits::of(session, normalizer);
+
+    let mut similar: Vec<SimilarSession> = candidates.into_iter()
+        .filter(|other| other.src_ip != session.src_ip)
+        .filter_map(|other| {
+            let (score, shared) = traits.compare(&Traits::of(other, normalizer));
+            if score > ASN_WEIGHT {
+                Some(SimilarSession { id: other.id.clone(), score, shared })
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    similar.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    similar.truncate(limit);
+    similar
+}
+
+/// What a session is compared on
+struct Traits<'a> {
+    /// Username and password, as aggregated
+    credentials: Option<(String, String)>,
+    /// Distinct commands, whitespace collapsed
+    commands: HashSet<String>,
+    /// SHA-256 of transferred files
+    hashes: HashSet<&'a str>,
+    /// Client key exchange fingerprint
+    hassh: Option<&'a str>,
+    /// Network the session came from
+    asn: Option<&'a str>,
+}
+
+impl<'a> Traits<'a> {
+    /// Traits of `session`
+    fn of(session: &'a Session, normalizer: &CredentialNormalizer) -> Self {
+        Self {
+            credentials: session.user.as_ref().and_then(|user| {
+                let password = user.password.as_deref()?;
+                Some((
+                    normalizer.username(&user.username).into_owned(),
+                    normalizer.password(password).into_owned(),
+                ))
+            }),
+            commands: session.commands.iter()
+                .map(|command| command.command.split_whitespace().collect::<Vec<_>>().join(" "))
+                .filter(|command| !command.is_empty())
+                .collect(),
+            hashes: session.files.iter().filter_map(|file| file.shasum.as_deref()).collect(),
+            hassh: session.hassh.as_deref(),
+            asn: session.geo_location.as_ref().and_then(|geo| geo.asn.as_deref()),
+        }
+    }
+
+    /// Score and shared traits, strongest first
+    fn compare(&self, other: &Traits) -> (f64, Vec<String>) {
+        let mut shared: Vec<(f64, String)> = Vec::new();
+
+        if self.hashes.iter().any(|hash| other.hashes.contains(hash)) {
+            shared.push((FILE_HASH_WEIGHT, "file hash".to_string()));
+        }
+
+        if !self.commands.is_empty() && !other.commands.is_empty() {
+            let common = self.commands.intersection(&other.commands).count();
+            let overlap = common as f64 / self.commands.union(&other.commands).count() as f64;
+            if overlap >= MIN_COMMAND_OVERLAP {
+                shared.push((COMMANDS_WEIGHT * overlap, format!("{:.0}% of commands", overlap * 100.0)));
+            }
+        }
+
+        if let (Some(credentials), Some(other_credentials)) = (&self.credentials, &other.credentials) {
+            if credentials == other_credentials {
+                shared.push((CREDENTIALS_WEIGHT, "credentials".to_string()));
+            }
+        }
+
+        if self.hassh.is_some() && self.hassh == other.hassh {
+            shared.push((HASSH_WEIGHT, "HASSH".to_string()));
+        }
+
+        if self.asn.is_some() && self.asn == other.asn {
+            shared.push((ASN_WEIGHT, "ASN".to_string()));
+        }
+
+        shared.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        let score = shared.iter().map(|(weight, _)| weight).sum();
+        (score, shared.into_iter().map(|(_, trait_name)| trait_name).collect())
+    }
+}
@@ -0,0 +1,426 @@
+//! SQLite storage backend (`storage.backend = "sqlite"`)
+//!
+//! Log entries and sessions are kept whole as JSON, next to the columns worth
+//! querying on. Commands, transferred files and geolocations get tables of
+//! their own, rewritten whenever their session is. Closed hours of the
+//! anomaly baselines are kept for [`AnomalyConfig::MAX_BASELINE_DAYS`].
+//! The alert history has a table of its own (see [`SqliteAlerts`]), which
+//! clearing the store leaves alone.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use log::{info, warn};
+use parking_lot::Mutex;
+use rusqlite::{params, Connection, OpenFlags, Transaction};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::AnomalyConfig;
+use crate::data::backend::{History, StorageBackend, StoredCounts};
+use crate::data::baseline::HourTotals;
+use crate::data::models::{FileTransferDirection, LogEntry, Session};
+use crate::data::store_writer::StoreCommand;
+
+/// File name of the database in the data directory
+pub const DATABASE_FILE: &str = "xkippo.db";
+
+/// Tables and indexes, created on first open
+const SCHEMA: &str = "
+PRAGMA journal_mode = WAL;
+PRAGMA synchronous = NORMAL;
+
+CREATE TABLE IF NOT EXISTS log_entries (
+    id TEXT PRIMARY KEY,
+    timestamp TEXT NOT NULL,
+    eventid TEXT NOT NULL,
+    session TEXT,
+    src_ip TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS log_entries_timestamp ON log_entries (timestamp);
+CREATE INDEX IF NOT EXISTS log_entries_session ON log_entries (session);
+CREATE INDEX IF NOT EXISTS log_entries_src_ip ON log_entries (src_ip);
+
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT PRIMARY KEY,
+    logged_id TEXT NOT NULL,
+    start_time TEXT NOT NULL,
+    end_time TEXT,
+    src_ip TEXT NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS sessions_start_time ON sessions (start_time);
+CREATE INDEX IF NOT EXISTS sessions_src_ip ON sessions (src_ip);
+CREATE INDEX IF NOT EXISTS sessions_logged_id ON sessions (logged_id);
+
+CREATE TABLE IF NOT EXISTS commands (
+    session_id TEXT NOT NULL,
+    timestamp TEXT NOT NULL,
+    command TEXT NOT NULL,
+    success INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS commands_session_id ON commands (session_id);
+
+CREATE TABLE IF NOT EXISTS files (
+    session_id TEXT NOT NULL,
+    timestamp TEXT NOT NULL,
+    filename TEXT NOT NULL,
+    direction TEXT NOT NULL,
+    size INTEGER,
+    shasum TEXT
+);
+CREATE INDEX IF NOT EXISTS files_session_id ON files (session_id);
+CREATE INDEX IF NOT EXISTS files_shasum ON files (shasum);
+
+CREATE TABLE IF NOT EXISTS geo (
+    ip TEXT PRIMARY KEY,
+    country_code TEXT NOT NULL,
+    country_name TEXT NOT NULL,
+    city TEXT,
+    latitude REAL,
+    longitude REAL,
+    asn TEXT,
+    isp TEXT
+);
+
+CREATE TABLE IF NOT EXISTS alerts (
+    id INTEGER PRIMARY KEY,
+    data TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS hour_totals (
+    hour INTEGER PRIMARY KEY,
+    sessions INTEGER NOT NULL,
+    login_failures INTEGER NOT NULL,
+    unique_ips INTEGER NOT NULL
+);
+";
+
+/// Captured data in a SQLite database
+pub struct SqliteBackend {
+    /// Where the database is
+    path: PathBuf,
+    /// Open database
+    conn: Connection,
+    /// Newest log entries brought back on restore
+    max_logs: usize,
+    /// Newest sessions brought back on restore
+    max_sessions: usize,
+    /// Rows in `sessions` and `log_entries`
+    counts: StoredCounts,
+}
+
+impl SqliteBackend {
+    /// Open or create the database at `path`
+    pub fn open(path: &Path, max_logs: usize, max_sessions: usize) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .context(format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        let conn = Connection::open(path)
+            .context(format!("Failed to open database: {}", path.display()))?;
+        conn.execute_batch(SCHEMA)
+            .context(format!("Failed to create tables in {}", path.display()))?;
+
+        let count = |table: &str| -> Result<usize> {
+            let rows: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))?;
+            Ok(rows as usize)
+        };
+        let counts = StoredCounts {
+            sessions: count("sessions")?,
+            log_entries: count("log_entries")?,
+        };
+        info!(
+            "Opened {} with {} sessions and {} log entries",
+            path.display(),
+            counts.sessions,
+            counts.log_entries
+        );
+
+        Ok(Self { path: path.to_path_buf(), conn, max_logs, max_sessions, counts })
+    }
+
+    /// Newest `limit` rows of `data` in `table`, oldest first; unreadable rows are skipped
+    fn newest<T: DeserializeOwned>(&self, table: &str, order: &str, limit: usize) -> Result<Vec<T>> {
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT data FROM (SELECT data, {order} FROM {table} ORDER BY {order} DESC LIMIT ?1) ORDER BY {order}",
+            order = order,
+            table = table,
+        ))?;
+        let rows = statement.query_map([limit as i64], |row| row.get::<_, String>(0))?;
+        decode_rows(table, rows)
+    }
+
+    /// Every stored hour of the anomaly baselines, oldest first
+    fn hour_totals(&self) -> Result<Vec<HourTotals>> {
+        let mut statement = self.conn.prepare(
+            "SELECT hour, sessions, login_failures, unique_ips FROM hour_totals ORDER BY hour",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok(HourTotals {
+                hour: row.get(0)?,
+                sessions: row.get::<_, i64>(1)? as u64,
+                login_failures: row.get::<_, i64>(2)? as u64,
+                unique_ips: row.get::<_, i64>(3)? as u64,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+impl StorageBackend for SqliteBackend {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn write(&mut self, command: &StoreCommand) -> Result<()> {
+        match command {
+            StoreCommand::AddLogEntry(entry) => {
+                let tx = self.conn.transaction()?;
+                self.counts.log_entries += insert_entry(&tx, entry)?;
+                tx.commit()?;
+            }
+            StoreCommand::AddLogEntries { entries, .. } => {
+                let tx = self.conn.transaction()?;
+                for entry in entries {
+                    self.counts.log_entries += insert_entry(&tx, entry)?;
+                }
+                tx.commit()?;
+            }
+            StoreCommand::UpsertSession(session) => {
+                let tx = self.conn.transaction()?;
+                self.counts.sessions += upsert_session(&tx, session)?;
+                tx.commit()?;
+            }
+            StoreCommand::RecordHourTotals(totals) => {
+                let tx = self.conn.transaction()?;
+                tx.execute(
+                    "INSERT OR REPLACE INTO hour_totals (hour, sessions, login_failures, unique_ips) VALUES (?1, ?2, ?3, ?4)",
+                    params![totals.hour, totals.sessions as i64, totals.login_failures as i64, totals.unique_ips as i64],
+                )?;
+                let oldest = totals.hour - AnomalyConfig::MAX_BASELINE_DAYS as i64 * 24;
+                tx.execute("DELETE FROM hour_totals WHERE hour < ?1", [oldest])?;
+                tx.commit()?;
+            }
+            StoreCommand::Clear => {
+                self.conn.execute_batch(
+                    "DELETE FROM log_entries; DELETE FROM sessions; DELETE FROM commands; DELETE FROM files; DELETE FROM geo; DELETE FROM hour_totals;",
+                )?;
+                self.counts = StoredCounts::default();
+            }
+            // The database keeps every session whole; restored scans are folded again
+            StoreCommand::RecordParsed(_) | StoreCommand::RecordParseFailure { .. } | StoreCommand::CollapseScans(_) => {}
+            StoreCommand::Applied(_) => {}
+        }
+        Ok(())
+    }
+
+    fn restore(&mut self) -> Result<Vec<StoreCommand>> {
+        // Entries first, as they arrived before the sessions built from them.
+        // Hour totals go last so they win over the partial counts of the replay
+        let entries: Vec<LogEntry> = self.newest("log_entries", "timestamp", self.max_logs)?;
+        let sessions: Vec<Session> = self.newest("sessions", "start_time", self.max_sessions)?;
+        let hours = self.hour_totals()?;
+
+        Ok(entries.into_iter()
+            .map(StoreCommand::AddLogEntry)
+            .chain(sessions.into_iter().map(|session| StoreCommand::UpsertSession(Box::new(session))))
+            .chain(hours.into_iter().map(StoreCommand::RecordHourTotals))
+            .collect())
+    }
+
+    fn counts(&self) -> StoredCounts {
+        self.counts
+    }
+
+    fn history(&self) -> Result<Box<dyn History>> {
+        let conn = Connection::open_with_flags(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .context(format!("Failed to open database: {}", self.path.display()))?;
+        conn.busy_timeout(Duration::from_secs(1))?;
+        Ok(Box::new(SqliteHistory { conn: Mutex::new(conn) }))
+    }
+}
+
+/// Queries on a read-only connection of their own, so they never wait on the writer's
+pub struct SqliteHistory {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteHistory {
+    /// `data` of the rows `query` selects for `key`; unreadable rows are skipped
+    fn rows<T: DeserializeOwned>(&self, table: &str, query: &str, key: &str) -> Result<Vec<T>> {
+        let conn = self.conn.lock();
+        let mut statement = conn.prepare_cached(query)?;
+        let rows = statement.query_map([key], |row| row.get::<_, String>(0))?;
+        decode_rows(table, rows)
+    }
+}
+
+impl History for SqliteHistory {
+    fn sessions_by_source_ip(&self, src_ip: &str) -> Result<Vec<Session>> {
+        self.rows("sessions", "SELECT data FROM sessions WHERE src_ip = ?1 ORDER BY start_time", src_ip)
+    }
+
+    fn sessions_by_logged_id(&self, logged_id: &str) -> Result<Vec<Session>> {
+        self.rows("sessions", "SELECT data FROM sessions WHERE logged_id = ?1 ORDER BY start_time", logged_id)
+    }
+
+    fn log_entries_by_logged_id(&self, logged_id: &str) -> Result<Vec<LogEntry>> {
+        self.rows("log_entries", "SELECT data FROM log_entries WHERE session = ?1 ORDER BY rowid", logged_id)
+    }
+}
+
+/// The `alerts` table, on a connection of its own
+///
+/// Alerts are kept whole as JSON, oldest first, like the alert history's file.
+#[derive(Debug)]
+pub struct SqliteAlerts {
+    conn: Connection,
+}
+
+impl SqliteAlerts {
+    /// Open or create the database at `path` for its alerts
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .context(format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        let conn = Connection::open(path)
+            .context(format!("Failed to open database: {}", path.display()))?;
+        conn.busy_timeout(Duration::from_secs(1))?;
+        conn.execute_batch(SCHEMA)
+            .context(format!("Failed to create tables in {}", path.display()))?;
+        Ok(Self { conn })
+    }
+
+    /// Every stored alert, oldest first; unreadable rows are skipped
+    pub fn load<T: DeserializeOwned>(&self) -> Result<Vec<T>> {
+        let mut statement = self.conn.prepare("SELECT data FROM alerts ORDER BY id")?;
+        let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+        decode_rows("alerts", rows)
+    }
+
+    /// Store one more alert
+    pub fn append<T: Serialize>(&self, alert: &T) -> Result<()> {
+        self.conn.execute("INSERT INTO alerts (data) VALUES (?1)", [serde_json::to_string(alert)?])?;
+        Ok(())
+    }
+
+    /// Replace every stored alert with `alerts`
+    pub fn replace<T: Serialize>(&mut self, alerts: &[T]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM alerts", [])?;
+        for alert in alerts {
+            tx.execute("INSERT INTO alerts (data) VALUES (?1)", [serde_json::to_string(alert)?])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+/// Decode the JSON `data` column of `rows` from `table`, skipping rows that don't parse
+fn decode_rows<T: DeserializeOwned>(
+    table: &str,
+    rows: impl Iterator<Item = rusqlite::Result<String>>,
+) -> Result<Vec<T>> {
+    let mut items = Vec::new();
+    for data in rows {
+        match serde_json::from_str(&data?) {
+            Ok(item) => items.push(item),
+            Err(e) => warn!("Skipping unreadable row in {}: {}", table, e),
+        }
+    }
+    Ok(items)
+}
+
+/// Timestamp as stored: RFC 3339 in UTC, so text order is time order
+fn timestamp(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+/// Insert `entry` unless it is already stored; how many rows were added
+fn insert_entry(tx: &Transaction, entry: &LogEntry) -> Result<usize> {
+    let added = tx.execute(
+        "INSERT OR IGNORE INTO log_entries (id, timestamp, eventid, session, src_ip, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            entry.id,
+            timestamp(&entry.timestamp),
+            entry.eventid,
+            entry.session,
+            entry.src_ip.as_deref(),
+            serde_json::to_string(entry)?,
+        ],
+    )?;
+    Ok(added)
+}
+
+/// Insert or replace `session` with its commands, files and location; how many sessions are new
+fn upsert_session(tx: &Transaction, session: &Session) -> Result<usize> {
+    let existed: bool = tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sessions WHERE id = ?1)",
+        [&session.id],
+        |row| row.get(0),
+    )?;
+
+    tx.execute(
+        "INSERT OR REPLACE INTO sessions (id, logged_id, start_time, end_time, src_ip, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            session.id,
+            session.logged_id,
+            timestamp(&session.start_time),
+            session.end_time.as_ref().map(timestamp),
+            session.src_ip.as_str(),
+            serde_json::to_string(session)?,
+        ],
+    )?;
+
+    tx.execute("DELETE FROM commands WHERE session_id = ?1", [&session.id])?;
+    for command in &session.commands {
+        tx.execute(
+            "INSERT INTO commands (session_id, timestamp, command, success) VALUES (?1, ?2, ?3, ?4)",
+            params![session.id, timestamp(&command.timestamp), command.command, command.success],
+        )?;
+    }
+
+    tx.execute("DELETE FROM files WHERE session_id = ?1", [&session.id])?;
+    for file in &session.files {
+        let direction = match file.direction {
+            FileTransferDirection::Upload => "upload",
+            FileTransferDirection::Download => "download",
+        };
+        tx.execute(
+            "INSERT INTO files (session_id, timestamp, filename, direction, size, shasum) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                session.id,
+                timestamp(&file.timestamp),
+                file.filename,
+                direction,
+                file.size.map(|size| size as i64),
+                file.shasum,
+            ],
+        )?;
+    }
+
+    if let Some(geo) = &session.geo_location {
+        tx.execute(
+            "INSERT OR REPLACE INTO geo (ip, country_code, country_name, city, latitude, longitude, asn, isp) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                session.src_ip.as_str(),
+                geo.country_code,
+                geo.country_name,
+                geo.city,
+                geo.latitude,
+                geo.longitude,
+                geo.asn,
+                geo.isp,
+            ],
+        )?;
+    }
+
+    Ok(if existed { 0 } else { 1 })
+}
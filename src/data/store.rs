@@ -1,36 +1,101 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use chrono::{DateTime, Utc};
-use log::{debug, error, info, warn};
-use parking_lot::RwLock;
-use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use log::{debug, info};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 
 use crate::config::Config;
-use crate::data::models::{EventType, LogEntry, Session, User};
+use crate::data::credentials::CredentialNormalizer;
+use crate::data::intern::Interner;
+use crate::data::baseline::{Anomaly, Baselines, HourTotals};
+use crate::data::rate::{HourlyCounts, MinuteCounts};
+use crate::data::models::{
+    ClientFingerprint, ClientStats, EventType, FileTransfer, IpActivity, ListenerCount, LogEntry, ParseFailure, ParseStats,
+    ScanSummary, Session,
+};
+use crate::data::IStr;
+
+/// Number of recent parse failures to keep for diagnostics
+const MAX_PARSE_FAILURES: usize = 200;
+
+/// Pruned entries and sessions between sweeps of the string table
+const RELEASE_EVERY: usize = 1000;
+
+/// Source IPs whose first and last sighting are remembered
+///
+/// Outlives the entries themselves, so a returning IP is recognised after its
+/// earlier entries have been pruned.
+const MAX_TRACKED_IPS: usize = 100_000;
+
+/// Collapsed scan sessions kept so a late login can promote them back
+///
+/// Older ones are forgotten but stay counted in their IP's summary.
+const MAX_COLLAPSED_SCANS: usize = 50_000;
 
 /// In-memory data store for honeypot data
+///
+/// Clones share the entries and sessions themselves, which are never changed
+/// in place, so a snapshot costs the indexes and counters rather than the data.
+#[derive(Clone)]
 pub struct Store {
     /// Log entries, indexed by ID
-    log_entries: HashMap<String, LogEntry>,
+    log_entries: HashMap<String, Arc<LogEntry>>,
     /// Sessions, indexed by session ID
-    sessions: HashMap<String, Session>,
+    sessions: HashMap<String, Arc<Session>>,
     /// Maximum number of log entries to keep
     max_logs: usize,
     /// Maximum number of sessions to track
     max_sessions: usize,
+    /// Whether log entries keep their original JSON
+    keep_raw: bool,
     /// Log entry IDs in chronological order
     log_entry_ids: Vec<String>,
     /// Session IDs in chronological order
     session_ids: Vec<String>,
+    /// Session IDs per ID the honeypot logged, oldest first
+    logged_sessions: HashMap<String, Vec<String>>,
+    /// Scan sessions folded out of `sessions`, per source IP (`filter.collapse_scans`)
+    scan_summaries: HashMap<IStr, ScanSummary>,
+    /// Folded scan sessions by the ID the honeypot logged, the latest per ID
+    collapsed_scans: HashMap<String, Arc<Session>>,
+    /// Logged IDs in `collapsed_scans`, oldest first
+    collapsed_order: VecDeque<String>,
     /// Unique source IPs
     unique_ips: HashSet<String>,
+    /// First and last sighting of each source IP
+    ip_activity: HashMap<String, IpActivity>,
+    /// Sessions per destination port, kept up to date as sessions come and change
+    listeners: HashMap<u16, ListenerCount>,
+    /// Sessions per client fingerprint, kept up to date the same way
+    clients: HashMap<ClientFingerprint, ClientStats>,
     /// Unique usernames
     unique_usernames: HashSet<String>,
     /// Unique passwords
     unique_passwords: HashSet<String>,
-    /// Database path (if using persistent storage)
-    db_path: Option<PathBuf>,
+    /// Applied to usernames and passwords before they are counted
+    credentials: CredentialNormalizer,
+    /// Parse counters per source
+    parse_stats: HashMap<String, ParseStats>,
+    /// Most recent parse failures, oldest first
+    parse_failures: VecDeque<ParseFailure>,
+    /// Counts of eventids that mapped to EventType::Unknown
+    unknown_eventids: HashMap<String, u64>,
+    /// Log entries per minute over the trailing hour
+    event_rate: MinuteCounts,
+    /// New sessions per minute over the trailing hour
+    session_rate: MinuteCounts,
+    /// Login attempts per minute over the trailing hour
+    login_rate: MinuteCounts,
+    /// Headline counts per hour over `dashboard.history_hours`
+    hourly: HourlyCounts,
+    /// Per-hour totals the hour in progress is compared with (`[anomaly]`)
+    baselines: Baselines,
+    /// When the last line arrived from any source, parsed or not
+    last_line_at: Option<DateTime<Utc>>,
+    /// Shared copies of IPs and usernames
+    interner: Interner,
+    /// Entries and sessions pruned since the string table was last swept
+    pruned_since_release: usize,
 }
 
 impl Store {
@@ -46,12 +111,31 @@ impl Store {
             sessions: HashMap::new(),
             max_logs,
             max_sessions,
+            keep_raw: config.filter.keep_raw,
             log_entry_ids: Vec::new(),
             session_ids: Vec::new(),
+            logged_sessions: HashMap::new(),
+            scan_summaries: HashMap::new(),
+            collapsed_scans: HashMap::new(),
+            collapsed_order: VecDeque::new(),
             unique_ips: HashSet::new(),
+            ip_activity: HashMap::new(),
+            listeners: HashMap::new(),
+            clients: HashMap::new(),
             unique_usernames: HashSet::new(),
             unique_passwords: HashSet::new(),
-            db_path: None,
+            credentials: CredentialNormalizer::from_config(&config.filter),
+            parse_stats: HashMap::new(),
+            parse_failures: VecDeque::with_capacity(MAX_PARSE_FAILURES),
+            unknown_eventids: HashMap::new(),
+            event_rate: MinuteCounts::new(),
+            session_rate: MinuteCounts::new(),
+            login_rate: MinuteCounts::new(),
+            hourly: HourlyCounts::new(config.dashboard.history_hours),
+            baselines: Baselines::new(&config.anomaly),
+            last_line_at: None,
+            interner: Interner::new(),
+            pruned_since_release: 0,
         };
         
         Ok(store)
@@ -59,91 +143,180 @@ impl Store {
     
     /// Add a new log entry
     pub fn add_log_entry(&mut self, entry: LogEntry) -> Result<()> {
+        self.insert_log_entry(entry);
+        self.prune_log_entries();
+        
+        Ok(())
+    }
+    
+    /// Add log entries in order, pruning once afterwards
+    pub fn add_log_entries(&mut self, entries: Vec<LogEntry>) -> Result<()> {
+        for entry in entries {
+            self.insert_log_entry(entry);
+        }
+        self.prune_log_entries();
+        
+        Ok(())
+    }
+    
+    /// Index and keep a log entry, without pruning
+    fn insert_log_entry(&mut self, mut entry: LogEntry) {
+        if !self.keep_raw {
+            entry.raw = serde_json::Value::Null;
+        }
+        
+        // Share repeated values with earlier entries
+        entry.src_ip = entry.src_ip.map(|ip| self.interner.intern(&ip));
+        entry.dst_ip = entry.dst_ip.map(|ip| self.interner.intern(&ip));
+        entry.username = entry.username.map(|username| self.interner.intern(&username));
+        
         // Track unique values
         if let Some(src_ip) = &entry.src_ip {
-            self.unique_ips.insert(src_ip.clone());
+            self.unique_ips.insert(src_ip.to_string());
+            
+            match self.ip_activity.get_mut(src_ip.as_str()) {
+                Some(activity) => activity.record(entry.timestamp),
+                None => {
+                    self.ip_activity.insert(src_ip.to_string(), IpActivity::new(entry.timestamp));
+                    self.prune_ip_activity();
+                }
+            }
         }
         
         if let Some(username) = &entry.username {
-            self.unique_usernames.insert(username.clone());
+            self.unique_usernames.insert(self.credentials.username(username).into_owned());
         }
         
         if let Some(password) = &entry.password {
-            self.unique_passwords.insert(password.clone());
+            self.unique_passwords.insert(self.credentials.password(password).into_owned());
+        }
+        
+        if entry.event_type == EventType::Unknown {
+            *self.unknown_eventids.entry(entry.eventid.clone()).or_insert(0) += 1;
+        }
+        
+        self.event_rate.record(&entry.timestamp);
+        if matches!(entry.event_type, EventType::LoginAttempt | EventType::LoginSuccess | EventType::LoginFailed) {
+            self.login_rate.record(&entry.timestamp);
+        }
+        
+        if let Some(src_ip) = &entry.src_ip {
+            self.hourly.record_ip(src_ip, &entry.timestamp);
+            self.baselines.record_ip(src_ip, &entry.timestamp);
+        }
+        match entry.event_type {
+            EventType::LoginSuccess => self.hourly.record_login(&entry.timestamp),
+            EventType::LoginFailed => self.baselines.record_login_failure(&entry.timestamp),
+            EventType::FileUpload | EventType::FileDownload if entry.file.as_ref().map_or(false, |file| file.is_malware) => {
+                self.hourly.record_malware(&entry.timestamp);
+            }
+            _ => {}
         }
         
         // Add to chronological index
         self.log_entry_ids.push(entry.id.clone());
         
         // Add to map
-        self.log_entries.insert(entry.id.clone(), entry);
-        
-        // Prune old entries if needed
-        self.prune_log_entries();
-        
-        Ok(())
+        self.log_entries.insert(entry.id.clone(), Arc::new(entry));
     }
     
     /// Get a log entry by ID
     pub fn get_log_entry(&self, id: &str) -> Option<&LogEntry> {
-        self.log_entries.get(id)
+        self.log_entries.get(id).map(Arc::as_ref)
     }
     
     /// Get all log entries
     pub fn get_log_entries(&self) -> Vec<&LogEntry> {
         // Return log entries in chronological order
         self.log_entry_ids.iter()
-            .filter_map(|id| self.log_entries.get(id))
+            .filter_map(|id| self.log_entries.get(id).map(Arc::as_ref))
             .collect()
     }
     
     /// Get log entries by session ID
+    ///
+    /// Entries only carry the logged ID, so when several sessions share it
+    /// each entry goes to the session it falls in (see [`Self::get_session_for_entry`]).
+    #[allow(dead_code)]
     pub fn get_log_entries_by_session(&self, session_id: &str) -> Vec<&LogEntry> {
+        let logged_id = match self.sessions.get(session_id) {
+            Some(session) => session.display_id(),
+            None => return Vec::new(),
+        };
         self.log_entry_ids.iter()
-            .filter_map(|id| self.log_entries.get(id))
-            .filter(|entry| entry.session.as_deref() == Some(session_id))
+            .filter_map(|id| self.log_entries.get(id).map(Arc::as_ref))
+            .filter(|entry| entry.session.as_deref() == Some(logged_id))
+            .filter(|entry| self.get_session_for_entry(entry).map_or(false, |session| session.id == session_id))
             .collect()
     }
     
     /// Get log entries by event type
+    #[allow(dead_code)]
     pub fn get_log_entries_by_event_type(&self, event_type: &EventType) -> Vec<&LogEntry> {
         self.log_entry_ids.iter()
-            .filter_map(|id| self.log_entries.get(id))
+            .filter_map(|id| self.log_entries.get(id).map(Arc::as_ref))
             .filter(|entry| &entry.event_type == event_type)
             .collect()
     }
     
     /// Get log entries by time range
+    #[allow(dead_code)]
     pub fn get_log_entries_by_time_range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<&LogEntry> {
         self.log_entry_ids.iter()
-            .filter_map(|id| self.log_entries.get(id))
+            .filter_map(|id| self.log_entries.get(id).map(Arc::as_ref))
             .filter(|entry| entry.timestamp >= start && entry.timestamp <= end)
             .collect()
     }
     
     /// Get log entries by source IP
+    #[allow(dead_code)]
     pub fn get_log_entries_by_source_ip(&self, src_ip: &str) -> Vec<&LogEntry> {
         self.log_entry_ids.iter()
-            .filter_map(|id| self.log_entries.get(id))
+            .filter_map(|id| self.log_entries.get(id).map(Arc::as_ref))
             .filter(|entry| entry.src_ip.as_deref() == Some(src_ip))
             .collect()
     }
     
     /// Get log entries by username
+    #[allow(dead_code)]
     pub fn get_log_entries_by_username(&self, username: &str) -> Vec<&LogEntry> {
         self.log_entry_ids.iter()
-            .filter_map(|id| self.log_entries.get(id))
+            .filter_map(|id| self.log_entries.get(id).map(Arc::as_ref))
             .filter(|entry| entry.username.as_deref() == Some(username))
             .collect()
     }
     
     /// Add a new session
-    pub fn add_session(&mut self, session: Session) -> Result<()> {
+    pub fn add_session(&mut self, mut session: Session) -> Result<()> {
+        // A folded scan that came back to life leaves its summary
+        let collapsed = self.collapsed_scans.get(session.display_id()).map_or(false, |scan| scan.id == session.id);
+        let promoted = if collapsed {
+            self.promote_scan(session.display_id())
+        } else {
+            self.session_rate.record(&session.start_time);
+            self.hourly.record_session(&session.start_time);
+            self.baselines.record_session(&session.start_time);
+            None
+        };
+        
+        // A promoted scan is still counted on its listener and client; swap that for the update
+        self.intern_session(&mut session);
+        self.count_listener(promoted.as_ref().map(|scan| scan.dst_port), &session);
+        self.count_client(promoted.as_deref(), &session);
+        
         // Add to chronological index
         self.session_ids.push(session.id.clone());
         
+        // Index by logged ID, keeping reused IDs in start order
+        let sessions = &self.sessions;
+        let logged = self.logged_sessions.entry(session.display_id().to_string()).or_default();
+        let index = logged.partition_point(|id| {
+            sessions.get(id).map_or(true, |other| other.start_time <= session.start_time)
+        });
+        logged.insert(index, session.id.clone());
+        
         // Add to map
-        self.sessions.insert(session.id.clone(), session);
+        self.sessions.insert(session.id.clone(), Arc::new(session));
         
         // Prune old sessions if needed
         self.prune_sessions();
@@ -152,35 +325,239 @@ impl Store {
     }
     
     /// Update an existing session
-    pub fn update_session(&mut self, session: Session) -> Result<()> {
+    pub fn update_session(&mut self, mut session: Session) -> Result<()> {
         // Check if session exists
         if !self.sessions.contains_key(&session.id) {
             return Err(anyhow::anyhow!("Session not found: {}", session.id));
         }
         
+        self.intern_session(&mut session);
+        let previous_port = self.sessions.get(&session.id).map(|previous| previous.dst_port);
+        self.count_listener(previous_port, &session);
+        if let Some(previous) = self.sessions.remove(&session.id) {
+            self.count_client(Some(&previous), &session);
+        }
+        
         // Update session
-        self.sessions.insert(session.id.clone(), session);
+        self.sessions.insert(session.id.clone(), Arc::new(session));
         
         Ok(())
     }
     
+    /// Count `session` on its destination port, moving it off `previous_port` if that changed
+    ///
+    /// Sessions start before their connect event is seen, so the port can
+    /// change from unknown (0) to the real listener; port 0 isn't counted.
+    fn count_listener(&mut self, previous_port: Option<u16>, session: &Session) {
+        if previous_port == Some(session.dst_port) {
+            if let Some(listener) = self.listeners.get_mut(&session.dst_port) {
+                listener.protocol.clone_from(&session.protocol);
+            }
+            return;
+        }
+        
+        if let Some(port) = previous_port.filter(|port| *port != 0) {
+            if let Some(listener) = self.listeners.get_mut(&port) {
+                listener.sessions = listener.sessions.saturating_sub(1);
+                if listener.sessions == 0 {
+                    self.listeners.remove(&port);
+                }
+            }
+        }
+        
+        if session.dst_port != 0 {
+            let listener = self.listeners.entry(session.dst_port).or_default();
+            listener.sessions += 1;
+            listener.protocol.clone_from(&session.protocol);
+        }
+    }
+    
+    /// Count `session` under its client fingerprint, taking back what `previous` contributed
+    ///
+    /// The HASSH and banner arrive after the session starts and the risk
+    /// score keeps changing, so every update swaps the old contribution for
+    /// the new one. First and last seen only ever widen.
+    fn count_client(&mut self, previous: Option<&Session>, session: &Session) {
+        let previous_fingerprint = previous.and_then(|previous| Some((previous, previous.client_fingerprint()?)));
+        if let Some((previous, fingerprint)) = previous_fingerprint {
+            if let Some(stats) = self.clients.get_mut(&fingerprint) {
+                stats.sessions = stats.sessions.saturating_sub(1);
+                stats.total_risk = stats.total_risk.saturating_sub(previous.malicious_score as u64);
+                if let Some(count) = stats.ips.get_mut(&previous.src_ip) {
+                    *count -= 1;
+                    if *count == 0 {
+                        stats.ips.remove(&previous.src_ip);
+                    }
+                }
+                if stats.sessions == 0 {
+                    self.clients.remove(&fingerprint);
+                }
+            }
+        }
+        
+        if let Some(fingerprint) = session.client_fingerprint() {
+            let last_activity = session.last_activity.unwrap_or(session.start_time);
+            let stats = self.clients.entry(fingerprint).or_insert_with(|| ClientStats::new(session.start_time));
+            stats.sessions += 1;
+            stats.total_risk += session.malicious_score as u64;
+            *stats.ips.entry(session.src_ip.clone()).or_insert(0) += 1;
+            stats.first_seen = stats.first_seen.min(session.start_time);
+            stats.last_seen = stats.last_seen.max(last_activity);
+        }
+    }
+    
+    /// Replace a session's IPs and username with shared copies
+    fn intern_session(&mut self, session: &mut Session) {
+        session.src_ip = self.interner.intern(&session.src_ip);
+        session.dst_ip = self.interner.intern(&session.dst_ip);
+        
+        if let Some(user) = session.user.as_mut() {
+            user.username = self.interner.intern(&user.username);
+        }
+    }
+    
+    /// Add a session, or replace it if it already exists
+    pub fn upsert_session(&mut self, session: Session) -> Result<()> {
+        if self.sessions.contains_key(&session.id) {
+            self.update_session(session)
+        } else {
+            self.add_session(session)
+        }
+    }
+    
     /// Get a session by ID
     pub fn get_session(&self, id: &str) -> Option<&Session> {
-        self.sessions.get(id)
+        self.sessions.get(id).map(Arc::as_ref)
+    }
+    
+    /// Sessions the honeypot logged as `logged_id`, oldest first
+    pub fn get_sessions_by_logged_id(&self, logged_id: &str) -> Vec<&Session> {
+        self.logged_sessions.get(logged_id)
+            .map(|ids| ids.iter().filter_map(|id| self.sessions.get(id).map(Arc::as_ref)).collect())
+            .unwrap_or_default()
+    }
+    
+    /// The session a log entry belongs to
+    ///
+    /// That is the last session with the entry's logged ID that started by
+    /// the entry's time, or the first one if the entry predates them all.
+    pub fn get_session_for_entry(&self, entry: &LogEntry) -> Option<&Session> {
+        let sessions = self.get_sessions_by_logged_id(entry.session.as_deref()?);
+        sessions.iter()
+            .rev()
+            .find(|session| session.start_time <= entry.timestamp)
+            .or_else(|| sessions.first())
+            .copied()
+    }
+    
+    /// Fold closed scan sessions that ended before `closed_before` into their IP's summary
+    ///
+    /// Returns how many were folded. They keep counting towards listeners and
+    /// clients, as pruned sessions do.
+    pub fn collapse_scans(&mut self, closed_before: DateTime<Utc>) -> usize {
+        let sessions = &self.sessions;
+        let folding: Vec<String> = self.session_ids.iter()
+            .filter(|id| {
+                sessions.get(*id).map_or(false, |session| {
+                    session.is_scan() && session.end_time.map_or(false, |end| end < closed_before)
+                })
+            })
+            .cloned()
+            .collect();
+        if folding.is_empty() {
+            return 0;
+        }
+        
+        for id in &folding {
+            let session = match self.sessions.remove(id) {
+                Some(session) => session,
+                None => continue,
+            };
+            self.unindex_logged(&session);
+            
+            let end = session.end_time.unwrap_or(session.start_time);
+            let summary = self.scan_summaries.entry(session.src_ip.clone())
+                .or_insert_with(|| ScanSummary::new(session.src_ip.clone(), session.start_time));
+            summary.sessions += 1;
+            summary.first_seen = summary.first_seen.min(session.start_time);
+            summary.last_seen = summary.last_seen.max(end);
+            *summary.ports.entry(session.dst_port).or_insert(0) += 1;
+            
+            let logged_id = session.display_id().to_string();
+            if self.collapsed_scans.insert(logged_id.clone(), session).is_none() {
+                self.collapsed_order.push_back(logged_id);
+            }
+        }
+        
+        let folded: HashSet<&String> = folding.iter().collect();
+        self.session_ids.retain(|id| !folded.contains(id));
+        
+        while self.collapsed_order.len() > MAX_COLLAPSED_SCANS {
+            if let Some(oldest) = self.collapsed_order.pop_front() {
+                self.collapsed_scans.remove(&oldest);
+            }
+        }
+        self.pruned_since_release += folding.len();
+        self.release_interned();
+        
+        debug!("Collapsed {} scan sessions into per-IP summaries", folding.len());
+        folding.len()
+    }
+    
+    /// Take the collapsed session logged as `logged_id` back out of its IP's summary
+    ///
+    /// First and last seen keep the span the summary covered. Returns the
+    /// session as it was folded.
+    fn promote_scan(&mut self, logged_id: &str) -> Option<Arc<Session>> {
+        let session = self.collapsed_scans.remove(logged_id)?;
+        self.collapsed_order.retain(|id| id != logged_id);
+        
+        if let Some(summary) = self.scan_summaries.get_mut(&session.src_ip) {
+            summary.sessions = summary.sessions.saturating_sub(1);
+            if let Some(count) = summary.ports.get_mut(&session.dst_port) {
+                *count -= 1;
+                if *count == 0 {
+                    summary.ports.remove(&session.dst_port);
+                }
+            }
+            if summary.sessions == 0 {
+                self.scan_summaries.remove(&session.src_ip);
+            }
+        }
+        
+        debug!("Promoted session {} out of the scan summaries", session.id);
+        Some(session)
+    }
+    
+    /// The collapsed scan session last logged as `logged_id`, if it can still be promoted
+    pub fn get_collapsed_scan(&self, logged_id: &str) -> Option<&Session> {
+        self.collapsed_scans.get(logged_id).map(Arc::as_ref)
+    }
+    
+    /// Scan summaries, the most sessions first
+    pub fn get_scan_summaries(&self) -> Vec<&ScanSummary> {
+        let mut summaries: Vec<&ScanSummary> = self.scan_summaries.values().collect();
+        summaries.sort_by(|a, b| b.sessions.cmp(&a.sessions).then_with(|| a.src_ip.cmp(&b.src_ip)));
+        summaries
+    }
+    
+    /// Sessions folded into scan summaries
+    pub fn get_collapsed_scan_count(&self) -> u64 {
+        self.scan_summaries.values().map(|summary| summary.sessions).sum()
     }
     
     /// Get all sessions
     pub fn get_sessions(&self) -> Vec<&Session> {
         // Return sessions in chronological order
         self.session_ids.iter()
-            .filter_map(|id| self.sessions.get(id))
+            .filter_map(|id| self.sessions.get(id).map(Arc::as_ref))
             .collect()
     }
     
     /// Get active sessions (not ended)
     pub fn get_active_sessions(&self) -> Vec<&Session> {
         self.session_ids.iter()
-            .filter_map(|id| self.sessions.get(id))
+            .filter_map(|id| self.sessions.get(id).map(Arc::as_ref))
             .filter(|session| session.end_time.is_none())
             .collect()
     }
@@ -188,15 +565,16 @@ impl Store {
     /// Get sessions by source IP
     pub fn get_sessions_by_source_ip(&self, src_ip: &str) -> Vec<&Session> {
         self.session_ids.iter()
-            .filter_map(|id| self.sessions.get(id))
+            .filter_map(|id| self.sessions.get(id).map(Arc::as_ref))
             .filter(|session| session.src_ip == src_ip)
             .collect()
     }
     
     /// Get sessions by username
+    #[allow(dead_code)]
     pub fn get_sessions_by_username(&self, username: &str) -> Vec<&Session> {
         self.session_ids.iter()
-            .filter_map(|id| self.sessions.get(id))
+            .filter_map(|id| self.sessions.get(id).map(Arc::as_ref))
             .filter(|session| {
                 session.user.as_ref().map_or(false, |user| user.username == username)
             })
@@ -204,9 +582,10 @@ impl Store {
     }
     
     /// Get sessions by time range
+    #[allow(dead_code)]
     pub fn get_sessions_by_time_range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<&Session> {
         self.session_ids.iter()
-            .filter_map(|id| self.sessions.get(id))
+            .filter_map(|id| self.sessions.get(id).map(Arc::as_ref))
             .filter(|session| {
                 (session.start_time >= start && session.start_time <= end) ||
                 (session.end_time.map_or(false, |end_time| end_time >= start && end_time <= end))
@@ -214,11 +593,80 @@ impl Store {
             .collect()
     }
     
+    /// Get file transfers across all sessions, newest first
+    pub fn get_recent_files(&self) -> Vec<(&Session, &FileTransfer)> {
+        let mut files: Vec<_> = self.session_ids.iter()
+            .filter_map(|id| self.sessions.get(id).map(Arc::as_ref))
+            .flat_map(|session| session.files.iter().map(move |file| (session, file)))
+            .collect();
+        
+        files.sort_by(|a, b| b.1.timestamp.cmp(&a.1.timestamp));
+        files
+    }
+    
+    /// Get log entries per minute over the trailing hour
+    pub fn get_event_rate(&self) -> &MinuteCounts {
+        &self.event_rate
+    }
+    
+    /// Get new sessions per minute over the trailing hour
+    ///
+    /// Sessions are counted at their connect time, so this is also the
+    /// connection rate.
+    pub fn get_session_rate(&self) -> &MinuteCounts {
+        &self.session_rate
+    }
+    
+    /// Headline counts per hour, for comparing periods
+    pub fn get_hourly_counts(&self) -> &HourlyCounts {
+        &self.hourly
+    }
+    
+    /// Rates running above their baseline in the hour `now` falls in
+    pub fn get_anomalies(&self, now: &DateTime<Utc>) -> Vec<Anomaly> {
+        self.baselines.anomalies(now)
+    }
+    
+    /// Bring back the persisted totals of a closed hour
+    pub fn restore_hour_totals(&mut self, totals: HourTotals) {
+        self.baselines.restore(totals);
+    }
+    
+    /// Hours closed since the last call, for the store writer to persist
+    pub fn take_unsaved_hours(&mut self) -> Vec<HourTotals> {
+        self.baselines.take_unsaved()
+    }
+    
+    /// Get login attempts per minute over the trailing hour
+    pub fn get_login_rate(&self) -> &MinuteCounts {
+        &self.login_rate
+    }
+    
+    /// Get when the last line arrived from any source
+    pub fn get_last_line_time(&self) -> Option<DateTime<Utc>> {
+        self.last_line_at
+    }
+    
     /// Get unique source IPs
     pub fn get_unique_source_ips(&self) -> &HashSet<String> {
         &self.unique_ips
     }
     
+    /// Get when a source IP was first and last seen
+    pub fn get_ip_activity(&self, ip: &str) -> Option<&IpActivity> {
+        self.ip_activity.get(ip)
+    }
+    
+    /// Sessions per client fingerprint
+    pub fn get_client_stats(&self) -> &HashMap<ClientFingerprint, ClientStats> {
+        &self.clients
+    }
+    
+    /// Sessions per destination port
+    pub fn get_listener_counts(&self) -> &HashMap<u16, ListenerCount> {
+        &self.listeners
+    }
+    
     /// Get unique usernames
     pub fn get_unique_usernames(&self) -> &HashSet<String> {
         &self.unique_usernames
@@ -229,6 +677,11 @@ impl Store {
         &self.unique_passwords
     }
     
+    /// Normalizer credential aggregates are counted with
+    pub fn credential_normalizer(&self) -> &CredentialNormalizer {
+        &self.credentials
+    }
+    
     /// Get total number of log entries
     pub fn get_log_entry_count(&self) -> usize {
         self.log_entries.len()
@@ -239,7 +692,54 @@ impl Store {
         self.sessions.len()
     }
     
+    /// Record a successfully parsed line from `source`
+    pub fn record_parsed(&mut self, source: &str) {
+        self.parse_stats.entry(source.to_string()).or_default().parsed += 1;
+        self.last_line_at = Some(Utc::now());
+    }
+    
+    /// Record a line from `source` that failed to parse
+    pub fn record_parse_failure(&mut self, source: &str, line: &str, error: &str) {
+        self.parse_stats.entry(source.to_string()).or_default().failed += 1;
+        self.last_line_at = Some(Utc::now());
+        
+        if self.parse_failures.len() >= MAX_PARSE_FAILURES {
+            self.parse_failures.pop_front();
+        }
+        
+        self.parse_failures.push_back(ParseFailure {
+            source: source.to_string(),
+            timestamp: Utc::now(),
+            line: line.to_string(),
+            error: error.to_string(),
+        });
+    }
+    
+    /// Get parse counters per source
+    pub fn get_parse_stats(&self) -> &HashMap<String, ParseStats> {
+        &self.parse_stats
+    }
+    
+    /// Get parse counters summed over all sources
+    pub fn get_total_parse_stats(&self) -> ParseStats {
+        self.parse_stats.values().fold(ParseStats::default(), |total, stats| ParseStats {
+            parsed: total.parsed + stats.parsed,
+            failed: total.failed + stats.failed,
+        })
+    }
+    
+    /// Get counts of eventids that are not mapped to a known event type
+    pub fn get_unknown_eventids(&self) -> &HashMap<String, u64> {
+        &self.unknown_eventids
+    }
+    
+    /// Get recent parse failures, most recent first
+    pub fn get_parse_failures(&self) -> Vec<&ParseFailure> {
+        self.parse_failures.iter().rev().collect()
+    }
+    
     /// Search log entries by keyword
+    #[allow(dead_code)]
     pub fn search_log_entries(&self, keyword: &str, case_sensitive: bool) -> Vec<&LogEntry> {
         let keyword = if case_sensitive {
             keyword.to_string()
@@ -248,7 +748,7 @@ impl Store {
         };
         
         self.log_entry_ids.iter()
-            .filter_map(|id| self.log_entries.get(id))
+            .filter_map(|id| self.log_entries.get(id).map(Arc::as_ref))
             .filter(|entry| {
                 // Search in various fields
                 let command_match = entry.command.as_ref().map_or(false, |cmd| {
@@ -286,22 +786,48 @@ impl Store {
         self.sessions.clear();
         self.log_entry_ids.clear();
         self.session_ids.clear();
+        self.logged_sessions.clear();
+        self.scan_summaries.clear();
+        self.collapsed_scans.clear();
+        self.collapsed_order.clear();
         self.unique_ips.clear();
+        self.ip_activity.clear();
+        self.listeners.clear();
+        self.clients.clear();
         self.unique_usernames.clear();
         self.unique_passwords.clear();
+        self.parse_stats.clear();
+        self.parse_failures.clear();
+        self.unknown_eventids.clear();
+        self.event_rate.clear();
+        self.session_rate.clear();
+        self.login_rate.clear();
+        self.hourly.clear();
+        self.baselines.clear();
+        self.interner.release_unused();
         
         debug!("Cleared all data from store");
     }
     
     /// Prune old log entries if needed
     fn prune_log_entries(&mut self) {
-        while self.log_entries.len() > self.max_logs {
-            if let Some(oldest_id) = self.log_entry_ids.first().cloned() {
-                self.log_entries.remove(&oldest_id);
-                self.log_entry_ids.remove(0);
-                debug!("Pruned oldest log entry: {}", oldest_id);
-            } else {
-                break;
+        // A batch can overshoot by many entries, so drop them in one go
+        let excess = self.log_entries.len().saturating_sub(self.max_logs).min(self.log_entry_ids.len());
+        for oldest_id in self.log_entry_ids.drain(..excess) {
+            self.log_entries.remove(&oldest_id);
+            debug!("Pruned oldest log entry: {}", oldest_id);
+        }
+        self.pruned_since_release += excess;
+        
+        self.release_interned();
+    }
+    
+    /// Drop `session` from the logged ID index
+    fn unindex_logged(&mut self, session: &Session) {
+        if let Some(ids) = self.logged_sessions.get_mut(session.display_id()) {
+            ids.retain(|id| *id != session.id);
+            if ids.is_empty() {
+                self.logged_sessions.remove(session.display_id());
             }
         }
     }
@@ -310,12 +836,275 @@ impl Store {
     fn prune_sessions(&mut self) {
         while self.sessions.len() > self.max_sessions {
             if let Some(oldest_id) = self.session_ids.first().cloned() {
-                self.sessions.remove(&oldest_id);
+                if let Some(oldest) = self.sessions.remove(&oldest_id) {
+                    self.unindex_logged(&oldest);
+                }
                 self.session_ids.remove(0);
+                self.pruned_since_release += 1;
                 debug!("Pruned oldest session: {}", oldest_id);
             } else {
                 break;
             }
         }
+        
+        self.release_interned();
+    }
+    
+    /// Forget the least recently seen IPs once too many are tracked
+    ///
+    /// Drops a tenth of the map at a time so the sort is not paid on every new IP.
+    fn prune_ip_activity(&mut self) {
+        if self.ip_activity.len() <= MAX_TRACKED_IPS {
+            return;
+        }
+        
+        let mut by_last_seen: Vec<(DateTime<Utc>, String)> = self.ip_activity.iter()
+            .map(|(ip, activity)| (activity.last_seen, ip.clone()))
+            .collect();
+        by_last_seen.sort();
+        
+        let excess = self.ip_activity.len() - MAX_TRACKED_IPS + MAX_TRACKED_IPS / 10;
+        for (_, ip) in by_last_seen.into_iter().take(excess) {
+            self.ip_activity.remove(&ip);
+        }
+        
+        debug!("Pruned {} least recently seen IPs from activity tracking", excess);
+    }
+    
+    /// Drop interned strings no longer used by any entry or session, now and then
+    fn release_interned(&mut self) {
+        if self.pruned_since_release >= RELEASE_EVERY {
+            self.interner.release_unused();
+            self.pruned_since_release = 0;
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::store_writer::{apply, StoreCommand};
+    use crate::data::testing::{at, command, login, scan, session};
+    use pretty_assertions::assert_eq;
+
+    /// A store with `commands` applied as the writer applies them
+    fn store(commands: impl IntoIterator<Item = StoreCommand>) -> Store {
+        let mut store = Store::new(&Config::default()).unwrap();
+        for command in commands {
+            apply(&mut store, command);
+        }
+        store
+    }
+
+    fn upsert(session: &Session) -> StoreCommand {
+        StoreCommand::UpsertSession(Box::new(session.clone()))
+    }
+
+    #[test]
+    fn a_late_login_promotes_a_collapsed_scan() {
+        let mut session = scan("abc123", "198.51.100.4", at(0), 2);
+        session.hassh = Some("hassh-1".to_string());
+        let mut store = store([upsert(&session), StoreCommand::CollapseScans(at(60))]);
+        assert!(store.get_collapsed_scan("abc123").is_some());
+
+        // The login was logged late, after the scan was folded
+        session.user = Some(login("root", at(1)));
+        apply(&mut store, upsert(&session));
+
+        assert_eq!(store.get_sessions_by_logged_id("abc123").len(), 1);
+        assert!(store.get_collapsed_scan("abc123").is_none());
+        assert!(store.get_scan_summaries().is_empty());
+        assert_eq!(store.get_listener_counts()[&22].sessions, 1);
+        let clients: Vec<_> = store.get_client_stats().values().collect();
+        assert_eq!(clients.len(), 1);
+        assert_eq!(clients[0].sessions, 1);
+        assert_eq!(clients[0].ips[&session.src_ip], 1);
+    }
+
+    #[test]
+    fn collapsed_scans_keep_counting_on_listeners() {
+        let store = store([
+            upsert(&scan("a", "198.51.100.4", at(0), 1)),
+            upsert(&scan("b", "198.51.100.4", at(5), 1)),
+            StoreCommand::CollapseScans(at(60)),
+        ]);
+
+        assert_eq!(store.get_session_count(), 0);
+        assert_eq!(store.get_scan_summaries()[0].sessions, 2);
+        assert_eq!(store.get_listener_counts()[&22].sessions, 2);
+    }
+
+    #[test]
+    fn a_batch_counts_its_lines_and_prunes_the_oldest() {
+        let mut config = Config::default();
+        config.filter.max_logs = 2;
+        let mut store = Store::new(&config).unwrap();
+        let entries = ["id", "w", "ls"].iter().enumerate()
+            .map(|(i, input)| command("s1", "198.51.100.4", input, at(i as i64)))
+            .collect();
+        apply(&mut store, StoreCommand::AddLogEntries { source: "cowrie.json".to_string(), entries });
+
+        let inputs: Vec<_> = store.get_log_entries().iter().filter_map(|entry| entry.command.as_deref()).collect();
+        assert_eq!(inputs, ["w", "ls"]);
+        assert_eq!(store.get_parse_stats()["cowrie.json"].parsed, 3);
+        assert!(store.get_unique_source_ips().contains("198.51.100.4"));
+    }
+
+    #[test]
+    fn unmapped_eventids_are_counted_by_name() {
+        let unknown = |eventid: &str, seconds| LogEntry {
+            event_type: EventType::Unknown,
+            eventid: eventid.to_string(),
+            ..command("s1", "198.51.100.4", "ls", at(seconds))
+        };
+        let entries = vec![
+            unknown("cowrie.session.params", 0),
+            command("s1", "198.51.100.4", "id", at(1)),
+            unknown("cowrie.client.size", 2),
+            unknown("cowrie.session.params", 3),
+        ];
+        let store = store([StoreCommand::AddLogEntries { source: "cowrie.json".to_string(), entries }]);
+
+        let mut unknown: Vec<_> = store.get_unknown_eventids().iter().map(|(eventid, count)| (eventid.as_str(), *count)).collect();
+        unknown.sort_unstable();
+        assert_eq!(unknown, [("cowrie.client.size", 1), ("cowrie.session.params", 2)]);
+    }
+
+    /// Heap bytes held by a JSON value, as serde_json lays it out
+    fn json_bytes(value: &serde_json::Value) -> usize {
+        use serde_json::Value;
+        match value {
+            Value::String(text) => text.capacity(),
+            Value::Array(items) => {
+                items.capacity() * std::mem::size_of::<Value>() + items.iter().map(json_bytes).sum::<usize>()
+            }
+            Value::Object(fields) => fields.iter()
+                .map(|(key, value)| key.capacity() + std::mem::size_of::<Value>() + json_bytes(value))
+                .sum(),
+            _ => 0,
+        }
     }
-}
\ No newline at end of file
+
+    /// Raw JSON bytes a store keeps for 10,000 fixture entries, with `filter.keep_raw` as given
+    fn raw_bytes(keep_raw: bool) -> usize {
+        let mut config = Config::default();
+        config.filter.max_logs = 10_000;
+        config.filter.keep_raw = keep_raw;
+        let analyzer = crate::core::LogAnalyzer::new();
+        let lines: Vec<&str> = include_str!("../core/fixtures/cowrie-2.x.json").lines().collect();
+        let entries = (0..10_000)
+            .map(|i| {
+                let line = lines[i % lines.len()].replace("a1b2c3d4e5f6", &format!("s{:06}", i / lines.len()));
+                analyzer.parse_log_entry(&line).unwrap()
+            })
+            .collect();
+        let mut store = Store::new(&config).unwrap();
+        apply(&mut store, StoreCommand::AddLogEntries { source: "cowrie.json".to_string(), entries });
+
+        let kept = store.get_log_entries();
+        assert_eq!(kept.len(), 10_000);
+        // Parsed fields stay either way
+        assert!(kept.iter().all(|entry| entry.session.is_some() && entry.fields.contains_key("sensor")));
+        kept.iter().map(|entry| json_bytes(&entry.raw)).sum()
+    }
+
+    #[test]
+    fn not_keeping_raw_json_frees_its_memory() {
+        let (kept, dropped) = (raw_bytes(true), raw_bytes(false));
+        println!("raw JSON for 10,000 entries: {} KiB kept, {} KiB with keep_raw = false", kept / 1024, dropped / 1024);
+
+        assert!(kept > 4 * 1024 * 1024, "{} bytes", kept);
+        assert_eq!(dropped, 0);
+    }
+
+    /// A failed login as `username` from `src_ip`, in session `number`
+    fn login_failed(src_ip: &str, username: &str, number: usize) -> LogEntry {
+        LogEntry {
+            event_type: EventType::LoginFailed,
+            eventid: "cowrie.login.failed".to_string(),
+            username: Some(username.into()),
+            ..crate::data::testing::connect(&format!("s{}", number), src_ip, at(number as i64))
+        }
+    }
+
+    /// Heap bytes of the distinct allocations behind `strings`, each with its reference counts
+    fn string_bytes<'a>(strings: impl Iterator<Item = &'a IStr>) -> usize {
+        let mut seen = HashSet::new();
+        strings
+            .filter(|value| seen.insert(value.as_str().as_ptr()))
+            .map(|value| value.len() + 2 * std::mem::size_of::<usize>())
+            .sum()
+    }
+
+    #[test]
+    fn a_brute_force_keeps_one_copy_of_each_ip() {
+        let mut config = Config::default();
+        config.filter.max_logs = 100_000;
+        let entries: Vec<LogEntry> = (0..100_000)
+            .map(|i| login_failed(&format!("198.51.100.{}", i % 50), "root", i))
+            .collect();
+        let parsed = string_bytes(entries.iter().filter_map(|entry| entry.src_ip.as_ref()));
+
+        let mut store = Store::new(&config).unwrap();
+        apply(&mut store, StoreCommand::AddLogEntries { source: "cowrie.json".to_string(), entries });
+        let stored = store.get_log_entries();
+        let interned = string_bytes(stored.iter().filter_map(|entry| entry.src_ip.as_ref()));
+        println!("src_ip of 100,000 entries from 50 IPs: {} KiB as parsed, {} bytes interned", parsed / 1024, interned);
+
+        assert_eq!(stored.len(), 100_000);
+        let one_each: usize = (0..50).map(|i| format!("198.51.100.{}", i).len() + 2 * std::mem::size_of::<usize>()).sum();
+        assert_eq!(interned, one_each);
+        assert!(parsed > 1000 * interned);
+    }
+
+    #[test]
+    fn pruning_releases_strings_only_the_table_holds() {
+        let mut config = Config::default();
+        config.filter.max_logs = 1000;
+        let mut store = Store::new(&config).unwrap();
+        let old = (0..1000)
+            .map(|i| login_failed(&format!("198.51.100.{}", i % 50), &format!("user{}", i % 30), i))
+            .collect();
+        apply(&mut store, StoreCommand::AddLogEntries { source: "cowrie.json".to_string(), entries: old });
+        // The IPs, the usernames and the honeypot's address
+        assert_eq!(store.interner.len(), 81);
+
+        let new = (1000..2000).map(|i| login_failed(&format!("198.51.100.{}", i % 50), "root", i)).collect();
+        apply(&mut store, StoreCommand::AddLogEntries { source: "cowrie.json".to_string(), entries: new });
+
+        // The pruned entries' usernames are gone; the IPs are still in use
+        assert_eq!(store.interner.len(), 52);
+    }
+
+    #[test]
+    fn an_update_replaces_the_session_and_a_snapshot_keeps_the_old_one() {
+        let open = session("abc", "198.51.100.4", at(0));
+        let mut store = store([upsert(&open)]);
+        let snapshot = store.clone();
+
+        let mut closed = open.clone();
+        closed.end_time = Some(at(30));
+        apply(&mut store, upsert(&closed));
+
+        assert_eq!(store.get_session_count(), 1);
+        assert_eq!(store.get_session(&open.id).unwrap().end_time, Some(at(30)));
+        assert_eq!(snapshot.get_session(&open.id).unwrap().end_time, None);
+    }
+
+    #[test]
+    fn clearing_forgets_everything() {
+        let store = store([
+            upsert(&session("abc", "198.51.100.4", at(0))),
+            StoreCommand::AddLogEntry(command("abc", "198.51.100.4", "id", at(1))),
+            StoreCommand::RecordParseFailure {
+                source: "cowrie.json".to_string(),
+                line: "{".to_string(),
+                error: "EOF".to_string(),
+            },
+            StoreCommand::Clear,
+        ]);
+
+        assert_eq!((store.get_session_count(), store.get_log_entry_count()), (0, 0));
+        assert!(store.get_parse_failures().is_empty());
+        assert!(store.get_unique_source_ips().is_empty());
+    }
+}
@@ -0,0 +1,891 @@
+use chrono::{DateTime, Utc};
+use log::{debug, error, info};
+use parking_lot::RwLock;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinHandle;
+
+use crate::config::Config;
+use crate::data::backend::{History, StorageBackend, StoredCounts};
+use crate::data::baseline::HourTotals;
+use crate::data::models::{LogEntry, Session};
+use crate::data::store::Store;
+use crate::utils::ip::IpList;
+
+/// Capacity of the command channel; senders wait when the writer falls behind
+const COMMAND_BUFFER: usize = 10_000;
+
+/// Entries an [`EntryBatch`] collects before queueing them as one command
+const BATCH_SIZE: usize = 256;
+
+/// Longest changes wait to be published while no snapshot is being read
+const UNREAD_PUBLISH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Immutable view of the store as of the last publish
+pub type StoreSnapshot = Store;
+
+/// Mutation applied by the store writer
+#[derive(Debug, Clone)]
+pub enum StoreCommand {
+    /// Add a parsed log entry
+    AddLogEntry(LogEntry),
+    /// Add log entries parsed from `source`, counting each as parsed
+    AddLogEntries {
+        source: String,
+        entries: Vec<LogEntry>,
+    },
+    /// Add or replace a session
+    UpsertSession(Box<Session>),
+    /// Count a successfully parsed line from a source
+    RecordParsed(String),
+    /// Record a line that failed to parse
+    RecordParseFailure {
+        source: String,
+        line: String,
+        error: String,
+    },
+    /// Fold scan sessions closed before the given time into per-IP summaries
+    CollapseScans(DateTime<Utc>),
+    /// Totals of a closed hour for the anomaly baselines, persisted or restored
+    RecordHourTotals(HourTotals),
+    /// Remove all data
+    #[allow(dead_code)]
+    Clear,
+    /// Wake the sender once every command queued before it has been applied
+    Applied(Arc<Notify>),
+}
+
+/// What happens to traffic from `alert.ip_whitelist` (`filter.exclude_whitelisted`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WhitelistExclusion {
+    /// Stored and shown like any other traffic
+    Off,
+    /// Stored, but hidden from the views unless re-included
+    View,
+    /// Never stored
+    Ingest,
+}
+
+/// Decides which commands carry whitelisted traffic
+#[derive(Debug, Clone)]
+pub struct WhitelistFilter {
+    /// What to do with whitelisted traffic
+    pub exclusion: WhitelistExclusion,
+    /// Whitelisted addresses and ranges
+    pub ips: IpList,
+}
+
+impl WhitelistFilter {
+    /// Filter configured by `filter.exclude_whitelisted` and `alert.ip_whitelist`
+    pub fn from_config(config: &Config) -> Self {
+        let exclusion = match config.filter.exclude_whitelisted.as_str() {
+            "view" => WhitelistExclusion::View,
+            "ingest" => WhitelistExclusion::Ingest,
+            _ => WhitelistExclusion::Off,
+        };
+        let ips = IpList::parse(&config.alert.ip_whitelist);
+
+        Self { exclusion, ips }
+    }
+
+    /// Whether `command` adds whitelisted traffic that should be kept out
+    ///
+    /// Batches are never excluded as a whole; see [`Self::excludes_entry`].
+    fn excludes(&self, command: &StoreCommand) -> bool {
+        if self.exclusion == WhitelistExclusion::Off {
+            return false;
+        }
+
+        match command {
+            StoreCommand::AddLogEntry(entry) => self.excludes_entry(entry),
+            StoreCommand::UpsertSession(session) => self.ips.matches(&session.src_ip),
+            _ => false,
+        }
+    }
+
+    /// Whether `entry` is whitelisted traffic that should be kept out
+    fn excludes_entry(&self, entry: &LogEntry) -> bool {
+        self.exclusion != WhitelistExclusion::Off && entry.src_ip.as_deref().map_or(false, |ip| self.ips.matches(ip))
+    }
+}
+
+/// Handle to the store
+///
+/// A single writer task owns the [`Store`] and applies commands in the order
+/// they are sent. Readers never lock the store itself; they get the most
+/// recently published [`StoreSnapshot`]. Publishing shares the store's
+/// entries and sessions rather than copying them, and only happens for
+/// changes someone reads: at most every publish interval while snapshots
+/// are being taken, otherwise every [`UNREAD_PUBLISH_INTERVAL`].
+///
+/// With [`WhitelistExclusion::View`] the writer keeps a second store that
+/// also holds whitelisted traffic, so it can be re-included without a replay.
+#[derive(Clone)]
+pub struct StoreHandle {
+    /// Commands for the writer task
+    commands: mpsc::Sender<StoreCommand>,
+    /// Latest published snapshot
+    snapshot: Arc<RwLock<Arc<StoreSnapshot>>>,
+    /// Latest published snapshot including whitelisted traffic (view exclusion only)
+    full_snapshot: Option<Arc<RwLock<Arc<StoreSnapshot>>>>,
+    /// Read from `full_snapshot` instead of `snapshot`
+    include_whitelisted: Arc<AtomicBool>,
+    /// Set when a snapshot is taken, so the writer publishes its next changes
+    read: Arc<AtomicBool>,
+    /// Whitelisted traffic kept out of `snapshot`
+    whitelist: Arc<WhitelistFilter>,
+    /// What the storage backend holds, as of its last write (`None` = memory only)
+    stored: Option<Arc<RwLock<StoredCounts>>>,
+    /// Queries on what the storage backend holds
+    history: Option<Arc<dyn History>>,
+}
+
+impl StoreHandle {
+    /// Start the writer task, publishing a snapshot at most every `publish_interval`
+    ///
+    /// With a `backend`, the newest data it holds is restored first, and every
+    /// command applied from then on is written through to it on a blocking
+    /// thread of its own.
+    pub fn spawn(
+        store: Store,
+        whitelist: WhitelistFilter,
+        backend: Option<Box<dyn StorageBackend>>,
+        publish_interval: Duration,
+    ) -> (Self, JoinHandle<()>) {
+        let (commands, rx) = mpsc::channel(COMMAND_BUFFER);
+        let snapshot = Arc::new(RwLock::new(Arc::new(store.clone())));
+        let full_store = match whitelist.exclusion {
+            WhitelistExclusion::View => Some(store.clone()),
+            _ => None,
+        };
+        let full_snapshot = full_store.as_ref().map(|full| Arc::new(RwLock::new(Arc::new(full.clone()))));
+
+        let mut writer = Writer {
+            store,
+            full_store,
+            whitelist: whitelist.clone(),
+            snapshot: snapshot.clone(),
+            full_snapshot: full_snapshot.clone(),
+            read: Arc::new(AtomicBool::new(false)),
+            backend: None,
+        };
+
+        let mut stored = None;
+        let mut history = None;
+        if let Some(mut backend) = backend {
+            // Replayed before the backend is attached, so nothing is written back
+            match backend.restore() {
+                Ok(commands) => {
+                    info!("Restoring {} stored entries and sessions from {}", commands.len(), backend.name());
+                    for command in commands {
+                        writer.restore(command);
+                    }
+                    writer.publish();
+                }
+                Err(e) => error!("Failed to restore data from {} storage: {:#}", backend.name(), e),
+            }
+            match backend.history() {
+                Ok(queries) => history = Some(Arc::from(queries)),
+                Err(e) => error!("Unable to query {} storage; views only show what is in memory: {:#}", backend.name(), e),
+            }
+            let counts = Arc::new(RwLock::new(backend.counts()));
+            writer.backend = Some(spawn_backend(backend, counts.clone()));
+            stored = Some(counts);
+        }
+
+        let read = writer.read.clone();
+        let task = tokio::spawn(run_writer(writer, rx, publish_interval));
+
+        let handle = Self {
+            commands,
+            snapshot,
+            full_snapshot,
+            include_whitelisted: Arc::new(AtomicBool::new(false)),
+            read,
+            whitelist: Arc::new(whitelist),
+            stored,
+            history,
+        };
+        (handle, task)
+    }
+
+    /// Latest published snapshot
+    pub fn snapshot(&self) -> Arc<StoreSnapshot> {
+        self.read.store(true, Ordering::Relaxed);
+        match &self.full_snapshot {
+            Some(full) if self.includes_whitelisted() => full.read().clone(),
+            _ => self.snapshot.read().clone(),
+        }
+    }
+
+    /// Whether whitelisted traffic is hidden but can be re-included
+    pub fn can_include_whitelisted(&self) -> bool {
+        self.full_snapshot.is_some()
+    }
+
+    /// Whether whitelisted traffic is currently re-included
+    pub fn includes_whitelisted(&self) -> bool {
+        self.include_whitelisted.load(Ordering::Relaxed)
+    }
+
+    /// Whether traffic from `ip` is currently kept out of the snapshot
+    pub fn hides_ip(&self, ip: &str) -> bool {
+        match self.whitelist.exclusion {
+            WhitelistExclusion::Off => false,
+            WhitelistExclusion::View if self.includes_whitelisted() => false,
+            _ => self.whitelist.ips.matches(ip),
+        }
+    }
+
+    /// What the storage backend holds in total, if there is one
+    pub fn stored_counts(&self) -> Option<StoredCounts> {
+        self.stored.as_ref().map(|stored| *stored.read())
+    }
+
+    /// Sessions from `src_ip`, oldest first, including those only the backend still holds
+    ///
+    /// Without a backend (or when it can't be read) these are the snapshot's.
+    pub fn sessions_by_source_ip(&self, src_ip: &str) -> Vec<Session> {
+        let snapshot = self.snapshot();
+        let current = snapshot.get_sessions_by_source_ip(src_ip);
+        let stored = self.query(|history| history.sessions_by_source_ip(src_ip));
+        let mut sessions = merge_sessions(stored, current);
+        sessions.sort_by_key(|session| session.start_time);
+        sessions
+    }
+
+    /// Log entries of `session` in the order they were ingested, including those only the backend still holds
+    ///
+    /// Entries carry the logged ID only, so they are assigned among the
+    /// sessions sharing it as [`Store::get_session_for_entry`] does.
+    pub fn session_log_entries(&self, session: &Session) -> Vec<LogEntry> {
+        let snapshot = self.snapshot();
+        let logged_id = session.display_id();
+        let mut sessions = merge_sessions(
+            self.query(|history| history.sessions_by_logged_id(logged_id)),
+            snapshot.get_sessions_by_logged_id(logged_id),
+        );
+        sessions.sort_by_key(|session| session.start_time);
+
+        let mut entries = self.query(|history| history.log_entries_by_logged_id(logged_id));
+        let stored: HashSet<String> = entries.iter().map(|entry| entry.id.clone()).collect();
+        entries.extend(
+            snapshot.get_log_entries().into_iter()
+                .filter(|entry| entry.session.as_deref() == Some(logged_id) && !stored.contains(&entry.id))
+                .cloned(),
+        );
+
+        entries.retain(|entry| {
+            let owner = sessions.iter()
+                .rev()
+                .find(|other| other.start_time <= entry.timestamp)
+                .or_else(|| sessions.first());
+            owner.map_or(false, |owner| owner.id == session.id)
+        });
+        entries
+    }
+
+    /// Run a backend query, treating a failed one (or no backend) as finding nothing
+    fn query<T>(&self, query: impl FnOnce(&dyn History) -> anyhow::Result<Vec<T>>) -> Vec<T> {
+        let history = match &self.history {
+            Some(history) => history,
+            None => return Vec::new(),
+        };
+        query(history.as_ref()).unwrap_or_else(|e| {
+            error!("Failed to query stored data: {:#}", e);
+            Vec::new()
+        })
+    }
+
+    /// Show or hide whitelisted traffic again; no effect unless [`Self::can_include_whitelisted`]
+    pub fn set_include_whitelisted(&self, include: bool) {
+        self.include_whitelisted.store(include, Ordering::Relaxed);
+    }
+
+    /// Queue a command for the writer
+    pub async fn send(&self, command: StoreCommand) {
+        if self.commands.send(command).await.is_err() {
+            error!("Store writer has stopped; dropping command");
+        }
+    }
+
+    /// Queue a log entry
+    pub async fn add_log_entry(&self, entry: LogEntry) {
+        self.send(StoreCommand::AddLogEntry(entry)).await;
+    }
+
+    /// Wait until the writer has applied (and persisted) everything queued so far
+    ///
+    /// Returns straight away if the writer has stopped.
+    pub async fn applied(&self) {
+        let notify = Arc::new(Notify::new());
+        if self.commands.send(StoreCommand::Applied(notify.clone())).await.is_ok() {
+            notify.notified().await;
+        }
+    }
+
+    /// Collect entries parsed from `source` and queue them in batches
+    pub fn batch(&self, source: &str) -> EntryBatch {
+        EntryBatch {
+            store: self.clone(),
+            source: source.to_string(),
+            entries: Vec::with_capacity(BATCH_SIZE),
+        }
+    }
+    
+    /// Queue a session insert or update
+    pub async fn upsert_session(&self, session: Session) {
+        self.send(StoreCommand::UpsertSession(Box::new(session))).await;
+    }
+
+    /// Queue a parse success for `source`
+    pub async fn record_parsed(&self, source: &str) {
+        self.send(StoreCommand::RecordParsed(source.to_string())).await;
+    }
+
+    /// Queue a parse failure for `source`
+    pub async fn record_parse_failure(&self, source: &str, line: &str, error: &str) {
+        self.send(StoreCommand::RecordParseFailure {
+            source: source.to_string(),
+            line: line.to_string(),
+            error: error.to_string(),
+        })
+        .await;
+    }
+}
+
+/// Parsed entries from one source, queued for the writer as a single command
+///
+/// Replaces a `record_parsed` and `add_log_entry` per line when a watcher
+/// reads many lines at once. Entries are queued every [`BATCH_SIZE`] and on
+/// [`EntryBatch::flush`], which must be called once the lines at hand are
+/// read; entries still held when the batch is dropped are lost.
+pub struct EntryBatch {
+    /// Where the batch is queued
+    store: StoreHandle,
+    /// Source the entries are counted against
+    source: String,
+    /// Entries not yet queued
+    entries: Vec<LogEntry>,
+}
+
+impl EntryBatch {
+    /// Add an entry, queueing the batch once it is full
+    ///
+    /// Returns whether the batch was queued.
+    pub async fn push(&mut self, entry: LogEntry) -> bool {
+        self.entries.push(entry);
+        if self.entries.len() < BATCH_SIZE {
+            return false;
+        }
+        self.flush().await;
+        true
+    }
+
+    /// Queue whatever has been collected
+    pub async fn flush(&mut self) {
+        if self.entries.is_empty() {
+            return;
+        }
+
+        let entries = std::mem::replace(&mut self.entries, Vec::with_capacity(BATCH_SIZE));
+        self.store.send(StoreCommand::AddLogEntries {
+            source: self.source.clone(),
+            entries,
+        })
+        .await;
+    }
+}
+
+/// State owned by the writer task
+struct Writer {
+    /// Store the views read, without excluded traffic
+    store: Store,
+    /// Store including whitelisted traffic (view exclusion only)
+    full_store: Option<Store>,
+    /// Which commands to keep out of `store`
+    whitelist: WhitelistFilter,
+    /// Where `store` is published
+    snapshot: Arc<RwLock<Arc<StoreSnapshot>>>,
+    /// Where `full_store` is published
+    full_snapshot: Option<Arc<RwLock<Arc<StoreSnapshot>>>>,
+    /// Whether a snapshot was taken since the last publish
+    read: Arc<AtomicBool>,
+    /// Commands for the thread writing to `storage.backend`
+    backend: Option<mpsc::Sender<StoreCommand>>,
+}
+
+impl Writer {
+    /// Apply a command to every store it belongs in, then queue it for the backend
+    async fn apply(&mut self, command: StoreCommand) {
+        if let StoreCommand::Applied(notify) = command {
+            // The backend answers once it has written everything before it
+            match &self.backend {
+                Some(backend) => {
+                    if backend.send(StoreCommand::Applied(notify.clone())).await.is_err() {
+                        notify.notify_one();
+                    }
+                }
+                None => notify.notify_one(),
+            }
+            return;
+        }
+
+        let persisted = self.backend.as_ref().and_then(|_| self.persisted(&command));
+        self.apply_to_stores(command);
+        let closed_hours = self.closed_hours();
+
+        for command in persisted.into_iter().chain(closed_hours) {
+            self.persist(command).await;
+        }
+    }
+
+    /// Apply a command brought back from the backend, without writing it back
+    fn restore(&mut self, command: StoreCommand) {
+        self.apply_to_stores(command);
+        self.closed_hours();
+    }
+
+    /// Apply a command to every store it belongs in
+    fn apply_to_stores(&mut self, command: StoreCommand) {
+        // Whitelisted entries are dropped from a batch, but still count as parsed
+        if let StoreCommand::AddLogEntries { source, entries } = command {
+            if let Some(full_store) = &mut self.full_store {
+                add_batch(full_store, &source, entries.len(), entries.clone());
+            }
+            let parsed = entries.len();
+            let whitelist = &self.whitelist;
+            let kept = entries.into_iter().filter(|entry| !whitelist.excludes_entry(entry)).collect();
+            add_batch(&mut self.store, &source, parsed, kept);
+        } else {
+            let excluded = self.whitelist.excludes(&command);
+
+            if let Some(full_store) = &mut self.full_store {
+                apply(full_store, command.clone());
+            }
+            if !excluded {
+                apply(&mut self.store, command);
+            }
+        }
+    }
+
+    /// Hours the store closed, as commands persisting them so its baselines survive a restart
+    ///
+    /// Baselines follow the views, so whitelisted traffic is left out of them.
+    fn closed_hours(&mut self) -> Vec<StoreCommand> {
+        if let Some(full_store) = &mut self.full_store {
+            full_store.take_unsaved_hours();
+        }
+        self.store.take_unsaved_hours().into_iter().map(StoreCommand::RecordHourTotals).collect()
+    }
+
+    /// `command` as the backend should store it, as the widest store keeps it; `None` to skip it
+    fn persisted(&self, command: &StoreCommand) -> Option<StoreCommand> {
+        // Whitelisted traffic is only kept out when it is never stored
+        if self.full_store.is_some() || self.whitelist.exclusion == WhitelistExclusion::Off {
+            Some(command.clone())
+        } else if let StoreCommand::AddLogEntries { source, entries } = command {
+            let whitelist = &self.whitelist;
+            Some(StoreCommand::AddLogEntries {
+                source: source.clone(),
+                entries: entries.iter().filter(|entry| !whitelist.excludes_entry(entry)).cloned().collect(),
+            })
+        } else if self.whitelist.excludes(command) {
+            None
+        } else {
+            Some(command.clone())
+        }
+    }
+
+    /// Queue `command` for the backend thread
+    async fn persist(&self, command: StoreCommand) {
+        if let Some(backend) = &self.backend {
+            if backend.send(command).await.is_err() {
+                error!("Storage writer has stopped; dropping command");
+            }
+        }
+    }
+
+    /// Publish the current stores to readers
+    fn publish(&self) {
+        *self.snapshot.write() = Arc::new(self.store.clone());
+        if let (Some(full_store), Some(full_snapshot)) = (&self.full_store, &self.full_snapshot) {
+            *full_snapshot.write() = Arc::new(full_store.clone());
+        }
+    }
+}
+
+/// Write commands through to `backend` on a blocking thread, publishing its counts to `stored`
+///
+/// The thread answers [`StoreCommand::Applied`] once everything queued before
+/// it is written, and stops once the writer drops the returned sender.
+fn spawn_backend(mut backend: Box<dyn StorageBackend>, stored: Arc<RwLock<StoredCounts>>) -> mpsc::Sender<StoreCommand> {
+    let (commands, mut rx) = mpsc::channel(COMMAND_BUFFER);
+
+    tokio::task::spawn_blocking(move || {
+        while let Some(command) = rx.blocking_recv() {
+            if let StoreCommand::Applied(notify) = command {
+                notify.notify_one();
+                continue;
+            }
+            if let Err(e) = backend.write(&command) {
+                error!("Error writing to {} storage: {:#}", backend.name(), e);
+            }
+            *stored.write() = backend.counts();
+        }
+        debug!("{} storage writer stopped", backend.name());
+    });
+
+    commands
+}
+
+/// `stored` sessions with the `current` ones (which are fresher) in place of any they share an ID with
+fn merge_sessions(stored: Vec<Session>, current: Vec<&Session>) -> Vec<Session> {
+    let current_ids: HashSet<&str> = current.iter().map(|session| session.id.as_str()).collect();
+    stored.into_iter()
+        .filter(|session| !current_ids.contains(session.id.as_str()))
+        .chain(current.into_iter().cloned())
+        .collect()
+}
+
+/// Apply commands until every handle is dropped, publishing snapshots as we go
+async fn run_writer(
+    mut writer: Writer,
+    mut rx: mpsc::Receiver<StoreCommand>,
+    publish_interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(publish_interval);
+    let mut dirty = false;
+    let mut published = Instant::now();
+
+    loop {
+        tokio::select! {
+            command = rx.recv() => match command {
+                Some(command) => {
+                    writer.apply(command).await;
+                    dirty = true;
+                }
+                None => break,
+            },
+            _ = ticker.tick() => {
+                // A snapshot taken while nothing changed asks for the next change
+                if dirty && (writer.read.swap(false, Ordering::Relaxed) || published.elapsed() >= UNREAD_PUBLISH_INTERVAL) {
+                    writer.publish();
+                    dirty = false;
+                    published = Instant::now();
+                }
+            }
+        }
+    }
+
+    writer.publish();
+    debug!("Store writer stopped");
+}
+
+/// Apply a single command to the store
+pub(crate) fn apply(store: &mut Store, command: StoreCommand) {
+    let result = match command {
+        StoreCommand::AddLogEntry(entry) => store.add_log_entry(entry),
+        StoreCommand::AddLogEntries { source, entries } => {
+            let parsed = entries.len();
+            add_batch(store, &source, parsed, entries);
+            Ok(())
+        }
+        StoreCommand::UpsertSession(session) => store.upsert_session(*session),
+        StoreCommand::RecordParsed(source) => {
+            store.record_parsed(&source);
+            Ok(())
+        }
+        StoreCommand::RecordParseFailure { source, line, error } => {
+            store.record_parse_failure(&source, &line, &error);
+            Ok(())
+        }
+        StoreCommand::CollapseScans(closed_before) => {
+            store.collapse_scans(closed_before);
+            Ok(())
+        }
+        StoreCommand::RecordHourTotals(totals) => {
+            store.restore_hour_totals(totals);
+            Ok(())
+        }
+        StoreCommand::Clear => {
+            store.clear();
+            Ok(())
+        }
+        StoreCommand::Applied(notify) => {
+            notify.notify_one();
+            Ok(())
+        }
+    };
+
+    if let Err(e) = result {
+        error!("Error applying store command: {}", e);
+    }
+}
+
+/// Count `parsed` lines from `source` and add `entries` under one write
+fn add_batch(store: &mut Store, source: &str, parsed: usize, entries: Vec<LogEntry>) {
+    for _ in 0..parsed {
+        store.record_parsed(source);
+    }
+    if let Err(e) = store.add_log_entries(entries) {
+        error!("Error adding log entries: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "sqlite-db")]
+    use crate::data::sqlite::SqliteBackend;
+    use crate::data::testing::{at, command, eventually};
+    #[cfg(feature = "sqlite-db")]
+    use crate::data::testing::session;
+    use pretty_assertions::assert_eq;
+
+    /// A UI drawing at 60 frames a second until `done`, returning how long each `read` took
+    fn frames<F, R>(done: &Arc<AtomicBool>, read: F) -> JoinHandle<Vec<Duration>>
+    where
+        F: Fn() -> R + Send + 'static,
+        R: std::future::Future<Output = ()> + Send,
+    {
+        let done = done.clone();
+        tokio::spawn(async move {
+            let mut waits = Vec::new();
+            while !done.load(Ordering::Relaxed) {
+                tokio::time::sleep(Duration::from_millis(16)).await;
+                let started = Instant::now();
+                read().await;
+                waits.push(started.elapsed());
+            }
+            waits.sort();
+            waits
+        })
+    }
+
+    /// The 99th percentile and the longest of sorted `waits`
+    fn p99_and_max(waits: &[Duration]) -> (Duration, Duration) {
+        (waits[waits.len() * 99 / 100], waits[waits.len() - 1])
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture backfill_of_200k`
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore]
+    async fn backfill_of_200k_entries_while_the_ui_reads() {
+        let mut config = Config::default();
+        config.filter.max_logs = 100_000;
+        let total = 200_000;
+        let entry = |i: usize| command(&format!("s{}", i / 10), &format!("198.51.{}.{}", i / 256 % 256, i % 256), "uname -a", at(i as i64));
+
+        // Before the writer task: a write lock per entry, shared with the UI
+        let locked = Arc::new(tokio::sync::RwLock::new(Store::new(&config).unwrap()));
+        let done = Arc::new(AtomicBool::new(false));
+        let reader = locked.clone();
+        let frames_locked = frames(&done, move || {
+            let reader = reader.clone();
+            async move {
+                drop(reader.read().await);
+            }
+        });
+        let started = Instant::now();
+        for i in 0..total {
+            let mut store = locked.write().await;
+            apply(&mut store, StoreCommand::RecordParsed("backfill".to_string()));
+            apply(&mut store, StoreCommand::AddLogEntry(entry(i)));
+        }
+        let elapsed_locked = started.elapsed();
+        done.store(true, Ordering::Relaxed);
+        let waits_locked = frames_locked.await.unwrap();
+
+        let (store, _writer) = StoreHandle::spawn(
+            Store::new(&config).unwrap(),
+            WhitelistFilter::from_config(&config),
+            None,
+            Duration::from_millis(250),
+        );
+        let done = Arc::new(AtomicBool::new(false));
+        let reader = store.clone();
+        let frames_snapshot = frames(&done, move || {
+            drop(reader.snapshot());
+            async {}
+        });
+        let started = Instant::now();
+        let mut batch = store.batch("backfill");
+        for i in 0..total {
+            batch.push(entry(i)).await;
+        }
+        batch.flush().await;
+        store.applied().await;
+        let elapsed = started.elapsed();
+        done.store(true, Ordering::Relaxed);
+        let waits = frames_snapshot.await.unwrap();
+
+        let (p99_locked, max_locked) = p99_and_max(&waits_locked);
+        let (p99, max) = p99_and_max(&waits);
+        println!("write lock: {} entries in {:?}, UI reads waited {:?} at the 99th percentile, {:?} at most",
+            total, elapsed_locked, p99_locked, max_locked);
+        println!("writer task: {} entries in {:?}, UI reads waited {:?} at the 99th percentile, {:?} at most",
+            total, elapsed, p99, max);
+        assert!(max < Duration::from_millis(16), "a UI read waited longer than a frame: {:?}", max);
+        assert!(p99 <= p99_locked, "UI reads waited longer than with the write lock: {:?} > {:?}", p99, p99_locked);
+    }
+
+    /// A handle on an in-memory store for `config`, publishing every 10ms
+    fn in_memory(config: &Config) -> StoreHandle {
+        let (handle, _writer) = StoreHandle::spawn(
+            Store::new(config).unwrap(),
+            WhitelistFilter::from_config(config),
+            None,
+            Duration::from_millis(10),
+        );
+        handle
+    }
+
+    /// Inputs of the entries `store` holds, oldest first
+    fn inputs(store: &Store) -> Vec<String> {
+        let mut entries = store.get_log_entries();
+        entries.sort_by_key(|entry| entry.timestamp);
+        entries.into_iter().filter_map(|entry| entry.command.clone()).collect()
+    }
+
+    #[tokio::test]
+    async fn a_batch_is_queued_when_full_and_on_flush() {
+        let store = in_memory(&Config::default());
+        let mut batch = store.batch("cowrie.json");
+        for i in 0..BATCH_SIZE - 1 {
+            assert!(!batch.push(command("s1", "198.51.100.4", "id", at(i as i64))).await);
+        }
+        assert!(batch.push(command("s1", "198.51.100.4", "id", at(BATCH_SIZE as i64))).await);
+        batch.push(command("s1", "198.51.100.4", "id", at(BATCH_SIZE as i64 + 1))).await;
+
+        store.applied().await;
+        eventually(|| store.snapshot().get_parse_stats().get("cowrie.json").map_or(false, |stats| stats.parsed == BATCH_SIZE as u64)).await;
+        assert_eq!(store.snapshot().get_log_entries().len(), BATCH_SIZE);
+
+        batch.flush().await;
+        store.applied().await;
+        eventually(|| store.snapshot().get_log_entries().len() == BATCH_SIZE + 1).await;
+    }
+
+    #[test]
+    fn a_batch_leaves_the_store_as_the_same_lines_one_by_one() {
+        let mut config = Config::default();
+        config.filter.max_logs = 5;
+        let entries: Vec<LogEntry> = (0..8)
+            .map(|i| command("s1", &format!("198.51.100.{}", i % 3), &format!("echo {}", i), at(i)))
+            .collect();
+
+        let mut one_by_one = Store::new(&config).unwrap();
+        for entry in entries.clone() {
+            apply(&mut one_by_one, StoreCommand::RecordParsed("cowrie.json".to_string()));
+            apply(&mut one_by_one, StoreCommand::AddLogEntry(entry));
+        }
+        let mut batched = Store::new(&config).unwrap();
+        apply(&mut batched, StoreCommand::AddLogEntries { source: "cowrie.json".to_string(), entries });
+
+        assert_eq!(inputs(&batched), ["echo 3", "echo 4", "echo 5", "echo 6", "echo 7"]);
+        assert_eq!(inputs(&batched), inputs(&one_by_one));
+        assert_eq!(batched.get_parse_stats()["cowrie.json"].parsed, 8);
+        assert_eq!(batched.get_unique_source_ips(), one_by_one.get_unique_source_ips());
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture per_line_and_batched`
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore]
+    async fn per_line_and_batched_writes_of_100k_entries() {
+        let mut config = Config::default();
+        config.filter.max_logs = 50_000;
+        let total = 100_000;
+        let entry = |i: usize| command(&format!("s{}", i / 10), &format!("198.51.{}.{}", i / 256 % 256, i % 256), "uname -a", at(i as i64));
+
+        let store = in_memory(&config);
+        let started = Instant::now();
+        for i in 0..total {
+            store.record_parsed("cowrie.json").await;
+            store.add_log_entry(entry(i)).await;
+        }
+        store.applied().await;
+        let per_line = started.elapsed();
+
+        let store = in_memory(&config);
+        let started = Instant::now();
+        let mut batch = store.batch("cowrie.json");
+        for i in 0..total {
+            batch.push(entry(i)).await;
+        }
+        batch.flush().await;
+        store.applied().await;
+        let batched = started.elapsed();
+
+        println!("{} entries: {:?} line by line, {:?} in batches of {}", total, per_line, batched, BATCH_SIZE);
+        assert!(batched < per_line, "batches should be faster than single lines");
+    }
+
+    /// A handle keeping `max` sessions and entries in memory, writing through to a database in `dir`
+    #[cfg(feature = "sqlite-db")]
+    fn spawn(dir: &std::path::Path, max: usize) -> StoreHandle {
+        let mut config = Config::default();
+        config.filter.max_sessions = max;
+        config.filter.max_logs = max;
+        let backend = SqliteBackend::open(&dir.join("xkippo.db"), max, max).unwrap();
+        let (handle, _writer) = StoreHandle::spawn(
+            Store::new(&config).unwrap(),
+            WhitelistFilter::from_config(&config),
+            Some(Box::new(backend)),
+            Duration::from_millis(10),
+        );
+        handle
+    }
+
+    #[cfg(feature = "sqlite-db")]
+    #[tokio::test]
+    async fn applied_waits_for_the_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = spawn(dir.path(), 100);
+        for i in 0..3 {
+            store.upsert_session(session(&format!("s{}", i), "198.51.100.4", at(i))).await;
+        }
+        store.applied().await;
+
+        assert_eq!(store.stored_counts().unwrap().sessions, 3);
+    }
+
+    #[cfg(feature = "sqlite-db")]
+    #[tokio::test]
+    async fn history_reaches_past_the_window() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = spawn(dir.path(), 2);
+        for i in 0..4 {
+            store.upsert_session(session(&format!("s{}", i), "198.51.100.4", at(i * 60))).await;
+        }
+        store.upsert_session(session("other", "203.0.113.9", at(0))).await;
+        store.applied().await;
+
+        let ids: Vec<String> = store.sessions_by_source_ip("198.51.100.4").into_iter().map(|s| s.logged_id).collect();
+        assert_eq!(ids, ["s0", "s1", "s2", "s3"]);
+    }
+
+    #[cfg(feature = "sqlite-db")]
+    #[tokio::test]
+    async fn a_session_keeps_its_pruned_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = spawn(dir.path(), 2);
+        let open = session("abc", "198.51.100.4", at(0));
+        store.upsert_session(open.clone()).await;
+        // A later session reusing the logged ID takes the entries after it starts
+        store.upsert_session(session("abc", "198.51.100.4", at(100))).await;
+        let mut batch = store.batch("test");
+        for (i, input) in ["uname -a", "id", "w", "ls"].iter().enumerate() {
+            batch.push(command("abc", "198.51.100.4", input, at(i as i64 * 10))).await;
+        }
+        batch.push(command("abc", "198.51.100.4", "exit", at(150))).await;
+        batch.flush().await;
+        store.applied().await;
+
+        let inputs: Vec<String> = store.session_log_entries(&open).into_iter().filter_map(|entry| entry.command).collect();
+        assert_eq!(inputs, ["uname -a", "id", "w", "ls"]);
+    }
+}
@@ -0,0 +1,127 @@
+//! Sessions and log entries for tests, with only the fields a test sets filled in
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::data::{EventType, LogEntry, Session, User};
+
+/// `seconds` after a fixed start, so tests don't depend on the clock
+pub fn at(seconds: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(1_700_000_000 + seconds, 0).unwrap()
+}
+
+/// An open session logged as `logged_id` from `src_ip` to port 22
+pub fn session(logged_id: &str, src_ip: &str, start: DateTime<Utc>) -> Session {
+    Session {
+        id: Session::key(logged_id, &start),
+        logged_id: logged_id.to_string(),
+        start_time: start,
+        end_time: None,
+        src_ip: src_ip.into(),
+        src_port: 40_000,
+        dst_ip: "10.0.0.2".into(),
+        dst_port: 22,
+        protocol: "ssh".to_string(),
+        client_version: None,
+        hassh: None,
+        user: None,
+        duration: None,
+        last_activity: None,
+        active_duration: None,
+        commands: Vec::new(),
+        files: Vec::new(),
+        forwards: Vec::new(),
+        geo_location: None,
+        tty_log: None,
+        shasum: None,
+        is_malicious: false,
+        malicious_score: 0,
+        tags: Vec::new(),
+        techniques: Vec::new(),
+    }
+}
+
+/// `session` closed after `seconds` without doing anything: a scan
+pub fn scan(logged_id: &str, src_ip: &str, start: DateTime<Utc>, seconds: i64) -> Session {
+    let mut scan = session(logged_id, src_ip, start);
+    scan.end_time = Some(start + chrono::Duration::seconds(seconds));
+    scan.duration = Some(seconds as u64);
+    scan
+}
+
+/// A successful login as `username` at `time`
+pub fn login(username: &str, time: DateTime<Utc>) -> User {
+    User {
+        username: username.into(),
+        password: Some("123456".to_string()),
+        key_fingerprint: None,
+        login_success: true,
+        login_time: time,
+    }
+}
+
+/// A connection from `src_ip` logged as `logged_id` at `time`
+pub fn connect(logged_id: &str, src_ip: &str, time: DateTime<Utc>) -> LogEntry {
+    LogEntry {
+        id: format!("{}-{}-connect", logged_id, time.timestamp()),
+        timestamp: time,
+        event_type: EventType::Connect,
+        eventid: "cowrie.session.connect".to_string(),
+        session: Some(logged_id.to_string()),
+        src_ip: Some(src_ip.into()),
+        src_port: Some(40_000),
+        dst_ip: Some("10.0.0.2".into()),
+        dst_port: Some(22),
+        username: None,
+        password: None,
+        command: None,
+        file: None,
+        fields: Default::default(),
+        raw: serde_json::json!({ "eventid": "cowrie.session.connect", "session": logged_id, "src_ip": src_ip }),
+    }
+}
+
+/// A command `input` run in the session logged as `logged_id` at `time`
+pub fn command(logged_id: &str, src_ip: &str, input: &str, time: DateTime<Utc>) -> LogEntry {
+    LogEntry {
+        id: format!("{}-{}-{}", logged_id, time.timestamp(), input),
+        timestamp: time,
+        event_type: EventType::Command,
+        eventid: "cowrie.command.input".to_string(),
+        session: Some(logged_id.to_string()),
+        src_ip: Some(src_ip.into()),
+        src_port: None,
+        dst_ip: None,
+        dst_port: None,
+        username: None,
+        password: None,
+        command: Some(input.to_string()),
+        file: None,
+        fields: Default::default(),
+        raw: serde_json::json!({ "eventid": "cowrie.command.input", "session": logged_id, "input": input }),
+    }
+}
+
+/// A Cowrie ttylog of write records: direction (1 typed, 2 shown), second and data
+pub fn tty_log(records: &[(i32, u32, &str)]) -> Vec<u8> {
+    let mut log = Vec::new();
+    for &(direction, second, data) in records {
+        for field in [3, 0, data.len() as i32, direction] {
+            log.extend_from_slice(&field.to_le_bytes());
+        }
+        log.extend_from_slice(&second.to_le_bytes());
+        log.extend_from_slice(&0u32.to_le_bytes());
+        log.extend_from_slice(data.as_bytes());
+    }
+    log
+}
+
+/// Wait up to five seconds for `condition`, failing the test if it never holds
+pub async fn eventually(condition: impl Fn() -> bool) {
+    for _ in 0..500 {
+        if condition() {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("condition still false after 5s");
+}
@@ -2,13 +2,14 @@ mod app;
 mod config;
 mod core;
 mod data;
+#[allow(dead_code)] // not loaded by the app yet
 mod plugins;
 mod ui;
 mod utils;
 
 use anyhow::{Context, Result};
 use clap::Parser;
-use log::{info, LevelFilter};
+use log::{info, warn};
 
 /// Command line arguments for xKippo
 #[derive(Parser, Debug)]
@@ -31,9 +32,21 @@ struct Args {
     #[clap(short, long)]
     debug: bool,
 
-    /// Run setup script to configure xKippo
+    /// Open the setup wizard to create or update the configuration file
     #[clap(long)]
     setup: bool,
+
+    /// Rewrite an older configuration file in the current format, keeping a .bak copy
+    #[clap(long)]
+    migrate_config: bool,
+
+    /// Only show events from this time on, in ui.timezone (e.g. "2024-03-31 02:30:00")
+    #[clap(long, value_name = "TIME")]
+    from: Option<String>,
+
+    /// Only show events up to this time, in ui.timezone
+    #[clap(long, value_name = "TIME")]
+    to: Option<String>,
 }
 
 #[tokio::main]
@@ -41,73 +54,63 @@ async fn main() -> Result<()> {
     // Parse command line arguments
     let args = Args::parse();
 
-    // Configure logger
-    let log_level = match args.verbose {
-        0 => LevelFilter::Info,
-        1 => LevelFilter::Debug,
-        _ => LevelFilter::Trace,
-    };
-
-    env_logger::Builder::new()
-        .filter_level(log_level)
-        .format_timestamp_secs()
-        .init();
+    // Install the logger; records are held back until [logging] is applied
+    utils::logger::init()?;
 
     info!("Starting xKippo-tui v{}", env!("CARGO_PKG_VERSION"));
 
-    // Load configuration
+    // Load configuration, noting first runs before a default file is written
     let config_path = args.config;
-    let config = config::Config::load(config_path.as_deref())
-        .context("Failed to load configuration")?;
+    let first_run = !config::Config::exists(config_path.as_deref());
+    let config = match config::Config::load(config_path.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            // Nothing else will show what was logged so far
+            let console = config::LoggingConfig { console: true, ..Default::default() };
+            let _ = utils::logger::configure(&console, args.verbose);
+            return Err(e.context("Failed to load configuration"));
+        }
+    };
+    if let Err(e) = utils::logger::configure(&config.logging, args.verbose) {
+        warn!("{:#}; logging to the log viewer (F12) only", e);
+    }
 
-    // Run setup mode if requested
-    if args.setup {
-        return run_setup(&config).await;
+    // The configuration was upgraded in memory; write it back only when asked
+    if args.migrate_config {
+        if let Some(path) = &config.source_path {
+            match config::migrate::upgrade_file(path)? {
+                Some(from) => info!(
+                    "Upgraded {} from config version {} to {}; the original is {}",
+                    path.display(),
+                    from,
+                    config::migrate::CONFIG_VERSION,
+                    config::migrate::backup_path(path).display()
+                ),
+                None => info!("{} is already config version {}", path.display(), config::migrate::CONFIG_VERSION),
+            }
+        }
     }
 
     // Initialize the application
-    let app = app::App::new(config).await?;
+    let mut app = app::App::new(config).await?;
+
+    // A fixed time range is entered in the same zone the UI shows times in
+    if let Some(from) = &args.from {
+        app.filters.from_time = Some(app.clock.parse(from).context("Invalid --from")?);
+    }
+    if let Some(to) = &args.to {
+        app.filters.to_time = Some(app.clock.parse(to).context("Invalid --to")?);
+    }
+
+    // Open the setup wizard on first run or when asked to
+    if args.setup || first_run {
+        let path = config::wizard::target_path(config_path.as_deref(), &app.config)?;
+        app.open_wizard(path);
+    }
 
     // Start the UI
-    ui::start_ui(app).context("UI error")?;
+    ui::start_ui(app).await.context("UI error")?;
 
     info!("xKippo-tui shut down successfully");
     Ok(())
 }
-
-/// Run the setup wizard
-async fn run_setup(config: &config::Config) -> Result<()> {
-    use std::process::Command;
-    use std::path::Path;
-    
-    println!("Running xKippo-tui advanced security analyst setup...");
-    
-    // Find the setup script
-    let script_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scripts").join("setup.sh");
-    
-    if !script_path.exists() {
-        return Err(anyhow::anyhow!("Setup script not found at {}", script_path.display()));
-    }
-    
-    // Ensure the script is executable
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let metadata = std::fs::metadata(&script_path)?;
-        let mut perms = metadata.permissions();
-        perms.set_mode(0o755); // rwxr-xr-x
-        std::fs::set_permissions(&script_path, perms)?;
-    }
-    
-    // Execute the setup script
-    let status = Command::new(&script_path)
-        .status()
-        .context("Failed to execute setup script")?;
-    
-    if !status.success() {
-        return Err(anyhow::anyhow!("Setup script failed with exit code: {}", status));
-    }
-    
-    println!("Setup completed successfully. Run xKippo-tui to start monitoring.");
-    Ok(())
-}
\ No newline at end of file
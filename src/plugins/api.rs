@@ -3,8 +3,8 @@ use serde_json::Value;
 
 use crate::data::Store as DataStore;
 use crate::data::LogEntry;
-use crate::core::alert_engine::Alert;
-use crate::config::Settings;
+use crate::core::Alert;
+use crate::config::Config as Settings;
 
 /// Plugin metadata
 #[derive(Debug, Clone)]
@@ -3,9 +3,9 @@ use std::fs;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 
-use crate::config::Settings;
+use crate::config::Config as Settings;
 use crate::data::models::LogEntry;
-use crate::data::store::DataStore;
+use crate::data::Store as DataStore;
 use crate::plugins::api::{Plugin, PluginMetadata};
 use crate::utils::errors::AppError;
 
@@ -66,26 +66,24 @@ impl PluginManager {
         let plugin_dir = Path::new(&self.plugin_dir);
         
         if !plugin_dir.exists() || !plugin_dir.is_dir() {
-            return Ok(); // Plugin directory doesn't exist, nothing to load
+            return Ok(()); // Plugin directory doesn't exist, nothing to load
         }
         
         // In a real implementation, we would load dynamic libraries or scripts from the plugin directory
         // For now, we'll just log the found plugins
         
-        for entry in fs::read_dir(plugin_dir).map_err(|e| AppError::PluginLoadError(e.to_string()))? {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if path.is_file() && path.extension().and_then(|ext| ext.to_str()) == Some("so") {
-                    // Here we would load the dynamic library and register the plugin
-                    // For example:
-                    //
-                    // let lib = unsafe { Library::new(path) }.map_err(|e| AppError::PluginLoadError(e.to_string()))?;
-                    // let constructor: Symbol<extern fn() -> Box<dyn Plugin>> = unsafe {
-                    //     lib.get(b"create_plugin")
-                    // }.map_err(|e| AppError::PluginLoadError(e.to_string()))?;
-                    // let plugin = constructor();
-                    // self.register_plugin(plugin)?;
-                }
+        for entry in fs::read_dir(plugin_dir).map_err(|e| AppError::PluginLoadError(e.to_string()))?.flatten() {
+            let path = entry.path();
+            if path.is_file() && path.extension().and_then(|ext| ext.to_str()) == Some("so") {
+                // Here we would load the dynamic library and register the plugin
+                // For example:
+                //
+                // let lib = unsafe { Library::new(path) }.map_err(|e| AppError::PluginLoadError(e.to_string()))?;
+                // let constructor: Symbol<extern fn() -> Box<dyn Plugin>> = unsafe {
+                //     lib.get(b"create_plugin")
+                // }.map_err(|e| AppError::PluginLoadError(e.to_string()))?;
+                // let plugin = constructor();
+                // self.register_plugin(plugin)?;
             }
         }
         
@@ -99,7 +97,7 @@ impl PluginManager {
         
         // Check if plugin is enabled in settings
         if !self.settings.plugins.enabled_plugins.contains(&name) {
-            return Ok();
+            return Ok(());
         }
         
         // Get plugin-specific settings
@@ -126,8 +124,8 @@ impl PluginManager {
             return;
         }
         
-        let plugins = self.plugins.lock().unwrap();
-        for (name, plugin) in plugins.iter() {
+        let mut plugins = self.plugins.lock().unwrap();
+        for (name, plugin) in plugins.iter_mut() {
             if let Err(e) = plugin.process_log(log_entry) {
                 eprintln!("Error processing log with plugin {}: {}", name, e);
             }
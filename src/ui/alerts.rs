@@ -0,0 +1,180 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, TableState, Wrap},
+    Frame,
+};
+
+use crate::app::App;
+use crate::core::{Alert, Severity};
+use crate::data::attack::attack_mapping;
+use crate::data::{Session, StoreSnapshot};
+use crate::ui::anonymize::Anonymizer;
+use crate::ui::panels::render_empty_state;
+use crate::ui::sanitize::escape_controls;
+
+/// Full-screen alerts view, opened with `a` on the security tab
+#[derive(Debug, Default)]
+pub struct AlertsView {
+    /// Selected row, newest first
+    pub cursor: usize,
+}
+
+/// History positions of the alerts at or above `min_severity`, newest first
+pub fn visible_alerts(alerts: &[Alert], min_severity: Severity) -> Vec<usize> {
+    (0..alerts.len())
+        .rev()
+        .filter(|index| alerts[*index].severity >= min_severity)
+        .collect()
+}
+
+/// The session `alert` was raised for
+///
+/// Alerts raised from log entries name the session as the honeypot logged it,
+/// which may have been reused; the one started last before the alert is taken.
+pub fn alert_session<'a>(store: &'a StoreSnapshot, alert: &Alert) -> Option<&'a Session> {
+    let id = alert.alert_type.session_id()?;
+    store.get_session(id).or_else(|| {
+        let sessions = store.get_sessions_by_logged_id(id);
+        sessions.iter()
+            .rev()
+            .find(|session| session.start_time <= alert.timestamp)
+            .or_else(|| sessions.last())
+            .copied()
+    })
+}
+
+/// Draw the alerts view over the tab area
+pub fn render_alerts_view(f: &mut Frame, view: &AlertsView, app: &App, area: Rect) {
+    let history = app.alert_history.lock();
+    let alerts = history.alerts();
+    let visible = visible_alerts(alerts, app.alert_min_severity);
+    let unacknowledged = alerts.iter().filter(|alert| !alert.acknowledged).count();
+
+    f.render_widget(Clear, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(6), Constraint::Length(9)].as_ref())
+        .split(area);
+
+    let filter = match app.alert_min_severity {
+        Severity::Info => String::new(),
+        minimum => format!(", {} and up", minimum),
+    };
+    let block = Block::default()
+        .title(format!("Alerts: {} ({} unacknowledged{})", alerts.len(), unacknowledged, filter))
+        .title(
+            ratatui::widgets::block::Title::from(" Space: acknowledge  c/C: clear acknowledged/all  f: severity  Enter: session  Esc: close ")
+                .position(ratatui::widgets::block::Position::Bottom),
+        )
+        .borders(Borders::ALL);
+
+    if visible.is_empty() {
+        render_empty_state(f, app, block, "alerts", alerts.len(), area);
+        return;
+    }
+
+    let anon = Anonymizer::for_app(app);
+    let header = Row::new(
+        ["Time", "Severity", "Ack", "Alert"].iter()
+            .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow))),
+    )
+    .height(1)
+    .bottom_margin(1);
+
+    let rows = visible.iter().map(|index| {
+        let alert = &alerts[*index];
+        let style = if alert.acknowledged {
+            Style::default().fg(Color::DarkGray)
+        } else {
+            Style::default()
+        };
+        Row::new(vec![
+            Cell::from(app.clock.date_time(&alert.timestamp)),
+            Cell::from(app.theme.alert_label(alert.severity)).style(app.theme.style_for_alert(alert.severity)),
+            Cell::from(if alert.acknowledged { "✓" } else { "" }),
+            Cell::from(alert_text(&anon, alert)),
+        ])
+        .style(style)
+    });
+
+    let widths = [
+        Constraint::Length(app.clock.date_time(&alerts[visible[0]].timestamp).chars().count() as u16),
+        Constraint::Length(if app.theme.risk_symbols() { 10 } else { 8 }),
+        Constraint::Length(3),
+        Constraint::Min(20),
+    ];
+    let table = Table::new(rows)
+        .header(header)
+        .block(block)
+        .widths(&widths)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    let cursor = view.cursor.min(visible.len() - 1);
+    let mut state = TableState::default();
+    state.select(Some(cursor));
+    f.render_stateful_widget(table, chunks[0], &mut state);
+
+    render_alert_details(f, app, &alerts[visible[cursor]], chunks[1]);
+}
+
+/// An alert's message, with how often it repeated
+pub fn alert_text(anon: &Anonymizer, alert: &Alert) -> String {
+    let message = escape_controls(&anon.alert_message(alert)).into_owned();
+    match alert.repeats() {
+        Some(repeats) => format!("{} ({})", message, repeats),
+        None => message,
+    }
+}
+
+/// Detail pane for the selected alert
+fn render_alert_details(f: &mut Frame, app: &App, alert: &Alert, area: Rect) {
+    let anon = Anonymizer::for_app(app);
+    let store = app.store.snapshot();
+    let label = Style::default().fg(Color::Yellow);
+
+    let session = match (alert.alert_type.session_id(), alert_session(&store, alert)) {
+        (_, Some(session)) => Span::raw(format!("{} (Enter: open)", escape_controls(session.display_id()))),
+        (Some(id), None) => Span::styled(format!("{} (no longer stored)", escape_controls(id)), Style::default().fg(Color::DarkGray)),
+        (None, None) => Span::styled("none", Style::default().fg(Color::DarkGray)),
+    };
+
+    let techniques = alert.techniques.iter()
+        .map(|id| match attack_mapping().technique(id) {
+            Some(technique) => format!("{} {}", id, technique.name),
+            None => id.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    let lines = vec![
+        Line::from(vec![
+            Span::styled("Severity: ", label),
+            Span::styled(alert.severity.as_str(), app.theme.style_for_alert(alert.severity)),
+            Span::styled("  Kind: ", label),
+            Span::raw(alert.alert_type.kind()),
+            Span::styled("  Acknowledged: ", label),
+            Span::raw(if alert.acknowledged { "yes" } else { "no" }),
+        ]),
+        Line::from(vec![
+            Span::styled("Time: ", label),
+            Span::raw(app.clock.date_time(&alert.timestamp)),
+            Span::raw(match (alert.repeats(), &alert.last_seen) {
+                (Some(repeats), Some(last_seen)) => format!("  ({}, last at {})", repeats, app.clock.date_time(last_seen)),
+                _ => String::new(),
+            }),
+        ]),
+        Line::from(vec![Span::styled("Session: ", label), session]),
+        Line::from(vec![
+            Span::styled("ATT&CK: ", label),
+            Span::raw(if techniques.is_empty() { "-".to_string() } else { techniques.join(", ") }),
+        ]),
+        Line::from(vec![Span::styled("Message: ", label), Span::raw(escape_controls(&anon.alert_message(alert)).into_owned())]),
+    ];
+
+    let details = Paragraph::new(lines)
+        .block(Block::default().title("Alert Details").borders(Borders::ALL))
+        .wrap(Wrap { trim: false });
+    f.render_widget(details, area);
+}
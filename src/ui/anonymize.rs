@@ -0,0 +1,298 @@
+use std::borrow::Cow;
+use std::net::IpAddr;
+
+use serde_json::Value;
+
+use crate::app::App;
+use crate::core::{Alert, AlertType};
+
+/// Shown in place of a password while secrets are masked
+pub const SECRET_MASK: &str = "••••";
+
+/// Masks sensitive values at render time; stored data is never modified
+#[derive(Debug, Clone, Copy)]
+pub struct Anonymizer {
+    enabled: bool,
+    mask_secrets: bool,
+}
+
+impl Anonymizer {
+    /// Anonymizer reflecting the application's current toggle
+    pub fn for_app(app: &App) -> Self {
+        Self { enabled: app.anonymize, mask_secrets: app.mask_secrets }
+    }
+
+    /// Mask the host part of an IP address (last two octets for IPv4, last 80 bits for IPv6)
+    pub fn ip<'a>(&self, ip: &'a str) -> Cow<'a, str> {
+        if !self.enabled {
+            return Cow::Borrowed(ip);
+        }
+
+        match ip.parse::<IpAddr>() {
+            Ok(IpAddr::V4(addr)) => {
+                let octets = addr.octets();
+                Cow::Owned(format!("{}.{}.x.x", octets[0], octets[1]))
+            }
+            Ok(IpAddr::V6(addr)) => {
+                let segments = addr.segments();
+                Cow::Owned(format!("{:x}:{:x}:{:x}:x:x:x:x:x", segments[0], segments[1], segments[2]))
+            }
+            // Not an address we understand, so don't risk leaking it
+            Err(_) => Cow::Owned(format!("ip-{}", short_hash(ip))),
+        }
+    }
+
+    /// Replace a username or password with a short stable hash
+    pub fn credential<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        if !self.enabled {
+            return Cow::Borrowed(value);
+        }
+
+        Cow::Owned(format!("#{}", short_hash(value)))
+    }
+
+    /// Format an attempted password: masked outright while secrets are
+    /// masked, otherwise handled like any other credential
+    pub fn password<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        if self.mask_secrets {
+            return Cow::Borrowed(SECRET_MASK);
+        }
+
+        self.credential(value)
+    }
+
+    /// Hide a file hash entirely
+    pub fn file_hash<'a>(&self, hash: &'a str) -> Cow<'a, str> {
+        if !self.enabled {
+            return Cow::Borrowed(hash);
+        }
+
+        Cow::Borrowed("[hidden]")
+    }
+
+    /// Mask a file name or download URL
+    ///
+    /// A URL keeps its scheme and port, with the host masked like an IP and
+    /// the path replaced by a short hash; any other name becomes a short hash.
+    pub fn file_name<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        if !self.enabled {
+            return Cow::Borrowed(name);
+        }
+
+        let (scheme, rest) = match name.split_once("://") {
+            Some(parts) => parts,
+            None => return Cow::Owned(format!("file-{}", short_hash(name))),
+        };
+        let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let host_port = authority.rsplit('@').next().unwrap_or(authority);
+
+        let (host, port) = match host_port.strip_prefix('[').and_then(|host| host.split_once(']')) {
+            Some((host, port)) => (host, port),
+            None => match host_port.rsplit_once(':') {
+                Some((host, port)) => (host, &host_port[host.len()..host.len() + port.len() + 1]),
+                None => (host_port, ""),
+            },
+        };
+        let host = match host.parse::<IpAddr>() {
+            Ok(IpAddr::V6(_)) => format!("[{}]", self.ip(host)),
+            Ok(IpAddr::V4(_)) => self.ip(host).into_owned(),
+            Err(_) => format!("host-{}", short_hash(host)),
+        };
+        let path = match path {
+            "" | "/" => String::new(),
+            path => format!("file-{}", short_hash(path)),
+        };
+
+        Cow::Owned(format!("{}://{}{}/{}", scheme, host, port, path))
+    }
+
+    /// An alert's message, with the file it names and every IP address masked
+    pub fn alert_message<'a>(&self, alert: &'a Alert) -> Cow<'a, str> {
+        if !self.enabled {
+            return Cow::Borrowed(&alert.message);
+        }
+
+        let mut message = alert.message.clone();
+        if let AlertType::FileUpload { filename, shasum, .. } = &alert.alert_type {
+            if let Some(hash) = shasum.as_deref().filter(|hash| !hash.is_empty()) {
+                message = message.replace(hash, &self.file_hash(hash));
+            }
+            if !filename.is_empty() {
+                message = message.replace(filename.as_str(), &self.file_name(filename));
+            }
+        }
+
+        Cow::Owned(self.text(&message).into_owned())
+    }
+
+    /// Mask a raw Cowrie field according to what it holds
+    pub fn field<'a>(&self, key: &str, value: &'a str) -> Cow<'a, str> {
+        match key {
+            "src_ip" | "dst_ip" | "peerIP" | "hostIP" => self.ip(value),
+            "username" => self.credential(value),
+            "password" => self.password(value),
+            "shasum" | "sha256" | "md5" | "hash" => self.file_hash(value),
+            "url" | "filename" | "outfile" | "destfile" => self.file_name(value),
+            _ => self.text(value),
+        }
+    }
+
+    /// Render a raw Cowrie event, masking its password wherever it appears
+    /// (including the `message` field) as well as any IP addresses
+    pub fn raw(&self, raw: &Value) -> String {
+        let text = raw.to_string();
+        let password = raw.get("password").and_then(Value::as_str).map(|password| {
+            let escaped = Value::from(password).to_string();
+            escaped[1..escaped.len() - 1].to_string()
+        });
+
+        let text = self.without_password(&text, password.as_deref());
+        self.text(&text).into_owned()
+    }
+
+    /// Replace occurrences of `password` in free text while secrets are masked,
+    /// e.g. in Cowrie's `login attempt [root/123456] succeeded` messages
+    pub fn without_password<'a>(&self, text: &'a str, password: Option<&str>) -> Cow<'a, str> {
+        match password {
+            Some(password) if self.mask_secrets && !password.is_empty() && text.contains(password) => {
+                Cow::Owned(text.replace(password, SECRET_MASK))
+            }
+            _ => Cow::Borrowed(text),
+        }
+    }
+
+    /// Mask every IP address occurring in free text, wherever it sits: on its
+    /// own, as `user@ip`, in a URL, with a `:port` or in quotes
+    pub fn text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if !self.enabled {
+            return Cow::Borrowed(text);
+        }
+
+        let mut masked = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(is_ip_char) {
+            let (before, candidate) = rest.split_at(start);
+            let len = candidate.find(|c| !is_ip_char(c)).unwrap_or(candidate.len());
+            let (run, after) = candidate.split_at(len);
+            masked.push_str(before);
+
+            // Only whole words, so `std::fs` or `cafe:` inside a word aren't taken for addresses
+            let in_word = before.chars().next_back().map_or(false, is_word_char)
+                || after.chars().next().map_or(false, is_word_char);
+            match leading_ip(run).filter(|_| !in_word) {
+                Some(ip) => {
+                    masked.push_str(&self.ip(ip));
+                    masked.push_str(&run[ip.len()..]);
+                }
+                None => masked.push_str(run),
+            }
+            rest = after;
+        }
+        masked.push_str(rest);
+
+        Cow::Owned(masked)
+    }
+}
+
+/// Characters an IPv4 or IPv6 address is written with
+fn is_ip_char(c: char) -> bool {
+    c.is_ascii_hexdigit() || c == '.' || c == ':'
+}
+
+/// Characters that make an address-like run part of a longer word
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// The longest IP address `run` starts with, leaving a `:port` or a full stop after it
+fn leading_ip(run: &str) -> Option<&str> {
+    if !run.contains(|c: char| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mut ends: Vec<usize> = run.match_indices(['.', ':']).map(|(end, _)| end).collect();
+    ends.push(run.len());
+    ends.into_iter()
+        .rev()
+        .map(|end| &run[..end])
+        .find(|prefix| prefix.parse::<IpAddr>().is_ok())
+}
+
+/// Short, stable (per value) hash so identical values stay recognisable
+fn short_hash(value: &str) -> String {
+    let hash = value.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+
+    format!("{:08x}", hash >> 32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Severity;
+    use crate::data::testing::at;
+    use pretty_assertions::assert_eq;
+
+    const ANON: Anonymizer = Anonymizer { enabled: true, mask_secrets: false };
+
+    #[test]
+    fn addresses_are_masked_wherever_they_sit() {
+        let cases = [
+            ("ssh root@203.0.113.7", "ssh root@203.0.x.x"),
+            ("wget http://203.0.113.7:8080/x.sh", "wget http://203.0.x.x:8080/x.sh"),
+            ("connect to 203.0.113.7:22.", "connect to 203.0.x.x:22."),
+            ("echo \"203.0.113.7\",'198.51.100.4'", "echo \"203.0.x.x\",'198.51.x.x'"),
+            ("curl http://[2001:db8::1]:80/", "curl http://[2001:db8:0:x:x:x:x:x]:80/"),
+            ("ip=198.51.100.4;", "ip=198.51.x.x;"),
+        ];
+
+        for (text, masked) in cases {
+            assert_eq!(ANON.text(text), masked, "{}", text);
+        }
+    }
+
+    #[test]
+    fn things_that_only_look_like_addresses_are_kept() {
+        for text in ["uptime 12:34:56", "cat /etc/passwd", "use std::fs", "echo deadbeef", "v1.2.3"] {
+            assert_eq!(ANON.text(text), text);
+        }
+    }
+
+    #[test]
+    fn download_urls_keep_only_their_shape() {
+        let url = ANON.file_name("http://user@203.0.113.7:8080/bins/x86.sh");
+        assert!(url.starts_with("http://203.0.x.x:8080/file-"), "{}", url);
+        assert!(!url.contains("x86"));
+
+        let host = ANON.file_name("https://evil.example/");
+        assert!(host.starts_with("https://host-") && host.ends_with('/'), "{}", host);
+        assert!(ANON.file_name("/tmp/.x/miner").starts_with("file-"));
+    }
+
+    #[test]
+    fn file_alerts_hide_the_name_and_hash() {
+        let filename = "http://203.0.113.7/mirai.arm7".to_string();
+        let shasum = "ab".repeat(32);
+        let alert = Alert {
+            message: format!("File uploaded: {} (SHA256: {})", filename, shasum),
+            alert_type: AlertType::FileUpload {
+                session_id: "abc123".to_string(),
+                filename,
+                shasum: Some(shasum.clone()),
+                hash_list: None,
+            },
+            timestamp: at(0),
+            acknowledged: false,
+            severity: Severity::High,
+            techniques: Vec::new(),
+            count: 1,
+            last_seen: None,
+        };
+
+        let message = ANON.alert_message(&alert);
+        assert!(message.starts_with("File uploaded: http://203.0.x.x/file-"), "{}", message);
+        assert!(message.ends_with("(SHA256: [hidden])"), "{}", message);
+        assert!(!message.contains("mirai") && !message.contains(&shasum));
+    }
+}
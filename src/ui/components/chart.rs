@@ -28,6 +28,10 @@ pub struct ChartWidget<'a> {
     pub y_title: &'a str,
     /// Data series
     pub datasets: Vec<(String, Vec<DataPoint>, Color)>,
+    /// Series drawn as unconnected points, over the others
+    pub scatter: Vec<(String, Vec<DataPoint>, Color, symbols::Marker)>,
+    /// X-axis labels, spread evenly; numbers from the bounds when unset
+    pub x_labels: Option<Vec<String>>,
     /// X-axis bounds
     pub x_bounds: [f64; 2],
     /// Y-axis bounds
@@ -47,6 +51,8 @@ impl<'a> Default for ChartWidget<'a> {
             x_title: "",
             y_title: "",
             datasets: Vec::new(),
+            scatter: Vec::new(),
+            x_labels: None,
             x_bounds: [0.0, 100.0],
             y_bounds: [0.0, 100.0],
             block: None,
@@ -56,6 +62,7 @@ impl<'a> Default for ChartWidget<'a> {
     }
 }
 
+#[allow(dead_code)]
 impl<'a> ChartWidget<'a> {
     /// Create a new chart widget
     pub fn new() -> Self {
@@ -86,6 +93,18 @@ impl<'a> ChartWidget<'a> {
         self
     }
     
+    /// Add a series of unconnected points drawn with `marker`
+    pub fn add_points(mut self, name: String, data: Vec<DataPoint>, color: Color, marker: symbols::Marker) -> Self {
+        self.scatter.push((name, data, color, marker));
+        self
+    }
+    
+    /// Set X-axis labels, e.g. formatted times
+    pub fn x_labels(mut self, labels: Vec<String>) -> Self {
+        self.x_labels = Some(labels);
+        self
+    }
+    
     /// Set X-axis bounds
     pub fn x_bounds(mut self, bounds: [f64; 2]) -> Self {
         self.x_bounds = bounds;
@@ -149,27 +168,44 @@ impl<'a> ChartWidget<'a> {
 
 impl<'a> Widget for ChartWidget<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        // Convert data points to format expected by ratatui
-        let datasets: Vec<Dataset> = self.datasets
+        // Convert data points to format expected by ratatui; datasets borrow them
+        let line_points: Vec<Vec<(f64, f64)>> = self.datasets.iter()
+            .map(|(_, data, _)| data.iter().map(|point| (point.x, point.y)).collect())
+            .collect();
+        let scatter_points: Vec<Vec<(f64, f64)>> = self.scatter.iter()
+            .map(|(_, data, _, _)| data.iter().map(|point| (point.x, point.y)).collect())
+            .collect();
+        
+        let mut datasets: Vec<Dataset> = self.datasets
             .iter()
-            .map(|(name, data, color)| {
+            .zip(&line_points)
+            .map(|((name, _, color), points)| {
                 Dataset::default()
-                    .name(name)
+                    .name(name.as_str())
                     .marker(symbols::Marker::Dot)
                     .style(Style::default().fg(*color))
                     .graph_type(self.chart_type)
-                    .data(&data.iter()
-                        .map(|point| (point.x, point.y))
-                        .collect::<Vec<(f64, f64)>>())
+                    .data(points)
             })
             .collect();
+        datasets.extend(self.scatter.iter().zip(&scatter_points).map(|((name, _, color, marker), points)| {
+            Dataset::default()
+                .name(name.as_str())
+                .marker(*marker)
+                .style(Style::default().fg(*color))
+                .graph_type(GraphType::Scatter)
+                .data(points)
+        }));
         
         // Create X-axis
-        let x_labels = vec![
-            Span::raw(format!("{:.1}", self.x_bounds[0])),
-            Span::raw(format!("{:.1}", (self.x_bounds[0] + self.x_bounds[1]) / 2.0)),
-            Span::raw(format!("{:.1}", self.x_bounds[1])),
-        ];
+        let x_labels = match &self.x_labels {
+            Some(labels) => labels.iter().map(|label| Span::raw(label.clone())).collect(),
+            None => vec![
+                Span::raw(format!("{:.1}", self.x_bounds[0])),
+                Span::raw(format!("{:.1}", (self.x_bounds[0] + self.x_bounds[1]) / 2.0)),
+                Span::raw(format!("{:.1}", self.x_bounds[1])),
+            ],
+        };
         
         let y_labels = vec![
             Span::raw(format!("{:.1}", self.y_bounds[0])),
@@ -178,8 +214,9 @@ impl<'a> Widget for ChartWidget<'a> {
         ];
         
         // Create the chart
+        let title = self.title;
         let chart = RatatuiChart::new(datasets)
-            .block(self.block.unwrap_or_else(|| Block::default().title(self.title)))
+            .block(self.block.unwrap_or_else(|| Block::default().title(title)))
             .x_axis(
                 Axis::default()
                     .title(Span::styled(self.x_title, Style::default().fg(Color::Gray)))
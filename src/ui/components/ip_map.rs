@@ -2,18 +2,21 @@ use std::collections::{HashMap, HashSet};
 
 use ratatui::{
     buffer::Buffer,
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::Rect,
     style::{Color, Style},
-    text::{Line, Span},
-    widgets::{Block, Paragraph, StatefulWidget, Widget},
+    widgets::{Block, StatefulWidget, Widget},
 };
 
+use crate::data::GeoLocation;
+
 /// IP map model for mapping IPs to geographical locations
 pub struct IpMapModel {
     /// Mapping of IPs to coordinates (latitude, longitude)
     pub ip_coordinates: HashMap<String, (f64, f64)>,
     /// IPs to highlight
     pub highlighted_ips: HashSet<String>,
+    /// IPs seen without usable coordinates (not plotted)
+    pub unlocated_ips: HashSet<String>,
     /// Map dimensions
     pub width: usize,
     pub height: usize,
@@ -29,6 +32,7 @@ impl Default for IpMapModel {
         Self {
             ip_coordinates: HashMap::new(),
             highlighted_ips: HashSet::new(),
+            unlocated_ips: HashSet::new(),
             width: 80,
             height: 24,
             min_lat: -90.0,
@@ -45,12 +49,44 @@ impl IpMapModel {
         self.ip_coordinates.insert(ip, (lat, lon));
     }
     
+    /// Add an IP from its GeoIP lookup, returning whether it can be plotted
+    ///
+    /// IPs without both latitude and longitude are tracked as unlocated rather
+    /// than plotted at 0,0.
+    pub fn add_location(&mut self, ip: String, location: Option<&GeoLocation>) -> bool {
+        match location.and_then(|geo| geo.latitude.zip(geo.longitude)) {
+            Some((lat, lon)) => {
+                self.unlocated_ips.remove(&ip);
+                self.add_ip(ip, lat, lon);
+                true
+            }
+            None => {
+                if !self.ip_coordinates.contains_key(&ip) {
+                    self.unlocated_ips.insert(ip);
+                }
+                false
+            }
+        }
+    }
+    
+    /// Number of IPs that can be plotted
+    pub fn located_count(&self) -> usize {
+        self.ip_coordinates.len()
+    }
+    
+    /// Number of IPs without coordinates
+    pub fn unlocated_count(&self) -> usize {
+        self.unlocated_ips.len()
+    }
+    
     /// Highlight an IP
+    #[allow(dead_code)]
     pub fn highlight_ip(&mut self, ip: String) {
         self.highlighted_ips.insert(ip);
     }
     
     /// Clear highlighted IPs
+    #[allow(dead_code)]
     pub fn clear_highlights(&mut self) {
         self.highlighted_ips.clear();
     }
@@ -64,6 +100,7 @@ impl IpMapModel {
 }
 
 /// World map widget state
+#[derive(Default)]
 pub struct IpMapState {
     /// Selected IP
     pub selected_ip: Option<String>,
@@ -71,15 +108,6 @@ pub struct IpMapState {
     pub scroll: (u16, u16),
 }
 
-impl Default for IpMapState {
-    fn default() -> Self {
-        Self {
-            selected_ip: None,
-            scroll: (0, 0),
-        }
-    }
-}
-
 /// Widget for displaying IPs on a world map
 pub struct IpMapWidget<'a> {
     /// Block to wrap the widget in
@@ -94,6 +122,7 @@ pub struct IpMapWidget<'a> {
 
 impl<'a> IpMapWidget<'a> {
     /// Create a new IP map widget
+    #[allow(dead_code)]
     pub fn new(model: &'a IpMapModel) -> Self {
         Self {
             block: None,
@@ -104,18 +133,21 @@ impl<'a> IpMapWidget<'a> {
     }
     
     /// Set the block for the widget
+    #[allow(dead_code)]
     pub fn block(mut self, block: Block<'a>) -> Self {
         self.block = Some(block);
         self
     }
     
     /// Set the normal style
+    #[allow(dead_code)]
     pub fn style(mut self, style: Style) -> Self {
         self.style = style;
         self
     }
     
     /// Set the highlight style
+    #[allow(dead_code)]
     pub fn highlight_style(mut self, style: Style) -> Self {
         self.highlight_style = style;
         self
@@ -145,14 +177,14 @@ impl<'a> StatefulWidget for IpMapWidget<'a> {
         let mut map = vec![vec![' '; area.width as usize]; area.height as usize];
         
         // Draw a simple world outline
-        for y in 0..area.height as usize {
-            for x in 0..area.width as usize {
+        for (y, row) in map.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
                 let lat = self.model.max_lat - (y as f64 / area.height as f64) * (self.model.max_lat - self.model.min_lat);
                 let lon = self.model.min_lon + (x as f64 / area.width as f64) * (self.model.max_lon - self.model.min_lon);
                 
                 // Very simple map outline (this would be improved in a real implementation)
-                if lat > 60.0 || lat < -60.0 {
-                    map[y][x] = '.';
+                if !(-60.0..=60.0).contains(&lat) {
+                    *cell = '.';
                 }
                 
                 // Continents (extremely simplified)
@@ -164,7 +196,7 @@ impl<'a> StatefulWidget for IpMapWidget<'a> {
                    (lat > 15.0 && lat < 70.0 && lon > -170.0 && lon < -50.0) || // North America
                    (lat > -60.0 && lat < 15.0 && lon > -80.0 && lon < -35.0) // South America
                 {
-                    map[y][x] = '█';
+                    *cell = '█';
                 }
             }
         }
@@ -212,4 +244,45 @@ impl<'a> Widget for IpMapWidget<'a> {
         let mut state = IpMapState::default();
         StatefulWidget::render(self, area, buf, &mut state);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn geo(latitude: Option<f64>, longitude: Option<f64>) -> GeoLocation {
+        GeoLocation {
+            country_code: "NL".to_string(),
+            country_name: "Netherlands".to_string(),
+            city: None,
+            latitude,
+            longitude,
+            asn: Some("AS1136".to_string()),
+            isp: None,
+        }
+    }
+
+    #[test]
+    fn sessions_without_coordinates_are_not_plotted() {
+        let mut model = IpMapModel::default();
+        assert!(!model.add_location("198.51.100.4".to_string(), None));
+        assert!(!model.add_location("198.51.100.5".to_string(), Some(&geo(None, None))));
+        assert!(!model.add_location("198.51.100.6".to_string(), Some(&geo(Some(52.4), None))));
+        assert!(model.add_location("203.0.113.9".to_string(), Some(&geo(Some(52.4), Some(4.9)))));
+
+        assert_eq!(model.ip_coordinates.keys().collect::<Vec<_>>(), ["203.0.113.9"]);
+        assert!(!model.ip_coordinates.values().any(|&coordinates| coordinates == (0.0, 0.0)));
+        assert_eq!((model.located_count(), model.unlocated_count()), (1, 3));
+    }
+
+    #[test]
+    fn an_ip_is_located_once_any_of_its_sessions_is() {
+        let mut model = IpMapModel::default();
+        model.add_location("203.0.113.9".to_string(), Some(&geo(None, None)));
+        model.add_location("203.0.113.9".to_string(), Some(&geo(Some(52.4), Some(4.9))));
+        model.add_location("203.0.113.9".to_string(), None);
+
+        assert_eq!((model.located_count(), model.unlocated_count()), (1, 0));
+    }
+}
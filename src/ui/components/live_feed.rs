@@ -0,0 +1,246 @@
+use std::collections::{HashSet, VecDeque};
+
+use chrono::{DateTime, Utc};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Widget},
+};
+
+use crate::app::AppEvent;
+use crate::utils::time::Clock;
+use crate::data::EventType;
+use crate::ui::anonymize::Anonymizer;
+use crate::ui::sanitize::escape_controls;
+use crate::ui::theme::Theme;
+
+/// Risk score at which a session is reported as high risk
+const HIGH_RISK_SCORE: u8 = 70;
+
+/// Severity of a feed line, used for coloring
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FeedSeverity {
+    Info,
+    Medium,
+    High,
+}
+
+/// Notable event shown in the feed
+#[derive(Debug, Clone)]
+pub enum FeedKind {
+    /// A new connection
+    NewSession,
+    /// Successful login
+    LoginSuccess { username: String },
+    /// File uploaded or downloaded by the attacker
+    File { filename: String, upload: bool },
+    /// Session crossed the high-risk threshold
+    HighRisk { score: u8, tags: Vec<String> },
+}
+
+impl FeedKind {
+    /// Severity of this kind of event
+    pub fn severity(&self) -> FeedSeverity {
+        match self {
+            FeedKind::NewSession => FeedSeverity::Info,
+            FeedKind::LoginSuccess { .. } | FeedKind::File { .. } => FeedSeverity::Medium,
+            FeedKind::HighRisk { .. } => FeedSeverity::High,
+        }
+    }
+}
+
+/// One line of the feed
+#[derive(Debug, Clone)]
+pub struct FeedItem {
+    /// When the event happened
+    pub timestamp: DateTime<Utc>,
+    /// Source IP of the attacker
+    pub src_ip: String,
+    /// What happened
+    pub kind: FeedKind,
+}
+
+/// Append-only feed of notable events, capped at a maximum number of lines
+pub struct LiveFeed {
+    /// Retained items, oldest first
+    items: VecDeque<FeedItem>,
+    /// Maximum number of items to retain
+    capacity: usize,
+    /// Sessions already reported as high risk
+    high_risk_sessions: HashSet<String>,
+}
+
+impl LiveFeed {
+    /// Create a feed retaining at most `capacity` lines
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            items: VecDeque::with_capacity(capacity.min(1024)),
+            capacity: capacity.max(1),
+            high_risk_sessions: HashSet::new(),
+        }
+    }
+
+    /// Append an item, dropping the oldest if the feed is full
+    pub fn push(&mut self, item: FeedItem) {
+        if self.items.len() >= self.capacity {
+            self.items.pop_front();
+        }
+        self.items.push_back(item);
+    }
+
+    /// Append a line for the event if it is notable
+    pub fn handle_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::NewLogEntry(entry) => {
+                let kind = match entry.event_type {
+                    EventType::Connect if entry.eventid == "cowrie.session.connect" => FeedKind::NewSession,
+                    EventType::LoginSuccess => FeedKind::LoginSuccess {
+                        username: entry.username.as_deref().unwrap_or_default().to_string(),
+                    },
+                    EventType::FileUpload | EventType::FileDownload => FeedKind::File {
+                        filename: entry.file.as_ref().map_or_else(String::new, |file| file.filename.clone()),
+                        upload: entry.event_type == EventType::FileUpload,
+                    },
+                    _ => return,
+                };
+
+                self.push(FeedItem {
+                    timestamp: entry.timestamp,
+                    src_ip: entry.src_ip.as_deref().unwrap_or_default().to_string(),
+                    kind,
+                });
+            }
+            AppEvent::SessionUpdate(session) | AppEvent::NewSession(session) => {
+                if session.malicious_score >= HIGH_RISK_SCORE
+                    && self.high_risk_sessions.insert(session.id.clone())
+                {
+                    self.push(FeedItem {
+                        timestamp: Utc::now(),
+                        src_ip: session.src_ip.to_string(),
+                        kind: FeedKind::HighRisk {
+                            score: session.malicious_score,
+                            tags: session.tags.clone(),
+                        },
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Retained items, oldest first
+    pub fn items(&self) -> impl DoubleEndedIterator<Item = &FeedItem> + ExactSizeIterator {
+        self.items.iter()
+    }
+
+    /// Number of retained items
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Whether the feed is empty
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// Widget rendering the newest feed lines that fit, newest at the bottom
+pub struct LiveFeedWidget<'a> {
+    /// Block to wrap the widget in
+    block: Option<Block<'a>>,
+    /// Feed to render
+    feed: &'a LiveFeed,
+    /// Masks sensitive values
+    anonymizer: Option<Anonymizer>,
+    /// Formats item times
+    clock: &'a Clock,
+    /// Styles and marks severities
+    theme: &'a Theme,
+}
+
+impl<'a> LiveFeedWidget<'a> {
+    /// Create a new feed widget
+    pub fn new(feed: &'a LiveFeed, clock: &'a Clock, theme: &'a Theme) -> Self {
+        Self {
+            block: None,
+            feed,
+            anonymizer: None,
+            clock,
+            theme,
+        }
+    }
+
+    /// Set the block
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    /// Mask IPs and usernames when rendering
+    pub fn anonymizer(mut self, anonymizer: Anonymizer) -> Self {
+        self.anonymizer = Some(anonymizer);
+        self
+    }
+
+    /// Format one item as a styled line
+    fn line(&self, item: &'a FeedItem) -> Line<'a> {
+        let (ip, summary) = match self.anonymizer {
+            Some(anon) => (anon.ip(&item.src_ip).into_owned(), summarize(&item.kind, Some(anon))),
+            None => (item.src_ip.clone(), summarize(&item.kind, None)),
+        };
+
+        let severity = item.kind.severity();
+
+        Line::from(vec![
+            Span::styled(
+                format!("{} ", self.clock.when(&item.timestamp)),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(format!("{:<15} ", ip), Style::default().fg(Color::Cyan)),
+            Span::styled(
+                format!("{}{}", self.theme.severity_symbol(severity), summary),
+                self.theme.style_for_severity(severity),
+            ),
+        ])
+    }
+}
+
+impl<'a> Widget for LiveFeedWidget<'a> {
+    fn render(mut self, area: Rect, buf: &mut Buffer) {
+        let inner = match self.block.take() {
+            Some(block) => {
+                let inner = block.inner(area);
+                block.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+
+        // Only build lines for what fits, keeping the newest in view
+        let visible = inner.height as usize;
+        let skip = self.feed.len().saturating_sub(visible);
+        let lines = self.feed.items().skip(skip).map(|item| self.line(item)).collect::<Vec<_>>();
+
+        Paragraph::new(lines).render(inner, buf);
+    }
+}
+
+/// Short description of a feed event
+fn summarize(kind: &FeedKind, anon: Option<Anonymizer>) -> String {
+    match kind {
+        FeedKind::NewSession => "new session".to_string(),
+        FeedKind::LoginSuccess { username } => {
+            let username = anon.map_or_else(|| username.clone(), |anon| anon.credential(username).into_owned());
+            format!("login success as {}", escape_controls(&username))
+        }
+        FeedKind::File { filename, upload } => {
+            let filename = anon.map_or_else(|| filename.clone(), |anon| anon.file_name(filename).into_owned());
+            let verb = if *upload { "uploaded" } else { "downloaded" };
+            format!("{} {}", verb, escape_controls(&filename))
+        }
+        FeedKind::HighRisk { score, tags } if tags.is_empty() => format!("HIGH RISK (score {})", score),
+        FeedKind::HighRisk { score, tags } => format!("HIGH RISK (score {}): {}", score, tags.join(", ")),
+    }
+}
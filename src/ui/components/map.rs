@@ -6,7 +6,9 @@ use ratatui::{
 };
 
 /// World map dimensions
+#[allow(dead_code)]
 const MAP_WIDTH: usize = 180;
+#[allow(dead_code)]
 const MAP_HEIGHT: usize = 90;
 
 /// ASCII world map (this would be loaded from a data file in a real implementation)
@@ -116,6 +118,7 @@ impl WorldMap {
     }
     
     /// Set the map style
+    #[allow(dead_code)]
     pub fn style(mut self, style: Style) -> Self {
         self.style = style;
         self
@@ -143,7 +146,6 @@ impl Widget for WorldMap {
         
         // Draw the base map
         let lines: Vec<&str> = WORLD_MAP.lines().collect();
-        let map_height = lines.len();
         
         for (y, line) in lines.iter().enumerate() {
             if y >= area.height as usize {
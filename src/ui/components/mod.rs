@@ -1,7 +1,11 @@
 mod stateful_table;
 mod ip_map;
 mod chart;
+mod map;
+mod live_feed;
 
-pub use stateful_table::StatefulTable;
-pub use ip_map::IpMapWidget;
-pub use chart::ChartWidget;
\ No newline at end of file
+pub use stateful_table::{visible_window, StatefulTable};
+pub use ip_map::IpMapModel;
+pub use chart::{ChartWidget, DataPoint};
+pub use map::WorldMap;
+pub use live_feed::{FeedSeverity, LiveFeed, LiveFeedWidget};
\ No newline at end of file
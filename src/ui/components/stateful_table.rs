@@ -1,4 +1,6 @@
+use ratatui::layout::Rect;
 use ratatui::widgets::TableState;
+use std::ops::Range;
 
 /// A stateful table that wraps a vector of items and manages table state
 pub struct StatefulTable<T> {
@@ -10,6 +12,7 @@ pub struct StatefulTable<T> {
     pub total: usize,
 }
 
+#[allow(dead_code)]
 impl<T> StatefulTable<T> {
     /// Create a new stateful table
     pub fn new() -> Self {
@@ -119,4 +122,17 @@ impl<T> StatefulTable<T> {
         self.state.select(None);
         self.total = 0;
     }
-}
\ No newline at end of file
+}
+
+/// Rows of a `len`-row table in `area` worth building, with `selected` shown
+///
+/// `area` is a bordered table with a header and a blank line under it. A
+/// fresh `TableState` scrolls just far enough to bring the selected row to
+/// the bottom; these are exactly the rows that leaves on screen, so a store
+/// of 50k sessions builds a screenful of rows rather than all of them.
+/// Select `selected - start` in the window.
+pub fn visible_window(len: usize, selected: Option<usize>, area: Rect) -> Range<usize> {
+    let height = (area.height.saturating_sub(4) as usize).max(1);
+    let start = selected.map_or(0, |selected| (selected + 1).saturating_sub(height));
+    start..(start + height).min(len)
+}
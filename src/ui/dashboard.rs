@@ -1,73 +1,173 @@
-use anyhow::Result;
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Cell, LineGauge, Paragraph, Row, Table, Wrap},
+    widgets::{Block, Borders, Cell, LineGauge, Paragraph, Row, Sparkline, Table, Wrap},
     Frame,
 };
+use chrono::{DateTime, Duration, Timelike, Utc};
 use std::collections::HashMap;
-use tokio::sync::RwLock;
 
 use crate::app::App;
+use crate::data::attackers::{attacker_profiles, rank_attackers};
+use crate::data::baseline::AnomalyMetric;
 use crate::data::EventType;
+use crate::data::rate::{per_minute, RATE_WINDOW_MINUTES};
+use crate::ui::anonymize::Anonymizer;
+use crate::ui::columns::{Columns, WidthBucket};
+use crate::ui::components::{ChartWidget, DataPoint};
+use crate::ui::panels::{cached_title, highlight_focused, panel_areas, render_empty_state, table_rows};
+use crate::ui::sessions::{recent_session_table, session_style};
+use crate::ui::theme::Theme;
+use crate::utils::time::humanize_ago;
+
+/// Dashboard panels in focus order: summary, activity, recent sessions,
+/// connection rate, attackers, credentials
+const PANELS: [fn(&mut Frame, &App, Rect); 6] = [
+    render_summary,
+    render_activity,
+    render_sessions,
+    render_connection_rate,
+    render_attackers,
+    render_credentials,
+];
+
+/// Number of panels on the dashboard, for moving focus between them
+pub const DASHBOARD_PANELS: usize = PANELS.len();
+
+/// What the activity gauges are filled relative to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GaugeScale {
+    /// Each category's share of all four together
+    #[default]
+    Total,
+    /// Each category against the busiest one, which always looks full
+    Max,
+}
+
+impl GaugeScale {
+    /// The other scale
+    pub fn toggle(self) -> Self {
+        match self {
+            GaugeScale::Total => GaugeScale::Max,
+            GaugeScale::Max => GaugeScale::Total,
+        }
+    }
+
+    /// Shown in the activity panel's title
+    fn label(self) -> &'static str {
+        match self {
+            GaugeScale::Total => "share of total",
+            GaugeScale::Max => "vs busiest",
+        }
+    }
+}
 
 /// Render the dashboard view
+///
+/// The panels are stacked in one column on narrow terminals, laid out in two
+/// columns at medium widths and in three on wide ones (see [`WidthBucket`]).
 pub fn render_dashboard(f: &mut Frame, app: &App, area: Rect) {
-    // Create dashboard layout
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage(30),
-            Constraint::Percentage(40),
-            Constraint::Percentage(30),
-        ].as_ref())
-        .split(area);
-    
-    // Create horizontal splits for the top section
-    let top_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(60),
-            Constraint::Percentage(40),
-        ].as_ref())
-        .split(chunks[0]);
-    
-    // Create horizontal splits for the bottom section
-    let bottom_chunks = Layout::default()
+    // Render each component, or only the zoomed one
+    let areas = dashboard_areas(area);
+    for (index, panel_area) in panel_areas(&app.panel_focus, areas, area) {
+        PANELS[index](f, app, panel_area);
+        highlight_focused(f, &app.panel_focus, index, PANELS.len(), panel_area);
+    }
+}
+
+/// Areas of the dashboard panels in focus order, arranged for the width of `area`
+fn dashboard_areas(area: Rect) -> Vec<Rect> {
+    match WidthBucket::of(area) {
+        // One panel per row, recent sessions getting the most room
+        WidthBucket::Narrow => Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Ratio(3, 20),
+                Constraint::Ratio(3, 20),
+                Constraint::Ratio(5, 20),
+                Constraint::Ratio(3, 20),
+                Constraint::Ratio(3, 20),
+                Constraint::Ratio(3, 20),
+            ].as_ref())
+            .split(area)
+            .to_vec(),
+        // Pairs: summary and activity, sessions and rate, attackers and credentials
+        WidthBucket::Medium => {
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Percentage(30),
+                    Constraint::Percentage(40),
+                    Constraint::Percentage(30),
+                ].as_ref())
+                .split(area);
+            let widths = [[60, 40], [60, 40], [50, 50]];
+            rows.iter()
+                .zip(widths.iter())
+                .flat_map(|(row, widths)| split_row(*row, widths))
+                .collect()
+        }
+        // Summary, activity and recent sessions on top; rate, attackers and credentials below
+        WidthBucket::Wide => {
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Percentage(45),
+                    Constraint::Percentage(55),
+                ].as_ref())
+                .split(area);
+            let widths = [[30, 30, 40], [40, 30, 30]];
+            rows.iter()
+                .zip(widths.iter())
+                .flat_map(|(row, widths)| split_row(*row, widths))
+                .collect()
+        }
+    }
+}
+
+/// `row` split side by side into the given percentages
+fn split_row(row: Rect, widths: &[u16]) -> Vec<Rect> {
+    Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(50),
-            Constraint::Percentage(50),
-        ].as_ref())
-        .split(chunks[2]);
-    
-    // Render each component
-    render_summary(f, app, top_chunks[0]);
-    render_activity(f, app, top_chunks[1]);
-    render_sessions(f, app, chunks[1]);
-    render_attackers(f, app, bottom_chunks[0]);
-    render_credentials(f, app, bottom_chunks[1]);
+        .constraints(widths.iter().map(|width| Constraint::Percentage(*width)).collect::<Vec<_>>())
+        .split(row)
+        .to_vec()
 }
 
 /// Render honeypot summary
 fn render_summary(f: &mut Frame, app: &App, area: Rect) {
-    let store_guard = match app.store.try_read() {
-        Ok(guard) => guard,
-        Err(_) => return,
-    };
+    let store = app.store.snapshot();
     
     // Calculate statistics
-    let total_logs = store_guard.get_log_entry_count();
-    let total_sessions = store_guard.get_session_count();
-    let active_sessions = store_guard.get_active_sessions().len();
-    let unique_ips = store_guard.get_unique_source_ips().len();
-    let unique_usernames = store_guard.get_unique_usernames().len();
-    let unique_passwords = store_guard.get_unique_passwords().len();
+    let total_logs = store.get_log_entry_count();
+    let total_sessions = store.get_session_count();
+    let active_sessions = store.get_active_sessions().len();
+    let unique_ips = store.get_unique_source_ips().len();
+    let unique_usernames = store.get_unique_usernames().len();
+    let unique_passwords = store.get_unique_passwords().len();
+    let now = Utc::now();
+    
+    // With a time filter from a start, the headline counts cover its range and
+    // are compared with the period before; "all time" has nothing to compare
+    let headline = match app.filters.from_time {
+        Some(start) => {
+            let end = app.filters.to_time.unwrap_or(now);
+            let (current, previous) = store.get_hourly_counts().compare(&start, &end);
+            vec![
+                headline_line("Sessions", current.sessions, previous.map(|totals| totals.sessions)),
+                headline_line("Unique Source IPs", current.unique_ips, previous.map(|totals| totals.unique_ips)),
+                headline_line("Successful Logins", current.successful_logins, previous.map(|totals| totals.successful_logins)),
+                headline_line("Malware Uploads", current.malware_uploads, previous.map(|totals| totals.malware_uploads)),
+                Line::from(""),
+            ]
+        }
+        None => Vec::new(),
+    };
     
     // Create summary text
-    let text = vec![
+    let mut text = headline;
+    text.extend(vec![
         Line::from(vec![
             Span::styled("Total Sessions: ", Style::default().fg(Color::Yellow)),
             Span::raw(format!("{}", total_sessions)),
@@ -93,27 +193,203 @@ fn render_summary(f: &mut Frame, app: &App, area: Rect) {
             Span::styled("Unique Passwords: ", Style::default().fg(Color::Yellow)),
             Span::raw(format!("{}", unique_passwords)),
         ]),
-    ];
+    ]);
+    
+    // Rates running above their baseline for this hour of day
+    let anomalies = store.get_anomalies(&now);
+    if !anomalies.is_empty() {
+        text.push(Line::from(""));
+    }
+    for anomaly in &anomalies {
+        let color = if anomaly.severe { Color::Red } else { Color::LightRed };
+        text.push(Line::from(Span::styled(
+            format!("⚠ {} {:.1}x normal for this hour", anomaly.metric.label(), anomaly.ratio()),
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        )));
+    }
     
     let block = Block::default()
         .title("Honeypot Summary")
         .borders(Borders::ALL);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(2),
+            Constraint::Length(2),
+        ].as_ref())
+        .split(inner);
     
     let paragraph = Paragraph::new(text)
-        .block(block)
         .wrap(Wrap { trim: true });
     
-    f.render_widget(paragraph, area);
+    f.render_widget(paragraph, chunks[0]);
+    
+    // Per-minute rates: the trailing hour, or the filtered window when a time filter is set
+    let filtered = app.filters.from_time.is_some() || app.filters.to_time.is_some();
+    let (events, sessions) = if filtered {
+        let end = app.filters.to_time.unwrap_or(now);
+        let start = app.filters.from_time
+            .unwrap_or_else(|| end - Duration::minutes(RATE_WINDOW_MINUTES as i64));
+        (
+            per_minute(store.get_log_entries().into_iter().map(|entry| &entry.timestamp), &start, &end),
+            per_minute(store.get_sessions().into_iter().map(|session| &session.start_time), &start, &end),
+        )
+    } else {
+        (store.get_event_rate().series(&now), store.get_session_rate().series(&now))
+    };
+    
+    // The minutes of this hour are tinted while the session rate is anomalous
+    let anomalous_minutes = if !filtered && anomalies.iter().any(|anomaly| anomaly.metric == AnomalyMetric::Sessions) {
+        now.minute() as usize + 1
+    } else {
+        0
+    };
+    
+    render_rate(f, "Events", &events, filtered, Color::Cyan, 0, chunks[1]);
+    render_rate(f, "New sessions", &sessions, filtered, Color::Green, anomalous_minutes, chunks[2]);
+}
+
+/// "Sessions: 412 ▲ 38%": a count for the filtered period and its change from the one before
+///
+/// `previous` is `None` when there is no earlier period to compare with.
+/// More activity than before is shown in red, less in green.
+fn headline_line(label: &str, current: u64, previous: Option<u64>) -> Line<'static> {
+    let mut spans = vec![
+        Span::styled(format!("{}: ", label), Style::default().fg(Color::Yellow)),
+        Span::raw(current.to_string()),
+    ];
+    
+    let delta = match previous {
+        None => None,
+        Some(previous) if previous == current => Some(("=".to_string(), Color::DarkGray)),
+        // Nothing to take a percentage of
+        Some(0) => Some(("▲ new".to_string(), Color::Red)),
+        Some(previous) => {
+            let change = (current as f64 - previous as f64) / previous as f64 * 100.0;
+            if current > previous {
+                Some((format!("▲ {:.0}%", change), Color::Red))
+            } else {
+                Some((format!("▼ {:.0}%", -change), Color::Green))
+            }
+        }
+    };
+    if let Some((text, color)) = delta {
+        spans.push(Span::styled(format!(" {}", text), Style::default().fg(color)));
+    }
+    
+    Line::from(spans)
+}
+
+/// Render a label with the current rate above a per-minute sparkline
+///
+/// The newest `anomalous` minutes are drawn in red.
+fn render_rate(f: &mut Frame, label: &str, series: &[u64], filtered: bool, color: Color, anomalous: usize, area: Rect) {
+    let rate = if filtered {
+        // A whole window: its average is more telling than its last minute
+        let average = series.iter().sum::<u64>() as f64 / series.len().max(1) as f64;
+        format!("avg {:.1}/min (filtered)", average)
+    } else {
+        // The newest minute is still filling up, so report the one before it
+        let last_full = series.len().checked_sub(2).map_or(0, |index| series[index]);
+        format!("{}/min (last hour)", last_full)
+    };
+    
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref())
+        .split(area);
+    
+    f.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::styled(format!("{}: ", label), Style::default().fg(Color::Yellow)),
+            Span::raw(rate),
+        ])),
+        chunks[0],
+    );
+    
+    // Keep the newest minutes when the window is wider than the panel
+    let visible = &series[series.len().saturating_sub(chunks[1].width as usize)..];
+    let (usual, tinted) = visible.split_at(visible.len() - anomalous.min(visible.len()));
+    
+    // Side by side on a shared scale, so the tinted minutes line up with the rest
+    let peak = visible.iter().copied().max().unwrap_or(0).max(1);
+    let segments = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length(usual.len() as u16),
+            Constraint::Length(tinted.len() as u16),
+            Constraint::Min(0),
+        ].as_ref())
+        .split(chunks[1]);
+    for (data, color, segment) in [(usual, color, segments[0]), (tinted, Color::Red, segments[1])] {
+        f.render_widget(
+            Sparkline::default()
+                .data(data)
+                .max(peak)
+                .style(Style::default().fg(color)),
+            segment,
+        );
+    }
+}
+
+/// Render connections and login attempts per minute over the trailing hour
+///
+/// Scan waves show up as spikes here before anything else moves. The window
+/// rolls with the clock, so quiet minutes push old spikes out on every tick.
+fn render_connection_rate(f: &mut Frame, app: &App, area: Rect) {
+    let store = app.store.snapshot();
+    let now = Utc::now();
+    let connections = store.get_session_rate().series(&now);
+    let logins = store.get_login_rate().series(&now);
+    
+    // The newest minute is still filling up, so the title reports the one before it
+    let last_full = |series: &[u64]| series.len().checked_sub(2).map_or(0, |index| series[index]);
+    let title = format!(
+        "Connection Rate: {}/min, {} logins/min",
+        last_full(&connections),
+        last_full(&logins)
+    );
+    let block = Block::default().title(title).borders(Borders::ALL);
+    
+    let peak = connections.iter().chain(&logins).copied().max().unwrap_or(0);
+    if peak == 0 {
+        render_empty_state(f, app, block, "connections", 0, area);
+        return;
+    }
+    
+    // Minutes relative to now, oldest at the left
+    let points = |series: &[u64]| {
+        series.iter()
+            .enumerate()
+            .map(|(minute, count)| DataPoint {
+                x: minute as f64 - (series.len() - 1) as f64,
+                y: *count as f64,
+            })
+            .collect::<Vec<_>>()
+    };
+    let oldest = 1.0 - RATE_WINDOW_MINUTES as f64;
+    
+    let chart = ChartWidget::new()
+        .block(block)
+        .y_title("/min")
+        .add_dataset("connections".to_string(), points(&connections), Color::Green)
+        .add_dataset("logins".to_string(), points(&logins), Color::Red)
+        .x_bounds([oldest, 0.0])
+        .y_bounds([0.0, (peak as f64 * 1.1).ceil()])
+        .x_labels(vec![format!("-{}m", RATE_WINDOW_MINUTES), format!("-{}m", RATE_WINDOW_MINUTES / 2), "now".to_string()]);
+    
+    f.render_widget(chart, area);
 }
 
 /// Render activity gauges
 fn render_activity(f: &mut Frame, app: &App, area: Rect) {
-    let store_guard = match app.store.try_read() {
-        Ok(guard) => guard,
-        Err(_) => return,
-    };
+    let store = app.store.snapshot();
     
-    let logs = store_guard.get_log_entries();
+    let logs = store.get_log_entries();
     
     // Count event types
     let mut event_counts = HashMap::new();
@@ -147,34 +423,54 @@ fn render_activity(f: &mut Frame, app: &App, area: Rect) {
         .margin(1)
         .split(area);
     
-    // Calculate percentages (with a max value to prevent division by zero)
-    let max_value = [login_attempts, *commands, *connections, file_transfers].iter().max().copied().unwrap_or(1).max(1) as f64;
+    // Gauges fill against the sum or the busiest category (at least 1, for empty stores)
+    let counts = [login_attempts, *commands, *connections, file_transfers];
+    let scale = match app.gauge_scale {
+        GaugeScale::Total => counts.iter().sum::<usize>(),
+        GaugeScale::Max => counts.iter().max().copied().unwrap_or(0),
+    }
+    .max(1) as f64;
     
     // Render activity block
     let block = Block::default()
-        .title("Activity")
+        .title(format!("Activity ({}, v: switch)", app.gauge_scale.label()))
         .borders(Borders::ALL);
     f.render_widget(block, area);
     
     // Render gauges
-    render_gauge(f, "Logins", login_attempts as f64 / max_value, login_attempts, Color::Red, chunks[0]);
-    render_gauge(f, "Commands", *commands as f64 / max_value, *commands, Color::Blue, chunks[1]);
-    render_gauge(f, "Connections", *connections as f64 / max_value, *connections, Color::Green, chunks[2]);
-    render_gauge(f, "Files", file_transfers as f64 / max_value, file_transfers, Color::Yellow, chunks[3]);
+    let theme = &app.theme;
+    render_gauge(f, theme, "Logins", login_attempts as f64 / scale, login_attempts, Color::Red, chunks[0]);
+    render_gauge(f, theme, "Commands", *commands as f64 / scale, *commands, Color::Blue, chunks[1]);
+    render_gauge(f, theme, "Connections", *connections as f64 / scale, *connections, Color::Green, chunks[2]);
+    render_gauge(f, theme, "Files", file_transfers as f64 / scale, file_transfers, Color::Yellow, chunks[3]);
 }
 
 /// Helper to render a single gauge
+///
+/// Without colour a line gauge's filled and empty parts look alike, so the
+/// bar is drawn with solid and shaded blocks and the percentage instead.
 fn render_gauge(
     f: &mut Frame, 
+    theme: &Theme,
     label: &str, 
     ratio: f64, 
     value: usize, 
     color: Color, 
     area: Rect
 ) {
+    let title = format!("{}: {}", label, value);
+    if !theme.color() {
+        let percent = format!(" {:>3.0}%", ratio * 100.0);
+        let width = (area.width as usize).saturating_sub(percent.len());
+        let filled = ((width as f64 * ratio).round() as usize).min(width);
+        let bar = format!("{}{}{}", "█".repeat(filled), "░".repeat(width - filled), percent);
+        f.render_widget(Paragraph::new(bar).block(Block::default().title(title)), area);
+        return;
+    }
+    
     let gauge = LineGauge::default()
-        .block(Block::default().title(format!("{}: {}", label, value)))
-        .gauge_style(Style::default().fg(color).bg(Color::Black))
+        .block(Block::default().title(title))
+        .gauge_style(theme.gauge_style(color))
         .line_set(ratatui::symbols::line::THICK)
         .ratio(ratio);
     
@@ -183,107 +479,92 @@ fn render_gauge(
 
 /// Render recent sessions
 fn render_sessions(f: &mut Frame, app: &App, area: Rect) {
-    let store_guard = match app.store.try_read() {
-        Ok(guard) => guard,
-        Err(_) => return,
-    };
+    let store = app.store.snapshot();
     
     // Get recent sessions (up to 10)
-    let sessions = store_guard.get_sessions();
-    let sessions = sessions.iter().rev().take(10).collect::<Vec<_>>();
-    
-    // Create header row
-    let header_cells = ["ID", "Source IP", "Username", "Status", "Commands", "Duration"]
-        .iter()
-        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
-    let header = Row::new(header_cells).height(1).bottom_margin(1);
+    let sessions = store.get_sessions();
+    let block = Block::default().title("Recent Sessions").borders(Borders::ALL);
+    if sessions.is_empty() {
+        render_empty_state(f, app, block, "sessions", 0, area);
+        return;
+    }
+    let rows = sessions.iter().rev().take(table_rows(area)).map(|session| (*session, session_style(&app.theme, session)));
     
-    // Create data rows
-    let rows = sessions.iter().map(|session| {
-        let username = session.user.as_ref().map_or("N/A", |user| &user.username);
-        let status = if session.end_time.is_some() { "Closed" } else { "Active" };
-        let duration = session.duration.map_or("N/A".to_string(), |d| format!("{}s", d));
-        
-        let style = if session.is_malicious {
-            Style::default().fg(Color::Red)
-        } else if session.user.as_ref().map_or(false, |u| u.login_success) {
-            Style::default().fg(Color::Green)
-        } else {
-            Style::default()
-        };
-        
-        let cells = [
-            Cell::from(session.id.chars().take(8).collect::<String>()),
-            Cell::from(session.src_ip.clone()),
-            Cell::from(username),
-            Cell::from(status),
-            Cell::from(session.commands.len().to_string()),
-            Cell::from(duration),
-        ];
-        
-        Row::new(cells).style(style)
-    });
+    let spec = recent_session_table();
+    let chosen = spec.chosen(&app.config.ui);
     
     // Create table
-    let table = Table::new(rows)
-        .header(header)
-        .block(Block::default().title("Recent Sessions").borders(Borders::ALL))
-        .widths(&[
-            Constraint::Percentage(15),
-            Constraint::Percentage(25),
-            Constraint::Percentage(20),
-            Constraint::Percentage(10),
-            Constraint::Percentage(10),
-            Constraint::Percentage(20),
-        ])
+    let layout = spec.layout(app, area, &chosen);
+    let table = spec.table(app, &layout, &chosen, rows)
+        .block(block)
         .highlight_style(Style::default().add_modifier(Modifier::BOLD));
     
     f.render_widget(table, area);
 }
 
-/// Render top attackers
+/// Render top attackers, ranked by the `[scoring.attacker]` score
 fn render_attackers(f: &mut Frame, app: &App, area: Rect) {
-    let store_guard = match app.store.try_read() {
-        Ok(guard) => guard,
-        Err(_) => return,
-    };
+    let store = app.store.snapshot();
     
-    // Count sessions by source IP
-    let mut ip_counts = HashMap::new();
-    for session in store_guard.get_sessions() {
-        *ip_counts.entry(session.src_ip.clone()).or_insert(0) += 1;
-    }
+    let (attackers, age) = app.panel_caches.attackers.get_or_compute(|| {
+        let mut attackers = attacker_profiles(&store);
+        rank_attackers(&mut attackers, &app.config.scoring.attacker);
+        attackers
+    });
     
-    // Sort by count
-    let mut ip_counts = ip_counts.into_iter().collect::<Vec<_>>();
-    ip_counts.sort_by(|a, b| b.1.cmp(&a.1));
+    // Take as many as fit
+    let attackers = attackers.iter().take(table_rows(area)).collect::<Vec<_>>();
     
-    // Take top 10
-    let ip_counts = ip_counts.into_iter().take(10).collect::<Vec<_>>();
+    let block = Block::default()
+        .title(cached_title("Top Attackers (↻ returning)", age))
+        .borders(Borders::ALL);
+    if attackers.is_empty() {
+        render_empty_state(f, app, block, "attackers", 0, area);
+        return;
+    }
     
     // Create header row
-    let header_cells = ["IP Address", "Sessions"]
+    let header_cells = ["IP Address", "Score", "Sessions", "Why", "First Seen", "Last Seen"]
         .iter()
         .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
     let header = Row::new(header_cells).height(1).bottom_margin(1);
     
     // Create data rows
-    let rows = ip_counts.iter().map(|(ip, count)| {
+    let anon = Anonymizer::for_app(app);
+    let weights = &app.config.scoring.attacker;
+    let rows = attackers.iter().map(|attacker| {
+        // Returning IPs are marked so they stand out from one-shot scanners
+        let activity = store.get_ip_activity(&attacker.ip);
+        let returning = activity.map_or(false, |activity| activity.is_returning());
+        let seen = |at: Option<DateTime<Utc>>| at.map_or_else(|| "-".to_string(), |at| humanize_ago(Utc::now() - at));
+        
         let cells = [
-            Cell::from(ip.clone()),
-            Cell::from(count.to_string()),
+            Cell::from(format!("{}{}", anon.ip(&attacker.ip), if returning { " ↻" } else { "" })),
+            Cell::from(format!("{:.0}", attacker.score(weights))),
+            Cell::from(attacker.sessions.to_string()),
+            Cell::from(attacker.badges(app.config.rules.min_risk_score).join(" ")),
+            Cell::from(seen(activity.map(|activity| activity.first_seen))),
+            Cell::from(seen(activity.map(|activity| activity.last_seen))),
         ];
         
-        Row::new(cells)
+        if returning {
+            Row::new(cells).style(Style::default().fg(Color::Magenta))
+        } else {
+            Row::new(cells)
+        }
     });
     
     // Create table
     let table = Table::new(rows)
         .header(header)
-        .block(Block::default().title("Top Attackers").borders(Borders::ALL))
+        .block(block)
         .widths(&[
-            Constraint::Percentage(70),
-            Constraint::Percentage(30),
+            Constraint::Percentage(26),
+            Constraint::Percentage(8),
+            Constraint::Percentage(10),
+            Constraint::Percentage(24),
+            Constraint::Percentage(16),
+            Constraint::Percentage(16),
         ]);
     
     f.render_widget(table, area);
@@ -291,19 +572,18 @@ fn render_attackers(f: &mut Frame, app: &App, area: Rect) {
 
 /// Render top credentials
 fn render_credentials(f: &mut Frame, app: &App, area: Rect) {
-    let store_guard = match app.store.try_read() {
-        Ok(guard) => guard,
-        Err(_) => return,
-    };
+    let store = app.store.snapshot();
     
-    // Count username/password combinations
+    // Count username/password combinations, normalized as configured
+    let normalizer = store.credential_normalizer();
     let mut cred_counts = HashMap::new();
-    for entry in store_guard.get_log_entries() {
+    for entry in store.get_log_entries() {
         if entry.event_type == EventType::LoginAttempt || 
            entry.event_type == EventType::LoginSuccess || 
            entry.event_type == EventType::LoginFailed {
             if let (Some(username), Some(password)) = (&entry.username, &entry.password) {
-                *cred_counts.entry((username.clone(), password.clone())).or_insert(0) += 1;
+                let key = (normalizer.username(username).into_owned(), normalizer.password(password).into_owned());
+                *cred_counts.entry(key).or_insert(0) += 1;
             }
         }
     }
@@ -312,8 +592,14 @@ fn render_credentials(f: &mut Frame, app: &App, area: Rect) {
     let mut cred_counts = cred_counts.into_iter().collect::<Vec<_>>();
     cred_counts.sort_by(|a, b| b.1.cmp(&a.1));
     
-    // Take top 10
-    let cred_counts = cred_counts.into_iter().take(10).collect::<Vec<_>>();
+    // Take as many as fit
+    let cred_counts = cred_counts.into_iter().take(table_rows(area)).collect::<Vec<_>>();
+    
+    let block = Block::default().title("Top Credentials").borders(Borders::ALL);
+    if cred_counts.is_empty() {
+        render_empty_state(f, app, block, "login attempts", 0, area);
+        return;
+    }
     
     // Create header row
     let header_cells = ["Username", "Password", "Count"]
@@ -321,11 +607,18 @@ fn render_credentials(f: &mut Frame, app: &App, area: Rect) {
         .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
     let header = Row::new(header_cells).height(1).bottom_margin(1);
     
+    let columns = Columns::new(area, WidthBucket::of(area).pick(
+        vec![Constraint::Percentage(40), Constraint::Percentage(40), Constraint::Length(6)],
+        vec![Constraint::Percentage(40), Constraint::Percentage(45), Constraint::Length(8)],
+        vec![Constraint::Percentage(40), Constraint::Percentage(50), Constraint::Length(8)],
+    ), app.config.ui.max_field_width);
+    
     // Create data rows
+    let anon = Anonymizer::for_app(app);
     let rows = cred_counts.iter().map(|((username, password), count)| {
         let cells = [
-            Cell::from(username.clone()),
-            Cell::from(password.clone()),
+            Cell::from(columns.fit(0, &anon.credential(username)).into_owned()),
+            Cell::from(columns.fit(1, &anon.password(password)).into_owned()),
             Cell::from(count.to_string()),
         ];
         
@@ -335,12 +628,73 @@ fn render_credentials(f: &mut Frame, app: &App, area: Rect) {
     // Create table
     let table = Table::new(rows)
         .header(header)
-        .block(Block::default().title("Top Credentials").borders(Borders::ALL))
-        .widths(&[
-            Constraint::Percentage(35),
-            Constraint::Percentage(45),
-            Constraint::Percentage(20),
-        ]);
+        .block(block)
+        .widths(columns.constraints());
     
     f.render_widget(table, area);
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// Panels per row and rows of panels the dashboard gets at `width`
+    fn grid(width: u16) -> (usize, usize) {
+        let areas = dashboard_areas(Rect::new(0, 0, width, 50));
+        assert_eq!(areas.len(), DASHBOARD_PANELS);
+        let mut rows: Vec<u16> = areas.iter().map(|area| area.y).collect();
+        rows.dedup();
+        let columns = rows.iter()
+            .map(|y| areas.iter().filter(|area| area.y == *y).count())
+            .max()
+            .unwrap();
+        (columns, rows.len())
+    }
+
+    #[test]
+    fn the_layout_follows_the_width() {
+        for (width, layout) in [
+            (40, (1, 6)),
+            (80, (1, 6)),
+            (99, (1, 6)),
+            (100, (2, 3)),
+            (130, (2, 3)),
+            (159, (2, 3)),
+            (160, (3, 2)),
+            (250, (3, 2)),
+        ] {
+            assert_eq!(grid(width), layout, "width {}", width);
+        }
+    }
+
+    #[test]
+    fn the_panels_tile_the_dashboard() {
+        for width in [80, 130, 200] {
+            let area = Rect::new(3, 2, width, 48);
+            let areas = dashboard_areas(area);
+            for (i, a) in areas.iter().enumerate() {
+                assert_eq!(a.intersection(area), *a);
+                assert!(areas[i + 1..].iter().all(|b| !a.intersects(*b)), "width {}: {} overlaps", width, i);
+            }
+            let covered: u32 = areas.iter().map(|a| a.width as u32 * a.height as u32).sum();
+            assert_eq!(covered, width as u32 * 48, "width {}", width);
+        }
+    }
+
+    #[test]
+    fn panels_keep_their_reading_order() {
+        // Focus moves through the panels in the order they are read
+        for width in [80, 130, 200] {
+            let areas = dashboard_areas(Rect::new(0, 0, width, 50));
+            assert!(areas.windows(2).all(|pair| (pair[0].y, pair[0].x) < (pair[1].y, pair[1].x)), "width {}", width);
+        }
+    }
+
+    #[test]
+    fn narrow_terminals_give_recent_sessions_the_most_room() {
+        let areas = dashboard_areas(Rect::new(0, 0, 80, 40));
+        let tallest = areas.iter().map(|area| area.height).max().unwrap();
+        assert_eq!(areas[2].height, tallest);
+        assert!(areas.iter().enumerate().all(|(i, area)| i == 2 || area.height < tallest));
+    }
+}
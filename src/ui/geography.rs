@@ -1,19 +1,104 @@
 use crossterm::event::KeyCode;
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::{Span, Spans},
+    text::{Line, Span},
     widgets::{Block, Borders, List, ListItem, Paragraph},
     Frame,
 };
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::fmt;
 
 use crate::app::App;
-use crate::ui::components::map::WorldMap;
+use crate::ui::components::{IpMapModel, WorldMap};
+use crate::ui::panels::{cached_title, highlight_focused, list_rows, panel_areas, render_empty_state};
+
+/// How the country and ASN panels are ranked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GeoRanking {
+    /// Sum of session risk scores
+    #[default]
+    TotalRisk,
+    /// Mean session risk score, so a few highly malicious sessions outrank a noisy scanner
+    AverageRisk,
+    /// Number of sessions
+    Count,
+}
+
+impl GeoRanking {
+    /// Next ranking in the toggle order
+    pub fn next(self) -> Self {
+        match self {
+            GeoRanking::TotalRisk => GeoRanking::AverageRisk,
+            GeoRanking::AverageRisk => GeoRanking::Count,
+            GeoRanking::Count => GeoRanking::TotalRisk,
+        }
+    }
+}
+
+impl fmt::Display for GeoRanking {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeoRanking::TotalRisk => write!(f, "total risk"),
+            GeoRanking::AverageRisk => write!(f, "average risk"),
+            GeoRanking::Count => write!(f, "sessions"),
+        }
+    }
+}
+
+/// Sessions and risk per country and per ASN, as cached for the rankings
+#[derive(Debug, Clone, Default)]
+pub struct GeoAggregates {
+    /// Keyed by country name
+    countries: HashMap<String, RiskAggregate>,
+    /// Keyed by "ASN - ISP"
+    asns: HashMap<String, RiskAggregate>,
+}
+
+/// Sessions and risk attributed to one country or ASN
+#[derive(Debug, Clone, Copy, Default)]
+struct RiskAggregate {
+    /// Number of sessions
+    sessions: usize,
+    /// Sum of the sessions' `malicious_score`
+    total_risk: u64,
+}
+
+impl RiskAggregate {
+    /// Count a session with the given risk score
+    fn add(&mut self, score: u8) {
+        self.sessions += 1;
+        self.total_risk += score as u64;
+    }
+    
+    /// Mean risk score per session
+    fn average_risk(&self) -> f64 {
+        if self.sessions == 0 {
+            0.0
+        } else {
+            self.total_risk as f64 / self.sessions as f64
+        }
+    }
+    
+    /// Order by `ranking`, highest first
+    fn cmp_by(&self, other: &Self, ranking: GeoRanking) -> Ordering {
+        let primary = match ranking {
+            GeoRanking::TotalRisk => other.total_risk.cmp(&self.total_risk),
+            GeoRanking::AverageRisk => other.average_risk()
+                .partial_cmp(&self.average_risk())
+                .unwrap_or(Ordering::Equal),
+            GeoRanking::Count => other.sessions.cmp(&self.sessions),
+        };
+        
+        primary
+            .then_with(|| other.total_risk.cmp(&self.total_risk))
+            .then_with(|| other.sessions.cmp(&self.sessions))
+    }
+}
 
 /// Render geography view - interface for UI module
-pub fn render_geography(f: &mut Frame, app: &App, area: Rect) {
+pub fn render_geography(f: &mut Frame, app: &App, _area: Rect) {
     draw(f, app);
 }
 
@@ -37,14 +122,14 @@ pub fn draw(f: &mut Frame, app: &App) {
 /// Draw the title area
 fn draw_title(f: &mut Frame, app: &App, area: Rect) {
     let title = Paragraph::new(vec![
-        Spans::from(vec![
+        Line::from(vec![
             Span::styled(
-                app.title.clone(),
+                app.config.ui.terminal_title.clone(),
                 Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
             ),
             Span::raw(" - Geographic Visualization"),
         ]),
-        Spans::from(vec![
+        Line::from(vec![
             Span::raw("Press "),
             Span::styled("1-6", Style::default().fg(Color::Yellow)),
             Span::raw(" to switch tabs, "),
@@ -59,6 +144,12 @@ fn draw_title(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(title, area);
 }
 
+/// Geography panels in focus order: world map, then country and ASN rankings
+const PANELS: [fn(&mut Frame, &App, Rect); 2] = [draw_world_map, draw_country_stats];
+
+/// Number of panels on the geography tab, for moving focus between them
+pub const GEOGRAPHY_PANELS: usize = PANELS.len();
+
 /// Draw the main content area
 fn draw_content(f: &mut Frame, app: &App, area: Rect) {
     let horizontal_chunks = Layout::default()
@@ -69,31 +160,45 @@ fn draw_content(f: &mut Frame, app: &App, area: Rect) {
         ])
         .split(area);
         
-    draw_world_map(f, app, horizontal_chunks[0]);
-    draw_country_stats(f, app, horizontal_chunks[1]);
+    for (index, panel_area) in panel_areas(&app.panel_focus, horizontal_chunks.to_vec(), area) {
+        PANELS[index](f, app, panel_area);
+        highlight_focused(f, &app.panel_focus, index, PANELS.len(), panel_area);
+    }
 }
 
 /// Draw the world map with attack points
 fn draw_world_map(f: &mut Frame, app: &App, area: Rect) {
-    let map_block = Block::default()
-        .title("Attack Origins")
-        .borders(Borders::ALL);
+    let store = app.store.snapshot();
     
-    f.render_widget(map_block, area);
+    // Build the map model from session locations, counting sessions per IP for intensity
+    let mut model = IpMapModel::default();
+    let mut ip_sessions: HashMap<String, usize> = HashMap::new();
+    
+    for session in store.get_sessions() {
+        model.add_location(session.src_ip.to_string(), session.geo_location.as_ref());
+        *ip_sessions.entry(session.src_ip.to_string()).or_insert(0) += 1;
+    }
     
-    // In a real implementation, we would use actual attacker coordinates
-    // For demonstration, we'll create some example attack points
-    let attack_points = vec![
-        (40.7128, -74.0060, 5),  // New York
-        (51.5074, -0.1278, 8),   // London
-        (39.9042, 116.4074, 15), // Beijing
-        (55.7558, 37.6173, 7),   // Moscow
-        (35.6762, 139.6503, 10), // Tokyo
-        (-33.8688, 151.2093, 3), // Sydney
-    ];
+    // Only IPs with real coordinates are plotted
+    let attack_points = model.ip_coordinates.iter()
+        .map(|(ip, (lat, lon))| {
+            let count = ip_sessions.get(ip).copied().unwrap_or(1);
+            (*lat, *lon, count.min(u8::MAX as usize) as u8)
+        })
+        .collect::<Vec<_>>();
+    
+    let map_block = Block::default()
+        .title(format!(
+            "Attack Origins ({} located / {} unlocated IPs)",
+            model.located_count(),
+            model.unlocated_count()
+        ))
+        .borders(Borders::ALL);
     
     // Create a world map and draw it
     let inner_area = map_block.inner(area);
+    f.render_widget(map_block, area);
+    
     let world_map = WorldMap::new(attack_points);
     f.render_widget(world_map, inner_area);
 }
@@ -107,60 +212,66 @@ fn draw_country_stats(f: &mut Frame, app: &App, area: Rect) {
             Constraint::Percentage(50),
         ])
         .split(area);
+    
+    // Aggregate sessions and risk by country and ASN; coordinates aren't needed for either.
+    // Ranking happens per draw, so `s` takes effect without waiting for a refresh
+    let (aggregates, age) = app.panel_caches.geo_aggregates.get_or_compute(|| {
+        let store = app.store.snapshot();
+        let mut aggregates = GeoAggregates::default();
+        
+        for session in store.get_sessions() {
+            if let Some(geo) = &session.geo_location {
+                aggregates.countries.entry(geo.country_name.clone()).or_default().add(session.malicious_score);
+                
+                if let Some(asn) = &geo.asn {
+                    let label = match &geo.isp {
+                        Some(isp) => format!("{} - {}", asn, isp),
+                        None => asn.clone(),
+                    };
+                    aggregates.asns.entry(label).or_default().add(session.malicious_score);
+                }
+            }
+        }
         
+        aggregates
+    });
+    
+    let ranking = app.geo_ranking;
+    
     // Top country stats panel
     let country_block = Block::default()
-        .title("Top Countries")
+        .title(cached_title(format!("Top Countries (by {}, s: sort)", ranking), age))
         .borders(Borders::ALL);
     
-    // Example country data (in a real app, this would come from the data store)
-    let countries = vec![
-        ("Russia", 158),
-        ("China", 143),
-        ("United States", 87),
-        ("Brazil", 42),
-        ("Netherlands", 36),
-        ("Germany", 29),
-        ("France", 18),
-        ("India", 15),
-    ];
-    
-    let country_items: Vec<ListItem> = countries
-        .iter()
-        .map(|(country, count)| {
-            ListItem::new(format!("{}: {} attacks", country, count))
-        })
-        .collect();
-    
-    let country_list = List::new(country_items)
-        .block(country_block)
-        .style(Style::default().fg(Color::White))
-        .highlight_style(Style::default().add_modifier(Modifier::BOLD));
-    
-    f.render_widget(country_list, vertical_chunks[0]);
+    if aggregates.countries.is_empty() {
+        render_empty_state(f, app, country_block, "geolocated sessions", 0, vertical_chunks[0]);
+    } else {
+        let country_items: Vec<ListItem> = top_ranked(&aggregates.countries, ranking, list_rows(vertical_chunks[0]))
+            .into_iter()
+            .map(|(country, aggregate)| ListItem::new(format_aggregate(&country, &aggregate)))
+            .collect();
+        
+        let country_list = List::new(country_items)
+            .block(country_block)
+            .style(Style::default().fg(Color::White))
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD));
+        
+        f.render_widget(country_list, vertical_chunks[0]);
+    }
     
     // Bottom ASN stats panel
     let asn_block = Block::default()
-        .title("Top ASNs")
+        .title(cached_title(format!("Top ASNs (by {})", ranking), age))
         .borders(Borders::ALL);
     
-    // Example ASN data
-    let asns = vec![
-        ("AS4134 - China Telecom", 87),
-        ("AS3462 - Hinet", 46),
-        ("AS4837 - China Unicom", 38),
-        ("AS7922 - Comcast", 27),
-        ("AS16509 - Amazon", 22),
-        ("AS45899 - VNPT Corp", 18),
-        ("AS9121 - Turk Telekom", 15),
-        ("AS8151 - Telmex", 12),
-    ];
-    
-    let asn_items: Vec<ListItem> = asns
-        .iter()
-        .map(|(asn, count)| {
-            ListItem::new(format!("{}: {} attacks", asn, count))
-        })
+    if aggregates.asns.is_empty() {
+        render_empty_state(f, app, asn_block, "sessions with a known ASN", 0, vertical_chunks[1]);
+        return;
+    }
+    
+    let asn_items: Vec<ListItem> = top_ranked(&aggregates.asns, ranking, list_rows(vertical_chunks[1]))
+        .into_iter()
+        .map(|(asn, aggregate)| ListItem::new(format_aggregate(&asn, &aggregate)))
         .collect();
     
     let asn_list = List::new(asn_items)
@@ -171,10 +282,35 @@ fn draw_country_stats(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(asn_list, vertical_chunks[1]);
 }
 
+/// Rank aggregates by `ranking` and keep the top `n`
+fn top_ranked(
+    aggregates: &HashMap<String, RiskAggregate>,
+    ranking: GeoRanking,
+    n: usize,
+) -> Vec<(String, RiskAggregate)> {
+    let mut aggregates = aggregates.iter()
+        .map(|(label, aggregate)| (label.clone(), *aggregate))
+        .collect::<Vec<_>>();
+    aggregates.sort_by(|a, b| a.1.cmp_by(&b.1, ranking).then_with(|| a.0.cmp(&b.0)));
+    aggregates.truncate(n);
+    aggregates
+}
+
+/// One line of a ranking panel: sessions, total and average risk
+fn format_aggregate(label: &str, aggregate: &RiskAggregate) -> String {
+    format!(
+        "{}: {} sessions, risk {} (avg {:.0})",
+        label,
+        aggregate.sessions,
+        aggregate.total_risk,
+        aggregate.average_risk(),
+    )
+}
+
 /// Draw the status bar
-fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
+fn draw_status_bar(f: &mut Frame, _app: &App, area: Rect) {
     let status = Paragraph::new(vec![
-        Spans::from(vec![
+        Line::from(vec![
             Span::styled(
                 "TOTAL COUNTRIES: ",
                 Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
@@ -199,7 +335,8 @@ fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
 }
 
 /// Handle keyboard input for the geography view
-pub fn handle_input(app: &mut App, key: KeyCode) {
+#[allow(dead_code)]
+pub fn handle_input(_app: &mut App, key: KeyCode) {
     match key {
         // Add geography-specific key handlers here
         KeyCode::Char('z') => {
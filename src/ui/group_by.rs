@@ -0,0 +1,256 @@
+use ratatui::{
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Clear, List, ListItem, ListState, Row, Table, TableState},
+    Frame,
+};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::app::App;
+use crate::data::{LogEntry, StoreSnapshot};
+use crate::ui::anonymize::Anonymizer;
+use crate::ui::logs::visible_logs;
+use crate::ui::sanitize::escape_controls;
+
+/// Field the logs tab can be grouped by (`gb`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupField {
+    SrcIp,
+    Username,
+    Password,
+    EventType,
+    BaseCommand,
+    Country,
+    DstPort,
+}
+
+impl GroupField {
+    /// Every field, in chooser order
+    pub const ALL: [GroupField; 7] = [
+        GroupField::SrcIp,
+        GroupField::Username,
+        GroupField::Password,
+        GroupField::EventType,
+        GroupField::BaseCommand,
+        GroupField::Country,
+        GroupField::DstPort,
+    ];
+
+    /// Name in the chooser, table header and export file
+    pub fn name(self) -> &'static str {
+        match self {
+            GroupField::SrcIp => "src_ip",
+            GroupField::Username => "username",
+            GroupField::Password => "password",
+            GroupField::EventType => "event_type",
+            GroupField::BaseCommand => "command",
+            GroupField::Country => "country",
+            GroupField::DstPort => "dst_port",
+        }
+    }
+
+    /// Value `entry` is counted under, if it has one
+    ///
+    /// Commands are grouped by their first word, so `wget http://a` and
+    /// `wget http://b` land together; countries come from the entry's session.
+    fn value(self, entry: &LogEntry, store: &StoreSnapshot) -> Option<String> {
+        match self {
+            GroupField::SrcIp => entry.src_ip.as_deref().map(str::to_string),
+            GroupField::Username => entry.username.as_deref().map(str::to_string),
+            GroupField::Password => entry.password.clone(),
+            GroupField::EventType => Some(entry.event_type.to_string()),
+            GroupField::BaseCommand => entry.command.as_deref()
+                .and_then(|command| command.split_whitespace().next())
+                .map(str::to_string),
+            GroupField::Country => store.get_session_for_entry(entry)
+                .and_then(|session| session.geo_location.as_ref())
+                .map(|geo| geo.country_name.clone()),
+            GroupField::DstPort => entry.dst_port.map(|port| port.to_string()),
+        }
+    }
+
+    /// How a value is shown on screen, masked like everywhere else
+    fn display(self, anon: &Anonymizer, value: &str) -> String {
+        let shown = match self {
+            GroupField::SrcIp => anon.ip(value),
+            GroupField::Username => anon.credential(value),
+            GroupField::Password => anon.password(value),
+            GroupField::BaseCommand => anon.text(value),
+            _ => Cow::Borrowed(value),
+        };
+        escape_controls(&shown).into_owned()
+    }
+}
+
+/// Order of a group-by table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupSort {
+    /// Largest groups first
+    Count,
+    /// Alphabetical by value
+    Value,
+}
+
+/// Group-by table shown in place of the log list
+#[derive(Debug, Clone)]
+pub struct GroupBy {
+    /// Field entries are grouped by
+    pub field: GroupField,
+    /// Row order
+    pub sort: GroupSort,
+    /// Highlighted row
+    pub cursor: usize,
+}
+
+impl GroupBy {
+    /// Group by `field`, largest groups first
+    pub fn new(field: GroupField) -> Self {
+        Self {
+            field,
+            sort: GroupSort::Count,
+            cursor: 0,
+        }
+    }
+
+    /// Count `entries` by the field, in table order, along with how many had a value
+    ///
+    /// Entries without the field (e.g. no command on a login) are left out.
+    pub fn rows<'a>(
+        &self,
+        entries: impl IntoIterator<Item = &'a LogEntry>,
+        store: &StoreSnapshot,
+    ) -> (Vec<(String, usize)>, usize) {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for entry in entries {
+            if let Some(value) = self.field.value(entry, store) {
+                *counts.entry(value).or_insert(0) += 1;
+            }
+        }
+
+        let total = counts.values().sum();
+        let mut rows: Vec<(String, usize)> = counts.into_iter().collect();
+        match self.sort {
+            GroupSort::Count => rows.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0))),
+            GroupSort::Value => rows.sort_by(|a, b| a.0.cmp(&b.0)),
+        }
+
+        (rows, total)
+    }
+
+    /// Rows for the logs tab as currently filtered
+    pub fn visible_rows(&self, app: &App, store: &StoreSnapshot) -> (Vec<(String, usize)>, usize) {
+        self.rows(visible_logs(app, store), store)
+    }
+
+    /// Move the highlight by `delta` rows, within `len` rows
+    pub fn move_cursor(&mut self, delta: isize, len: usize) {
+        let last = len.saturating_sub(1) as isize;
+        self.cursor = (self.cursor as isize + delta).max(0).min(last) as usize;
+    }
+
+    /// Switch between count and value order
+    pub fn cycle_sort(&mut self) {
+        self.sort = match self.sort {
+            GroupSort::Count => GroupSort::Value,
+            GroupSort::Value => GroupSort::Count,
+        };
+        self.cursor = 0;
+    }
+}
+
+/// Render a group-by table of the filtered log entries
+pub fn render_group_by(f: &mut Frame, app: &App, group_by: &GroupBy, area: Rect) {
+    let store = app.store.snapshot();
+    let (rows, total) = group_by.visible_rows(app, &store);
+    let anon = Anonymizer::for_app(app);
+
+    let header = Row::new(vec![
+        Cell::from(group_by.field.name()),
+        Cell::from("Count"),
+        Cell::from("%"),
+    ])
+    .style(Style::default().fg(Color::Yellow))
+    .bottom_margin(1);
+
+    let table_rows = rows.iter().map(|(value, count)| {
+        let percent = *count as f64 * 100.0 / total.max(1) as f64;
+        Row::new(vec![
+            Cell::from(group_by.field.display(&anon, value)),
+            Cell::from(count.to_string()),
+            Cell::from(format!("{:.1}", percent)),
+        ])
+    });
+
+    let sort = match group_by.sort {
+        GroupSort::Count => "count",
+        GroupSort::Value => "value",
+    };
+    let mut title = vec![
+        Span::raw(format!("Group by {}", group_by.field.name())),
+        Span::styled(
+            format!(" [{} groups, {} entries, by {}]", rows.len(), total, sort),
+            Style::default().fg(Color::DarkGray),
+        ),
+    ];
+    if let Some(search) = &app.filters.search {
+        title.push(Span::styled(format!(" [/{}]", escape_controls(search)), Style::default().fg(Color::Cyan)));
+    }
+
+    let table = Table::new(table_rows)
+        .header(header)
+        .block(
+            Block::default()
+                .title(Line::from(title))
+                .title(
+                    ratatui::widgets::block::Title::from(" s: sort  E: export  Esc: back to logs ")
+                        .position(ratatui::widgets::block::Position::Bottom),
+                )
+                .borders(Borders::ALL),
+        )
+        .widths(&[Constraint::Min(20), Constraint::Length(8), Constraint::Length(6)])
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    let mut state = TableState::default();
+    if !rows.is_empty() {
+        state.select(Some(group_by.cursor.min(rows.len() - 1)));
+    }
+
+    f.render_stateful_widget(table, area, &mut state);
+}
+
+/// Render the group-by field chooser as a popup over `area`
+pub fn render_group_by_chooser(f: &mut Frame, cursor: usize, area: Rect) {
+    let width = area.width.min(40);
+    let height = area.height.min(GroupField::ALL.len() as u16 + 2);
+    let popup = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    let items: Vec<ListItem> = GroupField::ALL.iter()
+        .map(|field| ListItem::new(field.name()))
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title("Group by")
+                .title(
+                    ratatui::widgets::block::Title::from(" Enter: group  Esc: cancel ")
+                        .position(ratatui::widgets::block::Position::Bottom),
+                )
+                .borders(Borders::ALL)
+                .style(Style::default().bg(Color::Black)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    let mut state = ListState::default();
+    state.select(Some(cursor));
+
+    f.render_widget(Clear, popup);
+    f.render_stateful_widget(list, popup, &mut state);
+}
@@ -0,0 +1,76 @@
+use chrono::Utc;
+use ratatui::{
+    layout::{Constraint, Rect},
+    style::{Color, Style},
+    widgets::{Block, Borders, Cell, Clear, Row, Table},
+    Frame,
+};
+
+use crate::core::{TaskHealth, TaskState};
+use crate::ui::sanitize::escape_controls;
+use crate::utils::time::{humanize_ago, Clock};
+
+/// Colour for a task's state
+fn state_style(state: TaskState) -> Style {
+    match state {
+        TaskState::Running => Style::default().fg(Color::Green),
+        TaskState::Restarting => Style::default().fg(Color::Yellow),
+        TaskState::Failed => Style::default().fg(Color::Red),
+        TaskState::Stopped => Style::default().fg(Color::DarkGray),
+    }
+}
+
+/// Draw the task health overlay (F10) over the tab area
+pub fn render_health(f: &mut Frame, health: &TaskHealth, clock: &Clock, area: Rect) {
+    let tasks = health.snapshot();
+    let unhealthy = tasks.iter().filter(|task| task.is_unhealthy()).count();
+
+    let block = Block::default()
+        .title(format!("Background tasks: {} ({} unhealthy)", tasks.len(), unhealthy))
+        .title(
+            ratatui::widgets::block::Title::from(" Any key: close ")
+                .position(ratatui::widgets::block::Position::Bottom),
+        )
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let header = Row::new(
+        ["Task", "State", "Heartbeat", "Restarts", "Last error"].iter()
+            .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow))),
+    )
+    .height(1)
+    .bottom_margin(1);
+
+    let now = Utc::now();
+    let rows = tasks.iter().map(|task| {
+        let state = match (task.state, task.restartable) {
+            (TaskState::Running, true) => "running (restartable)".to_string(),
+            (state, _) => state.label().to_string(),
+        };
+        let last_error = match &task.last_error {
+            Some((at, error)) => format!("{}: {}", clock.date_time(at), escape_controls(error)),
+            None => String::new(),
+        };
+        Row::new(vec![
+            Cell::from(escape_controls(&task.name).into_owned()),
+            Cell::from(state).style(state_style(task.state)),
+            Cell::from(humanize_ago(now - task.heartbeat)),
+            Cell::from(task.restarts.to_string()),
+            Cell::from(last_error),
+        ])
+    });
+
+    let table = Table::new(rows)
+        .header(header)
+        .block(block)
+        .widths(&[
+            Constraint::Percentage(30),
+            Constraint::Length(21),
+            Constraint::Length(11),
+            Constraint::Length(8),
+            Constraint::Min(20),
+        ]);
+
+    f.render_widget(Clear, area);
+    f.render_widget(table, area);
+}
@@ -0,0 +1,376 @@
+use crossterm::event::KeyCode;
+use std::time::{Duration, Instant};
+
+/// Rows moved by PageUp/PageDown
+pub const PAGE_SIZE: usize = 10;
+
+/// How long a partial sequence such as `g` waits for its next key
+const SEQUENCE_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// What a key binding asks the UI to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Quit the application
+    Quit,
+    /// Switch to the next tab
+    NextTab,
+    /// Switch to the previous tab
+    PreviousTab,
+    /// Switch to a tab by index
+    SelectTab(usize),
+    /// Toggle anonymization
+    ToggleAnonymize,
+    /// Toggle password masking
+    ToggleMaskSecrets,
+    /// Re-include or hide whitelisted traffic
+    ToggleWhitelisted,
+    /// Toggle relative times
+    ToggleRelativeTimes,
+    /// Cycle the rolling time filter (last hour, day, week, all time)
+    CycleTimeRange,
+    /// Recompute cached panel data now
+    Refresh,
+    /// Switch between continuous and manual redraws
+    ToggleManualRefresh,
+    /// Export the store
+    Export,
+    /// Show or hide the help overlay
+    ToggleHelp,
+    /// Open the application log viewer
+    OpenLogViewer,
+    /// Show or hide the task health overlay
+    ToggleHealth,
+    /// Select the previous row
+    MoveUp,
+    /// Select the next row
+    MoveDown,
+    /// Move the selection up a page
+    PageUp,
+    /// Move the selection down a page
+    PageDown,
+    /// Select the first row
+    Top,
+    /// Select the last row
+    Bottom,
+    /// Open details for the selected row
+    Open,
+    /// Clear the search or close details
+    Close,
+    /// Start typing a search
+    Search,
+    /// Open the column chooser for the current table
+    ChooseColumns,
+    /// Focus the next panel
+    NextPanel,
+    /// Focus the previous panel
+    PreviousPanel,
+    /// Zoom the focused panel, or restore the grid
+    ToggleZoom,
+    /// Cycle the security dashboard layout
+    CycleLayout,
+    /// Extract the selected captured file
+    ExtractSample,
+    /// Export the open session's terminal recording
+    ExportCast,
+    /// Export the open session's original log lines
+    ExportRawSession,
+    /// Open the attacker's previous session (or switch tab when none is open)
+    PreviousIpSession,
+    /// Open the attacker's next session (or switch tab when none is open)
+    NextIpSession,
+    /// Toggle the parse failure list
+    ToggleParseErrors,
+    /// Open the group-by chooser
+    GroupBy,
+    /// Sort the group-by table by count or value
+    CycleGroupSort,
+    /// Toggle the threat intel column
+    ToggleIntel,
+    /// Collapse runs of identical log entries into one row, or list them all again
+    ToggleDuplicates,
+    /// Expand the selected collapsed log row, or collapse it again
+    ExpandRepeats,
+    /// Cycle the session tag filter
+    CycleTagFilter,
+    /// Clear the session tag, port and client filters
+    ClearTagFilter,
+    /// Show or hide the collapsed scan summaries under the sessions list
+    ToggleScanSummaries,
+    /// Move the keys to the open session's similar sessions, or back
+    FocusSimilar,
+    /// Cycle the geography ranking
+    CycleGeoRanking,
+    /// Switch the activity gauges between share of total and relative to the busiest
+    ToggleGaugeScale,
+    /// Change how the clients panel is sorted
+    CycleClientSort,
+    /// Raise the alerts panel's minimum severity, wrapping back to everything
+    CycleAlertSeverity,
+    /// Open the full-screen alerts view
+    OpenAlerts,
+    /// Reload threat intel in the background
+    RefreshIntel,
+    /// Look up the selected IP's registration over RDAP
+    LookupRdap,
+    /// Open the setup wizard
+    RunWizard,
+    /// Open the setup wizard's file browser to add a log file
+    BrowseLogPath,
+    /// Open the rule pattern tester
+    TestRule,
+}
+
+/// Where a binding is active
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Context {
+    /// Every tab
+    Global,
+    /// The dashboard tab
+    Dashboard,
+    /// The tabs made of panels: dashboard, security and geography
+    Panels,
+    /// The tables in the security, logs and sessions tabs
+    Tables,
+    /// The security tab
+    Security,
+    /// The logs tab
+    Logs,
+    /// The sessions tab
+    Sessions,
+    /// The geography tab
+    Geography,
+    /// The settings tab
+    Settings,
+}
+
+impl Context {
+    /// Heading in the help overlay
+    pub fn title(self) -> &'static str {
+        match self {
+            Context::Global => "General",
+            Context::Dashboard => "Dashboard",
+            Context::Panels => "Panels (Dashboard, Security, Geography)",
+            Context::Tables => "Tables (Security, Logs, Sessions)",
+            Context::Security => "Security View",
+            Context::Logs => "Logs View",
+            Context::Sessions => "Sessions View",
+            Context::Geography => "Geography View",
+            Context::Settings => "Settings View",
+        }
+    }
+
+    /// Whether bindings in this context are active on tab `tab`
+    pub fn applies_to(self, tab: usize) -> bool {
+        match self {
+            Context::Global => true,
+            Context::Dashboard => tab == 0,
+            Context::Panels => matches!(tab, 0 | 1 | 4),
+            Context::Tables => matches!(tab, 1..=3),
+            Context::Security => tab == 1,
+            Context::Logs => tab == 2,
+            Context::Sessions => tab == 3,
+            Context::Geography => tab == 4,
+            Context::Settings => tab == 5,
+        }
+    }
+}
+
+/// A key sequence bound to an action
+pub struct Binding {
+    /// Keys pressed in order, e.g. `g g`
+    pub keys: &'static [KeyCode],
+    /// Tabs the binding is active on
+    pub context: Context,
+    /// What the keys do
+    pub action: Action,
+    /// Shown in the help overlay; bindings sharing one are listed together
+    pub description: &'static str,
+}
+
+/// Every key binding, in help overlay order
+///
+/// A sequence must not also be the start of a longer one in an overlapping
+/// context, or the longer one could never be typed.
+pub const BINDINGS: &[Binding] = &[
+    // Ahead of the global ←/→, which they fall back to with no session open
+    Binding { keys: &[KeyCode::Left], context: Context::Sessions, action: Action::PreviousIpSession, description: "Previous/next session from the same IP, along the risk trend" },
+    Binding { keys: &[KeyCode::Right], context: Context::Sessions, action: Action::NextIpSession, description: "Previous/next session from the same IP, along the risk trend" },
+    Binding { keys: &[KeyCode::Tab], context: Context::Global, action: Action::NextTab, description: "Next tab" },
+    Binding { keys: &[KeyCode::Right], context: Context::Global, action: Action::NextTab, description: "Next tab" },
+    Binding { keys: &[KeyCode::BackTab], context: Context::Global, action: Action::PreviousTab, description: "Previous tab" },
+    Binding { keys: &[KeyCode::Left], context: Context::Global, action: Action::PreviousTab, description: "Previous tab" },
+    Binding { keys: &[KeyCode::Char('1')], context: Context::Global, action: Action::SelectTab(0), description: "Select tab directly" },
+    Binding { keys: &[KeyCode::Char('2')], context: Context::Global, action: Action::SelectTab(1), description: "Select tab directly" },
+    Binding { keys: &[KeyCode::Char('3')], context: Context::Global, action: Action::SelectTab(2), description: "Select tab directly" },
+    Binding { keys: &[KeyCode::Char('4')], context: Context::Global, action: Action::SelectTab(3), description: "Select tab directly" },
+    Binding { keys: &[KeyCode::Char('5')], context: Context::Global, action: Action::SelectTab(4), description: "Select tab directly" },
+    Binding { keys: &[KeyCode::Char('6')], context: Context::Global, action: Action::SelectTab(5), description: "Select tab directly" },
+    Binding { keys: &[KeyCode::Char('A')], context: Context::Global, action: Action::ToggleAnonymize, description: "Toggle anonymization of IPs, credentials and file hashes" },
+    Binding { keys: &[KeyCode::F(9)], context: Context::Global, action: Action::ToggleMaskSecrets, description: "Toggle password masking (\"••••\") for screen shares" },
+    Binding { keys: &[KeyCode::Char('W')], context: Context::Global, action: Action::ToggleWhitelisted, description: "Re-include or hide whitelisted IPs (filter.exclude_whitelisted = \"view\")" },
+    Binding { keys: &[KeyCode::Char('T')], context: Context::Global, action: Action::ToggleRelativeTimes, description: "Toggle relative times (\"3m ago\")" },
+    Binding { keys: &[KeyCode::Char('P')], context: Context::Global, action: Action::CycleTimeRange, description: "Limit the view to the last hour, day or week, or all time; the dashboard summary compares it with the period before" },
+    Binding { keys: &[KeyCode::F(5)], context: Context::Global, action: Action::Refresh, description: "Redraw with the latest data and recompute the cached panels (correlation, distributions, geography)" },
+    Binding { keys: &[KeyCode::Char('r')], context: Context::Global, action: Action::Refresh, description: "Redraw with the latest data and recompute the cached panels (correlation, distributions, geography)" },
+    Binding { keys: &[KeyCode::Char('R')], context: Context::Global, action: Action::ToggleManualRefresh, description: "Toggle manual refresh (redraw only on keys and r/F5; ingestion continues)" },
+    Binding { keys: &[KeyCode::Char('E')], context: Context::Global, action: Action::Export, description: "Export events (json/ecs, see [export]); asks whether to export the filtered view or all" },
+    Binding { keys: &[KeyCode::Char('?')], context: Context::Global, action: Action::ToggleHelp, description: "Show this help" },
+    Binding { keys: &[KeyCode::F(12)], context: Context::Global, action: Action::OpenLogViewer, description: "Show the application's own log" },
+    Binding { keys: &[KeyCode::F(10)], context: Context::Global, action: Action::ToggleHealth, description: "Show the health of background tasks (watchers, store writer, alert engine)" },
+    Binding { keys: &[KeyCode::Char('q')], context: Context::Global, action: Action::Quit, description: "Quit the application" },
+    Binding { keys: &[KeyCode::Char(']')], context: Context::Panels, action: Action::NextPanel, description: "Focus the next/previous panel" },
+    Binding { keys: &[KeyCode::Char('[')], context: Context::Panels, action: Action::PreviousPanel, description: "Focus the next/previous panel" },
+    Binding { keys: &[KeyCode::Char('z')], context: Context::Panels, action: Action::ToggleZoom, description: "Zoom the focused panel to the whole screen, or restore the grid" },
+    Binding { keys: &[KeyCode::Esc], context: Context::Panels, action: Action::Close, description: "Restore the grid from a zoomed panel" },
+    Binding { keys: &[KeyCode::Up], context: Context::Tables, action: Action::MoveUp, description: "Move up" },
+    Binding { keys: &[KeyCode::Char('k')], context: Context::Tables, action: Action::MoveUp, description: "Move up" },
+    Binding { keys: &[KeyCode::Down], context: Context::Tables, action: Action::MoveDown, description: "Move down" },
+    Binding { keys: &[KeyCode::Char('j')], context: Context::Tables, action: Action::MoveDown, description: "Move down" },
+    Binding { keys: &[KeyCode::PageUp], context: Context::Tables, action: Action::PageUp, description: "Page up" },
+    Binding { keys: &[KeyCode::PageDown], context: Context::Tables, action: Action::PageDown, description: "Page down" },
+    Binding { keys: &[KeyCode::Char('g'), KeyCode::Char('g')], context: Context::Tables, action: Action::Top, description: "Jump to the first row" },
+    Binding { keys: &[KeyCode::Home], context: Context::Tables, action: Action::Top, description: "Jump to the first row" },
+    Binding { keys: &[KeyCode::Char('G')], context: Context::Tables, action: Action::Bottom, description: "Jump to the last row (logs: follow new entries)" },
+    Binding { keys: &[KeyCode::End], context: Context::Tables, action: Action::Bottom, description: "Jump to the last row (logs: follow new entries)" },
+    Binding { keys: &[KeyCode::Enter], context: Context::Tables, action: Action::Open, description: "Show details" },
+    Binding { keys: &[KeyCode::Esc], context: Context::Tables, action: Action::Close, description: "Clear the search, then close details" },
+    Binding { keys: &[KeyCode::Char('/')], context: Context::Tables, action: Action::Search, description: "Search the table (re: or Ctrl+R for regex, Enter to apply, Esc to cancel)" },
+    Binding { keys: &[KeyCode::Char('o')], context: Context::Tables, action: Action::ChooseColumns, description: "Choose and reorder columns (logs and sessions)" },
+    Binding { keys: &[KeyCode::Char('o')], context: Context::Dashboard, action: Action::ChooseColumns, description: "Choose and reorder the recent sessions columns" },
+    Binding { keys: &[KeyCode::Char('v')], context: Context::Dashboard, action: Action::ToggleGaugeScale, description: "Fill the activity gauges by share of the total, or relative to the busiest category" },
+    Binding { keys: &[KeyCode::Char('l')], context: Context::Security, action: Action::CycleLayout, description: "Switch dashboard layout (presets, then [dashboard.grids])" },
+    Binding { keys: &[KeyCode::Char('L')], context: Context::Security, action: Action::CycleLayout, description: "Switch dashboard layout (presets, then [dashboard.grids])" },
+    Binding { keys: &[KeyCode::Char('s')], context: Context::Security, action: Action::CycleClientSort, description: "Sort the clients panel by sessions, IPs, average risk or last seen" },
+    Binding { keys: &[KeyCode::Char('a')], context: Context::Security, action: Action::OpenAlerts, description: "Open the alerts view: every kept alert, with acknowledge and clear" },
+    Binding { keys: &[KeyCode::Char('f')], context: Context::Security, action: Action::CycleAlertSeverity, description: "Show only alerts at or above a severity (cycles info to critical)" },
+    Binding { keys: &[KeyCode::Char('i')], context: Context::Security, action: Action::RefreshIntel, description: "Refresh threat intel now instead of waiting for update_frequency" },
+    Binding { keys: &[KeyCode::Char('d'), KeyCode::Char('d')], context: Context::Security, action: Action::ExtractSample, description: "Extract the selected captured file as a sample" },
+    Binding { keys: &[KeyCode::Char('i')], context: Context::Logs, action: Action::ToggleIntel, description: "Toggle the threat intel column" },
+    Binding { keys: &[KeyCode::Char('g'), KeyCode::Char('b')], context: Context::Logs, action: Action::GroupBy, description: "Group the filtered entries by a field, with counts (Esc to go back)" },
+    Binding { keys: &[KeyCode::Char('s')], context: Context::Logs, action: Action::CycleGroupSort, description: "Sort the group-by table by count or value" },
+    Binding { keys: &[KeyCode::Char('!')], context: Context::Logs, action: Action::ToggleParseErrors, description: "Show recent parse failures" },
+    Binding { keys: &[KeyCode::Char('c')], context: Context::Logs, action: Action::ToggleDuplicates, description: "Collapse runs of identical entries into one row with a count (ui.duplicate_fields)" },
+    Binding { keys: &[KeyCode::Char('e')], context: Context::Logs, action: Action::ExpandRepeats, description: "Expand the selected collapsed row, or collapse it again" },
+    Binding { keys: &[KeyCode::Char('w')], context: Context::Logs, action: Action::LookupRdap, description: "Look up the selected entry's source IP over RDAP: network, organisation, country, abuse contact (rdap.enabled)" },
+    Binding { keys: &[KeyCode::Char('t')], context: Context::Sessions, action: Action::CycleTagFilter, description: "Cycle the tag filter" },
+    Binding { keys: &[KeyCode::Backspace], context: Context::Sessions, action: Action::ClearTagFilter, description: "Clear the tag, port and client filters" },
+    Binding { keys: &[KeyCode::Char('s')], context: Context::Sessions, action: Action::FocusSimilar, description: "Select among the open session's similar sessions (Enter: open, Esc: back)" },
+    Binding { keys: &[KeyCode::Char('a')], context: Context::Sessions, action: Action::ExportCast, description: "Export the open session's terminal recording as an asciinema .cast" },
+    Binding { keys: &[KeyCode::Char('x')], context: Context::Sessions, action: Action::ExportRawSession, description: "Export the open session's original log lines as newline-delimited JSON" },
+    Binding { keys: &[KeyCode::Char('w')], context: Context::Sessions, action: Action::LookupRdap, description: "Look up the session's source IP over RDAP: network, organisation, country, abuse contact (rdap.enabled)" },
+    Binding { keys: &[KeyCode::Char('S')], context: Context::Sessions, action: Action::ToggleScanSummaries, description: "Show or hide scans collapsed into per-IP summaries (filter.collapse_scans)" },
+    Binding { keys: &[KeyCode::Char('s')], context: Context::Geography, action: Action::CycleGeoRanking, description: "Rank countries/ASNs by total risk, average risk or sessions" },
+    Binding { keys: &[KeyCode::Char('w')], context: Context::Settings, action: Action::RunWizard, description: "Run the setup wizard again" },
+    Binding { keys: &[KeyCode::Char('b')], context: Context::Settings, action: Action::BrowseLogPath, description: "Browse for a Cowrie log file to monitor" },
+    Binding { keys: &[KeyCode::Char('t')], context: Context::Settings, action: Action::TestRule, description: "Test a rule pattern against the stored commands" },
+];
+
+/// Outcome of feeding one key to a [`KeySequence`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// The keys so far start a longer sequence
+    Pending,
+    /// A complete sequence was typed
+    Action(Action),
+    /// Nothing is bound to the key
+    Unbound,
+}
+
+/// Matches keys against [`BINDINGS`], holding partial sequences such as `g`
+#[derive(Debug, Default)]
+pub struct KeySequence {
+    /// Keys of the unfinished sequence
+    pending: Vec<KeyCode>,
+    /// When the last key arrived
+    last_key: Option<Instant>,
+}
+
+impl KeySequence {
+    /// Feed a key pressed on tab `tab`
+    ///
+    /// A key that doesn't continue the pending sequence drops it and is
+    /// matched on its own, so `g` then `j` still moves down.
+    pub fn feed(&mut self, key: KeyCode, tab: usize) -> Step {
+        if self.expired() {
+            self.pending.clear();
+        }
+        self.pending.push(key);
+        self.last_key = Some(Instant::now());
+
+        let mut prefix = false;
+        for binding in BINDINGS.iter().filter(|binding| binding.context.applies_to(tab)) {
+            if binding.keys == self.pending.as_slice() {
+                self.pending.clear();
+                return Step::Action(binding.action);
+            }
+            prefix |= binding.keys.starts_with(&self.pending);
+        }
+
+        if prefix {
+            return Step::Pending;
+        }
+
+        let retry = self.pending.len() > 1;
+        self.pending.clear();
+        if retry { self.feed(key, tab) } else { Step::Unbound }
+    }
+
+    /// Keys typed so far of an unfinished sequence, for the status bar
+    pub fn pending(&self) -> Option<String> {
+        if self.pending.is_empty() || self.expired() {
+            return None;
+        }
+        Some(self.pending.iter().map(|key| key_label(*key)).collect())
+    }
+
+    /// Drop the pending sequence
+    #[allow(dead_code)]
+    pub fn reset(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Whether the pending sequence waited too long for its next key
+    fn expired(&self) -> bool {
+        self.last_key.map_or(false, |last| last.elapsed() > SEQUENCE_TIMEOUT)
+    }
+}
+
+/// Bindings active in `context`, grouped by description: ("↑/k", "Move up")
+pub fn help_entries(context: Context) -> Vec<(String, &'static str)> {
+    let mut entries: Vec<(String, &'static str)> = Vec::new();
+
+    for binding in BINDINGS.iter().filter(|binding| binding.context == context) {
+        let keys: String = binding.keys.iter().map(|key| key_label(*key)).collect();
+        match entries.iter_mut().find(|(_, description)| *description == binding.description) {
+            Some((label, _)) => {
+                label.push('/');
+                label.push_str(&keys);
+            }
+            None => entries.push((keys, binding.description)),
+        }
+    }
+
+    entries
+}
+
+/// How a key is written in help and the status bar
+fn key_label(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Up => "↑".to_string(),
+        KeyCode::Down => "↓".to_string(),
+        KeyCode::Left => "←".to_string(),
+        KeyCode::Right => "→".to_string(),
+        KeyCode::PageUp => "PgUp".to_string(),
+        KeyCode::PageDown => "PgDn".to_string(),
+        KeyCode::Home => "Home".to_string(),
+        KeyCode::End => "End".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::BackTab => "Shift+Tab".to_string(),
+        KeyCode::Backspace => "Backspace".to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        other => format!("{:?}", other),
+    }
+}
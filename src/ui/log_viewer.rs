@@ -0,0 +1,56 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::ui::sanitize::escape_controls;
+use crate::utils::logger;
+
+/// Viewer of the application's own log, opened with F12
+#[derive(Debug, Default)]
+pub struct LogViewer {
+    /// Lines scrolled back from the newest (0 = follow new lines)
+    pub scroll: usize,
+}
+
+/// Colour for a log line, going by the level it carries
+fn line_style(line: &str) -> Style {
+    match line.split_whitespace().nth(2) {
+        Some("ERROR") => Style::default().fg(Color::Red),
+        Some("WARN") => Style::default().fg(Color::Yellow),
+        Some("DEBUG") | Some("TRACE") => Style::default().fg(Color::DarkGray),
+        _ => Style::default(),
+    }
+}
+
+/// Draw the viewer over the tab area
+pub fn render_log_viewer(f: &mut Frame, viewer: &LogViewer, area: Rect) {
+    let lines = logger::recent_lines();
+    let height = area.height.saturating_sub(2) as usize;
+    let scroll = viewer.scroll.min(lines.len().saturating_sub(height));
+    let end = lines.len() - scroll;
+    let start = end.saturating_sub(height);
+
+    let title = match scroll {
+        0 => format!("Application log ({} lines, following)", lines.len()),
+        scroll => format!("Application log ({} lines, {} back)", lines.len(), scroll),
+    };
+    let block = Block::default()
+        .title(title)
+        .title(
+            ratatui::widgets::block::Title::from(" ↑↓/PgUp/PgDn: scroll  End: follow  Esc/F12: close ")
+                .position(ratatui::widgets::block::Position::Bottom),
+        )
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let text: Vec<Line> = lines[start..end].iter()
+        .map(|line| Line::from(Span::styled(escape_controls(line).into_owned(), line_style(line))))
+        .collect();
+
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(text).block(block), area);
+}
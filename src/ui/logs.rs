@@ -1,45 +1,243 @@
 use anyhow::Result;
-use chrono::Local;
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::{Line, Span, Text},
-    widgets::{Block, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table, Tabs},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, TableState, Tabs},
     Frame,
 };
-use std::collections::HashMap;
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::RwLock;
 
 use crate::app::App;
-use crate::data::EventType;
+use crate::data::{EventType, LogEntry, StoreSnapshot};
+use crate::ui::anonymize::Anonymizer;
+use crate::ui::columns::{CellContent, ColumnDef, TableColumns};
+use crate::ui::components::visible_window;
+use crate::ui::keys::{Action, PAGE_SIZE};
+use crate::ui::sanitize::escape_controls;
+use crate::ui::panels::render_empty_state;
+use crate::ui::group_by::{render_group_by, render_group_by_chooser, GroupBy};
+use crate::ui::search::highlight_search;
+use crate::ui::theme::Theme;
+use crate::ui::watchlist::{highlight_command, watch_style, CommandWatchlist};
 
 /// Log view state
 pub struct LogViewState {
-    /// Table state for log entry list
-    pub table_state: ListState,
     /// Current filter
     pub filter: String,
     /// Selected event type filter
     pub event_type_filter: Option<EventType>,
     /// Show details view
     pub show_details: bool,
-    /// Selected log entry ID
-    pub selected_log_id: Option<String>,
+    /// Selected entry (while not following the tail)
+    selected: RowAnchor,
+    /// Keep the newest entry selected as new ones arrive
+    pub follow: bool,
+    /// Newest entry when the view stopped following, to count arrivals since
+    last_seen: RowAnchor,
+    /// Group-by table shown instead of the list
+    pub group_by: Option<GroupBy>,
+    /// Highlighted field while the group-by chooser is open
+    pub group_by_chooser: Option<usize>,
+    /// Show runs of identical entries (`ui.duplicate_fields`) as one row
+    pub collapse_duplicates: bool,
+    /// First entries of the runs shown whole while collapsing
+    pub expanded: HashSet<String>,
+    /// The list as last drawn, for the repeat counts of its rows
+    drawn: Mutex<Option<Arc<LogRows>>>,
+}
+
+/// A run of identical log entries, shown as one row unless expanded
+#[derive(Debug, Clone, Copy)]
+struct Repeat {
+    /// Entries in the run
+    count: usize,
+    /// Whether every entry of the run is listed
+    expanded: bool,
+}
+
+/// Rows of the log list: entry IDs oldest first, and the runs collapsed into them
+#[derive(Debug, Default)]
+pub struct LogRows {
+    ids: Vec<String>,
+    /// Runs of two or more, by the ID of their first entry
+    repeats: HashMap<String, Repeat>,
+}
+
+impl LogRows {
+    /// Rows in the list
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Whether nothing is listed
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+/// An entry remembered by ID, with the row it was found at in the list last looked in
+///
+/// Rows are only searched for when the list changes, so following a selection
+/// costs nothing per frame however long the list is.
+#[derive(Debug, Default)]
+struct RowAnchor {
+    id: Option<String>,
+    /// List last looked in, held so its address can't be reused, and the row found there
+    found: Mutex<Option<(Arc<LogRows>, Option<usize>)>>,
+}
+
+impl RowAnchor {
+    /// Remember the entry at `index` of `rows`
+    fn set(&mut self, rows: &Arc<LogRows>, index: usize) {
+        self.id = rows.ids.get(index).cloned();
+        *self.found.get_mut() = Some((Arc::clone(rows), Some(index)));
+    }
+
+    /// Remember the entry `id`, wherever it is listed
+    fn set_id(&mut self, id: String) {
+        self.id = Some(id);
+        *self.found.get_mut() = None;
+    }
+
+    fn clear(&mut self) {
+        self.id = None;
+        *self.found.get_mut() = None;
+    }
+
+    /// Row of the entry in `rows`, if it is still listed
+    fn locate(&self, rows: &Arc<LogRows>) -> Option<usize> {
+        let id = self.id.as_ref()?;
+        let mut found = self.found.lock();
+        if let Some((list, index)) = &*found {
+            if Arc::ptr_eq(list, rows) {
+                return *index;
+            }
+        }
+
+        let index = rows.ids.iter().position(|row| row == id);
+        *found = Some((Arc::clone(rows), index));
+        index
+    }
 }
 
 impl Default for LogViewState {
     fn default() -> Self {
-        let mut table_state = ListState::default();
-        table_state.select(Some(0));
-        
         Self {
-            table_state,
             filter: String::new(),
             event_type_filter: None,
             show_details: false,
-            selected_log_id: None,
+            selected: RowAnchor::default(),
+            follow: true,
+            last_seen: RowAnchor::default(),
+            group_by: None,
+            group_by_chooser: None,
+            collapse_duplicates: false,
+            expanded: HashSet::new(),
+            drawn: Mutex::new(None),
+        }
+    }
+}
+
+impl LogViewState {
+    /// Start with tail following on or off
+    pub fn new(follow: bool) -> Self {
+        Self {
+            follow,
+            ..Self::default()
+        }
+    }
+    
+    /// Index of the selected row in `rows` (chronological order)
+    pub fn selected_index(&self, rows: &Arc<LogRows>) -> Option<usize> {
+        if rows.is_empty() {
+            return None;
+        }
+        
+        if self.follow {
+            return Some(rows.len() - 1);
+        }
+        
+        // The selected entry may have been pruned since
+        Some(self.selected.locate(rows).unwrap_or(0))
+    }
+    
+    /// Entries that arrived since the view stopped following the tail
+    pub fn new_below(&self, rows: &Arc<LogRows>) -> usize {
+        if self.follow || rows.is_empty() {
+            return 0;
+        }
+        
+        let seen = self.last_seen.locate(rows)
+            .or_else(|| self.selected_index(rows))
+            .unwrap_or(0);
+        
+        rows.len() - 1 - seen
+    }
+    
+    /// Move the selection by `delta` rows; reaching the newest entry resumes following
+    pub fn move_selection(&mut self, rows: &Arc<LogRows>, delta: isize) {
+        let current = match self.selected_index(rows) {
+            Some(index) => index as isize,
+            None => return,
+        };
+        
+        let index = (current + delta).max(0).min(rows.len() as isize - 1) as usize;
+        self.select(rows, index);
+    }
+    
+    /// Select the row at `index`
+    pub fn select(&mut self, rows: &Arc<LogRows>, index: usize) {
+        if index + 1 >= rows.len() {
+            self.follow_tail();
+            return;
+        }
+        
+        if self.follow {
+            self.last_seen.set(rows, rows.len() - 1);
+            self.follow = false;
+        }
+        
+        self.selected.set(rows, index);
+    }
+    
+    /// Jump to the newest entry and keep following it
+    pub fn follow_tail(&mut self) {
+        self.follow = true;
+        self.selected.clear();
+        self.last_seen.clear();
+    }
+    
+    /// Expand the run the selected row belongs to, or collapse it again
+    ///
+    /// The selection moves to the run's first entry, the row it collapses to.
+    fn toggle_expanded(&mut self, rows: &Arc<LogRows>, store: &StoreSnapshot, fields: &[String]) {
+        let selected = match self.selected_index(rows) {
+            Some(index) => index,
+            None => return,
+        };
+        let entry = |index: usize| store.get_log_entry(&rows.ids[index]);
+        let same = |index: usize| match (entry(index), entry(selected)) {
+            (Some(a), Some(b)) => same_entry(a, b, fields),
+            _ => false,
+        };
+        let mut first = selected;
+        while first > 0 && same(first - 1) {
+            first -= 1;
+        }
+        let run = (first..rows.len()).take_while(|index| same(*index)).count();
+        if run < 2 {
+            return;
+        }
+        
+        let id = rows.ids[first].clone();
+        if !self.expanded.remove(&id) {
+            self.expanded.insert(id.clone());
+        }
+        if !self.follow {
+            self.selected.set_id(id);
         }
     }
 }
@@ -58,6 +256,22 @@ pub fn render_logs(f: &mut Frame, app: &App, area: Rect) {
     // Render filter bar
     render_filter_bar(f, app, chunks[0]);
     
+    // Diagnostic view replaces the log list while open
+    if app.show_parse_errors {
+        render_parse_failures(f, app, chunks[1]);
+        return;
+    }
+    
+    if let Some(cursor) = app.log_view.group_by_chooser {
+        render_group_by_chooser(f, cursor, area);
+    }
+    
+    // Group-by table replaces the log list while open
+    if let Some(group_by) = &app.log_view.group_by {
+        render_group_by(f, app, group_by, chunks[1]);
+        return;
+    }
+    
     // Create main area layout
     let main_chunks = if app.selected_log_entry_id.is_some() {
         // Split view for log list and details
@@ -70,7 +284,7 @@ pub fn render_logs(f: &mut Frame, app: &App, area: Rect) {
             .split(chunks[1])
     } else {
         // Full width for log list
-        vec![chunks[1]]
+        vec![chunks[1]].into()
     };
     
     // Render log list
@@ -83,15 +297,13 @@ pub fn render_logs(f: &mut Frame, app: &App, area: Rect) {
 }
 
 /// Render the filter bar at the top
-fn render_filter_bar(f: &mut Frame, app: &App, area: Rect) {
+fn render_filter_bar(f: &mut Frame, _app: &App, area: Rect) {
     let block = Block::default()
         .title("Filters")
         .borders(Borders::ALL);
     
     // Create event type filters
-    let event_types = vec![
-        "All", "Login", "Command", "Connect", "File", "Other"
-    ];
+    let event_types = ["All", "Login", "Command", "Connect", "File", "Other"];
     
     let tabs = Tabs::new(event_types.iter().map(|t| Span::raw(*t)).collect())
         .block(block)
@@ -104,99 +316,213 @@ fn render_filter_bar(f: &mut Frame, app: &App, area: Rect) {
 
 /// Render the list of log entries
 fn render_log_list(f: &mut Frame, app: &App, area: Rect) {
-    let store_guard = match app.store.try_read() {
-        Ok(guard) => guard,
-        Err(_) => return,
-    };
+    let store = app.store.snapshot();
     
-    // Get log entries
-    let logs = store_guard.get_log_entries();
+    // Get log entries, oldest first so the tail is at the bottom
+    let logs = log_rows(app, &store);
+    let collapsed_entries = logs.repeats.values().filter(|repeat| !repeat.expanded).map(|repeat| repeat.count - 1).sum::<usize>();
+    *app.log_view.drawn.lock() = Some(Arc::clone(&logs));
+    let selected = app.log_view.selected_index(&logs);
+    let new_below = app.log_view.new_below(&logs);
     
-    // Create header row
-    let header_cells = ["Time", "Event", "Session", "Source IP", "Username", "Details"]
-        .iter()
-        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
-    let header = Row::new(header_cells).height(1).bottom_margin(1);
+    // The intel column is only worth its width when there is intel to show
+    let show_intel = app.show_intel_column && app.session_manager.analyzer().has_threat_intel();
     
-    // Create data rows
-    let rows = logs.iter().rev().map(|log| {
-        // Format timestamp
-        let timestamp = log.timestamp.with_timezone(&Local)
-            .format("%H:%M:%S").to_string();
-        
-        // Get event-specific details
-        let details = match log.event_type {
-            EventType::Command => log.command.clone().unwrap_or_default(),
-            EventType::LoginAttempt | EventType::LoginSuccess | EventType::LoginFailed => {
-                format!("{}:{}", 
-                        log.username.clone().unwrap_or_default(),
-                        log.password.clone().unwrap_or_default())
-            },
-            EventType::FileUpload => {
-                if let Some(file) = &log.file {
-                    format!("Upload: {}", file.filename)
-                } else {
-                    "File upload".to_string()
-                }
-            },
-            EventType::FileDownload => {
-                if let Some(file) = &log.file {
-                    format!("Download: {}", file.filename)
-                } else {
-                    "File download".to_string()
-                }
-            },
-            _ => String::new(),
-        };
-        
-        // Style based on event type
-        let style = match log.event_type {
-            EventType::LoginSuccess => Style::default().fg(Color::Green),
-            EventType::LoginFailed => Style::default().fg(Color::Red),
-            EventType::Command => Style::default().fg(Color::Blue),
-            EventType::FileUpload | EventType::FileDownload => Style::default().fg(Color::Yellow),
-            _ => Style::default(),
-        };
-        
-        let cells = [
-            Cell::from(timestamp),
-            Cell::from(format!("{}", log.event_type)),
-            Cell::from(log.session.clone().unwrap_or_default()),
-            Cell::from(log.src_ip.clone().unwrap_or_default()),
-            Cell::from(log.username.clone().unwrap_or_default()),
-            Cell::from(details),
-        ];
-        
-        Row::new(cells).style(style)
-    });
+    let spec = log_table();
+    let mut chosen = spec.chosen(&app.config.ui);
+    if !show_intel {
+        chosen.retain(|column| column.key != "intel");
+    }
+    // The repeat counts lead the row while collapsing, unless placed with 'o'
+    if !app.log_view.collapse_duplicates {
+        chosen.retain(|column| column.key != "repeats");
+    } else if !chosen.iter().any(|column| column.key == "repeats") {
+        if let Some(column) = spec.available.iter().find(|column| column.key == "repeats") {
+            chosen.insert(0, column);
+        }
+    }
+    let window = visible_window(logs.len(), selected, area);
+    let rows = logs.ids[window.clone()].iter()
+        .filter_map(|id| store.get_log_entry(id))
+        .map(|log| (log, log_style(&app.theme, &app.watchlist, log)));
+    
+    // Show whether new entries are followed or held below
+    let mut title = vec![Span::raw("Log Entries")];
+    if app.log_view.follow {
+        title.push(Span::styled(" [following]", Style::default().fg(Color::Green)));
+    } else {
+        title.push(Span::styled(
+            format!(" [paused: {} new below, G to follow]", new_below),
+            Style::default().fg(Color::Black).bg(Color::Yellow),
+        ));
+    }
+    if app.log_view.collapse_duplicates {
+        title.push(Span::styled(
+            format!(" [{} repeats collapsed, e: expand]", collapsed_entries),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    if let Some(search) = &app.filters.search {
+        title.push(Span::styled(format!(" [/{}]", escape_controls(search)), Style::default().fg(Color::Cyan)));
+    }
+    let block = Block::default().title(Line::from(title)).borders(Borders::ALL);
+    
+    if logs.is_empty() {
+        render_empty_state(f, app, block, "log entries", store.get_log_entry_count(), area);
+        return;
+    }
     
     // Create table
-    let table = Table::new(rows)
-        .header(header)
-        .block(Block::default().title("Log Entries").borders(Borders::ALL))
-        .widths(&[
-            Constraint::Length(8),
-            Constraint::Length(12),
-            Constraint::Length(10),
-            Constraint::Length(15),
-            Constraint::Length(15),
-            Constraint::Percentage(40),
-        ])
+    let layout = spec.layout(app, area, &chosen);
+    let table = spec.table(app, &layout, &chosen, rows)
+        .block(block)
         .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
     
-    f.render_widget(table, area);
+    // Only the window's rows were built, so the selection is counted from its start
+    let mut table_state = TableState::default();
+    table_state.select(selected.map(|selected| selected - window.start));
+    
+    f.render_stateful_widget(table, area, &mut table_state);
+}
+
+/// Columns of the logs tab's list
+pub fn log_table() -> TableColumns<LogEntry> {
+    TableColumns {
+        table: "logs",
+        available: log_columns(),
+        default: &["time", "event", "session", "src_ip", "username", "intel", "details"],
+    }
+}
+
+/// Row color by event type, with watched commands standing out
+fn log_style(theme: &Theme, watchlist: &CommandWatchlist, log: &LogEntry) -> Style {
+    if log.command.as_deref().map_or(false, |command| watchlist.matches(command)) {
+        return watch_style();
+    }
+    
+    theme.style_for_event(&log.event_type)
+}
+
+/// Event-specific summary for the details column
+fn log_details(anon: &Anonymizer, log: &LogEntry) -> String {
+    match log.event_type {
+        EventType::Command => anon.text(log.command.as_deref().unwrap_or_default()).into_owned(),
+        EventType::LoginAttempt | EventType::LoginSuccess | EventType::LoginFailed => {
+            format!("{}:{}", 
+                    anon.credential(log.username.as_deref().unwrap_or_default()),
+                    anon.password(log.password.as_deref().unwrap_or_default()))
+        },
+        EventType::FileUpload => {
+            if let Some(file) = &log.file {
+                format!("Upload: {}", anon.file_name(&file.filename))
+            } else {
+                "File upload".to_string()
+            }
+        },
+        EventType::FileDownload => {
+            if let Some(file) = &log.file {
+                format!("Download: {}", anon.file_name(&file.filename))
+            } else {
+                "File download".to_string()
+            }
+        },
+        _ => String::new(),
+    }
+}
+
+/// Every column the log list can show
+fn log_columns() -> Vec<ColumnDef<LogEntry>> {
+    vec![
+        ColumnDef {
+            key: "time",
+            title: "Time",
+            width: |app, _| Constraint::Length(app.clock.column_width() as u16),
+            cell: |app, _, log| CellContent::Text(app.clock.when(&log.timestamp)),
+        },
+        ColumnDef {
+            key: "event",
+            title: "Event",
+            width: |_, bucket| Constraint::Length(bucket.pick(10, 12, 14)),
+            cell: |_, _, log| CellContent::Text(log.event_type.to_string()),
+        },
+        ColumnDef {
+            key: "eventid",
+            title: "Event ID",
+            width: |_, bucket| Constraint::Length(bucket.pick(16, 22, 28)),
+            cell: |_, _, log| CellContent::Text(log.eventid.clone()),
+        },
+        ColumnDef {
+            key: "session",
+            title: "Session",
+            width: |_, bucket| Constraint::Length(bucket.pick(8, 12, 36)),
+            cell: |_, _, log| CellContent::Text(log.session.clone().unwrap_or_default()),
+        },
+        ColumnDef {
+            key: "src_ip",
+            title: "Source IP",
+            width: |_, bucket| Constraint::Length(bucket.pick(15, 15, 39)),
+            cell: |_, anon, log| CellContent::Text(anon.ip(log.src_ip.as_deref().unwrap_or_default()).into_owned()),
+        },
+        ColumnDef {
+            key: "src_port",
+            title: "Src Port",
+            width: |_, _| Constraint::Length(8),
+            cell: |_, _, log| CellContent::Text(log.src_port.map_or_else(String::new, |port| port.to_string())),
+        },
+        ColumnDef {
+            key: "dst_port",
+            title: "Dst Port",
+            width: |_, _| Constraint::Length(8),
+            cell: |_, _, log| CellContent::Text(log.dst_port.map_or_else(String::new, |port| port.to_string())),
+        },
+        ColumnDef {
+            key: "username",
+            title: "Username",
+            width: |_, bucket| Constraint::Length(bucket.pick(10, 15, 20)),
+            cell: |_, anon, log| CellContent::Text(anon.credential(log.username.as_deref().unwrap_or_default()).into_owned()),
+        },
+        ColumnDef {
+            key: "intel",
+            title: "Intel",
+            width: |_, _| Constraint::Length(16),
+            cell: |app, _, log| {
+                let intel = log.src_ip.as_deref()
+                    .and_then(|ip| app.session_manager.analyzer().get_threat_intel(ip));
+                match intel {
+                    Some(intel) => CellContent::Styled(
+                        format!("{} ({})", intel.labels.first().map_or("listed", |label| label.as_str()), intel.score),
+                        Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD),
+                    ),
+                    None => CellContent::Text(String::new()),
+                }
+            },
+        },
+        ColumnDef {
+            key: "repeats",
+            title: "×",
+            width: |_, _| Constraint::Length(6),
+            cell: |app, _, log| match app.log_view.drawn.lock().as_ref().and_then(|rows| rows.repeats.get(&log.id).copied()) {
+                Some(repeat) if repeat.expanded => CellContent::Text(format!("▾{}", repeat.count)),
+                Some(repeat) => CellContent::Styled(format!("×{}", repeat.count), Style::default().fg(Color::Cyan)),
+                None => CellContent::Text(String::new()),
+            },
+        },
+        ColumnDef {
+            key: "details",
+            title: "Details",
+            width: |_, _| Constraint::Min(0),
+            cell: |_, anon, log| CellContent::Text(log_details(anon, log)),
+        },
+    ]
 }
 
 /// Render the details of a selected log entry
 fn render_log_details(f: &mut Frame, app: &App, area: Rect) {
-    let store_guard = match app.store.try_read() {
-        Ok(guard) => guard,
-        Err(_) => return,
-    };
+    let store = app.store.snapshot();
     
     // Get selected log entry
     let log_entry = match &app.selected_log_entry_id {
-        Some(id) => store_guard.get_log_entry(id),
+        Some(id) => store.get_log_entry(id),
         None => None,
     };
     
@@ -206,6 +532,7 @@ fn render_log_details(f: &mut Frame, app: &App, area: Rect) {
     };
     
     // Create detail lines
+    let anon = Anonymizer::for_app(app);
     let mut lines = Vec::new();
     
     // Add basic information
@@ -216,19 +543,18 @@ fn render_log_details(f: &mut Frame, app: &App, area: Rect) {
     
     lines.push(Line::from(vec![
         Span::styled("Timestamp: ", Style::default().fg(Color::Yellow)),
-        Span::raw(format!("{}", log_entry.timestamp.with_timezone(&Local))),
+        Span::raw(app.clock.date_time(&log_entry.timestamp)),
     ]));
     
-    lines.push(Line::from(vec![
-        Span::styled("Session: ", Style::default().fg(Color::Yellow)),
-        Span::raw(log_entry.session.clone().unwrap_or_default()),
-    ]));
+    // Values the search looks at have its matches highlighted
+    let mut session_line = vec![Span::styled("Session: ", Style::default().fg(Color::Yellow))];
+    session_line.extend(highlight_search(&app.filters, log_entry.session.clone().unwrap_or_default()));
+    lines.push(Line::from(session_line));
     
     if let Some(src_ip) = &log_entry.src_ip {
-        lines.push(Line::from(vec![
-            Span::styled("Source IP: ", Style::default().fg(Color::Yellow)),
-            Span::raw(src_ip),
-        ]));
+        let mut line = vec![Span::styled("Source IP: ", Style::default().fg(Color::Yellow))];
+        line.extend(highlight_search(&app.filters, anon.ip(src_ip).into_owned()));
+        lines.push(Line::from(line));
     }
     
     if let Some(src_port) = log_entry.src_port {
@@ -241,7 +567,7 @@ fn render_log_details(f: &mut Frame, app: &App, area: Rect) {
     if let Some(dst_ip) = &log_entry.dst_ip {
         lines.push(Line::from(vec![
             Span::styled("Destination IP: ", Style::default().fg(Color::Yellow)),
-            Span::raw(dst_ip),
+            Span::raw(anon.ip(dst_ip)),
         ]));
     }
     
@@ -253,36 +579,33 @@ fn render_log_details(f: &mut Frame, app: &App, area: Rect) {
     }
     
     if let Some(username) = &log_entry.username {
-        lines.push(Line::from(vec![
-            Span::styled("Username: ", Style::default().fg(Color::Yellow)),
-            Span::raw(username),
-        ]));
+        let mut line = vec![Span::styled("Username: ", Style::default().fg(Color::Yellow))];
+        line.extend(highlight_search(&app.filters, escape_controls(&anon.credential(username)).into_owned()));
+        lines.push(Line::from(line));
     }
     
     if let Some(password) = &log_entry.password {
         lines.push(Line::from(vec![
             Span::styled("Password: ", Style::default().fg(Color::Yellow)),
-            Span::raw(password),
+            Span::raw(escape_controls(&anon.password(password)).into_owned()),
         ]));
     }
     
     if let Some(command) = &log_entry.command {
-        lines.push(Line::from(vec![
-            Span::styled("Command: ", Style::default().fg(Color::Yellow)),
-            Span::raw(command),
-        ]));
+        let mut line = vec![Span::styled("Command: ", Style::default().fg(Color::Yellow))];
+        line.extend(highlight_command(&app.watchlist, &app.filters, escape_controls(&anon.text(command)).into_owned()));
+        lines.push(Line::from(line));
     }
     
     if let Some(file) = &log_entry.file {
-        lines.push(Line::from(vec![
-            Span::styled("File: ", Style::default().fg(Color::Yellow)),
-            Span::raw(&file.filename),
-        ]));
+        let mut line = vec![Span::styled("File: ", Style::default().fg(Color::Yellow))];
+        line.extend(highlight_search(&app.filters, escape_controls(&anon.file_name(&file.filename)).into_owned()));
+        lines.push(Line::from(line));
         
         if let Some(shasum) = &file.shasum {
             lines.push(Line::from(vec![
                 Span::styled("SHA256: ", Style::default().fg(Color::Yellow)),
-                Span::raw(shasum),
+                Span::raw(anon.file_hash(shasum)),
             ]));
         }
     }
@@ -293,18 +616,32 @@ fn render_log_details(f: &mut Frame, app: &App, area: Rect) {
     
     for (key, value) in &log_entry.fields {
         if let Some(value_str) = value.as_str() {
+            let value_str = anon.without_password(value_str, log_entry.password.as_deref());
             lines.push(Line::from(vec![
                 Span::styled(format!("{}: ", key), Style::default().fg(Color::Cyan)),
-                Span::raw(value_str),
+                Span::raw(escape_controls(&anon.field(key, &value_str)).into_owned()),
             ]));
         } else {
             lines.push(Line::from(vec![
                 Span::styled(format!("{}: ", key), Style::default().fg(Color::Cyan)),
-                Span::raw(format!("{}", value)),
+                Span::raw(anon.text(&value.to_string()).into_owned()),
             ]));
         }
     }
     
+    // Add the original line, unless the store dropped it
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("Raw:", Style::default().fg(Color::Yellow))));
+    
+    if log_entry.raw.is_null() {
+        lines.push(Line::from(Span::styled(
+            "raw not retained (filter.keep_raw = false)",
+            Style::default().fg(Color::DarkGray),
+        )));
+    } else {
+        lines.push(Line::from(anon.raw(&log_entry.raw)));
+    }
+    
     // Create paragraph
     let paragraph = Paragraph::new(lines)
         .block(Block::default().title("Log Details").borders(Borders::ALL))
@@ -313,23 +650,294 @@ fn render_log_details(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(paragraph, area);
 }
 
-/// Handle input in the logs view
-pub async fn handle_logs_input(key: crossterm::event::KeyEvent, app: &mut App) -> Result<()> {
-    match key.code {
-        crossterm::event::KeyCode::Down => {
-            // TODO: Navigate logs
+/// Render recent lines that failed to parse, with per-source counters
+fn render_parse_failures(f: &mut Frame, app: &App, area: Rect) {
+    let store = app.store.snapshot();
+    
+    let anon = Anonymizer::for_app(app);
+    let mut lines = Vec::new();
+    
+    // Per-source counters
+    let mut sources = store.get_parse_stats().iter().collect::<Vec<_>>();
+    sources.sort_by(|a, b| a.0.cmp(b.0));
+    
+    for (source, stats) in sources {
+        let style = if stats.failure_rate() * 100.0 > app.config.ui.parse_error_warn_percent {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default()
+        };
+        
+        lines.push(Line::from(vec![
+            Span::styled(format!("{}: ", source), Style::default().fg(Color::Yellow)),
+            Span::styled(
+                format!(
+                    "{} parsed, {} failed ({:.1}%)",
+                    stats.parsed,
+                    stats.failed,
+                    stats.failure_rate() * 100.0
+                ),
+                style,
+            ),
+        ]));
+    }
+    
+    // Eventids without a mapping, most frequent first
+    let mut unknown = store.get_unknown_eventids().iter().collect::<Vec<_>>();
+    unknown.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    
+    if !unknown.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            "Unmapped eventids (add to [honeypot.event_mapping]):",
+            Style::default().fg(Color::Yellow),
+        )));
+        
+        for (eventid, count) in unknown {
+            lines.push(Line::from(format!("  {} x{}", eventid, count)));
         }
-        crossterm::event::KeyCode::Up => {
-            // TODO: Navigate logs
+    }
+    
+    lines.push(Line::from(""));
+    
+    // Most recent failures first
+    let failures = store.get_parse_failures();
+    if failures.is_empty() {
+        lines.push(Line::from("No parse failures recorded"));
+    }
+    
+    for failure in failures {
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{} ", app.clock.when(&failure.timestamp)),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(format!("[{}] ", failure.source), Style::default().fg(Color::Cyan)),
+            Span::styled(failure.error.clone(), Style::default().fg(Color::Red)),
+        ]));
+        lines.push(Line::from(Span::raw(format!("  {}", escape_controls(&anon.text(&failure.line))))));
+    }
+    
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().title("Diagnostics (! or Esc to close)").borders(Borders::ALL))
+        .wrap(ratatui::widgets::Wrap { trim: false });
+    
+    f.render_widget(paragraph, area);
+}
+
+/// Handle an action in the logs view
+pub async fn handle_logs_input(action: Action, app: &mut App) -> Result<()> {
+    let store = app.store.snapshot();
+    
+    // The group-by table takes the navigation keys while open
+    let group_rows = app.log_view.group_by.as_ref().map(|group_by| group_by.visible_rows(app, &store).0.len());
+    if let (Some(len), Some(group_by)) = (group_rows, app.log_view.group_by.as_mut()) {
+        match action {
+            Action::MoveDown => group_by.move_cursor(1, len),
+            Action::MoveUp => group_by.move_cursor(-1, len),
+            Action::PageDown => group_by.move_cursor(PAGE_SIZE as isize, len),
+            Action::PageUp => group_by.move_cursor(-(PAGE_SIZE as isize), len),
+            Action::Top => group_by.cursor = 0,
+            Action::Bottom => group_by.cursor = len.saturating_sub(1),
+            Action::CycleGroupSort => group_by.cycle_sort(),
+            Action::GroupBy => app.log_view.group_by_chooser = Some(0),
+            Action::Close => app.log_view.group_by = None,
+            _ => {}
+        }
+        return Ok(());
+    }
+    
+    let logs = log_rows(app, &store);
+    
+    match action {
+        Action::GroupBy => app.log_view.group_by_chooser = Some(0),
+        Action::MoveDown => app.log_view.move_selection(&logs, 1),
+        Action::MoveUp => app.log_view.move_selection(&logs, -1),
+        Action::PageDown => app.log_view.move_selection(&logs, PAGE_SIZE as isize),
+        Action::PageUp => app.log_view.move_selection(&logs, -(PAGE_SIZE as isize)),
+        Action::Top => app.log_view.select(&logs, 0),
+        Action::Bottom => app.log_view.follow_tail(),
+        Action::Open => {
+            app.selected_log_entry_id = app.log_view.selected_index(&logs)
+                .map(|index| logs.ids[index].clone());
+        }
+        Action::Close => {
+            app.selected_log_entry_id = None;
+            app.show_parse_errors = false;
         }
-        crossterm::event::KeyCode::Enter => {
-            // TODO: Show details for selected log
+        Action::ToggleParseErrors => app.show_parse_errors = !app.show_parse_errors,
+        Action::ToggleIntel => app.show_intel_column = !app.show_intel_column,
+        Action::ToggleDuplicates => {
+            app.log_view.collapse_duplicates = !app.log_view.collapse_duplicates;
+            app.log_view.expanded.clear();
         }
-        crossterm::event::KeyCode::Esc => {
-            // TODO: Close details view
+        Action::ExpandRepeats if app.log_view.collapse_duplicates => {
+            app.log_view.toggle_expanded(&logs, &store, &app.config.ui.duplicate_fields);
+        }
+        Action::LookupRdap => {
+            // The entry in the details pane, else the selected row
+            let entry = app.selected_log_entry_id.as_ref()
+                .or_else(|| app.log_view.selected_index(&logs).map(|index| &logs.ids[index]))
+                .and_then(|id| store.get_log_entry(id));
+            match entry.and_then(|entry| entry.src_ip.clone()) {
+                Some(ip) => app.lookup_rdap(&ip),
+                None => log::info!("The selected entry has no source IP to look up"),
+            }
         }
         _ => {}
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Log entries shown in the list, oldest first, narrowed by the search
+pub fn visible_logs<'a>(app: &App, store: &'a StoreSnapshot) -> Vec<&'a LogEntry> {
+    store.get_log_entries()
+        .into_iter()
+        .filter(|log| {
+            let fields = [
+                log.eventid.as_str(),
+                log.session.as_deref().unwrap_or_default(),
+                log.src_ip.as_deref().unwrap_or_default(),
+                log.username.as_deref().unwrap_or_default(),
+                log.command.as_deref().unwrap_or_default(),
+                log.file.as_ref().map_or("", |file| file.filename.as_str()),
+            ];
+            app.filters.matches_search(fields.iter().copied())
+        })
+        .collect()
+}
+/// What the log list is narrowed from: the snapshot, the search, and how runs are collapsed
+pub struct LogListKey {
+    /// Snapshot listed; held so it can't be freed and its address reused
+    snapshot: Arc<StoreSnapshot>,
+    /// Search as typed, and whether it is case-sensitive
+    search: Option<(String, bool)>,
+    /// While collapsing, the fields runs agree on and the runs shown whole
+    collapse: Option<(Vec<String>, HashSet<String>)>,
+}
+
+impl LogListKey {
+    /// Key for the list `app` shows of `snapshot`
+    pub fn new(app: &App, snapshot: &Arc<StoreSnapshot>) -> Self {
+        Self {
+            snapshot: Arc::clone(snapshot),
+            search: app.filters.search.clone().map(|search| (search, app.filters.search_case_sensitive)),
+            collapse: app.log_view.collapse_duplicates
+                .then(|| (app.config.ui.duplicate_fields.clone(), app.log_view.expanded.clone())),
+        }
+    }
+}
+
+impl PartialEq for LogListKey {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.snapshot, &other.snapshot) && self.search == other.search && self.collapse == other.collapse
+    }
+}
+
+/// Rows of the log list, kept until a snapshot is published or the search or collapsing changes
+fn log_rows(app: &App, store: &Arc<StoreSnapshot>) -> Arc<LogRows> {
+    app.panel_caches.log_rows.get_or_compute(LogListKey::new(app, store), || collect_log_rows(app, store))
+}
+
+/// The visible entries, with runs of identical ones collapsed to their first
+/// entry while `collapse_duplicates` is on
+fn collect_log_rows(app: &App, store: &StoreSnapshot) -> LogRows {
+    let logs = visible_logs(app, store);
+    if !app.log_view.collapse_duplicates {
+        return LogRows { ids: logs.iter().map(|entry| entry.id.clone()).collect(), repeats: HashMap::new() };
+    }
+    
+    let fields = &app.config.ui.duplicate_fields;
+    let mut ids = Vec::with_capacity(logs.len());
+    let mut repeats = HashMap::new();
+    let mut start = 0;
+    while start < logs.len() {
+        let first = logs[start];
+        let count = logs[start..].iter().take_while(|entry| same_entry(entry, first, fields)).count();
+        if count == 1 {
+            ids.push(first.id.clone());
+        } else {
+            let expanded = app.log_view.expanded.contains(&first.id);
+            repeats.insert(first.id.clone(), Repeat { count, expanded });
+            if expanded {
+                ids.extend(logs[start..start + count].iter().map(|entry| entry.id.clone()));
+            } else {
+                ids.push(first.id.clone());
+            }
+        }
+        start += count;
+    }
+    
+    LogRows { ids, repeats }
+}
+
+/// Whether two entries agree on every field in `ui.duplicate_fields`
+fn same_entry(a: &LogEntry, b: &LogEntry, fields: &[String]) -> bool {
+    fields.iter().all(|field| match field.as_str() {
+        "event" => a.event_type == b.event_type,
+        "eventid" => a.eventid == b.eventid,
+        "session" => a.session == b.session,
+        "src_ip" => a.src_ip == b.src_ip,
+        "dst_ip" => a.dst_ip == b.dst_ip,
+        "dst_port" => a.dst_port == b.dst_port,
+        "username" => a.username == b.username,
+        "password" => a.password == b.password,
+        "command" => a.command == b.command,
+        "file" => a.file.as_ref().map(|file| &file.filename) == b.file.as_ref().map(|file| &file.filename),
+        _ => true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::testing;
+    use pretty_assertions::assert_eq;
+
+    fn rows(ids: &[&str]) -> Arc<LogRows> {
+        Arc::new(LogRows { ids: ids.iter().map(|id| id.to_string()).collect(), repeats: HashMap::new() })
+    }
+
+    #[test]
+    fn a_paused_selection_stays_on_its_entry_as_the_list_changes() {
+        let mut view = LogViewState::new(true);
+        let before = rows(&["a", "b", "c", "d"]);
+        assert_eq!(view.selected_index(&before), Some(3));
+
+        view.move_selection(&before, -2);
+        assert!(!view.follow);
+        assert_eq!(view.selected_index(&before), Some(1));
+
+        // "a" pruned, two new at the tail
+        let after = rows(&["b", "c", "d", "e", "f"]);
+        assert_eq!(view.selected_index(&after), Some(0));
+        assert_eq!(view.new_below(&after), 2);
+
+        // Once "b" is pruned too the top row is taken
+        assert_eq!(view.selected_index(&rows(&["c", "d"])), Some(0));
+
+        view.move_selection(&after, 10);
+        assert!(view.follow);
+        assert_eq!(view.selected_index(&after), Some(4));
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture logs_tab_draw_time`
+    #[tokio::test]
+    #[ignore]
+    async fn logs_tab_draw_time_does_not_grow_with_the_store() {
+        let mut per_frame = Vec::new();
+        for count in [1_000, 50_000] {
+            let dir = tempfile::tempdir().unwrap();
+            let mut app = testing::app_with_sessions(dir.path(), count).await;
+            app.selected_tab = 2;
+            // Paused in the middle, so the selection has to be found
+            let rows = log_rows(&app, &app.store.snapshot());
+            app.log_view.select(&rows, count / 2);
+            per_frame.push(testing::frame_time(&app, 200));
+        }
+
+        println!("logs tab: {:?} per frame with 1000 entries, {:?} with 50000", per_frame[0], per_frame[1]);
+        assert!(per_frame[1] < per_frame[0] * 3, "drawing 50k entries should cost about what 1k do");
+    }
+}
@@ -1,3 +1,13 @@
+mod anonymize;
+pub mod alerts;
+pub mod sanitize;
+mod search;
+pub mod watchlist;
+pub mod columns;
+pub mod log_viewer;
+mod health;
+mod rdap;
+pub mod rule_tester;
 mod components;
 mod dashboard;
 mod logs;
@@ -6,26 +16,43 @@ mod settings;
 mod help;
 mod security;
 mod geography;
+mod group_by;
+pub mod panels;
+pub mod keys;
+pub mod theme;
+pub mod wizard;
+#[cfg(test)]
+mod testing;
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
-    backend::{Backend, CrosstermBackend},
+    backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::Span,
     widgets::{Block, Borders, Tabs},
     Frame, Terminal,
 };
+use chrono::Utc;
 use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
-use crate::app::{App, AppEvent, AppState};
+use crate::app::{compile_search, App, AppEvent, AppState, REGEX_SEARCH_PREFIX};
+use crate::core::export::ExportScope;
+use crate::utils::logger;
+use crate::utils::time::humanize_ago;
+use keys::{Action, Step};
+use panels::PanelFocus;
+use rule_tester::RuleTester;
+use wizard::WizardOutcome;
 
 // Re-export for easy access
 pub use components::*;
@@ -36,6 +63,7 @@ pub use settings::*;
 pub use help::*;
 pub use security::*;
 pub use geography::*;
+pub use group_by::*;
 
 /// Starts the UI event loop
 pub async fn start_ui(mut app: App) -> Result<()> {
@@ -43,33 +71,36 @@ pub async fn start_ui(mut app: App) -> Result<()> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    logger::set_tui_active(true);
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // Create UI event channel
     let (ui_tx, mut ui_rx) = mpsc::channel(100);
     
-    // Spawn input handling thread
+    // Spawn input handling thread; it checks `stop_input` at least every tick
     let ui_tx_clone = ui_tx.clone();
-    std::thread::spawn(move || {
+    let stop_input = Arc::new(AtomicBool::new(false));
+    let stop_flag = stop_input.clone();
+    let input_thread = std::thread::spawn(move || {
         let tick_rate = Duration::from_millis(200);
         let mut last_tick = Instant::now();
         
-        loop {
+        while !stop_flag.load(Ordering::Relaxed) {
             let timeout = tick_rate
                 .checked_sub(last_tick.elapsed())
                 .unwrap_or_else(|| Duration::from_secs(0));
             
             if event::poll(timeout).unwrap() {
                 if let Ok(event) = event::read() {
-                    if let Err(_) = ui_tx_clone.blocking_send(UIEvent::Input(event)) {
+                    if ui_tx_clone.blocking_send(UIEvent::Input(event)).is_err() {
                         break;
                     }
                 }
             }
             
             if last_tick.elapsed() >= tick_rate {
-                if let Err(_) = ui_tx_clone.blocking_send(UIEvent::Tick) {
+                if ui_tx_clone.blocking_send(UIEvent::Tick).is_err() {
                     break;
                 }
                 last_tick = Instant::now();
@@ -77,42 +108,62 @@ pub async fn start_ui(mut app: App) -> Result<()> {
         }
     });
 
+    // Subscribe to application events before connecting so early status changes aren't missed
+    let mut app_events = app.event_tx.subscribe();
+    
     // Start connecting to honeypot
     app.connect().await?;
     
-    // Subscribe to application events
-    let mut app_events = app.event_tx.subscribe();
-    
     // Spawn app event handling task
     let ui_tx_clone = ui_tx.clone();
-    tokio::spawn(async move {
+    let forwarder = tokio::spawn(async move {
         while let Ok(event) = app_events.recv().await {
-            if let Err(_) = ui_tx_clone.send(UIEvent::AppEvent(event)).await {
+            if ui_tx_clone.send(UIEvent::AppEvent(event)).await.is_err() {
                 break;
             }
         }
     });
 
     // Main event loop
+    app.drawn_at = Utc::now();
     terminal.draw(|f| ui(f, &app))?;
 
     while app.state != AppState::ShuttingDown {
-        match ui_rx.recv().await {
+        // In manual refresh mode only input (keys, resizes) redraws the view
+        let redraw = match ui_rx.recv().await {
             Some(UIEvent::Input(event)) => {
                 if !handle_input(event, &mut app).await? {
                     break;
                 }
+                true
             }
             Some(UIEvent::Tick) => {
                 app.update()?;
+                !app.manual_refresh
             }
             Some(UIEvent::AppEvent(event)) => {
                 handle_app_event(event, &mut app).await?;
+                !app.manual_refresh
             }
             None => break,
-        }
+        };
         
-        terminal.draw(|f| ui(f, &app))?;
+        if redraw {
+            app.drawn_at = Utc::now();
+            terminal.draw(|f| ui(f, &app))?;
+        }
+    }
+
+    // Stop the input thread and the event forwarder; dropping the receiver
+    // unblocks a thread waiting on a full channel
+    if app.state != AppState::ShuttingDown {
+        app.quit().await?;
+    }
+    forwarder.abort();
+    stop_input.store(true, Ordering::Relaxed);
+    drop(ui_rx);
+    if input_thread.join().is_err() {
+        log::warn!("Input thread panicked");
     }
 
     // Clean up
@@ -123,6 +174,7 @@ pub async fn start_ui(mut app: App) -> Result<()> {
         DisableMouseCapture
     )?;
     terminal.show_cursor()?;
+    logger::set_tui_active(false);
     
     Ok(())
 }
@@ -141,49 +193,138 @@ async fn handle_input(event: Event, app: &mut App) -> Result<bool> {
             return Ok(true);
         }
 
-        match key.code {
-            KeyCode::Char('q') => {
-                app.quit().await?;
-                return Ok(false);
-            }
-            KeyCode::Char('c') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
-                app.quit().await?;
-                return Ok(false);
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(event::KeyModifiers::CONTROL) {
+            app.quit().await?;
+            return Ok(false);
+        }
+
+        // The setup wizard takes every key until it is saved or closed
+        if app.wizard.is_some() {
+            handle_wizard_input(key, app).await?;
+            return Ok(true);
+        }
+
+        // The column chooser takes every key until it is closed
+        if app.column_chooser.is_some() {
+            handle_column_chooser_input(key, app)?;
+            return Ok(true);
+        }
+
+        // So does the group-by chooser
+        if app.log_view.group_by_chooser.is_some() {
+            handle_group_by_chooser_input(key, app);
+            return Ok(true);
+        }
+
+        // The log viewer takes every key until it is closed
+        if let Some(viewer) = app.log_viewer.as_mut() {
+            let last = logger::recent_lines().len();
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') => viewer.scroll = (viewer.scroll + 1).min(last),
+                KeyCode::Down | KeyCode::Char('j') => viewer.scroll = viewer.scroll.saturating_sub(1),
+                KeyCode::PageUp => viewer.scroll = (viewer.scroll + keys::PAGE_SIZE).min(last),
+                KeyCode::PageDown => viewer.scroll = viewer.scroll.saturating_sub(keys::PAGE_SIZE),
+                KeyCode::Home => viewer.scroll = last,
+                KeyCode::End => viewer.scroll = 0,
+                KeyCode::Esc | KeyCode::F(12) | KeyCode::Char('q') => app.log_viewer = None,
+                _ => {}
             }
-            KeyCode::Tab => {
-                app.selected_tab = (app.selected_tab + 1) % 6;
+            return Ok(true);
+        }
+
+        // The health overlay closes on any key, F10 included
+        if app.show_health {
+            app.show_health = false;
+            return Ok(true);
+        }
+
+        // So does the registration popup
+        if app.rdap_popup.is_some() {
+            app.rdap_popup = None;
+            return Ok(true);
+        }
+
+        // The alerts view takes every key until it is closed
+        if app.alerts_view.is_some() {
+            handle_alerts_view_input(key, app);
+            return Ok(true);
+        }
+
+        // As does the rule tester
+        if let Some(tester) = app.rule_tester.as_mut() {
+            match key.code {
+                KeyCode::Char(c) => tester.input.push(c),
+                KeyCode::Backspace => {
+                    tester.input.pop();
+                }
+                KeyCode::Enter | KeyCode::Esc => app.rule_tester = None,
+                _ => {}
             }
-            KeyCode::BackTab => {
-                app.selected_tab = (app.selected_tab + 5) % 6;
+            if let Some(tester) = app.rule_tester.as_mut() {
+                tester.update();
             }
-            KeyCode::Left => {
-                if app.selected_tab > 0 {
-                    app.selected_tab -= 1;
-                } else {
-                    app.selected_tab = 5;
+            return Ok(true);
+        }
+
+        // The export prompt waits for its answer
+        if app.export_prompt.is_some() {
+            let scope = match key.code {
+                KeyCode::Char('f') | KeyCode::Enter => Some(ExportScope::Filtered),
+                KeyCode::Char('a') => Some(ExportScope::All),
+                _ => None,
+            };
+            app.export_prompt = None;
+            if let Some(scope) = scope {
+                if let Err(e) = app.export(scope) {
+                    log::error!("{:#}", e);
                 }
             }
-            KeyCode::Right => {
-                app.selected_tab = (app.selected_tab + 1) % 6;
-            }
-            KeyCode::Char('1') => app.selected_tab = 0,
-            KeyCode::Char('2') => app.selected_tab = 1,
-            KeyCode::Char('3') => app.selected_tab = 2,
-            KeyCode::Char('4') => app.selected_tab = 3,
-            KeyCode::Char('5') => app.selected_tab = 4,
-            KeyCode::Char('6') => app.selected_tab = 5,
-            _ => {
-                // Handle tab-specific input
-                match app.selected_tab {
-                    0 => handle_dashboard_input(key, app).await?,
-                    1 => handle_security_input(key, app).await?,
-                    2 => handle_logs_input(key, app).await?,
-                    3 => handle_sessions_input(key, app).await?,
-                    4 => handle_geography_input(key, app).await?,
-                    5 => handle_settings_input(key, app).await?,
-                    _ => {}
+            return Ok(true);
+        }
+
+        // A search being typed takes every key until Enter or Esc
+        if let Some(input) = app.search_input.as_mut() {
+            let case_sensitive = app.config.filter.case_sensitive;
+            match key.code {
+                // Ctrl+R switches between substring and regex search
+                KeyCode::Char('r') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                    match input.strip_prefix(REGEX_SEARCH_PREFIX) {
+                        Some(pattern) => *input = pattern.to_string(),
+                        None => input.insert_str(0, REGEX_SEARCH_PREFIX),
+                    }
+                }
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
                 }
+                KeyCode::Enter => {
+                    let search = input.clone();
+                    // A pattern that doesn't compile stays in the input with its error
+                    if app.filters.set_search(search, case_sensitive).is_ok() {
+                        app.search_input = None;
+                        app.session_cursor = 0;
+                        app.selected_malware = 0;
+                    }
+                }
+                KeyCode::Esc => app.search_input = None,
+                _ => {}
             }
+
+            app.search_error = match &app.search_input {
+                Some(input) if !input.is_empty() => compile_search(input, case_sensitive).err(),
+                _ => None,
+            };
+            return Ok(true);
+        }
+
+        // Any key closes the help overlay
+        if app.show_help {
+            app.show_help = false;
+            return Ok(true);
+        }
+
+        if let Step::Action(action) = app.keys.feed(key.code, app.selected_tab) {
+            return handle_action(action, app).await;
         }
     } else if let Event::Mouse(_) = event {
         // Handle mouse events
@@ -192,12 +333,250 @@ async fn handle_input(event: Event, app: &mut App) -> Result<bool> {
     Ok(true)
 }
 
+/// Handle a key while the setup wizard is open
+async fn handle_wizard_input(key: event::KeyEvent, app: &mut App) -> Result<()> {
+    let outcome = match app.wizard.as_mut() {
+        Some(wizard) => wizard.handle_key(key),
+        None => return Ok(()),
+    };
+
+    match outcome {
+        WizardOutcome::Continue => {}
+        WizardOutcome::Cancelled => app.wizard = None,
+        WizardOutcome::Saved(config) => {
+            app.wizard = None;
+            app.apply_setup(*config).await?;
+            log::info!("Configuration saved; source, GeoIP and alert changes apply after a restart");
+        }
+    }
+
+    Ok(())
+}
+
+/// Handle a key while the column chooser is open
+fn handle_column_chooser_input(key: event::KeyEvent, app: &mut App) -> Result<()> {
+    let shift = key.modifiers.contains(event::KeyModifiers::SHIFT);
+    let chooser = match app.column_chooser.as_mut() {
+        Some(chooser) => chooser,
+        None => return Ok(()),
+    };
+
+    match key.code {
+        KeyCode::Up if shift => chooser.shift(-1),
+        KeyCode::Down if shift => chooser.shift(1),
+        KeyCode::Char('K') => chooser.shift(-1),
+        KeyCode::Char('J') => chooser.shift(1),
+        KeyCode::Up | KeyCode::Char('k') => chooser.move_cursor(-1),
+        KeyCode::Down | KeyCode::Char('j') => chooser.move_cursor(1),
+        KeyCode::Char(' ') => chooser.toggle(),
+        KeyCode::Char('r') => {
+            let table = chooser.table;
+            app.column_chooser = None;
+            app.set_columns(table, None)?;
+        }
+        KeyCode::Enter => {
+            let (table, columns) = (chooser.table, chooser.keys());
+            if columns.is_empty() {
+                log::warn!("Pick at least one column");
+                return Ok(());
+            }
+            app.column_chooser = None;
+            app.set_columns(table, Some(columns))?;
+        }
+        KeyCode::Esc => app.column_chooser = None,
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Handle a key while the alerts view is open
+fn handle_alerts_view_input(key: event::KeyEvent, app: &mut App) {
+    let mut history = app.alert_history.lock();
+    let visible = alerts::visible_alerts(history.alerts(), app.alert_min_severity);
+    let view = match app.alerts_view.as_mut() {
+        Some(view) => view,
+        None => return,
+    };
+    let last = visible.len().saturating_sub(1);
+    view.cursor = view.cursor.min(last);
+
+    match key.code {
+        KeyCode::Up | KeyCode::Char('k') => view.cursor = view.cursor.saturating_sub(1),
+        KeyCode::Down | KeyCode::Char('j') => view.cursor = (view.cursor + 1).min(last),
+        KeyCode::PageUp => view.cursor = view.cursor.saturating_sub(keys::PAGE_SIZE),
+        KeyCode::PageDown => view.cursor = (view.cursor + keys::PAGE_SIZE).min(last),
+        KeyCode::Home => view.cursor = 0,
+        KeyCode::End => view.cursor = last,
+        KeyCode::Char(' ') => {
+            if let Some(index) = visible.get(view.cursor) {
+                history.acknowledge(*index);
+            }
+        }
+        KeyCode::Char('c') => history.clear_acknowledged(),
+        KeyCode::Char('C') => history.clear_all(),
+        KeyCode::Char('f') => {
+            app.alert_min_severity = app.alert_min_severity.next();
+            view.cursor = 0;
+        }
+        KeyCode::Enter => {
+            let store = app.store.snapshot();
+            let session = visible.get(view.cursor)
+                .and_then(|index| alerts::alert_session(&store, &history.alerts()[*index]));
+            if let Some(session) = session {
+                app.selected_session_id = Some(session.id.clone());
+                app.selected_tab = 3;
+                app.alerts_view = None;
+            }
+        }
+        KeyCode::Esc | KeyCode::Char('q') => app.alerts_view = None,
+        _ => {}
+    }
+}
+
+/// Handle a key while the group-by chooser is open
+fn handle_group_by_chooser_input(key: event::KeyEvent, app: &mut App) {
+    let cursor = match app.log_view.group_by_chooser.as_mut() {
+        Some(cursor) => cursor,
+        None => return,
+    };
+
+    match key.code {
+        KeyCode::Up | KeyCode::Char('k') => *cursor = cursor.saturating_sub(1),
+        KeyCode::Down | KeyCode::Char('j') => *cursor = (*cursor + 1).min(GroupField::ALL.len() - 1),
+        KeyCode::Enter => {
+            let field = GroupField::ALL[*cursor];
+            app.log_view.group_by_chooser = None;
+            app.log_view.group_by = Some(GroupBy::new(field));
+        }
+        KeyCode::Esc => app.log_view.group_by_chooser = None,
+        _ => {}
+    }
+}
+
+/// Number of panels on the current tab that focus can move between
+fn panel_count(app: &App) -> usize {
+    match app.selected_tab {
+        0 => DASHBOARD_PANELS,
+        1 => app.config.dashboard.grid().map_or(0, |grid| grid.panels().count()),
+        4 => GEOGRAPHY_PANELS,
+        _ => 0,
+    }
+}
+
+/// Run a bound action, returning false to quit
+async fn handle_action(action: Action, app: &mut App) -> Result<bool> {
+    let tab = app.selected_tab;
+    match action {
+        Action::Quit => {
+            app.quit().await?;
+            return Ok(false);
+        }
+        Action::NextTab => app.selected_tab = (app.selected_tab + 1) % 6,
+        Action::PreviousTab => app.selected_tab = (app.selected_tab + 5) % 6,
+        // ←/→ only walk an attacker's sessions while one is open
+        Action::NextIpSession if app.selected_session_id.is_none() => app.selected_tab = (app.selected_tab + 1) % 6,
+        Action::PreviousIpSession if app.selected_session_id.is_none() => app.selected_tab = (app.selected_tab + 5) % 6,
+        Action::SelectTab(tab) => app.selected_tab = tab,
+        Action::ToggleAnonymize => app.anonymize = !app.anonymize,
+        Action::ToggleMaskSecrets => app.mask_secrets = !app.mask_secrets,
+        Action::ToggleWhitelisted => {
+            if app.store.can_include_whitelisted() {
+                let include = !app.store.includes_whitelisted();
+                app.store.set_include_whitelisted(include);
+                app.panel_caches.invalidate();
+            } else {
+                log::info!("Whitelisted IPs can only be re-included with filter.exclude_whitelisted = \"view\"");
+            }
+        }
+        Action::Refresh => app.panel_caches.invalidate(),
+        Action::ToggleManualRefresh => app.manual_refresh = !app.manual_refresh,
+        Action::ToggleRelativeTimes => {
+            let relative = !app.clock.relative();
+            app.clock.set_relative(relative);
+        }
+        Action::CycleTimeRange => {
+            app.filters.cycle_time_range(Utc::now());
+            app.panel_caches.invalidate();
+        }
+        Action::Export if app.selected_tab == 2 && app.log_view.group_by.is_some() => {
+            if let Err(e) = app.export_group_by() {
+                log::error!("{:#}", e);
+            }
+        }
+        Action::Export => app.start_export(),
+        Action::ToggleHelp => app.show_help = !app.show_help,
+        Action::OpenLogViewer => app.log_viewer = Some(log_viewer::LogViewer::default()),
+        Action::ToggleHealth => app.show_health = !app.show_health,
+        Action::NextPanel => {
+            let count = panel_count(app);
+            app.panel_focus.step(1, count);
+        }
+        Action::PreviousPanel => {
+            let count = panel_count(app);
+            app.panel_focus.step(-1, count);
+        }
+        Action::ToggleZoom => app.panel_focus.toggle_zoom(),
+        // Esc backs out of a zoomed panel before anything else
+        Action::Close if app.panel_focus.zoomed() => app.panel_focus.unzoom(),
+        Action::Search => app.search_input = Some(app.filters.search.clone().unwrap_or_default()),
+        Action::ChooseColumns => {
+            let config = &app.config.ui;
+            app.column_chooser = match app.selected_tab {
+                0 => Some(recent_session_table().chooser(config)),
+                2 => Some(log_table().chooser(config)),
+                3 => Some(session_table().chooser(config)),
+                _ => None,
+            };
+        }
+        // Esc drops an applied search before closing anything
+        Action::Close if app.filters.search.is_some() => app.filters.clear_search(),
+        _ => {
+            // Handle tab-specific actions
+            match app.selected_tab {
+                0 => handle_dashboard_input(action, app).await?,
+                1 => handle_security_input(action, app).await?,
+                2 => handle_logs_input(action, app).await?,
+                3 => handle_sessions_input(action, app).await?,
+                4 => handle_geography_input(action, app).await?,
+                5 => handle_settings_input(action, app).await?,
+                _ => {}
+            }
+        }
+    }
+    
+    // Each tab starts on its first panel, unzoomed
+    if app.selected_tab != tab {
+        app.panel_focus = PanelFocus::default();
+    }
+
+    Ok(true)
+}
+
+/// Whether `event` comes from a whitelisted address currently kept out of the views
+fn hidden_by_whitelist(event: &AppEvent, app: &App) -> bool {
+    let src_ip = match event {
+        AppEvent::NewLogEntry(entry) => entry.src_ip.as_deref(),
+        AppEvent::NewSession(session) | AppEvent::SessionUpdate(session) => Some(&*session.src_ip),
+        _ => None,
+    };
+
+    src_ip.map_or(false, |ip| app.store.hides_ip(ip))
+}
+
 /// Handle application events
 async fn handle_app_event(event: AppEvent, app: &mut App) -> Result<()> {
+    if !hidden_by_whitelist(&event, app) {
+        app.live_feed.handle_event(&event);
+    }
+    
     match event {
         AppEvent::Quit => {
             app.state = AppState::ShuttingDown;
         }
+        AppEvent::ConnectionStatusChange(status) => {
+            app.set_connection_status(status);
+        }
         _ => {}
     }
 
@@ -220,7 +599,7 @@ fn ui(f: &mut Frame, app: &App) {
         .split(size);
     
     // Create tabs
-    let titles = vec!["Dashboard", "Security", "Logs", "Sessions", "Geography", "Settings"];
+    let titles = ["Dashboard", "Security", "Logs", "Sessions", "Geography", "Settings"];
     let tabs = Tabs::new(titles.iter().map(|t| Span::styled(*t, Style::default())).collect())
         .select(app.selected_tab)
         .block(Block::default().title("xKippo Honeypot Monitor").borders(Borders::ALL))
@@ -242,23 +621,234 @@ fn ui(f: &mut Frame, app: &App) {
     
     // Render status bar
     render_status_bar(f, app, chunks[2]);
+    
+    if let Some(chooser) = &app.column_chooser {
+        columns::render_column_chooser(f, chooser, chunks[1]);
+    }
+
+    if let Some(view) = &app.alerts_view {
+        alerts::render_alerts_view(f, view, app, chunks[1]);
+    }
+
+    if let Some(tester) = &app.rule_tester {
+        rule_tester::render_rule_tester(f, tester, app, chunks[1]);
+    }
+    
+    if app.show_help {
+        render_help(f, app, size);
+    }
+
+    if let Some(viewer) = &app.log_viewer {
+        log_viewer::render_log_viewer(f, viewer, chunks[1]);
+    }
+
+    if app.show_health {
+        health::render_health(f, &app.health, &app.clock, chunks[1]);
+    }
+
+    if let Some(ip) = &app.rdap_popup {
+        rdap::render_rdap(f, app, ip, chunks[1]);
+    }
+
+    if let Some(wizard) = &app.wizard {
+        wizard::render_wizard(f, wizard, size);
+    }
+}
+
+/// Seconds the outcome of a threat intel refresh stays in the status bar
+const INTEL_STATUS_SECS: i64 = 30;
+
+/// Status bar text for a threat intel refresh, while there is something to say
+fn intel_status(app: &App) -> Option<String> {
+    let recent = |at: &chrono::DateTime<Utc>| (Utc::now() - *at).num_seconds() < INTEL_STATUS_SECS;
+    match &*app.intel_refresh.lock() {
+        crate::app::IntelRefresh::Running => Some("refreshing intel...".to_string()),
+        crate::app::IntelRefresh::Done { entries, at } if recent(at) => Some(format!("intel: {} entries", entries)),
+        crate::app::IntelRefresh::Failed { error, at } if recent(at) => Some(format!("intel refresh failed: {}", error)),
+        _ => None,
+    }
 }
 
 /// Render the status bar at the bottom of the screen
 fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
+    let store = app.store.snapshot();
+    // With a storage backend, totals include what the store has pruned
+    let (sessions, logs) = match app.store.stored_counts() {
+        Some(stored) => (
+            format!("{} ({} stored)", store.get_session_count(), stored.sessions),
+            format!("{} ({} stored)", store.get_log_entry_count(), stored.log_entries),
+        ),
+        None => (store.get_session_count().to_string(), store.get_log_entry_count().to_string()),
+    };
+    let intel = intel_status(app).map(|status| format!(" | {}", status)).unwrap_or_default();
+    let stream = app.stream_stats.as_ref()
+        .map(|stats| match stats.dropped() {
+            0 => format!(" | stream: {} clients", stats.clients()),
+            dropped => format!(" | stream: {} clients, {} dropped", stats.clients(), dropped),
+        })
+        .unwrap_or_default();
     let status = format!(
-        "{} | {} sessions | {} logs | Press '?' for help",
+        "{} | {} sessions | {} logs{}{} | {} | Press '?' for help",
         match app.connection_status {
             crate::app::ConnectionStatus::Disconnected => "Not Connected",
             crate::app::ConnectionStatus::Connecting => "Connecting...",
             crate::app::ConnectionStatus::Connected => "Connected",
+            crate::app::ConnectionStatus::Reconnecting(_) => "Reconnecting...",
             crate::app::ConnectionStatus::Failed(_) => "Connection Failed",
+            crate::app::ConnectionStatus::Stale(_) => "Connected (stale)",
         },
-        app.store.try_read().map_or(0, |store| store.get_session_count()),
-        app.store.try_read().map_or(0, |store| store.get_log_entry_count()),
+        sessions,
+        logs,
+        intel,
+        stream,
+        app.clock.now(),
     );
     
-    let text = ratatui::text::Line::from(status);
+    // The search prompt takes over the status bar while typing
+    if let Some(input) = &app.search_input {
+        let (mode, query) = match input.strip_prefix(REGEX_SEARCH_PREFIX) {
+            Some(pattern) => (Span::styled("regex /", Style::default().fg(Color::Magenta)), pattern),
+            None => (Span::styled("/", Style::default().fg(Color::Yellow)), input.as_str()),
+        };
+        let prompt = ratatui::text::Line::from(vec![
+            mode,
+            Span::raw(sanitize::escape_controls(query).into_owned()),
+            Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+            Span::styled("  Enter: apply  Esc: cancel  Ctrl+R: regex", Style::default().fg(Color::DarkGray)),
+        ]);
+        f.render_widget(
+            ratatui::widgets::Paragraph::new(prompt).style(Style::default().fg(Color::White).bg(Color::Black)),
+            area,
+        );
+
+        // The status bar is the bottom row, so the error goes on the row above it
+        if let (Some(error), true) = (&app.search_error, area.y > 0) {
+            let error_area = Rect::new(area.x, area.y - 1, area.width, 1);
+            f.render_widget(ratatui::widgets::Clear, error_area);
+            f.render_widget(
+                ratatui::widgets::Paragraph::new(format!(" regex error: {}", error))
+                    .style(Style::default().fg(Color::White).bg(Color::Red)),
+                error_area,
+            );
+        }
+        return;
+    }
+    
+    // As does the export prompt, until it is answered
+    if let Some(prompt) = &app.export_prompt {
+        let line = ratatui::text::Line::from(vec![
+            Span::styled(" Export ", Style::default().fg(Color::Black).bg(Color::Yellow)),
+            Span::raw(" "),
+            Span::styled("f", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(format!(": filtered view ({} events; {})  ", prompt.filtered, sanitize::escape_controls(&prompt.filters))),
+            Span::styled("a", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(format!(": all ({} events)", prompt.total)),
+            Span::styled("  Esc: cancel", Style::default().fg(Color::DarkGray)),
+        ]);
+        f.render_widget(
+            ratatui::widgets::Paragraph::new(line).style(Style::default().fg(Color::White).bg(Color::Black)),
+            area,
+        );
+        return;
+    }
+    
+    let mut spans = Vec::new();
+    if let Some(pending) = app.keys.pending() {
+        spans.push(Span::styled(
+            format!(" {} ", pending),
+            Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::raw(" "));
+    }
+    if let crate::app::ConnectionStatus::Stale(since) = app.connection_status {
+        // A quiet source usually means Cowrie died; make it hard to miss
+        spans.push(Span::styled(
+            format!(" STALE: no events for {} ", humanize_ago(Utc::now() - since).trim_end_matches(" ago")),
+            Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD | Modifier::SLOW_BLINK),
+        ));
+        spans.push(Span::raw(" "));
+    }
+    if let Some(sampling) = app.session_manager.governor().status() {
+        // Sessions on screen are a sample until the flood passes
+        spans.push(Span::styled(
+            format!(
+                " SAMPLING 1/{} since {}: {} sessions summarized ",
+                sampling.sample_every,
+                app.clock.time(&sampling.since),
+                sampling.summarized_sessions
+            ),
+            Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::raw(" "));
+    }
+    let unhealthy = app.health.unhealthy();
+    if unhealthy > 0 {
+        spans.push(Span::styled(
+            format!(" {} TASK{} DOWN (F10) ", unhealthy, if unhealthy == 1 { "" } else { "S" }),
+            Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::raw(" "));
+    }
+    let parse_stats = store.get_total_parse_stats();
+    if parse_stats.failure_rate() * 100.0 > app.config.ui.parse_error_warn_percent {
+        spans.push(Span::styled(
+            format!(" {} PARSE ERRORS ", parse_stats.failed),
+            Style::default().fg(Color::Black).bg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::raw(" "));
+    }
+    if app.anonymize {
+        spans.push(Span::styled(
+            " ANONYMIZED ",
+            Style::default().fg(Color::Black).bg(Color::Magenta).add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::raw(" "));
+    }
+    if app.store.includes_whitelisted() {
+        spans.push(Span::styled(
+            " WHITELIST SHOWN ",
+            Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::raw(" "));
+    }
+    if let Some(hours) = app.filters.last_hours {
+        let range = match hours {
+            hours if hours > 24 && hours % 24 == 0 => format!("{}D", hours / 24),
+            hours => format!("{}H", hours),
+        };
+        spans.push(Span::styled(
+            format!(" LAST {} (P: change) ", range),
+            Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::raw(" "));
+    }
+    if app.panel_focus.zoomed() && panel_count(app) > 0 {
+        spans.push(Span::styled(
+            " ZOOMED (z/Esc: back) ",
+            Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::raw(" "));
+    }
+    if app.manual_refresh {
+        // Drawn as part of the view, so it shows how old everything else on screen is
+        spans.push(Span::styled(
+            format!(" MANUAL: as of {} (r: refresh) ", app.clock.time(&app.drawn_at)),
+            Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::raw(" "));
+    } else {
+        spans.push(Span::styled(" LIVE ", Style::default().fg(Color::Green)));
+    }
+    if app.mask_secrets {
+        spans.push(Span::styled(
+            " SECRETS MASKED ",
+            Style::default().fg(Color::Black).bg(Color::Magenta).add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::raw(" "));
+    }
+    spans.push(Span::raw(status));
+    
+    let text = ratatui::text::Line::from(spans);
     let status_bar = ratatui::widgets::Paragraph::new(text)
         .style(Style::default().fg(Color::White).bg(Color::Black));
     
@@ -266,56 +856,36 @@ fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
 }
 
 // Tab-specific input handlers
-async fn handle_dashboard_input(key: event::KeyEvent, app: &mut App) -> Result<()> {
-    // Implementation will be added later
-    Ok(())
-}
-
-async fn handle_logs_input(key: event::KeyEvent, app: &mut App) -> Result<()> {
-    // Implementation will be added later
-    Ok(())
-}
-
-async fn handle_sessions_input(key: event::KeyEvent, app: &mut App) -> Result<()> {
-    // Implementation will be added later
-    Ok(())
-}
-
-async fn handle_settings_input(key: event::KeyEvent, app: &mut App) -> Result<()> {
-    // Implementation will be added later
+async fn handle_dashboard_input(action: Action, app: &mut App) -> Result<()> {
+    if action == Action::ToggleGaugeScale {
+        app.gauge_scale = app.gauge_scale.toggle();
+    }
     Ok(())
 }
 
-/// Handle input for the security dashboard
-async fn handle_security_input(key: event::KeyEvent, app: &mut App) -> Result<()> {
-    match key.code {
-        KeyCode::Char('f') => {
-            // Filter by risk score
-        },
-        KeyCode::Char('s') => {
-            // Sort by different metrics
-        },
-        KeyCode::Char('t') => {
-            // Toggle different views
-        },
+async fn handle_settings_input(action: Action, app: &mut App) -> Result<()> {
+    match action {
+        Action::RunWizard => {
+            let path = crate::config::wizard::target_path(None, &app.config)?;
+            app.open_wizard(path);
+        }
+        Action::BrowseLogPath => {
+            let path = crate::config::wizard::target_path(None, &app.config)?;
+            app.open_wizard(path);
+            if let Some(wizard) = app.wizard.as_mut() {
+                wizard.browse_for_log();
+            }
+        }
+        Action::TestRule => app.rule_tester = Some(RuleTester::default()),
         _ => {}
     }
     Ok(())
 }
 
 /// Handle input for the geography view
-async fn handle_geography_input(key: event::KeyEvent, app: &mut App) -> Result<()> {
-    match key.code {
-        KeyCode::Char('z') => {
-            // Zoom in
-        },
-        KeyCode::Char('Z') => {
-            // Zoom out
-        },
-        KeyCode::Char('r') => {
-            // Reset view
-        },
-        _ => {}
+async fn handle_geography_input(action: Action, app: &mut App) -> Result<()> {
+    if action == Action::CycleGeoRanking {
+        app.geo_ranking = app.geo_ranking.next();
     }
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,215 @@
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Wrap},
+    Frame,
+};
+use std::time::Duration;
+
+use crate::app::{App, ConnectionStatus};
+use crate::data::{AttackerProfile, Session};
+use crate::data::similarity::SimilarSession;
+use crate::ui::geography::GeoAggregates;
+use crate::ui::logs::{LogListKey, LogRows};
+use crate::ui::sessions::SessionListKey;
+use crate::utils::time::humanize_ago;
+use crate::utils::cache::{KeyedCache, TimedCache};
+
+/// Focused panel on the dashboard, security and geography tabs, and whether it is zoomed
+///
+/// The index is kept as typed and clamped against the tab's panel count when
+/// read, so switching to a layout with fewer panels never points past the end.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PanelFocus {
+    /// Focused panel, in the tab's reading order
+    index: usize,
+    /// Render the focused panel alone across the content area
+    zoomed: bool,
+}
+
+impl PanelFocus {
+    /// Focused panel out of `count`
+    pub fn index(&self, count: usize) -> usize {
+        self.index.min(count.saturating_sub(1))
+    }
+
+    /// Whether the focused panel fills the content area
+    pub fn zoomed(&self) -> bool {
+        self.zoomed
+    }
+
+    /// Move focus by `delta` panels out of `count`, wrapping around
+    pub fn step(&mut self, delta: isize, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let index = self.index(count) as isize + delta;
+        self.index = index.rem_euclid(count as isize) as usize;
+    }
+
+    /// Zoom into the focused panel, or back out to the grid
+    pub fn toggle_zoom(&mut self) {
+        self.zoomed = !self.zoomed;
+    }
+
+    /// Back out to the grid
+    pub fn unzoom(&mut self) {
+        self.zoomed = false;
+    }
+}
+
+/// Panel areas in reading order, or just the focused one across `area` when zoomed
+///
+/// Returns each panel's index alongside its area so callers can render only
+/// what is on screen.
+pub fn panel_areas(focus: &PanelFocus, areas: Vec<Rect>, area: Rect) -> Vec<(usize, Rect)> {
+    if focus.zoomed() && !areas.is_empty() {
+        vec![(focus.index(areas.len()), area)]
+    } else {
+        areas.into_iter().enumerate().collect()
+    }
+}
+
+/// Colour the border of the focused panel, keeping whatever title it drew
+///
+/// Nothing is marked while zoomed, when the panel is the only one on screen.
+pub fn highlight_focused(f: &mut Frame, focus: &PanelFocus, index: usize, count: usize, area: Rect) {
+    if focus.zoomed() || count < 2 || focus.index(count) != index || area.width < 2 || area.height < 2 {
+        return;
+    }
+
+    let style = Style::default().fg(Color::Cyan);
+    let buffer = f.buffer_mut();
+    buffer.set_style(Rect::new(area.x, area.y, area.width, 1), style);
+    buffer.set_style(Rect::new(area.x, area.bottom() - 1, area.width, 1), style);
+    buffer.set_style(Rect::new(area.x, area.y, 1, area.height), style);
+    buffer.set_style(Rect::new(area.right() - 1, area.y, 1, area.height), style);
+}
+
+/// Draw `block` with a message in place of a panel that has nothing to show
+///
+/// `what` names the missing data, e.g. "sessions". An empty panel on a fresh
+/// honeypot reads "waiting for activity", one whose log source isn't open says
+/// so, and `hidden` counts what the panel's filters or search are hiding.
+pub fn render_empty_state(f: &mut Frame, app: &App, block: Block, what: &str, hidden: usize, area: Rect) {
+    let (message, hint) = match app.connection_status {
+        ConnectionStatus::Disconnected | ConnectionStatus::Failed(_) => (
+            "Not connected to the honeypot".to_string(),
+            "Check the log source in Settings (tab 5) or run the setup wizard".to_string(),
+        ),
+        ConnectionStatus::Connecting | ConnectionStatus::Reconnecting(_) => (
+            "Connecting to the honeypot...".to_string(),
+            format!("{} will appear once the log source is open", capitalize(what)),
+        ),
+        ConnectionStatus::Connected | ConnectionStatus::Stale(_) if hidden > 0 => (
+            format!("No {} match the current search or filters", what),
+            format!("{} {} hidden", hidden, what),
+        ),
+        ConnectionStatus::Connected | ConnectionStatus::Stale(_) => (
+            format!("No {} yet — waiting for honeypot activity", what),
+            String::new(),
+        ),
+    };
+
+    let inner = block.inner(area);
+    let mut lines = vec![Line::from(""); (inner.height.saturating_sub(2) / 2) as usize];
+    lines.push(Line::from(message));
+    lines.push(Line::from(Span::styled(hint, Style::default().fg(Color::DarkGray))));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+    f.render_widget(paragraph, area);
+}
+
+/// `text` with its first letter in upper case
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+}
+
+/// Rows a bordered table with a header and a blank line under it can show in `area`
+pub fn table_rows(area: Rect) -> usize {
+    (area.height.saturating_sub(4) as usize).max(1)
+}
+
+/// Items a bordered list can show in `area`
+pub fn list_rows(area: Rect) -> usize {
+    (area.height.saturating_sub(2) as usize).max(1)
+}
+
+/// `host`, `port`, forward requests and requesting source IPs of one forward destination
+pub type ForwardDestination = (String, u16, usize, Vec<String>);
+
+/// Aggregations behind the heavier panels, recomputed every `dashboard.refresh_interval` seconds
+///
+/// Counters that are cheap to recompute are still drawn fresh on every tick.
+pub struct PanelCaches {
+    /// Source IPs ranked by attacker score (top attackers), highest first
+    pub attackers: TimedCache<Vec<AttackerProfile>>,
+    /// Sessions per source IP and username, with how many other IPs share a rare HASSH with it (attacker correlation)
+    pub correlations: TimedCache<Vec<(String, String, usize, usize)>>,
+    /// Commands per base command and whether any of them was watched (command distribution), most first
+    pub command_counts: TimedCache<Vec<(String, usize, bool)>>,
+    /// Forward requests and requesting source IPs per `host:port` (forward destinations), most first
+    pub forward_destinations: TimedCache<Vec<ForwardDestination>>,
+    /// Sessions and distinct source IPs per pre-payload command sequence (recon patterns), most first
+    pub recon_patterns: TimedCache<Vec<(String, usize, usize)>>,
+    /// Sessions per country (geographic distribution), most first
+    pub country_counts: TimedCache<Vec<(String, usize)>>,
+    /// Sessions and risk per country and ASN (geography tab)
+    pub geo_aggregates: TimedCache<GeoAggregates>,
+    /// Sessions similar to the open one, and the one they were computed for (session details)
+    pub similar_sessions: TimedCache<(String, Vec<SimilarSession>)>,
+    /// Every stored session from the open session's IP, and the IP (risk trend)
+    pub attacker_history: TimedCache<(String, Vec<Session>)>,
+    /// IDs of the sessions listed, for the snapshot and filters they were narrowed by (sessions tab)
+    pub session_list: KeyedCache<SessionListKey, Vec<String>>,
+    /// Entries listed and the runs collapsed into them, for the snapshot, search and collapsing (logs tab)
+    pub log_rows: KeyedCache<LogListKey, LogRows>,
+}
+
+impl PanelCaches {
+    /// Empty caches whose values live for `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            attackers: TimedCache::new(ttl),
+            correlations: TimedCache::new(ttl),
+            command_counts: TimedCache::new(ttl),
+            forward_destinations: TimedCache::new(ttl),
+            recon_patterns: TimedCache::new(ttl),
+            country_counts: TimedCache::new(ttl),
+            geo_aggregates: TimedCache::new(ttl),
+            similar_sessions: TimedCache::new(ttl),
+            attacker_history: TimedCache::new(ttl),
+            session_list: KeyedCache::default(),
+            log_rows: KeyedCache::default(),
+        }
+    }
+
+    /// Recompute everything on the next draw (`F5`, or when the data shown changes)
+    pub fn invalidate(&self) {
+        self.attackers.invalidate();
+        self.correlations.invalidate();
+        self.command_counts.invalidate();
+        self.forward_destinations.invalidate();
+        self.recon_patterns.invalidate();
+        self.country_counts.invalidate();
+        self.geo_aggregates.invalidate();
+        self.similar_sessions.invalidate();
+        self.attacker_history.invalidate();
+        self.session_list.invalidate();
+        self.log_rows.invalidate();
+    }
+}
+
+/// Panel title followed by how old its cached data is, e.g. "Attack Correlation (12s ago)"
+pub fn cached_title(title: impl Into<String>, age: Duration) -> Line<'static> {
+    let age = chrono::Duration::from_std(age).unwrap_or_else(|_| chrono::Duration::zero());
+    Line::from(vec![
+        Span::raw(title.into()),
+        Span::styled(format!(" ({})", humanize_ago(age)), Style::default().fg(Color::DarkGray)),
+    ])
+}
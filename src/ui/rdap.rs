@@ -0,0 +1,56 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::app::{App, RdapLookup};
+use crate::ui::anonymize::Anonymizer;
+use crate::ui::sanitize::escape_controls;
+
+/// Draw the registration popup (`w`) for `ip` over the tab area
+pub fn render_rdap(f: &mut Frame, app: &App, ip: &str, area: Rect) {
+    let width = area.width.min(76);
+    let height = area.height.min(9);
+    let popup = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    let label = |name: &str| Span::styled(format!("{:<14}", name), Style::default().fg(Color::Yellow));
+    // Registries are remote input, so their text is escaped like log data
+    let value = |value: &Option<String>| match value {
+        Some(value) => Span::raw(escape_controls(value).into_owned()),
+        None => Span::styled("not given", Style::default().fg(Color::DarkGray)),
+    };
+
+    let lines = match app.rdap_lookups.lock().get(ip) {
+        Some(RdapLookup::Done(info)) => vec![
+            Line::from(vec![label("Network"), value(&info.range)]),
+            Line::from(vec![label("Name"), value(&info.name)]),
+            Line::from(vec![label("Organisation"), value(&info.org)]),
+            Line::from(vec![label("Country"), value(&info.country)]),
+            Line::from(vec![label("Abuse contact"), value(&info.abuse_email)]),
+        ],
+        Some(RdapLookup::Failed(error)) => vec![
+            Line::from(Span::styled(escape_controls(error).into_owned(), Style::default().fg(Color::Red))),
+        ],
+        Some(RdapLookup::Running) | None => vec![Line::from(format!("Asking {}...", app.config.rdap.server))],
+    };
+
+    let block = Block::default()
+        .title(format!("Registration of {}", Anonymizer::for_app(app).ip(ip)))
+        .title(
+            ratatui::widgets::block::Title::from(" Any key: close ")
+                .position(ratatui::widgets::block::Position::Bottom),
+        )
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    f.render_widget(Clear, popup);
+    f.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: true }), popup);
+}
@@ -0,0 +1,144 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
+    Frame,
+};
+use regex::Regex;
+use std::collections::HashMap;
+
+use crate::app::{compile_search, App, REGEX_SEARCH_PREFIX};
+use crate::ui::anonymize::Anonymizer;
+use crate::ui::sanitize::escape_controls;
+use crate::ui::search::{search_style, styled_ranges};
+
+/// Regex tester for detection rules, opened with `t` on the settings tab
+///
+/// Patterns are compiled the way `rules.tag_rules` compiles them, so what
+/// matches here is what a tag rule with the same pattern would tag.
+#[derive(Debug, Default)]
+pub struct RuleTester {
+    /// Pattern being typed
+    pub input: String,
+    /// `input` compiled, while it compiles
+    pattern: Option<Regex>,
+    /// Why `input` doesn't compile, if it doesn't
+    pub error: Option<String>,
+}
+
+impl RuleTester {
+    /// Recompile after `input` changed
+    pub fn update(&mut self) {
+        if self.input.is_empty() {
+            self.pattern = None;
+            self.error = None;
+            return;
+        }
+
+        // Case-sensitive like a tag rule, with the search's size limits
+        match compile_search(&format!("{}{}", REGEX_SEARCH_PREFIX, self.input), true) {
+            Ok(pattern) => {
+                self.pattern = Some(pattern);
+                self.error = None;
+            }
+            Err(error) => {
+                self.pattern = None;
+                self.error = Some(error);
+            }
+        }
+    }
+}
+
+/// Draw the tester over the settings tab
+pub fn render_rule_tester(f: &mut Frame, tester: &RuleTester, app: &App, area: Rect) {
+    let width = area.width.min(100);
+    let height = area.height.min(30);
+    let popup = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    // Every distinct command in the stored sessions, with how often it was run
+    let store = app.store.snapshot();
+    let mut corpus: HashMap<&str, usize> = HashMap::new();
+    for session in store.get_sessions() {
+        for command in &session.commands {
+            *corpus.entry(command.command.as_str()).or_default() += 1;
+        }
+    }
+
+    let mut matches: Vec<(&str, usize)> = match &tester.pattern {
+        Some(pattern) => corpus.iter()
+            .filter(|(command, _)| pattern.is_match(command))
+            .map(|(command, runs)| (*command, *runs))
+            .collect(),
+        None => Vec::new(),
+    };
+    matches.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    let block = Block::default()
+        .title("Test a rule pattern")
+        .title(
+            ratatui::widgets::block::Title::from(" Enter/Esc: close ")
+                .position(ratatui::widgets::block::Position::Bottom),
+        )
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+    let inner = block.inner(popup);
+    f.render_widget(Clear, popup);
+    f.render_widget(block, popup);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ].as_ref())
+        .split(inner);
+
+    let prompt = Line::from(vec![
+        Span::styled("regex /", Style::default().fg(Color::Magenta)),
+        Span::raw(escape_controls(&tester.input).into_owned()),
+        Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+    ]);
+    f.render_widget(Paragraph::new(prompt), chunks[0]);
+
+    let status = match (&tester.error, &tester.pattern) {
+        (Some(error), _) => Paragraph::new(format!(" regex error: {}", error))
+            .style(Style::default().fg(Color::White).bg(Color::Red)),
+        (None, Some(_)) => Paragraph::new(format!(
+            " {} of {} distinct commands match ({} runs)",
+            matches.len(),
+            corpus.len(),
+            matches.iter().map(|(_, runs)| runs).sum::<usize>(),
+        ))
+        .style(Style::default().fg(Color::Yellow)),
+        (None, None) => Paragraph::new(format!(" Type a pattern to test it against {} distinct stored commands", corpus.len()))
+            .style(Style::default().fg(Color::DarkGray)),
+    };
+    f.render_widget(status, chunks[1]);
+
+    // Highlight on the text as shown, which escaping and anonymizing may have changed
+    let anon = Anonymizer::for_app(app);
+    let items: Vec<ListItem> = match &tester.pattern {
+        Some(pattern) => matches.iter()
+            .take(chunks[2].height as usize)
+            .map(|(command, runs)| {
+                let text = escape_controls(&anon.text(command)).into_owned();
+                let ranges = pattern.find_iter(&text)
+                    .map(|m| m.range())
+                    .filter(|range| !range.is_empty())
+                    .collect();
+                let mut spans = vec![Span::styled(format!("{:>5} ", runs), Style::default().fg(Color::DarkGray))];
+                spans.extend(styled_ranges(text, &[(ranges, search_style())]));
+                ListItem::new(Line::from(spans))
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    f.render_widget(List::new(items), chunks[2]);
+}
@@ -0,0 +1,138 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Appended to values cut short by [`truncate_width`]
+const ELLIPSIS: &str = "…";
+
+/// Make an attacker-supplied value safe to put in a cell, then fit it to `max_width`
+///
+/// A `max_width` of 0 disables truncation.
+pub fn sanitize(value: &str, max_width: usize) -> Cow<'_, str> {
+    match escape_controls(value) {
+        Cow::Borrowed(value) => truncate_width(value, max_width),
+        Cow::Owned(escaped) => Cow::Owned(truncate_width(&escaped, max_width).into_owned()),
+    }
+}
+
+/// Replace control and bidi override characters with visible escapes (`\n`, `\x1b`, `\u{202e}`)
+///
+/// ANSI sequences lose their ESC and show up as plain text instead of
+/// restyling or moving the cursor.
+pub fn escape_controls(value: &str) -> Cow<'_, str> {
+    if !value.chars().any(needs_escape) {
+        return Cow::Borrowed(value);
+    }
+
+    let mut escaped = String::with_capacity(value.len() + 8);
+    for c in value.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if needs_escape(c) && (c as u32) < 0x100 => {
+                let _ = write!(escaped, "\\x{:02x}", c as u32);
+            }
+            c if needs_escape(c) => {
+                let _ = write!(escaped, "\\u{{{:04x}}}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+
+    Cow::Owned(escaped)
+}
+
+/// Cut `value` to at most `max_width` terminal columns, ending in an ellipsis
+///
+/// Width is measured per grapheme cluster, so wide characters and emoji
+/// sequences are never split. A `max_width` of 0 disables truncation.
+pub fn truncate_width(value: &str, max_width: usize) -> Cow<'_, str> {
+    if max_width == 0 || value.width() <= max_width {
+        return Cow::Borrowed(value);
+    }
+
+    // Leave a column for the ellipsis
+    let budget = max_width - 1;
+    let mut used = 0;
+    let mut end = 0;
+
+    for (index, grapheme) in value.grapheme_indices(true) {
+        let width = grapheme.width();
+        if used + width > budget {
+            break;
+        }
+        used += width;
+        end = index + grapheme.len();
+    }
+
+    Cow::Owned(format!("{}{}", &value[..end], ELLIPSIS))
+}
+
+/// Characters that would corrupt the layout or reorder the text if rendered
+fn needs_escape(c: char) -> bool {
+    c.is_control()
+        || matches!(c, '\u{200e}' | '\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn ansi_colour_injection_is_shown_not_applied() {
+        assert_eq!(escape_controls("\x1b[31mroot\x1b[0m"), "\\x1b[31mroot\\x1b[0m");
+        // Clear screen, move home, then a fake prompt
+        assert_eq!(escape_controls("\x1b[2J\x1b[Hadmin# "), "\\x1b[2J\\x1b[Hadmin# ");
+    }
+
+    #[test]
+    fn line_breaks_and_other_controls_get_visible_escapes() {
+        assert_eq!(escape_controls("a\nb\rc\td"), "a\\nb\\rc\\td");
+        assert_eq!(escape_controls("nul\0bell\x07del\x7f"), "nul\\x00bell\\x07del\\x7f");
+        assert_eq!(escape_controls("c1\u{9b}31m"), "c1\\x9b31m");
+    }
+
+    #[test]
+    fn bidi_overrides_cannot_reorder_the_text() {
+        // Rendered, this reads "invoiceexe.txt"
+        assert_eq!(escape_controls("invoice\u{202e}txt.exe"), "invoice\\u{202e}txt.exe");
+        assert_eq!(escape_controls("\u{2066}x\u{2069}"), "\\u{2066}x\\u{2069}");
+    }
+
+    #[test]
+    fn clean_values_are_borrowed_untouched() {
+        for value in ["uname -a", "pässwörd", "密码", "🦀 root"] {
+            assert!(matches!(escape_controls(value), Cow::Borrowed(borrowed) if borrowed == value));
+            assert!(matches!(sanitize(value, 40), Cow::Borrowed(_)));
+        }
+    }
+
+    #[test]
+    fn truncation_counts_columns_not_bytes() {
+        assert_eq!(truncate_width("abcdefgh", 5), "abcd…");
+        assert_eq!(truncate_width("abcde", 5), "abcde");
+        // Two columns each: four fit beside the ellipsis in 9
+        assert_eq!(truncate_width("密码密码密码", 9), "密码密码…");
+        assert_eq!(truncate_width("密码密码密码", 8), "密码密…");
+        assert_eq!(truncate_width("anything", 0), "anything");
+    }
+
+    #[test]
+    fn emoji_sequences_are_never_split() {
+        let family = "👨\u{200d}👩\u{200d}👧";
+        assert_eq!(truncate_width(&format!("{}{}x", family, family), 4), format!("{}…", family));
+        let flag = "🇳🇱";
+        assert_eq!(truncate_width(&format!("a{}{}", flag, flag), 4), format!("a{}…", flag));
+        assert_eq!(truncate_width("e\u{301}e\u{301}e\u{301}", 2), "e\u{301}…");
+    }
+
+    #[test]
+    fn escapes_count_toward_the_width() {
+        let value = sanitize("\x1b[31mroot", 8);
+        assert_eq!(value, "\\x1b[31…");
+        assert_eq!(value.width(), 8);
+    }
+}
@@ -0,0 +1,45 @@
+use ratatui::{
+    style::{Color, Style},
+    text::Span,
+};
+use std::ops::Range;
+
+use crate::app::AppFilters;
+
+/// How matches of the current search are drawn
+pub fn search_style() -> Style {
+    Style::default().fg(Color::Black).bg(Color::Yellow)
+}
+
+/// Split `text` into spans, highlighting the parts matched by the current search
+pub fn highlight_search(filters: &AppFilters, text: String) -> Vec<Span<'static>> {
+    let matches = filters.search_matches(&text);
+    styled_ranges(text, &[(matches, search_style())])
+}
+
+/// Split `text` into spans styled by layers of byte ranges
+///
+/// Where layers overlap, later ones are patched over earlier ones, so a
+/// search match inside a watched command keeps both looks.
+pub fn styled_ranges(text: String, layers: &[(Vec<Range<usize>>, Style)]) -> Vec<Span<'static>> {
+    let mut bounds: Vec<usize> = layers.iter()
+        .flat_map(|(ranges, _)| ranges.iter().flat_map(|range| [range.start, range.end]))
+        .chain([0, text.len()])
+        .collect();
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    if bounds.len() <= 2 && layers.iter().all(|(ranges, _)| ranges.is_empty()) {
+        return vec![Span::raw(text)];
+    }
+
+    bounds.windows(2)
+        .map(|pair| {
+            let segment = pair[0]..pair[1];
+            let style = layers.iter()
+                .filter(|(ranges, _)| ranges.iter().any(|range| range.start <= segment.start && segment.end <= range.end))
+                .fold(Style::default(), |style, (_, layer)| style.patch(*layer));
+            Span::styled(text[segment].to_string(), style)
+        })
+        .collect()
+}
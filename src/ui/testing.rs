@@ -0,0 +1,62 @@
+//! An app to draw in tests, writing nothing outside a temporary directory
+
+use ratatui::backend::TestBackend;
+use ratatui::Terminal;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::app::App;
+use crate::config::Config;
+use crate::data::testing::{at, connect, eventually, session};
+use crate::data::{LogEntry, Session, StoreCommand};
+
+/// An app on an in-memory store keeping up to `capacity` sessions and log
+/// entries, with its alert history in `dir`
+pub async fn app(dir: &Path, capacity: usize) -> App {
+    let mut config = Config::default();
+    config.honeypot.log_paths = Some(vec![dir.join("cowrie.json").display().to_string()]);
+    config.honeypot.auto_detect = false;
+    config.alert.history_path = Some(dir.join("alerts.jsonl").display().to_string());
+    config.storage.backend = "memory".to_string();
+    config.filter.max_sessions = capacity;
+    config.filter.max_logs = capacity;
+    App::new(config).await.unwrap()
+}
+
+/// Queue `sessions` and `entries`, returning once a snapshot has them
+pub async fn fill(app: &App, sessions: Vec<Session>, entries: Vec<LogEntry>) {
+    let (session_count, entry_count) = (sessions.len(), entries.len());
+    for session in sessions {
+        app.store.send(StoreCommand::UpsertSession(Box::new(session))).await;
+    }
+    app.store.send(StoreCommand::AddLogEntries { source: "test".to_string(), entries }).await;
+    app.store.applied().await;
+
+    eventually(|| {
+        let snapshot = app.store.snapshot();
+        snapshot.get_sessions().len() == session_count && snapshot.get_log_entries().len() == entry_count
+    })
+    .await;
+}
+
+/// An app holding `count` sessions from a few hundred addresses, with a connect entry each
+pub async fn app_with_sessions(dir: &Path, count: usize) -> App {
+    let app = app(dir, count).await;
+    let ips = |i: usize| format!("198.51.{}.{}", i / 250 % 4, i % 250);
+    let sessions = (0..count).map(|i| session(&format!("s{}", i), &ips(i), at(i as i64))).collect();
+    let entries = (0..count).map(|i| connect(&format!("s{}", i), &ips(i), at(i as i64))).collect();
+    fill(&app, sessions, entries).await;
+    app
+}
+
+/// Mean time to draw the whole screen, after a first draw has filled the caches
+pub fn frame_time(app: &App, frames: u32) -> Duration {
+    let mut terminal = Terminal::new(TestBackend::new(200, 60)).unwrap();
+    terminal.draw(|f| super::ui(f, app)).unwrap();
+
+    let started = Instant::now();
+    for _ in 0..frames {
+        terminal.draw(|f| super::ui(f, app)).unwrap();
+    }
+    started.elapsed() / frames
+}
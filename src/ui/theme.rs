@@ -0,0 +1,248 @@
+use ratatui::style::{Color, Modifier, Style};
+
+use crate::config::UIConfig;
+use crate::data::EventType;
+use crate::core::Severity;
+use crate::ui::components::FeedSeverity;
+
+/// Session risk bands shared by every view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RiskLevel {
+    /// 70 and above
+    High,
+    /// 30 to 69
+    Medium,
+    /// Below 30
+    Low,
+}
+
+impl RiskLevel {
+    /// Band of a 0-100 risk score
+    pub fn of(score: u8) -> Self {
+        match score {
+            70..=u8::MAX => RiskLevel::High,
+            30..=69 => RiskLevel::Medium,
+            _ => RiskLevel::Low,
+        }
+    }
+
+    /// Marker shown in front of risk values when symbols are on
+    pub fn symbol(self) -> &'static str {
+        match self {
+            RiskLevel::High => "‼",
+            RiskLevel::Medium => "!",
+            RiskLevel::Low => "·",
+        }
+    }
+}
+
+/// How risk levels are told apart (`ui.risk_labels`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RiskLabels {
+    /// Red, yellow and green only
+    Color,
+    /// ‼, ! and · markers, with the text otherwise uncoloured
+    Symbol,
+    /// Markers and colours
+    Both,
+}
+
+/// Semantic styles for everything whose colour carries meaning
+///
+/// Views ask for the style of a risk level, event type or outcome instead of
+/// picking colours, so `ui.theme`, `ui.color_enabled` and `ui.risk_labels`
+/// apply the same way everywhere. Without colour, meaning falls back to
+/// markers and bold text.
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    /// Brighter colours that stay readable on black (`ui.theme = "high-contrast"`)
+    high_contrast: bool,
+    /// Colours are drawn at all (`ui.color_enabled`)
+    color: bool,
+    /// How risk levels are marked
+    labels: RiskLabels,
+}
+
+impl Theme {
+    /// Theme for the `[ui]` settings; unknown values fall back to the defaults
+    pub fn from_config(ui: &UIConfig) -> Self {
+        let labels = match ui.risk_labels.as_str() {
+            "symbol" => RiskLabels::Symbol,
+            "both" => RiskLabels::Both,
+            _ => RiskLabels::Color,
+        };
+
+        Self {
+            high_contrast: ui.theme == "high-contrast",
+            color: ui.color_enabled,
+            // Colour alone says nothing on a monochrome terminal
+            labels: if ui.color_enabled { labels } else { RiskLabels::Symbol },
+        }
+    }
+
+    /// Whether colours are drawn
+    pub fn color(&self) -> bool {
+        self.color
+    }
+
+    /// Whether risk values carry a ‼/!/· marker
+    pub fn risk_symbols(&self) -> bool {
+        self.labels != RiskLabels::Color
+    }
+
+    /// `fg` in colour mode, nothing otherwise
+    fn fg(&self, color: Color) -> Style {
+        if self.color {
+            Style::default().fg(color)
+        } else {
+            Style::default()
+        }
+    }
+
+    /// Style of text marking a risk level
+    pub fn style_for_risk(&self, level: RiskLevel) -> Style {
+        if self.labels == RiskLabels::Symbol {
+            return match level {
+                RiskLevel::High => Style::default().add_modifier(Modifier::BOLD),
+                _ => Style::default(),
+            };
+        }
+
+        match (level, self.high_contrast) {
+            (RiskLevel::High, false) => self.fg(Color::Red),
+            (RiskLevel::High, true) => self.fg(Color::LightRed).add_modifier(Modifier::BOLD),
+            (RiskLevel::Medium, false) => self.fg(Color::Yellow),
+            (RiskLevel::Medium, true) => self.fg(Color::LightYellow),
+            (RiskLevel::Low, false) => self.fg(Color::Green),
+            (RiskLevel::Low, true) => self.fg(Color::LightGreen),
+        }
+    }
+
+    /// Style of a 0-100 risk score
+    pub fn style_for_score(&self, score: u8) -> Style {
+        self.style_for_risk(RiskLevel::of(score))
+    }
+
+    /// `text` with the level's marker in front when symbols are on
+    pub fn risk_label(&self, level: RiskLevel, text: impl Into<String>) -> String {
+        if self.risk_symbols() {
+            format!("{} {}", level.symbol(), text.into())
+        } else {
+            text.into()
+        }
+    }
+
+    /// A risk score as shown in tables, e.g. "85" or "‼ 85"
+    pub fn risk_score(&self, score: u8) -> String {
+        self.risk_label(RiskLevel::of(score), score.to_string())
+    }
+
+    /// Columns a risk score needs
+    pub fn risk_width(&self) -> u16 {
+        if self.risk_symbols() { 5 } else { 4 }
+    }
+
+    /// Row style of a log entry by event type
+    pub fn style_for_event(&self, event_type: &EventType) -> Style {
+        if !self.color {
+            return match event_type {
+                EventType::LoginSuccess | EventType::FileUpload | EventType::FileDownload => {
+                    Style::default().add_modifier(Modifier::BOLD)
+                }
+                _ => Style::default(),
+            };
+        }
+
+        match (event_type, self.high_contrast) {
+            (EventType::LoginSuccess, false) => Style::default().fg(Color::Green),
+            (EventType::LoginSuccess, true) => Style::default().fg(Color::LightGreen).add_modifier(Modifier::BOLD),
+            (EventType::LoginFailed, false) => Style::default().fg(Color::Red),
+            (EventType::LoginFailed, true) => Style::default().fg(Color::LightRed),
+            // Blue is nearly invisible on black, so high contrast uses cyan
+            (EventType::Command, false) => Style::default().fg(Color::Blue),
+            (EventType::Command, true) => Style::default().fg(Color::LightCyan),
+            (EventType::FileUpload | EventType::FileDownload, false) => Style::default().fg(Color::Yellow),
+            (EventType::FileUpload | EventType::FileDownload, true) => Style::default().fg(Color::LightYellow),
+            _ => Style::default(),
+        }
+    }
+
+    /// Style of a success or failure (logins, commands)
+    pub fn style_for_outcome(&self, success: bool) -> Style {
+        match (success, self.color, self.high_contrast) {
+            (true, false, _) => Style::default().add_modifier(Modifier::BOLD),
+            (false, false, _) => Style::default(),
+            (true, true, false) => Style::default().fg(Color::Green),
+            (true, true, true) => Style::default().fg(Color::LightGreen),
+            (false, true, false) => Style::default().fg(Color::Red),
+            (false, true, true) => Style::default().fg(Color::LightRed),
+        }
+    }
+
+    /// Style of a live feed line by severity
+    pub fn style_for_severity(&self, severity: FeedSeverity) -> Style {
+        match severity {
+            FeedSeverity::High => self.style_for_risk(RiskLevel::High).add_modifier(Modifier::BOLD),
+            FeedSeverity::Medium => self.style_for_risk(RiskLevel::Medium),
+            FeedSeverity::Info => Style::default(),
+        }
+    }
+
+    /// Style of an alert's severity; critical stands out even without color
+    pub fn style_for_alert(&self, severity: Severity) -> Style {
+        match severity {
+            Severity::Critical => self.style_for_risk(RiskLevel::High).add_modifier(Modifier::BOLD | Modifier::REVERSED),
+            Severity::High => self.style_for_risk(RiskLevel::High),
+            Severity::Medium => self.style_for_risk(RiskLevel::Medium),
+            Severity::Low => self.style_for_risk(RiskLevel::Low),
+            Severity::Info => Style::default(),
+        }
+    }
+
+    /// An alert's severity as shown in tables, e.g. "high" or "‼ high"
+    pub fn alert_label(&self, severity: Severity) -> String {
+        let level = match severity {
+            Severity::Critical | Severity::High => RiskLevel::High,
+            Severity::Medium => RiskLevel::Medium,
+            Severity::Low | Severity::Info => RiskLevel::Low,
+        };
+        self.risk_label(level, severity.as_str())
+    }
+
+    /// Marker in front of live feed lines when symbols are on
+    pub fn severity_symbol(&self, severity: FeedSeverity) -> &'static str {
+        match severity {
+            _ if !self.risk_symbols() => "",
+            FeedSeverity::High => "‼ ",
+            FeedSeverity::Medium => "! ",
+            FeedSeverity::Info => "  ",
+        }
+    }
+
+    /// Style of an activity gauge in `color`
+    ///
+    /// Without colour the filled and empty parts of a line gauge look the
+    /// same, so callers also print the percentage (see [`Theme::color`]).
+    pub fn gauge_style(&self, color: Color) -> Style {
+        if !self.color {
+            Style::default()
+        } else if self.high_contrast {
+            Style::default().fg(brighten(color)).bg(Color::DarkGray)
+        } else {
+            Style::default().fg(color).bg(Color::Black)
+        }
+    }
+}
+
+/// The light variant of a basic colour
+fn brighten(color: Color) -> Color {
+    match color {
+        Color::Red => Color::LightRed,
+        Color::Green => Color::LightGreen,
+        Color::Yellow => Color::LightYellow,
+        Color::Blue => Color::LightCyan,
+        Color::Magenta => Color::LightMagenta,
+        Color::Cyan => Color::LightCyan,
+        other => other,
+    }
+}
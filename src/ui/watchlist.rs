@@ -0,0 +1,73 @@
+use anyhow::{anyhow, Result};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::Span,
+};
+use regex::Regex;
+use std::ops::Range;
+
+use crate::app::{compile_search, AppFilters};
+use crate::config::Config;
+use crate::ui::search::{search_style, styled_ranges};
+
+/// How watched commands, and the parts of them that matched, are drawn
+pub fn watch_style() -> Style {
+    Style::default().fg(Color::LightMagenta).add_modifier(Modifier::BOLD)
+}
+
+/// Commands to make stand out wherever they are shown
+///
+/// Built from `ui.watch_commands` and `alert.on_commands`. Entries are
+/// case-sensitive substrings, or regular expressions after `re:` as in the
+/// search prompt.
+#[derive(Debug, Clone, Default)]
+pub struct CommandWatchlist {
+    /// Compiled entries
+    patterns: Vec<Regex>,
+}
+
+impl CommandWatchlist {
+    /// Compile the watchlist, failing on the first entry that doesn't compile
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let patterns = config.ui.watch_commands.iter()
+            .chain(config.alert.on_commands.iter().map(|alert| &alert.pattern))
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                compile_search(entry, true)
+                    .map_err(|e| anyhow!("Invalid watched command '{}': {}", entry, e))
+            })
+            .collect::<Result<Vec<Regex>>>()?;
+
+        Ok(Self { patterns })
+    }
+
+    /// Whether any entry matches somewhere in `command`
+    pub fn matches(&self, command: &str) -> bool {
+        self.patterns.iter().any(|pattern| pattern.is_match(command))
+    }
+
+    /// Byte ranges of `command` matched by any entry, in order and merged where they overlap
+    pub fn match_ranges(&self, command: &str) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = self.patterns.iter()
+            .flat_map(|pattern| pattern.find_iter(command).map(|m| m.range()))
+            .filter(|range| !range.is_empty())
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+}
+
+/// Split a command into spans, marking watched parts and then the current search's matches
+pub fn highlight_command(watchlist: &CommandWatchlist, filters: &AppFilters, text: String) -> Vec<Span<'static>> {
+    let watched = watchlist.match_ranges(&text);
+    let searched = filters.search_matches(&text);
+    styled_ranges(text, &[(watched, watch_style()), (searched, search_style())])
+}
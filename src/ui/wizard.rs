@@ -0,0 +1,808 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::config::detect::detect_log_files;
+use crate::config::{CommandAlert, Config, HoneypotConfig};
+use crate::core::LogAnalyzer;
+use crate::ui::sanitize::escape_controls;
+
+/// Non-empty lines read from a candidate log file to check that it parses
+const CHECK_LINES: usize = 20;
+
+/// File picked when a directory is chosen in the browser
+const COWRIE_LOG_NAME: &str = "cowrie.json";
+
+/// Wizard pages, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Honeypot,
+    LogPaths,
+    GeoIp,
+    Alerts,
+    Review,
+}
+
+impl Step {
+    /// Every page, in order
+    const ALL: [Step; 5] = [Step::Honeypot, Step::LogPaths, Step::GeoIp, Step::Alerts, Step::Review];
+
+    /// Name in the step bar
+    fn title(self) -> &'static str {
+        match self {
+            Step::Honeypot => "Honeypot",
+            Step::LogPaths => "Log files",
+            Step::GeoIp => "GeoIP",
+            Step::Alerts => "Alerts",
+            Step::Review => "Review",
+        }
+    }
+}
+
+/// What reading the start of a candidate log file found
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LogCheck {
+    /// Nothing at the path yet (Cowrie may not have started)
+    Missing,
+    /// A directory rather than a file
+    Directory,
+    /// The file could not be opened or read
+    Unreadable(String),
+    /// The file has no lines yet
+    Empty,
+    /// How many of the first non-empty lines parsed, with the first error
+    Parsed { parsed: usize, lines: usize, error: Option<String> },
+}
+
+impl LogCheck {
+    /// Read up to [`CHECK_LINES`] lines of `path` and try to parse each
+    fn run(analyzer: &LogAnalyzer, path: &Path) -> Self {
+        if path.is_dir() {
+            return LogCheck::Directory;
+        }
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return LogCheck::Missing,
+            Err(e) => return LogCheck::Unreadable(e.to_string()),
+        };
+
+        let (mut parsed, mut lines, mut error) = (0, 0, None);
+        for line in BufReader::new(file).split(b'\n').take(CHECK_LINES * 4) {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return LogCheck::Unreadable(e.to_string()),
+            };
+            let line = String::from_utf8_lossy(&line);
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            lines += 1;
+            match analyzer.parse_log_entry(line.trim()) {
+                Ok(_) => parsed += 1,
+                Err(e) => {
+                    error.get_or_insert_with(|| format!("{:#}", e));
+                }
+            }
+            if lines == CHECK_LINES {
+                break;
+            }
+        }
+
+        if lines == 0 {
+            LogCheck::Empty
+        } else {
+            LogCheck::Parsed { parsed, lines, error }
+        }
+    }
+
+    /// One-line summary next to the path
+    fn describe(&self) -> String {
+        match self {
+            LogCheck::Missing => "not found yet".to_string(),
+            LogCheck::Directory => "a directory".to_string(),
+            LogCheck::Unreadable(e) => format!("unreadable: {}", e),
+            LogCheck::Empty => "empty so far".to_string(),
+            LogCheck::Parsed { parsed, lines, error: None } => format!("{}/{} lines parse", parsed, lines),
+            LogCheck::Parsed { parsed, lines, error: Some(e) } => format!("{}/{} lines parse: {}", parsed, lines, e),
+        }
+    }
+
+    /// Why a file picked in the browser isn't taken, if it isn't
+    ///
+    /// An empty file is taken: Cowrie may have just rotated its log.
+    fn rejection(&self) -> Option<String> {
+        match self {
+            LogCheck::Parsed { parsed, .. } if *parsed > 0 => None,
+            LogCheck::Empty => None,
+            LogCheck::Parsed { error, .. } => Some(format!(
+                "Doesn't look like Cowrie JSON: {}",
+                error.as_deref().unwrap_or("no line parses")
+            )),
+            other => Some(format!("Can't use this: {}", other.describe())),
+        }
+    }
+
+    /// Green when events parse, yellow when there is nothing to check yet, red otherwise
+    fn style(&self) -> Style {
+        match self {
+            LogCheck::Parsed { parsed, .. } if *parsed > 0 => Style::default().fg(Color::Green),
+            LogCheck::Missing | LogCheck::Empty => Style::default().fg(Color::Yellow),
+            _ => Style::default().fg(Color::Red),
+        }
+    }
+}
+
+/// A log file the wizard offers
+struct Candidate {
+    /// Path as written to `honeypot.log_paths`
+    path: String,
+    /// How it came to be offered: configured, a detection probe, or added here
+    origin: String,
+    /// Whether it will be monitored
+    selected: bool,
+    /// Result of reading its first lines
+    check: LogCheck,
+}
+
+/// Directory browser for picking a log file
+struct DirPicker {
+    /// Directory being listed
+    dir: PathBuf,
+    /// Parent (if any), then subdirectories, then files, each sorted by name
+    entries: Vec<PathBuf>,
+    /// Highlighted entry
+    cursor: usize,
+    /// Check of the highlighted entry, when it is a file
+    preview: Option<LogCheck>,
+    /// Why the last pick wasn't taken
+    rejected: Option<String>,
+}
+
+impl DirPicker {
+    /// List `dir`; unreadable directories list only their parent
+    fn open(dir: PathBuf, analyzer: &LogAnalyzer) -> Self {
+        let mut children: Vec<PathBuf> = fs::read_dir(&dir)
+            .map(|entries| entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect())
+            .unwrap_or_default();
+        children.sort_by_key(|path| (!path.is_dir(), path.file_name().map(|name| name.to_os_string())));
+
+        let mut entries: Vec<PathBuf> = dir.parent().map(Path::to_path_buf).into_iter().collect();
+        entries.extend(children);
+
+        let mut picker = Self { dir, entries, cursor: 0, preview: None, rejected: None };
+        picker.refresh_preview(analyzer);
+        picker
+    }
+
+    /// Move the highlight by `delta` entries
+    fn move_cursor(&mut self, delta: isize, analyzer: &LogAnalyzer) {
+        let last = self.entries.len().saturating_sub(1) as isize;
+        self.cursor = (self.cursor as isize + delta).max(0).min(last) as usize;
+        self.refresh_preview(analyzer);
+    }
+
+    /// Check the highlighted file as the cursor moves over it
+    fn refresh_preview(&mut self, analyzer: &LogAnalyzer) {
+        self.rejected = None;
+        self.preview = self.entries.get(self.cursor)
+            .filter(|path| path.is_file())
+            .map(|path| LogCheck::run(analyzer, path));
+    }
+
+    /// How an entry is listed
+    fn label(&self, index: usize) -> String {
+        let path = &self.entries[index];
+        if index == 0 && self.dir.parent() == Some(path.as_path()) {
+            return "../".to_string();
+        }
+        let name = path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned());
+        if path.is_dir() { format!("{}/", name) } else { name }
+    }
+}
+
+/// Something on a wizard page that can be selected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Name,
+    HoneypotType,
+    Source,
+    DatabaseUrl,
+    JournaldUnit,
+    LogPath(usize),
+    AddPath,
+    Browse,
+    AutoDetect,
+    GeoEnabled,
+    GeoDatabase,
+    GeoAutoDownload,
+    GeoLicenseKey,
+    AlertsEnabled,
+    AlertLogin,
+    AlertUpload,
+    AlertNewIp,
+    AlertCommands,
+    Save,
+}
+
+/// What a key did to the wizard
+pub enum WizardOutcome {
+    /// Still open
+    Continue,
+    /// Closed without writing anything
+    Cancelled,
+    /// Written to disk; the configuration to switch to
+    Saved(Box<Config>),
+}
+
+/// Setup wizard drawn over the whole screen: on first run, with `--setup`, or `w` on the settings tab
+pub struct SetupWizard {
+    /// Configuration being edited
+    config: Config,
+    /// Where it is written
+    path: PathBuf,
+    /// Index into [`Step::ALL`]
+    step: usize,
+    /// Highlighted field on the page
+    cursor: usize,
+    /// Log files offered on the log files page
+    candidates: Vec<Candidate>,
+    /// Field being typed into, with the text so far
+    editing: Option<(Field, String)>,
+    /// Directory browser, while open
+    picker: Option<DirPicker>,
+    /// Parses candidate files the way the watchers will
+    analyzer: LogAnalyzer,
+    /// Result of the last save attempt, and whether it failed
+    message: Option<(String, bool)>,
+}
+
+impl SetupWizard {
+    /// Start from `config`, offering its log paths and any detected ones, to be written to `path`
+    pub fn new(config: &Config, path: PathBuf) -> Self {
+        let analyzer = LogAnalyzer::with_config(config);
+
+        let configured = config.honeypot.log_paths.clone().unwrap_or_default();
+        let detected = detect_log_files().into_iter()
+            .map(|log| (log.path.display().to_string(), log.source.to_string()))
+            .filter(|(path, _)| !configured.contains(path))
+            .collect::<Vec<_>>();
+
+        let candidates = configured.into_iter()
+            .map(|path| (path, "configured".to_string()))
+            .chain(detected)
+            .map(|(path, origin)| Candidate {
+                check: LogCheck::run(&analyzer, Path::new(&path)),
+                path,
+                origin,
+                selected: true,
+            })
+            .collect();
+
+        Self {
+            config: config.clone(),
+            path,
+            step: 0,
+            cursor: 0,
+            candidates,
+            editing: None,
+            picker: None,
+            analyzer,
+            message: None,
+        }
+    }
+
+    /// Page being shown
+    fn current_step(&self) -> Step {
+        Step::ALL[self.step]
+    }
+
+    /// Fields on the current page, top to bottom
+    fn fields(&self) -> Vec<Field> {
+        let config = &self.config;
+        match self.current_step() {
+            Step::Honeypot => {
+                let mut fields = vec![Field::Name, Field::HoneypotType, Field::Source];
+                match config.honeypot.source.as_str() {
+                    "mysql" => fields.push(Field::DatabaseUrl),
+                    "journald" => fields.push(Field::JournaldUnit),
+                    _ => {}
+                }
+                fields
+            }
+            Step::LogPaths => (0..self.candidates.len()).map(Field::LogPath)
+                .chain([Field::AddPath, Field::Browse, Field::AutoDetect])
+                .collect(),
+            Step::GeoIp => {
+                let mut fields = vec![Field::GeoEnabled];
+                if config.geoip.enabled {
+                    fields.extend([Field::GeoDatabase, Field::GeoAutoDownload]);
+                    if config.geoip.auto_download {
+                        fields.push(Field::GeoLicenseKey);
+                    }
+                }
+                fields
+            }
+            Step::Alerts => {
+                let mut fields = vec![Field::AlertsEnabled];
+                if config.alert.enabled {
+                    fields.extend([Field::AlertLogin, Field::AlertUpload, Field::AlertNewIp, Field::AlertCommands]);
+                }
+                fields
+            }
+            Step::Review => vec![Field::Save],
+        }
+    }
+
+    /// Label and current value of `field`
+    fn describe(&self, field: Field) -> (String, String) {
+        let config = &self.config;
+        let yes_no = |value: bool| if value { "yes" } else { "no" }.to_string();
+        let optional = |value: &Option<String>| value.clone().unwrap_or_else(|| "(none)".to_string());
+
+        match field {
+            Field::Name => ("Honeypot name".into(), config.honeypot.name.clone()),
+            Field::HoneypotType => ("Honeypot type".into(), config.honeypot.honeypot_type.clone()),
+            Field::Source => ("Read events from".into(), config.honeypot.source.clone()),
+            Field::DatabaseUrl => ("Database URL".into(), optional(&config.honeypot.database_url)),
+            Field::JournaldUnit => ("systemd unit".into(), config.honeypot.journald_unit.clone()),
+            Field::LogPath(index) => {
+                let candidate = &self.candidates[index];
+                let mark = if candidate.selected { "[x]" } else { "[ ]" };
+                (format!("{} {}", mark, candidate.path), format!("{} ({})", candidate.check.describe(), candidate.origin))
+            }
+            Field::AddPath => ("Type a path…".into(), String::new()),
+            Field::Browse => ("Browse for a file…".into(), String::new()),
+            Field::AutoDetect => ("Also pick up logs in common locations".into(), yes_no(config.honeypot.auto_detect)),
+            Field::GeoEnabled => ("Look up attacker locations".into(), yes_no(config.geoip.enabled)),
+            Field::GeoDatabase => ("GeoLite2 database path".into(), optional(&config.geoip.database_path)),
+            Field::GeoAutoDownload => ("Download the database if missing".into(), yes_no(config.geoip.auto_download)),
+            Field::GeoLicenseKey => ("MaxMind license key".into(), optional(&config.geoip.license_key)),
+            Field::AlertsEnabled => ("Enable alerts".into(), yes_no(config.alert.enabled)),
+            Field::AlertLogin => ("Alert on successful logins".into(), yes_no(config.alert.on_successful_login)),
+            Field::AlertUpload => ("Alert on file uploads".into(), yes_no(config.alert.on_file_upload)),
+            Field::AlertNewIp => ("Alert on new source IPs".into(), yes_no(config.alert.on_new_source_ip)),
+            Field::AlertCommands => ("Alert on commands containing".into(), command_patterns(config)),
+            Field::Save => (format!("Save to {}", self.path.display()), String::new()),
+        }
+    }
+
+    /// Text a field starts with when it is edited
+    fn edit_text(&self, field: Field) -> String {
+        let config = &self.config;
+        match field {
+            Field::Name => config.honeypot.name.clone(),
+            Field::DatabaseUrl => config.honeypot.database_url.clone().unwrap_or_default(),
+            Field::JournaldUnit => config.honeypot.journald_unit.clone(),
+            Field::GeoDatabase => config.geoip.database_path.clone().unwrap_or_default(),
+            Field::GeoLicenseKey => config.geoip.license_key.clone().unwrap_or_default(),
+            Field::AlertCommands => command_patterns(config),
+            _ => String::new(),
+        }
+    }
+
+    /// Store typed `text` in `field`; empty text clears optional values
+    fn commit(&mut self, field: Field, text: String) {
+        let text = text.trim().to_string();
+        let optional = if text.is_empty() { None } else { Some(text.clone()) };
+        let config = &mut self.config;
+        match field {
+            Field::Name if !text.is_empty() => config.honeypot.name = text,
+            Field::DatabaseUrl => config.honeypot.database_url = optional,
+            Field::JournaldUnit if !text.is_empty() => config.honeypot.journald_unit = text,
+            Field::GeoDatabase => config.geoip.database_path = optional,
+            Field::GeoLicenseKey => config.geoip.license_key = optional,
+            Field::AlertCommands => {
+                // Patterns kept from before keep their severity and score
+                let previous = std::mem::take(&mut config.alert.on_commands);
+                config.alert.on_commands = text.split(',')
+                    .map(str::trim)
+                    .filter(|pattern| !pattern.is_empty())
+                    .map(|pattern| {
+                        previous.iter()
+                            .find(|alert| alert.pattern == pattern)
+                            .cloned()
+                            .unwrap_or_else(|| CommandAlert::new(pattern))
+                    })
+                    .collect();
+            }
+            Field::AddPath if !text.is_empty() => self.add_candidate(text),
+            _ => {}
+        }
+    }
+
+    /// Offer a typed or picked `path`, selected and checked; an existing candidate is selected instead
+    fn add_candidate(&mut self, path: String) {
+        match self.candidates.iter_mut().find(|candidate| candidate.path == path) {
+            Some(candidate) => candidate.selected = true,
+            None => self.candidates.push(Candidate {
+                check: LogCheck::run(&self.analyzer, Path::new(&path)),
+                path,
+                origin: "added".to_string(),
+                selected: true,
+            }),
+        }
+    }
+
+    /// Toggle, cycle, edit or run the highlighted field
+    fn activate(&mut self) -> WizardOutcome {
+        let field = match self.fields().get(self.cursor) {
+            Some(field) => *field,
+            None => return WizardOutcome::Continue,
+        };
+
+        let config = &mut self.config;
+        match field {
+            Field::HoneypotType => {
+                config.honeypot.honeypot_type = cycle(HoneypotConfig::TYPES, &config.honeypot.honeypot_type);
+            }
+            Field::Source => config.honeypot.source = cycle(HoneypotConfig::SOURCES, &config.honeypot.source),
+            Field::LogPath(index) => {
+                let candidate = &mut self.candidates[index];
+                candidate.selected = !candidate.selected;
+                candidate.check = LogCheck::run(&self.analyzer, Path::new(&candidate.path));
+            }
+            Field::Browse => self.picker = Some(DirPicker::open(self.browse_start(), &self.analyzer)),
+            Field::AutoDetect => config.honeypot.auto_detect = !config.honeypot.auto_detect,
+            Field::GeoEnabled => config.geoip.enabled = !config.geoip.enabled,
+            Field::GeoAutoDownload => config.geoip.auto_download = !config.geoip.auto_download,
+            Field::AlertsEnabled => config.alert.enabled = !config.alert.enabled,
+            Field::AlertLogin => config.alert.on_successful_login = !config.alert.on_successful_login,
+            Field::AlertUpload => config.alert.on_file_upload = !config.alert.on_file_upload,
+            Field::AlertNewIp => config.alert.on_new_source_ip = !config.alert.on_new_source_ip,
+            Field::Save => return self.save(),
+            _ => self.editing = Some((field, self.edit_text(field))),
+        }
+
+        WizardOutcome::Continue
+    }
+
+    /// Go straight to the log files page with the browser open
+    ///
+    /// Used by `b` on the settings tab, so a log can be picked without
+    /// stepping through the honeypot page first.
+    pub fn browse_for_log(&mut self) {
+        if let Some(step) = Step::ALL.iter().position(|step| *step == Step::LogPaths) {
+            self.go_to(step);
+        }
+        self.picker = Some(DirPicker::open(self.browse_start(), &self.analyzer));
+    }
+
+    /// Offer `path` if it looks like a Cowrie log, else say why not in the browser
+    ///
+    /// A directory stands for the `cowrie.json` inside it.
+    fn pick(&mut self, path: PathBuf) {
+        let path = if path.is_dir() { path.join(COWRIE_LOG_NAME) } else { path };
+        let check = LogCheck::run(&self.analyzer, &path);
+        match check.rejection() {
+            Some(reason) => {
+                if let Some(picker) = self.picker.as_mut() {
+                    picker.rejected = Some(format!("{}: {}", path.display(), reason));
+                }
+            }
+            None => {
+                self.picker = None;
+                self.add_candidate(path.display().to_string());
+            }
+        }
+    }
+
+    /// Where the browser opens: next to the first candidate, else /var/log, else home
+    fn browse_start(&self) -> PathBuf {
+        self.candidates.iter()
+            .filter_map(|candidate| Path::new(&candidate.path).parent())
+            .find(|dir| dir.is_dir())
+            .map(Path::to_path_buf)
+            .or_else(|| Some(PathBuf::from("/var/log")).filter(|dir| dir.is_dir()))
+            .or_else(dirs::home_dir)
+            .unwrap_or_else(|| PathBuf::from("/"))
+    }
+
+    /// Configuration with the selected log files, as it would be written
+    fn result(&self) -> Config {
+        let mut config = self.config.clone();
+        let paths: Vec<String> = self.candidates.iter()
+            .filter(|candidate| candidate.selected)
+            .map(|candidate| candidate.path.clone())
+            .collect();
+        config.honeypot.log_paths = if paths.is_empty() { None } else { Some(paths) };
+        config
+    }
+
+    /// Validate and write the configuration
+    fn save(&mut self) -> WizardOutcome {
+        let mut config = self.result();
+        if let Err(e) = config.validate() {
+            self.message = Some((format!("{:#}", e), true));
+            return WizardOutcome::Continue;
+        }
+        if let Err(e) = config.save(&self.path) {
+            self.message = Some((format!("{:#}", e), true));
+            return WizardOutcome::Continue;
+        }
+
+        config.source_path = Some(self.path.clone());
+        WizardOutcome::Saved(Box::new(config))
+    }
+
+    /// Move to another page, keeping the cursor on it
+    fn go_to(&mut self, step: usize) {
+        self.step = step.min(Step::ALL.len() - 1);
+        self.cursor = 0;
+        self.message = None;
+    }
+
+    /// Handle a key; the wizard takes every key while it is open
+    pub fn handle_key(&mut self, key: KeyEvent) -> WizardOutcome {
+        if let Some(picker) = self.picker.as_mut() {
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') => picker.move_cursor(-1, &self.analyzer),
+                KeyCode::Down | KeyCode::Char('j') => picker.move_cursor(1, &self.analyzer),
+                KeyCode::Backspace | KeyCode::Left => {
+                    if let Some(parent) = picker.dir.parent().map(Path::to_path_buf) {
+                        *picker = DirPicker::open(parent, &self.analyzer);
+                    }
+                }
+                KeyCode::Enter | KeyCode::Right => {
+                    if let Some(path) = picker.entries.get(picker.cursor).cloned() {
+                        if path.is_dir() {
+                            *picker = DirPicker::open(path, &self.analyzer);
+                        } else {
+                            self.pick(path);
+                        }
+                    }
+                }
+                KeyCode::Char(' ') => {
+                    if let Some(path) = picker.entries.get(picker.cursor).cloned() {
+                        self.pick(path);
+                    }
+                }
+                KeyCode::Esc => self.picker = None,
+                _ => {}
+            }
+            return WizardOutcome::Continue;
+        }
+
+        if let Some((field, text)) = self.editing.as_mut() {
+            match key.code {
+                KeyCode::Char(c) => text.push(c),
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Enter => {
+                    let (field, text) = (*field, std::mem::take(text));
+                    self.editing = None;
+                    self.commit(field, text);
+                }
+                KeyCode::Esc => self.editing = None,
+                _ => {}
+            }
+            return WizardOutcome::Continue;
+        }
+
+        let count = self.fields().len();
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.cursor = (self.cursor + 1).min(count.saturating_sub(1)),
+            KeyCode::Left | KeyCode::BackTab => self.go_to(self.step.saturating_sub(1)),
+            KeyCode::Right | KeyCode::Tab => self.go_to(self.step + 1),
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                let outcome = self.activate();
+                // Toggles can hide fields below the cursor
+                self.cursor = self.cursor.min(self.fields().len().saturating_sub(1));
+                return outcome;
+            }
+            KeyCode::Esc => return WizardOutcome::Cancelled,
+            _ => {}
+        }
+
+        WizardOutcome::Continue
+    }
+}
+
+/// `alert.on_commands` patterns as typed in the wizard, comma-separated
+fn command_patterns(config: &Config) -> String {
+    config.alert.on_commands.iter().map(|alert| alert.pattern.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+/// The value after `current` in `choices`, wrapping around
+fn cycle(choices: &[&str], current: &str) -> String {
+    let index = choices.iter().position(|choice| *choice == current).map_or(0, |index| index + 1);
+    choices[index % choices.len()].to_string()
+}
+
+/// Draw the wizard over `area`
+pub fn render_wizard(f: &mut Frame, wizard: &SetupWizard, area: Rect) {
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .title(format!(
+            "xKippo-tui setup - step {} of {}: {}",
+            wizard.step + 1,
+            Step::ALL.len(),
+            wizard.current_step().title()
+        ))
+        .borders(Borders::ALL);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Min(0),
+            Constraint::Length(4),
+        ].as_ref())
+        .split(inner);
+
+    // Step bar
+    let mut steps = Vec::new();
+    for (index, step) in Step::ALL.iter().enumerate() {
+        let style = if index == wizard.step {
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        steps.push(Span::styled(format!(" {}. {} ", index + 1, step.title()), style));
+    }
+    f.render_widget(Paragraph::new(Line::from(steps)), chunks[0]);
+
+    // Fields, with the summary and validation on the review page
+    let body = if wizard.current_step() == Step::Review {
+        let parts = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3)].as_ref())
+            .split(chunks[1]);
+        render_review(f, wizard, parts[0]);
+        parts[1]
+    } else {
+        chunks[1]
+    };
+    render_fields(f, wizard, body);
+
+    // Help and the last save error
+    let help = if wizard.picker.is_some() {
+        "↑/↓: move  Enter/→: open or pick  Space: pick (cowrie.json in a directory)  Backspace/←: parent directory  Esc: close"
+    } else if wizard.editing.is_some() {
+        "Type the value  Enter: keep  Esc: discard (empty clears optional values)"
+    } else {
+        "↑/↓: move  Enter/Space: toggle, change or edit  ←/→ (Tab): previous/next step  Esc: close without saving"
+    };
+    let mut footer = vec![Line::from(Span::styled(help, Style::default().fg(Color::DarkGray)))];
+    if let Some((message, failed)) = &wizard.message {
+        let color = if *failed { Color::Red } else { Color::Green };
+        footer.push(Line::from(Span::styled(message.clone(), Style::default().fg(color))));
+    }
+    f.render_widget(Paragraph::new(footer).wrap(Wrap { trim: true }), chunks[2]);
+
+    if let Some(picker) = &wizard.picker {
+        render_picker(f, picker, chunks[1]);
+    }
+}
+
+/// The current page's fields, the highlighted one being edited if it is
+fn render_fields(f: &mut Frame, wizard: &SetupWizard, area: Rect) {
+    let items: Vec<ListItem> = wizard.fields().into_iter()
+        .map(|field| {
+            let (label, value) = wizard.describe(field);
+            let mut spans = vec![Span::raw(escape_controls(&label).into_owned())];
+            match &wizard.editing {
+                Some((editing, text)) if *editing == field => {
+                    spans.push(Span::raw(": "));
+                    spans.push(Span::styled(escape_controls(text).into_owned(), Style::default().fg(Color::Cyan)));
+                    spans.push(Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)));
+                }
+                _ if value.is_empty() => {}
+                _ => {
+                    let style = match field {
+                        Field::LogPath(index) => wizard.candidates[index].check.style(),
+                        _ => Style::default().fg(Color::Cyan),
+                    };
+                    spans.push(Span::raw(": "));
+                    spans.push(Span::styled(escape_controls(&value).into_owned(), style));
+                }
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::TOP))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default();
+    state.select(Some(wizard.cursor));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+/// Summary of what will be written, and whether it validates
+fn render_review(f: &mut Frame, wizard: &SetupWizard, area: Rect) {
+    let config = wizard.result();
+    let label = |text: &str| Span::styled(format!("{}: ", text), Style::default().fg(Color::Yellow));
+    let mut lines = vec![
+        Line::from(vec![label("Honeypot"), Span::raw(format!("{} ({})", config.honeypot.name, config.honeypot.honeypot_type))]),
+    ];
+
+    if config.honeypot.source == "mysql" {
+        lines.push(Line::from(vec![label("Source"), Span::raw("MySQL database")]));
+    } else if config.honeypot.source == "journald" {
+        lines.push(Line::from(vec![label("Source"), Span::raw(format!("journal of {}", config.honeypot.journald_unit))]));
+    } else {
+        let paths = config.honeypot.log_paths.clone().unwrap_or_default();
+        lines.push(Line::from(vec![
+            label("Log files"),
+            Span::raw(if paths.is_empty() { "(none)".to_string() } else { paths.join(", ") }),
+        ]));
+    }
+
+    lines.push(Line::from(vec![
+        label("GeoIP"),
+        Span::raw(match (config.geoip.enabled, config.geoip.auto_download) {
+            (false, _) => "off",
+            (true, true) => "on, downloading the database if missing",
+            (true, false) => "on",
+        }),
+    ]));
+    lines.push(Line::from(vec![label("Alerts"), Span::raw(if config.alert.enabled { "on" } else { "off" })]));
+    lines.push(Line::from(""));
+
+    match config.validate() {
+        Ok(()) => lines.push(Line::from(Span::styled("Ready to save.", Style::default().fg(Color::Green)))),
+        Err(e) => {
+            for line in format!("{:#}", e).lines() {
+                lines.push(Line::from(Span::styled(line.to_string(), Style::default().fg(Color::Red))));
+            }
+        }
+    }
+
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: true }), area);
+}
+
+/// Directory browser popup over `area`, with the highlighted file's check below the list
+fn render_picker(f: &mut Frame, picker: &DirPicker, area: Rect) {
+    let popup = Rect::new(
+        area.x + area.width / 10,
+        area.y,
+        area.width - area.width / 5,
+        area.height,
+    );
+    f.render_widget(Clear, popup);
+
+    let block = Block::default()
+        .title(format!("Pick a log file - {}", picker.dir.display()))
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+    let inner = block.inner(popup);
+    f.render_widget(block, popup);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(2)].as_ref())
+        .split(inner);
+
+    let items: Vec<ListItem> = (0..picker.entries.len())
+        .map(|index| ListItem::new(escape_controls(&picker.label(index)).into_owned()))
+        .collect();
+    let list = List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default();
+    if !picker.entries.is_empty() {
+        state.select(Some(picker.cursor));
+    }
+    f.render_stateful_widget(list, chunks[0], &mut state);
+
+    let preview = match (&picker.rejected, &picker.preview) {
+        (Some(reason), _) => Span::styled(reason.clone(), Style::default().fg(Color::Red)),
+        (None, Some(check)) => Span::styled(check.describe(), check.style()),
+        (None, None) => Span::styled("Highlight a file to check that it parses", Style::default().fg(Color::DarkGray)),
+    };
+    f.render_widget(
+        Paragraph::new(Line::from(preview)).block(Block::default().borders(Borders::TOP)).wrap(Wrap { trim: true }),
+        chunks[1],
+    );
+}
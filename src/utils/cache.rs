@@ -0,0 +1,136 @@
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A value recomputed at most once per time-to-live, or when invalidated
+///
+/// Lookups take `&self`, so a cache can sit in state that renderers only
+/// borrow immutably.
+pub struct TimedCache<T> {
+    /// How long a computed value is served before it is recomputed
+    ttl: Duration,
+    /// The value and when it was computed
+    entry: Mutex<Option<(Instant, Arc<T>)>>,
+}
+
+impl<T> TimedCache<T> {
+    /// Empty cache whose values live for `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entry: Mutex::new(None),
+        }
+    }
+
+    /// The cached value and its age, computing it first if it is missing or expired
+    pub fn get_or_compute(&self, compute: impl FnOnce() -> T) -> (Arc<T>, Duration) {
+        let mut entry = self.entry.lock();
+        match entry.as_ref() {
+            Some((computed_at, value)) if computed_at.elapsed() < self.ttl => {
+                (Arc::clone(value), computed_at.elapsed())
+            }
+            _ => {
+                let value = Arc::new(compute());
+                *entry = Some((Instant::now(), Arc::clone(&value)));
+                (value, Duration::from_secs(0))
+            }
+        }
+    }
+
+    /// Drop the cached value so the next lookup recomputes it
+    pub fn invalidate(&self) {
+        *self.entry.lock() = None;
+    }
+}
+
+/// A value recomputed whenever the key it was computed for changes, or when invalidated
+///
+/// For views derived from a snapshot and the filters on it: the key holds
+/// both, and the value stays until either changes.
+pub struct KeyedCache<K, T> {
+    /// The value and the key it was computed for
+    entry: Mutex<Option<(K, Arc<T>)>>,
+}
+
+impl<K, T> Default for KeyedCache<K, T> {
+    fn default() -> Self {
+        Self { entry: Mutex::new(None) }
+    }
+}
+
+impl<K: PartialEq, T> KeyedCache<K, T> {
+    /// The value for `key`, computing it first if the cached one is for another key
+    pub fn get_or_compute(&self, key: K, compute: impl FnOnce() -> T) -> Arc<T> {
+        let mut entry = self.entry.lock();
+        match entry.as_ref() {
+            Some((cached, value)) if *cached == key => Arc::clone(value),
+            _ => {
+                let value = Arc::new(compute());
+                *entry = Some((key, Arc::clone(&value)));
+                value
+            }
+        }
+    }
+
+    /// Drop the cached value so the next lookup recomputes it
+    pub fn invalidate(&self) {
+        *self.entry.lock() = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::cell::Cell;
+
+    #[test]
+    fn values_are_served_until_they_expire() {
+        let cache = TimedCache::new(Duration::from_millis(50));
+        let computed = Cell::new(0);
+        let compute = || {
+            computed.set(computed.get() + 1);
+            computed.get()
+        };
+
+        assert_eq!(*cache.get_or_compute(compute).0, 1);
+        std::thread::sleep(Duration::from_millis(10));
+        let (value, age) = cache.get_or_compute(compute);
+        assert_eq!(*value, 1);
+        assert!(age >= Duration::from_millis(10), "{:?}", age);
+
+        std::thread::sleep(Duration::from_millis(50));
+        let (value, age) = cache.get_or_compute(compute);
+        assert_eq!((*value, age), (2, Duration::from_secs(0)));
+    }
+
+    #[test]
+    fn a_zero_ttl_recomputes_every_time() {
+        let cache = TimedCache::new(Duration::from_secs(0));
+        assert_eq!(*cache.get_or_compute(|| 1).0, 1);
+        assert_eq!(*cache.get_or_compute(|| 2).0, 2);
+    }
+
+    #[test]
+    fn invalidating_forces_a_recompute() {
+        let cache = TimedCache::new(Duration::from_secs(3600));
+        assert_eq!(*cache.get_or_compute(|| "before").0, "before");
+        assert_eq!(*cache.get_or_compute(|| "unused").0, "before");
+
+        cache.invalidate();
+        let (value, age) = cache.get_or_compute(|| "after");
+        assert_eq!((*value, age), ("after", Duration::from_secs(0)));
+    }
+
+    #[test]
+    fn keyed_values_last_until_the_key_changes() {
+        let cache = KeyedCache::default();
+        assert_eq!(*cache.get_or_compute(("snapshot 1", "ssh"), || 10), 10);
+        assert_eq!(*cache.get_or_compute(("snapshot 1", "ssh"), || 11), 10);
+        assert_eq!(*cache.get_or_compute(("snapshot 2", "ssh"), || 12), 12);
+        assert_eq!(*cache.get_or_compute(("snapshot 2", "telnet"), || 13), 13);
+
+        cache.invalidate();
+        assert_eq!(*cache.get_or_compute(("snapshot 2", "telnet"), || 14), 14);
+    }
+}
@@ -4,6 +4,7 @@ use std::io;
 
 /// Application error types
 #[derive(Debug)]
+#[allow(dead_code)]
 pub enum AppError {
     /// IO errors
     IoError(io::Error),
@@ -1,16 +1,18 @@
 use anyhow::Result;
 use log::debug;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::fs;
 
 /// Helper functions for xKippo-TUI
 
 /// Check if a path exists
+#[allow(dead_code)]
 pub fn path_exists(path: &str) -> bool {
     Path::new(path).exists()
 }
 
 /// Create directory if it doesn't exist
+#[allow(dead_code)]
 pub fn ensure_directory(path: &str) -> Result<()> {
     let path = Path::new(path);
     if !path.exists() {
@@ -20,7 +22,19 @@ pub fn ensure_directory(path: &str) -> Result<()> {
     Ok(())
 }
 
+/// Expand a leading `~/` to the local home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix("~/") {
+        if let Some(home) = dirs::home_dir() {
+            return home.join(rest);
+        }
+    }
+
+    PathBuf::from(path)
+}
+
 /// Format bytes to human-readable string (KB, MB, GB)
+#[allow(dead_code)]
 pub fn format_bytes(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
@@ -37,6 +51,16 @@ pub fn format_bytes(bytes: u64) -> String {
     }
 }
 
+/// Format duration in seconds as its largest unit only ("45s", "31m", "2h", "3d")
+pub fn format_duration_short(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m", seconds / 60),
+        3600..=86399 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
+
 /// Format duration in seconds to human-readable string
 pub fn format_duration(seconds: u64) -> String {
     let days = seconds / 86400;
@@ -53,4 +77,51 @@ pub fn format_duration(seconds: u64) -> String {
     } else {
         format!("{}s", seconds)
     }
-}
\ No newline at end of file
+}
+
+/// Draw `values` as a unicode sparkline at most `width` characters wide
+///
+/// Longer series keep the last value of each stretch they are squeezed into,
+/// which suits running totals. Heights are scaled to the largest value.
+pub fn sparkline(values: &[u64], width: usize) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let points = values.len().min(width);
+    let max = values.iter().copied().max().unwrap_or(0);
+    (1..=points)
+        .map(|point| {
+            let value = values[point * values.len() / points - 1];
+            let level = if max == 0 { 0 } else { (value * 7 / max) as usize };
+            BARS[level]
+        })
+        .collect()
+}
+/// Decode standard base64, padded or not; `None` if `text` isn't valid base64
+pub fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    // A lone trailing character can't encode a whole byte
+    if bits == 6 {
+        return None;
+    }
+    Some(bytes)
+}
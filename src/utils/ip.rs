@@ -0,0 +1,46 @@
+use ipnetwork::IpNetwork;
+use log::warn;
+use std::net::IpAddr;
+
+/// Addresses and CIDR ranges from a config list such as `alert.ip_whitelist`
+#[derive(Debug, Clone, Default)]
+pub struct IpList {
+    networks: Vec<IpNetwork>,
+}
+
+impl IpList {
+    /// Parse `entries`, skipping (with a warning) any that aren't an address or CIDR range
+    pub fn parse(entries: &[String]) -> Self {
+        let networks = entries
+            .iter()
+            .filter_map(|entry| match entry.trim().parse::<IpNetwork>() {
+                Ok(network) => Some(network),
+                Err(e) => {
+                    warn!("Ignoring invalid IP or CIDR range '{}': {}", entry, e);
+                    None
+                }
+            })
+            .collect();
+
+        Self { networks }
+    }
+
+    /// Whether `ip` is listed or falls in a listed range
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.networks.iter().any(|network| network.contains(ip))
+    }
+
+    /// Like [`IpList::contains`] for an address as logged; unparseable ones never match
+    pub fn matches(&self, ip: &str) -> bool {
+        ip.parse().map_or(false, |ip| self.contains(ip))
+    }
+}
+
+/// Entries of `entries` that are neither an address nor a CIDR range
+pub fn invalid_entries(entries: &[String]) -> Vec<&str> {
+    entries
+        .iter()
+        .filter(|entry| entry.trim().parse::<IpNetwork>().is_err())
+        .map(String::as_str)
+        .collect()
+}
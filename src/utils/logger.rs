@@ -1,130 +1,277 @@
-use std::fs::{File, OpenOptions};
-use std::io::Write;
-use std::path::Path;
-use std::sync::{Arc, Mutex};
+//! Application log, configured by `[logging]`
+//!
+//! The logger is installed first thing, before the configuration is read;
+//! records logged until [`configure`] opens the log file are held back and
+//! written once it does. The file is rotated by size, keeping
+//! `logging.max_files` files in all. Records go to stderr only with
+//! `logging.console = true`, and never while the TUI owns the terminal.
+//! The most recent lines are also kept in memory for the log viewer (F12).
+
+use anyhow::{Context, Result};
 use chrono::Local;
-use log::{Level, LevelFilter, Metadata, Record, SetLoggerError};
+use log::{LevelFilter, Log, Metadata, Record};
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::config::LoggingConfig;
+use crate::utils::helpers::expand_home;
+
+/// Lines the log viewer can scroll back through
+const RECENT_LINES: usize = 2000;
+
+/// Records held back before the log file opens; older ones are dropped
+const MAX_PENDING: usize = 1000;
 
-/// Custom logger for the application
+/// The installed logger
+static LOGGER: OnceLock<AppLogger> = OnceLock::new();
+
+/// Logger for the whole application
 struct AppLogger {
-    /// Log file handle
-    file: Option<Arc<Mutex<File>>>,
-    /// Log level
-    level: Level,
+    /// Everything that changes, behind one lock so lines keep their order
+    state: Mutex<LoggerState>,
+}
+
+/// Where records go, and what has been logged
+struct LoggerState {
+    /// Most verbose level logged
+    level: LevelFilter,
+    /// Log file, once configured (`logging.file_path`)
+    file: Option<RotatingFile>,
+    /// Whether [`configure`] has run; until then records are held back
+    configured: bool,
+    /// Records logged before the configuration was applied
+    pending: VecDeque<String>,
+    /// Copy records to stderr (`logging.console`)
+    console: bool,
+    /// The TUI owns the terminal, so stderr is off limits
+    tui_active: bool,
+    /// Most recent lines, oldest first, for the log viewer
+    recent: VecDeque<String>,
 }
 
-impl AppLogger {
-    /// Create a new logger instance
-    pub fn new(log_file_path: Option<&Path>, level: Level) -> Result<Self, std::io::Error> {
-        let file = match log_file_path {
-            Some(path) => {
-                // Create parent directories if they don't exist
-                if let Some(parent) = path.parent() {
-                    std::fs::create_dir_all(parent)?;
-                }
-                
-                // Open log file
-                let file = OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .open(path)?;
-                
-                Some(Arc::new(Mutex::new(file)))
+/// Log file that is rotated once it reaches its size limit
+struct RotatingFile {
+    /// Path of the current file; older ones get `.1`, `.2`, ... appended
+    path: PathBuf,
+    /// Open current file
+    file: File,
+    /// Bytes in the current file
+    size: u64,
+    /// Size that triggers rotation, in bytes (0 = never)
+    max_size: u64,
+    /// Files kept in all, the current one included
+    max_files: u32,
+}
+
+impl RotatingFile {
+    /// Open `path` for appending, creating it and its directory if needed
+    fn open(path: PathBuf, max_size: u64, max_files: u32) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .context(format!("Failed to create directory: {}", parent.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .context(format!("Failed to open log file: {}", path.display()))?;
+        let size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+
+        Ok(Self { path, file, size, max_size, max_files })
+    }
+
+    /// Append `line`, rotating first if it would take the file past its limit
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        if self.max_size > 0 && self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shift `path.1` to `path.2` and so on, dropping the oldest, and start a new file
+    fn rotate(&mut self) -> io::Result<()> {
+        let kept = self.max_files.saturating_sub(1);
+        if kept > 0 {
+            let _ = fs::remove_file(numbered(&self.path, kept));
+            for n in (1..kept).rev() {
+                let _ = fs::rename(numbered(&self.path, n), numbered(&self.path, n + 1));
             }
-            None => None,
-        };
-        
-        Ok(Self {
-            file,
-            level,
-        })
+            fs::rename(&self.path, numbered(&self.path, 1))?;
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
     }
 }
 
-impl log::Log for AppLogger {
+/// `path` with `.n` appended
+fn numbered(path: &Path, n: u32) -> PathBuf {
+    let mut numbered = path.as_os_str().to_owned();
+    numbered.push(format!(".{}", n));
+    PathBuf::from(numbered)
+}
+
+impl LoggerState {
+    /// Send a formatted line wherever it belongs
+    fn write(&mut self, line: String) {
+        if !self.configured {
+            if self.pending.len() == MAX_PENDING {
+                self.pending.pop_front();
+            }
+            self.pending.push_back(line);
+            return;
+        }
+
+        if let Some(file) = &mut self.file {
+            // Nowhere left to report a failing log file; the viewer still has the line
+            let _ = file.write(&line);
+        }
+        if self.console && !self.tui_active {
+            eprint!("{}", line);
+        }
+
+        if self.recent.len() == RECENT_LINES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(line);
+    }
+}
+
+impl Log for AppLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= self.level
+        metadata.level() <= self.state.lock().level
     }
-    
+
     fn log(&self, record: &Record) {
-        if self.enabled(record.metadata()) {
-            let now = Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
-            let message = format!("[{} {} {}] {}\n", 
-                now, 
-                record.level(),
-                record.target(),
-                record.args()
-            );
-            
-            // Write to stderr
-            eprint!("{}", message);
-            
-            // Write to file if available
-            if let Some(ref file) = self.file {
-                if let Ok(mut file) = file.lock() {
-                    let _ = file.write_all(message.as_bytes());
-                }
-            }
+        if !self.enabled(record.metadata()) {
+            return;
         }
+
+        let line = format!(
+            "[{} {} {}] {}\n",
+            Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        self.state.lock().write(line);
     }
-    
+
     fn flush(&self) {
-        if let Some(ref file) = self.file {
-            if let Ok(mut file) = file.lock() {
-                let _ = file.flush();
-            }
+        if let Some(file) = &mut self.state.lock().file {
+            let _ = file.file.flush();
         }
     }
 }
 
-/// Global logger instance
-static mut LOGGER: Option<AppLogger> = None;
-
-/// Initialize the logger
-pub fn init_logger() -> Result<(), SetLoggerError> {
-    // Default log level
-    let level = LevelFilter::Info;
-    
-    unsafe {
-        // Create logger instance
-        LOGGER = match AppLogger::new(None, Level::Info) {
-            Ok(logger) => Some(logger),
-            Err(_) => {
-                eprintln!("Failed to initialize logger, falling back to stderr only");
-                Some(AppLogger::new(None, Level::Info).unwrap())
-            }
-        };
-        
-        // Set the global logger
-        if let Some(ref logger) = LOGGER {
-            log::set_max_level(level);
-            log::set_logger(logger)?;
-        }
-    }
-    
+/// Install the logger, holding records back until [`configure`]
+///
+/// Everything is held at first; `configure` drops what its level excludes.
+pub fn init() -> Result<()> {
+    let logger = LOGGER.get_or_init(|| AppLogger {
+        state: Mutex::new(LoggerState {
+            level: LevelFilter::Trace,
+            file: None,
+            configured: false,
+            pending: VecDeque::new(),
+            console: false,
+            tui_active: false,
+            recent: VecDeque::new(),
+        }),
+    });
+    log::set_logger(logger).map_err(|e| anyhow::anyhow!("Failed to install logger: {}", e))?;
+    log::set_max_level(LevelFilter::Trace);
     Ok(())
 }
 
-/// Initialize the logger with a custom file and level
-pub fn init_logger_with_file(log_file_path: &Path, level: Level) -> Result<(), Box<dyn std::error::Error>> {
-    let level_filter = match level {
-        Level::Error => LevelFilter::Error,
-        Level::Warn => LevelFilter::Warn,
-        Level::Info => LevelFilter::Info,
-        Level::Debug => LevelFilter::Debug,
-        Level::Trace => LevelFilter::Trace,
+/// Apply `[logging]`, then write out what was held back
+///
+/// `verbosity` is the number of `-v` flags: one raises the level to debug,
+/// more to trace. If the log file can't be opened, logging carries on
+/// without it and the error is returned.
+pub fn configure(config: &LoggingConfig, verbosity: u8) -> Result<()> {
+    let logger = match LOGGER.get() {
+        Some(logger) => logger,
+        None => return Ok(()),
+    };
+
+    let configured = LevelFilter::from_str(&config.level).unwrap_or(LevelFilter::Info);
+    let level = match (config.enabled, verbosity) {
+        (false, _) => LevelFilter::Off,
+        (true, 0) => configured,
+        (true, 1) => configured.max(LevelFilter::Debug),
+        (true, _) => LevelFilter::Trace,
+    };
+
+    let file = match (&config.file_path, config.enabled) {
+        (Some(path), true) => Some(RotatingFile::open(
+            expand_home(path),
+            config.max_file_size.saturating_mul(1024 * 1024),
+            config.max_files.max(1),
+        )),
+        _ => None,
+    };
+
+    let mut state = logger.state.lock();
+    let (file, error) = match file {
+        Some(Ok(file)) => (Some(file), None),
+        Some(Err(e)) => (None, Some(e)),
+        None => (None, None),
     };
-    
-    unsafe {
-        // Create logger instance
-        LOGGER = Some(AppLogger::new(Some(log_file_path), level)?);
-        
-        // Set the global logger
-        if let Some(ref logger) = LOGGER {
-            log::set_max_level(level_filter);
-            log::set_logger(logger)?;
+    state.level = level;
+    state.file = file;
+    state.console = config.console;
+    state.configured = true;
+    log::set_max_level(level);
+
+    // Held-back records carry their level only in the text; filter on it
+    let pending = std::mem::take(&mut state.pending);
+    for line in pending {
+        if line_level(&line).map_or(true, |line_level| line_level <= level) {
+            state.write(line);
         }
     }
-    
-    Ok(())
-}
\ No newline at end of file
+    drop(state);
+
+    match error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Level of a line formatted by [`AppLogger::log`]
+fn line_level(line: &str) -> Option<LevelFilter> {
+    let level = line.split_whitespace().nth(2)?;
+    LevelFilter::from_str(level).ok()
+}
+
+/// Note whether the TUI owns the terminal, which keeps records off stderr
+pub fn set_tui_active(active: bool) {
+    if let Some(logger) = LOGGER.get() {
+        logger.state.lock().tui_active = active;
+    }
+}
+
+/// Most recent log lines, oldest first, with multi-line records split up
+pub fn recent_lines() -> Vec<String> {
+    match LOGGER.get() {
+        Some(logger) => logger.state.lock()
+            .recent
+            .iter()
+            .flat_map(|record| record.lines().map(str::to_string))
+            .collect(),
+        None => Vec::new(),
+    }
+}
@@ -1,3 +1,6 @@
 pub mod logger;
+pub mod cache;
 pub mod errors;
-pub mod helpers;
\ No newline at end of file
+pub mod helpers;
+pub mod ip;
+pub mod time;
\ No newline at end of file
@@ -0,0 +1,359 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Local, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use log::warn;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::UIConfig;
+
+/// Columns reserved for a relative time, enough for "23h 59m ago"
+const RELATIVE_WIDTH: usize = 11;
+
+/// Zone timestamps are shown and entered in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayZone {
+    /// The machine's local zone
+    Local,
+    /// UTC
+    Utc,
+    /// An IANA zone such as `Europe/Berlin`
+    Named(Tz),
+}
+
+impl FromStr for DisplayZone {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "local" | "" => Ok(DisplayZone::Local),
+            "utc" => Ok(DisplayZone::Utc),
+            _ => s.parse::<Tz>()
+                .map(DisplayZone::Named)
+                .map_err(|_| anyhow!("Unknown timezone '{}' (use local, utc or an IANA name)", s)),
+        }
+    }
+}
+
+impl fmt::Display for DisplayZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisplayZone::Local => write!(f, "local"),
+            DisplayZone::Utc => write!(f, "utc"),
+            DisplayZone::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+/// Formats and parses timestamps in `ui.timezone` with `ui.date_format`/`ui.time_format`
+///
+/// Every panel goes through one of these so the whole UI agrees on the zone.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    zone: DisplayZone,
+    date_format: String,
+    time_format: String,
+    /// Columns a formatted time of day takes
+    time_width: usize,
+    /// Show recent times as "12s ago" instead of a time of day
+    relative: bool,
+    /// Times older than this are shown absolute even in relative mode
+    relative_cutoff: Duration,
+}
+
+impl Clock {
+    /// Create a clock for `zone` with the given strftime formats
+    pub fn new(zone: DisplayZone, date_format: &str, time_format: &str) -> Self {
+        Self {
+            zone,
+            date_format: date_format.to_string(),
+            time_format: time_format.to_string(),
+            time_width: Utc::now().format(time_format).to_string().chars().count(),
+            relative: false,
+            relative_cutoff: Duration::hours(24),
+        }
+    }
+
+    /// Clock for the UI settings, falling back to local time on an unknown zone
+    pub fn from_config(config: &UIConfig) -> Self {
+        let zone = config.timezone.parse().unwrap_or_else(|e| {
+            warn!("{}; showing local time", e);
+            DisplayZone::Local
+        });
+
+        let mut clock = Self::new(zone, &config.date_format, &config.time_format);
+        clock.relative = config.relative_times;
+        clock.relative_cutoff = Duration::hours(config.relative_cutoff_hours as i64);
+        clock
+    }
+
+    /// Whether recent times are shown relative to now
+    pub fn relative(&self) -> bool {
+        self.relative
+    }
+
+    /// Switch between relative and absolute times
+    pub fn set_relative(&mut self, relative: bool) {
+        self.relative = relative;
+    }
+
+    /// Time of day, e.g. for table columns
+    pub fn time(&self, timestamp: &DateTime<Utc>) -> String {
+        self.format(timestamp, &self.time_format)
+    }
+
+    /// Time for a table column: "12s ago" in relative mode, else the time of day
+    ///
+    /// Padded to [`Clock::column_width`] so columns don't jitter as values change.
+    /// Recomputed on every draw, so relative values stay current.
+    pub fn when(&self, timestamp: &DateTime<Utc>) -> String {
+        let elapsed = Utc::now() - *timestamp;
+        let text = if self.relative && elapsed < self.relative_cutoff {
+            humanize_ago(elapsed)
+        } else {
+            self.time(timestamp)
+        };
+
+        format!("{:>width$}", text, width = self.column_width())
+    }
+
+    /// Columns needed for a value from [`Clock::when`]
+    pub fn column_width(&self) -> usize {
+        if self.relative {
+            self.time_width.max(RELATIVE_WIDTH)
+        } else {
+            self.time_width
+        }
+    }
+
+    /// Full date and time with the zone abbreviation, e.g. for detail panes
+    pub fn date_time(&self, timestamp: &DateTime<Utc>) -> String {
+        self.format(timestamp, &format!("{} {} %Z", self.date_format, self.time_format))
+    }
+
+    /// Current date and time, for the status bar
+    pub fn now(&self) -> String {
+        self.date_time(&Utc::now())
+    }
+
+    /// Format `timestamp` with a strftime pattern in the display zone
+    pub fn format(&self, timestamp: &DateTime<Utc>, pattern: &str) -> String {
+        match self.zone {
+            DisplayZone::Local => timestamp.with_timezone(&Local).format(pattern).to_string(),
+            DisplayZone::Utc => timestamp.format(pattern).to_string(),
+            DisplayZone::Named(tz) => timestamp.with_timezone(&tz).format(pattern).to_string(),
+        }
+    }
+
+    /// Parse a date and time entered in the display zone, e.g. a time-range filter bound
+    ///
+    /// Accepts `date_format time_format` or `date_format` alone (midnight).
+    /// A time repeated by a DST change resolves to its first occurrence; a
+    /// time skipped by one is an error.
+    pub fn parse(&self, input: &str) -> Result<DateTime<Utc>> {
+        let input = input.trim();
+        let pattern = format!("{} {}", self.date_format, self.time_format);
+
+        let naive = NaiveDateTime::parse_from_str(input, &pattern)
+            .or_else(|_| {
+                NaiveDate::parse_from_str(input, &self.date_format)
+                    .map(|date| date.and_hms_opt(0, 0, 0).expect("midnight is a valid time"))
+            })
+            .map_err(|_| anyhow!("Expected '{}' or '{}', got '{}'", pattern, self.date_format, input))?;
+
+        let resolved = match self.zone {
+            DisplayZone::Local => to_utc(Local.from_local_datetime(&naive)),
+            DisplayZone::Utc => Some(Utc.from_utc_datetime(&naive)),
+            DisplayZone::Named(tz) => to_utc(tz.from_local_datetime(&naive)),
+        };
+
+        resolved.ok_or_else(|| anyhow!("{} does not exist in {} (skipped by a DST change)", input, self.zone))
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::from_config(&UIConfig::default())
+    }
+}
+
+/// How long ago `elapsed` was, in at most two units: "just now", "12s ago", "3m ago", "4h 03m ago", "2d 05h ago"
+///
+/// Negative durations (timestamps slightly ahead of this machine's clock) read as "just now".
+pub fn humanize_ago(elapsed: Duration) -> String {
+    let seconds = elapsed.num_seconds();
+    if seconds < 1 {
+        return "just now".to_string();
+    }
+
+    let (days, hours, minutes) = (seconds / 86_400, seconds / 3_600 % 24, seconds / 60 % 60);
+    if days > 0 {
+        format!("{}d {:02}h ago", days, hours)
+    } else if hours > 0 {
+        format!("{}h {:02}m ago", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m ago", minutes)
+    } else {
+        format!("{}s ago", seconds)
+    }
+}
+
+/// Earliest UTC instant a local time maps to, if any
+fn to_utc<Z: TimeZone>(local: LocalResult<DateTime<Z>>) -> Option<DateTime<Utc>> {
+    local.earliest().map(|time| time.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn berlin() -> Clock {
+        Clock::new(DisplayZone::Named(chrono_tz::Europe::Berlin), "%Y-%m-%d", "%H:%M:%S")
+    }
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn formats_in_the_configured_zone() {
+        let timestamp = utc("2024-01-15T12:00:00Z");
+        let utc_clock = Clock::new(DisplayZone::Utc, "%Y-%m-%d", "%H:%M:%S");
+        let new_york = Clock::new(DisplayZone::Named(chrono_tz::America::New_York), "%d/%m/%Y", "%H:%M");
+
+        assert_eq!(utc_clock.date_time(&timestamp), "2024-01-15 12:00:00 UTC");
+        assert_eq!(berlin().date_time(&timestamp), "2024-01-15 13:00:00 CET");
+        assert_eq!(new_york.date_time(&timestamp), "15/01/2024 07:00 EST");
+    }
+
+    #[test]
+    fn formats_across_a_dst_change() {
+        let clock = berlin();
+
+        assert_eq!(clock.date_time(&utc("2024-03-31T00:59:59Z")), "2024-03-31 01:59:59 CET");
+        assert_eq!(clock.date_time(&utc("2024-03-31T01:00:00Z")), "2024-03-31 03:00:00 CEST");
+        assert_eq!(clock.date_time(&utc("2024-10-27T00:30:00Z")), "2024-10-27 02:30:00 CEST");
+        assert_eq!(clock.date_time(&utc("2024-10-27T01:30:00Z")), "2024-10-27 02:30:00 CET");
+    }
+
+    /// Clock for `ui` settings with `timezone` and formats changed
+    fn configured(timezone: &str, date_format: &str, time_format: &str) -> Clock {
+        Clock::from_config(&UIConfig {
+            timezone: timezone.to_string(),
+            date_format: date_format.to_string(),
+            time_format: time_format.to_string(),
+            ..UIConfig::default()
+        })
+    }
+
+    #[test]
+    fn zones_are_keywords_or_iana_names() {
+        assert_eq!("UTC".parse::<DisplayZone>().unwrap(), DisplayZone::Utc);
+        assert_eq!("Local".parse::<DisplayZone>().unwrap(), DisplayZone::Local);
+        assert_eq!("".parse::<DisplayZone>().unwrap(), DisplayZone::Local);
+        assert_eq!("Asia/Kolkata".parse::<DisplayZone>().unwrap(), DisplayZone::Named(chrono_tz::Asia::Kolkata));
+        for zone in ["local", "utc", "Australia/Sydney"] {
+            assert_eq!(zone.parse::<DisplayZone>().unwrap().to_string(), zone);
+        }
+        assert_eq!(
+            "Mars/Olympus_Mons".parse::<DisplayZone>().unwrap_err().to_string(),
+            "Unknown timezone 'Mars/Olympus_Mons' (use local, utc or an IANA name)"
+        );
+    }
+
+    #[test]
+    fn the_ui_settings_pick_zone_and_formats() {
+        let timestamp = utc("2024-01-15T12:00:00Z");
+        let kolkata = configured("Asia/Kolkata", "%d.%m.%Y", "%H:%M");
+        // Summer down under: daylight time in January
+        let sydney = configured("Australia/Sydney", "%Y/%m/%d", "%I:%M %p");
+
+        assert_eq!(kolkata.date_time(&timestamp), "15.01.2024 17:30 IST");
+        assert_eq!(kolkata.time(&timestamp), "17:30");
+        assert_eq!(sydney.date_time(&timestamp), "2024/01/15 11:00 PM AEDT");
+        assert_eq!(sydney.date_time(&utc("2024-07-15T12:00:00Z")), "2024/07/15 10:00 PM AEST");
+        assert_eq!(kolkata.parse("15.01.2024 17:30").unwrap(), timestamp);
+    }
+
+    #[test]
+    fn unknown_zones_fail_validation_and_show_local_time() {
+        let mut config = crate::config::Config::default();
+        config.ui.timezone = "Europe/Atlantis".to_string();
+        let problems = config.validate().unwrap_err().to_string();
+        assert!(problems.contains("ui.timezone: Unknown timezone 'Europe/Atlantis'"), "{}", problems);
+
+        let timestamp = utc("2024-01-15T12:00:00Z");
+        let clock = configured("Europe/Atlantis", "%Y-%m-%d", "%H:%M:%S");
+        assert_eq!(clock.time(&timestamp), timestamp.with_timezone(&Local).format("%H:%M:%S").to_string());
+    }
+
+    #[test]
+    fn parses_in_the_configured_zone() {
+        let clock = berlin();
+
+        assert_eq!(clock.parse("2024-01-15 13:00:00").unwrap(), utc("2024-01-15T12:00:00Z"));
+        assert_eq!(clock.parse(" 2024-07-01 ").unwrap(), utc("2024-06-30T22:00:00Z"));
+        assert!(clock.parse("15.01.2024").is_err());
+    }
+
+    #[test]
+    fn parse_resolves_dst_gaps_and_repeats() {
+        let clock = berlin();
+
+        // 02:30 never happens on the spring change
+        assert!(clock.parse("2024-03-31 02:30:00").is_err());
+        // and happens twice on the autumn one; the first is taken
+        assert_eq!(clock.parse("2024-10-27 02:30:00").unwrap(), utc("2024-10-27T00:30:00Z"));
+    }
+
+    #[test]
+    fn humanizes_elapsed_time() {
+        assert_eq!(humanize_ago(Duration::seconds(-5)), "just now");
+        assert_eq!(humanize_ago(Duration::seconds(12)), "12s ago");
+        assert_eq!(humanize_ago(Duration::minutes(3)), "3m ago");
+        assert_eq!(humanize_ago(Duration::minutes(243)), "4h 03m ago");
+        assert_eq!(humanize_ago(Duration::hours(53)), "2d 05h ago");
+    }
+
+    #[test]
+    fn humanizes_edge_durations() {
+        assert_eq!(humanize_ago(Duration::zero()), "just now");
+        assert_eq!(humanize_ago(Duration::milliseconds(999)), "just now");
+        assert_eq!(humanize_ago(Duration::seconds(1)), "1s ago");
+        assert_eq!(humanize_ago(Duration::seconds(59)), "59s ago");
+        assert_eq!(humanize_ago(Duration::seconds(60)), "1m ago");
+        assert_eq!(humanize_ago(Duration::seconds(3_599)), "59m ago");
+        assert_eq!(humanize_ago(Duration::hours(1)), "1h 00m ago");
+        assert_eq!(humanize_ago(Duration::seconds(86_399)), "23h 59m ago");
+        assert_eq!(humanize_ago(Duration::days(1)), "1d 00h ago");
+        assert_eq!(humanize_ago(Duration::days(30) + Duration::hours(23)), "30d 23h ago");
+        assert_eq!(humanize_ago(Duration::days(400)), "400d 00h ago");
+    }
+
+    #[test]
+    fn relative_times_stop_at_the_cutoff() {
+        let mut clock = Clock::new(DisplayZone::Utc, "%Y-%m-%d", "%H:%M:%S");
+        clock.set_relative(true);
+        let recent = Utc::now() - Duration::hours(2);
+        let old = Utc::now() - Duration::hours(25);
+
+        assert_eq!(clock.when(&recent), " 2h 00m ago");
+        assert_eq!(clock.when(&old), format!("   {}", old.format("%H:%M:%S")));
+
+        clock.set_relative(false);
+        assert_eq!(clock.when(&recent), recent.format("%H:%M:%S").to_string());
+    }
+
+    #[test]
+    fn relative_times_keep_the_column_width() {
+        let mut clock = Clock::new(DisplayZone::Utc, "%Y-%m-%d", "%H:%M:%S");
+        clock.set_relative(true);
+        assert_eq!(clock.column_width(), RELATIVE_WIDTH);
+
+        for ago in [Duration::zero(), Duration::seconds(5), Duration::minutes(42), Duration::seconds(86_399), Duration::days(3)] {
+            let when = clock.when(&(Utc::now() - ago));
+            assert_eq!(when.chars().count(), RELATIVE_WIDTH, "{:?}", when);
+        }
+    }
+}